@@ -0,0 +1,91 @@
+// Re-renders a boundary-heavy view over and over, once sizing tiles from the previous frame's
+// cost map and once with fixed tiles, and prints the wall time spread of each. Successive frames
+// are offset by half a pixel so that none can be copied from the last.
+//
+//     cargo run --release --example cost_map -- [frames]
+
+use iced::Size;
+
+use std::env;
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use mandelbrot::fractal::{AngleKind, FractalKind};
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams, IterationBuffer};
+use mandelbrot::storage::Precision;
+use mandelbrot::tiling::Focus;
+use mandelbrot::viewport::Viewport;
+
+const SIZE: Size = Size::new(1200.0, 720.0);
+const ROUNDS: usize = 3;
+
+fn main() {
+    let frames = env::args()
+        .nth(1)
+        .and_then(|frames| frames.parse().ok())
+        .unwrap_or(20);
+    let pool = ThreadPool::new(8);
+    let mut fixed = Vec::new();
+    let mut planned = Vec::new();
+    // Alternate so that neither side gets the warmer caches.
+    for _ in 0..ROUNDS {
+        fixed.extend(repeat(&pool, frames, false));
+        planned.extend(repeat(&pool, frames, true));
+    }
+    report("fixed tiles", &fixed);
+    report("cost-sized tiles", &planned);
+}
+
+// Wall time of each frame after the first, which has no cost map to go on either way.
+fn repeat(pool: &ThreadPool, frames: usize, use_costs: bool) -> Vec<Duration> {
+    let view = Viewport::new(-0.743643887037151, 0.131825904205330, 0.002);
+    let mut previous = IterationBuffer::default();
+    let mut times = Vec::new();
+    for frame in 0..frames {
+        let shift = (frame % 2) as f64 * view.pixel_size(SIZE) / 2.0;
+        let params = FrameParams {
+            viewport: view.shifted(shift, shift, view.width),
+            max_iterations: 3000,
+            fractal: FractalKind::Mandelbrot,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: true,
+            arithmetic: Arithmetic::F64,
+        };
+        if !use_costs {
+            previous.costs = None;
+        }
+        let start = Instant::now();
+        previous = render::render_reusing(
+            pool,
+            &previous,
+            SIZE,
+            params,
+            &CancelToken::new(),
+            &Focus::default(),
+            |_| {},
+        )
+        .expect("renders without a cancel request complete");
+        if frame > 0 {
+            times.push(start.elapsed());
+        }
+    }
+    times
+}
+
+fn report(name: &str, times: &[Duration]) {
+    let seconds: Vec<f64> = times.iter().map(Duration::as_secs_f64).collect();
+    let mean = seconds.iter().sum::<f64>() / seconds.len() as f64;
+    let variance =
+        seconds.iter().map(|time| (time - mean).powi(2)).sum::<f64>() / seconds.len() as f64;
+    let max = seconds.iter().copied().fold(0.0, f64::max);
+    println!(
+        "{:>16}: mean {:>7.2} ms, std dev {:>6.2} ms, max {:>7.2} ms over {} frames",
+        name,
+        mean * 1e3,
+        variance.sqrt() * 1e3,
+        max * 1e3,
+        seconds.len()
+    );
+}
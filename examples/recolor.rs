@@ -0,0 +1,82 @@
+// Recolors one rendered frame over and over, on a single thread and split across every core, and
+// prints the wall time of each way for a plain and an occluded coloring, with the speedup.
+//
+//     cargo run --release --example recolor -- [rounds]
+
+use iced::Size;
+
+use std::env;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use mandelbrot::coloring;
+use mandelbrot::fractal::{AngleKind, FractalKind};
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams, IterationBuffer};
+use mandelbrot::settings::ColoringSettings;
+use mandelbrot::storage::Precision;
+use mandelbrot::viewport::Viewport;
+
+const SIZE: Size = Size::new(1920.0, 1080.0);
+
+fn main() {
+    let rounds = env::args()
+        .nth(1)
+        .and_then(|rounds| rounds.parse().ok())
+        .unwrap_or(20);
+    let threads = thread::available_parallelism().map_or(8, |threads| threads.get());
+    let params = FrameParams {
+        viewport: Viewport::new(-0.743643887037151, 0.131825904205330, 0.002),
+        max_iterations: 3000,
+        fractal: FractalKind::Mandelbrot,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: true,
+        arithmetic: Arithmetic::F64,
+    };
+    let buffer = render::threaded_fractal_calc(
+        &ThreadPool::new(threads),
+        SIZE,
+        params,
+        &CancelToken::new(),
+        |_| {},
+    )
+    .expect("renders without a cancel request complete");
+    let plain = ColoringSettings::default();
+    let occluded = ColoringSettings {
+        occlusion: true,
+        ..ColoringSettings::default()
+    };
+    for (name, settings) in [("plain", &plain), ("occluded", &occluded)] {
+        let mut single = Vec::new();
+        let mut parallel = Vec::new();
+        // Alternate so that neither side gets the warmer caches.
+        for _ in 0..rounds {
+            single.push(time(&buffer, settings, 1));
+            parallel.push(time(&buffer, settings, threads));
+        }
+        let (single, parallel) = (median(&mut single), median(&mut parallel));
+        println!(
+            "{:>8}: 1 thread {:>7.2} ms, {} threads {:>7.2} ms, {:.1}x faster",
+            name,
+            single.as_secs_f64() * 1e3,
+            threads,
+            parallel.as_secs_f64() * 1e3,
+            single.as_secs_f64() / parallel.as_secs_f64()
+        );
+    }
+}
+
+fn time(buffer: &IterationBuffer, settings: &ColoringSettings, threads: usize) -> Duration {
+    let start = Instant::now();
+    let rgba = coloring::recolor(buffer, settings, threads);
+    let elapsed = start.elapsed();
+    assert_eq!(rgba.len(), buffer.width * buffer.height * 4);
+    elapsed
+}
+
+fn median(times: &mut [Duration]) -> Duration {
+    times.sort();
+    times[times.len() / 2]
+}
@@ -0,0 +1,89 @@
+// Renders the home view through the streaming API, drawing a progress bar on the terminal and
+// saving every tile as its own PNG as soon as it arrives.
+//
+//     cargo run --release --example stream_tiles -- [output directory]
+
+use iced::Size;
+
+use std::env;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use threadpool::ThreadPool;
+
+use mandelbrot::coloring;
+use mandelbrot::export;
+use mandelbrot::fractal::{AngleKind, FractalKind};
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams};
+use mandelbrot::settings::ColoringSettings;
+use mandelbrot::storage::Precision;
+use mandelbrot::viewport::Viewport;
+
+const BAR_WIDTH: usize = 40;
+
+fn main() {
+    let out_dir = env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| env::temp_dir().join("mandelbrot-tiles"));
+    let size = Size::new(1200.0, 720.0);
+    let params = FrameParams {
+        viewport: Viewport::home(FractalKind::Mandelbrot, size),
+        max_iterations: 1000,
+        fractal: FractalKind::Mandelbrot,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: true,
+        arithmetic: Arithmetic::F64,
+    };
+    let coloring_settings = ColoringSettings {
+        palette: String::from("Ocean"),
+        ..ColoringSettings::default()
+    };
+
+    let pool = ThreadPool::new(8);
+    let result = render::render_streaming(
+        &pool,
+        size,
+        params,
+        &CancelToken::new(),
+        |progress| {
+            let filled = (progress.fraction() * BAR_WIDTH as f32) as usize;
+            print!(
+                "\r[{}{}] {:>3.0}% {:>7}/{} pixels {:.2?}",
+                "#".repeat(filled),
+                " ".repeat(BAR_WIDTH - filled),
+                progress.fraction() * 100.0,
+                progress.pixels_done,
+                progress.total_pixels,
+                progress.elapsed
+            );
+            let _ = io::stdout().flush();
+        },
+        |tile| {
+            let rgba = coloring::recolor(&tile.to_buffer(), &coloring_settings, 1);
+            let path = out_dir.join(format!("tile_{:04}_{:04}.png", tile.rect.x, tile.rect.y));
+            if let Err(err) = export::write_png(
+                &path,
+                tile.rect.width as u32,
+                tile.rect.height as u32,
+                &rgba,
+            ) {
+                eprintln!("\nfailed to save {}: {}", path.display(), err);
+            }
+        },
+    );
+    println!();
+    match result {
+        Ok(summary) => println!(
+            "{} tiles ({}x{}, {} pixels) in {:.2?}, saved to {}",
+            summary.tiles,
+            summary.width,
+            summary.height,
+            summary.pixels_computed,
+            summary.elapsed,
+            out_dir.display()
+        ),
+        Err(err) => eprintln!("{}", err),
+    }
+}
@@ -0,0 +1,616 @@
+use iced::keyboard::{self, Modifiers};
+
+use mandelbrot::boundary;
+use mandelbrot::fuzzy;
+
+use crate::controls::Setting;
+use crate::Message;
+
+// Recently run commands kept at the top of the command palette.
+pub const RECENT_COMMANDS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Category {
+    View,
+    Coloring,
+    Export,
+    Edit,
+    Panels,
+    Overlays,
+    Tools,
+}
+
+impl Category {
+    pub fn name(self) -> &'static str {
+        match self {
+            Category::View => "View",
+            Category::Coloring => "Coloring",
+            Category::Export => "Export",
+            Category::Edit => "Edit",
+            Category::Panels => "Panels",
+            Category::Overlays => "Overlays",
+            Category::Tools => "Tools",
+        }
+    }
+}
+
+// A key that runs an action: a character as typed, so "P" is Shift+P, or a named key by its name.
+// With Ctrl (Cmd on macOS) the character's case is ignored and Shift is asked for separately.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shortcut {
+    pub key: &'static str,
+    pub command: bool,
+    pub shift: bool,
+}
+
+const fn key(key: &'static str) -> Shortcut {
+    Shortcut {
+        key,
+        command: false,
+        shift: false,
+    }
+}
+
+const fn ctrl(key: &'static str) -> Shortcut {
+    Shortcut {
+        key,
+        command: true,
+        shift: false,
+    }
+}
+
+const fn ctrl_shift(key: &'static str) -> Shortcut {
+    Shortcut {
+        key,
+        command: true,
+        shift: true,
+    }
+}
+
+impl Shortcut {
+    fn matches(&self, pressed: &str, modifiers: Modifiers) -> bool {
+        if modifiers.command() != self.command {
+            return false;
+        }
+        if self.command {
+            modifiers.shift() == self.shift && pressed.eq_ignore_ascii_case(self.key)
+        } else {
+            pressed == self.key
+        }
+    }
+
+    // As the palette shows it, e.g. "Ctrl+Shift+C".
+    pub fn label(&self) -> String {
+        // Named keys are spelled out; characters show as on the keycap.
+        let named = self.key.chars().count() > 1;
+        let typed_shifted = !named && self.key.chars().any(char::is_uppercase);
+        format!(
+            "{}{}{}",
+            if self.command { "Ctrl+" } else { "" },
+            if self.shift || typed_shifted {
+                "Shift+"
+            } else {
+                ""
+            },
+            if named {
+                self.key.to_string()
+            } else {
+                self.key.to_uppercase()
+            }
+        )
+    }
+}
+
+// Something the user can do by name: from the command palette, and by its shortcuts, which the
+// keyboard handling looks up here. `name` is stable and machine-readable; recent commands are
+// remembered by it.
+#[derive(Clone, Debug)]
+pub struct Action {
+    pub name: &'static str,
+    pub title: &'static str,
+    pub category: Category,
+    pub shortcuts: Vec<Shortcut>,
+    pub message: Message,
+}
+
+fn action(
+    name: &'static str,
+    title: &'static str,
+    category: Category,
+    shortcuts: Vec<Shortcut>,
+    message: Message,
+) -> Action {
+    Action {
+        name,
+        title,
+        category,
+        shortcuts,
+        message,
+    }
+}
+
+// Every action, in the order the palette lists them before anything is typed. Actions that need
+// a parameter open the control that takes it.
+pub fn all() -> Vec<Action> {
+    use Category::*;
+    vec![
+        action(
+            "view.reset",
+            "Reset view",
+            View,
+            vec![key("Home")],
+            Message::ViewReset,
+        ),
+        action(
+            "view.zoom_in",
+            "Zoom in",
+            View,
+            vec![key("+"), key("=")],
+            Message::ViewZoomed(0.5),
+        ),
+        action(
+            "view.zoom_out",
+            "Zoom out",
+            View,
+            vec![key("-")],
+            Message::ViewZoomed(2.0),
+        ),
+        action(
+            "view.fractal",
+            "Next fractal",
+            View,
+            vec![key("f")],
+            Message::FractalCycled,
+        ),
+        action(
+            "view.profile",
+            "Next quality profile",
+            View,
+            vec![key("q")],
+            Message::ProfileCycled,
+        ),
+        action(
+            "view.goto",
+            "Go to coordinates",
+            View,
+            vec![],
+            Message::SettingFocused(Setting::Goto),
+        ),
+        action(
+            "view.open",
+            "Open file",
+            View,
+            vec![],
+            Message::SettingFocused(Setting::OpenFile),
+        ),
+        action(
+            "coloring.palette_next",
+            "Next palette",
+            Coloring,
+            vec![key("p")],
+            Message::PaletteCycled,
+        ),
+        action(
+            "coloring.palette_previous",
+            "Previous palette",
+            Coloring,
+            vec![key("P")],
+            Message::PaletteCycledBack,
+        ),
+        action(
+            "coloring.palette_wrap",
+            "Next palette wrap",
+            Coloring,
+            vec![],
+            Message::PaletteWrapCycled,
+        ),
+        action(
+            "coloring.mode",
+            "Next coloring mode",
+            Coloring,
+            vec![],
+            Message::ColoringModeCycled,
+        ),
+        action(
+            "coloring.interior_color",
+            "Next interior color",
+            Coloring,
+            vec![],
+            Message::InteriorColorCycled,
+        ),
+        action(
+            "coloring.unresolved_color",
+            "Next unresolved color",
+            Coloring,
+            vec![],
+            Message::UnresolvedColorCycled,
+        ),
+        action(
+            "coloring.auto_levels",
+            "Auto levels",
+            Coloring,
+            vec![key("l")],
+            Message::AutoLevelsApplied,
+        ),
+        action(
+            "coloring.auto_levels_after_render",
+            "Toggle auto levels after each render",
+            Coloring,
+            vec![],
+            Message::AutoLevelsAfterRenderToggled,
+        ),
+        action(
+            "coloring.occlusion",
+            "Toggle ambient occlusion",
+            Coloring,
+            vec![],
+            Message::OcclusionToggled,
+        ),
+        action(
+            "coloring.post_expression",
+            "Add expression stage",
+            Coloring,
+            vec![],
+            Message::SettingFocused(Setting::PostExpression),
+        ),
+        action(
+            "coloring.export_look",
+            "Export look",
+            Coloring,
+            vec![],
+            Message::LookExported,
+        ),
+        action(
+            "export.quick",
+            "Quick export",
+            Export,
+            vec![ctrl("s")],
+            Message::QuickExported,
+        ),
+        action(
+            "export.copy_image",
+            "Copy image",
+            Export,
+            vec![ctrl_shift("c")],
+            Message::ImageCopied,
+        ),
+        action(
+            "export.copy_location",
+            "Copy location",
+            Export,
+            vec![ctrl_shift("l")],
+            Message::LocationCopied,
+        ),
+        action(
+            "export.data",
+            "Export data",
+            Export,
+            vec![ctrl("d")],
+            Message::DataExported,
+        ),
+        action(
+            "export.boundary_csv",
+            "Export boundary points as CSV",
+            Export,
+            vec![],
+            Message::BoundaryExported(boundary::Format::Csv),
+        ),
+        action(
+            "export.boundary_geojson",
+            "Export boundary lines as GeoJSON",
+            Export,
+            vec![],
+            Message::BoundaryExported(boundary::Format::GeoJson),
+        ),
+        action(
+            "export.project",
+            "Save project",
+            Export,
+            vec![],
+            Message::ProjectSaved(false),
+        ),
+        action(
+            "export.project_frame",
+            "Save project with frame",
+            Export,
+            vec![],
+            Message::ProjectSaved(true),
+        ),
+        action(
+            "export.queue",
+            "Queue export",
+            Export,
+            vec![ctrl("e")],
+            Message::ExportQueued,
+        ),
+        action(
+            "export.profile",
+            "Next export profile",
+            Export,
+            vec![],
+            Message::ExportProfileCycled,
+        ),
+        action(
+            "edit.paste",
+            "Paste coordinates",
+            Edit,
+            vec![ctrl("v")],
+            Message::PasteRequested,
+        ),
+        action("edit.undo", "Undo", Edit, vec![ctrl("z")], Message::Undo),
+        action(
+            "edit.redo",
+            "Redo",
+            Edit,
+            vec![ctrl_shift("z"), ctrl("y")],
+            Message::Redo,
+        ),
+        action(
+            "edit.bookmark",
+            "Add bookmark",
+            Edit,
+            vec![key("b")],
+            Message::BookmarkAdded,
+        ),
+        action(
+            "edit.relative_bookmark",
+            "Add bookmark relative to the anchor",
+            Edit,
+            vec![key("B")],
+            Message::RelativeBookmarkAdded,
+        ),
+        action(
+            "edit.anchor",
+            "Anchor at the minibrot under the cursor",
+            Edit,
+            vec![key("N")],
+            Message::AnchorAtCursor,
+        ),
+        action(
+            "edit.keyframe",
+            "Add keyframe",
+            Edit,
+            vec![],
+            Message::KeyframeAdded,
+        ),
+        action(
+            "panels.settings",
+            "Toggle settings",
+            Panels,
+            vec![key("s")],
+            Message::SettingsToggled,
+        ),
+        action(
+            "panels.bookmarks",
+            "Toggle bookmarks",
+            Panels,
+            vec![key("m")],
+            Message::BookmarksToggled,
+        ),
+        action(
+            "panels.minibrots",
+            "Toggle minibrots",
+            Panels,
+            vec![key("n")],
+            Message::MinibrotsToggled,
+        ),
+        action(
+            "panels.queue",
+            "Toggle export queue",
+            Panels,
+            vec![key("e")],
+            Message::QueueToggled,
+        ),
+        action(
+            "panels.journal",
+            "Toggle journal",
+            Panels,
+            vec![key("j")],
+            Message::JournalToggled,
+        ),
+        action(
+            "panels.path",
+            "Toggle camera path",
+            Panels,
+            vec![key("k")],
+            Message::PathToggled,
+        ),
+        action(
+            "panels.commands",
+            "Command palette",
+            Panels,
+            vec![ctrl("p")],
+            Message::CommandPaletteToggled,
+        ),
+        action(
+            "overlays.crosshair",
+            "Toggle crosshair",
+            Overlays,
+            vec![],
+            Message::CrosshairToggled,
+        ),
+        action(
+            "overlays.thirds",
+            "Toggle rule of thirds",
+            Overlays,
+            vec![],
+            Message::ThirdsToggled,
+        ),
+        action(
+            "overlays.annotate",
+            "Add annotation",
+            Overlays,
+            vec![key("a")],
+            Message::AnnotationAdded,
+        ),
+        action(
+            "overlays.annotations",
+            "Toggle annotations",
+            Overlays,
+            vec![],
+            Message::AnnotationsToggled,
+        ),
+        action(
+            "overlays.regions",
+            "Edit adjustment regions",
+            Overlays,
+            vec![],
+            Message::RegionsEdited,
+        ),
+        action(
+            "overlays.add_region",
+            "Add adjustment region",
+            Overlays,
+            vec![],
+            Message::RegionAdded,
+        ),
+        action(
+            "overlays.safe_area",
+            "Next safe area",
+            Overlays,
+            vec![],
+            Message::SafeAreaCycled,
+        ),
+        action(
+            "overlays.tile_order",
+            "Toggle tile order",
+            Overlays,
+            vec![],
+            Message::TileOrderToggled,
+        ),
+        action(
+            "overlays.perf_hud",
+            "Toggle performance HUD",
+            Overlays,
+            vec![key("F3")],
+            Message::PerfHudToggled,
+        ),
+        action(
+            "tools.minibrots",
+            "Scan for minibrots",
+            Tools,
+            vec![],
+            Message::MinibrotsRequested,
+        ),
+        action(
+            "tools.tune",
+            "Tune thread count",
+            Tools,
+            vec![],
+            Message::TuneRequested,
+        ),
+    ]
+}
+
+pub fn named(name: &str) -> Option<Action> {
+    all().into_iter().find(|action| action.name == name)
+}
+
+// What a key press runs, if it is an action's shortcut.
+pub fn for_key(pressed: &keyboard::Key, modifiers: Modifiers) -> Option<Message> {
+    let pressed = match pressed.as_ref() {
+        keyboard::Key::Character(c) => c,
+        keyboard::Key::Named(keyboard::key::Named::Home) => "Home",
+        keyboard::Key::Named(keyboard::key::Named::F3) => "F3",
+        _ => return None,
+    };
+    all()
+        .into_iter()
+        .find(|action| {
+            action
+                .shortcuts
+                .iter()
+                .any(|shortcut| shortcut.matches(pressed, modifiers))
+        })
+        .map(|action| action.message)
+}
+
+// The actions matching `query`, best first: by fuzzy score against the title or the name, then
+// the most recently run, then in the order of `all`. An empty query matches everything, which
+// puts the recent commands at the top.
+pub fn ranked(query: &str, recent: &[String]) -> Vec<Action> {
+    let mut scored: Vec<_> = all()
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, action)| {
+            let score = fuzzy::score(query, action.title).max(fuzzy::score(query, action.name))?;
+            let recency = recent
+                .iter()
+                .position(|name| name == action.name)
+                .unwrap_or(usize::MAX);
+            Some((std::cmp::Reverse(score), recency, index, action))
+        })
+        .collect();
+    scored.sort_by_key(|(score, recency, index, _)| (*score, *recency, *index));
+    scored.into_iter().map(|(.., action)| action).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An action sharing a name or a shortcut with another is unreachable.
+    #[test]
+    fn names_and_shortcuts_are_registered_once() {
+        let actions = all();
+        for (index, action) in actions.iter().enumerate() {
+            for other in &actions[index + 1..] {
+                assert_ne!(
+                    action.name, other.name,
+                    "{} is registered twice",
+                    action.name
+                );
+                for shortcut in &action.shortcuts {
+                    assert!(
+                        !other.shortcuts.contains(shortcut),
+                        "{} runs both {} and {}",
+                        shortcut.label(),
+                        action.name,
+                        other.name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn shortcuts_run_their_action() {
+        let presses = [
+            ("p", Modifiers::empty(), "coloring.palette_next"),
+            ("P", Modifiers::SHIFT, "coloring.palette_previous"),
+            ("Z", Modifiers::CTRL | Modifiers::SHIFT, "edit.redo"),
+            ("C", Modifiers::CTRL | Modifiers::SHIFT, "export.copy_image"),
+            ("e", Modifiers::CTRL, "export.queue"),
+            ("e", Modifiers::empty(), "panels.queue"),
+        ];
+        for (pressed, modifiers, name) in presses {
+            let key = keyboard::Key::Character(pressed.into());
+            let expected = named(name).map(|action| format!("{:?}", action.message));
+            assert_eq!(
+                for_key(&key, modifiers).map(|message| format!("{:?}", message)),
+                expected,
+                "{} with {:?} does not run {}",
+                pressed,
+                modifiers,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn the_palette_ranks_queries_and_recent_commands() {
+        let recent = [String::from("view.fractal"), String::from("edit.undo")];
+        let queries = [
+            ("gc", &[][..], "view.goto"),
+            ("undo", &[][..], "edit.undo"),
+            ("zoom in", &[][..], "view.zoom_in"),
+            ("palette", &[][..], "coloring.palette_next"),
+            ("", &recent[..], "view.fractal"),
+            ("next", &recent[..], "view.fractal"),
+        ];
+        for (query, recent, name) in queries {
+            let first = ranked(query, recent).first().map(|action| action.name);
+            assert_eq!(first, Some(name), "{:?} puts {:?} first", query, first);
+        }
+    }
+}
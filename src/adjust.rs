@@ -0,0 +1,597 @@
+use iced::{Point, Rectangle, Size};
+
+use serde::{Deserialize, Serialize};
+
+use crate::annotation::Pin;
+use crate::doubledouble::DoubleDouble;
+use crate::postprocess;
+use crate::viewport::Viewport;
+
+// Local adjustments: regions of the plane whose pixels are recolored apart from the rest of the
+// frame, fading out over a feathered edge. They are pinned to the plane like annotations, so they
+// stay on their feature as the view moves, and run after every other stage of coloring.
+//
+// Regions apply in the order they are listed, each over what the ones before it left: a pixel's
+// color so far is graded by the region, and the graded color replaces it in proportion to the
+// region's weight there. Where regions overlap, later ones therefore grade the earlier ones'
+// results, and a later region at full weight hides any earlier one's palette offset.
+
+// How far from a handle a click still grabs it, in window pixels.
+pub const HIT_RADIUS: f32 = 8.0;
+// Fewest window pixels a region's half width or height may be dragged down to.
+pub const MIN_HALF_EXTENT: f32 = 4.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Shape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
+
+impl Shape {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Rectangle => "Rectangle",
+            Shape::Ellipse => "Ellipse",
+        }
+    }
+
+    pub fn next(self) -> Shape {
+        match self {
+            Shape::Rectangle => Shape::Ellipse,
+            Shape::Ellipse => Shape::Rectangle,
+        }
+    }
+}
+
+// A region and how it recolors what it covers. Half its width and height are in the units of the
+// plane, so that it grows and shrinks with zoom.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Region {
+    pub id: u64,
+    #[serde(default)]
+    pub shape: Shape,
+    pub center: Pin,
+    pub half_width: f64,
+    pub half_height: f64,
+    // How far in from the edge the region fades in, as a fraction of its smaller half extent; 0
+    // is a hard edge.
+    #[serde(default = "default_feather")]
+    pub feather: f32,
+    // Stops of brightness: each doubles the channels.
+    #[serde(default)]
+    pub exposure: f32,
+    // Above 1 brightens midtones.
+    #[serde(default = "default_gamma")]
+    pub gamma: f32,
+    // Turns round the color wheel.
+    #[serde(default)]
+    pub hue_shift: f32,
+    // Passes through the palette added to the frame's own offset for the pixels covered.
+    #[serde(default)]
+    pub palette_offset: f32,
+}
+
+fn default_feather() -> f32 {
+    0.25
+}
+
+fn default_gamma() -> f32 {
+    1.0
+}
+
+// The settings of a region the editor steps up and down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Knob {
+    Exposure,
+    Gamma,
+    HueShift,
+    PaletteOffset,
+    Feather,
+}
+
+impl Knob {
+    pub const ALL: [Knob; 5] = [
+        Knob::Exposure,
+        Knob::Gamma,
+        Knob::HueShift,
+        Knob::PaletteOffset,
+        Knob::Feather,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Knob::Exposure => "Exposure",
+            Knob::Gamma => "Gamma",
+            Knob::HueShift => "Hue shift",
+            Knob::PaletteOffset => "Palette offset",
+            Knob::Feather => "Feather",
+        }
+    }
+}
+
+impl Region {
+    // A region at `center` with nothing to change yet.
+    pub fn new(id: u64, center: Pin, half_width: f64, half_height: f64) -> Region {
+        Region {
+            id,
+            shape: Shape::default(),
+            center,
+            half_width,
+            half_height,
+            feather: default_feather(),
+            exposure: 0.0,
+            gamma: default_gamma(),
+            hue_shift: 0.0,
+            palette_offset: 0.0,
+        }
+    }
+
+    // The knob's setting as shown beside its name.
+    pub fn describe(&self, knob: Knob) -> String {
+        match knob {
+            Knob::Exposure => format!("{:+.1} stops", self.exposure),
+            Knob::Gamma => format!("{:.2}", self.gamma),
+            Knob::HueShift => format!("{:+.2} turns", self.hue_shift),
+            Knob::PaletteOffset => format!("{:+.2}", self.palette_offset),
+            Knob::Feather => format!("{:.0}%", self.feather * 100.0),
+        }
+    }
+
+    // Nudges the knob's setting up or down a step.
+    pub fn adjust(&mut self, knob: Knob, up: bool) {
+        let step = if up { 1.0 } else { -1.0 };
+        match knob {
+            Knob::Exposure => self.exposure = (self.exposure + step * 0.25).clamp(-4.0, 4.0),
+            Knob::Gamma => self.gamma = (self.gamma + step * 0.1).clamp(0.1, 5.0),
+            Knob::HueShift => self.hue_shift = (self.hue_shift + step * 0.05).clamp(-1.0, 1.0),
+            Knob::PaletteOffset => {
+                self.palette_offset = (self.palette_offset + step * 0.05).clamp(-1.0, 1.0)
+            }
+            Knob::Feather => self.feather = (self.feather + step * 0.05).clamp(0.0, 1.0),
+        }
+    }
+
+    // `color`, channels from 0 to 1, as the region grades it: exposure, then gamma, then hue.
+    pub fn grade(&self, color: [f32; 3]) -> [f32; 3] {
+        let gain = self.exposure.exp2();
+        let inverse_gamma = 1.0 / self.gamma.max(0.01);
+        let color = color.map(|c| (c * gain).clamp(0.0, 1.0).powf(inverse_gamma));
+        if self.hue_shift == 0.0 {
+            color
+        } else {
+            postprocess::shift_hue(color, self.hue_shift)
+        }
+    }
+
+    // Where the region falls in a `size` frame of `viewport`, in that frame's pixels.
+    pub fn placed(&self, viewport: &Viewport, size: Size) -> Placed {
+        let pixel_size = viewport.pixel_size(size);
+        let center = self.center.position(viewport, size);
+        let half_width = (self.half_width / pixel_size) as f32;
+        let half_height = (self.half_height / pixel_size) as f32;
+        Placed {
+            shape: self.shape,
+            center,
+            half_width,
+            half_height,
+            feather: self.feather.clamp(0.0, 1.0) * half_width.min(half_height),
+        }
+    }
+}
+
+// A region laid out in a frame or the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Placed {
+    pub shape: Shape,
+    pub center: Point,
+    pub half_width: f32,
+    pub half_height: f32,
+    // Width of the band inside the edge over which the weight rises from 0 to 1.
+    pub feather: f32,
+}
+
+impl Placed {
+    // How strongly the region applies at `point`: 1 well inside, 0 outside and easing from one to
+    // the other across the feather.
+    pub fn weight(&self, point: Point) -> f32 {
+        if self.half_width <= 0.0 || self.half_height <= 0.0 {
+            return 0.0;
+        }
+        let (dx, dy) = (
+            (point.x - self.center.x).abs(),
+            (point.y - self.center.y).abs(),
+        );
+        // How far inside the edge the point is, in pixels; for an ellipse measured along the ray
+        // from its center, in units of its smaller half extent.
+        let inside = match self.shape {
+            Shape::Rectangle => (self.half_width - dx).min(self.half_height - dy),
+            Shape::Ellipse => {
+                let (u, v) = (dx / self.half_width, dy / self.half_height);
+                (1.0 - (u * u + v * v).sqrt()) * self.half_width.min(self.half_height)
+            }
+        };
+        if inside <= 0.0 {
+            0.0
+        } else if inside >= self.feather {
+            1.0
+        } else {
+            let t = inside / self.feather;
+            t * t * (3.0 - 2.0 * t)
+        }
+    }
+
+    // The box the region covers.
+    pub fn bounds(&self) -> Rectangle {
+        Rectangle {
+            x: self.center.x - self.half_width,
+            y: self.center.y - self.half_height,
+            width: 2.0 * self.half_width,
+            height: 2.0 * self.half_height,
+        }
+    }
+
+    // The handle it is resized by, at the bottom right corner of its box.
+    pub fn corner(&self) -> Point {
+        Point::new(
+            self.center.x + self.half_width,
+            self.center.y + self.half_height,
+        )
+    }
+
+    // Which handle `point` grabs, if any; the corner wins where the two are close.
+    pub fn hit(&self, point: Point) -> Option<Handle> {
+        if point.distance(self.corner()) <= HIT_RADIUS {
+            Some(Handle::Corner)
+        } else if point.distance(self.center) <= HIT_RADIUS {
+            Some(Handle::Center)
+        } else {
+            None
+        }
+    }
+}
+
+// What of a region a click grabbed: its center moves it, its corner resizes it about the center.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Handle {
+    Center,
+    Corner,
+}
+
+// The next free id among `regions`.
+pub fn next_id(regions: &[Region]) -> u64 {
+    regions
+        .iter()
+        .map(|region| region.id + 1)
+        .max()
+        .unwrap_or(1)
+}
+
+// The topmost region with a handle at `point` of a `size` window of `viewport`, and the handle.
+pub fn hit(
+    regions: &[Region],
+    viewport: &Viewport,
+    size: Size,
+    point: Point,
+) -> Option<(u64, Handle)> {
+    regions
+        .iter()
+        .rev()
+        .find_map(|region| Some((region.id, region.placed(viewport, size).hit(point)?)))
+}
+
+// How far `to` is from `from` across and up the plane.
+pub fn offset(from: Pin, to: Pin) -> (f64, f64) {
+    (
+        (DoubleDouble::new(to.re, to.re_lo) - DoubleDouble::new(from.re, from.re_lo)).to_f64(),
+        (DoubleDouble::new(to.im, to.im_lo) - DoubleDouble::new(from.im, from.im_lo)).to_f64(),
+    )
+}
+
+// Applies `regions` to `rgba`, an sRGB frame of `viewport`, in order. `shifted` holds, for each
+// region with a palette offset, the frame colored with that offset added, which the region grades
+// in place of the color so far; for other regions it is None.
+pub fn apply(
+    regions: &[Region],
+    rgba: &mut [u8],
+    width: usize,
+    height: usize,
+    viewport: &Viewport,
+    shifted: &[Option<Vec<u8>>],
+) {
+    let size = Size::new(width as f32, height as f32);
+    for (region, shifted) in regions.iter().zip(shifted) {
+        let placed = region.placed(viewport, size);
+        let bounds = placed.bounds();
+        let clamp = |value: f32, max: usize| (value.floor().max(0.0) as usize).min(max);
+        let (x0, x1) = (
+            clamp(bounds.x, width),
+            clamp(bounds.x + bounds.width + 1.0, width),
+        );
+        let (y0, y1) = (
+            clamp(bounds.y, height),
+            clamp(bounds.y + bounds.height + 1.0, height),
+        );
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let weight = placed.weight(Point::new(x as f32 + 0.5, y as f32 + 0.5));
+                if weight <= 0.0 {
+                    continue;
+                }
+                let index = (y * width + x) * 4;
+                let color = |bytes: &[u8]| {
+                    [bytes[index], bytes[index + 1], bytes[index + 2]].map(|c| c as f32 / 255.0)
+                };
+                let so_far = color(rgba);
+                let base = shifted.as_deref().map_or(so_far, color);
+                let graded = blend(so_far, region.grade(base), weight);
+                for (channel, c) in rgba[index..index + 3].iter_mut().zip(graded) {
+                    *channel = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+                }
+            }
+        }
+    }
+}
+
+// `from` moved towards `to` by `weight`, from 0 to 1.
+pub fn blend(from: [f32; 3], to: [f32; 3], weight: f32) -> [f32; 3] {
+    let mut mixed = from;
+    for (channel, target) in mixed.iter_mut().zip(to) {
+        *channel += (target - *channel) * weight;
+    }
+    mixed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring;
+    use crate::project::Project;
+    use crate::settings::{ColoringSettings, RenderSettings};
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    const GRAY: [u8; 4] = [64, 64, 64, 255];
+
+    fn view() -> Viewport {
+        Viewport::new(-0.75, 0.1, 3.0)
+    }
+
+    // A 40 by 20 pixel half-extent rectangle in the middle of the canonical frame, its feather
+    // half of the half height: 10 pixels in from every edge.
+    fn rectangle() -> Region {
+        let size = CANONICAL_SIZE;
+        let pixel_size = view().pixel_size(size);
+        let center = Pin::at(&view(), Point::new(80.0, 60.0), size);
+        Region {
+            feather: 0.5,
+            ..Region::new(1, center, 40.0 * pixel_size, 20.0 * pixel_size)
+        }
+    }
+
+    fn brighter() -> Region {
+        Region {
+            exposure: 1.0,
+            feather: 0.0,
+            ..rectangle()
+        }
+    }
+
+    fn lighter() -> Region {
+        Region {
+            id: 2,
+            gamma: 2.0,
+            feather: 0.0,
+            ..rectangle()
+        }
+    }
+
+    fn level(c: f32) -> u8 {
+        (c.clamp(0.0, 1.0) * 255.0).round() as u8
+    }
+
+    // The red level of the middle pixel of a gray frame with `regions` applied.
+    fn overlap(regions: &[Region]) -> u8 {
+        let mut rgba: Vec<u8> = GRAY.repeat(160 * 120);
+        let shifted = vec![None; regions.len()];
+        apply(regions, &mut rgba, 160, 120, &view(), &shifted);
+        rgba[(60 * 160 + 80) * 4]
+    }
+
+    #[test]
+    fn feathers_fade_rectangles_out_at_their_edge() {
+        let placed = rectangle().placed(&view(), CANONICAL_SIZE);
+        let expected = [
+            ((80.0, 60.0), 1.0),
+            ((115.0, 60.0), 0.5),
+            ((80.0, 75.0), 0.5),
+            ((121.0, 60.0), 0.0),
+            ((80.0, 90.0), 0.0),
+            ((111.0, 60.0), 0.972),
+        ];
+        for ((x, y), want) in expected {
+            let got = placed.weight(Point::new(x, y));
+            assert!(
+                (got - want).abs() <= 1e-3,
+                "a rectangle weighs {} at ({}, {}), not {}",
+                got,
+                x,
+                y,
+                want
+            );
+        }
+    }
+
+    #[test]
+    fn ellipses_leave_their_box_corners_out() {
+        let ellipse = Region {
+            shape: Shape::Ellipse,
+            feather: 0.0,
+            ..rectangle()
+        }
+        .placed(&view(), CANONICAL_SIZE);
+        assert_eq!(ellipse.weight(Point::new(118.0, 78.0)), 0.0);
+        assert_eq!(ellipse.weight(Point::new(100.0, 65.0)), 1.0);
+    }
+
+    // Panned and zoomed in twice, the region stays on its point and doubles in size.
+    #[test]
+    fn regions_stay_on_their_point_of_the_plane() {
+        let size = CANONICAL_SIZE;
+        let region = rectangle();
+        let moved = Viewport::new(-0.7, 0.05, 1.5);
+        let placed = region.placed(&moved, size);
+        let back = Pin::at(&moved, placed.center, size);
+        let (across, up) = offset(region.center, back);
+        assert!(
+            across.abs().max(up.abs()) <= 1e-3 * moved.pixel_size(size)
+                && (placed.half_width - 80.0).abs() <= 1e-3
+                && (placed.half_height - 40.0).abs() <= 1e-3,
+            "after a pan and zoom a region is at {:?}, {:?} off its point",
+            placed,
+            (across, up)
+        );
+    }
+
+    // Overlapping regions apply in list order, each over what the ones before it left.
+    #[test]
+    fn overlapping_regions_blend_in_list_order() {
+        let c = 64.0 / 255.0;
+        let orders = [
+            (
+                overlap(&[brighter(), lighter()]),
+                level((c * 2.0f32).sqrt()),
+            ),
+            (overlap(&[lighter(), brighter()]), level(c.sqrt() * 2.0)),
+        ];
+        for (got, want) in orders {
+            assert!(
+                got.abs_diff(want) <= 1,
+                "overlapping regions give {} where their order gives {}",
+                got,
+                want
+            );
+        }
+    }
+
+    // Across a feather each region moves the color so far towards its grade of it by its weight.
+    #[test]
+    fn feathered_regions_blend_by_their_weight() {
+        let size = CANONICAL_SIZE;
+        let pixel_size = view().pixel_size(size);
+        let soft = [
+            Region {
+                feather: 0.5,
+                ..brighter()
+            },
+            Region {
+                feather: 0.5,
+                half_width: 30.0 * pixel_size,
+                ..lighter()
+            },
+        ];
+        let mut rgba: Vec<u8> = GRAY.repeat(160 * 120);
+        apply(&soft, &mut rgba, 160, 120, &view(), &[None, None]);
+        let pixel = Point::new(104.5, 60.5);
+        let mut want = [64.0 / 255.0; 3];
+        for region in &soft {
+            let weight = region.placed(&view(), size).weight(pixel);
+            want = blend(want, region.grade(want), weight).map(|c| level(c) as f32 / 255.0);
+        }
+        let got = rgba[(60 * 160 + 104) * 4];
+        assert!(
+            got.abs_diff(level(want[0])) <= 1,
+            "feathered regions overlapping give {} rather than {}",
+            got,
+            level(want[0])
+        );
+    }
+
+    // A region over the whole frame with a palette offset colors as the offset would, and one
+    // out of view changes nothing.
+    #[test]
+    fn palette_offset_regions_color_like_the_offset() {
+        let size = CANONICAL_SIZE;
+        let params = CANONICAL_VIEWS[0].params();
+        let frame = stats::render_frame(size, params);
+        let mut coloring = ColoringSettings::default();
+        coloring.set_palette(String::from("Ocean"));
+        let view = params.viewport;
+        let whole = Region {
+            palette_offset: 0.3,
+            feather: 0.0,
+            ..Region::new(
+                1,
+                Pin::at(&view, Point::new(80.0, 60.0), size),
+                view.width,
+                view.width,
+            )
+        };
+        let with_region = coloring::recolor(
+            &frame,
+            &ColoringSettings {
+                regions: vec![whole.clone()],
+                ..coloring.clone()
+            },
+            1,
+        );
+        let offset = coloring::recolor(
+            &frame,
+            &ColoringSettings {
+                palette_offset: 0.3,
+                ..coloring.clone()
+            },
+            1,
+        );
+        assert!(
+            with_region == offset,
+            "a palette offset region colors otherwise than the offset"
+        );
+        let far = Region {
+            center: Pin::at(&view, Point::new(-1000.0, -1000.0), size),
+            half_width: view.width / 100.0,
+            half_height: view.width / 100.0,
+            ..whole
+        };
+        let untouched = coloring::recolor(
+            &frame,
+            &ColoringSettings {
+                regions: vec![far],
+                ..coloring.clone()
+            },
+            1,
+        );
+        assert!(
+            untouched == coloring::recolor(&frame, &coloring, 1),
+            "a region out of view changes the frame"
+        );
+    }
+
+    // Regions are kept by projects, but not by their looks.
+    #[test]
+    fn projects_keep_their_regions() {
+        let params = CANONICAL_VIEWS[0].params();
+        let with_regions = ColoringSettings {
+            regions: vec![brighter(), lighter()],
+            ..ColoringSettings::default()
+        };
+        let project = Project::new(
+            params.fractal,
+            params.viewport,
+            RenderSettings::default(),
+            &with_regions,
+            Vec::new(),
+        );
+        let (reopened, _) = project
+            .to_bytes(None)
+            .and_then(|bytes| Project::parse(&bytes))
+            .expect("a project with regions reopens");
+        assert_eq!(reopened.regions, with_regions.regions);
+        assert!(
+            reopened
+                .look
+                .settings()
+                .is_ok_and(|look| look.regions.is_empty()),
+            "a project's look carries its regions"
+        );
+    }
+}
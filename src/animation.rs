@@ -0,0 +1,334 @@
+use iced::Size;
+
+use serde::{Deserialize, Serialize};
+
+use threadpool::ThreadPool;
+
+use crate::camera::CameraPath;
+use crate::fractal::{AngleKind, FractalKind};
+use crate::render::{self, Arithmetic, CancelToken, FrameParams, IterationBuffer, TileUpdate};
+use crate::storage::Precision;
+use crate::tiling::Focus;
+use crate::viewport::Viewport;
+
+// Ends a zoom sequence once there is nothing left worth zooming into.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoStop {
+    pub enabled: bool,
+    // Frames whose structure metric (in iterations) stays below this count as featureless.
+    pub threshold: f64,
+    // Featureless frames in a row before stopping.
+    pub consecutive: usize,
+    // Smallest pixel spacing, in ulps of the target coordinate, the arithmetic is trusted with.
+    pub min_pixel_ulps: f64,
+}
+
+impl Default for AutoStop {
+    fn default() -> Self {
+        AutoStop {
+            enabled: true,
+            threshold: 0.5,
+            consecutive: 5,
+            min_pixel_ulps: 16.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct ZoomSequence {
+    pub target_re: f64,
+    pub target_im: f64,
+    pub start_width: f64,
+    // Factor by which the view narrows between consecutive frames.
+    pub zoom_per_frame: f64,
+    pub max_frames: usize,
+    pub size: Size,
+    pub max_iterations: u32,
+    pub fractal: FractalKind,
+    pub coarse_prepass: bool,
+    // Switch to double-double once f64 runs out, instead of stopping there.
+    pub double_double: bool,
+    pub auto_stop: AutoStop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopReason {
+    FrameLimit,
+    DetailExhausted,
+    PrecisionLimit,
+    Cancelled,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestFrame {
+    pub index: usize,
+    pub file: String,
+    pub width: f64,
+    pub metric: f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ZoomManifest {
+    pub fractal: FractalKind,
+    pub target_re: f64,
+    pub target_im: f64,
+    pub max_iterations: u32,
+    pub stop_reason: StopReason,
+    // Depth of the last frame written, as a view width and as magnification over width 1.
+    pub final_width: f64,
+    pub final_zoom: f64,
+    pub frames: Vec<ManifestFrame>,
+}
+
+// Standard deviation of the escape values, with interior pixels counted as never escaping. A
+// frame that is uniformly interior, or uniformly one band, scores zero.
+pub fn structure_metric(buffer: &IterationBuffer) -> f64 {
+    let count = buffer.values.len();
+    if count == 0 {
+        return 0.0;
+    }
+    let ceiling = buffer
+        .params
+        .map_or(0.0, |params| params.max_iterations as f64);
+    let value = |index: usize| {
+        let value = buffer.values.get(index);
+        if !render::escaped(value) {
+            ceiling
+        } else {
+            value as f64
+        }
+    };
+    let mean = (0..count).map(value).sum::<f64>() / count as f64;
+    let variance = (0..count)
+        .map(|index| (value(index) - mean).powi(2))
+        .sum::<f64>()
+        / count as f64;
+    variance.sqrt()
+}
+
+// Whether pixels `width / size.width` apart are still well separated around the target in the
+// arithmetic the frame would be rendered with.
+pub fn within_precision(sequence: &ZoomSequence, width: f64) -> bool {
+    let viewport = Viewport::new(sequence.target_re, sequence.target_im, width);
+    Arithmetic::select(&viewport, sequence.size, sequence.double_double).resolves(
+        &viewport,
+        sequence.size,
+        sequence.auto_stop.min_pixel_ulps,
+    )
+}
+
+// Renders frames zooming into the target, handing each to `on_frame`, which saves it and returns
+// the file name recorded in the manifest. `on_tile` sees each frame's tiles as they land. Skipping
+// through `cancel` drops the frame being rendered and goes on with the next.
+pub fn render_zoom(
+    pool: &ThreadPool,
+    sequence: &ZoomSequence,
+    cancel: &CancelToken,
+    mut on_tile: impl FnMut(usize, &TileUpdate),
+    mut on_frame: impl FnMut(usize, &IterationBuffer) -> String,
+) -> ZoomManifest {
+    let mut manifest = ZoomManifest {
+        fractal: sequence.fractal,
+        target_re: sequence.target_re,
+        target_im: sequence.target_im,
+        max_iterations: sequence.max_iterations,
+        stop_reason: StopReason::FrameLimit,
+        final_width: sequence.start_width,
+        final_zoom: 1.0 / sequence.start_width,
+        frames: Vec::new(),
+    };
+    let auto_stop = sequence.auto_stop;
+    let mut width = sequence.start_width;
+    let mut featureless = 0;
+    for index in 0..sequence.max_frames {
+        if auto_stop.enabled && !within_precision(sequence, width) {
+            manifest.stop_reason = StopReason::PrecisionLimit;
+            break;
+        }
+        let viewport = Viewport::new(sequence.target_re, sequence.target_im, width);
+        let params = FrameParams {
+            viewport,
+            max_iterations: sequence.max_iterations,
+            fractal: sequence.fractal,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: sequence.coarse_prepass,
+            arithmetic: Arithmetic::select(&viewport, sequence.size, sequence.double_double),
+        };
+        let rendered = render::render_focused(
+            pool,
+            sequence.size,
+            params,
+            cancel,
+            &Focus::default(),
+            |update| on_tile(index, &update),
+        );
+        let Ok(buffer) = rendered else {
+            // A skipped frame leaves a gap in the numbering; the zoom carries on past it.
+            if cancel.take_skip() {
+                width /= sequence.zoom_per_frame;
+                continue;
+            }
+            manifest.stop_reason = StopReason::Cancelled;
+            break;
+        };
+        let metric = structure_metric(&buffer);
+        let file = on_frame(index, &buffer);
+        manifest.frames.push(ManifestFrame {
+            index,
+            file,
+            width,
+            metric,
+        });
+        manifest.final_width = width;
+        manifest.final_zoom = 1.0 / width;
+
+        featureless = if metric < auto_stop.threshold {
+            featureless + 1
+        } else {
+            0
+        };
+        if auto_stop.enabled && featureless >= auto_stop.consecutive {
+            manifest.stop_reason = StopReason::DetailExhausted;
+            break;
+        }
+        width /= sequence.zoom_per_frame;
+    }
+    manifest
+}
+
+// What the frames of a camera path export are rendered at; their views come from the path.
+#[derive(Clone, Copy, Debug)]
+pub struct PathSequence {
+    pub size: Size,
+    pub max_iterations: u32,
+    pub coarse_prepass: bool,
+    pub double_double: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PathFrame {
+    pub index: usize,
+    pub file: String,
+    // Seconds into the path.
+    pub time: f64,
+    pub viewport: Viewport,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PathManifest {
+    pub fractal: FractalKind,
+    pub fps: f64,
+    pub max_iterations: u32,
+    pub cancelled: bool,
+    pub frames: Vec<PathFrame>,
+}
+
+// Renders every frame of `path` at its frame rate, taking each view from `viewport_at` as the
+// preview scrubber does, and hands them to `on_frame` like `render_zoom`. Skipping through
+// `cancel` drops the frame being rendered.
+pub fn render_path(
+    pool: &ThreadPool,
+    path: &CameraPath,
+    sequence: &PathSequence,
+    cancel: &CancelToken,
+    mut on_tile: impl FnMut(usize, &TileUpdate),
+    mut on_frame: impl FnMut(usize, &IterationBuffer) -> String,
+) -> PathManifest {
+    let mut manifest = PathManifest {
+        fractal: path.fractal,
+        fps: path.fps,
+        max_iterations: sequence.max_iterations,
+        cancelled: false,
+        frames: Vec::new(),
+    };
+    for index in 0..path.frame_count() {
+        let time = path.frame_time(index);
+        let Some(viewport) = path.viewport_at(time) else {
+            break;
+        };
+        let params = FrameParams {
+            viewport,
+            max_iterations: sequence.max_iterations,
+            fractal: path.fractal,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: sequence.coarse_prepass,
+            arithmetic: Arithmetic::select(&viewport, sequence.size, sequence.double_double),
+        };
+        let rendered = render::render_focused(
+            pool,
+            sequence.size,
+            params,
+            cancel,
+            &Focus::default(),
+            |update| on_tile(index, &update),
+        );
+        let Ok(buffer) = rendered else {
+            if cancel.take_skip() {
+                continue;
+            }
+            manifest.cancelled = true;
+            break;
+        };
+        let file = on_frame(index, &buffer);
+        manifest.frames.push(PathFrame {
+            index,
+            file,
+            time,
+            viewport,
+        });
+    }
+    manifest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::{Easing, Keyframe};
+
+    // An export renders exactly the views the preview shows, one per frame.
+    #[test]
+    fn path_exports_render_the_previewed_views() {
+        let wide = Viewport::new(-0.75, 0.0, 3.0);
+        let keyframe = |viewport, travel| Keyframe {
+            viewport,
+            hold: 0.5,
+            travel,
+            easing: Easing::EaseInOut,
+        };
+        let path = CameraPath {
+            fractal: FractalKind::Mandelbrot,
+            fps: 4.0,
+            keyframes: vec![
+                keyframe(wide, 2.0),
+                keyframe(Viewport::new(-0.7436, 0.1318, 3e-3), 0.0),
+            ],
+        };
+        let sequence = PathSequence {
+            size: Size::new(16.0, 9.0),
+            max_iterations: 50,
+            coarse_prepass: false,
+            double_double: false,
+        };
+        let manifest = render_path(
+            &ThreadPool::new(1),
+            &path,
+            &sequence,
+            &CancelToken::new(),
+            |_, _| {},
+            |index, _| index.to_string(),
+        );
+        assert_eq!(manifest.frames.len(), path.frame_count());
+        for frame in &manifest.frames {
+            assert_eq!(
+                path.viewport_at(frame.time),
+                Some(frame.viewport),
+                "frame {} at {} s",
+                frame.file,
+                frame.time
+            );
+        }
+    }
+}
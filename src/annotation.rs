@@ -0,0 +1,606 @@
+use iced::{Point, Rectangle, Size, Vector};
+
+use serde::{Deserialize, Serialize};
+
+use crate::doubledouble::DoubleDouble;
+use crate::viewport::Viewport;
+
+// Side of one pixel of the label font on screen; exports scale it with their size.
+pub const CELL: f32 = 2.0;
+// How far from an anchor, a tail or a label a click still grabs it, in window pixels.
+pub const HIT_RADIUS: f32 = 8.0;
+// The label font: printable ASCII from ' ', five columns a glyph, bit n of a column lit in row n.
+const FIRST_GLYPH: char = ' ';
+const GLYPH_ROWS: usize = 8;
+const GLYPH_ADVANCE: f32 = 6.0;
+const GLYPHS: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5f, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7f, 0x14, 0x7f, 0x14],
+    [0x24, 0x2a, 0x7f, 0x2a, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x56, 0x20, 0x50],
+    [0x00, 0x08, 0x07, 0x03, 0x00],
+    [0x00, 0x1c, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1c, 0x00],
+    [0x2a, 0x1c, 0x7f, 0x1c, 0x2a],
+    [0x08, 0x08, 0x3e, 0x08, 0x08],
+    [0x00, 0x80, 0x70, 0x30, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x00, 0x60, 0x60, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3e, 0x51, 0x49, 0x45, 0x3e],
+    [0x00, 0x42, 0x7f, 0x40, 0x00],
+    [0x72, 0x49, 0x49, 0x49, 0x46],
+    [0x21, 0x41, 0x49, 0x4d, 0x33],
+    [0x18, 0x14, 0x12, 0x7f, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3c, 0x4a, 0x49, 0x49, 0x31],
+    [0x41, 0x21, 0x11, 0x09, 0x07],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x46, 0x49, 0x49, 0x29, 0x1e],
+    [0x00, 0x00, 0x14, 0x00, 0x00],
+    [0x00, 0x40, 0x34, 0x00, 0x00],
+    [0x00, 0x08, 0x14, 0x22, 0x41],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x59, 0x09, 0x06],
+    [0x3e, 0x41, 0x5d, 0x59, 0x4e],
+    [0x7c, 0x12, 0x11, 0x12, 0x7c],
+    [0x7f, 0x49, 0x49, 0x49, 0x36],
+    [0x3e, 0x41, 0x41, 0x41, 0x22],
+    [0x7f, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x49, 0x49, 0x49, 0x41],
+    [0x7f, 0x09, 0x09, 0x09, 0x01],
+    [0x3e, 0x41, 0x41, 0x51, 0x73],
+    [0x7f, 0x08, 0x08, 0x08, 0x7f],
+    [0x00, 0x41, 0x7f, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3f, 0x01],
+    [0x7f, 0x08, 0x14, 0x22, 0x41],
+    [0x7f, 0x40, 0x40, 0x40, 0x40],
+    [0x7f, 0x02, 0x1c, 0x02, 0x7f],
+    [0x7f, 0x04, 0x08, 0x10, 0x7f],
+    [0x3e, 0x41, 0x41, 0x41, 0x3e],
+    [0x7f, 0x09, 0x09, 0x09, 0x06],
+    [0x3e, 0x41, 0x51, 0x21, 0x5e],
+    [0x7f, 0x09, 0x19, 0x29, 0x46],
+    [0x26, 0x49, 0x49, 0x49, 0x32],
+    [0x03, 0x01, 0x7f, 0x01, 0x03],
+    [0x3f, 0x40, 0x40, 0x40, 0x3f],
+    [0x1f, 0x20, 0x40, 0x20, 0x1f],
+    [0x3f, 0x40, 0x38, 0x40, 0x3f],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x03, 0x04, 0x78, 0x04, 0x03],
+    [0x61, 0x59, 0x49, 0x4d, 0x43],
+    [0x00, 0x7f, 0x41, 0x41, 0x41],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x41, 0x7f],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x03, 0x07, 0x08, 0x00],
+    [0x20, 0x54, 0x54, 0x78, 0x40],
+    [0x7f, 0x28, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x28],
+    [0x38, 0x44, 0x44, 0x28, 0x7f],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x00, 0x08, 0x7e, 0x09, 0x02],
+    [0x18, 0xa4, 0xa4, 0x9c, 0x78],
+    [0x7f, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7d, 0x40, 0x00],
+    [0x20, 0x40, 0x40, 0x3d, 0x00],
+    [0x7f, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7f, 0x40, 0x00],
+    [0x7c, 0x04, 0x78, 0x04, 0x78],
+    [0x7c, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0xfc, 0x18, 0x24, 0x24, 0x18],
+    [0x18, 0x24, 0x24, 0x18, 0xfc],
+    [0x7c, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x24],
+    [0x04, 0x04, 0x3f, 0x44, 0x24],
+    [0x3c, 0x40, 0x40, 0x20, 0x7c],
+    [0x1c, 0x20, 0x40, 0x20, 0x1c],
+    [0x3c, 0x40, 0x30, 0x40, 0x3c],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x4c, 0x90, 0x90, 0x90, 0x7c],
+    [0x44, 0x64, 0x54, 0x4c, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x77, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x02, 0x01, 0x02, 0x04, 0x02],
+];
+// Arrowheads are this many cells long, their sides this far off the shaft in radians.
+const HEAD_LENGTH: f32 = 5.0;
+const HEAD_ANGLE: f32 = 0.45;
+
+// A point of the plane an annotation is pinned to. Like a viewport's center it carries low-order
+// parts, so it stays on its feature in views too narrow for f64 coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Pin {
+    pub re: f64,
+    pub im: f64,
+    #[serde(default)]
+    pub re_lo: f64,
+    #[serde(default)]
+    pub im_lo: f64,
+}
+
+impl Pin {
+    // The point shown at `point` of a `size` frame of `viewport`, worked out in pixel offsets
+    // from its center.
+    pub fn at(viewport: &Viewport, point: Point, size: Size) -> Pin {
+        let pixel_size = viewport.pixel_size(size);
+        let (re, im) = viewport.center();
+        let re = re + DoubleDouble::from((point.x - size.width / 2.0) as f64 * pixel_size);
+        let im = im - DoubleDouble::from((point.y - size.height / 2.0) as f64 * pixel_size);
+        Pin {
+            re: re.hi,
+            im: im.hi,
+            re_lo: re.lo,
+            im_lo: im.lo,
+        }
+    }
+
+    // Where the anchor is shown in a `size` frame of `viewport`, which may be outside it.
+    pub fn position(&self, viewport: &Viewport, size: Size) -> Point {
+        let pixel_size = viewport.pixel_size(size);
+        let (re, im) = viewport.center();
+        let dx = (DoubleDouble::new(self.re, self.re_lo) - re).to_f64() / pixel_size;
+        let dy = (DoubleDouble::new(self.im, self.im_lo) - im).to_f64() / pixel_size;
+        Point::new(
+            (size.width as f64 / 2.0 + dx) as f32,
+            (size.height as f64 / 2.0 - dy) as f32,
+        )
+    }
+}
+
+// A text label pinned to a feature, optionally with an arrow pointing at it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: u64,
+    pub text: String,
+    pub anchor: Pin,
+    // Where an arrow to the anchor starts, with the text beside it; None for a plain label.
+    #[serde(default)]
+    pub tail: Option<Pin>,
+    // The view widths the annotation is shown between; None leaves that end open.
+    #[serde(default)]
+    pub min_width: Option<f64>,
+    #[serde(default)]
+    pub max_width: Option<f64>,
+}
+
+impl Annotation {
+    pub fn visible(&self, viewport: &Viewport) -> bool {
+        self.min_width.is_none_or(|min| viewport.width >= min)
+            && self.max_width.is_none_or(|max| viewport.width <= max)
+    }
+
+    // The same text and placement, whatever the ids; how copies from bookmarks and projects are
+    // told apart from annotations already there.
+    pub fn same_as(&self, other: &Annotation) -> bool {
+        Annotation {
+            id: 0,
+            ..self.clone()
+        } == Annotation {
+            id: 0,
+            ..other.clone()
+        }
+    }
+}
+
+// The next free id among `annotations`.
+pub fn next_id(annotations: &[Annotation]) -> u64 {
+    annotations
+        .iter()
+        .map(|annotation| annotation.id + 1)
+        .max()
+        .unwrap_or(1)
+}
+
+// Which point of an annotation a click grabbed; grabbing the text moves what it is placed by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Part {
+    Anchor,
+    Tail,
+}
+
+// An annotation laid out in a frame: the cells of its text, arrow and anchor dot to fill, each
+// to be drawn over a halo, and the points and box it is grabbed by.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Marks {
+    pub id: u64,
+    pub ink: Vec<Rectangle>,
+    pub anchor: Point,
+    pub tail: Option<Point>,
+    pub label: Rectangle,
+    // How far the halo reaches around the ink.
+    pub halo: f32,
+}
+
+impl Marks {
+    // `annotation` with its points where `place` shows them and its font's cells `scale` times
+    // `CELL`. The text stays upright however the points are placed.
+    pub fn of(annotation: &Annotation, place: &impl Fn(Pin) -> Point, scale: f32) -> Marks {
+        let cell = CELL * scale;
+        let anchor = place(annotation.anchor);
+        let tail = annotation.tail.map(place);
+        let text_width = annotation.text.chars().count() as f32 * GLYPH_ADVANCE * cell;
+        let text_height = GLYPH_ROWS as f32 * cell;
+        let mut ink = Vec::new();
+        let label = match tail {
+            None => {
+                ink.push(square(anchor, 3.0 * cell));
+                Rectangle {
+                    x: anchor.x + 3.0 * cell,
+                    y: anchor.y - 3.5 * cell,
+                    width: text_width,
+                    height: text_height,
+                }
+            }
+            Some(tail) => {
+                stroke(&mut ink, tail, anchor, cell);
+                let length = anchor.distance(tail);
+                if length > 0.0 {
+                    let back = (tail - anchor) * (HEAD_LENGTH * cell / length);
+                    for angle in [HEAD_ANGLE, -HEAD_ANGLE] {
+                        let (sin, cos) = angle.sin_cos();
+                        let side =
+                            Vector::new(back.x * cos - back.y * sin, back.x * sin + back.y * cos);
+                        stroke(&mut ink, anchor, anchor + side, cell);
+                    }
+                }
+                // The text sits on the side of the tail away from the anchor.
+                let x = if anchor.x >= tail.x {
+                    tail.x - 2.0 * cell - text_width
+                } else {
+                    tail.x + 2.0 * cell
+                };
+                Rectangle {
+                    x,
+                    y: tail.y - 3.5 * cell,
+                    width: text_width,
+                    height: text_height,
+                }
+            }
+        };
+        for (index, letter) in annotation.text.chars().enumerate() {
+            let glyph = glyph(letter);
+            let left = label.x + index as f32 * GLYPH_ADVANCE * cell;
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in 0..GLYPH_ROWS {
+                    if bits & (1 << row) != 0 {
+                        ink.push(Rectangle {
+                            x: left + column as f32 * cell,
+                            y: label.y + row as f32 * cell,
+                            width: cell,
+                            height: cell,
+                        });
+                    }
+                }
+            }
+        }
+        Marks {
+            id: annotation.id,
+            ink,
+            anchor,
+            tail,
+            label,
+            halo: cell,
+        }
+    }
+
+    // Which part of the annotation `point` grabs, if any.
+    pub fn hit(&self, point: Point) -> Option<Part> {
+        let label = Rectangle {
+            x: self.label.x - HIT_RADIUS,
+            y: self.label.y - HIT_RADIUS,
+            width: self.label.width + 2.0 * HIT_RADIUS,
+            height: self.label.height + 2.0 * HIT_RADIUS,
+        };
+        if point.distance(self.anchor) <= HIT_RADIUS {
+            Some(Part::Anchor)
+        } else if self
+            .tail
+            .is_some_and(|tail| point.distance(tail) <= HIT_RADIUS)
+        {
+            Some(Part::Tail)
+        } else if label.contains(point) {
+            Some(if self.tail.is_some() {
+                Part::Tail
+            } else {
+                Part::Anchor
+            })
+        } else {
+            None
+        }
+    }
+}
+
+// The annotations shown in `viewport`, placed by `place` and laid out at `scale`, in drawing
+// order.
+pub fn layout(
+    annotations: &[Annotation],
+    viewport: &Viewport,
+    place: impl Fn(Pin) -> Point,
+    scale: f32,
+) -> Vec<Marks> {
+    annotations
+        .iter()
+        .filter(|annotation| annotation.visible(viewport))
+        .map(|annotation| Marks::of(annotation, &place, scale))
+        .collect()
+}
+
+// The topmost annotation shown at `point` of a window of `viewport` that `place` puts points in,
+// and the part of it grabbed. Everything is laid out in window pixels, so a grab reaches as far
+// at any zoom.
+pub fn hit(
+    annotations: &[Annotation],
+    viewport: &Viewport,
+    place: impl Fn(Pin) -> Point,
+    point: Point,
+) -> Option<(u64, Part)> {
+    layout(annotations, viewport, place, 1.0)
+        .iter()
+        .rev()
+        .find_map(|marks| Some((marks.id, marks.hit(point)?)))
+}
+
+// Paints the annotations shown in an RGBA image of `viewport`, with the font scaled by `scale`:
+// each one's halo in black and then its ink in white, over whatever was painted before it.
+pub fn burn_in(
+    annotations: &[Annotation],
+    viewport: &Viewport,
+    rgba: &mut [u8],
+    width: u32,
+    height: u32,
+    scale: f32,
+) {
+    let size = Size::new(width as f32, height as f32);
+    let (width, height) = (width as usize, height as usize);
+    let mut fill = |rect: Rectangle, color: [u8; 4]| {
+        let clamp = |value: f32, max: usize| (value.round().max(0.0) as usize).min(max);
+        let (x0, x1) = (clamp(rect.x, width), clamp(rect.x + rect.width, width));
+        let (y0, y1) = (clamp(rect.y, height), clamp(rect.y + rect.height, height));
+        for y in y0..y1 {
+            for pixel in rgba[(y * width + x0) * 4..(y * width + x1) * 4].chunks_exact_mut(4) {
+                pixel.copy_from_slice(&color);
+            }
+        }
+    };
+    let place = |anchor: Pin| anchor.position(viewport, size);
+    for marks in layout(annotations, viewport, place, scale) {
+        for rect in &marks.ink {
+            fill(grown(*rect, marks.halo), [0, 0, 0, 255]);
+        }
+        for rect in &marks.ink {
+            fill(*rect, [255, 255, 255, 255]);
+        }
+    }
+}
+
+// `rect` grown by `by` on every side, as a halo is drawn.
+pub fn grown(rect: Rectangle, by: f32) -> Rectangle {
+    Rectangle {
+        x: rect.x - by,
+        y: rect.y - by,
+        width: rect.width + 2.0 * by,
+        height: rect.height + 2.0 * by,
+    }
+}
+
+fn glyph(letter: char) -> [u8; 5] {
+    let index = (letter as u32).wrapping_sub(FIRST_GLYPH as u32) as usize;
+    GLYPHS
+        .get(index)
+        .copied()
+        .unwrap_or(GLYPHS['?' as usize - FIRST_GLYPH as usize])
+}
+
+fn square(center: Point, side: f32) -> Rectangle {
+    Rectangle {
+        x: center.x - side / 2.0,
+        y: center.y - side / 2.0,
+        width: side,
+        height: side,
+    }
+}
+
+// Cells along the line from `from` to `to`, half a cell apart.
+fn stroke(ink: &mut Vec<Rectangle>, from: Point, to: Point, cell: f32) {
+    let steps = (from.distance(to) / (cell / 2.0)).ceil().max(1.0) as usize;
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let point = Point::new(from.x + (to.x - from.x) * t, from.y + (to.y - from.y) * t);
+        ink.push(square(point, cell));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Size = Size::new(400.0, 300.0);
+
+    fn wide() -> Viewport {
+        Viewport::new(-0.75, 0.1, 2.0)
+    }
+
+    // Narrower than f64 resolves around its center, which only the low-order parts place.
+    fn deep() -> Viewport {
+        let mut deep = Viewport::new(-0.75, 0.1, 1e-20);
+        deep.center_re_lo = 3e-18;
+        deep.center_im_lo = -2e-18;
+        deep
+    }
+
+    fn label() -> Annotation {
+        Annotation {
+            id: 1,
+            text: String::from("Seahorse"),
+            anchor: Pin::at(&wide(), Point::new(100.0, 100.0), SIZE),
+            tail: None,
+            min_width: None,
+            max_width: Some(4.0),
+        }
+    }
+
+    fn arrow() -> Annotation {
+        Annotation {
+            id: 2,
+            text: String::from("Spiral"),
+            anchor: Pin::at(&wide(), Point::new(300.0, 200.0), SIZE),
+            tail: Some(Pin::at(&wide(), Point::new(250.0, 150.0), SIZE)),
+            min_width: None,
+            max_width: None,
+        }
+    }
+
+    #[test]
+    fn pins_stay_on_their_point_of_the_plane() {
+        for viewport in [wide(), deep()] {
+            for point in [
+                Point::new(0.0, 0.0),
+                Point::new(123.0, 45.5),
+                Point::new(399.0, 299.0),
+            ] {
+                let back = Pin::at(&viewport, point, SIZE).position(&viewport, SIZE);
+                assert!(
+                    back.distance(point) <= 1e-3,
+                    "a pin at {:?} of a view {:e} wide comes back at {:?}",
+                    point,
+                    viewport.width,
+                    back
+                );
+            }
+        }
+        // Zooming in ten times on the pin keeps it at the center.
+        let deep = deep();
+        let pin = Pin::at(&deep, Point::new(300.0, 75.0), SIZE);
+        let (dre, dim) = (100.0 * deep.pixel_size(SIZE), 75.0 * deep.pixel_size(SIZE));
+        let center = pin.position(&deep.shifted(dre, dim, deep.width / 10.0), SIZE);
+        assert!(
+            center.distance(Point::new(200.0, 150.0)) <= 1e-2,
+            "a pin zoomed in on lands at {:?}",
+            center
+        );
+    }
+
+    #[test]
+    fn ranges_hide_annotations_outside_them() {
+        let label = Annotation {
+            min_width: Some(1e-3),
+            ..label()
+        };
+        for (width, shown) in [(2.0, true), (1e-4, false), (8.0, false)] {
+            assert_eq!(
+                label.visible(&Viewport::new(-0.75, 0.1, width)),
+                shown,
+                "a label for widths 1e-3 to 4 is shown {} at width {:e}",
+                !shown,
+                width
+            );
+        }
+    }
+
+    // Each pin seen as is and a thousand times closer, at the center in turn.
+    #[test]
+    fn clicks_reach_as_far_at_any_zoom() {
+        let (wide, annotations) = (wide(), [label(), arrow()]);
+        for annotation in &annotations {
+            let point = annotation.anchor.position(&wide, SIZE);
+            let (re, im) = (
+                (point.x - 200.0) as f64 * wide.pixel_size(SIZE),
+                (150.0 - point.y) as f64 * wide.pixel_size(SIZE),
+            );
+            let zoomed = wide.shifted(re, im, wide.width / 1000.0);
+            for viewport in [wide, zoomed] {
+                let place = |pin: Pin| pin.position(&viewport, SIZE);
+                let at = place(annotation.anchor);
+                let clicked = |offset| hit(&annotations, &viewport, place, at + offset);
+                assert_eq!(
+                    clicked(Vector::new(5.0, 5.0)),
+                    Some((annotation.id, Part::Anchor)),
+                    "a click beside annotation {} at width {:e} misses it",
+                    annotation.id,
+                    viewport.width
+                );
+                assert_eq!(clicked(Vector::new(0.0, 30.0)), None);
+            }
+        }
+    }
+
+    // A label is grabbed by its text, an arrow by its text beside its tail.
+    #[test]
+    fn labels_are_grabbed_by_their_text() {
+        let (wide, label, mut arrow) = (wide(), label(), arrow());
+        let place = |pin: Pin| pin.position(&wide, SIZE);
+        let marks = layout(&[label, arrow.clone()], &wide, place, 1.0);
+        let label_center = |marks: &Marks| {
+            Point::new(
+                marks.label.x + marks.label.width / 2.0,
+                marks.label.y + marks.label.height / 2.0,
+            )
+        };
+        assert_eq!(marks.len(), 2);
+        assert_eq!(marks[0].hit(label_center(&marks[0])), Some(Part::Anchor));
+        assert_eq!(marks[1].hit(label_center(&marks[1])), Some(Part::Tail));
+        assert!(
+            marks[1].label.x + marks[1].label.width <= marks[1].tail.map_or(0.0, |tail| tail.x),
+            "an arrow's text is not beside its tail"
+        );
+        arrow.max_width = Some(1.0);
+        assert_eq!(
+            hit(&[arrow], &wide, place, Point::new(300.0, 200.0)),
+            None,
+            "a hidden annotation is grabbed"
+        );
+    }
+
+    // "I" lights eleven cells of the font and the anchor's dot nine more, so twice the scale
+    // paints four times the pixels.
+    #[test]
+    fn labels_are_painted_at_the_scale_of_the_export() {
+        let sample = Annotation {
+            text: String::from("I"),
+            anchor: Pin::at(&wide(), Point::new(20.0, 40.0), Size::new(80.0, 80.0)),
+            ..label()
+        };
+        for scale in [1.0, 2.0] {
+            let mut rgba = vec![128; 80 * 80 * 4];
+            burn_in(
+                std::slice::from_ref(&sample),
+                &wide(),
+                &mut rgba,
+                80,
+                80,
+                scale,
+            );
+            let count = |value: u8| {
+                rgba.chunks_exact(4)
+                    .filter(|pixel| pixel[..3] == [value; 3])
+                    .count()
+            };
+            let cell = CELL * scale;
+            let expected = 20 * (cell * cell) as usize;
+            assert!(
+                count(255) == expected && count(0) > 0,
+                "a label painted at scale {} has {} ink and {} halo pixels, not {} ink",
+                scale,
+                count(255),
+                count(0),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn annotations_survive_saving() {
+        let annotation = arrow();
+        let text = toml::to_string(&annotation).expect("annotations serialize");
+        let read: Annotation = toml::from_str(&text).expect("saved annotations read back");
+        assert_eq!(read, annotation);
+    }
+}
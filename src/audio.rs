@@ -0,0 +1,102 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+use num::complex::Complex;
+
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mandelbrot::fractal::FractalKind;
+use mandelbrot::sonify::{self, Pacer};
+
+// How long the audio thread sleeps with nothing waiting to be played.
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+enum Request {
+    Hover(FractalKind, Complex<f64>, u32),
+    Quiet,
+}
+
+// Plays the orbits of hovered points on a thread of its own, which owns the output stream and
+// paces requests with a `Pacer`. Dropping it stops the thread and the stream.
+#[derive(Debug)]
+pub struct Sonifier {
+    tx: Sender<Request>,
+}
+
+impl Sonifier {
+    pub fn start() -> Sonifier {
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || play(rx));
+        Sonifier { tx }
+    }
+
+    pub fn hover(&self, kind: FractalKind, c: Complex<f64>, max_iterations: u32) {
+        let _ = self.tx.send(Request::Hover(kind, c, max_iterations));
+    }
+
+    // The cursor left the view; whatever was waiting to be played is dropped.
+    pub fn quiet(&self) {
+        let _ = self.tx.send(Request::Quiet);
+    }
+}
+
+fn play(rx: Receiver<Request>) {
+    let queue = Arc::new(Mutex::new(VecDeque::new()));
+    let (stream, sample_rate) = match open_stream(Arc::clone(&queue)) {
+        Ok(opened) => opened,
+        Err(err) => {
+            println!("orbit sound unavailable: {}", err);
+            return;
+        }
+    };
+    let mut pacer = Pacer::default();
+    loop {
+        let wait = pacer.wait(Instant::now()).unwrap_or(IDLE_WAIT);
+        match rx.recv_timeout(wait) {
+            Ok(Request::Hover(kind, c, max_iterations)) => {
+                pacer.request(sonify::orbit(kind, c, max_iterations), Instant::now());
+            }
+            Ok(Request::Quiet) => pacer.cancel(),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        if let Some(orbit) = pacer.due(Instant::now(), |orbit| sonify::duration(orbit.len())) {
+            queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .extend(sonify::samples(&orbit, sample_rate));
+        }
+    }
+    drop(stream);
+}
+
+// The default output device's stream, fed from `queue` and silent while it is empty, and its
+// sample rate.
+fn open_stream(queue: Arc<Mutex<VecDeque<f32>>>) -> Result<(cpal::Stream, u32), String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or_else(|| String::from("no output device"))?;
+    let config = device
+        .default_output_config()
+        .map_err(|err| err.to_string())?;
+    let sample_rate = config.sample_rate().0;
+    let channels = config.channels() as usize;
+    let stream = device
+        .build_output_stream(
+            &config.into(),
+            move |data: &mut [f32], _| {
+                let mut queue = queue.lock().unwrap_or_else(PoisonError::into_inner);
+                for frame in data.chunks_mut(channels) {
+                    frame.fill(queue.pop_front().unwrap_or(0.0));
+                }
+            },
+            |err| println!("orbit sound stream failed: {}", err),
+            None,
+        )
+        .map_err(|err| err.to_string())?;
+    stream.play().map_err(|err| err.to_string())?;
+    Ok((stream, sample_rate))
+}
@@ -0,0 +1,478 @@
+use iced::Size;
+
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use threadpool::ThreadPool;
+
+use mandelbrot::annotation::Annotation;
+use mandelbrot::coloring;
+use mandelbrot::export;
+use mandelbrot::fractal::{AngleKind, FractalKind};
+use mandelbrot::merge::{self, Merged, Record};
+use mandelbrot::relative::RelativeView;
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams};
+use mandelbrot::sampling::Sampling;
+use mandelbrot::settings::{ColoringSettings, Overrides};
+use mandelbrot::storage::Precision;
+use mandelbrot::store::{self, Stamp};
+use mandelbrot::viewport::Viewport;
+
+pub const THUMBNAIL_SIZE: Size = Size::new(128.0, 80.0);
+const THUMBNAIL_ITERATIONS: u32 = 500;
+// Thumbnails are rendered at this many times their size each way and averaged down by escape
+// value, so filaments narrower than a thumbnail pixel still show.
+const THUMBNAIL_SUPERSAMPLE: f32 = 3.0;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub id: u64,
+    pub name: String,
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    // The iterations and coloring the bookmark was added with, applied only when asked for.
+    #[serde(default)]
+    pub overrides: Overrides,
+    // Whether selecting the bookmark applies its overrides without a Shift-click.
+    #[serde(default)]
+    pub restore_settings: bool,
+    // The annotations shown in the view when it was added, brought back when it is selected.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    // Set on relative bookmarks: the view as taken relative to a minibrot, to be applied at
+    // another. `viewport` is where it was taken.
+    #[serde(default)]
+    pub relative: Option<RelativeView>,
+}
+
+impl Bookmark {
+    // Thumbnails live as PNGs beside the bookmark file, named after the entry and the view it
+    // shows: other windows share the folder, and may hold a different bookmark under the same id.
+    pub fn thumbnail_name(&self) -> String {
+        let mut hash = 0xcbf29ce484222325u64;
+        for byte in format!("{:?} {:?}", self.fractal, self.viewport).bytes() {
+            hash = (hash ^ byte as u64).wrapping_mul(0x100000001b3);
+        }
+        format!("bookmark-{}-{:016x}.png", self.id, hash)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Preset {
+    pub name: &'static str,
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    // What the view is best seen with, applied on a Shift-click.
+    pub overrides: Overrides,
+}
+
+impl Preset {
+    pub fn builtin() -> Vec<Preset> {
+        let preset =
+            |name, fractal, center_re, center_im, width, max_iterations, palette: &str| Preset {
+                name,
+                fractal,
+                viewport: Viewport::new(center_re, center_im, width),
+                overrides: Overrides {
+                    max_iterations: Some(max_iterations),
+                    coloring: Some(ColoringSettings {
+                        palette: palette.to_string(),
+                        ..ColoringSettings::default()
+                    }),
+                },
+            };
+        vec![
+            preset(
+                "Seahorse Valley",
+                FractalKind::Mandelbrot,
+                -0.7436,
+                0.1318,
+                0.012,
+                2000,
+                "Ocean",
+            ),
+            preset(
+                "Elephant Valley",
+                FractalKind::Mandelbrot,
+                0.2925,
+                0.0155,
+                0.04,
+                1500,
+                "Fire",
+            ),
+            preset(
+                "Triple Spiral",
+                FractalKind::Mandelbrot,
+                -0.0885,
+                0.6545,
+                0.02,
+                2000,
+                "Ocean",
+            ),
+            preset(
+                "Period-3 Minibrot",
+                FractalKind::Mandelbrot,
+                -1.7549,
+                0.0,
+                0.04,
+                3000,
+                "Monochrome",
+            ),
+            preset(
+                "Armada",
+                FractalKind::BurningShip,
+                -1.77,
+                -0.04,
+                0.2,
+                1000,
+                "Fire",
+            ),
+        ]
+    }
+
+    pub fn thumbnail_name(&self) -> String {
+        format!("preset-{}.png", self.name.to_lowercase().replace(' ', "-"))
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bookmarks {
+    pub bookmarks: Vec<Bookmark>,
+    // The bookmarks as last read from or written to the file, which changes since are merged
+    // against when another window has saved it meanwhile, and the file's stamp then.
+    #[serde(skip)]
+    synced: Vec<Bookmark>,
+    #[serde(skip)]
+    stamp: Option<Stamp>,
+}
+
+impl Bookmarks {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mandelbrot").join("bookmarks.toml"))
+    }
+
+    pub fn thumbnail_dir() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mandelbrot").join("thumbnails"))
+    }
+
+    pub fn load() -> Bookmarks {
+        Bookmarks::path().map_or_else(Bookmarks::default, |path| Bookmarks::load_from(&path))
+    }
+
+    pub fn load_from(path: &Path) -> Bookmarks {
+        let mut bookmarks = store::load(path, "bookmarks", parse).unwrap_or_default();
+        bookmarks.synced = bookmarks.bookmarks.clone();
+        bookmarks.stamp = store::stamp(path);
+        bookmarks
+    }
+
+    // Saves the bookmarks, first merging in whatever another window saved since they were last
+    // read or written. Returns what the merge brought in, if anything.
+    pub fn save(&mut self) -> Option<Merged> {
+        self.save_to(&Bookmarks::path()?)
+    }
+
+    pub fn save_to(&mut self, path: &Path) -> Option<Merged> {
+        let mut merged = None;
+        let result = store::update(path, |current| {
+            if let Some(theirs) = current.and_then(|contents| parse(contents).ok()) {
+                merged = self.merge_in(&theirs.bookmarks);
+            }
+            toml::to_string_pretty(self).map_err(|err| err.to_string())
+        });
+        match result {
+            Ok(_) => {
+                self.synced = self.bookmarks.clone();
+                self.stamp = store::stamp(path);
+            }
+            Err(err) => println!("failed to save bookmarks {}: {}", path.display(), err),
+        }
+        merged
+    }
+
+    // Picks up the file if another window has saved it since, merging it with any changes here
+    // that are not saved yet. Returns what the merge brought in, if anything.
+    pub fn reload(&mut self) -> Option<Merged> {
+        self.reload_from(&Bookmarks::path()?)
+    }
+
+    pub fn reload_from(&mut self, path: &Path) -> Option<Merged> {
+        let stamp = store::stamp(path);
+        if stamp == self.stamp {
+            return None;
+        }
+        let contents = fs::read_to_string(path).ok()?;
+        let theirs = parse(&contents).ok()?.bookmarks;
+        self.stamp = stamp;
+        let merged = self.merge_in(&theirs)?;
+        self.synced = theirs;
+        if self.bookmarks != self.synced {
+            self.save_to(path);
+        }
+        Some(merged)
+    }
+
+    // Merges `theirs`, what the file holds now, into the bookmarks here, unless it still holds
+    // what they were last synced with.
+    fn merge_in(&mut self, theirs: &[Bookmark]) -> Option<Merged> {
+        if theirs == self.synced.as_slice() {
+            return None;
+        }
+        let merged = merge::merge(
+            &records(&self.synced),
+            &records(&self.bookmarks),
+            &records(theirs),
+        );
+        self.bookmarks = merged
+            .records
+            .iter()
+            .filter_map(|record| toml::Value::Table(record.clone()).try_into().ok())
+            .collect();
+        Some(merged)
+    }
+
+    pub fn add(
+        &mut self,
+        fractal: FractalKind,
+        viewport: Viewport,
+        max_iterations: u32,
+        coloring: &ColoringSettings,
+    ) -> &Bookmark {
+        let id = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| bookmark.id + 1)
+            .max()
+            .unwrap_or(1);
+        self.bookmarks.push(Bookmark {
+            id,
+            name: format!("Bookmark {}", id),
+            fractal,
+            viewport,
+            overrides: Overrides {
+                max_iterations: Some(max_iterations),
+                coloring: Some(coloring.clone()),
+            },
+            restore_settings: false,
+            annotations: Vec::new(),
+            relative: None,
+        });
+        &self.bookmarks[self.bookmarks.len() - 1]
+    }
+}
+
+fn parse(contents: &str) -> Result<Bookmarks, String> {
+    toml::from_str(contents).map_err(|err| err.to_string())
+}
+
+// Bookmarks as the tables they are saved as, which is what `merge` works on.
+fn records(bookmarks: &[Bookmark]) -> Vec<Record> {
+    bookmarks
+        .iter()
+        .filter_map(|bookmark| match toml::Value::try_from(bookmark) {
+            Ok(toml::Value::Table(record)) => Some(record),
+            _ => None,
+        })
+        .collect()
+}
+
+// Renders a small RGBA preview of `viewport` at `THUMBNAIL_SIZE`; with `sampling`, a rough one
+// computed from sparse samples, to show while the full one renders.
+pub fn render_thumbnail(
+    pool: &ThreadPool,
+    fractal: FractalKind,
+    viewport: Viewport,
+    coloring_settings: &ColoringSettings,
+    allow_double_double: bool,
+    sampling: Option<Sampling>,
+) -> Vec<u8> {
+    let size = Size::new(
+        THUMBNAIL_SIZE.width * THUMBNAIL_SUPERSAMPLE,
+        THUMBNAIL_SIZE.height * THUMBNAIL_SUPERSAMPLE,
+    );
+    let params = FrameParams {
+        viewport,
+        max_iterations: THUMBNAIL_ITERATIONS,
+        fractal,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: true,
+        arithmetic: Arithmetic::select(&viewport, size, allow_double_double),
+    };
+    let cancel = CancelToken::new();
+    let rendered = match sampling {
+        Some(sampling) => render::render_sampled(pool, size, params, sampling, &cancel),
+        None => render::threaded_fractal_calc(pool, size, params, &cancel, |_| {}),
+    };
+    match rendered {
+        Ok(buffer) => coloring::downscale(
+            &buffer,
+            coloring_settings,
+            THUMBNAIL_SIZE.width as usize,
+            THUMBNAIL_SIZE.height as usize,
+        ),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Loads a cached thumbnail, or renders and caches it.
+pub fn load_or_render_thumbnail(
+    pool: &ThreadPool,
+    name: &str,
+    fractal: FractalKind,
+    viewport: Viewport,
+    coloring_settings: &ColoringSettings,
+    allow_double_double: bool,
+) -> Option<Vec<u8>> {
+    let path = Bookmarks::thumbnail_dir()?.join(name);
+    if let Some(rgba) = read_png(&path) {
+        return Some(rgba);
+    }
+    let rgba = render_thumbnail(
+        pool,
+        fractal,
+        viewport,
+        coloring_settings,
+        allow_double_double,
+        None,
+    );
+    if rgba.is_empty() {
+        return None;
+    }
+    let (width, height) = (THUMBNAIL_SIZE.width as u32, THUMBNAIL_SIZE.height as u32);
+    if let Err(err) = export::write_png(&path, width, height, &rgba) {
+        println!("failed to cache thumbnail {}: {}", path.display(), err);
+    }
+    Some(rgba)
+}
+
+// A thumbnail cached by `load_or_render_thumbnail`, if there is one.
+pub fn cached_thumbnail(name: &str) -> Option<Vec<u8>> {
+    read_png(&Bookmarks::thumbnail_dir()?.join(name))
+}
+
+fn read_png(path: &Path) -> Option<Vec<u8>> {
+    let file = fs::File::open(path).ok()?;
+    let mut reader = png::Decoder::new(file).read_info().ok()?;
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut rgba).ok()?;
+    let expected = (THUMBNAIL_SIZE.width as u32, THUMBNAIL_SIZE.height as u32);
+    if info.color_type != png::ColorType::Rgba || (info.width, info.height) != expected {
+        return None;
+    }
+    rgba.truncate(info.buffer_size());
+    Some(rgba)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::{env, process, thread};
+
+    use super::*;
+
+    fn add(bookmarks: &mut Bookmarks, re: f64, im: f64) {
+        bookmarks.add(
+            FractalKind::Mandelbrot,
+            Viewport::new(re, im, 0.1),
+            100,
+            &ColoringSettings::default(),
+        );
+    }
+
+    fn names(bookmarks: &Bookmarks) -> Vec<String> {
+        bookmarks
+            .bookmarks
+            .iter()
+            .map(|bookmark| format!("{} {}", bookmark.id, bookmark.name))
+            .collect()
+    }
+
+    // Two windows sharing one bookmark file: adds under the same id, a reload and a rename made
+    // in both.
+    #[test]
+    fn windows_sharing_a_file_keep_each_others_edits() {
+        let root = env::temp_dir().join(format!("mandelbrot-shared-{}", process::id()));
+        let file = root.join("bookmarks.toml");
+        let mut first = Bookmarks::load_from(&file);
+        add(&mut first, -0.5, 0.0);
+        first.save_to(&file);
+        let mut second = Bookmarks::load_from(&file);
+        add(&mut first, -1.0, 0.0);
+        first.save_to(&file);
+        add(&mut second, -1.5, 0.0);
+        let merged = second.save_to(&file);
+        assert!(
+            merged.is_some_and(|merged| merged.added == 1) && second.bookmarks.len() == 3,
+            "adds under one id from two windows save as {:?}",
+            names(&second)
+        );
+        assert!(
+            first.reload_from(&file).is_some() && first.bookmarks == second.bookmarks,
+            "a reload picks up {:?} of {:?}",
+            names(&first),
+            names(&second)
+        );
+        let thumbnails: HashSet<String> = first
+            .bookmarks
+            .iter()
+            .map(|bookmark| bookmark.thumbnail_name())
+            .collect();
+        assert_eq!(
+            thumbnails.len(),
+            first.bookmarks.len(),
+            "bookmarks share a thumbnail"
+        );
+
+        first.bookmarks[0].name = String::from("first");
+        first.save_to(&file);
+        second.bookmarks[0].name = String::from("second");
+        let merged = second.save_to(&file);
+        let saved = names(&Bookmarks::load_from(&file));
+        let copy = format!("4 first{}", merge::CONFLICT_SUFFIX);
+        assert!(
+            merged.is_some_and(|merged| merged.conflicts == 1)
+                && saved.first().map(String::as_str) == Some("1 second")
+                && saved.last() == Some(&copy),
+            "a rename made in both windows saves as {:?}",
+            saved
+        );
+        fs::remove_dir_all(&root).expect("removes the scratch directory");
+    }
+
+    #[test]
+    fn windows_saving_at_once_lose_nothing() {
+        let root = env::temp_dir().join(format!("mandelbrot-racing-{}", process::id()));
+        let file = root.join("bookmarks.toml");
+        let writers: Vec<_> = (0..2)
+            .map(|writer| {
+                let file = file.clone();
+                thread::spawn(move || {
+                    let mut bookmarks = Bookmarks::load_from(&file);
+                    for step in 0..20 {
+                        add(&mut bookmarks, writer as f64 + step as f64 * 0.01, 1.0);
+                        bookmarks.save_to(&file);
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().expect("a writer finishes");
+        }
+        let saved = Bookmarks::load_from(&file).bookmarks;
+        let mut ids: Vec<u64> = saved.iter().map(|bookmark| bookmark.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!(
+            (saved.len(), ids.len()),
+            (40, 40),
+            "two windows saving twenty adds each leave {} bookmarks under {} ids",
+            saved.len(),
+            ids.len()
+        );
+        fs::remove_dir_all(&root).expect("removes the scratch directory");
+    }
+}
@@ -0,0 +1,502 @@
+use iced::Size;
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::render::{self, IterationBuffer};
+
+// The set's boundary in a rendered frame, as data for plotting elsewhere. Boundary pixels are the
+// escaped ones with a 4-neighbour that did not escape; the boundary lines are the outline between
+// the two, traced with marching squares through the pixels' sample points.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    // A header and one `re,im,value` row per boundary pixel, with its escape value.
+    Csv,
+    // A FeatureCollection of LineStrings, one per traced line, in (re, im) coordinates.
+    GeoJson,
+}
+
+impl Format {
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "CSV",
+            Format::GeoJson => "GeoJSON",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::GeoJson => "geojson",
+        }
+    }
+}
+
+// Writes the boundary of `buffer` to `path` in `format`, thinned so that no two points written
+// are closer than `spacing` pixels (0 keeps them all), and returns how many points it wrote.
+// Rows go out as they are found, so frames of any size stream to disk.
+pub fn write(
+    path: &Path,
+    buffer: &IterationBuffer,
+    format: Format,
+    spacing: f64,
+) -> Result<usize, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut out = BufWriter::new(file);
+    let points = match format {
+        Format::Csv => write_csv(&mut out, buffer, spacing)?,
+        Format::GeoJson => write_geojson(&mut out, buffer, spacing)?,
+    };
+    out.flush().map_err(|err| err.to_string())?;
+    Ok(points)
+}
+
+pub fn write_csv(
+    out: &mut impl Write,
+    buffer: &IterationBuffer,
+    spacing: f64,
+) -> Result<usize, String> {
+    let plane = Plane::of(buffer)?;
+    let mut thinning = Thinning::new(spacing);
+    let mut points = 0;
+    writeln!(out, "re,im,value").map_err(|err| err.to_string())?;
+    for (x, y) in pixels(buffer) {
+        if !thinning.keep(x as f64, y as f64) {
+            continue;
+        }
+        let (re, im) = plane.point(x as f64, y as f64);
+        let value = buffer.values.get(y * buffer.width + x);
+        writeln!(out, "{},{},{}", re, im, value).map_err(|err| err.to_string())?;
+        points += 1;
+    }
+    Ok(points)
+}
+
+pub fn write_geojson(
+    out: &mut impl Write,
+    buffer: &IterationBuffer,
+    spacing: f64,
+) -> Result<usize, String> {
+    let plane = Plane::of(buffer)?;
+    let mut points = 0;
+    let collection = "{\"type\": \"FeatureCollection\", \"features\": [";
+    write!(out, "{}", collection).map_err(|err| err.to_string())?;
+    for (index, line) in trace(buffer).iter().enumerate() {
+        let line = thin_line(line, spacing);
+        let coordinates: Vec<String> = line
+            .iter()
+            .map(|&(x, y)| {
+                let (re, im) = plane.point(x, y);
+                format!("[{}, {}]", re, im)
+            })
+            .collect();
+        write!(
+            out,
+            "{}\n{{\"type\": \"Feature\", \"properties\": {{}}, \"geometry\": \
+             {{\"type\": \"LineString\", \"coordinates\": [{}]}}}}",
+            if index == 0 { "" } else { "," },
+            coordinates.join(", ")
+        )
+        .map_err(|err| err.to_string())?;
+        points += line.len();
+    }
+    writeln!(out, "\n]}}").map_err(|err| err.to_string())?;
+    Ok(points)
+}
+
+// Where a frame's pixel positions lie in the plane, at the points its pixels were sampled.
+struct Plane {
+    left: f64,
+    top: f64,
+    pixel_size: f64,
+}
+
+impl Plane {
+    fn of(buffer: &IterationBuffer) -> Result<Plane, String> {
+        let params = buffer
+            .params
+            .ok_or_else(|| String::from("frame has no render parameters"))?;
+        let bounds = Size::new(buffer.width as f32, buffer.height as f32);
+        let (left, top) = params.viewport.top_left(bounds);
+        Ok(Plane {
+            left,
+            top,
+            pixel_size: params.viewport.pixel_size(bounds),
+        })
+    }
+
+    fn point(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.left + self.pixel_size * x,
+            self.top - self.pixel_size * y,
+        )
+    }
+}
+
+// The boundary pixels of `buffer`, row by row.
+pub fn pixels(buffer: &IterationBuffer) -> impl Iterator<Item = (usize, usize)> + '_ {
+    let (width, height) = (buffer.width, buffer.height);
+    let escaped = move |x: usize, y: usize| render::escaped(buffer.values.get(y * width + x));
+    (0..height)
+        .flat_map(move |y| (0..width).map(move |x| (x, y)))
+        .filter(move |&(x, y)| {
+            escaped(x, y)
+                && ((x > 0 && !escaped(x - 1, y))
+                    || (x + 1 < width && !escaped(x + 1, y))
+                    || (y > 0 && !escaped(x, y - 1))
+                    || (y + 1 < height && !escaped(x, y + 1)))
+        })
+}
+
+// Keeps points no closer than `spacing` to any point kept before, looking only at the grid
+// cells of that size around each new point.
+pub struct Thinning {
+    spacing: f64,
+    cells: HashMap<(i64, i64), Vec<(f64, f64)>>,
+}
+
+impl Thinning {
+    pub fn new(spacing: f64) -> Thinning {
+        Thinning {
+            spacing,
+            cells: HashMap::new(),
+        }
+    }
+
+    // Whether to keep the point at (x, y), which is then remembered if so.
+    pub fn keep(&mut self, x: f64, y: f64) -> bool {
+        if self.spacing <= 0.0 {
+            return true;
+        }
+        let cell = (
+            (x / self.spacing).floor() as i64,
+            (y / self.spacing).floor() as i64,
+        );
+        for dy in -1..=1 {
+            for dx in -1..=1 {
+                let Some(points) = self.cells.get(&(cell.0 + dx, cell.1 + dy)) else {
+                    continue;
+                };
+                if points
+                    .iter()
+                    .any(|&(px, py)| (px - x).hypot(py - y) < self.spacing)
+                {
+                    return false;
+                }
+            }
+        }
+        self.cells.entry(cell).or_default().push((x, y));
+        true
+    }
+}
+
+// `line` without the points closer than `spacing` to the last one kept; its ends always stay,
+// so closed lines stay closed.
+pub fn thin_line(line: &[(f64, f64)], spacing: f64) -> Vec<(f64, f64)> {
+    let Some((&last, rest)) = line.split_last() else {
+        return Vec::new();
+    };
+    let mut thinned: Vec<(f64, f64)> = Vec::with_capacity(line.len());
+    for &point in rest {
+        let far = thinned
+            .last()
+            .is_none_or(|kept| (kept.0 - point.0).hypot(kept.1 - point.1) >= spacing);
+        if far {
+            thinned.push(point);
+        }
+    }
+    thinned.push(last);
+    thinned
+}
+
+// An edge of the grid between pixel sample points: the one right of (x, y), or below it if
+// `down`. Its midpoint is where the outline crosses it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct Edge {
+    x: usize,
+    y: usize,
+    down: bool,
+}
+
+impl Edge {
+    fn midpoint(self) -> (f64, f64) {
+        if self.down {
+            (self.x as f64, self.y as f64 + 0.5)
+        } else {
+            (self.x as f64 + 0.5, self.y as f64)
+        }
+    }
+}
+
+// The outline between escaped pixels and the rest, as lines in pixel positions. Lines that meet
+// the frame's edge are open; the others are closed, ending where they start. Where two escaped
+// pixels touch only diagonally, the outline keeps them apart.
+pub fn trace(buffer: &IterationBuffer) -> Vec<Vec<(f64, f64)>> {
+    let (width, height) = (buffer.width, buffer.height);
+    let inside = |x: usize, y: usize| !render::escaped(buffer.values.get(y * width + x));
+    let mut segments = Vec::new();
+    for y in 0..height.saturating_sub(1) {
+        for x in 0..width.saturating_sub(1) {
+            let corners = [
+                inside(x, y),
+                inside(x + 1, y),
+                inside(x + 1, y + 1),
+                inside(x, y + 1),
+            ];
+            let top = Edge { x, y, down: false };
+            let right = Edge {
+                x: x + 1,
+                y,
+                down: true,
+            };
+            let bottom = Edge {
+                x,
+                y: y + 1,
+                down: false,
+            };
+            let left = Edge { x, y, down: true };
+            let crossed: Vec<Edge> = [top, right, bottom, left]
+                .into_iter()
+                .enumerate()
+                .filter(|(side, _)| corners[*side] != corners[(side + 1) % 4])
+                .map(|(_, edge)| edge)
+                .collect();
+            match crossed[..] {
+                [from, to] => segments.push((from, to)),
+                // A saddle: the outline cuts off each escaped corner on its own.
+                [_, _, _, _] if corners[0] => {
+                    segments.push((top, right));
+                    segments.push((bottom, left));
+                }
+                [_, _, _, _] => {
+                    segments.push((left, top));
+                    segments.push((right, bottom));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut at: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (index, (from, to)) in segments.iter().enumerate() {
+        at.entry(*from).or_default().push(index);
+        at.entry(*to).or_default().push(index);
+    }
+    let mut used = vec![false; segments.len()];
+    let follow = |start: Edge, used: &mut Vec<bool>| {
+        let mut line = vec![start.midpoint()];
+        let mut edge = start;
+        while let Some(&next) = at[&edge].iter().find(|&&index| !used[index]) {
+            used[next] = true;
+            let (from, to) = segments[next];
+            edge = if from == edge { to } else { from };
+            line.push(edge.midpoint());
+        }
+        line
+    };
+    let mut lines = Vec::new();
+    // Open lines first, from either end, then the closed ones from anywhere on them.
+    for (index, &(from, to)) in segments.iter().enumerate() {
+        for end in [from, to] {
+            if !used[index] && at[&end].len() == 1 {
+                lines.push(follow(end, &mut used));
+            }
+        }
+    }
+    for (index, &(from, _)) in segments.iter().enumerate() {
+        if !used[index] {
+            lines.push(follow(from, &mut used));
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json;
+    use crate::render::{PixelClass, Proof};
+    use crate::stats::CANONICAL_VIEWS;
+    use crate::storage::Channel;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+
+    // A frame of the first canonical view with the pixels `inside` proven in the set and the
+    // rest escaped.
+    fn frame(inside: impl Fn(f64, f64) -> bool) -> IterationBuffer {
+        IterationBuffer {
+            width: WIDTH,
+            height: HEIGHT,
+            values: Channel::Full(
+                (0..WIDTH * HEIGHT)
+                    .map(|index| {
+                        let (x, y) = ((index % WIDTH) as f64, (index / WIDTH) as f64);
+                        if inside(x, y) {
+                            PixelClass::Interior(Proof::Cardioid).value()
+                        } else {
+                            10.0
+                        }
+                    })
+                    .collect(),
+            ),
+            angles: Channel::default(),
+            params: Some(CANONICAL_VIEWS[0].params()),
+            costs: None,
+            failure: None,
+        }
+    }
+
+    fn disc() -> IterationBuffer {
+        frame(|x, y| (x - 32.0).hypot(y - 24.0) < 15.0)
+    }
+
+    fn two_discs() -> IterationBuffer {
+        frame(|x, y| (x - 14.0).hypot(y - 24.0) < 8.0 || (x - 48.0).hypot(y - 24.0) < 8.0)
+    }
+
+    fn closed(line: &[(f64, f64)]) -> bool {
+        line.len() > 2 && line.first() == line.last()
+    }
+
+    #[test]
+    fn discs_trace_as_their_circles() {
+        let lines = trace(&disc());
+        assert_eq!(lines.len(), 1);
+        assert!(closed(&lines[0]), "a disc's outline is open");
+        let off_circle = lines[0]
+            .iter()
+            .find(|(x, y)| ((x - 32.0).hypot(y - 24.0) - 15.0).abs() > 1.0);
+        assert_eq!(off_circle, None, "a disc's outline leaves its circle");
+        let pixels: Vec<(usize, usize)> = pixels(&disc()).collect();
+        let stray = pixels.iter().find(|(x, y)| {
+            let distance = (*x as f64 - 32.0).hypot(*y as f64 - 24.0);
+            !(15.0..16.5).contains(&distance)
+        });
+        assert!(!pixels.is_empty(), "a disc has no boundary pixels");
+        assert_eq!(stray, None, "a disc has a stray boundary pixel");
+        let lines = trace(&two_discs());
+        assert!(
+            lines.len() == 2 && lines.iter().all(|line| closed(line)),
+            "two discs trace as {} lines",
+            lines.len()
+        );
+    }
+
+    #[test]
+    fn shapes_cut_by_the_frame_trace_as_open_lines() {
+        let lines = trace(&frame(|x, y| x < 20.0 && (10.0..30.0).contains(&y)));
+        let on_frame = |&(x, y): &(f64, f64)| x < 1.0 || y < 1.0 || x > 62.0 || y > 46.0;
+        assert!(
+            lines.len() == 1 && !closed(&lines[0]),
+            "a block on the frame's edge traces as {} lines",
+            lines.len()
+        );
+        assert!(
+            lines[0].first().is_some_and(on_frame) && lines[0].last().is_some_and(on_frame),
+            "a block's outline runs from {:?} to {:?}",
+            lines[0].first(),
+            lines[0].last()
+        );
+    }
+
+    // Escaped pixels touching only at a corner, in the set.
+    #[test]
+    fn diagonal_neighbours_trace_apart() {
+        let lines = trace(&frame(|x, y| {
+            !matches!((x as usize, y as usize), (10, 10) | (11, 11))
+        }));
+        assert!(
+            lines.len() == 2 && lines.iter().all(|line| closed(line)),
+            "diagonal neighbours trace as {} lines",
+            lines.len()
+        );
+    }
+
+    #[test]
+    fn thinning_keeps_points_apart_without_dropping_more_than_it_needs_to() {
+        let mut thinning = Thinning::new(5.0);
+        let block: Vec<(f64, f64)> = (0..2500)
+            .map(|index| ((index % 50) as f64, (index / 50) as f64))
+            .collect();
+        let kept: Vec<(f64, f64)> = block
+            .iter()
+            .copied()
+            .filter(|&(x, y)| thinning.keep(x, y))
+            .collect();
+        let near = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).hypot(a.1 - b.1) < 5.0;
+        let crowded = kept
+            .iter()
+            .enumerate()
+            .any(|(index, a)| kept[index + 1..].iter().any(|b| near(*a, *b)));
+        let dropped_alone = block
+            .iter()
+            .any(|point| !kept.iter().any(|kept| near(*kept, *point)));
+        assert!(
+            !crowded && !dropped_alone && kept.len() >= 50,
+            "thinning a block to 5 pixels keeps {} points, crowded {}, one dropped alone {}",
+            kept.len(),
+            crowded,
+            dropped_alone
+        );
+        let ring: Vec<(f64, f64)> = (0..=100)
+            .map(|step| {
+                let angle = step as f64 * std::f64::consts::TAU / 100.0;
+                (10.0 * angle.cos(), 10.0 * angle.sin())
+            })
+            .collect();
+        let thinned = thin_line(&ring, 3.0);
+        assert_eq!(thinned.first(), ring.first());
+        assert_eq!(thinned.last(), ring.last());
+        assert!(
+            thinned.len() <= 25,
+            "thinning a closed line of {} points keeps {}",
+            ring.len(),
+            thinned.len()
+        );
+    }
+
+    // One row per boundary pixel, at its point of the plane.
+    #[test]
+    fn csv_lists_every_boundary_pixel() {
+        let disc = disc();
+        let pixels: Vec<(usize, usize)> = pixels(&disc).collect();
+        let mut csv = Vec::new();
+        let written = write_csv(&mut csv, &disc, 0.0);
+        let csv = String::from_utf8_lossy(&csv);
+        let rows: Vec<&str> = csv.lines().collect();
+        let viewport = CANONICAL_VIEWS[0].params().viewport;
+        let bounds = Size::new(WIDTH as f32, HEIGHT as f32);
+        let (left, top) = viewport.top_left(bounds);
+        let pixel_size = viewport.pixel_size(bounds);
+        let (x, y) = pixels[0];
+        let first = format!(
+            "{},{},10",
+            left + pixel_size * x as f64,
+            top - pixel_size * y as f64
+        );
+        assert_eq!(written, Ok(pixels.len()));
+        assert_eq!(rows.len(), pixels.len() + 1);
+        assert_eq!(rows[..2], ["re,im,value", first.as_str()]);
+    }
+
+    #[test]
+    fn geojson_has_a_feature_per_line() {
+        let mut geojson = Vec::new();
+        let written = write_geojson(&mut geojson, &two_discs(), 2.0);
+        assert!(written.is_ok(), "two discs write {:?}", written);
+        let collection: toml::Table =
+            json::from_str(&String::from_utf8_lossy(&geojson)).expect("the GeoJSON parses");
+        let features = collection
+            .get("features")
+            .and_then(toml::Value::as_array)
+            .map_or(0, Vec::len);
+        assert_eq!(features, 2);
+    }
+}
@@ -0,0 +1,32 @@
+use std::path::Path;
+
+use mandelbrot::boundary::{self, Format};
+use mandelbrot::rawdata;
+
+// Writes the set's boundary in a data file beside it, as CSV points unless `geojson` follows the
+// file, thinned to the pixel spacing given after that, if any.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let file = Path::new(args.first().ok_or("missing data file")?);
+    let format = match args.get(1).map(String::as_str) {
+        None | Some("csv") => Format::Csv,
+        Some("geojson") => Format::GeoJson,
+        Some(other) => return Err(format!("unknown format {}, expected csv or geojson", other)),
+    };
+    let spacing = match args.get(2) {
+        Some(spacing) => spacing
+            .parse()
+            .map_err(|err| format!("invalid spacing: {}", err))?,
+        None => 0.0,
+    };
+    let buffer =
+        rawdata::read(file).map_err(|err| format!("cannot open {}: {}", file.display(), err))?;
+    let path = file.with_extension(format.extension());
+    let points = boundary::write(&path, &buffer, format, spacing)?;
+    println!(
+        "{} boundary points as {} in {}",
+        points,
+        format.name(),
+        path.display()
+    );
+    Ok(())
+}
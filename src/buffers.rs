@@ -0,0 +1,71 @@
+use std::sync::{Mutex, PoisonError};
+
+// A free vector is only handed out for requests at least this fraction of its capacity, so a
+// request for a small frame does not take a large one's memory.
+const MAX_WASTE: usize = 4;
+
+// Per-tile iteration values and angles. Finished tiles can queue up behind a slow receiver, so
+// this keeps plenty; they are small.
+pub static TILES: BufferPool<f32> = BufferPool::new(256);
+// Whole-frame iteration values and angles: the frame on screen, the one being built and previews.
+pub static FRAMES: BufferPool<f32> = BufferPool::new(8);
+// Whole RGBA frames, before and after downsampling.
+pub static RGBA: BufferPool<u8> = BufferPool::new(4);
+
+// Vectors recycled between renders, so a steady stream of same-sized frames stops allocating
+// once the first few have warmed the pool up. Past `limit` free vectors the oldest go, which is
+// also how buffers of a size no longer rendered are let go.
+#[derive(Debug)]
+pub struct BufferPool<T> {
+    free: Mutex<Vec<Vec<T>>>,
+    limit: usize,
+}
+
+impl<T> BufferPool<T> {
+    pub const fn new(limit: usize) -> Self {
+        BufferPool {
+            free: Mutex::new(Vec::new()),
+            limit,
+        }
+    }
+
+    // An empty vector with room for at least `capacity` elements, reusing the smallest free one
+    // that fits.
+    pub fn take(&self, capacity: usize) -> Vec<T> {
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        let fits = |buffer: &Vec<T>| {
+            buffer.capacity() >= capacity && buffer.capacity() <= capacity.saturating_mul(MAX_WASTE)
+        };
+        let best = free
+            .iter()
+            .enumerate()
+            .filter(|(_, buffer)| fits(buffer))
+            .min_by_key(|(_, buffer)| buffer.capacity())
+            .map(|(index, _)| index);
+        match best {
+            Some(index) => free.remove(index),
+            None => Vec::with_capacity(capacity),
+        }
+    }
+
+    // Returns a vector for later `take`s.
+    pub fn give(&self, mut buffer: Vec<T>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+        buffer.clear();
+        let mut free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        if free.len() >= self.limit {
+            free.remove(0);
+        }
+        free.push(buffer);
+    }
+
+    // Memory held by the free vectors.
+    pub fn bytes(&self) -> usize {
+        let free = self.free.lock().unwrap_or_else(PoisonError::into_inner);
+        free.iter()
+            .map(|buffer| buffer.capacity() * std::mem::size_of::<T>())
+            .sum()
+    }
+}
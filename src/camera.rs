@@ -0,0 +1,314 @@
+use serde::{Deserialize, Serialize};
+
+use crate::fractal::FractalKind;
+use crate::viewport::Viewport;
+
+pub const DEFAULT_FPS: f64 = 30.0;
+// Seconds a new keyframe's segment takes to reach the next one.
+pub const DEFAULT_TRAVEL: f64 = 4.0;
+// Widths closer than this in ratio count as the same zoom, so the segment pans in a straight
+// line rather than about a fixed point.
+const SAME_ZOOM: f64 = 1e-9;
+
+// How a segment moves from its keyframe to the next, as progress against time.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    // Speeds up and slows down gently.
+    EaseInOut,
+    // Lingers at both ends and covers most of the way mid-segment.
+    Exponential,
+}
+
+impl Easing {
+    pub fn name(self) -> &'static str {
+        match self {
+            Easing::Linear => "Linear",
+            Easing::EaseInOut => "Ease in-out",
+            Easing::Exponential => "Exponential",
+        }
+    }
+
+    pub fn next(self) -> Easing {
+        match self {
+            Easing::Linear => Easing::EaseInOut,
+            Easing::EaseInOut => Easing::Exponential,
+            Easing::Exponential => Easing::Linear,
+        }
+    }
+
+    // Progress at `u` of the segment's time, both from 0 to 1; exact at either end.
+    pub fn apply(self, u: f64) -> f64 {
+        let u = u.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => u,
+            Easing::EaseInOut => u * u * (3.0 - 2.0 * u),
+            Easing::Exponential if u == 0.0 || u == 1.0 => u,
+            Easing::Exponential if u < 0.5 => 2f64.powf(20.0 * u - 10.0) / 2.0,
+            Easing::Exponential => 1.0 - 2f64.powf(10.0 - 20.0 * u) / 2.0,
+        }
+    }
+}
+
+// A view the path passes through: held for `hold` seconds, then left for the next keyframe over
+// `travel` seconds as `easing` has it. The last keyframe's travel goes unused.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub hold: f64,
+    pub travel: f64,
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+// A camera path through one fractal, as saved beside the bookmarks. Previews and exports both
+// take their views from `viewport_at`, so a scrubbed preview is what the export renders.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub fractal: FractalKind,
+    pub fps: f64,
+    #[serde(default)]
+    pub keyframes: Vec<Keyframe>,
+}
+
+impl Default for CameraPath {
+    fn default() -> Self {
+        CameraPath {
+            fractal: FractalKind::default(),
+            fps: DEFAULT_FPS,
+            keyframes: Vec::new(),
+        }
+    }
+}
+
+impl CameraPath {
+    pub fn from_toml(text: &str) -> Result<CameraPath, String> {
+        let path: CameraPath = toml::from_str(text).map_err(|err| err.to_string())?;
+        path.check()?;
+        Ok(path)
+    }
+
+    pub fn to_toml(&self) -> Result<String, String> {
+        toml::to_string_pretty(self).map_err(|err| err.to_string())
+    }
+
+    // What makes the path unplayable, if anything.
+    pub fn check(&self) -> Result<(), String> {
+        if !(self.fps.is_finite() && self.fps > 0.0) {
+            return Err(format!("invalid frame rate {}", self.fps));
+        }
+        for (index, keyframe) in self.keyframes.iter().enumerate() {
+            let seconds = [keyframe.hold, keyframe.travel];
+            if seconds.iter().any(|s| !s.is_finite() || *s < 0.0) {
+                return Err(format!(
+                    "keyframe {} has a negative or invalid duration",
+                    index
+                ));
+            }
+            if !(keyframe.viewport.width.is_finite() && keyframe.viewport.width > 0.0) {
+                return Err(format!("keyframe {} has an invalid width", index));
+            }
+        }
+        Ok(())
+    }
+
+    // Seconds from the first keyframe to the end of the last one's hold.
+    pub fn duration(&self) -> f64 {
+        let holds: f64 = self.keyframes.iter().map(|keyframe| keyframe.hold).sum();
+        let travels: f64 = self
+            .keyframes
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|keyframe| keyframe.travel)
+            .sum();
+        holds + travels
+    }
+
+    // Frames an export at the path's rate renders, the first at 0 s and the last at or just
+    // before the end.
+    pub fn frame_count(&self) -> usize {
+        if self.keyframes.is_empty() {
+            return 0;
+        }
+        (self.duration() * self.fps + 1e-9).floor() as usize + 1
+    }
+
+    pub fn frame_time(&self, index: usize) -> f64 {
+        index as f64 / self.fps
+    }
+
+    // Seconds into the path at which keyframe `index` is reached.
+    pub fn keyframe_time(&self, index: usize) -> f64 {
+        self.keyframes[..index.min(self.keyframes.len())]
+            .iter()
+            .map(|keyframe| keyframe.hold + keyframe.travel)
+            .sum()
+    }
+
+    // The view `seconds` into the path, clamped to its ends; None without keyframes.
+    pub fn viewport_at(&self, seconds: f64) -> Option<Viewport> {
+        let (last, segments) = self.keyframes.split_last()?;
+        let mut remaining = seconds.max(0.0);
+        for (index, keyframe) in segments.iter().enumerate() {
+            if remaining <= keyframe.hold {
+                return Some(keyframe.viewport);
+            }
+            remaining -= keyframe.hold;
+            if remaining < keyframe.travel {
+                let next = &self.keyframes[index + 1];
+                let u = keyframe.easing.apply(remaining / keyframe.travel);
+                return Some(between(&keyframe.viewport, &next.viewport, u));
+            }
+            remaining -= keyframe.travel;
+        }
+        Some(last.viewport)
+    }
+}
+
+// The view `progress` of the way from `from` to `to`. Widths change geometrically, so zooming
+// keeps a steady pace at any depth, and the center moves with the width so that a zoom from one
+// view into another homes in on the point they share rather than sliding past it.
+pub fn between(from: &Viewport, to: &Viewport, progress: f64) -> Viewport {
+    if progress <= 0.0 {
+        return *from;
+    }
+    if progress >= 1.0 {
+        return *to;
+    }
+    let ratio = to.width / from.width;
+    let width = from.width * ratio.powf(progress);
+    let along = if ratio.ln().abs() > SAME_ZOOM {
+        (from.width - width) / (from.width - to.width)
+    } else {
+        progress
+    };
+    let (re, im) = to.center_delta(from);
+    from.shifted(re * along, im * along, width)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The point `wide` and `deep` share, at the same relative place in both.
+    const FIXED: (f64, f64) = (-0.7436, 0.1318);
+
+    fn wide() -> Viewport {
+        Viewport::new(-0.75, 0.0, 3.0)
+    }
+
+    fn deep() -> Viewport {
+        let wide = wide();
+        Viewport::new(
+            FIXED.0 + (wide.center_re - FIXED.0) * 1e-3,
+            FIXED.1 + (wide.center_im - FIXED.1) * 1e-3,
+            3e-3,
+        )
+    }
+
+    fn beside() -> Viewport {
+        deep().shifted(1e-3, 0.0, deep().width)
+    }
+
+    fn keyframe(viewport: Viewport, hold: f64, travel: f64, easing: Easing) -> Keyframe {
+        Keyframe {
+            viewport,
+            hold,
+            travel,
+            easing,
+        }
+    }
+
+    fn path() -> CameraPath {
+        CameraPath {
+            fractal: FractalKind::Mandelbrot,
+            fps: 30.0,
+            keyframes: vec![
+                keyframe(wide(), 1.0, 2.0, Easing::EaseInOut),
+                keyframe(deep(), 0.5, 4.0, Easing::Exponential),
+                keyframe(beside(), 1.0, 0.0, Easing::Linear),
+            ],
+        }
+    }
+
+    #[test]
+    fn easings_run_steadily_from_0_to_1() {
+        for easing in [Easing::Linear, Easing::EaseInOut, Easing::Exponential] {
+            let samples: Vec<f64> = (0..=1000)
+                .map(|i| easing.apply(i as f64 / 1000.0))
+                .collect();
+            assert!(
+                samples[0] == 0.0
+                    && samples[1000] == 1.0
+                    && (samples[500] - 0.5).abs() <= 1e-12
+                    && samples.windows(2).all(|pair| pair[1] >= pair[0]),
+                "{:?} is not a steady curve from 0 to 1",
+                easing
+            );
+        }
+    }
+
+    // Each keyframe is held, then traveled from; times outside the path clamp to its ends.
+    #[test]
+    fn views_follow_the_timeline_of_holds_and_travels() {
+        let path = path();
+        let times = [
+            (0.0, wide()),
+            (1.0, wide()),
+            (3.0, deep()),
+            (3.5, deep()),
+            (8.5, beside()),
+            (100.0, beside()),
+            (-1.0, wide()),
+        ];
+        for (seconds, expected) in times {
+            assert_eq!(
+                path.viewport_at(seconds),
+                Some(expected),
+                "at {} s",
+                seconds
+            );
+        }
+        assert_eq!(path.duration(), 8.5);
+        assert_eq!(path.keyframe_time(1), 3.0);
+        assert_eq!(path.frame_count(), 256);
+    }
+
+    #[test]
+    fn zooms_stay_on_the_point_two_views_share() {
+        let (wide, deep) = (wide(), deep());
+        let place = |view: &Viewport| {
+            (
+                (FIXED.0 - view.center_re) / view.width,
+                (FIXED.1 - view.center_im) / view.width,
+            )
+        };
+        for step in 1..10 {
+            let progress = step as f64 / 10.0;
+            let view = between(&wide, &deep, progress);
+            let expected_width = wide.width * (deep.width / wide.width).powf(progress);
+            let (drift_re, drift_im) = (
+                place(&view).0 - place(&wide).0,
+                place(&view).1 - place(&wide).1,
+            );
+            assert!(
+                (view.width / expected_width - 1.0).abs() <= 1e-12
+                    && drift_re.abs().max(drift_im.abs()) <= 1e-9,
+                "{:.0}% of the way in, the zoom drifted by {:.2e}, {:.2e} view widths",
+                progress * 100.0,
+                drift_re,
+                drift_im
+            );
+        }
+    }
+
+    #[test]
+    fn paths_survive_saving() {
+        let path = path();
+        let read = path.to_toml().and_then(|text| CameraPath::from_toml(&text));
+        assert_eq!(read, Ok(path));
+    }
+}
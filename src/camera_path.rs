@@ -0,0 +1,133 @@
+use iced::Size;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use threadpool::ThreadPool;
+
+use mandelbrot::animation::{self, PathSequence};
+use mandelbrot::camera::CameraPath;
+use mandelbrot::coloring;
+use mandelbrot::export;
+use mandelbrot::look::Look;
+use mandelbrot::render::CancelToken;
+use mandelbrot::store;
+
+use crate::config::{self, Config};
+use crate::tui::Tui;
+
+const FRAME_SIZE: Size = Size::new(640.0, 360.0);
+
+// The camera path lives beside the bookmarks.
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("mandelbrot").join("camera_path.toml"))
+}
+
+pub fn load() -> CameraPath {
+    path().map_or_else(CameraPath::default, |path| load_from(&path))
+}
+
+pub fn load_from(path: &Path) -> CameraPath {
+    store::load(path, "camera path", CameraPath::from_toml).unwrap_or_default()
+}
+
+pub fn save(camera_path: &CameraPath) {
+    if let Some(path) = path() {
+        save_to(camera_path, &path);
+    }
+}
+
+pub fn save_to(camera_path: &CameraPath, path: &Path) {
+    let result = camera_path
+        .to_toml()
+        .and_then(|contents| store::save(path, &contents));
+    if let Err(err) = result {
+        println!("failed to save camera path {}: {}", path.display(), err);
+    }
+}
+
+// Writes every frame of a camera path as numbered PNGs plus a manifest, using the profile
+// iterations and coloring of the path's fractal; `--profile NAME` picks another profile. Takes
+// the path file to export, or the saved one.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let (args, profile) = config::profile_option(args)?;
+    let camera_path = match args.first() {
+        Some(file) => {
+            let file = Path::new(file);
+            let contents = fs::read_to_string(file)
+                .map_err(|err| format!("cannot read {}: {}", file.display(), err))?;
+            CameraPath::from_toml(&contents)?
+        }
+        None => load(),
+    };
+    let frames = camera_path.frame_count();
+    if frames == 0 {
+        return Err(String::from("the camera path has no keyframes"));
+    }
+
+    let mut config = Config::load();
+    config.fractal = camera_path.fractal;
+    if let Some(name) = &profile {
+        config.select_profile(name)?;
+    }
+    let sequence = PathSequence {
+        size: FRAME_SIZE,
+        max_iterations: config.active().settings.max_iterations,
+        coarse_prepass: config.coarse_prepass,
+        double_double: config.experimental_double_double,
+    };
+    let dir = config
+        .export_dir()
+        .join(format!("path_{}", export::timestamp()));
+    let coloring_settings = config.state().coloring.clone();
+    let look = Look::of(&coloring_settings).png_text();
+    let pool = ThreadPool::new(8);
+    let cancel = CancelToken::new();
+    let tui = Tui::new(frames, &cancel);
+    let mut started = None;
+    let manifest = animation::render_path(
+        &pool,
+        &camera_path,
+        &sequence,
+        &cancel,
+        |index, update| {
+            if started != Some(index) {
+                started = Some(index);
+                tui.start_job(&format!("frame {}", index));
+            }
+            tui.update(update);
+        },
+        |index, buffer| {
+            let file = format!("frame_{:05}.png", index);
+            let rgba = coloring::recolor(buffer, &coloring_settings, pool.max_count());
+            if let Err(err) = export::write_png_with_text(
+                &dir.join(&file),
+                buffer.width as u32,
+                buffer.height as u32,
+                &rgba,
+                &look,
+            ) {
+                println!("failed to write {}: {}", file, err);
+            }
+            if let Some(params) = buffer.params {
+                tui.finish_job(&format!("width {:e}", params.viewport.width));
+            }
+            file
+        },
+    );
+    let contents = toml::to_string_pretty(&manifest).map_err(|err| err.to_string())?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    fs::write(dir.join("manifest.toml"), contents).map_err(|err| err.to_string())?;
+    println!(
+        "{} of {} frames in {}{}",
+        manifest.frames.len(),
+        frames,
+        dir.display(),
+        if manifest.cancelled {
+            ", cancelled"
+        } else {
+            ""
+        }
+    );
+    Ok(())
+}
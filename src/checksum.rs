@@ -0,0 +1,39 @@
+use mandelbrot::fractal::AngleKind;
+use mandelbrot::render::{Arithmetic, FrameParams};
+use mandelbrot::stats::{self, CANONICAL_SIZE};
+use mandelbrot::storage::Precision;
+use mandelbrot::viewport::Viewport;
+
+use crate::config::Config;
+
+// With `re im width [iterations]`, prints the stats of that view of the current fractal, as pinned
+// for the canonical views checked by the tests.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let parse = |index: usize, name: &str| {
+        args.get(index)
+            .ok_or_else(|| format!("missing {}", name))?
+            .parse::<f64>()
+            .map_err(|err| format!("invalid {}: {}", name, err))
+    };
+    let center_re = parse(0, "real coordinate")?;
+    let center_im = parse(1, "imaginary coordinate")?;
+    let width = parse(2, "width")?;
+    let config = Config::load();
+    let max_iterations = match args.get(3) {
+        Some(iterations) => iterations
+            .parse()
+            .map_err(|err| format!("invalid iteration count: {}", err))?,
+        None => config.active().settings.max_iterations,
+    };
+    let params = FrameParams {
+        viewport: Viewport::new(center_re, center_im, width),
+        max_iterations,
+        fractal: config.fractal,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: false,
+        arithmetic: Arithmetic::F64,
+    };
+    println!("{}", stats::frame_checksum(CANONICAL_SIZE, params));
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::borrow::Cow;
+use std::fmt;
+
+use mandelbrot::export;
+
+// The system clipboard, opened on first use and kept open: on X11 the copying process serves the
+// data itself, so dropping it would lose the image.
+#[derive(Default)]
+pub struct Clipboard(Option<arboard::Clipboard>);
+
+impl fmt::Debug for Clipboard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Clipboard").field(&self.0.is_some()).finish()
+    }
+}
+
+impl Clipboard {
+    fn open(&mut self) -> Result<&mut arboard::Clipboard, arboard::Error> {
+        if self.0.is_none() {
+            self.0 = Some(arboard::Clipboard::new()?);
+        }
+        Ok(self.0.as_mut().expect("opened above"))
+    }
+
+    // Puts an RGBA frame on the clipboard. Where images are not supported, saves it as a
+    // temporary PNG and copies that file's path instead. Returns a status line either way.
+    pub fn copy_image(&mut self, width: u32, height: u32, rgba: &[u8]) -> String {
+        let image = arboard::ImageData {
+            width: width as usize,
+            height: height as usize,
+            bytes: Cow::Borrowed(rgba),
+        };
+        let err = match self.open().and_then(|clipboard| clipboard.set_image(image)) {
+            Ok(()) => return format!("copied {}x{} image to clipboard", width, height),
+            Err(err) => err,
+        };
+        println!("image clipboard unavailable: {}", err);
+
+        let name = format!("mandelbrot-{}", export::timestamp());
+        let path = export::unique_path(&std::env::temp_dir(), &name, "png");
+        if let Err(write_err) = export::write_png(&path, width, height, rgba) {
+            return format!(
+                "copy failed ({}) and saving {} failed: {}",
+                err,
+                path.display(),
+                write_err
+            );
+        }
+        let text = path.display().to_string();
+        match self.open().and_then(|clipboard| clipboard.set_text(text)) {
+            Ok(()) => format!(
+                "clipboard takes no images; saved {} and copied its path",
+                path.display()
+            ),
+            Err(err) => format!(
+                "clipboard unavailable ({}); saved {} instead",
+                err,
+                path.display()
+            ),
+        }
+    }
+
+    // Puts text on the clipboard, for copying coordinates.
+    pub fn copy_text(&mut self, text: String) -> Result<(), String> {
+        self.open()
+            .and_then(|clipboard| clipboard.set_text(text))
+            .map_err(|err| format!("copy failed: {}", err))
+    }
+
+    // The clipboard's text, for pasting coordinates.
+    pub fn text(&mut self) -> Result<String, String> {
+        self.open()
+            .and_then(|clipboard| clipboard.get_text())
+            .map_err(|err| format!("nothing to paste: {}", err))
+    }
+}
@@ -0,0 +1,871 @@
+use iced::Color;
+
+use std::thread;
+
+use crate::adjust;
+use crate::buffers;
+use crate::display::DisplayProfile;
+use crate::palette::Palette;
+use crate::postprocess::{self, Pipeline, StageCache};
+use crate::render::{self, IterationBuffer, PixelClass, UNRESOLVED};
+use crate::settings::{ColoringMode, ColoringSettings, SolidColor};
+use crate::storage::Channel;
+use crate::viewport::PixelTransform;
+
+// Below this many pixels spawning workers costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 256 * 256;
+// Escape iterations spanned by one pass through the palette.
+pub const PALETTE_PERIOD: f32 = 64.0;
+// Pixels whose worker panicked, in a magenta no palette is likely to pass for.
+const FAILED_COLOR: Color = Color::from_rgb(1.0, 0.0, 1.0);
+// Number of external rays drawn as field lines.
+const FIELD_LINE_RAYS: f32 = 64.0;
+// Escape iterations at which field lines have faded to half strength; near the boundary the
+// angle estimate is mostly noise.
+const FIELD_LINE_FADE: f32 = 48.0;
+// Pixels of rise per unit of the log escape-time height field, setting how steep its slopes are
+// for ambient occlusion.
+const OCCLUSION_DEPTH: f32 = 16.0;
+const DIAGONAL: f32 = std::f32::consts::FRAC_1_SQRT_2;
+// Directions occlusion samples the neighborhood in, at the radius and half of it.
+const OCCLUSION_DIRECTIONS: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (DIAGONAL, DIAGONAL),
+    (0.0, 1.0),
+    (-DIAGONAL, DIAGONAL),
+    (-1.0, 0.0),
+    (-DIAGONAL, -DIAGONAL),
+    (0.0, -1.0),
+    (DIAGONAL, -DIAGONAL),
+];
+
+struct Colorizer {
+    palette: Palette,
+    mode: ColoringMode,
+    field_blend: f32,
+    inverse_gamma: f32,
+    density: f32,
+    offset: f32,
+    sectors: f32,
+    hue_rotation: f32,
+    occlusion: Option<Occlusion>,
+    interior: Color,
+    unresolved: Color,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Occlusion {
+    radius: f32,
+    strength: f32,
+}
+
+// The escape-time height field occlusion is estimated over: log escape time, NaN where the point
+// did not escape.
+struct HeightField {
+    heights: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+pub fn recolor(buffer: &IterationBuffer, settings: &ColoringSettings, threads: usize) -> Vec<u8> {
+    recolor_for_display(buffer, settings, threads, None)
+}
+
+// `recolor`, converting each worker's rows for `display` as soon as they are colored. Without a
+// profile the frame stays sRGB and nothing extra is done. Adjustment regions go last.
+pub fn recolor_for_display(
+    buffer: &IterationBuffer,
+    settings: &ColoringSettings,
+    threads: usize,
+    display: Option<&DisplayProfile>,
+) -> Vec<u8> {
+    let Some(params) = buffer.params.filter(|_| !settings.regions.is_empty()) else {
+        return colored(buffer, settings, threads, display, &postprocess::CACHE);
+    };
+    let mut bytes = colored(buffer, settings, threads, None, &postprocess::CACHE);
+    // Regions with a palette offset grade the frame colored with it, stages and all; those
+    // frames are not kept, so their stages run afresh.
+    let shifted: Vec<Option<Vec<u8>>> = settings
+        .regions
+        .iter()
+        .map(|region| {
+            (region.palette_offset != 0.0).then(|| {
+                let shifted = ColoringSettings {
+                    palette_offset: settings.palette_offset + region.palette_offset,
+                    regions: Vec::new(),
+                    ..settings.clone()
+                };
+                colored(buffer, &shifted, threads, None, &StageCache::new())
+            })
+        })
+        .collect();
+    adjust::apply(
+        &settings.regions,
+        &mut bytes,
+        buffer.width,
+        buffer.height,
+        &params.viewport,
+        &shifted,
+    );
+    for frame in shifted.into_iter().flatten() {
+        buffers::RGBA.give(frame);
+    }
+    if let Some(display) = display {
+        display.apply(&mut bytes);
+    }
+    bytes
+}
+
+// The frame colored and post-processed, without adjustment regions, taking stage outputs from
+// `cache` where it has them.
+fn colored(
+    buffer: &IterationBuffer,
+    settings: &ColoringSettings,
+    threads: usize,
+    display: Option<&DisplayProfile>,
+    cache: &StageCache,
+) -> Vec<u8> {
+    // The angle channel holds one kind of angle; a mode that wants the other falls back too.
+    let angle = settings.mode.angle();
+    let colorizer = Colorizer {
+        palette: settings.palette(),
+        mode: if buffer.angles.is_empty() || buffer.params.is_some_and(|p| p.angle != angle) {
+            ColoringMode::EscapeTime
+        } else {
+            settings.mode
+        },
+        field_blend: settings.field_blend.clamp(0.0, 1.0),
+        inverse_gamma: 1.0 / settings.gamma.max(0.01),
+        density: settings.density.max(0.01),
+        offset: settings.palette_offset,
+        sectors: settings.sectors.max(1) as f32,
+        hue_rotation: settings.hue_rotation,
+        occlusion: (settings.occlusion && settings.occlusion_strength > 0.0).then(|| Occlusion {
+            radius: settings.occlusion_radius.max(1) as f32,
+            strength: settings.occlusion_strength.clamp(0.0, 1.0),
+        }),
+        interior: solid(settings.interior_color),
+        unresolved: solid(settings.unresolved_color),
+    };
+    let field = colorizer
+        .occlusion
+        .map(|_| HeightField::of(buffer, threads));
+    let field = field.as_ref();
+    // Post-processing works on sRGB, so with stages to run the display conversion waits for them.
+    let post = postprocess::compile(&settings.post).unwrap_or_else(|err| {
+        println!("skipping post-processing: {}", err);
+        Pipeline::default()
+    });
+    let rows_display = if post.is_empty() { display } else { None };
+    let mut bytes = buffers::RGBA.take(buffer.width * buffer.height * 4);
+    bytes.resize(buffer.width * buffer.height * 4, 0);
+    if threads <= 1 || buffer.values.len() < PARALLEL_THRESHOLD {
+        colorizer.recolor_rows(buffer, field, 0, &mut bytes);
+        if let Some(display) = rows_display {
+            display.apply(&mut bytes);
+        }
+    } else {
+        let rows_per_chunk = buffer.height.div_ceil(threads);
+        let pixels_per_chunk = rows_per_chunk * buffer.width;
+        let colorizer = &colorizer;
+        // Scoped threads rather than the render pool: its jobs must own what they use, where these
+        // borrow the buffer and their rows of the frame, and a recolor during a render would
+        // otherwise wait behind the tiles queued for it.
+        thread::scope(|scope| {
+            for (index, out) in bytes.chunks_mut(pixels_per_chunk * 4).enumerate() {
+                let start = index * pixels_per_chunk;
+                scope.spawn(move || {
+                    colorizer.recolor_rows(buffer, field, start, out);
+                    if let Some(display) = rows_display {
+                        display.apply(out);
+                    }
+                });
+            }
+        });
+    }
+    if !post.is_empty() {
+        post.run(&mut bytes, buffer, threads, cache);
+        if let Some(display) = display {
+            display.apply(&mut bytes);
+        }
+    }
+    bytes
+}
+
+// Packs a color into the bytes of one RGBA pixel as iced's `Handle::from_rgba` and the PNG
+// encoder take them: each channel rounded to the nearest of 256 levels, alpha straight rather
+// than premultiplied. Frames are opaque, where the two conventions agree.
+pub fn pack(color: Color) -> [u8; 4] {
+    let level = |channel: f32| (channel.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [level(color.r), level(color.g), level(color.b), level(color.a)]
+}
+
+// Checks that `rgba` holds a `width` by `height` frame: rows top to bottom, each left to right,
+// four bytes per pixel. A mismatch would otherwise show as a sheared image or a renderer panic.
+pub fn assert_frame(rgba: &[u8], width: usize, height: usize) {
+    assert_eq!(
+        rgba.len(),
+        width * height * 4,
+        "RGBA frame of {}x{} pixels",
+        width,
+        height
+    );
+}
+
+pub fn downsample(bytes: &[u8], width: usize, height: usize, factor: usize) -> Vec<u8> {
+    let out_width = width / factor;
+    let out_height = height / factor;
+    let samples = (factor * factor) as u32;
+    let mut out = buffers::RGBA.take(out_width * out_height * 4);
+    for y in 0..out_height {
+        for x in 0..out_width {
+            let mut sum = [0u32; 4];
+            for sy in 0..factor {
+                let row = (y * factor + sy) * width;
+                for sx in 0..factor {
+                    let offset = (row + x * factor + sx) * 4;
+                    for (channel, total) in sum.iter_mut().enumerate() {
+                        *total += bytes[offset + channel] as u32;
+                    }
+                }
+            }
+            out.extend(sum.iter().map(|total| ((total + samples / 2) / samples) as u8));
+        }
+    }
+    out
+}
+
+// What a block of source pixels amounts to at a smaller size: the shares of its area proven
+// inside the set and left unresolved, and the typical escape value of the rest (`UNRESOLVED` when
+// there is none).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AreaSample {
+    pub interior: f32,
+    pub unresolved: f32,
+    pub value: f32,
+}
+
+// Averages `buffer` down to `width` by `height` by area, each source pixel counting for the part
+// of it a target pixel covers, so any ratio works, including a supersampled buffer. Escape
+// values are averaged in the log domain, where a few slow pixels beside the set do not outweigh
+// the rest of the block.
+pub fn area_samples(buffer: &IterationBuffer, width: usize, height: usize) -> Vec<AreaSample> {
+    let mut samples = Vec::with_capacity(width * height);
+    if width == 0 || height == 0 || buffer.width == 0 || buffer.height == 0 {
+        return samples;
+    }
+    let scale_x = buffer.width as f32 / width as f32;
+    let scale_y = buffer.height as f32 / height as f32;
+    // The source pixels a target span covers, each with how much of it is covered.
+    let spans = |index: usize, scale: f32, limit: usize| {
+        let start = index as f32 * scale;
+        let end = (start + scale).min(limit as f32);
+        (start as usize..(end.ceil() as usize).min(limit)).map(move |source| {
+            let covered = (end.min(source as f32 + 1.0) - start.max(source as f32)).max(0.0);
+            (source, covered)
+        })
+    };
+    for y in 0..height {
+        for x in 0..width {
+            let (mut area, mut inside, mut unresolved) = (0.0, 0.0, 0.0);
+            let (mut escaped, mut log_sum) = (0.0, 0.0);
+            for (sy, weight_y) in spans(y, scale_y, buffer.height) {
+                for (sx, weight_x) in spans(x, scale_x, buffer.width) {
+                    let weight = weight_x * weight_y;
+                    let value = buffer.values.get(sy * buffer.width + sx);
+                    area += weight;
+                    match PixelClass::of(value) {
+                        PixelClass::Escaped(value) => {
+                            escaped += weight;
+                            log_sum += weight * value.ln_1p();
+                        }
+                        PixelClass::Interior(_) => inside += weight,
+                        // Nothing is known of failed pixels either.
+                        PixelClass::Unresolved | PixelClass::Failed => unresolved += weight,
+                    }
+                }
+            }
+            let share = |part: f32| if area > 0.0 { part / area } else { 0.0 };
+            samples.push(AreaSample {
+                interior: share(inside),
+                unresolved: share(unresolved),
+                value: if escaped > 0.0 {
+                    (log_sum / escaped).exp_m1()
+                } else {
+                    UNRESOLVED
+                },
+            });
+        }
+    }
+    samples
+}
+
+// Colors `buffer` at `width` by `height` from its area samples rather than from its colors: the
+// palette is applied to each block's averaged escape value, then mixed with the interior and
+// unresolved colors by the shares of the block in those classes. Filaments too thin for the target size show as a darker shade instead of
+// vanishing between samples, and cycling palettes do not average to mud. Field lines are left
+// out, as they alias at any size this is used for.
+pub fn downscale(
+    buffer: &IterationBuffer,
+    settings: &ColoringSettings,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let colorizer = Colorizer {
+        palette: settings.palette(),
+        mode: ColoringMode::EscapeTime,
+        field_blend: 0.0,
+        inverse_gamma: 1.0 / settings.gamma.max(0.01),
+        density: settings.density.max(0.01),
+        offset: settings.palette_offset,
+        sectors: 1.0,
+        hue_rotation: 0.0,
+        occlusion: None,
+        interior: solid(settings.interior_color),
+        unresolved: solid(settings.unresolved_color),
+    };
+    let mut bytes = buffers::RGBA.take(width * height * 4);
+    for sample in area_samples(buffer, width, height) {
+        let color = colorizer.color_for(sample.value, None, 1.0);
+        let outside = 1.0 - sample.interior - sample.unresolved;
+        let mix = |escaped: f32, interior: f32, unresolved: f32| {
+            escaped * outside + interior * sample.interior + unresolved * sample.unresolved
+        };
+        let (interior, unresolved) = (colorizer.interior, colorizer.unresolved);
+        bytes.extend(pack(Color::from_rgb(
+            mix(color.r, interior.r, unresolved.r),
+            mix(color.g, interior.g, unresolved.g),
+            mix(color.b, interior.b, unresolved.b),
+        )));
+    }
+    bytes
+}
+
+fn solid(color: SolidColor) -> Color {
+    let [r, g, b] = color.rgb();
+    Color::from_rgb8(r, g, b)
+}
+
+// The fully saturated color of hue `turns` round the color wheel, red at 0, at `value`.
+fn hue(turns: f32, value: f32) -> Color {
+    let channel = |offset: f32| {
+        let k = (offset + turns * 6.0).rem_euclid(6.0);
+        value * (1.0 - (k.min(4.0 - k)).clamp(0.0, 1.0))
+    };
+    Color::from_rgb(channel(5.0), channel(3.0), channel(1.0))
+}
+
+// The sRGB transfer function and its inverse, for channels in 0..=1.
+fn to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn from_linear(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+// Gray squares shown where a warped frame has no source pixels, and their size in pixels.
+const CHECKER: [[u8; 4]; 2] = [[96, 96, 96, 255], [128, 128, 128, 255]];
+const CHECKER_SIZE: usize = 8;
+
+// Resamples the `width` by `height` RGBA frame `rgba` into an `out_width` by `out_height` one,
+// each output pixel taking the source position `transform` gives it, interpolated bilinearly.
+// Positions more than half a pixel outside the source show a checkerboard.
+pub fn warp(
+    rgba: &[u8],
+    (width, height): (usize, usize),
+    transform: &PixelTransform,
+    (out_width, out_height): (usize, usize),
+    threads: usize,
+) -> Vec<u8> {
+    assert_frame(rgba, width, height);
+    let mut out = buffers::RGBA.take(out_width * out_height * 4);
+    out.resize(out_width * out_height * 4, 0);
+    let row_bytes = out_width * 4;
+    if row_bytes == 0 {
+        return out;
+    }
+    let pixel = |x: usize, y: usize| {
+        let offset = (y * width + x) * 4;
+        &rgba[offset..offset + 4]
+    };
+    let warp_rows = |start: usize, out: &mut [u8]| {
+        for (index, target) in out.chunks_exact_mut(4).enumerate() {
+            let (x, y) = (index % out_width, start + index / out_width);
+            let (sx, sy) = transform.apply(x as f64, y as f64);
+            let inside = (-0.5..width as f64 - 0.5).contains(&sx)
+                && (-0.5..height as f64 - 0.5).contains(&sy);
+            if !inside {
+                target.copy_from_slice(&CHECKER[(x / CHECKER_SIZE + y / CHECKER_SIZE) % 2]);
+                continue;
+            }
+            let (sx, sy) = (
+                sx.clamp(0.0, (width - 1) as f64),
+                sy.clamp(0.0, (height - 1) as f64),
+            );
+            let (left, top) = (sx as usize, sy as usize);
+            let (right, bottom) = ((left + 1).min(width - 1), (top + 1).min(height - 1));
+            let (fx, fy) = (sx - left as f64, sy - top as f64);
+            for (channel, byte) in target.iter_mut().enumerate() {
+                let mix = |a: u8, b: u8, f: f64| a as f64 + (b as f64 - a as f64) * f;
+                let upper = mix(pixel(left, top)[channel], pixel(right, top)[channel], fx);
+                let lower = mix(
+                    pixel(left, bottom)[channel],
+                    pixel(right, bottom)[channel],
+                    fx,
+                );
+                *byte = (upper + (lower - upper) * fy).round() as u8;
+            }
+        }
+    };
+    if threads <= 1 || out_width * out_height < PARALLEL_THRESHOLD {
+        warp_rows(0, &mut out);
+    } else {
+        let rows_per_chunk = out_height.div_ceil(threads);
+        let warp_rows = &warp_rows;
+        thread::scope(|scope| {
+            for (index, chunk) in out.chunks_mut(rows_per_chunk * row_bytes).enumerate() {
+                scope.spawn(move || warp_rows(index * rows_per_chunk, chunk));
+            }
+        });
+    }
+    out
+}
+
+// Mirrors an RGBA frame top to bottom in place.
+pub fn flip_rows(rgba: &mut [u8], width: usize, height: usize) {
+    let row = width * 4;
+    for y in 0..height / 2 {
+        let (top, bottom) = rgba.split_at_mut((height - 1 - y) * row);
+        top[y * row..(y + 1) * row].swap_with_slice(&mut bottom[..row]);
+    }
+}
+
+impl HeightField {
+    fn of(buffer: &IterationBuffer, threads: usize) -> HeightField {
+        let mut heights = vec![0.0; buffer.width * buffer.height];
+        let fill = |values: &Channel, start: usize, out: &mut [f32]| {
+            for (offset, height) in out.iter_mut().enumerate() {
+                let value = values.get(start + offset);
+                *height = if !render::escaped(value) {
+                    f32::NAN
+                } else {
+                    value.max(0.0).ln_1p()
+                };
+            }
+        };
+        if threads <= 1 || heights.len() < PARALLEL_THRESHOLD {
+            fill(&buffer.values, 0, &mut heights);
+        } else {
+            let chunk = buffer.height.div_ceil(threads) * buffer.width;
+            thread::scope(|scope| {
+                for (index, out) in heights.chunks_mut(chunk).enumerate() {
+                    scope.spawn(move || fill(&buffer.values, index * chunk, out));
+                }
+            });
+        }
+        HeightField {
+            heights,
+            width: buffer.width,
+            height: buffer.height,
+        }
+    }
+
+    // How far the pixel at `index` sits in a valley, from 0 on open ground to 1 walled in: the
+    // mean sine of the horizon's elevation over the samples round it. Samples past the frame's
+    // edge read the edge pixel, so the border neither darkens nor brightens by itself, and the
+    // set's interior counts as open, leaving no dark rim along it.
+    fn occlusion(&self, index: usize, radius: f32) -> f32 {
+        let center = self.heights[index];
+        if center.is_nan() {
+            return 0.0;
+        }
+        let (x, y) = ((index % self.width) as f32, (index / self.width) as f32);
+        let (max_x, max_y) = ((self.width - 1) as f32, (self.height - 1) as f32);
+        let mut total = 0.0;
+        let mut samples = 0;
+        for distance in [(radius / 2.0).max(1.0), radius] {
+            for (dx, dy) in OCCLUSION_DIRECTIONS {
+                let sx = (x + dx * distance).round().clamp(0.0, max_x) as usize;
+                let sy = (y + dy * distance).round().clamp(0.0, max_y) as usize;
+                let rise = (self.heights[sy * self.width + sx] - center) * OCCLUSION_DEPTH;
+                samples += 1;
+                if rise > 0.0 {
+                    total += rise / rise.hypot(distance);
+                }
+            }
+        }
+        total / samples as f32
+    }
+}
+
+impl Colorizer {
+    // Colors the pixels from `start` onwards into `out`, reading whichever layout the channels use.
+    fn recolor_rows(
+        &self,
+        buffer: &IterationBuffer,
+        field: Option<&HeightField>,
+        start: usize,
+        out: &mut [u8],
+    ) {
+        let has_angles = !buffer.angles.is_empty();
+        for (offset, pixel) in out.chunks_exact_mut(4).enumerate() {
+            let index = start + offset;
+            let angle = has_angles.then(|| buffer.angles.get(index));
+            let shade = match (self.occlusion, field) {
+                (Some(occlusion), Some(field)) => {
+                    1.0 - occlusion.strength * field.occlusion(index, occlusion.radius)
+                }
+                _ => 1.0,
+            };
+            let color = self.color_for(buffer.values.get(index), angle, shade);
+            pixel.copy_from_slice(&pack(color));
+        }
+    }
+
+    // The color of a pixel, darkened by `shade` from 1 for none to 0 for black.
+    fn color_for(&self, value: f32, angle: Option<f32>, shade: f32) -> Color {
+        match PixelClass::of(value) {
+            PixelClass::Escaped(_) => {}
+            PixelClass::Interior(_) => return self.interior,
+            PixelClass::Unresolved => return self.unresolved,
+            PixelClass::Failed => return FAILED_COLOR,
+        }
+        let mut color = self
+            .palette
+            .color_at(value * self.density / PALETTE_PERIOD + self.offset);
+        let factor = match (self.mode, angle) {
+            (ColoringMode::FieldLines, Some(angle)) => {
+                let line = 0.5 + 0.5 * (angle * FIELD_LINE_RAYS * std::f32::consts::TAU).cos();
+                let strength = self.field_blend * FIELD_LINE_FADE / (FIELD_LINE_FADE + value);
+                1.0 - strength + strength * line
+            }
+            // The sector's hue at the palette color's brightness, mixed in by the blend in linear
+            // light so that mixing does not darken.
+            (ColoringMode::EscapeDirection, Some(angle)) => {
+                let turns = (angle + self.hue_rotation).rem_euclid(1.0);
+                let sector = (turns * self.sectors).floor().min(self.sectors - 1.0);
+                let brightness = color.r.max(color.g).max(color.b);
+                let hue = hue(sector / self.sectors, brightness);
+                let mix = |a: f32, b: f32| {
+                    let (a, b) = (to_linear(a), to_linear(b));
+                    from_linear(a + (b - a) * self.field_blend)
+                };
+                color = Color::from_rgb(
+                    mix(color.r, hue.r),
+                    mix(color.g, hue.g),
+                    mix(color.b, hue.b),
+                );
+                1.0
+            }
+            _ => 1.0,
+        } * shade;
+        let channel = |c: f32| {
+            if self.inverse_gamma == 1.0 {
+                c * factor
+            } else {
+                (c * factor).powf(self.inverse_gamma)
+            }
+        };
+        Color::from_rgb(channel(color.r), channel(color.g), channel(color.b))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Size;
+
+    use super::*;
+    use crate::fractal::{AngleKind, FractalKind};
+    use crate::render::{Arithmetic, FrameParams};
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+    use crate::storage::Precision;
+    use crate::viewport::{PixelTransform, Viewport};
+
+    // The seahorse valley at thumbnail size, whose spirals are threaded with filaments far thinner
+    // than a pixel. It is also rendered this many times larger each way as the ground truth, and
+    // at `FILAMENT_SUPERSAMPLE` times for the area-averaged path, as bookmark thumbnails are.
+    const FILAMENT_SIZE: Size = Size::new(128.0, 80.0);
+    const FILAMENT_VIEW: (f64, f64, f64) = (-0.7453, 0.1127, 0.0065);
+    const FILAMENT_TRUTH_SCALE: f32 = 8.0;
+    const FILAMENT_SUPERSAMPLE: f32 = 3.0;
+    // Thumbnail pixels with at least this share of their area in the set, but less than half,
+    // hold a filament: something thin enough that a single sample per pixel mostly misses it.
+    const FILAMENT_MIN_COVERAGE: f32 = 0.05;
+
+    // A pixel shows its filament if it is darkened by at least half the share of it truly inside
+    // the set.
+    #[test]
+    fn downscaled_thumbnails_show_more_filaments_than_sampled_ones() {
+        let render = |scale: f32| {
+            let size = Size::new(FILAMENT_SIZE.width * scale, FILAMENT_SIZE.height * scale);
+            let params = FrameParams {
+                viewport: Viewport::new(FILAMENT_VIEW.0, FILAMENT_VIEW.1, FILAMENT_VIEW.2),
+                max_iterations: 500,
+                fractal: FractalKind::Mandelbrot,
+                angle: AngleKind::Off,
+                precision: Precision::Full,
+                coarse_prepass: false,
+                arithmetic: Arithmetic::F64,
+            };
+            stats::render_frame(size, params)
+        };
+        let (width, height) = (FILAMENT_SIZE.width as usize, FILAMENT_SIZE.height as usize);
+        let coverage = |buffer: &IterationBuffer| {
+            area_samples(buffer, width, height)
+                .iter()
+                .map(|sample| sample.interior + sample.unresolved)
+                .collect::<Vec<_>>()
+        };
+        let truth = coverage(&render(FILAMENT_TRUTH_SCALE));
+        let downscaled = coverage(&render(FILAMENT_SUPERSAMPLE));
+        let sampled = render(1.0);
+        let filaments = (0..truth.len())
+            .filter(|&index| (FILAMENT_MIN_COVERAGE..0.5).contains(&truth[index]))
+            .collect::<Vec<_>>();
+        let share = |shows: &dyn Fn(usize) -> bool| {
+            let shown = filaments.iter().filter(|&&index| shows(index)).count();
+            shown as f32 / filaments.len().max(1) as f32
+        };
+        let downscaled = share(&|index| downscaled[index] >= truth[index] / 2.0);
+        let sampled = share(&|index| !render::escaped(sampled.values.get(index)));
+        assert!(
+            downscaled > sampled,
+            "downscaled thumbnails show {:.0}% of {} filament pixels, no more than sampling's \
+             {:.0}%",
+            downscaled * 100.0,
+            filaments.len(),
+            sampled * 100.0
+        );
+    }
+
+    // FNV-1a of the escape directions view colored with `escape_direction_look`. Like the
+    // canonical views' stats it is blessed by updating it when a change to the coloring is
+    // intended.
+    const ESCAPE_DIRECTION_IMAGE_HASH: u64 = 0x8cb6882e6bd75a31;
+    // The same for the seahorse valley view colored with `occlusion_look`.
+    const OCCLUSION_IMAGE_HASH: u64 = 0x0a99f738dbce5fad;
+
+    fn escape_direction_look() -> ColoringSettings {
+        ColoringSettings {
+            mode: ColoringMode::EscapeDirection,
+            field_blend: 0.75,
+            sectors: 6,
+            hue_rotation: 0.25,
+            ..ColoringSettings::default()
+        }
+    }
+
+    fn occlusion_look() -> ColoringSettings {
+        ColoringSettings {
+            occlusion: true,
+            occlusion_radius: 6,
+            occlusion_strength: 0.8,
+            ..ColoringSettings::default()
+        }
+    }
+
+    #[test]
+    fn escape_directions_color_the_pinned_image() {
+        let look = escape_direction_look();
+        let directions = stats::render_frame(CANONICAL_SIZE, CANONICAL_VIEWS[5].params());
+        let image = recolor(&directions, &look, 1);
+        let hash = stats::image_hash(&image);
+        assert_eq!(
+            hash, ESCAPE_DIRECTION_IMAGE_HASH,
+            "the image hashes to {:016x} rather than {:016x}",
+            hash, ESCAPE_DIRECTION_IMAGE_HASH
+        );
+        let escape_time = ColoringSettings {
+            mode: ColoringMode::EscapeTime,
+            ..look
+        };
+        assert!(
+            image != recolor(&directions, &escape_time, 1),
+            "the image is plain escape time"
+        );
+    }
+
+    #[test]
+    fn external_angles_are_not_escape_directions() {
+        let look = escape_direction_look();
+        let escape_time = ColoringSettings {
+            mode: ColoringMode::EscapeTime,
+            ..look.clone()
+        };
+        let field_lines = stats::render_frame(CANONICAL_SIZE, CANONICAL_VIEWS[3].params());
+        assert!(
+            recolor(&field_lines, &look, 1) == recolor(&field_lines, &escape_time, 1),
+            "external angles are colored as escape directions"
+        );
+    }
+
+    #[test]
+    fn occlusion_shades_the_pinned_image_darker() {
+        let buffer = stats::render_frame(CANONICAL_SIZE, CANONICAL_VIEWS[2].params());
+        let look = occlusion_look();
+        let image = recolor(&buffer, &look, 1);
+        let hash = stats::image_hash(&image);
+        assert_eq!(
+            hash, OCCLUSION_IMAGE_HASH,
+            "the image hashes to {:016x} rather than {:016x}",
+            hash, OCCLUSION_IMAGE_HASH
+        );
+        let plain = ColoringSettings {
+            occlusion: false,
+            ..look.clone()
+        };
+        let unshaded = recolor(&buffer, &plain, 1);
+        assert!(image != unshaded, "the image is not shaded");
+        assert!(
+            image
+                .iter()
+                .zip(&unshaded)
+                .all(|(shaded, plain)| shaded <= plain),
+            "occlusion brightens pixels"
+        );
+        let weightless = ColoringSettings {
+            occlusion_strength: 0.0,
+            ..look
+        };
+        assert!(
+            recolor(&buffer, &weightless, 1) == unshaded,
+            "zero strength shades the image"
+        );
+    }
+
+    // A field of escape counts large enough for workers.
+    fn field(value: impl Fn(usize, usize) -> f32) -> IterationBuffer {
+        let (width, height) = (300, 280);
+        IterationBuffer {
+            width,
+            height,
+            values: Channel::Full(
+                (0..width * height)
+                    .map(|index| value(index % width, index / width))
+                    .collect(),
+            ),
+            angles: Channel::default(),
+            params: None,
+            costs: None,
+            failure: None,
+        }
+    }
+
+    // A bowl, rising away from the middle: its bottom is darkened, the same by any workers.
+    #[test]
+    fn occlusion_darkens_valleys() {
+        let bowl = field(|x, y| 10.0 + (x as f32 - 150.0).hypot(y as f32 - 140.0));
+        let look = occlusion_look();
+        let plain = ColoringSettings {
+            occlusion: false,
+            ..look.clone()
+        };
+        let shaded = recolor(&bowl, &look, 1);
+        let unshaded = recolor(&bowl, &plain, 1);
+        let bottom = (140 * bowl.width + 150) * 4;
+        assert!(
+            shaded[bottom] < unshaded[bottom],
+            "the bottom of a valley is not darkened"
+        );
+        assert!(
+            recolor(&bowl, &look, 4) == shaded,
+            "threaded workers shade differently"
+        );
+    }
+
+    #[test]
+    fn occlusion_leaves_flat_fields_alone() {
+        let flat = field(|_, _| 40.0);
+        let look = occlusion_look();
+        let plain = ColoringSettings {
+            occlusion: false,
+            ..look.clone()
+        };
+        assert!(
+            recolor(&flat, &look, 4) == recolor(&flat, &plain, 4),
+            "a flat field or its border is darkened"
+        );
+    }
+
+    const WARP_SIZE: (usize, usize) = (64, 48);
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    // Black, with white marks at a few known pixels.
+    fn marked_frame() -> Vec<u8> {
+        let (width, height) = WARP_SIZE;
+        let marks = [(40, 30), (10, 5), (32, 24)];
+        let mut frame = vec![0u8; width * height * 4];
+        for y in 0..height {
+            for x in 0..width {
+                let offset = (y * width + x) * 4;
+                frame[offset + 3] = 255;
+                if marks.contains(&(x, y)) {
+                    frame[offset..offset + 4].copy_from_slice(&WHITE);
+                }
+            }
+        }
+        frame
+    }
+
+    fn at(rgba: &[u8], (x, y): (usize, usize)) -> [u8; 4] {
+        let offset = (y * WARP_SIZE.0 + x) * 4;
+        [
+            rgba[offset],
+            rgba[offset + 1],
+            rgba[offset + 2],
+            rgba[offset + 3],
+        ]
+    }
+
+    fn warp_to(width: f64, antialias: usize) -> Vec<u8> {
+        let size = Size::new(WARP_SIZE.0 as f32, WARP_SIZE.1 as f32);
+        let from = Viewport::new(-0.5, 0.25, 4.0);
+        let transform = PixelTransform::between(&from, size, &from.shifted(0.0, 0.0, width), size);
+        warp(&marked_frame(), WARP_SIZE, &transform, WARP_SIZE, antialias)
+    }
+
+    // Zoomed in by 2 about the center, the marks move twice as far from it, and (10, 5) falls
+    // out of the view.
+    #[test]
+    fn zooming_in_spreads_the_last_frame() {
+        let zoomed = warp_to(2.0, 4);
+        for (mark, expected) in [((40, 30), (48, 36)), ((32, 24), (32, 24))] {
+            assert_eq!(
+                at(&zoomed, expected),
+                WHITE,
+                "zooming in by 2 moves the mark from {:?} off {:?}",
+                mark,
+                expected
+            );
+        }
+        // Halfway between marks is half bright.
+        assert!(
+            at(&zoomed, (49, 36))[0] == 128 && at(&zoomed, (50, 36))[0] == 0,
+            "zooming in by 2 interpolates {:?} and {:?} beside a mark",
+            at(&zoomed, (49, 36)),
+            at(&zoomed, (50, 36))
+        );
+    }
+
+    // Zoomed out by 2, the marks come halfway in and the border has no source.
+    #[test]
+    fn zooming_out_leaves_a_gray_border() {
+        let zoomed_out = warp_to(8.0, 1);
+        let gray = |color: [u8; 4]| color[0] == color[1] && color[1] == color[2] && color[0] >= 96;
+        assert!(
+            at(&zoomed_out, (36, 27))[0] != 0 && gray(at(&zoomed_out, (1, 1))),
+            "zooming out by 2 shows {:?} for the mark and {:?} past the old frame",
+            at(&zoomed_out, (36, 27)),
+            at(&zoomed_out, (1, 1))
+        );
+    }
+}
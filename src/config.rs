@@ -0,0 +1,330 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use mandelbrot::animation::AutoStop;
+use mandelbrot::annotation::Annotation;
+use mandelbrot::export::DEFAULT_TEMPLATE;
+use mandelbrot::fractal::{FractalKind, Hybrid};
+use mandelbrot::guides::{GuideSettings, SafeAreaSettings};
+use mandelbrot::numbers::NumberFormat;
+use mandelbrot::power::EnergySaver;
+use mandelbrot::settings::{ColoringSettings, QualityProfile};
+use mandelbrot::storage::PrecisionSetting;
+use mandelbrot::store;
+use mandelbrot::tune::Tuning;
+use mandelbrot::viewport::Viewport;
+
+// Everything that is swapped in and out when switching fractal types.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FractalState {
+    pub viewport: Option<Viewport>,
+    pub active_profile: String,
+    pub profiles: Vec<QualityProfile>,
+    pub coloring: ColoringSettings,
+    // Labels and arrows pinned to features of this fractal.
+    pub annotations: Vec<Annotation>,
+}
+
+impl Default for FractalState {
+    fn default() -> Self {
+        FractalState {
+            viewport: None,
+            active_profile: String::from("Balanced"),
+            profiles: QualityProfile::builtin(),
+            coloring: ColoringSettings::default(),
+            annotations: Vec::new(),
+        }
+    }
+}
+
+impl FractalState {
+    pub fn profile_index(&self, name: &str) -> Option<usize> {
+        self.profiles
+            .iter()
+            .position(|profile| profile.name == name)
+    }
+
+    pub fn active_index(&self) -> usize {
+        self.profile_index(&self.active_profile).unwrap_or(0)
+    }
+
+    pub fn active(&self) -> &QualityProfile {
+        &self.profiles[self.active_index()]
+    }
+
+    pub fn active_mut(&mut self) -> &mut QualityProfile {
+        let index = self.active_index();
+        &mut self.profiles[index]
+    }
+
+    pub fn cycle_profile(&mut self) {
+        let next = (self.active_index() + 1) % self.profiles.len();
+        self.active_profile = self.profiles[next].name.clone();
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub fractal: FractalKind,
+    // The steps of the hybrid fractal, kept while another fractal is shown.
+    pub hybrid: Hybrid,
+    pub buffer_precision: PrecisionSetting,
+    // Size of the cached per-pixel buffers above which Auto precision switches to 16-bit storage.
+    pub cache_budget_mb: u32,
+    // Memory kept for tiles of recent views, so that going back to one copies its tiles instead
+    // of computing them; 0 turns the tile cache off.
+    pub tile_cache_mb: u32,
+    // Disk kept for the most valuable of those tiles between sessions, so that views visited
+    // before a restart come back without being computed; 0, the default, keeps none.
+    pub tile_disk_mb: u32,
+    // Skip per-pixel iteration in blocks a coarse probe finds far outside the set.
+    pub coarse_prepass: bool,
+    // Render views too narrow for f64 in double-double arithmetic. Experimental and several times
+    // slower, so off unless asked for.
+    pub experimental_double_double: bool,
+    // Keep improving the frame while the app sits idle, up to a fixed quality cap.
+    pub idle_refinement: bool,
+    // How long without input counts as idle.
+    pub idle_delay_ms: u64,
+    // Once refinement is done, compute a margin around the view while idle, so that small pans
+    // show at once. It takes at most `prefetch_mb`, or is not kept.
+    pub prefetch: bool,
+    pub prefetch_mb: u32,
+    // Outline rendered tiles and number them in the order they were computed.
+    pub debug_tile_order: bool,
+    // Measure the fastest chunk size and worker count on first launch, and again whenever the
+    // machine's fingerprint no longer matches the stored tuning.
+    pub auto_tune: bool,
+    pub tuning: Option<Tuning>,
+    // Suspends previews and idle refinement and uses fewer workers, always or on battery.
+    pub energy_saver: EnergySaver,
+    // The share of each worker's time renders may spend computing, in percent; they rest the
+    // rest. 100 leaves them uncapped.
+    pub cpu_cap: u32,
+    // Show frames converted from sRGB through `display_profile`, the ICC profile of the screen
+    // the window is on. Exports and copies stay sRGB either way.
+    pub color_management: bool,
+    pub display_profile: Option<PathBuf>,
+    // Where quick exports go; the user's pictures directory when unset.
+    pub export_dir: Option<PathBuf>,
+    pub filename_template: String,
+    // Start exports left queued by the last session on launch rather than holding them until
+    // resumed. Either way they render again from the start.
+    pub resume_exports: bool,
+    // The quality profile exports are queued with, by name; the active one when unset.
+    pub export_profile: Option<String>,
+    // Least distance in pixels between exported boundary points; 0 exports every one.
+    pub boundary_spacing: f64,
+    // Keep a log of every view reached, with thumbnails, for browsing the session later.
+    pub exploration_log: bool,
+    // Oldest log entries beyond this many are dropped with their thumbnails.
+    pub exploration_log_limit: Option<usize>,
+    pub zoom_auto_stop: AutoStop,
+    // How coordinates are displayed; input accepts either decimal separator regardless.
+    pub number_format: NumberFormat,
+    // Show +imaginary down the screen, as image rows run, instead of up. Only the display and
+    // what window positions point at are mirrored; locations and files are the same either way.
+    pub invert_y: bool,
+    // Play the orbit of the point under the cursor as a short sound; only builds with the `sound`
+    // feature can.
+    pub orbit_sound: bool,
+    // Run auto levels on every view once it has rendered.
+    pub auto_levels: bool,
+    pub guides: GuideSettings,
+    pub show_annotations: bool,
+    // Paint the annotations into exported and copied images too.
+    pub annotations_in_exports: bool,
+    pub safe_area: SafeAreaSettings,
+    // The first-run tour of the core gestures was finished or skipped, so it is not shown again.
+    pub onboarded: bool,
+    // Names of the commands last run from the command palette, most recent first.
+    pub recent_commands: Vec<String>,
+    pub fractals: BTreeMap<FractalKind, FractalState>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            fractal: FractalKind::default(),
+            hybrid: Hybrid::default(),
+            buffer_precision: PrecisionSetting::default(),
+            cache_budget_mb: 256,
+            tile_cache_mb: 64,
+            tile_disk_mb: 0,
+            coarse_prepass: true,
+            experimental_double_double: false,
+            idle_refinement: true,
+            idle_delay_ms: 1500,
+            prefetch: false,
+            prefetch_mb: 128,
+            debug_tile_order: false,
+            auto_tune: true,
+            tuning: None,
+            energy_saver: EnergySaver::default(),
+            cpu_cap: 100,
+            color_management: false,
+            display_profile: None,
+            export_dir: None,
+            filename_template: String::from(DEFAULT_TEMPLATE),
+            resume_exports: true,
+            export_profile: None,
+            boundary_spacing: 0.0,
+            exploration_log: false,
+            exploration_log_limit: None,
+            zoom_auto_stop: AutoStop::default(),
+            number_format: NumberFormat::default(),
+            invert_y: false,
+            orbit_sound: false,
+            auto_levels: false,
+            guides: GuideSettings::default(),
+            show_annotations: true,
+            annotations_in_exports: false,
+            safe_area: SafeAreaSettings::default(),
+            onboarded: false,
+            recent_commands: Vec::new(),
+            fractals: FractalKind::ALL
+                .into_iter()
+                .map(|kind| (kind, FractalState::default()))
+                .collect(),
+        }
+    }
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("mandelbrot").join("config.toml"))
+    }
+
+    pub fn load() -> Config {
+        Config::path().map_or_else(Config::default, |path| Config::load_from(&path))
+    }
+
+    pub fn load_from(path: &Path) -> Config {
+        let parse = |contents: &str| toml::from_str(contents).map_err(|err| err.to_string());
+        let Some(mut config): Option<Config> = store::load(path, "config", parse) else {
+            return Config::default();
+        };
+        for kind in FractalKind::ALL {
+            let state = config.fractals.entry(kind).or_default();
+            if state.profiles.is_empty() {
+                state.profiles = QualityProfile::builtin();
+            }
+        }
+        config
+    }
+
+    pub fn save(&self) {
+        if let Some(path) = Config::path() {
+            self.save_to(&path);
+        }
+    }
+
+    pub fn save_to(&self, path: &Path) {
+        let result = toml::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| store::save(path, &contents));
+        if let Err(err) = result {
+            println!("failed to save config {}: {}", path.display(), err);
+        }
+    }
+
+    pub fn export_dir(&self) -> PathBuf {
+        self.export_dir
+            .clone()
+            .or_else(dirs::picture_dir)
+            .unwrap_or_else(|| PathBuf::from("."))
+    }
+
+    // Every hybrid shares one state, so that editing its steps keeps the view and settings.
+    pub fn state(&self) -> &FractalState {
+        &self.fractals[&self.fractal.family()]
+    }
+
+    pub fn state_mut(&mut self) -> &mut FractalState {
+        self.fractals.entry(self.fractal.family()).or_default()
+    }
+
+    // Shows `fractal`, remembering a hybrid's steps for when it is next cycled to.
+    pub fn set_fractal(&mut self, fractal: FractalKind) {
+        self.fractal = fractal;
+        if let FractalKind::Hybrid(hybrid) = fractal {
+            self.hybrid = hybrid;
+        }
+    }
+
+    pub fn cycle_fractal(&mut self) {
+        let next = match self.fractal.next() {
+            FractalKind::Hybrid(_) => FractalKind::Hybrid(self.hybrid),
+            kind => kind,
+        };
+        self.set_fractal(next);
+    }
+
+    pub fn active(&self) -> &QualityProfile {
+        self.state().active()
+    }
+
+    pub fn active_mut(&mut self) -> &mut QualityProfile {
+        self.state_mut().active_mut()
+    }
+
+    pub fn cycle_profile(&mut self) {
+        self.state_mut().cycle_profile();
+    }
+
+    // Makes the profile called `name` the active one, as `--profile` asks.
+    pub fn select_profile(&mut self, name: &str) -> Result<(), String> {
+        let state = self.state_mut();
+        state.active_profile = QualityProfile::named(&state.profiles, name)?.name.clone();
+        Ok(())
+    }
+
+    // The quality profiles of `fractal`, among which its exports name theirs.
+    pub fn profiles(&self, fractal: FractalKind) -> &[QualityProfile] {
+        self.fractals
+            .get(&fractal.family())
+            .map_or(&[], |state| state.profiles.as_slice())
+    }
+
+    // The profile exports are queued with: the one named for them if the current fractal has it,
+    // otherwise the active one.
+    pub fn queued_profile(&self) -> &QualityProfile {
+        self.export_profile
+            .as_deref()
+            .and_then(|name| QualityProfile::named(&self.state().profiles, name).ok())
+            .unwrap_or_else(|| self.active())
+    }
+
+    // The export profile after the current one: the active one, then each profile by name.
+    pub fn next_export_profile(&self) -> Option<String> {
+        let profiles = &self.state().profiles;
+        let next = match &self.export_profile {
+            None => profiles.first(),
+            Some(current) => profiles
+                .iter()
+                .position(|profile| profile.name == *current)
+                .and_then(|index| profiles.get(index + 1)),
+        };
+        next.map(|profile| profile.name.clone())
+    }
+}
+
+// Takes `--profile NAME` out of a command line mode's arguments, leaving the rest in order.
+pub fn profile_option(args: &[String]) -> Result<(Vec<String>, Option<String>), String> {
+    let Some(index) = args.iter().position(|arg| arg == "--profile") else {
+        return Ok((args.to_vec(), None));
+    };
+    let name = args
+        .get(index + 1)
+        .ok_or("--profile needs the name of a quality profile")?;
+    let rest = args[..index]
+        .iter()
+        .chain(&args[index + 2..])
+        .cloned()
+        .collect();
+    Ok((rest, Some(name.clone())))
+}
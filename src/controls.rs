@@ -0,0 +1,168 @@
+use mandelbrot::guides::Edge;
+
+// Everything the keyboard can focus. Tab walks the open panels in order, the settings first and
+// then the list panel on the left; Enter activates the focused control, the arrow keys adjust
+// it and Delete removes it where that means something.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Control {
+    Setting(Setting),
+    // A step of the hybrid fractal, by its place in the sequence.
+    HybridStep(usize),
+    // A post-processing stage, by its place in the pipeline.
+    PostStage(usize),
+    Bookmark(u64),
+    Preset(usize),
+    MinibrotScan,
+    Minibrot(usize),
+    ExportJob(u64),
+    JournalExport,
+    JournalEntry(u64),
+    KeyframeAdd,
+    PathScrubber,
+    Keyframe(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    // Appends a step to the hybrid fractal, shown while it is.
+    HybridAdd,
+    Iterations,
+    Resolution,
+    Antialias,
+    Palette,
+    PaletteWrap,
+    Coloring,
+    InteriorColor,
+    UnresolvedColor,
+    FieldBlend,
+    Gamma,
+    Density,
+    PaletteOffset,
+    AutoLevels,
+    AutoLevelsAfterRender,
+    Sectors,
+    HueRotation,
+    Occlusion,
+    OcclusionRadius,
+    OcclusionStrength,
+    // Adds the built-in post-processing stage with this index.
+    AddStage(usize),
+    PostExpression,
+    Regions,
+    ExportLook,
+    ColorManagement,
+    CachePrecision,
+    Prepass,
+    DoubleDouble,
+    TileOrder,
+    EnergySaver,
+    CpuCap,
+    Tune,
+    ExplorationLog,
+    Prefetch,
+    OrbitSound,
+    Goto,
+    OpenFile,
+    Decimal,
+    Grouping,
+    InvertY,
+    Crosshair,
+    Thirds,
+    GuideColor,
+    GuideOpacity,
+    GuidesInExports,
+    Annotations,
+    AnnotationsInExports,
+    SafeArea,
+    SafeMargin(Edge),
+    ExportPadding,
+    ExportProfile,
+    Undo,
+    Redo,
+}
+
+impl Setting {
+    // In the order the settings panel shows them.
+    pub const ALL: [Setting; 59] = [
+        Setting::HybridAdd,
+        Setting::Iterations,
+        Setting::Resolution,
+        Setting::Antialias,
+        Setting::Palette,
+        Setting::PaletteWrap,
+        Setting::Coloring,
+        Setting::InteriorColor,
+        Setting::UnresolvedColor,
+        Setting::FieldBlend,
+        Setting::Gamma,
+        Setting::Density,
+        Setting::PaletteOffset,
+        Setting::AutoLevels,
+        Setting::AutoLevelsAfterRender,
+        Setting::Sectors,
+        Setting::HueRotation,
+        Setting::Occlusion,
+        Setting::OcclusionRadius,
+        Setting::OcclusionStrength,
+        Setting::AddStage(0),
+        Setting::AddStage(1),
+        Setting::AddStage(2),
+        Setting::AddStage(3),
+        Setting::PostExpression,
+        Setting::Regions,
+        Setting::ExportLook,
+        Setting::ColorManagement,
+        Setting::CachePrecision,
+        Setting::Prepass,
+        Setting::DoubleDouble,
+        Setting::TileOrder,
+        Setting::EnergySaver,
+        Setting::CpuCap,
+        Setting::Tune,
+        Setting::ExplorationLog,
+        Setting::Prefetch,
+        Setting::OrbitSound,
+        Setting::Goto,
+        Setting::OpenFile,
+        Setting::Decimal,
+        Setting::Grouping,
+        Setting::InvertY,
+        Setting::Crosshair,
+        Setting::Thirds,
+        Setting::GuideColor,
+        Setting::GuideOpacity,
+        Setting::GuidesInExports,
+        Setting::Annotations,
+        Setting::AnnotationsInExports,
+        Setting::SafeArea,
+        Setting::SafeMargin(Edge::Top),
+        Setting::SafeMargin(Edge::Right),
+        Setting::SafeMargin(Edge::Bottom),
+        Setting::SafeMargin(Edge::Left),
+        Setting::ExportPadding,
+        Setting::ExportProfile,
+        Setting::Undo,
+        Setting::Redo,
+    ];
+}
+
+// The control `step` places from `current` in `controls`, wrapping around at either end. Without
+// a current control among them, a forward step lands on the first and a backward one on the last.
+pub fn cycle(controls: &[Control], current: Option<Control>, step: isize) -> Option<Control> {
+    if controls.is_empty() {
+        return None;
+    }
+    let count = controls.len() as isize;
+    let index = match current.and_then(|current| controls.iter().position(|c| *c == current)) {
+        Some(index) => (index as isize + step).rem_euclid(count),
+        None if step < 0 => count - 1,
+        None => 0,
+    };
+    Some(controls[index as usize])
+}
+
+impl From<Setting> for Control {
+    fn from(setting: Setting) -> Control {
+        Control::Setting(setting)
+    }
+}
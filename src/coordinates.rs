@@ -0,0 +1,315 @@
+use crate::doubledouble::DoubleDouble;
+
+// Deep-zoom coordinates as decimal strings. Past f64's resolution a center needs more digits
+// than the status bar can usefully show, so the digits every point in view shares are cut short
+// and only the ones that vary across it are shown in full.
+
+// Digits after the point a coordinate may have before it is shown split.
+pub const SPLIT_DIGITS: usize = 20;
+// Digits after the point kept before the elision.
+pub const HEAD_DIGITS: usize = 8;
+// Fewest trailing digits shown after the elision.
+pub const MIN_TAIL_DIGITS: usize = 4;
+
+// Extra digits each part of a double-double is written to before their sum is rounded.
+const GUARD_DIGITS: usize = 3;
+
+// A coordinate as the user sees it: all of its digits, or the start of them and the end, with
+// how many are left out between.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Shown {
+    Plain(String),
+    Split {
+        head: String,
+        elided: usize,
+        tail: String,
+    },
+}
+
+impl Shown {
+    pub fn text(&self) -> String {
+        match self {
+            Shown::Plain(full) => full.clone(),
+            Shown::Split { head, tail, .. } => format!("{}…{}", head, tail),
+        }
+    }
+
+    pub fn elided(&self) -> usize {
+        match self {
+            Shown::Plain(_) => 0,
+            Shown::Split { elided, .. } => *elided,
+        }
+    }
+}
+
+// `value` to `digits` places after the point, every one of them correct: `{:.N}` of an f64 is
+// exact, so the two parts are written out and added as decimals.
+pub fn exact(value: DoubleDouble, digits: usize) -> String {
+    if value.lo == 0.0 {
+        return round(&format!("{:.*}", digits, value.hi), digits);
+    }
+    let hi = format!("{:.*}", digits + GUARD_DIGITS, value.hi);
+    let lo = format!("{:.*}", digits + GUARD_DIGITS, value.lo);
+    round(&add(&hi, &lo), digits)
+}
+
+// How `full`, a canonical decimal, is shown when its last `varying` digits change across the
+// view: whole up to `SPLIT_DIGITS` places, split past that.
+pub fn shown(full: &str, varying: usize) -> Shown {
+    let (integer, fraction) = full.split_once('.').unwrap_or((full, ""));
+    let tail = varying.max(MIN_TAIL_DIGITS);
+    let elided = fraction.len().saturating_sub(HEAD_DIGITS + tail);
+    if fraction.len() <= SPLIT_DIGITS || elided == 0 {
+        return Shown::Plain(full.to_string());
+    }
+    Shown::Split {
+        head: format!("{}.{}", integer, &fraction[..HEAD_DIGITS]),
+        elided,
+        tail: fraction[HEAD_DIGITS + elided..].to_string(),
+    }
+}
+
+// The sum of two canonical decimals, to the places of the longer fraction.
+pub fn add(a: &str, b: &str) -> String {
+    let (a, b) = (Decimal::parse(a), Decimal::parse(b));
+    let scale = a.scale.max(b.scale);
+    let (a, b) = (a.rescaled(scale), b.rescaled(scale));
+    let sum = if a.negative == b.negative {
+        Decimal {
+            negative: a.negative,
+            digits: add_digits(&a.digits, &b.digits),
+            scale,
+        }
+    } else if compare_digits(&a.digits, &b.digits).is_ge() {
+        Decimal {
+            negative: a.negative,
+            digits: subtract_digits(&a.digits, &b.digits),
+            scale,
+        }
+    } else {
+        Decimal {
+            negative: b.negative,
+            digits: subtract_digits(&b.digits, &a.digits),
+            scale,
+        }
+    };
+    sum.text()
+}
+
+// A canonical decimal rounded, half away from zero, to `digits` places; padded with zeros when
+// it has fewer. Rounding to zero drops the sign.
+pub fn round(value: &str, digits: usize) -> String {
+    let decimal = Decimal::parse(value);
+    if decimal.scale <= digits {
+        return decimal.rescaled(digits).text();
+    }
+    let cut = decimal.digits.len() - (decimal.scale - digits);
+    let mut kept = decimal.digits[..cut].to_vec();
+    if decimal.digits[cut] >= 5 {
+        kept = add_digits(&kept, &[1]);
+    }
+    Decimal {
+        negative: decimal.negative,
+        digits: kept,
+        scale: digits,
+    }
+    .text()
+}
+
+// A decimal as its sign and its digits, most significant first, `scale` of them after the point.
+struct Decimal {
+    negative: bool,
+    digits: Vec<u8>,
+    scale: usize,
+}
+
+impl Decimal {
+    fn parse(text: &str) -> Decimal {
+        let (negative, unsigned) = match text.strip_prefix('-') {
+            Some(unsigned) => (true, unsigned),
+            None => (false, text),
+        };
+        let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+        Decimal {
+            negative,
+            digits: integer
+                .bytes()
+                .chain(fraction.bytes())
+                .map(|digit| digit - b'0')
+                .collect(),
+            scale: fraction.len(),
+        }
+    }
+
+    fn rescaled(mut self, scale: usize) -> Decimal {
+        self.digits
+            .resize(self.digits.len() + scale - self.scale, 0);
+        self.scale = scale;
+        self
+    }
+
+    fn text(&self) -> String {
+        let mut digits = self.digits.clone();
+        while digits.len() <= self.scale {
+            digits.insert(0, 0);
+        }
+        let integer_len = digits.len() - self.scale;
+        let leading_zeros = digits[..integer_len - 1]
+            .iter()
+            .take_while(|digit| **digit == 0)
+            .count();
+        let digits = &digits[leading_zeros..];
+        let integer_len = integer_len - leading_zeros;
+        let zero = digits.iter().all(|digit| *digit == 0);
+        let mut text = String::from(if self.negative && !zero { "-" } else { "" });
+        for (index, digit) in digits.iter().enumerate() {
+            if index == integer_len {
+                text.push('.');
+            }
+            text.push(char::from(b'0' + digit));
+        }
+        text
+    }
+}
+
+fn compare_digits(a: &[u8], b: &[u8]) -> std::cmp::Ordering {
+    let width = a.len().max(b.len());
+    let padded = |digits: &[u8]| {
+        let mut padded = vec![0; width - digits.len()];
+        padded.extend_from_slice(digits);
+        padded
+    };
+    padded(a).cmp(&padded(b))
+}
+
+fn add_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut sum = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0;
+    for place in 0..a.len().max(b.len()) {
+        let digit = |digits: &[u8]| {
+            digits
+                .len()
+                .checked_sub(place + 1)
+                .map_or(0, |index| digits[index])
+        };
+        let total = digit(a) + digit(b) + carry;
+        sum.push(total % 10);
+        carry = total / 10;
+    }
+    if carry > 0 {
+        sum.push(carry);
+    }
+    sum.reverse();
+    sum
+}
+
+// `a - b` for `a >= b`.
+fn subtract_digits(a: &[u8], b: &[u8]) -> Vec<u8> {
+    let mut difference = Vec::with_capacity(a.len());
+    let mut borrow = 0;
+    for place in 0..a.len() {
+        let top = a[a.len() - 1 - place] as i8;
+        let bottom = b.len().checked_sub(place + 1).map_or(0, |index| b[index]) as i8;
+        let mut digit = top - bottom - borrow;
+        borrow = 0;
+        if digit < 0 {
+            digit += 10;
+            borrow = 1;
+        }
+        difference.push(digit as u8);
+    }
+    difference.reverse();
+    difference
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decimals_add_exactly() {
+        for (a, b, expected) in [
+            ("0.999", "0.001", "1.000"),
+            ("9.5", "0.55", "10.05"),
+            ("1.25", "-2.5", "-1.25"),
+            ("-0.5", "0.5", "0.0"),
+            ("-0.75", "-0.25", "-1.00"),
+            ("-1.000", "0.0001", "-0.9999"),
+            ("100", "-99.999", "0.001"),
+        ] {
+            assert_eq!(add(a, b), expected, "{} + {}", a, b);
+        }
+    }
+
+    #[test]
+    fn decimals_round_half_away_from_zero() {
+        for (value, digits, expected) in [
+            ("9.9996", 3, "10.000"),
+            ("-0.99995", 4, "-1.0000"),
+            ("-0.00004", 4, "0.0000"),
+            ("0.12345", 4, "0.1235"),
+            ("0.12344", 4, "0.1234"),
+            ("-2.5", 3, "-2.500"),
+        ] {
+            assert_eq!(
+                round(value, digits),
+                expected,
+                "{} to {} places",
+                value,
+                digits
+            );
+        }
+    }
+
+    #[test]
+    fn double_doubles_are_written_with_every_digit() {
+        for (value, digits, expected) in [
+            (
+                DoubleDouble::new(-0.75, 1e-20),
+                25,
+                "-0.7499999999999999999900000",
+            ),
+            (
+                DoubleDouble::new(0.25, -1e-30),
+                32,
+                "0.24999999999999999999999999999900",
+            ),
+            (DoubleDouble::new(0.5, 0.0), 3, "0.500"),
+            (DoubleDouble::new(-1e-40, 0.0), 8, "0.00000000"),
+        ] {
+            assert_eq!(
+                exact(value, digits),
+                expected,
+                "{:?} to {} places",
+                value,
+                digits
+            );
+        }
+    }
+
+    // Twenty places stay whole; the twenty-first splits them.
+    #[test]
+    fn long_coordinates_leave_out_the_digits_that_do_not_vary() {
+        let twenty = format!("-0.{}", "12345678901234567890");
+        assert_eq!(shown(&twenty, 4), Shown::Plain(twenty.clone()));
+        let twenty_one = format!("{}1", twenty);
+        let split = shown(&twenty_one, 3);
+        assert_eq!(
+            split,
+            Shown::Split {
+                head: String::from("-0.12345678"),
+                elided: 21 - HEAD_DIGITS - MIN_TAIL_DIGITS,
+                tail: String::from("8901"),
+            }
+        );
+        assert_eq!(split.text(), "-0.12345678…8901");
+    }
+
+    // When nearly every digit varies across the view there is nothing to leave out.
+    #[test]
+    fn coordinates_varying_throughout_stay_whole() {
+        let long = format!("0.{}", "1".repeat(30));
+        assert_eq!(shown(&long, 22), Shown::Plain(long.clone()));
+        assert_eq!(shown(&long, 21).elided(), 1);
+    }
+}
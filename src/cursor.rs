@@ -0,0 +1,144 @@
+use iced::Point;
+
+// While precision is asked for, physical cursor movement counts this much in a selection.
+pub const PRECISION_RATIO: f64 = 0.1;
+
+// Where a selection's moving corner is, in window pixels, kept as f64 and advanced by the raw
+// cursor's movement rather than copied from it, so that precise moves can count for less than a
+// pixel. As long as precision is never asked for it is exactly the raw cursor; moves made with
+// precision leave it offset from the raw cursor, and later moves without precision carry it
+// along one for one from there rather than jumping back.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PrecisionCursor {
+    pub x: f64,
+    pub y: f64,
+    // The raw position last followed, which the next move is measured from.
+    raw_x: f64,
+    raw_y: f64,
+}
+
+impl PrecisionCursor {
+    pub fn at(raw: Point) -> PrecisionCursor {
+        let (x, y) = (raw.x as f64, raw.y as f64);
+        PrecisionCursor {
+            x,
+            y,
+            raw_x: x,
+            raw_y: y,
+        }
+    }
+
+    // Follows the raw cursor to `raw`, the whole way or, with `precise`, `PRECISION_RATIO` of it.
+    pub fn moved(&mut self, raw: Point, precise: bool) -> Point {
+        let (raw_x, raw_y) = (raw.x as f64, raw.y as f64);
+        let ratio = if precise { PRECISION_RATIO } else { 1.0 };
+        if self.x == self.raw_x && self.y == self.raw_y && !precise {
+            // Taking the raw position itself keeps rounding from ever drifting it off.
+            self.x = raw_x;
+            self.y = raw_y;
+        } else {
+            self.x += (raw_x - self.raw_x) * ratio;
+            self.y += (raw_y - self.raw_y) * ratio;
+        }
+        self.raw_x = raw_x;
+        self.raw_y = raw_y;
+        self.point()
+    }
+
+    pub fn point(&self) -> Point {
+        Point::new(self.x as f32, self.y as f32)
+    }
+}
+
+// Significant digits that tell apart selections `size` wide, in complex units, differing by the
+// smallest precise move across pixels `pixel_size` wide.
+pub fn size_digits(size: f64, pixel_size: f64) -> usize {
+    let step = pixel_size * PRECISION_RATIO;
+    if size <= 0.0 || step <= 0.0 {
+        return 3;
+    }
+    ((size / step).log10().ceil().max(0.0) as usize + 1).max(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(point: Point, expected: (f64, f64), what: &str) {
+        assert!(
+            (point.x as f64 - expected.0).abs() < 1e-4
+                && (point.y as f64 - expected.1).abs() < 1e-4,
+            "{} lands at {:?}, not {:?}",
+            what,
+            point,
+            expected
+        );
+    }
+
+    #[test]
+    fn without_precision_the_cursor_follows_the_raw_one() {
+        let mut cursor = PrecisionCursor::at(Point::new(10.0, 20.0));
+        for point in [
+            Point::new(10.25, 20.5),
+            Point::new(11.0, 19.75),
+            Point::new(400.125, 3.0),
+            Point::new(0.5, 0.0),
+        ] {
+            assert_eq!(cursor.moved(point, false), point);
+        }
+    }
+
+    #[test]
+    fn precise_moves_are_scaled_down_without_jumps() {
+        let mut cursor = PrecisionCursor::at(Point::new(100.0, 100.0));
+        let expected = (
+            100.0 + 10.0 * PRECISION_RATIO,
+            100.0 - 10.0 * PRECISION_RATIO,
+        );
+        assert_close(
+            cursor.moved(Point::new(110.0, 90.0), true),
+            expected,
+            "a precise move",
+        );
+        // Letting go and moving on goes one for one from where the precise move left it.
+        let expected = (expected.0 + 5.0, expected.1);
+        assert_close(
+            cursor.moved(Point::new(115.0, 90.0), false),
+            expected,
+            "a move after letting go",
+        );
+        // Taking precision up again where the raw cursor is makes no jump, nor does letting go.
+        assert_close(
+            cursor.moved(Point::new(115.0, 90.0), true),
+            expected,
+            "taking precision up",
+        );
+        assert_close(
+            cursor.moved(Point::new(115.0, 90.0), false),
+            expected,
+            "letting precision go",
+        );
+    }
+
+    #[test]
+    fn new_selections_start_at_the_raw_cursor() {
+        let restart = Point::new(40.0, 30.0);
+        let mut cursor = PrecisionCursor::at(restart);
+        assert_eq!(cursor.point(), restart);
+        assert_eq!(
+            cursor.moved(Point::new(41.5, 30.0), false),
+            Point::new(41.5, 30.0)
+        );
+    }
+
+    // Deep enough that a tenth of a pixel is 1e-13 of a 1e-10 wide selection.
+    #[test]
+    fn deep_selections_show_enough_digits() {
+        let digits = size_digits(1e-10, 1e-12);
+        assert!(
+            digits >= 4,
+            "a 1e-10 selection of 1e-12 pixels shows {} digits",
+            digits
+        );
+    }
+}
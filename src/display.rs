@@ -0,0 +1,51 @@
+use qcms::{DataType, Intent, Profile, Transform};
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Converts frames from sRGB, which every palette is defined in, to a display's own color space,
+// so that a wide-gamut screen shows them as an sRGB screen or an exported image would.
+pub struct DisplayProfile {
+    path: PathBuf,
+    transform: Transform,
+}
+
+impl DisplayProfile {
+    // Reads the ICC profile at `path`. Fails for unreadable files and for profiles no RGB
+    // transform can be built to, such as printer or gray profiles.
+    pub fn load(path: &Path) -> Result<DisplayProfile, String> {
+        let data = fs::read(path).map_err(|err| err.to_string())?;
+        let mut output = Profile::new_from_slice(&data, false)
+            .ok_or_else(|| String::from("not a readable ICC profile"))?;
+        output.precache_output_transform();
+        let transform = Transform::new(
+            &Profile::new_sRGB(),
+            &output,
+            DataType::RGBA8,
+            Intent::Perceptual,
+        )
+        .ok_or_else(|| String::from("not an RGB display profile"))?;
+        Ok(DisplayProfile {
+            path: path.to_path_buf(),
+            transform,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Converts whole RGBA pixels in place; alpha is left as it is.
+    pub fn apply(&self, rgba: &mut [u8]) {
+        self.transform.apply(rgba);
+    }
+}
+
+impl fmt::Debug for DisplayProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DisplayProfile")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,114 @@
+use std::ops::{Add, Mul, Neg, Sub};
+
+// An unevaluated sum hi + lo with |lo| <= ulp(hi) / 2, giving about 106 bits of mantissa from
+// two f64s. Only the operations the escape loop and viewport math need are provided.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DoubleDouble {
+    pub hi: f64,
+    pub lo: f64,
+}
+
+// Relative precision of the representation, roughly 2^-104.
+pub const EPSILON: f64 = f64::EPSILON * f64::EPSILON * 4.0;
+
+impl DoubleDouble {
+    pub const ZERO: DoubleDouble = DoubleDouble { hi: 0.0, lo: 0.0 };
+
+    pub fn new(hi: f64, lo: f64) -> DoubleDouble {
+        let (hi, lo) = two_sum(hi, lo);
+        DoubleDouble { hi, lo }
+    }
+
+    pub fn to_f64(self) -> f64 {
+        self.hi + self.lo
+    }
+
+    pub fn abs(self) -> DoubleDouble {
+        if self.hi < 0.0 {
+            -self
+        } else {
+            self
+        }
+    }
+
+    pub fn sqr(self) -> DoubleDouble {
+        let (product, error) = two_prod(self.hi, self.hi);
+        let error = error + 2.0 * self.hi * self.lo;
+        let (hi, lo) = quick_two_sum(product, error);
+        DoubleDouble { hi, lo }
+    }
+
+    pub fn mul_f64(self, rhs: f64) -> DoubleDouble {
+        let (product, error) = two_prod(self.hi, rhs);
+        let (hi, lo) = quick_two_sum(product, error + self.lo * rhs);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl From<f64> for DoubleDouble {
+    fn from(value: f64) -> DoubleDouble {
+        DoubleDouble { hi: value, lo: 0.0 }
+    }
+}
+
+impl Add for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn add(self, rhs: DoubleDouble) -> DoubleDouble {
+        let (sum, error) = two_sum(self.hi, rhs.hi);
+        let (low_sum, low_error) = two_sum(self.lo, rhs.lo);
+        let (hi, lo) = quick_two_sum(sum, error + low_sum);
+        let (hi, lo) = quick_two_sum(hi, lo + low_error);
+        DoubleDouble { hi, lo }
+    }
+}
+
+impl Sub for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn sub(self, rhs: DoubleDouble) -> DoubleDouble {
+        self + -rhs
+    }
+}
+
+impl Neg for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn neg(self) -> DoubleDouble {
+        DoubleDouble {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+
+impl Mul for DoubleDouble {
+    type Output = DoubleDouble;
+
+    fn mul(self, rhs: DoubleDouble) -> DoubleDouble {
+        let (product, error) = two_prod(self.hi, rhs.hi);
+        let error = error + (self.hi * rhs.lo + self.lo * rhs.hi);
+        let (hi, lo) = quick_two_sum(product, error);
+        DoubleDouble { hi, lo }
+    }
+}
+
+// Exact a + b as a rounded sum and its error.
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    let b_virtual = sum - a;
+    let a_virtual = sum - b_virtual;
+    (sum, (a - a_virtual) + (b - b_virtual))
+}
+
+// `two_sum` for |a| >= |b|.
+fn quick_two_sum(a: f64, b: f64) -> (f64, f64) {
+    let sum = a + b;
+    (sum, b - (sum - a))
+}
+
+// Exact a * b as a rounded product and its error, using a fused multiply-add.
+fn two_prod(a: f64, b: f64) -> (f64, f64) {
+    let product = a * b;
+    (product, a.mul_add(b, -product))
+}
@@ -0,0 +1,195 @@
+use iced::{Point, Size};
+
+// Physical pixels covered by a logical size at `scale_factor`. Rounded to nearest, as the
+// windowing system rounds surface sizes, so a logical size it derived from a surface maps back to
+// exactly that surface even at fractional scales.
+pub fn to_physical(logical: Size, scale_factor: f64) -> (u32, u32) {
+    let scale = |length: f32| (f64::from(length) * scale_factor).round().max(1.0) as u32;
+    (scale(logical.width), scale(logical.height))
+}
+
+pub fn to_logical(physical: (u32, u32), scale_factor: f64) -> Size {
+    Size::new(
+        (f64::from(physical.0) / scale_factor) as f32,
+        (f64::from(physical.1) / scale_factor) as f32,
+    )
+}
+
+// The size frames are rendered at for a window: its physical size times `resolution_scale`, each
+// side rounded and at least one pixel, then supersampled `antialias` times per axis.
+pub fn render_size(
+    logical: Size,
+    scale_factor: f64,
+    resolution_scale: f32,
+    antialias: usize,
+) -> Size {
+    let (width, height) = to_physical(logical, scale_factor);
+    let scale = |length: u32| (length as f32 * resolution_scale).round().max(1.0);
+    Size::new(
+        scale(width) * antialias as f32,
+        scale(height) * antialias as f32,
+    )
+}
+
+// The largest texture the screen can show, per side. iced's wgpu backend asks the device for
+// wgpu's default limits and does not say what it got, so this is what those limits promise.
+pub const TEXTURE_LIMIT: (u32, u32) = (8192, 8192);
+
+// `size` scaled down as a whole until it fits in `limit`, or as it is if it already does.
+pub fn fit_texture(size: (u32, u32), limit: (u32, u32)) -> (u32, u32) {
+    let scale = (f64::from(limit.0) / f64::from(size.0.max(1)))
+        .min(f64::from(limit.1) / f64::from(size.1.max(1)));
+    if scale >= 1.0 {
+        return size;
+    }
+    let fit =
+        |length: u32, limit: u32| ((f64::from(length) * scale).round() as u32).clamp(1, limit);
+    (fit(size.0, limit.0), fit(size.1, limit.1))
+}
+
+// Like `render_size`, for frames shown on screen: the frame as shown, which is downsampled by its
+// antialiasing, is capped to `limit` and stretched to the window.
+pub fn screen_render_size(
+    logical: Size,
+    scale_factor: f64,
+    resolution_scale: f32,
+    antialias: usize,
+    limit: (u32, u32),
+) -> Size {
+    let full = render_size(logical, scale_factor, resolution_scale, 1);
+    let (width, height) = fit_texture((full.width as u32, full.height as u32), limit);
+    Size::new(
+        (width as usize * antialias) as f32,
+        (height as usize * antialias) as f32,
+    )
+}
+
+// Moves a window position to where the same spot of the stretched frame lies after the window
+// went from `from` to `to` logical size.
+pub fn remap(point: Point, from: Size, to: Size) -> Point {
+    if from.width <= 0.0 || from.height <= 0.0 {
+        return point;
+    }
+    Point::new(
+        point.x * to.width / from.width,
+        point.y * to.height / from.height,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCALES: [f64; 3] = [1.25, 1.5, 1.75];
+
+    // Every surface size up to 4K across, at each fractional scale: the logical size the
+    // windowing system reports for it maps back to exactly that surface.
+    #[test]
+    fn logical_sizes_map_back_to_their_surface() {
+        for scale_factor in SCALES {
+            for length in 1..=3840 {
+                let physical = (length, 4321 - length);
+                let logical = to_logical(physical, scale_factor);
+                assert_eq!(
+                    to_physical(logical, scale_factor),
+                    physical,
+                    "{:?} at {} is {:?} logical",
+                    physical,
+                    scale_factor,
+                    logical
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn frames_match_odd_surfaces_at_fractional_scales() {
+        for scale_factor in SCALES {
+            for physical in [(1, 1), (3, 5), (1001, 777), (1919, 1081), (2561, 1441)] {
+                let logical = to_logical(physical, scale_factor);
+                let frame = render_size(logical, scale_factor, 1.0, 1);
+                assert_eq!(
+                    (frame.width as u32, frame.height as u32),
+                    physical,
+                    "at {}",
+                    scale_factor
+                );
+                let supersampled = render_size(logical, scale_factor, 1.0, 3);
+                assert_eq!(
+                    (supersampled.width as u32, supersampled.height as u32),
+                    (physical.0 * 3, physical.1 * 3)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn odd_logical_sizes_round_to_the_nearest_pixel() {
+        assert_eq!(to_physical(Size::new(801.0, 601.0), 1.25), (1001, 751));
+        assert_eq!(to_physical(Size::new(801.0, 601.0), 1.5), (1202, 902));
+        assert_eq!(to_physical(Size::new(801.0, 601.0), 1.75), (1402, 1052));
+        assert_eq!(to_physical(Size::new(0.2, 0.2), 1.25), (1, 1));
+    }
+
+    // Frames too large for a texture are rendered at the cap, keeping their shape.
+    #[test]
+    fn screen_frames_fit_the_texture_limit() {
+        let square = TEXTURE_LIMIT;
+        // (window, scale factor, resolution scale, antialiasing, limit, expected render size)
+        let cases = [
+            ((1920.0, 1080.0), 1.0, 1.0, 1, square, (1920, 1080)),
+            ((8192.0, 100.0), 1.0, 1.0, 2, square, (16384, 200)),
+            // Three 4K monitors side by side.
+            ((11520.0, 2160.0), 1.0, 1.0, 1, square, (8192, 1536)),
+            ((11520.0, 2160.0), 1.0, 1.0, 3, square, (24576, 4608)),
+            // Logical sizes that only overflow once multiplied out on a HiDPI screen.
+            ((5000.0, 3000.0), 2.0, 1.0, 1, square, (8192, 4915)),
+            ((3000.0, 2000.0), 1.5, 2.0, 1, square, (8192, 5461)),
+            ((3000.0, 2000.0), 1.5, 0.5, 1, square, (2250, 1500)),
+            ((6000.0, 1500.0), 1.0, 1.0, 1, (4096, 2048), (4096, 1024)),
+            ((3000.0, 3000.0), 1.0, 1.0, 1, (4096, 2048), (2048, 2048)),
+            ((1000.0, 5000.0), 1.0, 1.0, 2, (4096, 2048), (820, 4096)),
+        ];
+        for (window, scale_factor, resolution, antialias, limit, expected) in cases {
+            let size = screen_render_size(
+                Size::new(window.0, window.1),
+                scale_factor,
+                resolution,
+                antialias,
+                limit,
+            );
+            let shown = (
+                size.width as u32 / antialias as u32,
+                size.height as u32 / antialias as u32,
+            );
+            assert!(
+                (size.width as u32, size.height as u32) == expected
+                    && shown.0 <= limit.0
+                    && shown.1 <= limit.1,
+                "a {:?} window at {}x scale, {} resolution and {}x antialiasing renders at {:?} \
+                 under a {:?} limit, not {:?}",
+                window,
+                scale_factor,
+                resolution,
+                antialias,
+                size,
+                limit,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn extreme_shapes_fit_without_vanishing() {
+        let square = TEXTURE_LIMIT;
+        for size in [(1, 100_000), (100_000, 1), (8193, 8193)] {
+            let fit = fit_texture(size, square);
+            assert!(
+                fit.0 > 0 && fit.1 > 0 && fit.0 <= square.0 && fit.1 <= square.1,
+                "{:?} fits the texture limit as {:?}",
+                size,
+                fit
+            );
+        }
+    }
+}
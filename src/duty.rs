@@ -0,0 +1,205 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+// Lowest CPU cap the settings offer, in percent.
+pub const MIN_CAP: u32 = 10;
+// Rests shorter than this wait until more is owed, since sleeps that brief mostly overrun.
+pub const MIN_REST: Duration = Duration::from_millis(1);
+// Work and rest older than about this much time count for less and less, so that the balance
+// stays recent and a worker does not sleep off a long stretch of work all at once.
+const WINDOW: Duration = Duration::from_secs(2);
+
+// The share of each worker's time renders may spend working, in percent, for every render in the
+// process. At 100 workers never rest.
+static CAP: AtomicU32 = AtomicU32::new(100);
+
+pub fn cap() -> u32 {
+    CAP.load(Ordering::Relaxed)
+}
+
+// Sets the cap renders keep to, from the next row each worker finishes.
+pub fn set_cap(percent: u32) {
+    CAP.store(percent.clamp(MIN_CAP, 100), Ordering::Relaxed);
+}
+
+// One worker's duty cycle: after each stretch of work, how long to rest so that work takes the
+// cap's share of its time. Rests count as long as they actually slept, so sleeps that overrun
+// are made up for by shorter ones after.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DutyCycle {
+    worked: Duration,
+    rested: Duration,
+    // The cap the balance was kept under; a new one starts afresh.
+    cap: u32,
+}
+
+impl DutyCycle {
+    // Records `worked` of work under a cap of `percent` and returns how long to rest now.
+    pub fn worked(&mut self, worked: Duration, percent: u32) -> Duration {
+        if percent != self.cap {
+            *self = DutyCycle {
+                cap: percent,
+                ..DutyCycle::default()
+            };
+        }
+        if percent >= 100 {
+            return Duration::ZERO;
+        }
+        self.worked += worked;
+        let due = self
+            .worked
+            .mul_f64(f64::from(100 - percent) / f64::from(percent));
+        let owed = due.saturating_sub(self.rested);
+        if owed < MIN_REST {
+            Duration::ZERO
+        } else {
+            owed
+        }
+    }
+
+    pub fn rested(&mut self, slept: Duration) {
+        self.rested += slept;
+        // Halving both keeps their ratio, and any rest still owed, while the past fades.
+        if self.worked + self.rested > WINDOW {
+            self.worked /= 2;
+            self.rested /= 2;
+        }
+    }
+
+    // The share of the time measured spent working, or 1 before any rest.
+    pub fn achieved(&self) -> f64 {
+        let total = (self.worked + self.rested).as_secs_f64();
+        if total == 0.0 {
+            1.0
+        } else {
+            self.worked.as_secs_f64() / total
+        }
+    }
+}
+
+thread_local! {
+    static WORKER: RefCell<DutyCycle> = RefCell::new(DutyCycle::default());
+}
+
+// How long the calling worker should rest after `worked` of work, under the current cap.
+pub fn owed(worked: Duration) -> Duration {
+    WORKER.with(|duty| duty.borrow_mut().worked(worked, cap()))
+}
+
+// Records that the calling worker rested for `slept`.
+pub fn rested(slept: Duration) {
+    WORKER.with(|duty| duty.borrow_mut().rested(slept));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use iced::Size;
+    use threadpool::ThreadPool;
+
+    use super::*;
+    use crate::fractal::{AngleKind, FractalKind};
+    use crate::render::{self, Arithmetic, CancelToken, FrameParams};
+    use crate::stats;
+    use crate::storage::Precision;
+    use crate::viewport::Viewport;
+
+    // Works `rows` rows of pseudo-random length under a `percent` cap, resting as `duty` says,
+    // and returns the share of the time spent working.
+    fn simulate(duty: &mut DutyCycle, percent: u32, rows: usize, seed: u64) -> f64 {
+        let mut rng = seed;
+        let (mut worked, mut rested) = (Duration::ZERO, Duration::ZERO);
+        for _ in 0..rows {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let row = Duration::from_micros(50 + (rng >> 33) % 3000);
+            worked += row;
+            let mut owed = duty.worked(row, percent);
+            while !owed.is_zero() {
+                // Sleeps run over by a fifth of a millisecond, as they do on a busy machine.
+                let slept = owed.min(Duration::from_millis(50)) + Duration::from_micros(200);
+                duty.rested(slept);
+                rested += slept;
+                owed = owed.saturating_sub(slept);
+            }
+        }
+        worked.as_secs_f64() / (worked + rested).as_secs_f64()
+    }
+
+    #[test]
+    fn workers_keep_within_a_few_percent_of_the_cap() {
+        for percent in [10, 25, 50, 80, 100] {
+            let achieved = simulate(
+                &mut DutyCycle::default(),
+                percent,
+                5_000,
+                u64::from(percent),
+            );
+            assert!(
+                (achieved - f64::from(percent) / 100.0).abs() <= 0.02,
+                "a {}% cap works {:.1}% of the time",
+                percent,
+                achieved * 100.0
+            );
+        }
+    }
+
+    #[test]
+    fn workers_follow_a_cap_changed_partway() {
+        let mut duty = DutyCycle::default();
+        simulate(&mut duty, 30, 2_000, 1);
+        let after = simulate(&mut duty, 70, 2_000, 2);
+        assert!(
+            (after - 0.7).abs() <= 0.03,
+            "after a 30% cap rises to 70%, workers work {:.1}% of the time",
+            after * 100.0
+        );
+    }
+
+    // Renders under a cap take longer by about its inverse but give the same pixels.
+    #[test]
+    fn capped_renders_take_longer_for_the_same_pixels() {
+        let _settings = stats::changing_render_settings();
+        set_cap(5);
+        assert_eq!(cap(), MIN_CAP, "a 5% cap is kept as {}%", cap());
+        let params = FrameParams {
+            viewport: Viewport::new(-0.75, 0.0, 3.0),
+            max_iterations: 3000,
+            fractal: FractalKind::Mandelbrot,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: false,
+            arithmetic: Arithmetic::F64,
+        };
+        let render = |percent: u32| {
+            set_cap(percent);
+            let started = Instant::now();
+            let frame = render::threaded_fractal_calc(
+                &ThreadPool::new(1),
+                Size::new(160.0, 120.0),
+                params,
+                &CancelToken::new(),
+                |_| {},
+            )
+            .expect("renders without a cancel request complete");
+            (frame, started.elapsed())
+        };
+        let (free, free_time) = render(100);
+        let (capped, capped_time) = render(25);
+        set_cap(100);
+        assert!(
+            (0..free.values.len()).all(|index| capped.values.get(index) == free.values.get(index)),
+            "a capped render gives different pixels"
+        );
+        // A quarter of the time working takes four times as long; twice leaves room for noise.
+        assert!(
+            capped_time >= free_time * 2,
+            "a render capped at 25% took {:?}, against {:?} uncapped",
+            capped_time,
+            free_time
+        );
+    }
+}
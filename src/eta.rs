@@ -0,0 +1,283 @@
+use std::time::Duration;
+
+use crate::render::Progress;
+
+// Weight of each newly measured stretch of a render in its rate.
+const RATE_SMOOTHING: f64 = 0.25;
+// Chunks landing closer together than this are measured as one stretch, so that bursts, such as
+// cached tiles all landing at once, do not read as an enormous rate.
+const MIN_STRETCH: Duration = Duration::from_millis(50);
+// How much the estimate from the previous frame's cost map counts against the one from pixels
+// alone, when there is a map. Pixels take the rest of the frame to cost what the part done did;
+// the map knows better only as far as the previous frame is like this one.
+const COST_MAP_WEIGHT: f64 = 0.5;
+// The estimate shown changes at most this often, so that it does not flicker.
+pub const REFRESH: Duration = Duration::from_millis(500);
+
+// How fast some measure of work gets done: the work and the time of stretches of a render, each
+// smoothed exponentially, so that fast and slow stretches count by how long they took.
+#[derive(Clone, Copy, Debug, Default)]
+struct Rate {
+    // Work done so far, and how much of it and when the last measured stretch ended.
+    done: f64,
+    measured: f64,
+    measured_at: Duration,
+    // Smoothed work and seconds per stretch; None before the first stretch.
+    stretch: Option<(f64, f64)>,
+}
+
+impl Rate {
+    fn record(&mut self, done: f64, elapsed: Duration) {
+        self.done = done;
+        let seconds = elapsed.saturating_sub(self.measured_at);
+        if seconds < MIN_STRETCH || done <= self.measured {
+            return;
+        }
+        let latest = (done - self.measured, seconds.as_secs_f64());
+        self.stretch = Some(match self.stretch {
+            Some((work, time)) => (
+                work + (latest.0 - work) * RATE_SMOOTHING,
+                time + (latest.1 - time) * RATE_SMOOTHING,
+            ),
+            None => latest,
+        });
+        self.measured = done;
+        self.measured_at = elapsed;
+    }
+
+    // Takes the rate to be `factor` times as slow from here on.
+    fn slow_by(&mut self, factor: f64) {
+        if let Some((_, time)) = &mut self.stretch {
+            *time *= factor;
+        }
+    }
+
+    // Seconds until `total` is done at this rate, once there is one.
+    fn remaining(&self, total: f64) -> Option<f64> {
+        let (work, time) = self.stretch.filter(|(work, _)| *work > 0.0)?;
+        Some((total - self.done).max(0.0) * time / work)
+    }
+}
+
+// Estimated time left in a render, from the progress it reports: pixels done against the rate
+// they are being done at, blended, when the render has the previous frame's cost map, with the
+// predicted cost done against the rate that is being done at.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Eta {
+    pixels: Rate,
+    total_pixels: f64,
+    predicted: Rate,
+    predicted_total: f64,
+    // What `shown` returns, and when it was worked out.
+    shown: Option<Duration>,
+    shown_at: Option<Duration>,
+}
+
+impl Eta {
+    pub fn record(&mut self, progress: &Progress) {
+        self.pixels
+            .record(progress.pixels_done as f64, progress.elapsed);
+        self.total_pixels = progress.total_pixels as f64;
+        self.predicted
+            .record(progress.predicted_done.as_secs_f64(), progress.elapsed);
+        self.predicted_total = progress.predicted_total.as_secs_f64();
+        let due = self
+            .shown_at
+            .is_none_or(|at| progress.elapsed.saturating_sub(at) >= REFRESH);
+        if due {
+            self.shown = self.remaining();
+            self.shown_at = Some(progress.elapsed);
+        }
+    }
+
+    // The time left by the latest progress; None until a rate has been measured.
+    pub fn remaining(&self) -> Option<Duration> {
+        let pixels = self.pixels.remaining(self.total_pixels)?;
+        let seconds = match self.predicted.remaining(self.predicted_total) {
+            Some(predicted) if self.predicted_total > 0.0 => {
+                pixels + (predicted - pixels) * COST_MAP_WEIGHT
+            }
+            _ => pixels,
+        };
+        Duration::try_from_secs_f64(seconds).ok()
+    }
+
+    // Takes the render to go `factor` times as slowly from here on, as when the CPU cap changes
+    // partway through, so that the estimate does not wait on new stretches to catch up.
+    pub fn slow_by(&mut self, factor: f64) {
+        self.pixels.slow_by(factor);
+        self.predicted.slow_by(factor);
+        if self.shown.is_some() {
+            self.shown = self.remaining();
+        }
+    }
+
+    // `remaining` as last worked out, at most `REFRESH` ago by the render's clock.
+    pub fn shown(&self) -> Option<Duration> {
+        self.shown
+    }
+}
+
+// A time left as the status bar puts it, e.g. "about 1m 05s left".
+pub fn describe(remaining: Duration) -> String {
+    let seconds = remaining.as_secs_f64().ceil() as u64;
+    match seconds {
+        0..=59 => format!("about {}s left", seconds.max(1)),
+        60..=3599 => format!("about {}m {:02}s left", seconds / 60, seconds % 60),
+        _ => format!("about {}h {:02}m left", seconds / 3600, seconds / 60 % 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHUNKS: usize = 100;
+    const PIXELS: usize = 1000;
+
+    fn steady(_: usize) -> u64 {
+        100
+    }
+
+    fn costlier(chunk: usize) -> u64 {
+        if chunk < CHUNKS / 2 {
+            100
+        } else {
+            300
+        }
+    }
+
+    // Feeds an estimate a render of `CHUNKS` chunks taking `millis` milliseconds each, the first
+    // ten landing at once from the cache if `burst`, with a cost map predicting the chunks'
+    // relative costs if `predicted`. Checks that the estimate comes soon, converges, ends at zero
+    // and refreshes at most every `REFRESH`, and returns how many seconds off it was halfway.
+    fn simulate(name: &str, millis: fn(usize) -> u64, burst: bool, predicted: bool) -> f64 {
+        let chunk_costs: Vec<u64> = (0..CHUNKS)
+            .map(|chunk| {
+                if burst && chunk < 10 {
+                    0
+                } else {
+                    millis(chunk)
+                }
+            })
+            .collect();
+        let total: u64 = chunk_costs.iter().sum();
+        let longest = chunk_costs.iter().max().copied().unwrap_or(0) as f64 / 1000.0;
+        let millis_done = |upto: usize| (0..upto).map(millis).sum::<u64>();
+        let mut estimate = Eta::default();
+        let mut elapsed = 0;
+        let mut shown = Vec::new();
+        let mut halfway = 0.0;
+        for (chunk, cost) in chunk_costs.iter().enumerate() {
+            elapsed += cost;
+            let progress = Progress {
+                pixels_done: (chunk + 1) * PIXELS,
+                total_pixels: CHUNKS * PIXELS,
+                elapsed: Duration::from_millis(elapsed.max(1)),
+                predicted_done: Duration::from_millis(millis_done(chunk + 1) * predicted as u64),
+                predicted_total: Duration::from_millis(millis_done(CHUNKS) * predicted as u64),
+            };
+            estimate.record(&progress);
+            let truth = (total - elapsed) as f64 / 1000.0;
+            let remaining = estimate.remaining().map(|left| left.as_secs_f64());
+            if chunk >= 20 {
+                assert!(
+                    remaining.is_some(),
+                    "{} has no estimate after chunk {}",
+                    name,
+                    chunk
+                );
+            }
+            if chunk >= CHUNKS * 9 / 10 {
+                let off = remaining.map_or(0.0, |left| (left - truth).abs());
+                assert!(
+                    off <= (0.05 * truth).max(longest),
+                    "{} estimates {:.2?}s left after chunk {}, not {:.2}s",
+                    name,
+                    remaining,
+                    chunk,
+                    truth
+                );
+            }
+            if chunk == CHUNKS / 2 - 1 {
+                halfway = (remaining.unwrap_or(0.0) - truth).abs();
+            }
+            if shown.last().map(|(_, left)| *left) != Some(estimate.shown()) {
+                shown.push((elapsed, estimate.shown()));
+            }
+        }
+        assert_eq!(
+            estimate.remaining(),
+            Some(Duration::ZERO),
+            "{} ends with time left",
+            name
+        );
+        let refresh = REFRESH.as_millis() as u64;
+        assert!(
+            shown
+                .windows(2)
+                .all(|pair| pair[1].0 - pair[0].0 >= refresh),
+            "{} changes its shown estimate within {:?}",
+            name,
+            REFRESH
+        );
+        halfway
+    }
+
+    #[test]
+    fn steady_renders_converge() {
+        simulate("steady", steady, false, false);
+    }
+
+    #[test]
+    fn jittery_renders_converge() {
+        simulate(
+            "jittery",
+            |chunk| if chunk.is_multiple_of(2) { 50 } else { 150 },
+            false,
+            false,
+        );
+    }
+
+    #[test]
+    fn cached_bursts_are_not_read_as_speed() {
+        simulate("cached", steady, true, false);
+    }
+
+    #[test]
+    fn cost_maps_foresee_costlier_chunks() {
+        let unpredicted = simulate("unpredicted", costlier, false, false);
+        let predicted = simulate("predicted", costlier, false, true);
+        assert!(
+            predicted < unpredicted,
+            "a cost map leaves the estimate {:.2}s off halfway, without one {:.2}s",
+            predicted,
+            unpredicted
+        );
+    }
+
+    #[test]
+    fn slowing_down_stretches_the_estimate_at_once() {
+        let mut estimate = Eta::default();
+        for step in 1..=10u64 {
+            estimate.record(&Progress {
+                pixels_done: step as usize * 100,
+                total_pixels: 2_000,
+                elapsed: Duration::from_millis(step * 100),
+                predicted_done: Duration::ZERO,
+                predicted_total: Duration::ZERO,
+            });
+        }
+        let before = estimate.remaining().unwrap_or_default();
+        estimate.slow_by(2.0);
+        let after = estimate.remaining().unwrap_or_default();
+        assert!(
+            (after.as_secs_f64() - 2.0 * before.as_secs_f64()).abs() <= 1e-6
+                && estimate.shown() == Some(after),
+            "halving the cap moves the estimate from {:?} to {:?}, showing {:?}",
+            before,
+            after,
+            estimate.shown()
+        );
+    }
+}
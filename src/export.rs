@@ -0,0 +1,278 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_TEMPLATE: &str = "{fractal}_{re}_{im}_{zoom}_{iter}_{date}";
+// Quick exports and served renders carry the view's location under this keyword, +imaginary up
+// like every location.
+pub const LOCATION_PNG_KEYWORD: &str = "Location";
+
+// Values substituted into a filename template; everything is pre-formatted text so callers
+// decide how many digits a coordinate needs.
+pub struct TemplateFields {
+    pub fractal: String,
+    pub re: String,
+    pub im: String,
+    pub zoom: String,
+    pub iter: String,
+    pub date: String,
+}
+
+pub fn expand_template(template: &str, fields: &TemplateFields) -> String {
+    let expanded = template
+        .replace("{fractal}", &fields.fractal)
+        .replace("{re}", &fields.re)
+        .replace("{im}", &fields.im)
+        .replace("{zoom}", &fields.zoom)
+        .replace("{iter}", &fields.iter)
+        .replace("{date}", &fields.date);
+    sanitize_filename(&expanded)
+}
+
+// Replaces characters that are illegal (or awkward) on common filesystems and drops trailing
+// dots, which Windows strips, so expanded names are portable.
+pub fn sanitize_filename(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' | ' ' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    while sanitized.ends_with('.') {
+        sanitized.pop();
+    }
+    if sanitized.is_empty() {
+        sanitized = String::from("mandelbrot");
+    }
+    sanitized
+}
+
+// Gives `to` the contents of `from`, as a hard link where the file system allows one and as a
+// copy where it does not.
+pub fn link_or_copy(from: &Path, to: &Path) -> Result<(), String> {
+    if fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)
+        .map(|_| ())
+        .map_err(|err| err.to_string())
+}
+
+// First of `name.ext`, `name_1.ext`, `name_2.ext`, ... that does not exist yet.
+pub fn unique_path(dir: &Path, name: &str, extension: &str) -> PathBuf {
+    let mut path = dir.join(format!("{}.{}", name, extension));
+    let mut suffix = 1;
+    while path.exists() {
+        path = dir.join(format!("{}_{}.{}", name, suffix, extension));
+        suffix += 1;
+    }
+    path
+}
+
+pub fn timestamp() -> String {
+    let (year, month, day, time) = civil_time(unix_seconds());
+    format!(
+        "{:04}{:02}{:02}-{:02}{:02}{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+pub fn unix_seconds() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+// `seconds` since the epoch for people to read, in UTC.
+pub fn date_time(seconds: u64) -> String {
+    let (year, month, day, time) = civil_time(seconds);
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+// (year, month, day, seconds into the day) of `seconds` since the epoch.
+fn civil_time(seconds: u64) -> (i64, u32, u32, u64) {
+    let (year, month, day) = civil_from_days((seconds / 86_400) as i64);
+    (year, month, day, seconds % 86_400)
+}
+
+// Days since 1970-01-01 to a proleptic Gregorian (year, month, day).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+pub fn write_png(path: &Path, width: u32, height: u32, rgba: &[u8]) -> Result<(), String> {
+    write_png_with_text(path, width, height, rgba, &[])
+}
+
+// `write_png`, with (keyword, text) pairs stored as uncompressed text chunks.
+pub fn write_png_with_text(
+    path: &Path,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    text: &[(&str, String)],
+) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    encode_png(BufWriter::new(file), width, height, rgba, text)
+}
+
+// The PNG `write_png_with_text` writes, in memory.
+pub fn png_bytes(
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    text: &[(&str, String)],
+) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    encode_png(&mut bytes, width, height, rgba, text)?;
+    Ok(bytes)
+}
+
+fn encode_png(
+    out: impl Write,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+    text: &[(&str, String)],
+) -> Result<(), String> {
+    let mut encoder = png::Encoder::new(out, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    // Frames are colored in sRGB, and stay so under color management, which only converts what
+    // is shown.
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    for (keyword, text) in text {
+        encoder
+            .add_text_chunk(keyword.to_string(), text.clone())
+            .map_err(|err| err.to_string())?;
+    }
+    let mut writer = encoder.write_header().map_err(|err| err.to_string())?;
+    writer.write_image_data(rgba).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use super::*;
+
+    fn fields(re: &str, im: &str, zoom: &str) -> TemplateFields {
+        TemplateFields {
+            fractal: String::from("Mandelbrot"),
+            re: String::from(re),
+            im: String::from(im),
+            zoom: String::from(zoom),
+            iter: String::from("5000"),
+            date: String::from("20260115-093000"),
+        }
+    }
+
+    // Letters, digits and the punctuation every common file system takes in names.
+    fn portable(name: &str) -> bool {
+        !name.is_empty()
+            && !name.ends_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '+'))
+    }
+
+    #[test]
+    fn coordinates_expand_to_portable_names() {
+        let cases = [
+            (
+                fields("-0.743643887", "0.131825904", "2.500e5"),
+                "Mandelbrot_-0.743643887_0.131825904_2.500e5_5000_20260115-093000",
+            ),
+            (
+                fields("-1.5e-300", "2.25E+12", "1.000e-3"),
+                "Mandelbrot_-1.5e-300_2.25E+12_1.000e-3_5000_20260115-093000",
+            ),
+            (
+                fields(
+                    "-0.7436438870371587047521915061147743",
+                    "-0.1318259043091895899109623152766088",
+                    "3.163e31",
+                ),
+                "Mandelbrot_-0.7436438870371587047521915061147743_\
+                 -0.1318259043091895899109623152766088_3.163e31_5000_20260115-093000",
+            ),
+        ];
+        for (fields, expected) in cases {
+            let name = expand_template(DEFAULT_TEMPLATE, &fields);
+            assert_eq!(name, expected);
+            assert!(portable(&name), "{:?} is not portable", name);
+        }
+    }
+
+    #[test]
+    fn illegal_characters_are_replaced() {
+        let fields = TemplateFields {
+            fractal: String::from("Burning Ship"),
+            ..fields("-1.75", "-0.03", "1.000e2")
+        };
+        let name = expand_template("{fractal}: {re}/{im}\\<{zoom}>|*?\"\t{iter}.", &fields);
+        assert_eq!(name, "Burning_Ship__-1.75_-0.03__1.000e2______5000");
+        assert!(portable(&name), "{:?} is not portable", name);
+        assert_eq!(sanitize_filename("..."), "mandelbrot");
+        assert_eq!(sanitize_filename(""), "mandelbrot");
+    }
+
+    #[test]
+    fn collisions_get_the_next_free_number() {
+        let dir = env::temp_dir().join(format!("mandelbrot-unique-{}", process::id()));
+        fs::create_dir_all(&dir).expect("creates a scratch directory");
+        assert_eq!(unique_path(&dir, "view", "png"), dir.join("view.png"));
+        for taken in [
+            "view.png",
+            "view_1.png",
+            "view_2.png",
+            "other_3.png",
+            "view_4.png",
+        ] {
+            File::create(dir.join(taken)).expect("creates a file in the scratch directory");
+        }
+        assert_eq!(unique_path(&dir, "view", "png"), dir.join("view_3.png"));
+        assert_eq!(unique_path(&dir, "view", "json"), dir.join("view.json"));
+        fs::remove_dir_all(&dir).expect("removes the scratch directory");
+    }
+
+    #[test]
+    fn duplicates_get_the_contents_of_the_original() {
+        let dir = env::temp_dir().join(format!("mandelbrot-dedup-{}", process::id()));
+        fs::create_dir_all(&dir).expect("creates a scratch directory");
+        let (original, link) = (dir.join("a.png"), dir.join("b.png"));
+        fs::write(&original, b"image").expect("writes a file in the scratch directory");
+        link_or_copy(&original, &link).expect("links or copies a file");
+        assert_eq!(fs::read(&link).expect("reads the duplicate"), b"image");
+        fs::remove_dir_all(&dir).expect("removes the scratch directory");
+    }
+}
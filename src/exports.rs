@@ -0,0 +1,102 @@
+use iced::Size;
+
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use threadpool::ThreadPool;
+
+use mandelbrot::coloring;
+use mandelbrot::export;
+use mandelbrot::look::Look;
+use mandelbrot::queue::{ExportQueue, ExportSpec};
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams, Progress};
+use mandelbrot::storage::Precision;
+use mandelbrot::store;
+use mandelbrot::tiling::Focus;
+
+use crate::PROGRESS_INTERVAL;
+
+pub fn path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("mandelbrot").join("exports.toml"))
+}
+
+pub fn load() -> ExportQueue {
+    path().map_or_else(ExportQueue::default, |path| load_from(&path))
+}
+
+pub fn load_from(path: &Path) -> ExportQueue {
+    let parse = |contents: &str| toml::from_str(contents).map_err(|err| err.to_string());
+    store::load(path, "export queue", parse).unwrap_or_default()
+}
+
+pub fn save(queue: &ExportQueue) {
+    if let Some(path) = path() {
+        save_to(queue, &path);
+    }
+}
+
+pub fn save_to(queue: &ExportQueue, path: &Path) {
+    let result = toml::to_string_pretty(queue)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| store::save(path, &contents));
+    if let Err(err) = result {
+        println!("failed to save export queue {}: {}", path.display(), err);
+    }
+}
+
+// Renders, colors and writes one export, reporting the fraction done now and then. Returns the
+// file written, or `None` when `write` is off.
+pub fn run(
+    pool: &ThreadPool,
+    spec: &ExportSpec,
+    cancel: &CancelToken,
+    write: bool,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<Option<PathBuf>, String> {
+    let size = Size::new(spec.width as f32, spec.height as f32);
+    let params = FrameParams {
+        viewport: spec.viewport,
+        max_iterations: spec.max_iterations,
+        fractal: spec.fractal,
+        angle: spec.coloring.mode.angle(),
+        precision: Precision::Full,
+        coarse_prepass: spec.coarse_prepass,
+        arithmetic: Arithmetic::select(&spec.viewport, size, spec.double_double),
+    };
+    let mut last_progress = Instant::now();
+    let buffer = render::render_focused(pool, size, params, cancel, &Focus::default(), |update| {
+        if last_progress.elapsed() >= PROGRESS_INTERVAL {
+            last_progress = Instant::now();
+            on_progress(update.progress);
+        }
+    })
+    .map_err(|err| err.to_string())?;
+    if !write {
+        return Ok(None);
+    }
+    let factor = spec.antialias.max(1) as usize;
+    let mut rgba = coloring::recolor(&buffer, &spec.coloring, pool.max_count());
+    if factor > 1 {
+        rgba = coloring::downsample(&rgba, buffer.width, buffer.height, factor);
+    }
+    let (width, height) = (
+        (buffer.width / factor) as u32,
+        (buffer.height / factor) as u32,
+    );
+    let path = export::unique_path(&spec.dir, &spec.name, "png");
+    export::write_png_with_text(
+        &path,
+        width,
+        height,
+        &rgba,
+        &Look::of(&spec.coloring).png_text(),
+    )?;
+    Ok(Some(path))
+}
+
+// Writes `spec`'s file as a link to or copy of `original`, an image it would render the same.
+pub fn reuse(original: &Path, spec: &ExportSpec) -> Result<PathBuf, String> {
+    let path = export::unique_path(&spec.dir, &spec.name, "png");
+    export::link_or_copy(original, &path)?;
+    Ok(path)
+}
@@ -0,0 +1,369 @@
+// Arithmetic expressions over named per-pixel variables, such as `0.5 + 0.5 * sin(smooth / 4)`:
+// numbers, variables, `+ - * / ^` with the usual precedence, `^` binding right to left and
+// tighter than a leading minus, parentheses and a fixed set of functions. Everything is f64, and
+// errors name the column they were found at.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Power,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Log,
+    Sqrt,
+    Abs,
+    Floor,
+    Fract,
+    Min,
+    Max,
+    Pow,
+    Clamp,
+    Mix,
+}
+
+impl Function {
+    const ALL: [Function; 14] = [
+        Function::Sin,
+        Function::Cos,
+        Function::Tan,
+        Function::Exp,
+        Function::Log,
+        Function::Sqrt,
+        Function::Abs,
+        Function::Floor,
+        Function::Fract,
+        Function::Min,
+        Function::Max,
+        Function::Pow,
+        Function::Clamp,
+        Function::Mix,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Exp => "exp",
+            Function::Log => "log",
+            Function::Sqrt => "sqrt",
+            Function::Abs => "abs",
+            Function::Floor => "floor",
+            Function::Fract => "fract",
+            Function::Min => "min",
+            Function::Max => "max",
+            Function::Pow => "pow",
+            Function::Clamp => "clamp",
+            Function::Mix => "mix",
+        }
+    }
+
+    fn arity(self) -> usize {
+        match self {
+            Function::Min | Function::Max | Function::Pow => 2,
+            Function::Clamp | Function::Mix => 3,
+            _ => 1,
+        }
+    }
+
+    fn call(self, args: &[f64]) -> f64 {
+        match self {
+            Function::Sin => args[0].sin(),
+            Function::Cos => args[0].cos(),
+            Function::Tan => args[0].tan(),
+            Function::Exp => args[0].exp(),
+            Function::Log => args[0].ln(),
+            Function::Sqrt => args[0].sqrt(),
+            Function::Abs => args[0].abs(),
+            Function::Floor => args[0].floor(),
+            Function::Fract => args[0] - args[0].floor(),
+            Function::Min => args[0].min(args[1]),
+            Function::Max => args[0].max(args[1]),
+            Function::Pow => args[0].powf(args[1]),
+            Function::Clamp => args[0].max(args[1]).min(args[2]),
+            Function::Mix => args[0] + (args[1] - args[0]) * args[2],
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Number(f64),
+    // An index into the expression's variables.
+    Variable(usize),
+    Negate(Box<Node>),
+    Binary(Operator, Box<Node>, Box<Node>),
+    Call(Function, Vec<Node>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Expr {
+    root: Node,
+    variables: Vec<String>,
+}
+
+impl Expr {
+    pub fn parse(source: &str) -> Result<Expr, String> {
+        let mut parser = Parser {
+            chars: source.chars().collect(),
+            at: 0,
+            variables: Vec::new(),
+        };
+        let root = parser.sum()?;
+        parser.skip_space();
+        if parser.at < parser.chars.len() {
+            return Err(parser.error("unexpected characters"));
+        }
+        Ok(Expr {
+            root,
+            variables: parser.variables,
+        })
+    }
+
+    // The variables the expression reads, in the order `eval` takes their values.
+    pub fn variables(&self) -> &[String] {
+        &self.variables
+    }
+
+    pub fn eval(&self, values: &[f64]) -> f64 {
+        eval(&self.root, values)
+    }
+}
+
+fn eval(node: &Node, values: &[f64]) -> f64 {
+    match node {
+        Node::Number(number) => *number,
+        Node::Variable(index) => values[*index],
+        Node::Negate(operand) => -eval(operand, values),
+        Node::Binary(operator, left, right) => {
+            let (left, right) = (eval(left, values), eval(right, values));
+            match operator {
+                Operator::Add => left + right,
+                Operator::Subtract => left - right,
+                Operator::Multiply => left * right,
+                Operator::Divide => left / right,
+                Operator::Power => left.powf(right),
+            }
+        }
+        Node::Call(function, args) => {
+            let args: Vec<f64> = args.iter().map(|arg| eval(arg, values)).collect();
+            function.call(&args)
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    at: usize,
+    variables: Vec<String>,
+}
+
+impl Parser {
+    fn error(&self, message: &str) -> String {
+        format!("at column {}: {}", self.at + 1, message)
+    }
+
+    fn skip_space(&mut self) {
+        while self.chars.get(self.at).is_some_and(|c| c.is_whitespace()) {
+            self.at += 1;
+        }
+    }
+
+    // The next character that is not a space, consumed if it is `wanted`.
+    fn eat(&mut self, wanted: char) -> bool {
+        self.skip_space();
+        let found = self.chars.get(self.at) == Some(&wanted);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn sum(&mut self) -> Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let operator = if self.eat('+') {
+                Operator::Add
+            } else if self.eat('-') {
+                Operator::Subtract
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let operator = if self.eat('*') {
+                Operator::Multiply
+            } else if self.eat('/') {
+                Operator::Divide
+            } else {
+                return Ok(node);
+            };
+            node = Node::Binary(operator, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        if self.eat('-') {
+            return Ok(Node::Negate(Box::new(self.unary()?)));
+        }
+        let base = self.atom()?;
+        if self.eat('^') {
+            return Ok(Node::Binary(
+                Operator::Power,
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Node, String> {
+        self.skip_space();
+        let start = self.at;
+        match self.chars.get(self.at) {
+            None => Err(self.error("expected a value")),
+            Some('(') => {
+                self.at += 1;
+                let node = self.sum()?;
+                if !self.eat(')') {
+                    return Err(self.error("expected `)`"));
+                }
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || *c == '.' => {
+                self.take_while(|c| c.is_ascii_digit() || c == '.');
+                // An exponent, as in 1e-3.
+                if matches!(self.chars.get(self.at), Some('e' | 'E')) {
+                    let mantissa_end = self.at;
+                    self.at += 1;
+                    if matches!(self.chars.get(self.at), Some('+' | '-')) {
+                        self.at += 1;
+                    }
+                    if self.take_while(|c| c.is_ascii_digit()) == 0 {
+                        self.at = mantissa_end;
+                    }
+                }
+                let text: String = self.chars[start..self.at].iter().collect();
+                text.parse().map(Node::Number).map_err(|_| {
+                    self.at = start;
+                    self.error(&format!("`{}` is not a number", text))
+                })
+            }
+            Some(c) if c.is_alphabetic() || *c == '_' => {
+                self.take_while(|c| c.is_alphanumeric() || c == '_');
+                let name: String = self.chars[start..self.at].iter().collect();
+                if name == "pi" {
+                    return Ok(Node::Number(std::f64::consts::PI));
+                }
+                if !self.eat('(') {
+                    let index = match self.variables.iter().position(|known| *known == name) {
+                        Some(index) => index,
+                        None => {
+                            self.variables.push(name);
+                            self.variables.len() - 1
+                        }
+                    };
+                    return Ok(Node::Variable(index));
+                }
+                let Some(function) = Function::ALL.into_iter().find(|f| f.name() == name) else {
+                    self.at = start;
+                    return Err(self.error(&format!("unknown function `{}`", name)));
+                };
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.sum()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        if !self.eat(',') {
+                            return Err(self.error("expected `,` or `)`"));
+                        }
+                    }
+                }
+                if args.len() != function.arity() {
+                    self.at = start;
+                    return Err(self.error(&format!(
+                        "`{}` takes {} argument{}, not {}",
+                        name,
+                        function.arity(),
+                        if function.arity() == 1 { "" } else { "s" },
+                        args.len()
+                    )));
+                }
+                Ok(Node::Call(function, args))
+            }
+            Some(c) => Err(self.error(&format!("unexpected `{}`", c))),
+        }
+    }
+
+    // Consumes characters while `accept` holds, returning how many.
+    fn take_while(&mut self, accept: impl Fn(char) -> bool) -> usize {
+        let start = self.at;
+        while self.chars.get(self.at).is_some_and(|c| accept(*c)) {
+            self.at += 1;
+        }
+        self.at - start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Evaluates `source` with x = 5 and y = 0.5.
+    fn eval(source: &str) -> Result<f64, String> {
+        let expr = Expr::parse(source)?;
+        let values: Vec<f64> = expr
+            .variables()
+            .iter()
+            .map(|name| match name.as_str() {
+                "x" => 5.0,
+                "y" => 0.5,
+                _ => 0.0,
+            })
+            .collect();
+        Ok(expr.eval(&values))
+    }
+
+    #[test]
+    fn expressions_evaluate_with_the_usual_precedence() {
+        let cases = [
+            ("1 + 2 * 3", 7.0),
+            ("(1 + 2) * 3", 9.0),
+            ("-2 ^ 2", -4.0),
+            ("2 ^ 3 ^ 2", 512.0),
+            ("max(1, x) + min(2, 3)", 7.0),
+            ("mix(0, 10, y / 2)", 2.5),
+            ("clamp(x, 0, 1) - fract(1.75)", 0.25),
+            ("1e-3 * 1000 / y", 2.0),
+            ("cos(pi)", -1.0),
+        ];
+        for (source, expected) in cases {
+            let value = eval(source);
+            assert!(
+                value
+                    .as_ref()
+                    .is_ok_and(|value| (value - expected).abs() <= 1e-12),
+                "{} is {:?}, not {}",
+                source,
+                value,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,665 @@
+use num::complex::Complex;
+
+use serde::{Deserialize, Serialize};
+
+use std::f64::consts::{PI, TAU};
+
+use crate::doubledouble::DoubleDouble;
+use crate::render::{PixelClass, Proof};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sample {
+    pub value: f32,
+    pub angle: f32,
+}
+
+impl Sample {
+    pub const UNRESOLVED: Sample = Sample {
+        value: crate::render::UNRESOLVED,
+        angle: 0.0,
+    };
+
+    pub fn interior(proof: Proof) -> Sample {
+        Sample {
+            value: PixelClass::Interior(proof).value(),
+            angle: 0.0,
+        }
+    }
+}
+
+// How far inside the cardioid or bulb a point must be, in the squared units the tests compare,
+// before it counts as proven, so that rounding in the test never claims a point just outside.
+const SHAPE_MARGIN: f64 = 1e-12;
+
+// Whether the Mandelbrot set's main cardioid or period-2 bulb holds `c`, which saves iterating
+// the two largest parts of the set.
+fn in_known_shape(re: f64, im: f64) -> Option<Proof> {
+    let im_squared = im * im;
+    let shifted = re - 0.25;
+    let q = shifted * shifted + im_squared;
+    if q * (q + shifted) < im_squared * 0.25 - SHAPE_MARGIN {
+        return Some(Proof::Cardioid);
+    }
+    if (re + 1.0) * (re + 1.0) + im_squared < 0.0625 - SHAPE_MARGIN {
+        return Some(Proof::Bulb);
+    }
+    None
+}
+
+// What a sample's angle holds for an escaped point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum AngleKind {
+    // Nothing; the angle is 0.
+    #[default]
+    Off,
+    // The external angle, estimated along the orbit, for field lines.
+    External,
+    // The argument of z where the orbit escaped.
+    Escape,
+}
+
+impl AngleKind {
+    pub fn tracked(self) -> bool {
+        self != AngleKind::Off
+    }
+}
+
+pub trait Fractal: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    // (re_min, re_max, im_min, im_max) framed by the Home view.
+    fn bounds(&self) -> (f64, f64, f64, f64);
+
+    fn iterate(&self, c: Complex<f64>, max_iterations: u32, angle: AngleKind) -> Sample;
+
+    // `iterate` in double-double arithmetic, for views too narrow for f64 to separate pixels.
+    fn iterate_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iterations: u32,
+        angle: AngleKind,
+    ) -> Sample;
+}
+
+// Estimates the external angle by unwrapping arg(z) through the doubling map: each step's
+// deviation from doubling the previous argument contributes with half the weight of the last.
+#[derive(Default)]
+struct AngleTracker {
+    angle: f64,
+    last_arg: f64,
+    weight: f64,
+}
+
+impl AngleTracker {
+    fn push(&mut self, z: Complex<f64>) {
+        if self.weight == 0.0 {
+            self.last_arg = z.im.atan2(z.re);
+            self.angle = self.last_arg;
+            self.weight = 1.0;
+            return;
+        }
+        // Later terms fall below f64 precision, so skip the atan2.
+        if self.weight < f64::EPSILON {
+            return;
+        }
+        let arg = z.im.atan2(z.re);
+        let mut delta = (arg - 2.0 * self.last_arg) % TAU;
+        if delta > PI {
+            delta -= TAU;
+        } else if delta <= -PI {
+            delta += TAU;
+        }
+        self.weight *= 0.5;
+        self.angle += delta * self.weight;
+        self.last_arg = arg;
+    }
+
+    // Fraction of a full turn in [0, 1).
+    fn turns(&self) -> f32 {
+        (self.angle / TAU).rem_euclid(1.0) as f32
+    }
+}
+
+// The angle of a sample that escaped at `re + im i`, in turns.
+fn escape_angle(kind: AngleKind, tracker: &AngleTracker, re: f64, im: f64) -> f32 {
+    match kind {
+        AngleKind::Off => 0.0,
+        AngleKind::External => tracker.turns(),
+        AngleKind::Escape => (im.atan2(re) / TAU).rem_euclid(1.0) as f32,
+    }
+}
+
+// Saved as its name, with a hybrid's steps in brackets, so that it can key the per-fractal
+// settings in the config file.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(try_from = "String", into = "String")]
+pub enum FractalKind {
+    #[default]
+    Mandelbrot,
+    BurningShip,
+    Hybrid(Hybrid),
+}
+
+impl FractalKind {
+    pub const ALL: [FractalKind; 3] = [
+        FractalKind::Mandelbrot,
+        FractalKind::BurningShip,
+        FractalKind::Hybrid(Hybrid::DEFAULT),
+    ];
+
+    pub fn fractal(&self) -> &dyn Fractal {
+        match self {
+            FractalKind::Mandelbrot => &Mandelbrot,
+            FractalKind::BurningShip => &BurningShip,
+            FractalKind::Hybrid(hybrid) => hybrid,
+        }
+    }
+
+    // The entry of `ALL` this is one of: every hybrid shares one, whatever its steps.
+    pub fn family(self) -> FractalKind {
+        match self {
+            FractalKind::Hybrid(_) => FractalKind::Hybrid(Hybrid::DEFAULT),
+            kind => kind,
+        }
+    }
+
+    pub fn next(self) -> FractalKind {
+        let index = FractalKind::ALL
+            .iter()
+            .position(|kind| *kind == self.family())
+            .unwrap_or(0);
+        FractalKind::ALL[(index + 1) % FractalKind::ALL.len()]
+    }
+
+    // Whether iteration `n` takes absolute values of z before squaring, as the Burning Ship does.
+    pub fn folds(&self, n: u32) -> bool {
+        match self {
+            FractalKind::Mandelbrot => false,
+            FractalKind::BurningShip => true,
+            FractalKind::Hybrid(hybrid) => hybrid.rule(n) == Rule::BurningShip,
+        }
+    }
+}
+
+impl From<FractalKind> for String {
+    fn from(kind: FractalKind) -> String {
+        match kind {
+            FractalKind::Mandelbrot => String::from("Mandelbrot"),
+            FractalKind::BurningShip => String::from("BurningShip"),
+            FractalKind::Hybrid(hybrid) => format!("Hybrid({})", hybrid.describe()),
+        }
+    }
+}
+
+impl TryFrom<String> for FractalKind {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<FractalKind, String> {
+        match name.as_str() {
+            "Mandelbrot" => return Ok(FractalKind::Mandelbrot),
+            "BurningShip" => return Ok(FractalKind::BurningShip),
+            _ => {}
+        }
+        let steps = name
+            .strip_prefix("Hybrid(")
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| format!("unknown fractal \"{}\"", name))?;
+        let rules = steps
+            .split(',')
+            .map(|step| match step.trim() {
+                "Mandelbrot" => Ok(Rule::Mandelbrot),
+                "BurningShip" => Ok(Rule::BurningShip),
+                other => Err(format!("unknown hybrid step \"{}\"", other)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Hybrid::new(&rules).map(FractalKind::Hybrid)
+    }
+}
+
+// One of the built-in iteration rules a hybrid step can take.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Rule {
+    #[default]
+    Mandelbrot,
+    BurningShip,
+}
+
+impl Rule {
+    pub fn name(self) -> &'static str {
+        match self {
+            Rule::Mandelbrot => "Mandelbrot",
+            Rule::BurningShip => "BurningShip",
+        }
+    }
+
+    pub fn next(self) -> Rule {
+        match self {
+            Rule::Mandelbrot => Rule::BurningShip,
+            Rule::BurningShip => Rule::Mandelbrot,
+        }
+    }
+}
+
+pub const MIN_HYBRID_STEPS: usize = 2;
+pub const MAX_HYBRID_STEPS: usize = 8;
+
+// A fractal that takes its iteration rules in turn, cycling through `steps` from the first. Every
+// rule squares z, so escape counts, and the colorings built on them, mean what they do for the
+// built-in fractals; were rules of other powers added, the coloring would go by the dominant one.
+// Kept in a fixed array so that it stays `Copy` like the rest of the render parameters; the slots
+// past `len` are always the default rule, so that equal sequences compare equal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Rule>", into = "Vec<Rule>")]
+pub struct Hybrid {
+    steps: [Rule; MAX_HYBRID_STEPS],
+    len: u8,
+}
+
+impl Hybrid {
+    // Two Mandelbrot steps then one Burning Ship step.
+    pub const DEFAULT: Hybrid = Hybrid {
+        steps: [
+            Rule::Mandelbrot,
+            Rule::Mandelbrot,
+            Rule::BurningShip,
+            Rule::Mandelbrot,
+            Rule::Mandelbrot,
+            Rule::Mandelbrot,
+            Rule::Mandelbrot,
+            Rule::Mandelbrot,
+        ],
+        len: 3,
+    };
+
+    pub fn new(rules: &[Rule]) -> Result<Hybrid, String> {
+        if !(MIN_HYBRID_STEPS..=MAX_HYBRID_STEPS).contains(&rules.len()) {
+            return Err(format!(
+                "a hybrid takes {} to {} steps, not {}",
+                MIN_HYBRID_STEPS,
+                MAX_HYBRID_STEPS,
+                rules.len()
+            ));
+        }
+        let mut steps = [Rule::default(); MAX_HYBRID_STEPS];
+        steps[..rules.len()].copy_from_slice(rules);
+        Ok(Hybrid {
+            steps,
+            len: rules.len() as u8,
+        })
+    }
+
+    pub fn steps(&self) -> &[Rule] {
+        &self.steps[..self.len as usize]
+    }
+
+    // The rule iteration `n` takes.
+    pub fn rule(&self, n: u32) -> Rule {
+        self.steps[(n % self.len as u32) as usize]
+    }
+
+    pub fn describe(&self) -> String {
+        self.steps()
+            .iter()
+            .map(|rule| rule.name())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl From<Hybrid> for Vec<Rule> {
+    fn from(hybrid: Hybrid) -> Vec<Rule> {
+        hybrid.steps().to_vec()
+    }
+}
+
+impl TryFrom<Vec<Rule>> for Hybrid {
+    type Error = String;
+
+    fn try_from(rules: Vec<Rule>) -> Result<Hybrid, String> {
+        Hybrid::new(&rules)
+    }
+}
+
+impl Default for Hybrid {
+    fn default() -> Hybrid {
+        Hybrid::DEFAULT
+    }
+}
+
+pub struct Mandelbrot;
+
+impl Fractal for Mandelbrot {
+    fn name(&self) -> &'static str {
+        "Mandelbrot"
+    }
+
+    fn bounds(&self) -> (f64, f64, f64, f64) {
+        (-2.05, 0.65, -1.2, 1.2)
+    }
+
+    fn iterate(&self, c: Complex<f64>, max_iterations: u32, angle: AngleKind) -> Sample {
+        if let Some(proof) = in_known_shape(c.re, c.im) {
+            return Sample::interior(proof);
+        }
+        iterate(c, max_iterations, angle, |_| false, 1)
+    }
+
+    fn iterate_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iterations: u32,
+        angle: AngleKind,
+    ) -> Sample {
+        // The shape tests' margin is far wider than the low parts, so the high parts suffice.
+        if let Some(proof) = in_known_shape(c.0.hi, c.1.hi) {
+            return Sample::interior(proof);
+        }
+        iterate_dd(c, max_iterations, angle, |_| false, 1)
+    }
+}
+
+pub struct BurningShip;
+
+impl Fractal for BurningShip {
+    fn name(&self) -> &'static str {
+        "Burning Ship"
+    }
+
+    fn bounds(&self) -> (f64, f64, f64, f64) {
+        (-2.2, 1.3, -1.9, 0.9)
+    }
+
+    fn iterate(&self, c: Complex<f64>, max_iterations: u32, angle: AngleKind) -> Sample {
+        iterate(c, max_iterations, angle, |_| true, 1)
+    }
+
+    fn iterate_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iterations: u32,
+        angle: AngleKind,
+    ) -> Sample {
+        iterate_dd(c, max_iterations, angle, |_| true, 1)
+    }
+}
+
+impl Fractal for Hybrid {
+    fn name(&self) -> &'static str {
+        "Hybrid"
+    }
+
+    // Both built-in fractals' Home views at once.
+    fn bounds(&self) -> (f64, f64, f64, f64) {
+        (-2.2, 1.3, -1.9, 1.2)
+    }
+
+    fn iterate(&self, c: Complex<f64>, max_iterations: u32, angle: AngleKind) -> Sample {
+        let fold = |n| self.rule(n) == Rule::BurningShip;
+        iterate(c, max_iterations, angle, fold, self.len as u32)
+    }
+
+    fn iterate_dd(
+        &self,
+        c: (DoubleDouble, DoubleDouble),
+        max_iterations: u32,
+        angle: AngleKind,
+    ) -> Sample {
+        let fold = |n| self.rule(n) == Rule::BurningShip;
+        iterate_dd(c, max_iterations, angle, fold, self.len as u32)
+    }
+}
+
+// The escape loop shared by all fractals in f64; iteration n takes absolute values of z first,
+// as the Burning Ship does, where `fold(n)` says so, and the rules repeat every `period` steps.
+// The squares of z are kept for the next step and the bailout compares them against 4, saving the
+// square root of `norm`. Rounding matches `reference_iterate` exactly: folding only flips signs
+// and 2 * re * im is what `Complex` gets from re * im + im * re.
+fn iterate(
+    c: Complex<f64>,
+    max_iterations: u32,
+    angle: AngleKind,
+    fold: impl Fn(u32) -> bool,
+    period: u32,
+) -> Sample {
+    let (mut re, mut im) = (0.0f64, 0.0f64);
+    let (mut re_squared, mut im_squared) = (0.0f64, 0.0f64);
+    let mut tracker = AngleTracker::default();
+    // Brent's cycle check: z is saved at every power of two and each later z compared with it
+    // exactly, so a repeat is certain and the orbit will cycle through it forever. A repeat only
+    // counts a whole number of rule periods after the save, where the same rules follow.
+    let (mut saved_re, mut saved_im) = (re, im);
+    let mut saved_at = 0;
+    let mut next_save = 1;
+    for n in 0..max_iterations {
+        let product = if fold(n) { (re * im).abs() } else { re * im };
+        im = product * 2.0 + c.im;
+        re = re_squared - im_squared + c.re;
+        re_squared = re * re;
+        im_squared = im * im;
+        if angle == AngleKind::External {
+            tracker.push(Complex::new(re, im));
+        }
+        if re_squared + im_squared >= 4.0 {
+            return Sample {
+                value: n as f32,
+                angle: escape_angle(angle, &tracker, re, im),
+            };
+        }
+        if re == saved_re && im == saved_im && (n + 1 - saved_at).is_multiple_of(period) {
+            return Sample::interior(Proof::Periodic);
+        }
+        if n + 1 == next_save {
+            (saved_re, saved_im) = (re, im);
+            saved_at = n + 1;
+            next_save *= 2;
+        }
+    }
+    Sample::UNRESOLVED
+}
+
+// `Fractal::iterate` written plainly with `Complex`, the kernel as it was before it was tuned.
+// The checksum run compares the two over a dense grid. It proves nothing, leaving every point
+// that does not escape unresolved.
+pub fn reference_iterate(
+    kind: FractalKind,
+    c: Complex<f64>,
+    max_iterations: u32,
+    angle: AngleKind,
+) -> Sample {
+    let mut z: Complex<f64> = Complex::new(0.0, 0.0);
+    let mut tracker = AngleTracker::default();
+    for n in 0..max_iterations {
+        if kind.folds(n) {
+            z = Complex::new(z.re.abs(), z.im.abs());
+        }
+        z = z * z + c;
+        if angle == AngleKind::External {
+            tracker.push(z);
+        }
+        if z.norm() >= 2.0 {
+            return Sample {
+                value: n as f32,
+                angle: escape_angle(angle, &tracker, z.re, z.im),
+            };
+        }
+    }
+    Sample::UNRESOLVED
+}
+
+// `iterate` in double-double.
+fn iterate_dd(
+    c: (DoubleDouble, DoubleDouble),
+    max_iterations: u32,
+    angle: AngleKind,
+    fold: impl Fn(u32) -> bool,
+    period: u32,
+) -> Sample {
+    let (c_re, c_im) = c;
+    let (mut re, mut im) = (DoubleDouble::ZERO, DoubleDouble::ZERO);
+    let mut tracker = AngleTracker::default();
+    let (mut saved_re, mut saved_im) = (re, im);
+    let mut saved_at = 0;
+    let mut next_save = 1;
+    for n in 0..max_iterations {
+        if fold(n) {
+            re = re.abs();
+            im = im.abs();
+        }
+        let re_squared = re.sqr();
+        let im_squared = im.sqr();
+        im = (re * im).mul_f64(2.0) + c_im;
+        re = re_squared - im_squared + c_re;
+        if angle == AngleKind::External {
+            tracker.push(Complex::new(re.hi, im.hi));
+        }
+        // Escaping is decided far above the precision limit, so the high parts suffice.
+        if re.hi * re.hi + im.hi * im.hi >= 4.0 {
+            return Sample {
+                value: n as f32,
+                angle: escape_angle(angle, &tracker, re.hi, im.hi),
+            };
+        }
+        if re == saved_re && im == saved_im && (n + 1 - saved_at).is_multiple_of(period) {
+            return Sample::interior(Proof::Periodic);
+        }
+        if n + 1 == next_save {
+            (saved_re, saved_im) = (re, im);
+            saved_at = n + 1;
+            next_save *= 2;
+        }
+    }
+    Sample::UNRESOLVED
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use iced::Size;
+
+    use super::*;
+    use crate::rawdata;
+    use crate::render::{self, Arithmetic, FrameParams};
+    use crate::stats;
+    use crate::storage::Precision;
+    use crate::viewport::Viewport;
+
+    const M: Rule = Rule::Mandelbrot;
+    const B: Rule = Rule::BurningShip;
+
+    fn hybrid() -> Hybrid {
+        Hybrid::new(&[B, M, B, B, M]).expect("five steps are within the bounds")
+    }
+
+    #[test]
+    fn hybrids_take_between_the_bounds_of_steps() {
+        for len in [
+            0,
+            1,
+            MIN_HYBRID_STEPS,
+            MAX_HYBRID_STEPS,
+            MAX_HYBRID_STEPS + 1,
+        ] {
+            assert_eq!(
+                Hybrid::new(&vec![M; len]).is_ok(),
+                (MIN_HYBRID_STEPS..=MAX_HYBRID_STEPS).contains(&len),
+                "a hybrid of {} steps",
+                len
+            );
+        }
+        let hybrid = hybrid();
+        assert_eq!(hybrid.steps(), [B, M, B, B, M]);
+        for n in 0..10 {
+            assert_eq!(hybrid.rule(n), hybrid.steps()[n as usize % 5]);
+        }
+    }
+
+    #[test]
+    fn hybrids_are_one_entry_among_the_fractals() {
+        let kind = FractalKind::Hybrid(hybrid());
+        assert_eq!(kind.family(), FractalKind::ALL[2]);
+        assert_eq!(kind.next(), FractalKind::Mandelbrot);
+        assert_eq!(FractalKind::BurningShip.next().family(), kind.family());
+    }
+
+    // As a value and as a key, the way bookmarks and the config file save it.
+    #[test]
+    fn hybrids_survive_saving() {
+        let kind = FractalKind::Hybrid(hybrid());
+        let name = "Hybrid(BurningShip, Mandelbrot, BurningShip, BurningShip, Mandelbrot)";
+        assert_eq!(
+            toml::Value::try_from(kind).ok(),
+            Some(toml::Value::String(String::from(name)))
+        );
+        let states: BTreeMap<FractalKind, u32> = FractalKind::ALL
+            .into_iter()
+            .chain([kind])
+            .zip(0..)
+            .collect();
+        let text = toml::to_string(&states).expect("fractal keys serialize");
+        let read: BTreeMap<FractalKind, u32> =
+            toml::from_str(&text).expect("saved fractal keys read back");
+        assert_eq!(read, states);
+        for name in [
+            "Hybrid(Mandelbrot)",
+            "Hybrid(Mandelbrot, Julia)",
+            "Hybrid Mandelbrot",
+            "Julia",
+        ] {
+            let read = FractalKind::try_from(name.to_string());
+            assert!(read.is_err(), "{:?} is read as {:?}", name, read);
+        }
+    }
+
+    #[test]
+    fn hybrid_frames_keep_their_fractal_in_raw_data() {
+        let kind = FractalKind::Hybrid(hybrid());
+        let params = FrameParams {
+            viewport: Viewport::new(-0.4, 0.0, 3.2),
+            max_iterations: 200,
+            fractal: kind,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: false,
+            arithmetic: Arithmetic::F64,
+        };
+        let buffer = stats::render_frame(Size::new(48.0, 32.0), params);
+        let read = rawdata::to_bytes(&buffer)
+            .and_then(|bytes| rawdata::parse(&bytes))
+            .expect("raw data reads back");
+        assert_eq!(read.params.map(|params| params.fractal), Some(kind));
+    }
+
+    // Proofs aside.
+    #[test]
+    fn a_hybrid_of_one_rule_is_that_fractal() {
+        let grid = Size::new(40.0, 30.0);
+        for (rule, single) in [(M, FractalKind::Mandelbrot), (B, FractalKind::BurningShip)] {
+            let same = Hybrid::new(&[rule; 3]).expect("three steps are within the bounds");
+            let viewport = Viewport::home(single, grid);
+            let (left, top) = viewport.top_left(grid);
+            let pixel_size = viewport.pixel_size(grid);
+            let differing = (0..grid.height as usize)
+                .flat_map(|y| (0..grid.width as usize).map(move |x| (x, y)))
+                .filter(|&(x, y)| {
+                    let c = Complex::new(left + pixel_size * x as f64, top - pixel_size * y as f64);
+                    let escape = |kind: FractalKind| {
+                        let value = kind.fractal().iterate(c, 300, AngleKind::Off).value;
+                        render::escaped(value).then_some(value)
+                    };
+                    escape(FractalKind::Hybrid(same)) != escape(single)
+                })
+                .count();
+            assert_eq!(
+                differing,
+                0,
+                "a hybrid of {} steps only escapes differently",
+                rule.name()
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+// Bonus for a query character matched at the start of a word, so "gc" finds "Go to coordinates".
+const WORD_START: u32 = 8;
+// Bonus for a query character matched right after the previous one.
+const CONSECUTIVE: u32 = 4;
+
+// How well `query` matches `candidate`, higher being better: None unless the query's characters
+// all appear in the candidate in order, ignoring case and the query's spaces. Every character
+// matched scores one, plus bonuses at word starts and in runs, taking the best placement of them.
+pub fn score(query: &str, candidate: &str) -> Option<u32> {
+    let query: Vec<char> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+    if query.is_empty() {
+        return Some(0);
+    }
+    let candidate: Vec<char> = candidate
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+    let word_start = |index: usize| index == 0 || !candidate[index - 1].is_alphanumeric();
+
+    // The best score with the query so far matched and its last character at each position.
+    let mut best: Vec<Option<u32>> = vec![Some(0); candidate.len()];
+    for (position, &wanted) in query.iter().enumerate() {
+        let mut next = vec![None; candidate.len()];
+        // The best of `best` anywhere before the current position.
+        let mut before = None;
+        for index in 0..candidate.len() {
+            let previous = if position == 0 {
+                Some(0)
+            } else {
+                let adjacent = index
+                    .checked_sub(1)
+                    .and_then(|at| best[at])
+                    .map(|score| score + CONSECUTIVE);
+                before.max(adjacent)
+            };
+            if candidate[index] == wanted {
+                let bonus = if word_start(index) { 1 + WORD_START } else { 1 };
+                next[index] = previous.map(|score| score + bonus);
+            }
+            before = before.max(best[index]);
+        }
+        best = next;
+    }
+    best.into_iter().flatten().max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_match_titles_in_order() {
+        let matches = [
+            ("", "Reset view", true),
+            ("undo", "Undo", true),
+            ("UNDO", "undo", true),
+            ("gc", "Go to coordinates", true),
+            ("zoom in", "Zoom in", true),
+            ("xyz", "Undo", false),
+            ("oz", "Zoom out", false),
+            ("resetview", "Reset", false),
+        ];
+        for (query, title, expected) in matches {
+            assert_eq!(
+                score(query, title).is_some(),
+                expected,
+                "{:?} against {:?}",
+                query,
+                title
+            );
+        }
+    }
+
+    #[test]
+    fn better_placements_rank_higher() {
+        let rankings = [
+            // Word starts beat letters inside words.
+            ("gc", "Go to coordinates", "Toggle crosshair"),
+            // A run beats the same letters scattered.
+            ("set", "Toggle settings", "Select a region"),
+            // The best placement counts, not the first one found.
+            ("ex", "Next palette export", "Next palette"),
+        ];
+        for (query, better, worse) in rankings {
+            let (high, low) = (score(query, better), score(query, worse));
+            assert!(
+                high.is_some() && high > low,
+                "{:?} scores {:?} {:?} and {:?} {:?}",
+                query,
+                better,
+                high,
+                worse,
+                low
+            );
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+// Deflection below this is the stick at rest, since worn sticks rarely return exactly to center.
+pub const DEAD_ZONE: f32 = 0.15;
+// View widths per second a fully deflected stick pans by. Pans are in view widths, so they
+// feel the same at any depth.
+pub const PAN_SPEED: f64 = 0.8;
+// Factor per second the view narrows by at full zoom input.
+pub const ZOOM_SPEED: f64 = 2.0;
+
+// What the controller's sticks and triggers are read as. Stick values are in -1..=1, with up
+// positive; trigger values in 0..=1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadAxis {
+    PanX,
+    PanY,
+    // The right stick: up zooms in, down out.
+    Zoom,
+    ZoomIn,
+    ZoomOut,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PadButton {
+    NextPalette,
+    PreviousPalette,
+    Reset,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadInput {
+    Axis(PadAxis, f32),
+    Pressed(PadButton),
+    // A controller went away; whatever it held no longer counts.
+    Disconnected,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PadAction {
+    // View widths to move right and up.
+    Pan(f64, f64),
+    // Factor to scale the view width by.
+    Zoom(f64),
+    NextPalette,
+    PreviousPalette,
+    Reset,
+}
+
+// The sticks and triggers as last reported.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PadState {
+    pan: (f32, f32),
+    zoom: f32,
+    zoom_in: f32,
+    zoom_out: f32,
+}
+
+impl PadState {
+    // Records an input, returning the action a button press stands for.
+    pub fn apply(&mut self, input: PadInput) -> Option<PadAction> {
+        match input {
+            PadInput::Axis(axis, value) => {
+                let value = if value.is_finite() {
+                    value.clamp(-1.0, 1.0)
+                } else {
+                    0.0
+                };
+                match axis {
+                    PadAxis::PanX => self.pan.0 = value,
+                    PadAxis::PanY => self.pan.1 = value,
+                    PadAxis::Zoom => self.zoom = value,
+                    PadAxis::ZoomIn => self.zoom_in = value.max(0.0),
+                    PadAxis::ZoomOut => self.zoom_out = value.max(0.0),
+                }
+                None
+            }
+            PadInput::Pressed(PadButton::NextPalette) => Some(PadAction::NextPalette),
+            PadInput::Pressed(PadButton::PreviousPalette) => Some(PadAction::PreviousPalette),
+            PadInput::Pressed(PadButton::Reset) => Some(PadAction::Reset),
+            PadInput::Disconnected => {
+                *self = PadState::default();
+                None
+            }
+        }
+    }
+
+    // The pan and zoom that the sticks and triggers as held amount to over `elapsed`; nothing
+    // while they are all at rest.
+    pub fn motion(&self, elapsed: Duration) -> Vec<PadAction> {
+        let seconds = elapsed.as_secs_f64();
+        let mut actions = Vec::new();
+        let (x, y) = radial_dead_zone(self.pan.0, self.pan.1);
+        if x != 0.0 || y != 0.0 {
+            actions.push(PadAction::Pan(
+                x as f64 * PAN_SPEED * seconds,
+                y as f64 * PAN_SPEED * seconds,
+            ));
+        }
+        let zoom = dead_zone(self.zoom) + dead_zone(self.zoom_in) - dead_zone(self.zoom_out);
+        if zoom != 0.0 {
+            actions.push(PadAction::Zoom(ZOOM_SPEED.powf(-zoom as f64 * seconds)));
+        }
+        actions
+    }
+}
+
+// Rescales deflection past the dead zone to 0..=1, so motion starts from nothing at its edge
+// rather than jumping.
+pub fn dead_zone(value: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= DEAD_ZONE {
+        return 0.0;
+    }
+    value.signum() * ((magnitude - DEAD_ZONE) / (1.0 - DEAD_ZONE)).min(1.0)
+}
+
+// The dead zone applied to a stick's distance from center rather than each axis, so diagonals
+// are not snapped to the axes.
+pub fn radial_dead_zone(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = x.hypot(y);
+    if magnitude <= DEAD_ZONE {
+        return (0.0, 0.0);
+    }
+    let scale = dead_zone(magnitude.min(1.0)) / magnitude;
+    (x * scale, y * scale)
+}
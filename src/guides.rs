@@ -0,0 +1,364 @@
+use iced::Rectangle;
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+// Half the length of the crosshair's arms, as a fraction of the image's shorter side.
+const CROSSHAIR_ARM: f32 = 0.04;
+// Exported guides are one pixel wide per this many pixels of the image's shorter side.
+const EXPORT_LINE_SPAN: f32 = 720.0;
+// Opacities the settings cycle through.
+const OPACITY_STEPS: [f32; 4] = [0.25, 0.5, 0.75, 1.0];
+// Device templates for safe areas.
+const SAFE_AREAS: &str = include_str!("safe_areas.toml");
+// The safe area whose margins are set by hand rather than by a template.
+pub const CUSTOM_SAFE_AREA: &str = "Custom";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GuideColor {
+    #[default]
+    White,
+    Black,
+    Red,
+    Cyan,
+}
+
+impl GuideColor {
+    pub fn name(self) -> &'static str {
+        match self {
+            GuideColor::White => "white",
+            GuideColor::Black => "black",
+            GuideColor::Red => "red",
+            GuideColor::Cyan => "cyan",
+        }
+    }
+
+    pub fn next(self) -> GuideColor {
+        match self {
+            GuideColor::White => GuideColor::Black,
+            GuideColor::Black => GuideColor::Red,
+            GuideColor::Red => GuideColor::Cyan,
+            GuideColor::Cyan => GuideColor::White,
+        }
+    }
+
+    pub fn rgb(self) -> [f32; 3] {
+        match self {
+            GuideColor::White => [1.0, 1.0, 1.0],
+            GuideColor::Black => [0.0, 0.0, 0.0],
+            GuideColor::Red => [1.0, 0.2, 0.2],
+            GuideColor::Cyan => [0.2, 0.9, 1.0],
+        }
+    }
+}
+
+// Composition guides drawn over the frame: a crosshair on the viewport center, which zoom
+// animations converge on, and rule-of-thirds lines.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuideSettings {
+    pub crosshair: bool,
+    pub thirds: bool,
+    pub color: GuideColor,
+    pub opacity: f32,
+    // Burn the guides into exported and copied images too.
+    pub in_exports: bool,
+}
+
+impl Default for GuideSettings {
+    fn default() -> Self {
+        GuideSettings {
+            crosshair: false,
+            thirds: false,
+            color: GuideColor::default(),
+            opacity: 0.5,
+            in_exports: false,
+        }
+    }
+}
+
+impl GuideSettings {
+    pub fn any(&self) -> bool {
+        self.crosshair || self.thirds
+    }
+
+    pub fn next_opacity(&self) -> f32 {
+        OPACITY_STEPS
+            .into_iter()
+            .find(|step| *step > self.opacity)
+            .unwrap_or(OPACITY_STEPS[0])
+    }
+
+    // The guide lines for an image `width` by `height` as filled rectangles in its own
+    // coordinates, each `thickness` wide and centered on the exact line.
+    pub fn rects(&self, width: f32, height: f32, thickness: f32) -> Vec<Rectangle> {
+        let vertical = |x: f32, top: f32, bottom: f32| Rectangle {
+            x: x - thickness / 2.0,
+            y: top,
+            width: thickness,
+            height: bottom - top,
+        };
+        let horizontal = |y: f32, left: f32, right: f32| Rectangle {
+            x: left,
+            y: y - thickness / 2.0,
+            width: right - left,
+            height: thickness,
+        };
+        let mut rects = Vec::new();
+        if self.thirds {
+            for third in [1.0, 2.0] {
+                rects.push(vertical(width * third / 3.0, 0.0, height));
+                rects.push(horizontal(height * third / 3.0, 0.0, width));
+            }
+        }
+        if self.crosshair {
+            let (x, y) = (width / 2.0, height / 2.0);
+            let arm = (width.min(height) * CROSSHAIR_ARM).max(4.0 * thickness);
+            rects.push(vertical(x, y - arm, y + arm));
+            rects.push(horizontal(y, x - arm, x + arm));
+        }
+        rects
+    }
+
+    // Blends the guides into an RGBA image in place, at a line width that scales with it.
+    pub fn burn_in(&self, rgba: &mut [u8], width: u32, height: u32) {
+        let thickness = (width.min(height) as f32 / EXPORT_LINE_SPAN)
+            .round()
+            .max(1.0);
+        let rgb = self.color.rgb().map(|channel| channel * 255.0);
+        let alpha = self.opacity.clamp(0.0, 1.0);
+        let (width, height) = (width as usize, height as usize);
+        for rect in self.rects(width as f32, height as f32, thickness) {
+            let clamp = |value: f32, max: usize| (value.round().max(0.0) as usize).min(max);
+            let (x0, x1) = (clamp(rect.x, width), clamp(rect.x + rect.width, width));
+            let (y0, y1) = (clamp(rect.y, height), clamp(rect.y + rect.height, height));
+            for y in y0..y1 {
+                for pixel in rgba[(y * width + x0) * 4..(y * width + x1) * 4].chunks_exact_mut(4) {
+                    for (channel, target) in pixel.iter_mut().zip(rgb) {
+                        *channel = (*channel as f32 * (1.0 - alpha) + target * alpha).round() as u8;
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Edge {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+impl Edge {
+    pub const ALL: [Edge; 4] = [Edge::Top, Edge::Right, Edge::Bottom, Edge::Left];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Edge::Top => "top",
+            Edge::Right => "right",
+            Edge::Bottom => "bottom",
+            Edge::Left => "left",
+        }
+    }
+}
+
+// The bands along the edges of an image that something covers where it is shown, such as a
+// clock or a dock: `top` and `bottom` as fractions of its height, `left` and `right` of its width.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Margins {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Margins {
+    pub fn get(&self, edge: Edge) -> f32 {
+        match edge {
+            Edge::Top => self.top,
+            Edge::Right => self.right,
+            Edge::Bottom => self.bottom,
+            Edge::Left => self.left,
+        }
+    }
+
+    pub fn set(&mut self, edge: Edge, value: f32) {
+        match edge {
+            Edge::Top => self.top = value,
+            Edge::Right => self.right = value,
+            Edge::Bottom => self.bottom = value,
+            Edge::Left => self.left = value,
+        }
+    }
+
+    // The covered bands of an image `width` by `height` in its own coordinates, without
+    // overlaps: the top and bottom ones across its full width, the side ones between them.
+    pub fn regions(&self, width: f32, height: f32) -> Vec<Rectangle> {
+        let top = height * self.top.clamp(0.0, 1.0);
+        let bottom = (height * self.bottom.clamp(0.0, 1.0)).min(height - top);
+        let left = width * self.left.clamp(0.0, 1.0);
+        let right = (width * self.right.clamp(0.0, 1.0)).min(width - left);
+        let middle = height - top - bottom;
+        [
+            Rectangle::new((0.0, 0.0).into(), (width, top).into()),
+            Rectangle::new((0.0, height - bottom).into(), (width, bottom).into()),
+            Rectangle::new((0.0, top).into(), (left, middle).into()),
+            Rectangle::new((width - right, top).into(), (right, middle).into()),
+        ]
+        .into_iter()
+        .filter(|rect| rect.width > 0.0 && rect.height > 0.0)
+        .collect()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct SafeArea {
+    pub name: String,
+    #[serde(flatten)]
+    pub margins: Margins,
+}
+
+#[derive(Deserialize)]
+struct SafeAreaFile {
+    template: Vec<SafeArea>,
+}
+
+// The device templates, in the order the settings cycle through them.
+pub fn safe_areas() -> &'static [SafeArea] {
+    static TEMPLATES: OnceLock<Vec<SafeArea>> = OnceLock::new();
+    TEMPLATES.get_or_init(|| {
+        toml::from_str::<SafeAreaFile>(SAFE_AREAS)
+            .expect("the bundled safe-area templates parse")
+            .template
+    })
+}
+
+// The safe area shown over the image while composing, and the padding queued exports get. The
+// shaded bands are never part of an export.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SafeAreaSettings {
+    // A template's name, or `CUSTOM_SAFE_AREA` for `custom`; None shows no safe area.
+    pub template: Option<String>,
+    pub custom: Margins,
+    // Pixels added on every side of queued exports by widening the view, so that the fractal
+    // carries on into them.
+    pub padding: u32,
+}
+
+impl SafeAreaSettings {
+    // The margins shown, if any.
+    pub fn margins(&self) -> Option<Margins> {
+        let name = self.template.as_deref()?;
+        if name == CUSTOM_SAFE_AREA {
+            return Some(self.custom);
+        }
+        safe_areas()
+            .iter()
+            .find(|area| area.name == name)
+            .map(|area| area.margins)
+    }
+
+    // The template after the current one: none, each device template, then the custom margins.
+    pub fn next_template(&self) -> Option<String> {
+        let names: Vec<&str> = safe_areas()
+            .iter()
+            .map(|area| area.name.as_str())
+            .chain([CUSTOM_SAFE_AREA])
+            .collect();
+        let next = match &self.template {
+            None => names.first(),
+            Some(current) => names
+                .iter()
+                .position(|name| name == current)
+                .and_then(|index| names.get(index + 1)),
+        };
+        next.map(|name| name.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn templates_have_names_of_their_own() {
+        let areas = safe_areas();
+        assert!(!areas.is_empty(), "there are no safe-area templates");
+        for (index, area) in areas.iter().enumerate() {
+            assert!(
+                area.name != CUSTOM_SAFE_AREA
+                    && areas[..index].iter().all(|other| other.name != area.name),
+                "safe area {:?} is named twice",
+                area.name
+            );
+        }
+    }
+
+    // The shaded bands of each template cover exactly its margins of a portrait image, without
+    // overlapping or leaving it.
+    #[test]
+    fn templates_cover_their_margins() {
+        let (width, height) = (1080.0, 1920.0);
+        for area in safe_areas() {
+            let margins = area.margins;
+            let sides = [margins.top, margins.right, margins.bottom, margins.left];
+            assert!(
+                sides.iter().all(|side| (0.0..=0.5).contains(side)),
+                "safe area {:?} has margins of {:?}",
+                area.name,
+                sides
+            );
+            let regions = margins.regions(width, height);
+            let covered: f32 = regions.iter().map(|rect| rect.width * rect.height).sum();
+            let open = width
+                * (1.0 - margins.left - margins.right)
+                * height
+                * (1.0 - margins.top - margins.bottom);
+            let overlapping = regions.iter().enumerate().any(|(index, rect)| {
+                regions[index + 1..].iter().any(|other| {
+                    rect.intersection(other)
+                        .is_some_and(|shared| shared.area() > 0.0)
+                })
+            });
+            let outside = regions.iter().any(|rect| {
+                rect.x < 0.0
+                    || rect.y < 0.0
+                    || rect.x + rect.width > width
+                    || rect.y + rect.height > height
+            });
+            assert!(
+                !overlapping && !outside && (covered + open - width * height).abs() <= 1.0,
+                "safe area {:?} covers {:?} of a {}x{} image",
+                area.name,
+                regions,
+                width,
+                height
+            );
+        }
+    }
+
+    #[test]
+    fn settings_cycle_through_every_template_and_back_to_none() {
+        let areas = safe_areas();
+        let mut settings = SafeAreaSettings::default();
+        let mut shown = Vec::new();
+        for _ in 0..=areas.len() + 1 {
+            settings.template = settings.next_template();
+            shown.push(settings.margins());
+            if settings.template.is_none() {
+                break;
+            }
+        }
+        let expected: Vec<_> = areas
+            .iter()
+            .map(|area| Some(area.margins))
+            .chain([Some(settings.custom), None])
+            .collect();
+        assert_eq!(shown, expected);
+    }
+}
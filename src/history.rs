@@ -0,0 +1,200 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::fractal::FractalKind;
+use crate::settings::{ColoringSettings, RenderSettings};
+use crate::viewport::Viewport;
+
+// Entries kept for undo; the oldest are dropped beyond this.
+pub const DEFAULT_CAPACITY: usize = 200;
+// Changes with the same key closer together than this are one entry, so a slider drag undoes in
+// one step.
+pub const COALESCE_WINDOW: Duration = Duration::from_secs(1);
+
+// Everything a single undo step restores.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Snapshot {
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    pub profile: String,
+    pub settings: RenderSettings,
+    pub coloring: ColoringSettings,
+}
+
+impl Snapshot {
+    // Whether going from `self` to `other` needs new pixels rather than just a recolor.
+    pub fn needs_render(&self, other: &Snapshot) -> bool {
+        self.fractal != other.fractal
+            || self.viewport != other.viewport
+            || self.profile != other.profile
+            || self.settings != other.settings
+    }
+}
+
+#[derive(Debug)]
+pub struct History<T> {
+    undo: VecDeque<T>,
+    redo: Vec<T>,
+    capacity: usize,
+    last: Option<(&'static str, Instant)>,
+}
+
+impl<T> Default for History<T> {
+    fn default() -> Self {
+        History::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl<T> History<T> {
+    pub fn new(capacity: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            capacity: capacity.max(1),
+            last: None,
+        }
+    }
+
+    // Records `before`, the state just prior to a change. Changes tagged with the same
+    // `coalesce` key within `COALESCE_WINDOW` of each other keep only the first state.
+    pub fn record(&mut self, before: T, coalesce: Option<&'static str>, now: Instant) {
+        let previous = self.last.take();
+        if let Some(key) = coalesce {
+            self.last = Some((key, now));
+            if let Some((previous_key, at)) = previous {
+                if previous_key == key && now.duration_since(at) < COALESCE_WINDOW {
+                    return;
+                }
+            }
+        }
+        self.redo.clear();
+        self.undo.push_back(before);
+        if self.undo.len() > self.capacity {
+            self.undo.pop_front();
+        }
+    }
+
+    // Steps back, returning the state to restore; `current` becomes redoable.
+    pub fn undo(&mut self, current: T) -> Option<T> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        self.last = None;
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: T) -> Option<T> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        self.last = None;
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(400);
+
+    // Undoes from `current` until nothing is left, returning the states restored in order.
+    fn undo_all(history: &mut History<u32>, mut current: u32) -> Vec<u32> {
+        let mut restored = Vec::new();
+        while let Some(previous) = history.undo(current) {
+            restored.push(previous);
+            current = previous;
+        }
+        restored
+    }
+
+    #[test]
+    fn changes_within_the_window_coalesce() {
+        let mut history = History::new(10);
+        let start = Instant::now();
+        // A drag whose steps each come within the window of the one before, though the whole
+        // drag takes longer than it.
+        for step in 0..5 {
+            history.record(step, Some("gamma"), start + STEP * step);
+        }
+        assert_eq!(undo_all(&mut history, 5), [0]);
+    }
+
+    #[test]
+    fn changes_outside_the_window_or_of_other_kinds_do_not_coalesce() {
+        let mut history = History::new(10);
+        let start = Instant::now();
+        history.record(0, Some("gamma"), start);
+        history.record(1, Some("gamma"), start + COALESCE_WINDOW);
+        history.record(2, Some("density"), start + COALESCE_WINDOW + STEP);
+        history.record(3, None, start + COALESCE_WINDOW + STEP * 2);
+        history.record(4, None, start + COALESCE_WINDOW + STEP * 3);
+        assert_eq!(undo_all(&mut history, 5), [4, 3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn a_new_change_clears_redo() {
+        let mut history = History::new(10);
+        let start = Instant::now();
+        history.record(0, None, start);
+        history.record(1, None, start);
+        assert_eq!(history.undo(2), Some(1));
+        assert!(history.can_redo());
+        history.record(1, None, start);
+        assert!(!history.can_redo());
+        assert_eq!(history.redo(3), None);
+        assert_eq!(undo_all(&mut history, 3), [1, 0]);
+    }
+
+    #[test]
+    fn undo_and_redo_walk_back_and_forth() {
+        let mut history = History::new(10);
+        let start = Instant::now();
+        history.record(0, None, start);
+        history.record(1, None, start);
+        assert_eq!(history.undo(2), Some(1));
+        assert_eq!(history.undo(1), Some(0));
+        assert_eq!(history.undo(0), None);
+        assert_eq!(history.redo(0), Some(1));
+        assert_eq!(history.redo(1), Some(2));
+        assert_eq!(history.redo(2), None);
+    }
+
+    #[test]
+    fn undo_ends_coalescing() {
+        let mut history = History::new(10);
+        let start = Instant::now();
+        history.record(0, Some("gamma"), start);
+        assert_eq!(history.undo(1), Some(0));
+        // Dragging again right after the undo is a change of its own.
+        history.record(0, Some("gamma"), start + STEP);
+        history.record(1, Some("gamma"), start + STEP * 2);
+        assert_eq!(undo_all(&mut history, 2), [0]);
+        assert!(!history.can_undo());
+
+        history.record(0, Some("gamma"), start);
+        history.record(1, Some("gamma"), start + STEP);
+        assert_eq!(history.undo(2), Some(0));
+        assert_eq!(history.redo(0), Some(2));
+        // And so is dragging again right after a redo.
+        history.record(2, Some("gamma"), start + STEP * 2);
+        assert_eq!(undo_all(&mut history, 3), [2, 0]);
+    }
+
+    #[test]
+    fn the_oldest_entries_are_dropped_past_capacity() {
+        let mut history = History::new(3);
+        let start = Instant::now();
+        for state in 0..5 {
+            history.record(state, None, start);
+        }
+        assert_eq!(undo_all(&mut history, 5), [4, 3, 2]);
+        assert_eq!(History::<u32>::new(0).capacity, 1);
+    }
+}
@@ -0,0 +1,258 @@
+use serde::{Deserialize, Serialize};
+
+use std::fmt::Write as _;
+use std::fs::{self, OpenOptions};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use crate::coloring;
+use crate::export;
+use crate::fractal::FractalKind;
+use crate::render::IterationBuffer;
+use crate::settings::ColoringSettings;
+use crate::store;
+use crate::viewport::Viewport;
+
+// Thumbnails are shrunk to fit in this box, which keeps a long session's log to a few kilobytes
+// per entry.
+pub const JOURNAL_THUMBNAIL_WIDTH: usize = 96;
+pub const JOURNAL_THUMBNAIL_HEIGHT: usize = 64;
+const LOG_NAME: &str = "journal.toml";
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// One view the user settled on during a session.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub id: u64,
+    // Seconds since the epoch.
+    pub time: u64,
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    pub max_iterations: u32,
+    pub palette: String,
+    // File name of the thumbnail in the session directory, if one was written.
+    #[serde(default)]
+    pub thumbnail: Option<String>,
+}
+
+impl JournalEntry {
+    // An entry for now; the journal numbers it when it is appended.
+    pub fn new(
+        fractal: FractalKind,
+        viewport: Viewport,
+        max_iterations: u32,
+        palette: &str,
+    ) -> JournalEntry {
+        JournalEntry {
+            id: 0,
+            time: export::unix_seconds(),
+            fractal,
+            viewport,
+            max_iterations,
+            palette: palette.to_string(),
+            thumbnail: None,
+        }
+    }
+}
+
+// The wrapper a single appended entry is written through, so that the log file is a sequence of
+// `[[entries]]` tables that stays valid TOML as it grows.
+#[derive(Serialize, Deserialize)]
+struct Log {
+    entries: Vec<JournalEntry>,
+}
+
+// Every view of a session in the order it was reached. Unlike the undo history it is never
+// truncated except by explicit pruning, and it lives on disk in its own directory: the log
+// and the entries' thumbnails. Without a directory it is kept in memory only.
+#[derive(Clone, Debug, Default)]
+pub struct Journal {
+    pub entries: Vec<JournalEntry>,
+    dir: Option<PathBuf>,
+    next_id: u64,
+}
+
+impl Journal {
+    // Starts a session journal in a new directory under `parent`, named after the time.
+    pub fn start(parent: Option<PathBuf>) -> Journal {
+        Journal {
+            entries: Vec::new(),
+            dir: parent.map(|parent| {
+                let name = export::timestamp();
+                export::unique_path(&parent, &name, "session")
+            }),
+            next_id: 0,
+        }
+    }
+
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+
+    pub fn get(&self, id: u64) -> Option<&JournalEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    // Whether a view differs from the last one logged, so that re-renders of the same view (a
+    // resize, a new palette) do not add entries.
+    pub fn is_new_view(&self, fractal: FractalKind, viewport: &Viewport) -> bool {
+        self.entries
+            .last()
+            .is_none_or(|last| last.fractal != fractal || last.viewport != *viewport)
+    }
+
+    // Numbers `entry`, writes its thumbnail (an RGBA image with its width and height) and
+    // appends it to the log. The entry is kept even when writing fails.
+    pub fn append(
+        &mut self,
+        mut entry: JournalEntry,
+        thumbnail: Option<(&[u8], usize, usize)>,
+    ) -> Result<&JournalEntry, String> {
+        self.next_id += 1;
+        entry.id = self.next_id;
+        let result = match &self.dir {
+            Some(dir) => write_entry(dir, &mut entry, thumbnail),
+            None => Ok(()),
+        };
+        self.entries.push(entry);
+        result.map(|_| &self.entries[self.entries.len() - 1])
+    }
+
+    // Drops the oldest entries beyond `max` along with their thumbnails and rewrites the log.
+    // Returns the entries dropped.
+    pub fn prune(&mut self, max: usize) -> Result<Vec<JournalEntry>, String> {
+        if self.entries.len() <= max {
+            return Ok(Vec::new());
+        }
+        let dropped: Vec<JournalEntry> = self.entries.drain(..self.entries.len() - max).collect();
+        let Some(dir) = &self.dir else {
+            return Ok(dropped);
+        };
+        for entry in &dropped {
+            if let Some(name) = &entry.thumbnail {
+                let _ = fs::remove_file(dir.join(name));
+            }
+        }
+        let log = Log {
+            entries: self.entries.clone(),
+        };
+        let contents = toml::to_string_pretty(&log).map_err(|err| err.to_string())?;
+        store::save(&dir.join(LOG_NAME), &contents)?;
+        Ok(dropped)
+    }
+
+    // The session as a standalone HTML page: every entry's thumbnail, embedded, with the time,
+    // fractal and coordinates needed to go back there.
+    pub fn contact_sheet(&self, title: &str) -> String {
+        let mut html = String::new();
+        let _ = write!(
+            html,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>\nbody {{ font-family: sans-serif; background: #111; color: #ddd; }}\n\
+             figure {{ display: inline-block; margin: 8px; vertical-align: top; }}\n\
+             figcaption {{ font: 12px monospace; white-space: pre; }}\n</style>\n\
+             </head>\n<body>\n<h1>{}</h1>\n",
+            escape_html(title),
+            escape_html(title)
+        );
+        for entry in &self.entries {
+            html.push_str("<figure>\n");
+            let png = self
+                .dir
+                .as_ref()
+                .zip(entry.thumbnail.as_ref())
+                .and_then(|(dir, name)| fs::read(dir.join(name)).ok());
+            if let Some(png) = png {
+                let _ = writeln!(
+                    html,
+                    "<img src=\"data:image/png;base64,{}\" alt=\"entry {}\">",
+                    base64(&png),
+                    entry.id
+                );
+            }
+            let _ = writeln!(
+                html,
+                "<figcaption>{} {}\nre {}\nim {}\nwidth {:e}\n{} iterations, {}</figcaption>",
+                export::date_time(entry.time),
+                entry.fractal.fractal().name(),
+                entry.viewport.center_re,
+                entry.viewport.center_im,
+                entry.viewport.width,
+                entry.max_iterations,
+                escape_html(&entry.palette)
+            );
+            html.push_str("</figure>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+}
+
+fn write_entry(
+    dir: &Path,
+    entry: &mut JournalEntry,
+    thumbnail: Option<(&[u8], usize, usize)>,
+) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    if let Some((rgba, width, height)) = thumbnail {
+        let name = format!("entry-{}.png", entry.id);
+        export::write_png(&dir.join(&name), width as u32, height as u32, rgba)?;
+        entry.thumbnail = Some(name);
+    }
+    let log = Log {
+        entries: vec![entry.clone()],
+    };
+    let block = toml::to_string_pretty(&log).map_err(|err| err.to_string())?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(dir.join(LOG_NAME))
+        .map_err(|err| err.to_string())?;
+    writeln!(file, "{}", block).map_err(|err| err.to_string())
+}
+
+// Shrinks a frame's iteration buffer, keeping its shape, until it fits in the thumbnail box, and
+// colors it at that size. Returns the thumbnail with its size, or `None` for an empty frame.
+pub fn thumbnail(
+    buffer: &IterationBuffer,
+    settings: &ColoringSettings,
+) -> Option<(Vec<u8>, usize, usize)> {
+    let (width, height) = (buffer.width, buffer.height);
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let scale = (JOURNAL_THUMBNAIL_WIDTH as f32 / width as f32)
+        .min(JOURNAL_THUMBNAIL_HEIGHT as f32 / height as f32)
+        .min(1.0);
+    let width = ((width as f32 * scale).round() as usize).max(1);
+    let height = ((height as f32 * scale).round() as usize).max(1);
+    Some((
+        coloring::downscale(buffer, settings, width, height),
+        width,
+        height,
+    ))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (index, byte)| {
+            group | (*byte as u32) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(BASE64[(group >> (18 - 6 * index) & 63) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
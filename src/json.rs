@@ -0,0 +1,267 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use toml::map::Map;
+use toml::Value;
+
+// JSON for small documents meant to be shared, such as looks. Values go through the same serde
+// model as the TOML files, so the two agree on field names; JSON's `null` has no TOML
+// counterpart and is rejected, as are documents whose top level is not an object.
+
+pub fn to_string_pretty<T: Serialize>(value: &T) -> Result<String, String> {
+    let value = Value::try_from(value).map_err(|err| err.to_string())?;
+    let mut out = String::new();
+    write_value(&mut out, &value, 0)?;
+    out.push('\n');
+    Ok(out)
+}
+
+pub fn from_str<T: DeserializeOwned>(text: &str) -> Result<T, String> {
+    let mut parser = Parser {
+        text: text.as_bytes(),
+        at: 0,
+    };
+    let value = parser.value()?;
+    parser.skip_space();
+    if parser.at < parser.text.len() {
+        return Err(parser.error("trailing characters"));
+    }
+    if !value.is_table() {
+        return Err(String::from("expected a JSON object"));
+    }
+    value
+        .try_into()
+        .map_err(|err: toml::de::Error| err.to_string().trim().to_string())
+}
+
+fn write_value(out: &mut String, value: &Value, indent: usize) -> Result<(), String> {
+    match value {
+        Value::String(string) => write_string(out, string),
+        Value::Integer(integer) => out.push_str(&integer.to_string()),
+        // Debug formatting keeps a fraction on whole numbers and round-trips exactly; values that
+        // came from an f32 are written at that precision rather than with widening noise.
+        Value::Float(float) if float.is_finite() && (*float as f32) as f64 == *float => {
+            out.push_str(&format!("{:?}", *float as f32))
+        }
+        Value::Float(float) if float.is_finite() => out.push_str(&format!("{:?}", float)),
+        Value::Float(float) => return Err(format!("{} has no JSON representation", float)),
+        Value::Boolean(boolean) => out.push_str(if *boolean { "true" } else { "false" }),
+        Value::Datetime(datetime) => write_string(out, &datetime.to_string()),
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str(&"  ".repeat(indent + 1));
+                write_value(out, item, indent + 1)?;
+            }
+            if !items.is_empty() {
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+            }
+            out.push(']');
+        }
+        Value::Table(table) => {
+            out.push('{');
+            for (index, (key, item)) in table.iter().enumerate() {
+                out.push_str(if index == 0 { "\n" } else { ",\n" });
+                out.push_str(&"  ".repeat(indent + 1));
+                write_string(out, key);
+                out.push_str(": ");
+                write_value(out, item, indent + 1)?;
+            }
+            if !table.is_empty() {
+                out.push('\n');
+                out.push_str(&"  ".repeat(indent));
+            }
+            out.push('}');
+        }
+    }
+    Ok(())
+}
+
+fn write_string(out: &mut String, string: &str) {
+    out.push('"');
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> String {
+        format!("{} at byte {}", message, self.at)
+    }
+
+    fn skip_space(&mut self) {
+        while self.text.get(self.at).is_some_and(u8::is_ascii_whitespace) {
+            self.at += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_space();
+        let found = self.text.get(self.at) == Some(&byte);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), String> {
+        if self.eat(byte) {
+            return Ok(());
+        }
+        Err(self.error(&format!("expected '{}'", byte as char)))
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_space();
+        match self.text.get(self.at) {
+            Some(b'{') => self.object(),
+            Some(b'[') => self.array(),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b't') => self.keyword("true", Value::Boolean(true)),
+            Some(b'f') => self.keyword("false", Value::Boolean(false)),
+            Some(b'n') => Err(self.error("null is not supported")),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+            None => Err(self.error("unexpected end")),
+        }
+    }
+
+    fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        if !self.text[self.at..].starts_with(word.as_bytes()) {
+            return Err(self.error("unexpected character"));
+        }
+        self.at += word.len();
+        Ok(value)
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect(b'{')?;
+        let mut table = Map::new();
+        if self.eat(b'}') {
+            return Ok(Value::Table(table));
+        }
+        loop {
+            self.skip_space();
+            let key = self.string()?;
+            self.expect(b':')?;
+            let value = self.value()?;
+            table.insert(key, value);
+            if self.eat(b'}') {
+                return Ok(Value::Table(table));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.eat(b']') {
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            if self.eat(b']') {
+                return Ok(Value::Array(items));
+            }
+            self.expect(b',')?;
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        if self.text.get(self.at) != Some(&b'"') {
+            return Err(self.error("expected a string"));
+        }
+        self.at += 1;
+        let mut bytes = Vec::new();
+        loop {
+            let Some(&byte) = self.text.get(self.at) else {
+                return Err(self.error("unterminated string"));
+            };
+            self.at += 1;
+            match byte {
+                b'"' => break,
+                b'\\' => {
+                    let Some(&escape) = self.text.get(self.at) else {
+                        return Err(self.error("unterminated string"));
+                    };
+                    self.at += 1;
+                    let c = match escape {
+                        b'"' => '"',
+                        b'\\' => '\\',
+                        b'/' => '/',
+                        b'b' => '\u{8}',
+                        b'f' => '\u{c}',
+                        b'n' => '\n',
+                        b'r' => '\r',
+                        b't' => '\t',
+                        b'u' => self.unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                byte => bytes.push(byte),
+            }
+        }
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8 in string"))
+    }
+
+    // The code point of a `\u` escape whose `u` has been read, joining surrogate pairs.
+    fn unicode_escape(&mut self) -> Result<char, String> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) && self.text[self.at..].starts_with(b"\\u") {
+            self.at += 2;
+            let low = self.hex4()?;
+            0x10000 + ((high - 0xd800) << 10) + low.wrapping_sub(0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .text
+            .get(self.at..self.at + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.at += 4;
+        Ok(digits)
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.at;
+        while self
+            .text
+            .get(self.at)
+            .is_some_and(|byte| matches!(byte, b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9'))
+        {
+            self.at += 1;
+        }
+        let number = std::str::from_utf8(&self.text[start..self.at]).unwrap_or_default();
+        if let Ok(integer) = number.parse::<i64>() {
+            return Ok(Value::Integer(integer));
+        }
+        number
+            .parse::<f64>()
+            .map(Value::Float)
+            .map_err(|_| self.error("invalid number"))
+    }
+}
@@ -0,0 +1,175 @@
+use crate::coloring::PALETTE_PERIOD;
+use crate::render::{self, IterationBuffer};
+
+// The share of escaped pixels left below and above the span auto levels spreads across the
+// palette, so that a few stray pixels at either end do not squeeze the rest into one band.
+pub const CLIP: f64 = 0.01;
+// How bright the median pixel comes out, on the 0 to 1 scale gamma bends.
+const MIDTONE: f32 = 0.5;
+// The ranges of the density and gamma settings.
+pub const MIN_DENSITY: f32 = 0.1;
+pub const MAX_DENSITY: f32 = 16.0;
+pub const MIN_GAMMA: f32 = 0.2;
+pub const MAX_GAMMA: f32 = 3.0;
+
+// How many escaped pixels of a frame took each whole number of iterations to escape.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Histogram {
+    pub counts: Vec<u64>,
+}
+
+impl Histogram {
+    pub fn of(buffer: &IterationBuffer) -> Histogram {
+        let mut counts = Vec::new();
+        for index in 0..buffer.values.len() {
+            let value = buffer.values.get(index);
+            if !render::escaped(value) {
+                continue;
+            }
+            let bin = value as usize;
+            if bin >= counts.len() {
+                counts.resize(bin + 1, 0);
+            }
+            counts[bin] += 1;
+        }
+        Histogram { counts }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    // The escape time below which `fraction` of the pixels fall, spreading each bin's pixels
+    // evenly across it.
+    pub fn percentile(&self, fraction: f64) -> f32 {
+        let target = fraction.clamp(0.0, 1.0) * self.total() as f64;
+        let mut below = 0.0;
+        for (bin, &count) in self.counts.iter().enumerate() {
+            let count = count as f64;
+            if count > 0.0 && below + count >= target {
+                return (bin as f64 + (target - below) / count) as f32;
+            }
+            below += count;
+        }
+        self.counts.len() as f32
+    }
+}
+
+// Coloring settings auto levels picks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Levels {
+    pub density: f32,
+    pub offset: f32,
+    pub gamma: f32,
+}
+
+// Levels that spread the escape times between the `CLIP` and 1 - `CLIP` percentiles over one
+// pass through the palette, starting at its beginning, with a gamma that brings the median pixel
+// out at a midtone. None for a frame without escaped pixels, which levels cannot help.
+pub fn suggest(histogram: &Histogram) -> Option<Levels> {
+    if histogram.total() == 0 {
+        return None;
+    }
+    let low = histogram.percentile(CLIP);
+    let high = histogram.percentile(1.0 - CLIP);
+    let density = (PALETTE_PERIOD / (high - low).max(f32::EPSILON)).clamp(MIN_DENSITY, MAX_DENSITY);
+    let offset = (-low * density / PALETTE_PERIOD).rem_euclid(1.0);
+    // Where the median falls along the palette, kept off the ends, where no gamma moves it.
+    let median = ((histogram.percentile(0.5) - low) * density / PALETTE_PERIOD).clamp(0.01, 0.99);
+    let gamma = (median.ln() / MIDTONE.ln()).clamp(MIN_GAMMA, MAX_GAMMA);
+    Some(Levels {
+        density,
+        offset,
+        gamma,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Size;
+
+    use super::*;
+    use crate::render::ClassCounts;
+    use crate::stats::{self, CANONICAL_VIEWS};
+
+    // Escape times from `start` to `end` bins, `count` pixels each, for every band.
+    fn histogram(bands: &[(usize, usize, u64)]) -> Histogram {
+        let mut counts = vec![0; bands.iter().map(|band| band.1).max().unwrap_or(0)];
+        for &(start, end, count) in bands {
+            counts[start..end].iter_mut().for_each(|bin| *bin += count);
+        }
+        Histogram { counts }
+    }
+
+    // Where an escape time lands along the palette, in passes through it.
+    fn position(levels: &Levels, value: f32) -> f32 {
+        value * levels.density / PALETTE_PERIOD + levels.offset
+    }
+
+    fn at_start(position: f32) -> bool {
+        (position - position.round()).abs() < 1e-3
+    }
+
+    // Escape times in a narrow band are spread over the whole palette.
+    #[test]
+    fn washed_out_frames_are_spread_out() {
+        let washed_out = histogram(&[(300, 316, 100)]);
+        let levels = suggest(&washed_out);
+        assert!(
+            levels.is_some_and(|levels| (levels.density - 4.0).abs() < 0.1
+                && at_start(position(&levels, washed_out.percentile(CLIP)))
+                && (levels.gamma - 1.0).abs() < 0.05),
+            "a washed-out frame gets {:?}",
+            levels
+        );
+    }
+
+    // A bimodal frame is kept within one pass through the palette, its darker half brightened.
+    #[test]
+    fn bimodal_frames_keep_within_one_pass() {
+        let bimodal = histogram(&[(10, 20, 100), (200, 220, 50)]);
+        let levels = suggest(&bimodal).expect("a bimodal frame gets levels");
+        let low = bimodal.percentile(CLIP);
+        let high = bimodal.percentile(1.0 - CLIP);
+        assert!(
+            position(&levels, high) - position(&levels, low) <= 1.0 + 1e-3
+                && levels.gamma > 1.0
+                && levels.gamma <= MAX_GAMMA,
+            "a bimodal frame gets {:?}",
+            levels
+        );
+    }
+
+    #[test]
+    fn good_frames_are_left_about_as_they_were() {
+        let good = histogram(&[(0, 64, 100)]);
+        let levels = suggest(&good);
+        assert!(
+            levels.is_some_and(|levels| (levels.density - 1.0).abs() < 0.05
+                && at_start(position(&levels, good.percentile(CLIP)))
+                && (levels.offset + 0.05).rem_euclid(1.0) < 0.1
+                && (levels.gamma - 1.0).abs() < 0.05),
+            "an already good frame gets {:?}",
+            levels
+        );
+    }
+
+    #[test]
+    fn frames_with_nothing_escaped_are_left_alone() {
+        assert_eq!(suggest(&histogram(&[])), None);
+    }
+
+    // Escape times one bin apart stay within the density range.
+    #[test]
+    fn single_escape_times_stay_within_the_density_range() {
+        let levels = suggest(&histogram(&[(40, 41, 10)]));
+        assert_eq!(levels.map(|levels| levels.density), Some(MAX_DENSITY));
+    }
+
+    #[test]
+    fn histograms_count_every_escaped_pixel() {
+        let buffer = stats::render_frame(Size::new(64.0, 48.0), CANONICAL_VIEWS[0].params());
+        let escaped = ClassCounts::of(&buffer).escaped as u64;
+        assert_eq!(Histogram::of(&buffer).total(), escaped);
+    }
+}
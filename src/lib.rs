@@ -0,0 +1,59 @@
+pub mod adjust;
+pub mod animation;
+pub mod annotation;
+pub mod boundary;
+pub mod buffers;
+pub mod camera;
+pub mod coloring;
+pub mod coordinates;
+pub mod cursor;
+pub mod display;
+pub mod doubledouble;
+pub mod dpi;
+pub mod duty;
+pub mod eta;
+pub mod export;
+pub mod expr;
+pub mod fractal;
+pub mod fuzzy;
+pub mod gamepad;
+pub mod guides;
+pub mod history;
+pub mod journal;
+pub mod json;
+pub mod levels;
+pub mod locate;
+pub mod look;
+pub mod merge;
+pub mod minibrot;
+pub mod numbers;
+pub mod onboarding;
+pub mod palette;
+pub mod pasted;
+pub mod perf;
+pub mod postprocess;
+pub mod power;
+pub mod prefetch;
+pub mod project;
+pub mod queue;
+pub mod rawdata;
+#[cfg(test)]
+mod reference;
+pub mod refine;
+pub mod relative;
+pub mod render;
+pub mod sampling;
+#[cfg(feature = "http")]
+pub mod serve;
+pub mod settings;
+pub mod sonify;
+pub mod stats;
+pub mod storage;
+pub mod store;
+pub mod tilecache;
+pub mod tiledisk;
+pub mod tiling;
+pub mod tune;
+pub mod valve;
+pub mod viewport;
+pub mod watchdog;
@@ -0,0 +1,627 @@
+use iced::{Point, Size};
+
+use threadpool::ThreadPool;
+
+use std::fs;
+use std::path::Path;
+
+use crate::coloring;
+use crate::fractal::{AngleKind, FractalKind};
+use crate::render::{self, Arithmetic, CancelToken, FrameParams};
+use crate::settings::ColoringSettings;
+use crate::storage::Precision;
+use crate::viewport::Viewport;
+
+// Most cells across the image is compared in; its height follows its aspect.
+const TEMPLATE_WIDTH: usize = 48;
+// Width of the render of the region searched at each level.
+const SEARCH_WIDTH: usize = 384;
+// Smallest window tried, in pixels of that render. Windows shrink by a quarter of an octave at a
+// time.
+const MIN_WINDOW_PIXELS: f64 = 12.0;
+const WINDOW_STEP: f64 = 0.840_896_415_253_714_6;
+// Windows are tried this many to their own width apart.
+const POSITIONS_PER_WINDOW: f64 = 10.0;
+// How far detail reaches, in cells of a comparison `TEMPLATE_WIDTH` across.
+const DETAIL_CELLS: f64 = 6.0;
+// The next level searches a region this many times the width of the best window, centered on
+// it. A best window wider than the region over `FINAL_ZOOM` needs no further level.
+const ZOOM_OUT: f64 = 3.0;
+const FINAL_ZOOM: f64 = 4.5;
+// Levels searched before giving up on going deeper. Matching grows unreliable well before the
+// last of them: see `max_magnification`.
+pub const MAX_LEVELS: usize = 4;
+// Regions kept at each level of the search, and matches confirmed at the end.
+const BEAM: usize = 4;
+// Rounds of nudging the final window, each half as far as the one before.
+const REFINE_ROUNDS: usize = 4;
+// Matches scoring below this are reported as unlikely.
+pub const MIN_CONFIDENCE: f64 = 0.75;
+
+// A single-channel image, row by row from the top.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gray {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f32>,
+}
+
+impl Gray {
+    // The luminance of an RGBA image.
+    pub fn from_rgba(rgba: &[u8], width: usize, height: usize) -> Gray {
+        let values = rgba
+            .chunks_exact(4)
+            .take(width * height)
+            .map(|pixel| {
+                (0.2126 * pixel[0] as f32 + 0.7152 * pixel[1] as f32 + 0.0722 * pixel[2] as f32)
+                    / 255.0
+            })
+            .collect();
+        Gray {
+            width,
+            height,
+            values,
+        }
+    }
+
+    // Each value replaced by the fraction of values below it, ties sharing their middle rank.
+    // Two images related by any increasing mapping of intensities equalize alike, so an image
+    // colored with an unknown palette can still be compared with a render colored with another.
+    pub fn equalized(&self) -> Gray {
+        let mut order: Vec<usize> = (0..self.values.len()).collect();
+        order.sort_by(|a, b| self.values[*a].total_cmp(&self.values[*b]));
+        let mut values = vec![0.0; self.values.len()];
+        let scale = 1.0 / self.values.len().max(2).saturating_sub(1) as f32;
+        let mut start = 0;
+        while start < order.len() {
+            let value = self.values[order[start]];
+            let end = start
+                + order[start..]
+                    .iter()
+                    .take_while(|index| self.values[**index] == value)
+                    .count();
+            let rank = (start + end - 1) as f32 / 2.0 * scale;
+            for index in &order[start..end] {
+                values[*index] = rank;
+            }
+            start = end;
+        }
+        Gray {
+            width: self.width,
+            height: self.height,
+            values,
+        }
+    }
+
+    // Each value less the mean of the square around it, out to `radius` values, which leaves the
+    // detail and drops the slow gradients that much of any view is made of.
+    pub fn detail(&self, radius: usize) -> Gray {
+        let integral = Integral::of(self);
+        let mut values = Vec::with_capacity(self.values.len());
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mean = integral.mean(
+                    x.saturating_sub(radius),
+                    y.saturating_sub(radius),
+                    (x + radius + 1).min(self.width),
+                    (y + radius + 1).min(self.height),
+                );
+                values.push(self.values[y * self.width + x] - mean);
+            }
+        }
+        Gray {
+            width: self.width,
+            height: self.height,
+            values,
+        }
+    }
+
+    // The mean of each of `width` by `height` cells of the rectangle at (x, y) of size (w, h), in
+    // pixels, which may be fractional. Each cell takes at least one pixel.
+    pub fn sample(&self, rect: (f64, f64, f64, f64), width: usize, height: usize) -> Gray {
+        Integral::of(self).sample(rect, width, height)
+    }
+}
+
+// Sums of the values above and left of each corner, so that a box's mean costs four lookups.
+struct Integral {
+    width: usize,
+    height: usize,
+    sums: Vec<f64>,
+}
+
+impl Integral {
+    fn of(gray: &Gray) -> Integral {
+        let stride = gray.width + 1;
+        let mut sums = vec![0.0; stride * (gray.height + 1)];
+        for y in 0..gray.height {
+            let mut row = 0.0;
+            for x in 0..gray.width {
+                row += gray.values[y * gray.width + x] as f64;
+                sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
+            }
+        }
+        Integral {
+            width: gray.width,
+            height: gray.height,
+            sums,
+        }
+    }
+
+    fn mean(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> f32 {
+        let stride = self.width + 1;
+        let sum =
+            self.sums[y1 * stride + x1] - self.sums[y0 * stride + x1] - self.sums[y1 * stride + x0]
+                + self.sums[y0 * stride + x0];
+        (sum / ((x1 - x0) * (y1 - y0)) as f64) as f32
+    }
+
+    fn sample(&self, (x, y, w, h): (f64, f64, f64, f64), width: usize, height: usize) -> Gray {
+        // Cell edges in pixels, rounded to the nearest pixel but a pixel apart at least.
+        let edges = |start: f64, length: f64, cells: usize, limit: usize| -> Vec<(usize, usize)> {
+            (0..cells)
+                .map(|cell| {
+                    let from = start + length * cell as f64 / cells as f64;
+                    let to = start + length * (cell + 1) as f64 / cells as f64;
+                    let from = (from.round().max(0.0) as usize).min(limit - 1);
+                    let to = (to.round() as usize).clamp(from + 1, limit);
+                    (from, to)
+                })
+                .collect()
+        };
+        let columns = edges(x, w, width, self.width);
+        let rows = edges(y, h, height, self.height);
+        let mut values = Vec::with_capacity(width * height);
+        for (y0, y1) in &rows {
+            for (x0, x1) in &columns {
+                values.push(self.mean(*x0, *y0, *x1, *y1));
+            }
+        }
+        Gray {
+            width,
+            height,
+            values,
+        }
+    }
+}
+
+// Normalized cross-correlation of two images of the same size: 1 when one is the other brighter
+// or with more contrast, -1 when it is the other inverted, around 0 when they are unrelated. An
+// image of one flat value correlates with nothing.
+pub fn ncc(a: &Gray, b: &Gray) -> f64 {
+    let count = a.values.len().min(b.values.len());
+    if count == 0 {
+        return 0.0;
+    }
+    let mean =
+        |values: &[f32]| values[..count].iter().map(|v| *v as f64).sum::<f64>() / count as f64;
+    let (mean_a, mean_b) = (mean(&a.values), mean(&b.values));
+    let (mut cross, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (a, b) in a.values.iter().zip(&b.values) {
+        let (a, b) = (*a as f64 - mean_a, *b as f64 - mean_b);
+        cross += a * b;
+        var_a += a * a;
+        var_b += b * b;
+    }
+    if var_a <= 1e-12 || var_b <= 1e-12 {
+        return 0.0;
+    }
+    cross / (var_a * var_b).sqrt()
+}
+
+// Where in `haystack` a window of the image's aspect looks most like it, with the score.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Match {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub score: f64,
+}
+
+// An image to compare windows with, and its detail, each summed for sampling at any size.
+struct Compared {
+    image: Integral,
+    detail: Integral,
+}
+
+impl Compared {
+    // `gray` as compared in windows `window` pixels wide, whose detail reaches out an eighth of
+    // the window.
+    fn of(gray: &Gray, window: f64) -> Compared {
+        let radius = (window / TEMPLATE_WIDTH as f64 * DETAIL_CELLS)
+            .round()
+            .max(1.0) as usize;
+        Compared {
+            image: Integral::of(gray),
+            detail: Integral::of(&gray.detail(radius)),
+        }
+    }
+
+    fn sample(&self, rect: (f64, f64, f64, f64), width: usize, height: usize) -> (Gray, Gray) {
+        (
+            self.image.sample(rect, width, height),
+            self.detail.sample(rect, width, height),
+        )
+    }
+}
+
+// How alike two samples are, each an image and its detail: `ncc` of the images, and of their
+// detail, which keeps featureless gradients, of which the outside of the set has plenty, from
+// passing for anything with a slope across it.
+fn similarity(a: &(Gray, Gray), b: &(Gray, Gray)) -> f64 {
+    (ncc(&a.0, &b.0) + ncc(&a.1, &b.1)) / 2.0
+}
+
+// Tries windows of `image`'s aspect from as large as fits in `haystack` down to
+// `MIN_WINDOW_PIXELS` wide, at positions a fraction of a window apart. Each is compared with the
+// image by `similarity` at the window's own resolution, up to `TEMPLATE_WIDTH` cells across, so
+// that small windows are not compared by detail they do not have. Returns the best `count`
+// windows, best first, leaving out any that mostly overlap a better one of a similar size.
+pub fn best_matches(haystack: &Gray, image: &Gray, count: usize) -> Vec<Match> {
+    let aspect = image.height as f64 / image.width as f64;
+    let (hay_width, hay_height) = (haystack.width as f64, haystack.height as f64);
+    let whole = (0.0, 0.0, image.width as f64, image.height as f64);
+    let mut matches = Vec::new();
+    let mut window = hay_width.min(hay_height / aspect);
+    while window >= MIN_WINDOW_PIXELS {
+        let height = window * aspect;
+        let cells = (window.round() as usize).min(TEMPLATE_WIDTH);
+        let rows = ((cells as f64 * aspect).round() as usize).max(1);
+        let compared = Compared::of(haystack, window);
+        let needle = Compared::of(image, image.width as f64).sample(whole, cells, rows);
+        let step = (window / POSITIONS_PER_WINDOW).max(1.0);
+        let columns = ((hay_width - window) / step).floor() as usize;
+        let rows = ((hay_height - height) / step).floor() as usize;
+        // The spare space is shared evenly at either end.
+        let left = (hay_width - window - columns as f64 * step) / 2.0;
+        let top = (hay_height - height - rows as f64 * step) / 2.0;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (x, y) = (left + column as f64 * step, top + row as f64 * step);
+                let sample =
+                    compared.sample((x, y, window, height), needle.0.width, needle.0.height);
+                matches.push(Match {
+                    x,
+                    y,
+                    width: window,
+                    height,
+                    score: similarity(&sample, &needle),
+                });
+            }
+        }
+        window *= WINDOW_STEP;
+    }
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score));
+    let mut best: Vec<Match> = Vec::new();
+    for candidate in matches {
+        if best.len() == count {
+            break;
+        }
+        let overlaps = best.iter().any(|better| {
+            let ratio = candidate.width / better.width;
+            let dx = (candidate.x + candidate.width / 2.0) - (better.x + better.width / 2.0);
+            let dy = (candidate.y + candidate.height / 2.0) - (better.y + better.height / 2.0);
+            (0.5..2.0).contains(&ratio) && dx.hypot(dy) < better.width.max(candidate.width) / 2.0
+        });
+        if !overlaps {
+            best.push(candidate);
+        }
+    }
+    best
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Located {
+    pub viewport: Viewport,
+    // How well a render of `viewport` matches the image, from `ncc`.
+    pub confidence: f64,
+    pub levels: usize,
+    // The search ran out of levels while still zooming in, so the view is likely deeper than it
+    // can go and `viewport` is only the best region it reached.
+    pub too_deep: bool,
+}
+
+impl Located {
+    pub fn likely(&self) -> bool {
+        self.confidence >= MIN_CONFIDENCE && !self.too_deep
+    }
+}
+
+// Searches for the view `image` shows, starting from `home`: the regions that match it best are
+// rendered closer and matched again, keeping the best `BEAM` at each level, until a match needs
+// no further zoom. Matches are then confirmed and nudged into place by rendering them at the
+// image's shape. `render` gives the intensity of a view at a size in pixels, or None once the
+// search should stop.
+pub fn locate(
+    image: &Gray,
+    home: Viewport,
+    mut render: impl FnMut(Viewport, Size) -> Option<Gray>,
+) -> Result<Located, String> {
+    const STOPPED: &str = "the search was stopped";
+    if image.width < 8 || image.height < 8 {
+        return Err(String::from("the image is too small to locate"));
+    }
+    let image = image.equalized();
+    if image.values.iter().all(|value| *value == image.values[0]) {
+        return Err(String::from("the image is one flat color"));
+    }
+    let aspect = image.height as f64 / image.width as f64;
+    let shape = |width: usize| {
+        Size::new(
+            width as f32,
+            (width as f64 * aspect).round().max(1.0) as f32,
+        )
+    };
+    let template = shape(TEMPLATE_WIDTH);
+    let needle = Compared::of(&image, image.width as f64).sample(
+        (0.0, 0.0, image.width as f64, image.height as f64),
+        TEMPLATE_WIDTH,
+        template.height as usize,
+    );
+    let search = shape(SEARCH_WIDTH);
+
+    // Views matched closely enough to need no further zoom, and regions to zoom into next.
+    let mut finished: Vec<(Viewport, f64)> = Vec::new();
+    let mut regions = vec![(home, 0.0)];
+    let mut levels = 0;
+    while !regions.is_empty() && levels < MAX_LEVELS {
+        levels += 1;
+        let mut next = Vec::new();
+        for (region, _) in regions {
+            let haystack = render(region, search).ok_or(STOPPED)?.equalized();
+            for found in best_matches(&haystack, &image, BEAM) {
+                let (center_re, center_im) = region.pixel_to_complex(
+                    Point::new(
+                        (found.x + found.width / 2.0) as f32,
+                        (found.y + found.height / 2.0) as f32,
+                    ),
+                    search,
+                );
+                let width = region.width * found.width / search.width as f64;
+                if region.width / width < FINAL_ZOOM {
+                    finished.push((Viewport::new(center_re, center_im, width), found.score));
+                } else {
+                    let zoomed = Viewport::new(center_re, center_im, width * ZOOM_OUT);
+                    next.push((zoomed, found.score));
+                }
+            }
+        }
+        // Regions matching worse than a view already settled on are not worth going into.
+        let settled = finished
+            .iter()
+            .map(|(_, score)| *score)
+            .fold(f64::MIN, f64::max);
+        next.retain(|(_, score)| *score > settled);
+        next.sort_by(|a, b| b.1.total_cmp(&a.1));
+        next.truncate(BEAM);
+        regions = next;
+    }
+    // Nothing settled on before running out of levels: the best regions reached stand in.
+    let too_deep = finished.is_empty();
+    if too_deep {
+        finished.extend(regions.iter().map(|(region, score)| {
+            (
+                Viewport::new(region.center_re, region.center_im, region.width / ZOOM_OUT),
+                *score,
+            )
+        }));
+    }
+    finished.sort_by(|a, b| b.1.total_cmp(&a.1));
+    finished.truncate(BEAM);
+
+    // Confirms each by a render of exactly its view, nudging its center and width while that
+    // scores better.
+    let mut score = |view: Viewport| {
+        render(view, template).map(|gray| {
+            let whole = (0.0, 0.0, gray.width as f64, gray.height as f64);
+            let sample = Compared::of(&gray.equalized(), gray.width as f64).sample(
+                whole,
+                gray.width,
+                gray.height,
+            );
+            similarity(&sample, &needle)
+        })
+    };
+    let mut best: Option<(Viewport, f64)> = None;
+    for (mut window, _) in finished {
+        let mut confidence = score(window).ok_or(STOPPED)?;
+        let mut step = 1.0 / 16.0;
+        for _ in 0..REFINE_ROUNDS {
+            let mut nudged = (window, confidence);
+            for (dx, dy, zoom) in [
+                (-1.0, 0.0, 0.0),
+                (1.0, 0.0, 0.0),
+                (0.0, -1.0, 0.0),
+                (0.0, 1.0, 0.0),
+                (0.0, 0.0, -1.0),
+                (0.0, 0.0, 1.0),
+            ] {
+                let candidate = Viewport::new(
+                    window.center_re + dx * step * window.width,
+                    window.center_im + dy * step * window.width,
+                    window.width * (1.0 + zoom * step),
+                );
+                let candidate_score = score(candidate).ok_or(STOPPED)?;
+                if candidate_score > nudged.1 {
+                    nudged = (candidate, candidate_score);
+                }
+            }
+            if nudged.1 > confidence {
+                (window, confidence) = nudged;
+            } else {
+                step /= 2.0;
+            }
+        }
+        if best.is_none_or(|best| confidence > best.1) {
+            best = Some((window, confidence));
+        }
+    }
+    let (viewport, confidence) = best.ok_or("nothing in the set looks like the image")?;
+    Ok(Located {
+        viewport,
+        confidence,
+        levels,
+        too_deep,
+    })
+}
+
+// The deepest magnification of the home view the search can reach.
+// How far past the home view an image can be and still be found dependably: as deep as the
+// smallest window in the first render. Deeper views are searched for, but their matches are
+// often wrong.
+pub fn max_magnification() -> f64 {
+    SEARCH_WIDTH as f64 / MIN_WINDOW_PIXELS
+}
+
+// Whether `path` names an image `locate` can read.
+pub fn is_image_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("png"))
+}
+
+// The luminance of a PNG file of any color type.
+pub fn read_png(path: &Path) -> Result<Gray, String> {
+    let file = fs::File::open(path).map_err(|err| err.to_string())?;
+    let mut decoder = png::Decoder::new(file);
+    decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let mut bytes = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut bytes)
+        .map_err(|err| err.to_string())?;
+    let (width, height) = (info.width as usize, info.height as usize);
+    let channels = info.color_type.samples();
+    let rgba: Vec<u8> = bytes[..info.buffer_size()]
+        .chunks_exact(channels)
+        .flat_map(|pixel| match pixel.len() {
+            1 | 2 => [pixel[0], pixel[0], pixel[0], 255],
+            _ => [pixel[0], pixel[1], pixel[2], 255],
+        })
+        .collect();
+    Ok(Gray::from_rgba(&rgba, width, height))
+}
+
+// What `locate` compares the image with: `viewport` rendered and colored as the app would show
+// it, or None if `cancel` stopped the render.
+pub fn render_gray<'a>(
+    pool: &'a ThreadPool,
+    fractal: FractalKind,
+    max_iterations: u32,
+    coloring_settings: &'a ColoringSettings,
+    cancel: &'a CancelToken,
+) -> impl Fn(Viewport, Size) -> Option<Gray> + 'a {
+    move |viewport, size| {
+        let params = FrameParams {
+            viewport,
+            max_iterations,
+            fractal,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: true,
+            arithmetic: Arithmetic::select(&viewport, size, false),
+        };
+        let buffer = render::threaded_fractal_calc(pool, size, params, cancel, |_| {}).ok()?;
+        let rgba = coloring::recolor(&buffer, coloring_settings, pool.max_count());
+        Some(Gray::from_rgba(&rgba, buffer.width, buffer.height))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZE: Size = Size::new(160.0, 120.0);
+
+    fn home() -> Viewport {
+        Viewport::home(FractalKind::Mandelbrot, SIZE)
+    }
+
+    // The home view as `locate` renders it.
+    fn home_frame() -> Gray {
+        let (pool, coloring, cancel) = (
+            ThreadPool::new(4),
+            ColoringSettings::default(),
+            CancelToken::new(),
+        );
+        let render = render_gray(&pool, FractalKind::Mandelbrot, 200, &coloring, &cancel);
+        render(home(), SIZE).expect("renders without a cancel request complete")
+    }
+
+    fn flat(frame: &Gray) -> Gray {
+        Gray {
+            values: vec![0.5; frame.values.len()],
+            ..frame.clone()
+        }
+    }
+
+    // Brightness and contrast do not change the correlation, only its sign when inverted.
+    #[test]
+    fn correlation_ignores_brightness() {
+        let frame = home_frame();
+        let brighter = Gray {
+            values: frame.values.iter().map(|v| v * 2.0 + 0.3).collect(),
+            ..frame.clone()
+        };
+        let inverted = Gray {
+            values: frame.values.iter().map(|v| 1.0 - v).collect(),
+            ..frame.clone()
+        };
+        for (name, other, expected) in [
+            ("itself", &frame, 1.0),
+            ("a brighter copy", &brighter, 1.0),
+            ("an inverted copy", &inverted, -1.0),
+            ("a flat image", &flat(&frame), 0.0),
+        ] {
+            let score = ncc(&frame, other);
+            assert!(
+                (score - expected).abs() <= 1e-6,
+                "a frame correlates {} with {}",
+                score,
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn crops_are_matched_where_they_were_cut_from() {
+        let frame = home_frame();
+        let (x, y, width, height) = (56.0, 30.0, 48.0, 36.0);
+        let crop = frame.sample((x, y, width, height), 48, 36);
+        let found = best_matches(&frame, &crop, 1).first().copied();
+        let matched = found.as_ref().is_some_and(|found| {
+            (found.x - x).abs() <= 4.0
+                && (found.y - y).abs() <= 4.0
+                && (found.width / width - 1.0).abs() < 0.2
+        });
+        assert!(
+            matched,
+            "a crop at ({}, {}) {} wide is matched at {:?}",
+            x, y, width, found
+        );
+    }
+
+    #[test]
+    fn the_home_view_is_located_from_a_render_of_it() {
+        let (pool, coloring, cancel) = (
+            ThreadPool::new(4),
+            ColoringSettings::default(),
+            CancelToken::new(),
+        );
+        let render = render_gray(&pool, FractalKind::Mandelbrot, 200, &coloring, &cancel);
+        let (home, frame) = (home(), home_frame());
+        let located = locate(&frame, home, &render);
+        let found = located.as_ref().is_ok_and(|located| {
+            located.likely()
+                && (located.viewport.center_re - home.center_re).abs() < home.width * 0.05
+                && (located.viewport.center_im - home.center_im).abs() < home.width * 0.05
+                && (located.viewport.width / home.width - 1.0).abs() < 0.1
+        });
+        assert!(found, "the home view is located as {:?}", located);
+        assert!(
+            locate(&flat(&frame), home, &render).is_err(),
+            "a flat image is located"
+        );
+    }
+}
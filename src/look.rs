@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::path::Path;
+
+use crate::json;
+use crate::palette::{Palette, PaletteWrap};
+use crate::postprocess::{self, Stage};
+use crate::settings::{ColoringMode, ColoringSettings, SolidColor};
+
+pub const LOOK_VERSION: u32 = 1;
+// Looks are recognised by this suffix, e.g. when a file is dropped on the window.
+pub const LOOK_SUFFIX: &str = ".look.json";
+// Keyword of the PNG text chunk that carries the look an image was colored with.
+pub const LOOK_PNG_KEYWORD: &str = "Mandelbrot look";
+
+// Everything that decides how iteration data is colored, independent of where it was rendered,
+// as shared between users. Modes and palettes are stored by name so that a look from a newer
+// version can be refused by name rather than misread. Settings added since the first version
+// read as their defaults from looks without them.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Look {
+    pub version: u32,
+    pub mode: String,
+    pub palette: String,
+    pub field_blend: f32,
+    pub gamma: f32,
+    pub density: f32,
+    #[serde(default)]
+    pub palette_offset: f32,
+    #[serde(default = "default_sectors")]
+    pub sectors: u32,
+    #[serde(default)]
+    pub hue_rotation: f32,
+    #[serde(default)]
+    pub occlusion: bool,
+    #[serde(default = "default_occlusion_radius")]
+    pub occlusion_radius: u32,
+    #[serde(default = "default_occlusion_strength")]
+    pub occlusion_strength: f32,
+    #[serde(default)]
+    pub post: Vec<Stage>,
+    #[serde(default = "default_interior_color")]
+    pub interior_color: String,
+    #[serde(default = "default_unresolved_color")]
+    pub unresolved_color: String,
+    // How the palette wraps past its ends.
+    #[serde(default = "default_palette_wrap")]
+    pub palette_wrap: String,
+}
+
+fn default_sectors() -> u32 {
+    ColoringSettings::default().sectors
+}
+
+fn default_occlusion_radius() -> u32 {
+    ColoringSettings::default().occlusion_radius
+}
+
+fn default_occlusion_strength() -> f32 {
+    ColoringSettings::default().occlusion_strength
+}
+
+fn default_interior_color() -> String {
+    variant_name(ColoringSettings::default().interior_color)
+}
+
+fn default_unresolved_color() -> String {
+    variant_name(ColoringSettings::default().unresolved_color)
+}
+
+fn default_palette_wrap() -> String {
+    variant_name(PaletteWrap::default())
+}
+
+// The name an enum's variant is stored under.
+fn variant_name<T: Serialize>(value: T) -> String {
+    toml::Value::try_from(value)
+        .ok()
+        .and_then(|value| value.as_str().map(String::from))
+        .unwrap_or_default()
+}
+
+fn solid_color(name: &str) -> Result<SolidColor, String> {
+    toml::Value::String(name.to_string())
+        .try_into()
+        .map_err(|_| format!("unsupported color \"{}\"", name))
+}
+
+impl Look {
+    pub fn of(settings: &ColoringSettings) -> Look {
+        Look {
+            version: LOOK_VERSION,
+            mode: variant_name(settings.mode),
+            palette: settings.palette.clone(),
+            field_blend: settings.field_blend,
+            gamma: settings.gamma,
+            density: settings.density,
+            palette_offset: settings.palette_offset,
+            sectors: settings.sectors,
+            hue_rotation: settings.hue_rotation,
+            occlusion: settings.occlusion,
+            occlusion_radius: settings.occlusion_radius,
+            occlusion_strength: settings.occlusion_strength,
+            post: settings.post.clone(),
+            interior_color: variant_name(settings.interior_color),
+            unresolved_color: variant_name(settings.unresolved_color),
+            palette_wrap: variant_name(settings.palette_wrap()),
+        }
+    }
+
+    // The coloring settings this look stands for, or what this version is missing to show it.
+    pub fn settings(&self) -> Result<ColoringSettings, String> {
+        if self.version > LOOK_VERSION {
+            return Err(format!(
+                "look version {} is newer than the supported version {}",
+                self.version, LOOK_VERSION
+            ));
+        }
+        let mode: ColoringMode = toml::Value::String(self.mode.clone())
+            .try_into()
+            .map_err(|_| format!("unsupported coloring mode \"{}\"", self.mode))?;
+        if !Palette::builtin()
+            .iter()
+            .any(|palette| palette.name == self.palette)
+        {
+            return Err(format!("unknown palette \"{}\"", self.palette));
+        }
+        postprocess::compile(&self.post)?;
+        let interior_color = solid_color(&self.interior_color)?;
+        let unresolved_color = solid_color(&self.unresolved_color)?;
+        let palette_wrap: PaletteWrap =
+            toml::Value::String(self.palette_wrap.clone())
+                .try_into()
+                .map_err(|_| format!("unsupported palette wrap \"{}\"", self.palette_wrap))?;
+        let mut settings = ColoringSettings {
+            mode,
+            palette: self.palette.clone(),
+            field_blend: self.field_blend,
+            gamma: self.gamma,
+            density: self.density,
+            palette_offset: self.palette_offset,
+            sectors: self.sectors,
+            hue_rotation: self.hue_rotation,
+            occlusion: self.occlusion,
+            occlusion_radius: self.occlusion_radius,
+            occlusion_strength: self.occlusion_strength,
+            post: self.post.clone(),
+            ..ColoringSettings::default()
+        };
+        settings.set_palette_wrap(palette_wrap);
+        settings.set_interior_color(interior_color);
+        settings.set_unresolved_color(unresolved_color);
+        Ok(settings)
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        json::to_string_pretty(self)
+    }
+
+    // The text chunks that record this look in an exported PNG.
+    pub fn png_text(&self) -> Vec<(&'static str, String)> {
+        self.to_json()
+            .map(|json| (LOOK_PNG_KEYWORD, json))
+            .into_iter()
+            .collect()
+    }
+
+    pub fn from_json(text: &str) -> Result<Look, String> {
+        json::from_str(text)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, self.to_json()?).map_err(|err| err.to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<Look, String> {
+        let text = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Look::from_json(&text)
+    }
+
+    pub fn is_look_file(path: &Path) -> bool {
+        path.to_str()
+            .is_some_and(|path| path.to_lowercase().ends_with(LOOK_SUFFIX))
+    }
+}
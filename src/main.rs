@@ -1,226 +1,5889 @@
+mod actions;
+#[cfg(feature = "sound")]
+mod audio;
+mod bookmarks;
+mod boundary_export;
+mod camera_path;
+mod checksum;
+mod clipboard;
+mod config;
+mod controls;
+mod exports;
+#[cfg(feature = "gamepad")]
+mod pad;
+mod soak;
+mod tui;
+mod watcher;
+mod zoom;
+
 use bytes::Bytes;
 
-use iced::event::{self, Event};
-use iced::widget::{canvas, container, image, stack};
-use iced::{
-    mouse, window, Color, Element, Fill, Point, Rectangle, Renderer, Size, Subscription, Theme,
-};
+use iced::event::{self, Event};
+use iced::futures::channel::mpsc;
+use iced::widget::{
+    button, canvas, column, container, image, row, scrollable, slider, stack, text, text_input,
+    tooltip,
+};
+use iced::{
+    keyboard, mouse, window, Border, Color, ContentFit, Element, Fill, Font, Point, Rectangle,
+    Renderer, Size, Subscription, Task, Theme, Vector,
+};
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use bookmarks::{Bookmarks, Preset, THUMBNAIL_SIZE};
+use clipboard::Clipboard;
+use config::Config;
+use controls::{Control, Setting};
+use mandelbrot::adjust::{self, Handle, Knob, Placed, Region};
+use mandelbrot::annotation::{self, Annotation, Marks, Part, Pin};
+use mandelbrot::boundary;
+use mandelbrot::buffers;
+use mandelbrot::camera::{self, CameraPath, Easing, Keyframe};
+use mandelbrot::coloring;
+use mandelbrot::coordinates;
+use mandelbrot::cursor::{self, PrecisionCursor};
+use mandelbrot::display::DisplayProfile;
+use mandelbrot::doubledouble::DoubleDouble;
+use mandelbrot::dpi;
+use mandelbrot::duty;
+use mandelbrot::eta::{self, Eta};
+use mandelbrot::export::{self, TemplateFields, LOCATION_PNG_KEYWORD};
+use mandelbrot::fractal::{
+    AngleKind, FractalKind, Hybrid, Rule, MAX_HYBRID_STEPS, MIN_HYBRID_STEPS,
+};
+use mandelbrot::guides::{Edge, CUSTOM_SAFE_AREA};
+use mandelbrot::history::{History, Snapshot};
+use mandelbrot::journal::{self, Journal, JournalEntry};
+use mandelbrot::levels::{self, Histogram};
+use mandelbrot::locate::{self, Located};
+use mandelbrot::look::Look;
+use mandelbrot::minibrot::{self, Candidate};
+use mandelbrot::numbers;
+use mandelbrot::onboarding::{self, Anchor, Gesture, Tour};
+use mandelbrot::palette::Palette;
+use mandelbrot::pasted::{self, Scale};
+use mandelbrot::perf::{self, RenderSample, RenderStats};
+use mandelbrot::postprocess::{self, Stage};
+use mandelbrot::power::{self, EnergySaver, ENERGY_SAVER_THREADS};
+use mandelbrot::prefetch::Prefetch;
+use mandelbrot::project::{Project, ProjectBookmark, PROJECT_EXTENSION};
+use mandelbrot::queue::{BatchSummary, ExportQueue, ExportSpec};
+use mandelbrot::rawdata;
+use mandelbrot::refine::{self, RefineStep};
+use mandelbrot::relative::{self, RelativeView};
+use mandelbrot::render::{
+    self, Arithmetic, CancelToken, ClassCounts, FrameParams, IterationBuffer, PixelRect, Progress,
+    RenderError, TileResult,
+};
+use mandelbrot::sampling::Sampling;
+#[cfg(feature = "http")]
+use mandelbrot::serve;
+use mandelbrot::settings::{ColoringSettings, Overrides, RenderSettings, SolidColor};
+use mandelbrot::store;
+use mandelbrot::tilecache::TILE_CACHE;
+use mandelbrot::tiling::{self, Focus};
+use mandelbrot::tune::{self, Tuning, DEFAULT_THREADS, TUNE_BUDGET};
+use mandelbrot::valve::{Permit, PreviewValve};
+use mandelbrot::viewport::{self, PixelTransform, Viewport};
+use mandelbrot::watchdog::{self, Prediction, Throughput, Verdict};
+
+#[derive(Clone, Debug)]
+enum Message {
+    EventOccurred(Event),
+    MaxIterationsChanged(u32),
+    ResolutionScaleChanged(f32),
+    AntialiasChanged(u32),
+    SettingsReleased,
+    PaletteCycled,
+    PaletteCycledBack,
+    PaletteWrapCycled,
+    ColoringModeCycled,
+    InteriorColorCycled,
+    UnresolvedColorCycled,
+    FieldBlendChanged(f32),
+    GammaChanged(f32),
+    DensityChanged(f32),
+    PaletteOffsetChanged(f32),
+    // Sets density, offset and gamma to spread the frame's escape times over the palette.
+    AutoLevelsApplied,
+    AutoLevelsAfterRenderToggled,
+    SectorsChanged(u32),
+    HueRotationChanged(f32),
+    OcclusionToggled,
+    OcclusionRadiusChanged(u32),
+    OcclusionStrengthChanged(f32),
+    // Post-processing: a built-in stage added by its index in `Stage::BUILTIN`, a stage's setting
+    // nudged up or down, a stage moved along the pipeline or removed, and an expression stage
+    // typed in and added.
+    PostStageAdded(usize),
+    PostStageAdjusted(usize, bool),
+    PostStageMoved(usize, isize),
+    PostStageRemoved(usize),
+    PostExpressionChanged(String),
+    PostExpressionSubmitted,
+    LookExported,
+    ColorManagementToggled,
+    BufferPrecisionCycled,
+    CoarsePrepassToggled,
+    DoubleDoubleToggled,
+    TileOrderToggled,
+    PerfHudToggled,
+    EnergySaverCycled,
+    CpuCapChanged(u32),
+    ExplorationLogToggled,
+    PrefetchToggled,
+    OrbitSoundToggled,
+    Undo,
+    Redo,
+    GotoChanged(String),
+    GotoSubmitted,
+    DecimalSeparatorCycled,
+    DigitGroupingToggled,
+    InvertYToggled,
+    CrosshairToggled,
+    ThirdsToggled,
+    GuideColorCycled,
+    GuideOpacityCycled,
+    GuidesInExportsToggled,
+    // Pins a new label at the cursor and opens its editor.
+    AnnotationAdded,
+    // Pressing on a part of an annotation, which starts dragging it and opens its editor.
+    AnnotationGrabbed(u64, Part),
+    AnnotationDragged(Point),
+    AnnotationReleased,
+    AnnotationTextChanged(String),
+    // Switches the annotation being edited between a plain label and an arrow.
+    AnnotationArrowToggled,
+    // Hides the annotation being edited at views wider than this one, or with false narrower.
+    AnnotationRangeSet(bool),
+    AnnotationRangeCleared,
+    AnnotationDeleted,
+    AnnotationEditorClosed,
+    AnnotationsToggled,
+    AnnotationsInExportsToggled,
+    // Shows the adjustment regions' outlines and handles and their editor, or hides them.
+    RegionsEdited,
+    RegionAdded,
+    // Pressing on a region's handle, which starts dragging it and picks the region to edit.
+    RegionGrabbed(u64, Handle),
+    RegionDragged(Point),
+    RegionReleased,
+    RegionShapeToggled,
+    // Steps a setting of the region being edited up, with true, or down.
+    RegionAdjusted(Knob, bool),
+    RegionDeleted,
+    SafeAreaCycled,
+    SafeMarginChanged(Edge, f32),
+    ExportPaddingChanged(u32),
+    // Steps the profile exports are queued with: the active one, then each by name.
+    ExportProfileCycled,
+    OpenPathChanged(String),
+    OpenPathSubmitted,
+    // A look, project, data or image file to open, dropped on the window, typed in the settings
+    // or named on the command line.
+    FileOpened(PathBuf),
+    // Where the search for an opened image's view ended up.
+    ImageLocated(Result<Located, String>),
+    ProfileCycled,
+    QuickExported,
+    ImageCopied,
+    // Ctrl+Shift+L: copies the view as "re im width", every digit included.
+    LocationCopied,
+    // Ctrl+V: reads the clipboard's text and goes on as TextPasted.
+    PasteRequested,
+    // Text pasted in, offered as a view to jump to when it holds coordinates.
+    TextPasted(String),
+    PasteConfirmed,
+    PasteDismissed,
+    // Ctrl+P: opens the command palette, or closes it.
+    CommandPaletteToggled,
+    CommandQueryChanged(String),
+    // Runs the palette's best match for its query.
+    CommandSubmitted,
+    CommandRun(&'static str),
+    // Opens the settings with that setting focused, for commands that take a parameter.
+    SettingFocused(Setting),
+    DataExported,
+    // The peek key went down or up.
+    Peeked(bool),
+    // Writes the set's boundary in the current frame as plotting data.
+    BoundaryExported(boundary::Format),
+    // Saves the piece as a project file, with the frame if asked for.
+    ProjectSaved(bool),
+    SettingsToggled,
+    TourSkipped,
+    DataFileClosed,
+    ViewReset,
+    FractalCycled,
+    // Switches the hybrid step with this index to the next rule, adds a Mandelbrot step at the
+    // end, or removes the step with this index.
+    HybridStepCycled(usize),
+    HybridStepAdded,
+    HybridStepRemoved(usize),
+    // Moves the view by that many view widths right and up.
+    ViewPanned(f64, f64),
+    // Scales the view's width by that factor about its center.
+    ViewZoomed(f64),
+    // Region selection in window coordinates, driven by a mouse drag or the keyboard alike.
+    SelectionStarted(Point),
+    SelectionResized(Point),
+    // The raw cursor while dragging out a selection, followed slowly while Shift is held.
+    SelectionDragged(Point),
+    SelectionMoved(Vector),
+    SelectionCommitted,
+    SelectionCancelled,
+    // Keyboard focus: moved that many controls along the Tab order, activated, adjusted by that
+    // many steps (negative for left) or asked to delete what it is on.
+    FocusMoved(isize),
+    FocusActivated,
+    FocusAdjusted(i32),
+    FocusDeleted,
+    // Closes every panel and drops the keyboard focus.
+    PanelsDismissed,
+    BookmarkAdded,
+    // A bookmark or preset to go to, and whether to apply its settings as well as its view.
+    BookmarkSelected(u64, bool),
+    BookmarkDeleted(u64),
+    // Switches a bookmark between restoring only its view and restoring its settings too.
+    BookmarkRestoreToggled(u64),
+    PresetSelected(usize, bool),
+    BookmarksToggled,
+    // Another window may have saved the bookmark file.
+    BookmarksChanged,
+    MinibrotsToggled,
+    MinibrotsRequested,
+    // Minibrots found in the frame installed as that generation.
+    MinibrotsFound(u64, Vec<Candidate>),
+    MinibrotSelected(usize),
+    // Takes the minibrot under the cursor, or the scanned one, as the anchor relative bookmarks
+    // are added at and applied to.
+    AnchorAtCursor,
+    MinibrotAnchored(usize),
+    RelativeBookmarkAdded,
+    RelativeBookmarkApplied(u64),
+    JournalToggled,
+    JournalSelected(u64),
+    JournalExported,
+    PathToggled,
+    // Adds the current view to the end of the camera path.
+    KeyframeAdded,
+    KeyframeSelected(usize),
+    KeyframeDeleted(usize),
+    KeyframeHoldCycled(usize),
+    KeyframeTravelCycled(usize),
+    KeyframeEasingCycled(usize),
+    // Seconds into the camera path to preview.
+    PathScrubbed(f64),
+    PathScrubReleased,
+    ExportQueued,
+    QueueToggled,
+    // Moves a queued export by that many places, negative towards the front.
+    ExportMoved(u64, isize),
+    ExportPauseToggled(u64),
+    ExportCancelled(u64),
+    ExportProgress(u64, Progress),
+    // The file written, if the app persists anything.
+    ExportFinished(u64, Result<Option<PathBuf>, String>),
+    TuneRequested,
+    TuneProgress(f32),
+    TuneFinished(Result<Tuning, String>),
+    TuneCancelled,
+    ThumbnailReady(String, image::Handle),
+    Render(u64, RenderEvent),
+    // Sent every `WATCHDOG_INTERVAL` while a render runs, to check that it is progressing.
+    WatchdogTick(u64),
+    // What to do about a render the watchdog reported: wait on, give it up, or give it up for a
+    // draft of the view.
+    StallWaited,
+    StallCancelled,
+    StallDrafted,
+    // Sent once the input pause may have been long enough to start refining that generation.
+    IdleTick(u64),
+    // One piece of the prefetched margin around that generation's view, computed or not.
+    Prefetched(u64, PixelRect, Result<Vec<TileResult>, RenderError>),
+    // The window's scale factor, queried after it was opened, moved or resized.
+    ScaleFactorChanged(f32),
+    // Sent a moment after a scale factor change; stale unless it carries the latest token.
+    ScaleSettled(u64),
+}
+
+// How long a new scale factor has to hold before the frame is re-rendered for it, since moving a
+// window across monitors can flip it back and forth.
+const SCALE_SETTLE: Duration = Duration::from_millis(250);
+// How close the cursor has to be to the crosshair's center for its coordinates to show.
+const CROSSHAIR_HOVER_RADIUS: f32 = 12.0;
+// Opacity of the safe area's covered bands, relative to the guides'.
+const SAFE_AREA_SHADE: f32 = 0.4;
+// How often a running export reports its progress.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// How often a running render is checked for progress.
+const WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+// A draft renders this fraction of the frame's resolution, without supersampling and with at
+// most this many iterations.
+const DRAFT_SCALE: f32 = 0.25;
+const DRAFT_ITERATIONS: u32 = 250;
+// How often user input may trigger a look at the battery status, for the energy saver.
+const BATTERY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+// Least time between saves of cached tiles to disk while the app sits idle.
+const TILE_SAVE_INTERVAL: Duration = Duration::from_secs(300);
+// How far an arrow key moves or resizes the keyboard selection, as a fraction of the window.
+const SELECTION_STEP: f32 = 0.02;
+// How far an arrow key pans the view, in view widths.
+const PAN_STEP: f64 = 0.1;
+// Held to show the frame before the current one.
+const PEEK_KEY: &str = "\\";
+const NO_ANCHOR: &str = "no anchor: press N over a minibrot or pick one in the minibrots panel";
+// Where a new arrow starts, from the point it shows, in window pixels.
+const ARROW_REACH: Vector = Vector::new(60.0, 60.0);
+const GOTO_INPUT: &str = "goto";
+const POST_INPUT: &str = "post";
+const OPEN_INPUT: &str = "open";
+const COMMAND_INPUT: &str = "command";
+// Matches the command palette lists at most.
+const COMMAND_MATCHES: usize = 12;
+// Seconds a keyframe's hold and travel cycle through.
+const HOLD_STEPS: [f64; 5] = [0.0, 0.5, 1.0, 2.0, 4.0];
+const TRAVEL_STEPS: [f64; 5] = [1.0, 2.0, 4.0, 8.0, 16.0];
+
+#[derive(Clone, Debug)]
+enum RenderEvent {
+    Progress(Progress),
+    // The frame so far, for a preview, holding its place in flight until dropped.
+    Partial(Arc<IterationBuffer>, Permit),
+    // The tiles computed so far, with the order they were taken in.
+    TileOrder(Vec<(PixelRect, usize)>),
+    Finished(Result<Arc<IterationBuffer>, RenderError>),
+}
+
+// What the image on screen shows: the view, the fractal and the coloring it was painted with.
+#[derive(Clone, Debug)]
+struct ShownView {
+    viewport: Viewport,
+    fractal: FractalKind,
+    coloring: ColoringSettings,
+}
+
+// The render currently running in the background; results tagged with any other generation are
+// stale and dropped.
+#[derive(Debug)]
+struct RenderJob {
+    generation: u64,
+    antialias: usize,
+    cancel: CancelToken,
+    progress: Option<Progress>,
+    eta: Eta,
+    // An idle-time improvement of the installed frame rather than a render the user asked for;
+    // it shares that frame's generation and is cancelled by any input.
+    refining: bool,
+    started: Instant,
+    // How long the render should take, and how far into it the user chose to wait on after the
+    // watchdog reported it; the watchdog's clock starts over from there.
+    prediction: Prediction,
+    waited: Duration,
+    // Set while the watchdog reports it.
+    stalled: bool,
+}
+
+#[derive(Debug)]
+struct Mandelbrot {
+    current_mouse_location: Point,
+    draw_bounding_box: bool,
+    // Whether the left button is down, so the selection follows the cursor.
+    dragging: bool,
+    start_location: Point,
+    end_location: Point,
+    // The dragged corner of the selection, to sub-pixel precision.
+    cursor: PrecisionCursor,
+    viewport: Viewport,
+    // Logical size; multiplied by the scale factor for the surface's physical pixels.
+    window_size: Size,
+    scale_factor: f64,
+    scale_token: u64,
+    // The largest image the screen can show, and whether the user was told frames are capped to it.
+    texture_limit: (u32, u32),
+    texture_capped: bool,
+    threadpool: ThreadPool,
+    config: Config,
+    // False in headless runs: nothing is written to disk and no thumbnails are generated.
+    persist: bool,
+    // The first-run tour, while it shows.
+    tour: Option<Tour>,
+    show_settings: bool,
+    // The control keyboard input goes to; it may be in a panel that has since closed.
+    focused: Option<Control>,
+    generation: u64,
+    installed_generation: u64,
+    rendering: Option<RenderJob>,
+    // Recent render speed, for predicting how long the next one should take.
+    throughput: Throughput,
+    buffer: Arc<IterationBuffer>,
+    buffer_antialias: usize,
+    // How many of the installed frame's pixels escaped, were proven inside, or are unresolved.
+    pixel_classes: ClassCounts,
+    image: image::Handle,
+    image_size: (u32, u32),
+    frame: Bytes,
+    shown_view: Option<ShownView>,
+    // The installed frame as last colored, and the frame installed before it, with their sizes.
+    // The previous one is shown instead while the peek key is held; it is dropped when the
+    // resolution changes rather than kept beside a frame of another size.
+    settled_image: Option<(image::Handle, (u32, u32))>,
+    previous_image: Option<(image::Handle, (u32, u32))>,
+    peeking: bool,
+    // What the frame is converted through for the screen, while color management is on.
+    display_profile: Option<DisplayProfile>,
+    status_message: String,
+    // Set while showing a frame loaded from a data file instead of one rendered here.
+    data_file: Option<PathBuf>,
+    history: History<Snapshot>,
+    goto_input: String,
+    post_input: String,
+    open_input: String,
+    bookmarks: Bookmarks,
+    show_bookmarks: bool,
+    // Thumbnails by file name, and those being generated.
+    thumbnails: HashMap<String, image::Handle>,
+    thumbnails_requested: HashSet<String>,
+    // Every view the session settled on, with the thumbnails of its entries by id.
+    journal: Journal,
+    journal_thumbnails: HashMap<u64, image::Handle>,
+    show_journal: bool,
+    camera_path: CameraPath,
+    show_path: bool,
+    // Finished renders, for the performance HUD shown while `show_hud` is.
+    render_stats: RenderStats,
+    show_hud: bool,
+    // Seconds into the camera path the preview was last scrubbed to.
+    scrub: f64,
+    show_minibrots: bool,
+    // The last scan's findings, and the generation of the frame being scanned, if any.
+    minibrots: Vec<Candidate>,
+    minibrot_scan: Option<u64>,
+    anchor: Option<relative::Anchor>,
+    // Idle refinement steps applied to the installed frame.
+    refinement: usize,
+    // The margin computed around the view while idle, and what stops the piece in progress.
+    prefetch: Option<Arc<Prefetch>>,
+    prefetching: Option<CancelToken>,
+    last_input: Instant,
+    // The modifier keys held, so that a Shift-click on a bookmark can restore its settings.
+    modifiers: keyboard::Modifiers,
+    // Plays hovered orbits while the orbit sound is on, started when first needed.
+    #[cfg(feature = "sound")]
+    sound: Option<audio::Sonifier>,
+    clipboard: Clipboard,
+    // The view found in pasted text or a located image, until the user jumps to it or dismisses
+    // it.
+    paste_offer: Option<Viewport>,
+    // The annotation whose editor is open.
+    editing_annotation: Option<u64>,
+    // The part of an annotation being dragged, and how far the pointer is from it.
+    annotation_grab: Option<(u64, Part, Vector)>,
+    // Whether the adjustment regions are shown for editing, and the one being edited.
+    editing_regions: bool,
+    selected_region: Option<u64>,
+    // The handle of a region being dragged, and how far the pointer is from it.
+    region_grab: Option<(u64, Handle, Vector)>,
+    // Whether an opened image is being searched for.
+    locating: bool,
+    // What is typed into the command palette, while it is open.
+    command_query: Option<String>,
+    // Where renders start, in render pixels: the cursor while it is over the window.
+    focus: Focus,
+    // Tiles of the frame on screen in computation order, for the debug overlay.
+    tile_order: Vec<(PixelRect, usize)>,
+    // Exports run one at a time on a pool of their own, so that pausing one while the view
+    // renders leaves all of the render pool to the view.
+    exports: ExportQueue,
+    export_pool: ThreadPool,
+    // The running export and what stops or pauses it.
+    export_cancel: Option<(u64, CancelToken)>,
+    // Time left in the running export.
+    export_eta: Eta,
+    // Files exports wrote this run by the hash of their settings, so that an export of the same
+    // image links to one instead of rendering it again, and what the queue did since it was last
+    // empty.
+    exported: HashMap<u64, PathBuf>,
+    batch: BatchSummary,
+    show_queue: bool,
+    // What stops the tuner while it runs, and how far it got.
+    tune_cancel: Option<CancelToken>,
+    tune_progress: f32,
+    // Battery status as last checked, for the energy saver's automatic mode.
+    on_battery: bool,
+    battery_checked: Instant,
+    tiles_saved: Instant,
+}
+
+impl Default for Mandelbrot {
+    fn default() -> Self {
+        Mandelbrot::new(Config::load(), true)
+    }
+}
+
+impl Mandelbrot {
+    fn new(config: Config, persist: bool) -> Self {
+        let on_battery =
+            config.energy_saver == EnergySaver::OnBattery && power::on_battery().unwrap_or(false);
+        let tour = (!config.onboarded).then(|| Tour::new(&onboarding::STEPS));
+        let mut app = Mandelbrot {
+            current_mouse_location: Point::new(-0.5, 0.0),
+            draw_bounding_box: false,
+            dragging: false,
+            start_location: Point::default(),
+            end_location: Point::default(),
+            cursor: PrecisionCursor::default(),
+            viewport: Viewport::home(config.fractal, Size::new(1200.0, 720.0)),
+            window_size: Size::new(1200.0, 720.0),
+            scale_factor: 1.0,
+            scale_token: 0,
+            texture_limit: dpi::TEXTURE_LIMIT,
+            texture_capped: false,
+            threadpool: ThreadPool::new(DEFAULT_THREADS),
+            config,
+            persist,
+            tour,
+            show_settings: false,
+            focused: None,
+            generation: 0,
+            installed_generation: 0,
+            rendering: None,
+            throughput: Throughput::default(),
+            buffer: Arc::default(),
+            buffer_antialias: 1,
+            pixel_classes: ClassCounts::default(),
+            image: image::Handle::from_rgba(0, 0, Vec::new()),
+            image_size: (0, 0),
+            frame: Bytes::new(),
+            shown_view: None,
+            settled_image: None,
+            previous_image: None,
+            peeking: false,
+            display_profile: None,
+            status_message: String::new(),
+            data_file: None,
+            history: History::default(),
+            goto_input: String::new(),
+            post_input: String::new(),
+            open_input: String::new(),
+            bookmarks: if persist {
+                Bookmarks::load()
+            } else {
+                Bookmarks::default()
+            },
+            show_bookmarks: false,
+            thumbnails: HashMap::new(),
+            thumbnails_requested: HashSet::new(),
+            journal: Journal::start(
+                persist
+                    .then(|| dirs::config_dir().map(|dir| dir.join("mandelbrot").join("journal")))
+                    .flatten(),
+            ),
+            journal_thumbnails: HashMap::new(),
+            show_journal: false,
+            camera_path: if persist {
+                camera_path::load()
+            } else {
+                CameraPath::default()
+            },
+            show_path: false,
+            render_stats: RenderStats::default(),
+            show_hud: false,
+            scrub: 0.0,
+            show_minibrots: false,
+            minibrots: Vec::new(),
+            minibrot_scan: None,
+            anchor: None,
+            refinement: 0,
+            prefetch: None,
+            prefetching: None,
+            last_input: Instant::now(),
+            modifiers: keyboard::Modifiers::default(),
+            #[cfg(feature = "sound")]
+            sound: None,
+            clipboard: Clipboard::default(),
+            paste_offer: None,
+            editing_annotation: None,
+            annotation_grab: None,
+            editing_regions: false,
+            selected_region: None,
+            region_grab: None,
+            locating: false,
+            command_query: None,
+            focus: Focus::default(),
+            tile_order: Vec::new(),
+            exports: if persist {
+                exports::load()
+            } else {
+                ExportQueue::default()
+            },
+            export_pool: ThreadPool::new(DEFAULT_THREADS),
+            export_cancel: None,
+            export_eta: Eta::default(),
+            exported: HashMap::new(),
+            batch: BatchSummary::default(),
+            show_queue: false,
+            tune_cancel: None,
+            tune_progress: 0.0,
+            on_battery,
+            battery_checked: Instant::now(),
+            tiles_saved: Instant::now(),
+        };
+        if !app.config.resume_exports {
+            for job in &mut app.exports.jobs {
+                job.paused = true;
+            }
+        }
+        app.load_display_profile();
+        tiling::set_chunk_size(app.tuning().chunk_size);
+        app.threadpool.set_num_threads(app.render_threads());
+        app.export_pool.set_num_threads(app.render_threads());
+        TILE_CACHE.set_budget(app.config.tile_cache_mb as usize * 1024 * 1024);
+        TILE_CACHE.set_disk(
+            persist
+                .then(|| dirs::cache_dir().map(|dir| dir.join("mandelbrot").join("tiles")))
+                .flatten(),
+            app.config.tile_disk_mb as usize * 1024 * 1024,
+        );
+        duty::set_cap(app.config.cpu_cap);
+        // Stores found unreadable on the way here were set aside; say so where it is seen.
+        let warnings = store::take_warnings();
+        if !warnings.is_empty() {
+            app.status_message = warnings.join("; ");
+        }
+        app
+    }
+
+    // Whether background activity is suspended, to save energy.
+    fn energy_saving(&self) -> bool {
+        match self.config.energy_saver {
+            EnergySaver::Off => false,
+            EnergySaver::On => true,
+            EnergySaver::OnBattery => self.on_battery,
+        }
+    }
+
+    // The stored tuning if it was measured on this machine, otherwise the defaults.
+    fn tuning(&self) -> Tuning {
+        self.config
+            .tuning
+            .clone()
+            .filter(Tuning::is_current)
+            .unwrap_or_else(Tuning::heuristic)
+    }
+
+    fn render_threads(&self) -> usize {
+        let threads = self.tuning().threads;
+        if self.energy_saving() {
+            ENERGY_SAVER_THREADS.min(threads)
+        } else {
+            threads
+        }
+    }
+
+    // Brings the worker count and idle refinement in line with the energy saver after it turned
+    // on or off. Returns the wait for idle when refinement may resume.
+    fn energy_saver_changed(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        self.threadpool.set_num_threads(self.render_threads());
+        self.export_pool.set_num_threads(self.render_threads());
+        if self.energy_saving() {
+            if let Some(job) = self.rendering.as_ref().filter(|job| job.refining) {
+                job.cancel.cancel();
+            }
+            if let Some(cancel) = self.prefetching.take() {
+                cancel.cancel();
+            }
+            return None;
+        }
+        (self.idle_work() && self.rendering.is_none()).then(|| self.schedule_idle())
+    }
+
+    // Loads the display profile the config names while color management is on, and drops it
+    // otherwise. A profile that cannot be used leaves the frame in sRGB and says why.
+    fn load_display_profile(&mut self) {
+        self.display_profile = None;
+        if !self.config.color_management {
+            return;
+        }
+        let Some(path) = self.config.display_profile.clone() else {
+            self.status_message =
+                String::from("color management needs display_profile in the config");
+            println!("{}", self.status_message);
+            return;
+        };
+        match DisplayProfile::load(&path) {
+            Ok(profile) => {
+                println!("color managed for {}", profile.path().display());
+                self.display_profile = Some(profile);
+            }
+            Err(err) => {
+                self.status_message =
+                    format!("cannot use display profile {}: {}", path.display(), err);
+                println!("{}", self.status_message);
+            }
+        }
+    }
+
+    fn check_battery(&mut self, force: bool) {
+        if self.config.energy_saver != EnergySaver::OnBattery
+            || (!force && self.battery_checked.elapsed() < BATTERY_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.battery_checked = Instant::now();
+        self.on_battery = power::on_battery().unwrap_or(false);
+    }
+
+    // Saves the bookmarks, merged with whatever another window saved meanwhile.
+    fn save_bookmarks(&mut self) {
+        if !self.persist {
+            return;
+        }
+        if let Some(summary) = self.bookmarks.save().and_then(|merged| merged.summary()) {
+            self.status_message = format!("bookmarks from another window: {}", summary);
+        }
+    }
+
+    fn save_config(&self) {
+        if self.persist {
+            self.config.save();
+        }
+    }
+
+    // The window title, leading with how far the render has got so that task switchers and
+    // taskbars without live previews still show it.
+    fn title(&self) -> String {
+        match self.rendering.as_ref().and_then(|job| job.progress) {
+            Some(progress) => format!("{:.0}% - Mandelbrot", progress.fraction() * 100.0),
+            None => String::from("Mandelbrot"),
+        }
+    }
+
+    fn view(&self) -> Element<'_, Message> {
+        let (guides, center_label) = self.guide_overlay();
+        let shown = match &self.previous_image {
+            Some((previous, _)) if self.peeking => previous,
+            _ => &self.image,
+        };
+        let mut layers = stack![
+            image(shown.clone()).width(Fill).height(Fill).content_fit(
+                if self.data_file.is_some() {
+                    ContentFit::Contain
+                } else {
+                    ContentFit::Fill
+                }
+            ),
+            container(
+                canvas(RectangleProgram {
+                    region: Rectangle {
+                        x: self.start_location.x,
+                        y: self.start_location.y,
+                        width: self.end_location.x - self.start_location.x,
+                        height: self.end_location.y - self.start_location.y,
+                    },
+                    draw_bounding_box: self.draw_bounding_box,
+                    tiles: self.tile_overlay(),
+                    guides,
+                    guide_color: Color::from(self.config.guides.color.rgb())
+                        .scale_alpha(self.config.guides.opacity),
+                    safe_regions: self.safe_area_overlay(),
+                    annotations: self.annotation_marks(),
+                    editing_annotation: self.editing_annotation,
+                    regions: self.region_outlines(),
+                    selected_region: self.selected_region,
+                    center_label,
+                    tile_note: self.tile_note(),
+                })
+                .width(Fill)
+                .height(Fill),
+            ),
+            container(self.status_bar()).align_bottom(Fill),
+        ];
+        if self.show_settings {
+            layers = layers.push(container(self.settings_panel()).align_right(Fill));
+        }
+        if self.show_bookmarks {
+            layers = layers.push(container(self.bookmarks_panel()).align_left(Fill));
+        }
+        if self.show_minibrots {
+            layers = layers.push(container(self.minibrots_panel()).align_left(Fill));
+        }
+        if self.show_queue {
+            layers = layers.push(container(self.queue_panel()).align_left(Fill));
+        }
+        if self.show_journal {
+            layers = layers.push(container(self.journal_panel()).align_left(Fill));
+        }
+        if self.show_path {
+            layers = layers.push(container(self.path_panel()).align_left(Fill));
+        }
+        let banners: Vec<_> = [
+            self.stall_banner(),
+            self.paste_banner(),
+            self.annotation_editor(),
+            self.region_editor(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        if !banners.is_empty() {
+            layers = layers.push(container(column(banners).spacing(4)).center_x(Fill));
+        }
+        if self.show_hud {
+            layers = layers.push(container(self.perf_hud()).align_right(Fill).padding(8));
+        }
+        if let Some(palette) = self.command_palette() {
+            layers = layers.push(container(palette).center_x(Fill).padding(60));
+        }
+        if let Some(callout) = self.tour_callout() {
+            layers = layers.push(callout);
+        }
+        layers.into()
+    }
+
+    // Render timings and engine state, in a corner over the image.
+    fn perf_hud(&self) -> Element<'_, Message> {
+        let lines = perf::hud_lines(
+            &self.render_stats,
+            self.threadpool.max_count(),
+            duty::cap(),
+            &TILE_CACHE.stats(),
+            buffers::FRAMES.bytes() + buffers::TILES.bytes() + buffers::RGBA.bytes(),
+            self.generation,
+        );
+        container(text(lines.join("\n")).font(Font::MONOSPACE).size(13))
+            .padding(8)
+            .style(container::dark)
+            .into()
+    }
+
+    // The tour's current step over the dimmed window, by the part of it the step is about. Only
+    // the skip button takes input; everything else reaches the view beneath.
+    fn tour_callout(&self) -> Option<Element<'_, Message>> {
+        let tour = self.tour.as_ref()?;
+        let step = tour.step()?;
+        let (number, steps) = tour.position();
+        let callout = container(
+            column![
+                text(step.text).size(20),
+                row![
+                    text(format!("Step {} of {}", number, steps)),
+                    button(text("Skip tour")).on_press(Message::TourSkipped),
+                ]
+                .spacing(12),
+            ]
+            .spacing(8),
+        )
+        .padding(12)
+        .style(container::dark);
+        let layer = container(callout)
+            .width(Fill)
+            .height(Fill)
+            .padding(40)
+            .style(|_| container::Style::default().background(Color::BLACK.scale_alpha(0.4)));
+        let layer = match step.anchor {
+            Anchor::Center => layer.center(Fill),
+            Anchor::Left => layer.align_left(Fill).center_y(Fill),
+            Anchor::Right => layer.align_right(Fill).center_y(Fill),
+            Anchor::Bottom => layer.center_x(Fill).align_bottom(Fill),
+        };
+        Some(layer.into())
+    }
+
+    // Moves the tour on when `gesture` is what its step asks for, and ends it after the last.
+    fn observe_gesture(&mut self, gesture: Gesture) {
+        let Some(tour) = &mut self.tour else {
+            return;
+        };
+        if tour.observe(gesture) && tour.finished() {
+            self.end_tour();
+        }
+    }
+
+    fn end_tour(&mut self) {
+        self.tour = None;
+        self.config.onboarded = true;
+        self.save_config();
+    }
+
+    // Shown over the top of the window while the watchdog reports the render stuck. The view
+    // stays usable, and any new render replaces the stuck one.
+    fn stall_banner(&self) -> Option<Element<'_, Message>> {
+        let job = self.rendering.as_ref().filter(|job| job.stalled)?;
+        Some(
+            container(
+                row![
+                    text(format!(
+                        "Rendering has stopped progressing ({:.0} s, about {:.0} s expected)",
+                        job.started.elapsed().as_secs_f64(),
+                        job.prediction.frame.as_secs_f64()
+                    )),
+                    button(text("Keep waiting")).on_press(Message::StallWaited),
+                    button(text("Cancel")).on_press(Message::StallCancelled),
+                    button(text("Show a draft")).on_press(Message::StallDrafted),
+                ]
+                .spacing(8),
+            )
+            .padding(8)
+            .style(container::dark)
+            .into(),
+        )
+    }
+
+    // The command palette: the query, and the commands matching it with their shortcuts. Enter
+    // runs the first.
+    fn command_palette(&self) -> Option<Element<'_, Message>> {
+        let query = self.command_query.as_ref()?;
+        let matches = actions::ranked(query, &self.config.recent_commands);
+        let mut list = column![].spacing(2);
+        for (index, action) in matches.into_iter().take(COMMAND_MATCHES).enumerate() {
+            let shortcut = action
+                .shortcuts
+                .first()
+                .map(|shortcut| shortcut.label())
+                .unwrap_or_default();
+            list = list.push(
+                button(
+                    row![
+                        text(action.title).width(Fill),
+                        text(action.category.name()).size(12),
+                        text(shortcut).width(110),
+                    ]
+                    .spacing(12),
+                )
+                .width(Fill)
+                .style(if index == 0 {
+                    button::primary
+                } else {
+                    button::secondary
+                })
+                .on_press(Message::CommandRun(action.name)),
+            );
+        }
+        if query.is_empty() && self.config.recent_commands.is_empty() {
+            list = list.push(text("Type to search every command"));
+        }
+        Some(
+            container(
+                column![
+                    text_input("Command", query)
+                        .id(text_input::Id::new(COMMAND_INPUT))
+                        .on_input(Message::CommandQueryChanged)
+                        .on_submit(Message::CommandSubmitted),
+                    list,
+                ]
+                .spacing(8),
+            )
+            .width(480)
+            .padding(12)
+            .style(container::dark)
+            .into(),
+        )
+    }
+
+    // Asks before jumping to a view found in pasted text, since text can hold numbers that only
+    // look like coordinates.
+    fn paste_banner(&self) -> Option<Element<'_, Message>> {
+        let offer = self.paste_offer?;
+        Some(
+            container(
+                row![
+                    text(format!(
+                        "Jump to {} at width {:e}?",
+                        self.format_point(offer.center_re, offer.center_im),
+                        offer.width
+                    )),
+                    button(text("Jump")).on_press(Message::PasteConfirmed),
+                    button(text("Cancel")).on_press(Message::PasteDismissed),
+                ]
+                .spacing(8),
+            )
+            .padding(8)
+            .style(container::dark)
+            .into(),
+        )
+    }
+
+    // The editor of the annotation picked or just added, shown over the top of the window.
+    fn annotation_editor(&self) -> Option<Element<'_, Message>> {
+        let id = self.editing_annotation?;
+        let annotation = self
+            .config
+            .state()
+            .annotations
+            .iter()
+            .find(|annotation| annotation.id == id)?;
+        let width =
+            |width: Option<f64>| width.map_or(String::from("any"), |width| format!("{:e}", width));
+        Some(
+            container(
+                column![
+                    row![
+                        text_input("Label", &annotation.text)
+                            .on_input(Message::AnnotationTextChanged)
+                            .on_submit(Message::AnnotationEditorClosed)
+                            .width(240),
+                        button(text(if annotation.tail.is_some() {
+                            "Arrow"
+                        } else {
+                            "Label"
+                        }))
+                        .on_press(Message::AnnotationArrowToggled),
+                    ]
+                    .spacing(8),
+                    text(format!(
+                        "Shown at view widths from {} to {}",
+                        width(annotation.min_width),
+                        width(annotation.max_width)
+                    )),
+                    row![
+                        button(text("Hide zoomed out from here"))
+                            .on_press(Message::AnnotationRangeSet(true)),
+                        button(text("Hide zoomed in from here"))
+                            .on_press(Message::AnnotationRangeSet(false)),
+                        button(text("Always show")).on_press(Message::AnnotationRangeCleared),
+                    ]
+                    .spacing(8),
+                    row![
+                        button(text("Delete")).on_press(Message::AnnotationDeleted),
+                        button(text("Done")).on_press(Message::AnnotationEditorClosed),
+                    ]
+                    .spacing(8),
+                ]
+                .spacing(8),
+            )
+            .padding(8)
+            .style(container::dark)
+            .into(),
+        )
+    }
+
+    // The annotations shown in the view, laid out in window coordinates.
+    fn annotation_marks(&self) -> Vec<Marks> {
+        if !self.config.show_annotations || self.data_file.is_some() {
+            return Vec::new();
+        }
+        annotation::layout(
+            &self.config.state().annotations,
+            &self.viewport,
+            |anchor| self.annotation_point(anchor),
+            1.0,
+        )
+    }
+
+    // The annotation and the part of it under `point` of the window, if any.
+    fn annotation_at(&self, point: Point) -> Option<(u64, Part)> {
+        if !self.config.show_annotations || self.data_file.is_some() {
+            return None;
+        }
+        annotation::hit(
+            &self.config.state().annotations,
+            &self.viewport,
+            |anchor| self.annotation_point(anchor),
+            point,
+        )
+    }
+
+    // Where `pin` is in the window.
+    fn annotation_point(&self, pin: Pin) -> Point {
+        self.frame_point(pin.position(&self.viewport, self.window_size))
+    }
+
+    // The point of the plane at `point` of the window, to pin an annotation to.
+    fn window_pin(&self, point: Point) -> Pin {
+        Pin::at(&self.viewport, self.frame_point(point), self.window_size)
+    }
+
+    fn annotation_mut(&mut self, id: u64) -> Option<&mut Annotation> {
+        self.config
+            .state_mut()
+            .annotations
+            .iter_mut()
+            .find(|annotation| annotation.id == id)
+    }
+
+    // The editor of the adjustment regions, shown over the top of the window while they are.
+    fn region_editor(&self) -> Option<Element<'_, Message>> {
+        if !self.editing_regions {
+            return None;
+        }
+        let regions = &self.config.state().coloring.regions;
+        let mut editor = column![row![
+            button(text("Add region")).on_press(Message::RegionAdded),
+            button(text("Done")).on_press(Message::RegionsEdited),
+        ]
+        .spacing(8)]
+        .spacing(8);
+        let selected = self
+            .selected_region
+            .and_then(|id| regions.iter().find(|region| region.id == id));
+        match selected {
+            None => {
+                editor = editor.push(text(format!(
+                    "{} regions, applied in the order added. Drag a region's center to move it \
+                     and its corner to resize it.",
+                    regions.len()
+                )));
+            }
+            Some(region) => {
+                for knob in Knob::ALL {
+                    editor = editor.push(
+                        row![
+                            text(format!("{}: {}", knob.name(), region.describe(knob))).width(200),
+                            button(text("-")).on_press(Message::RegionAdjusted(knob, false)),
+                            button(text("+")).on_press(Message::RegionAdjusted(knob, true)),
+                        ]
+                        .spacing(4),
+                    );
+                }
+                editor = editor.push(
+                    row![
+                        button(text(region.shape.name())).on_press(Message::RegionShapeToggled),
+                        button(text("Delete")).on_press(Message::RegionDeleted),
+                    ]
+                    .spacing(8),
+                );
+            }
+        }
+        Some(container(editor).padding(8).style(container::dark).into())
+    }
+
+    // The regions laid out in the window while they are edited, with where their corner handles
+    // are drawn.
+    fn region_outlines(&self) -> Vec<(u64, Placed, Point)> {
+        if !self.editing_regions || self.data_file.is_some() {
+            return Vec::new();
+        }
+        self.config
+            .state()
+            .coloring
+            .regions
+            .iter()
+            .map(|region| {
+                let placed = region.placed(&self.viewport, self.window_size);
+                let corner = self.frame_point(placed.corner());
+                let shown = Placed {
+                    center: self.frame_point(placed.center),
+                    ..placed
+                };
+                (region.id, shown, corner)
+            })
+            .collect()
+    }
+
+    // The region and the handle of it under `point` of the window, if any.
+    fn region_at(&self, point: Point) -> Option<(u64, Handle)> {
+        if !self.editing_regions || self.data_file.is_some() {
+            return None;
+        }
+        adjust::hit(
+            &self.config.state().coloring.regions,
+            &self.viewport,
+            self.window_size,
+            self.frame_point(point),
+        )
+    }
+
+    fn region_mut(&mut self, id: u64) -> Option<&mut Region> {
+        self.config
+            .state_mut()
+            .coloring
+            .regions
+            .iter_mut()
+            .find(|region| region.id == id)
+    }
+
+    // Pins those of `annotations` the current fractal does not have yet, under ids of its own.
+    fn add_annotations(&mut self, annotations: &[Annotation]) {
+        let pinned = &mut self.config.state_mut().annotations;
+        let count = pinned.len();
+        for annotation in annotations {
+            if !pinned.iter().any(|other| other.same_as(annotation)) {
+                let id = annotation::next_id(pinned);
+                pinned.push(Annotation {
+                    id,
+                    ..annotation.clone()
+                });
+            }
+        }
+        if pinned.len() != count {
+            self.save_config();
+        }
+    }
+
+    // The recorded tile order in window coordinates, when the overlay is on.
+    fn tile_overlay(&self) -> Vec<(Rectangle, usize)> {
+        if !self.config.debug_tile_order {
+            return Vec::new();
+        }
+        let render_size = self.render_size();
+        let scale_x = self.window_size.width / render_size.width;
+        let scale_y = self.window_size.height / render_size.height;
+        self.tile_order
+            .iter()
+            .map(|(tile, order)| {
+                let mut rect = Rectangle {
+                    x: tile.x as f32 * scale_x,
+                    y: tile.y as f32 * scale_y,
+                    width: tile.width as f32 * scale_x,
+                    height: tile.height as f32 * scale_y,
+                };
+                if self.config.invert_y {
+                    rect.y = self.window_size.height - rect.y - rect.height;
+                }
+                (rect, *order)
+            })
+            .collect()
+    }
+
+    // Tile cache statistics for the tile order overlay.
+    fn tile_note(&self) -> Option<String> {
+        if !self.config.debug_tile_order {
+            return None;
+        }
+        let stats = TILE_CACHE.stats();
+        Some(format!(
+            "tile cache: {:.0}% hits ({} of {}), {} tiles, {} KiB; texture limit {}x{}",
+            stats.hit_rate() * 100.0,
+            stats.hits,
+            stats.hits + stats.misses,
+            stats.tiles,
+            stats.bytes / 1024,
+            self.texture_limit.0,
+            self.texture_limit.1
+        ))
+    }
+
+    // The position in the frame shown at `point` of the window.
+    fn frame_point(&self, point: Point) -> Point {
+        if self.config.invert_y {
+            viewport::mirror_y(point, self.window_size)
+        } else {
+            point
+        }
+    }
+
+    // A point of the plane in the user's number format, to the precision pixels resolve.
+    fn format_point(&self, re: f64, im: f64) -> String {
+        self.format_precise_point(re.into(), im.into())
+    }
+
+    // As `format_point`, keeping digits past f64's. Once there are too many to read, the digits
+    // every point in view shares are cut short and the count left out is given once.
+    fn format_precise_point(&self, re: DoubleDouble, im: DoubleDouble) -> String {
+        let digits = coordinate_digits(self.viewport.pixel_size(self.window_size));
+        let shared = (-self.viewport.width.log10()).floor().max(0.0) as usize;
+        let varying = digits.saturating_sub(shared);
+        let format = self.config.number_format;
+        let re = coordinates::shown(&coordinates::exact(re, digits), varying);
+        let im_sign = if im.hi < 0.0 { "-" } else { "+" };
+        let im = coordinates::shown(&coordinates::exact(im.abs(), digits), varying);
+        let point = format!(
+            "{} {} {}i",
+            numbers::localize(&re.text(), format),
+            im_sign,
+            numbers::localize(&im.text(), format)
+        );
+        match re.elided().max(im.elided()) {
+            0 => point,
+            elided => format!("{} (… is {} digits)", point, elided),
+        }
+    }
+
+    // The view as "re im width" with every digit pixels resolve, however the status bar shows it.
+    fn full_location(&self) -> String {
+        let digits = coordinate_digits(self.viewport.pixel_size(self.window_size));
+        let (re, im) = self.viewport.center();
+        format!(
+            "{} {} {:e}",
+            coordinates::exact(re, digits),
+            coordinates::exact(im, digits),
+            self.viewport.width
+        )
+    }
+
+    // Where the image is drawn in the window: all of it, except for data files, which are
+    // letterboxed at their own aspect ratio.
+    fn image_rect(&self) -> Rectangle {
+        let window = Rectangle::new(Point::ORIGIN, self.window_size);
+        let (width, height) = (self.image_size.0 as f32, self.image_size.1 as f32);
+        if self.data_file.is_none() || width == 0.0 || height == 0.0 {
+            return window;
+        }
+        let scale = (window.width / width).min(window.height / height);
+        let size = Size::new(width * scale, height * scale);
+        Rectangle::new(
+            Point::new(
+                (window.width - size.width) / 2.0,
+                (window.height - size.height) / 2.0,
+            ),
+            size,
+        )
+    }
+
+    // The composition guides placed over the image, and the center's coordinates while the
+    // cursor is on the crosshair.
+    fn guide_overlay(&self) -> (Vec<Rectangle>, Option<(Point, String)>) {
+        let guides = self.config.guides;
+        let image = self.image_rect();
+        let rects = guides
+            .rects(image.width, image.height, 1.0)
+            .into_iter()
+            .map(|rect| Rectangle {
+                x: rect.x + image.x,
+                y: rect.y + image.y,
+                ..rect
+            })
+            .collect();
+        let center = image.center();
+        let hovered = guides.crosshair
+            && self.current_mouse_location.distance(center) <= CROSSHAIR_HOVER_RADIUS;
+        let label = hovered.then(|| {
+            let (re, im) = self.viewport.center();
+            (center, self.format_precise_point(re, im))
+        });
+        (rects, label)
+    }
+
+    // The bands of the image the chosen safe area covers, if any.
+    fn safe_area_overlay(&self) -> Vec<Rectangle> {
+        let Some(margins) = self.config.safe_area.margins() else {
+            return Vec::new();
+        };
+        let image = self.image_rect();
+        margins
+            .regions(image.width, image.height)
+            .into_iter()
+            .map(|rect| Rectangle {
+                x: rect.x + image.x,
+                y: rect.y + image.y,
+                ..rect
+            })
+            .collect()
+    }
+
+    fn status_bar(&self) -> Element<'_, Message> {
+        let profile = self.config.active();
+        let mut status = format!(
+            "{} | {} | {} iterations",
+            self.config.fractal.fractal().name(),
+            profile.name,
+            profile.settings.max_iterations
+        );
+        let (re, im) = self.viewport.precise_point(
+            self.frame_point(self.current_mouse_location),
+            self.window_size,
+        );
+        status = format!("{} | {}", status, self.format_precise_point(re, im));
+        let render_size = self.render_size();
+        let arithmetic = Arithmetic::select(
+            &self.viewport,
+            render_size,
+            self.config.experimental_double_double,
+        );
+        if !arithmetic.resolves(&self.viewport, render_size, render::MIN_PIXEL_ULPS) {
+            status = format!("{} | {} precision exhausted", status, arithmetic.name());
+        } else if arithmetic != Arithmetic::F64 {
+            status = format!("{} | {}", status, arithmetic.name());
+        }
+        if let Some(path) = &self.data_file {
+            status = format!(
+                "{} | viewing {} ({}x{})",
+                status,
+                path.file_name().unwrap_or_default().to_string_lossy(),
+                self.buffer.width,
+                self.buffer.height
+            );
+        }
+        if let Some(job) = self.rendering.as_ref().filter(|job| !job.refining) {
+            if let Some(progress) = job.progress {
+                status = format!("{} | rendering {:.0}%", status, progress.fraction() * 100.0);
+            }
+            if let Some(remaining) = job.eta.shown() {
+                status = format!("{}, {}", status, eta::describe(remaining));
+            }
+        }
+        if self.refinement > 0 {
+            status = format!("{} | refined ×{}", status, self.refinement);
+        }
+        let classes = self.pixel_classes;
+        let total = classes.escaped + classes.interior + classes.unresolved + classes.failed;
+        if classes.unresolved > 0 {
+            status = format!(
+                "{} | {:.1}% unresolved",
+                status,
+                classes.unresolved as f64 * 100.0 / total as f64
+            );
+        }
+        if let Some(failure) = self.buffer.failure.as_ref().filter(|_| classes.failed > 0) {
+            status = format!(
+                "{} | {:.1}% failed: {}",
+                status,
+                classes.failed as f64 * 100.0 / total as f64,
+                failure
+            );
+        }
+        if self.energy_saving() {
+            status = format!("{} | energy saver", status);
+        }
+        if self.peeking {
+            status = format!(
+                "{} | {}",
+                status,
+                if self.previous_image.is_some() {
+                    "showing the previous frame"
+                } else {
+                    "no previous frame at this size"
+                }
+            );
+        }
+        if self.tune_cancel.is_some() {
+            status = format!(
+                "{} | tuning {:.0}%, esc to skip",
+                status,
+                self.tune_progress * 100.0
+            );
+        }
+        if self.draw_bounding_box {
+            // The selection's size in the plane, to as many digits as a precise move changes.
+            let pixel_size = self.viewport.pixel_size(self.window_size);
+            let width = (self.end_location.x - self.start_location.x).abs() as f64 * pixel_size;
+            let height = (self.end_location.y - self.start_location.y).abs() as f64 * pixel_size;
+            let digits = cursor::size_digits(width.max(height), pixel_size) - 1;
+            status = format!(
+                "{} | selection {:.*e} x {:.*e}",
+                status, digits, width, digits, height
+            );
+        }
+        if self.draw_bounding_box && self.dragging {
+            status = format!(
+                "{} | {}",
+                status,
+                if self.modifiers.shift() {
+                    "precise"
+                } else {
+                    "hold shift to slow the cursor"
+                }
+            );
+        }
+        if self.draw_bounding_box && !self.dragging {
+            status = format!(
+                "{} | arrows move, shift+arrows resize, enter zooms, esc cancels",
+                status
+            );
+        }
+        if !self.status_message.is_empty() {
+            status = format!("{} | {}", status, self.status_message);
+        }
+        container(text(status))
+            .padding(4)
+            .style(container::dark)
+            .into()
+    }
+
+    fn settings_panel(&self) -> Element<'_, Message> {
+        let profile = self.config.active();
+        let settings = profile.settings;
+        let coloring = &self.config.state().coloring;
+        let guides = self.config.guides;
+        let safe_area = &self.config.safe_area;
+        let ring = |setting: Setting, content: Element<'static, Message>| {
+            self.focus_ring(Control::Setting(setting), content)
+        };
+        // Colors the palette brought with it are marked as such, so that picking another for
+        // it does not come as a surprise when switching back.
+        let palette = coloring.palette();
+        let chosen_colors = coloring.palette_colors_chosen();
+        let palettes = |chosen: bool, preferred: Option<SolidColor>| {
+            if !chosen && preferred.is_some() {
+                " (palette's)"
+            } else {
+                ""
+            }
+        };
+        container(scrollable(
+            column![
+                text(format!("Profile: {}", profile.name)),
+                text("Tab moves focus, Enter activates, arrows adjust"),
+                self.hybrid_editor(),
+                text(format!("Iterations: {}", settings.max_iterations)),
+                text(format!(
+                    "Pixels: {} escaped, {} inside, {} unresolved",
+                    self.pixel_classes.escaped,
+                    self.pixel_classes.interior,
+                    self.pixel_classes.unresolved
+                )),
+                ring(
+                    Setting::Iterations,
+                    slider(
+                        50..=20000,
+                        settings.max_iterations,
+                        Message::MaxIterationsChanged
+                    )
+                    .step(50u32)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                text(format!(
+                    "Resolution: {:.0}%",
+                    settings.resolution_scale * 100.0
+                )),
+                ring(
+                    Setting::Resolution,
+                    slider(
+                        0.25..=1.0,
+                        settings.resolution_scale,
+                        Message::ResolutionScaleChanged
+                    )
+                    .step(0.25)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                text(format!("Antialiasing: {}x", settings.antialias)),
+                ring(
+                    Setting::Antialias,
+                    slider(1..=4, settings.antialias, Message::AntialiasChanged)
+                        .on_release(Message::SettingsReleased)
+                        .into()
+                ),
+                ring(
+                    Setting::Palette,
+                    button(text(format!("Palette: {}", coloring.palette)))
+                        .on_press(Message::PaletteCycled)
+                        .into()
+                ),
+                ring(
+                    Setting::PaletteWrap,
+                    button(text(format!(
+                        "Palette wrap: {}",
+                        coloring.palette_wrap().name()
+                    )))
+                    .on_press(Message::PaletteWrapCycled)
+                    .into()
+                ),
+                ring(
+                    Setting::Coloring,
+                    button(text(format!("Coloring: {}", coloring.mode.name())))
+                        .on_press(Message::ColoringModeCycled)
+                        .into()
+                ),
+                ring(
+                    Setting::InteriorColor,
+                    button(text(format!(
+                        "Interior: {}{}",
+                        coloring.interior_color.name(),
+                        palettes(chosen_colors.0, palette.interior)
+                    )))
+                    .on_press(Message::InteriorColorCycled)
+                    .into()
+                ),
+                ring(
+                    Setting::UnresolvedColor,
+                    button(text(format!(
+                        "Unresolved: {}{}",
+                        coloring.unresolved_color.name(),
+                        palettes(chosen_colors.1, palette.unresolved)
+                    )))
+                    .on_press(Message::UnresolvedColorCycled)
+                    .into()
+                ),
+                text(format!("Mode blend: {:.2}", coloring.field_blend)),
+                ring(
+                    Setting::FieldBlend,
+                    slider(0.0..=1.0, coloring.field_blend, Message::FieldBlendChanged)
+                        .step(0.05)
+                        .on_release(Message::SettingsReleased)
+                        .into()
+                ),
+                text(format!("Gamma: {:.2}", coloring.gamma)),
+                ring(
+                    Setting::Gamma,
+                    slider(0.2..=3.0, coloring.gamma, Message::GammaChanged)
+                        .step(0.05)
+                        .on_release(Message::SettingsReleased)
+                        .into()
+                ),
+                text(format!("Palette density: {:.1}", coloring.density)),
+                ring(
+                    Setting::Density,
+                    slider(
+                        levels::MIN_DENSITY..=levels::MAX_DENSITY,
+                        coloring.density,
+                        Message::DensityChanged
+                    )
+                    .step(0.1)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                text(format!("Palette offset: {:.2}", coloring.palette_offset)),
+                ring(
+                    Setting::PaletteOffset,
+                    slider(
+                        0.0..=1.0,
+                        coloring.palette_offset,
+                        Message::PaletteOffsetChanged
+                    )
+                    .step(0.01)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                ring(
+                    Setting::AutoLevels,
+                    button(text("Auto levels"))
+                        .on_press(Message::AutoLevelsApplied)
+                        .into()
+                ),
+                ring(
+                    Setting::AutoLevelsAfterRender,
+                    button(text(on_off(
+                        "Auto levels after each render",
+                        self.config.auto_levels
+                    )))
+                    .on_press(Message::AutoLevelsAfterRenderToggled)
+                    .into()
+                ),
+                text(format!("Direction sectors: {}", coloring.sectors)),
+                ring(
+                    Setting::Sectors,
+                    slider(2..=24, coloring.sectors, Message::SectorsChanged)
+                        .on_release(Message::SettingsReleased)
+                        .into()
+                ),
+                text(format!("Hue rotation: {:.2}", coloring.hue_rotation)),
+                ring(
+                    Setting::HueRotation,
+                    slider(
+                        0.0..=1.0,
+                        coloring.hue_rotation,
+                        Message::HueRotationChanged
+                    )
+                    .step(0.01)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                ring(
+                    Setting::Occlusion,
+                    button(text(on_off("Ambient occlusion", coloring.occlusion)))
+                        .on_press(Message::OcclusionToggled)
+                        .into()
+                ),
+                text(format!(
+                    "Occlusion radius: {} px",
+                    coloring.occlusion_radius
+                )),
+                ring(
+                    Setting::OcclusionRadius,
+                    slider(
+                        1..=16,
+                        coloring.occlusion_radius,
+                        Message::OcclusionRadiusChanged
+                    )
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                text(format!(
+                    "Occlusion strength: {:.2}",
+                    coloring.occlusion_strength
+                )),
+                ring(
+                    Setting::OcclusionStrength,
+                    slider(
+                        0.0..=1.0,
+                        coloring.occlusion_strength,
+                        Message::OcclusionStrengthChanged
+                    )
+                    .step(0.05)
+                    .on_release(Message::SettingsReleased)
+                    .into()
+                ),
+                self.post_stages(),
+                text(format!(
+                    "Expression stage ({})",
+                    postprocess::VARIABLES.join(", ")
+                )),
+                ring(
+                    Setting::PostExpression,
+                    text_input("0.5 + 0.5 * cos(smooth / 8)", &self.post_input)
+                        .id(text_input::Id::new(POST_INPUT))
+                        .on_input(Message::PostExpressionChanged)
+                        .on_submit(Message::PostExpressionSubmitted)
+                        .into()
+                ),
+                ring(
+                    Setting::Regions,
+                    button(text(format!(
+                        "Adjustment regions ({})",
+                        coloring.regions.len()
+                    )))
+                    .on_press(Message::RegionsEdited)
+                    .into()
+                ),
+                ring(
+                    Setting::ExportLook,
+                    button(text("Export look"))
+                        .on_press(Message::LookExported)
+                        .into()
+                ),
+                ring(
+                    Setting::ColorManagement,
+                    button(text(on_off(
+                        "Color management",
+                        self.display_profile.is_some()
+                    )))
+                    .on_press(Message::ColorManagementToggled)
+                    .into()
+                ),
+                ring(
+                    Setting::CachePrecision,
+                    button(text(format!(
+                        "Cache precision: {}",
+                        self.config.buffer_precision.name()
+                    )))
+                    .on_press(Message::BufferPrecisionCycled)
+                    .into()
+                ),
+                ring(
+                    Setting::Prepass,
+                    button(text(on_off(
+                        "Exterior pre-pass",
+                        self.config.coarse_prepass
+                    )))
+                    .on_press(Message::CoarsePrepassToggled)
+                    .into()
+                ),
+                ring(
+                    Setting::DoubleDouble,
+                    button(text(on_off(
+                        "Double-double (experimental)",
+                        self.config.experimental_double_double
+                    )))
+                    .on_press(Message::DoubleDoubleToggled)
+                    .into()
+                ),
+                ring(
+                    Setting::TileOrder,
+                    button(text(on_off(
+                        "Tile order overlay",
+                        self.config.debug_tile_order
+                    )))
+                    .on_press(Message::TileOrderToggled)
+                    .into()
+                ),
+                ring(
+                    Setting::EnergySaver,
+                    button(text(format!(
+                        "Energy saver: {}",
+                        self.config.energy_saver.name()
+                    )))
+                    .on_press(Message::EnergySaverCycled)
+                    .into()
+                ),
+                text(if self.config.cpu_cap >= 100 {
+                    String::from("CPU cap: off")
+                } else {
+                    format!("CPU cap: {}% per worker", self.config.cpu_cap)
+                }),
+                ring(
+                    Setting::CpuCap,
+                    slider(
+                        duty::MIN_CAP..=100,
+                        self.config.cpu_cap,
+                        Message::CpuCapChanged
+                    )
+                    .step(10u32)
+                    .into()
+                ),
+                ring(
+                    Setting::Tune,
+                    button(text(if self.tune_cancel.is_some() {
+                        format!("Tuning {:.0}%", self.tune_progress * 100.0)
+                    } else {
+                        let tuning = self.tuning();
+                        format!(
+                            "Tune: {} workers, {} px tiles{}",
+                            tuning.threads,
+                            tuning.chunk_size,
+                            if tuning.measured { "" } else { " (defaults)" }
+                        )
+                    }))
+                    .on_press_maybe(
+                        (self.persist && self.tune_cancel.is_none())
+                            .then_some(Message::TuneRequested)
+                    )
+                    .into()
+                ),
+                ring(
+                    Setting::ExplorationLog,
+                    button(text(on_off("Exploration log", self.config.exploration_log)))
+                        .on_press(Message::ExplorationLogToggled)
+                        .into()
+                ),
+                ring(
+                    Setting::Prefetch,
+                    button(text(on_off("Prefetch around view", self.config.prefetch)))
+                        .on_press(Message::PrefetchToggled)
+                        .into()
+                ),
+                ring(
+                    Setting::OrbitSound,
+                    button(text(if cfg!(feature = "sound") {
+                        on_off("Orbit sound", self.config.orbit_sound)
+                    } else {
+                        String::from("Orbit sound: not in this build")
+                    }))
+                    .on_press_maybe(cfg!(feature = "sound").then_some(Message::OrbitSoundToggled))
+                    .into()
+                ),
+                text("Go to (re im [width])"),
+                ring(
+                    Setting::Goto,
+                    text_input("-0.743 0.131", &self.goto_input)
+                        .id(text_input::Id::new(GOTO_INPUT))
+                        .on_input(Message::GotoChanged)
+                        .on_submit(Message::GotoSubmitted)
+                        .into()
+                ),
+                text("Open a look or data file"),
+                ring(
+                    Setting::OpenFile,
+                    text_input("path/to/file.mbit", &self.open_input)
+                        .id(text_input::Id::new(OPEN_INPUT))
+                        .on_input(Message::OpenPathChanged)
+                        .on_submit(Message::OpenPathSubmitted)
+                        .into()
+                ),
+                row![
+                    ring(
+                        Setting::Decimal,
+                        button(text(format!(
+                            "Decimal: {}",
+                            self.config.number_format.decimal.name()
+                        )))
+                        .on_press(Message::DecimalSeparatorCycled)
+                        .into()
+                    ),
+                    ring(
+                        Setting::Grouping,
+                        button(text(on_off("Grouping", self.config.number_format.grouping)))
+                            .on_press(Message::DigitGroupingToggled)
+                            .into()
+                    ),
+                    ring(
+                        Setting::InvertY,
+                        button(text(if self.config.invert_y {
+                            "+i down"
+                        } else {
+                            "+i up"
+                        }))
+                        .on_press(Message::InvertYToggled)
+                        .into()
+                    ),
+                ]
+                .spacing(4),
+                row![
+                    ring(
+                        Setting::Crosshair,
+                        button(text(on_off("Crosshair", guides.crosshair)))
+                            .on_press(Message::CrosshairToggled)
+                            .into()
+                    ),
+                    ring(
+                        Setting::Thirds,
+                        button(text(on_off("Thirds", guides.thirds)))
+                            .on_press(Message::ThirdsToggled)
+                            .into()
+                    ),
+                ]
+                .spacing(4),
+                row![
+                    ring(
+                        Setting::GuideColor,
+                        button(text(format!("Guides: {}", guides.color.name())))
+                            .on_press(Message::GuideColorCycled)
+                            .into()
+                    ),
+                    ring(
+                        Setting::GuideOpacity,
+                        button(text(format!("{:.0}%", guides.opacity * 100.0)))
+                            .on_press(Message::GuideOpacityCycled)
+                            .into()
+                    ),
+                ]
+                .spacing(4),
+                ring(
+                    Setting::GuidesInExports,
+                    button(text(on_off("Guides in exports", guides.in_exports)))
+                        .on_press(Message::GuidesInExportsToggled)
+                        .into()
+                ),
+                row![
+                    ring(
+                        Setting::Annotations,
+                        button(text(on_off("Annotations", self.config.show_annotations)))
+                            .on_press(Message::AnnotationsToggled)
+                            .into()
+                    ),
+                    ring(
+                        Setting::AnnotationsInExports,
+                        button(text(on_off(
+                            "In exports",
+                            self.config.annotations_in_exports
+                        )))
+                        .on_press(Message::AnnotationsInExportsToggled)
+                        .into()
+                    ),
+                ]
+                .spacing(4),
+                ring(
+                    Setting::SafeArea,
+                    button(text(format!(
+                        "Safe area: {}",
+                        safe_area.template.as_deref().unwrap_or("off")
+                    )))
+                    .on_press(Message::SafeAreaCycled)
+                    .into()
+                ),
+                self.safe_margin_sliders(),
+                text(format!("Export padding: {} px", safe_area.padding)),
+                ring(
+                    Setting::ExportPadding,
+                    slider(0..=256, safe_area.padding, Message::ExportPaddingChanged)
+                        .step(8u32)
+                        .on_release(Message::SettingsReleased)
+                        .into()
+                ),
+                ring(
+                    Setting::ExportProfile,
+                    button(text(format!(
+                        "Export profile: {}",
+                        self.config.export_profile.as_deref().unwrap_or("active")
+                    )))
+                    .on_press(Message::ExportProfileCycled)
+                    .into()
+                ),
+                row![
+                    ring(
+                        Setting::Undo,
+                        button(text("Undo"))
+                            .on_press_maybe(self.history.can_undo().then_some(Message::Undo))
+                            .into()
+                    ),
+                    ring(
+                        Setting::Redo,
+                        button(text("Redo"))
+                            .on_press_maybe(self.history.can_redo().then_some(Message::Redo))
+                            .into()
+                    ),
+                ]
+                .spacing(4),
+            ]
+            .spacing(6)
+            .width(264),
+        ))
+        .height(Fill)
+        .padding(12)
+        .style(container::dark)
+        .into()
+    }
+
+    fn bookmarks_panel(&self) -> Element<'_, Message> {
+        let thumbnail = |name: String| -> Element<'_, Message> {
+            match self.thumbnails.get(&name) {
+                Some(handle) => image(handle.clone())
+                    .width(THUMBNAIL_SIZE.width)
+                    .height(THUMBNAIL_SIZE.height)
+                    .into(),
+                None => container(text("..."))
+                    .center_x(THUMBNAIL_SIZE.width)
+                    .center_y(THUMBNAIL_SIZE.height)
+                    .into(),
+            }
+        };
+        // What restoring with settings would change, shown while hovering over an entry.
+        let changes = |entry: Element<'static, Message>, overrides: &Overrides| {
+            let changes = overrides.changes(
+                self.config.active().settings.max_iterations,
+                &self.config.state().coloring,
+            );
+            let tip = if changes.is_empty() {
+                String::from("Same settings as now")
+            } else {
+                format!("Shift-click also sets\n{}", changes.join("\n"))
+            };
+            tooltip(
+                entry,
+                container(text(tip)).padding(6).style(container::dark),
+                tooltip::Position::Right,
+            )
+        };
+        let shift = self.modifiers.shift();
+        let anchor = match &self.anchor {
+            Some(anchor) => format!("Anchor: {}, size {:.2e}", anchor.describe(), anchor.size),
+            None => String::from("No anchor (N over a minibrot)"),
+        };
+        let mut entries = column![
+            text("Bookmarks (b to add, Delete to remove, Shift-click to restore settings too)"),
+            text(anchor),
+            button(text("Add relative to anchor (B)")).on_press_maybe(
+                self.anchor
+                    .is_some()
+                    .then_some(Message::RelativeBookmarkAdded)
+            ),
+        ]
+        .spacing(8);
+        for bookmark in &self.bookmarks.bookmarks {
+            let mut details = column![text(&bookmark.name)].spacing(4);
+            if let Some(relative) = &bookmark.relative {
+                details = details.push(text(format!(
+                    "Relative to a {}",
+                    relative.anchor.describe()
+                )));
+                details = details.push(
+                    button(text("Apply at anchor")).on_press_maybe(
+                        self.anchor
+                            .is_some()
+                            .then_some(Message::RelativeBookmarkApplied(bookmark.id)),
+                    ),
+                );
+            }
+            entries = entries.push(
+                row![
+                    self.focus_ring(
+                        Control::Bookmark(bookmark.id),
+                        changes(
+                            button(thumbnail(bookmark.thumbnail_name()))
+                                .padding(0)
+                                .on_press(Message::BookmarkSelected(bookmark.id, shift))
+                                .into(),
+                            &bookmark.overrides
+                        )
+                    ),
+                    details
+                        .push(
+                            button(text(if bookmark.restore_settings {
+                                "Restores view and settings"
+                            } else {
+                                "Restores view only"
+                            }))
+                            .on_press(Message::BookmarkRestoreToggled(bookmark.id))
+                        )
+                        .push(
+                            button(text("Delete")).on_press(Message::BookmarkDeleted(bookmark.id))
+                        ),
+                ]
+                .spacing(8),
+            );
+        }
+        entries = entries.push(text("Presets"));
+        for (index, preset) in Preset::builtin().into_iter().enumerate() {
+            entries = entries.push(
+                row![
+                    self.focus_ring(
+                        Control::Preset(index),
+                        changes(
+                            button(thumbnail(preset.thumbnail_name()))
+                                .padding(0)
+                                .on_press(Message::PresetSelected(index, shift))
+                                .into(),
+                            &preset.overrides
+                        )
+                    ),
+                    text(preset.name),
+                ]
+                .spacing(8),
+            );
+        }
+        container(scrollable(entries.width(280)).height(Fill))
+            .padding(12)
+            .style(container::dark)
+            .into()
+    }
+
+    fn minibrots_panel(&self) -> Element<'_, Message> {
+        let mut entries = column![
+            text("Minibrots (n to close)"),
+            self.focus_ring(
+                Control::MinibrotScan,
+                button(text("Scan again")).on_press_maybe(
+                    self.minibrot_scan
+                        .is_none()
+                        .then_some(Message::MinibrotsRequested)
+                )
+            ),
+        ]
+        .spacing(8);
+        if self.minibrot_scan.is_some() {
+            entries = entries.push(text("Scanning..."));
+        } else if self.minibrots.is_empty() {
+            entries = entries.push(text("None found in this view"));
+        }
+        for (index, candidate) in self.minibrots.iter().enumerate() {
+            let period = candidate
+                .period
+                .map_or_else(|| String::from("?"), |period| period.to_string());
+            entries = entries.push(
+                row![
+                    self.focus_ring(
+                        Control::Minibrot(index),
+                        button(text(format!(
+                            "period {}, size {:.2e}\n{}",
+                            period,
+                            candidate.size,
+                            self.format_point(candidate.re, candidate.im)
+                        )))
+                        .on_press(Message::MinibrotSelected(index)),
+                    ),
+                    button(text("Anchor")).on_press(Message::MinibrotAnchored(index)),
+                ]
+                .spacing(4),
+            );
+        }
+        container(scrollable(entries.width(280)).height(Fill))
+            .padding(12)
+            .style(container::dark)
+            .into()
+    }
+
+    fn journal_panel(&self) -> Element<'_, Message> {
+        let mut entries = column![
+            text("Exploration log (j to close)"),
+            self.focus_ring(
+                Control::JournalExport,
+                button(text("Export contact sheet")).on_press_maybe(
+                    (self.persist && !self.journal.entries.is_empty())
+                        .then_some(Message::JournalExported)
+                )
+            ),
+        ]
+        .spacing(8);
+        if !self.config.exploration_log {
+            entries = entries.push(text("Logging is off; turn it on in the settings"));
+        } else if self.journal.entries.is_empty() {
+            entries = entries.push(text("No views logged yet"));
+        }
+        for entry in &self.journal.entries {
+            let thumbnail: Element<'_, Message> = match self.journal_thumbnails.get(&entry.id) {
+                Some(handle) => image(handle.clone()).into(),
+                None => text(entry.fractal.fractal().name()).into(),
+            };
+            entries = entries.push(
+                row![
+                    self.focus_ring(
+                        Control::JournalEntry(entry.id),
+                        button(thumbnail)
+                            .padding(0)
+                            .on_press(Message::JournalSelected(entry.id))
+                    ),
+                    text(format!(
+                        "{}\n{}\nwidth {:.3e}",
+                        export::date_time(entry.time),
+                        self.format_point(entry.viewport.center_re, entry.viewport.center_im),
+                        entry.viewport.width
+                    )),
+                ]
+                .spacing(8),
+            );
+        }
+        container(scrollable(entries.width(280)).height(Fill))
+            .padding(12)
+            .style(container::dark)
+            .into()
+    }
+
+    fn path_panel(&self) -> Element<'_, Message> {
+        let path = &self.camera_path;
+        let duration = path.duration();
+        let mut entries = column![
+            text("Camera path (k to close)"),
+            text(format!(
+                "{} keyframes, {:.1} s at {} fps; export it with --path",
+                path.keyframes.len(),
+                duration,
+                path.fps
+            )),
+            self.focus_ring(
+                Control::KeyframeAdd,
+                button(text("Add this view")).on_press(Message::KeyframeAdded)
+            ),
+        ]
+        .spacing(8);
+        if path.keyframes.len() > 1 {
+            entries = entries.push(text(format!("Preview: {:.2} s", self.scrub)));
+            entries = entries.push(
+                self.focus_ring(
+                    Control::PathScrubber,
+                    slider(
+                        0.0..=duration,
+                        self.scrub.min(duration),
+                        Message::PathScrubbed,
+                    )
+                    .step(1.0 / path.fps)
+                    .on_release(Message::PathScrubReleased),
+                ),
+            );
+        }
+        for (index, keyframe) in path.keyframes.iter().enumerate() {
+            let viewport = keyframe.viewport;
+            let mut timing = row![button(text(format!("Hold {} s", keyframe.hold)))
+                .on_press(Message::KeyframeHoldCycled(index))]
+            .spacing(4);
+            // The last keyframe goes nowhere.
+            if index + 1 < path.keyframes.len() {
+                timing = timing
+                    .push(
+                        button(text(format!("Travel {} s", keyframe.travel)))
+                            .on_press(Message::KeyframeTravelCycled(index)),
+                    )
+                    .push(
+                        button(text(keyframe.easing.name()))
+                            .on_press(Message::KeyframeEasingCycled(index)),
+                    );
+            }
+            entries = entries.push(
+                column![
+                    self.focus_ring(
+                        Control::Keyframe(index),
+                        button(text(format!(
+                            "{}. {}\nwidth {:.3e}",
+                            index + 1,
+                            self.format_point(viewport.center_re, viewport.center_im),
+                            viewport.width
+                        )))
+                        .on_press(Message::KeyframeSelected(index))
+                    ),
+                    timing,
+                    button(text("Delete")).on_press(Message::KeyframeDeleted(index)),
+                ]
+                .spacing(4),
+            );
+        }
+        container(scrollable(entries.width(280)).height(Fill))
+            .padding(12)
+            .style(container::dark)
+            .into()
+    }
+
+    fn save_camera_path(&self) {
+        if self.persist {
+            camera_path::save(&self.camera_path);
+        }
+    }
+
+    fn queue_panel(&self) -> Element<'_, Message> {
+        let busy = self.rendering.as_ref().is_some_and(|job| !job.refining);
+        let mut entries = column![
+            text("Export queue (ctrl+e to add, e to close)"),
+            text("Enter pauses, left and right move, Delete cancels"),
+        ]
+        .spacing(8);
+        if self.exports.jobs.is_empty() {
+            entries = entries.push(text("Nothing queued"));
+        }
+        for job in &self.exports.jobs {
+            let spec = &job.spec;
+            let state = match (job.running, job.paused) {
+                (true, false) if busy => String::from("waiting for the view to render"),
+                (true, false) => match self.export_eta.shown() {
+                    Some(remaining) => format!(
+                        "exporting {:.0}%, {}",
+                        job.progress * 100.0,
+                        eta::describe(remaining)
+                    ),
+                    None => format!("exporting {:.0}%", job.progress * 100.0),
+                },
+                (_, true) => String::from("paused"),
+                (false, false) => String::from("queued"),
+            };
+            entries = entries.push(
+                self.focus_ring(
+                    Control::ExportJob(job.id),
+                    column![
+                        text(&spec.name),
+                        text(format!(
+                            "{}x{}, {} iterations, {}{}",
+                            spec.width / spec.antialias,
+                            spec.height / spec.antialias,
+                            spec.max_iterations,
+                            spec.fractal.fractal().name(),
+                            spec.profile
+                                .as_ref()
+                                .map(|name| format!(", {} profile", name))
+                                .unwrap_or_default()
+                        )),
+                        text(state),
+                        row![
+                            button(text("Up")).on_press(Message::ExportMoved(job.id, -1)),
+                            button(text("Down")).on_press(Message::ExportMoved(job.id, 1)),
+                            button(text(if job.paused { "Resume" } else { "Pause" }))
+                                .on_press(Message::ExportPauseToggled(job.id)),
+                            button(text("Cancel")).on_press(Message::ExportCancelled(job.id)),
+                        ]
+                        .spacing(4),
+                    ]
+                    .spacing(4),
+                ),
+            );
+        }
+        container(scrollable(entries.width(280)).height(Fill))
+            .padding(12)
+            .style(container::dark)
+            .into()
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        // iced reports no scale factor changes, so ask whenever the window may have changed
+        // monitors.
+        let query_scale = matches!(
+            message,
+            Message::EventOccurred(Event::Window(
+                window::Event::Opened { .. } | window::Event::Moved(_) | window::Event::Resized(_)
+            ))
+        );
+        let focused = self.focused;
+        let commanding = self.command_query.is_some();
+        let mut task = Task::batch(self.apply(message).into_iter().map(Task::stream));
+        // Text fields take typing only while they have iced's focus, so it follows ours onto
+        // them and off them again. The command palette takes it while open.
+        if self.focused != focused || self.command_query.is_some() != commanding {
+            let input = match self.focused {
+                _ if self.command_query.is_some() => COMMAND_INPUT,
+                Some(Control::Setting(Setting::Goto)) => GOTO_INPUT,
+                Some(Control::Setting(Setting::PostExpression)) => POST_INPUT,
+                Some(Control::Setting(Setting::OpenFile)) => OPEN_INPUT,
+                _ => "",
+            };
+            task = Task::batch([task, text_input::focus(text_input::Id::new(input))]);
+        }
+        if query_scale {
+            let query = window::get_oldest()
+                .and_then(window::get_scale_factor)
+                .map(Message::ScaleFactorChanged);
+            Task::batch([task, query])
+        } else {
+            task
+        }
+    }
+
+    // Applies a message to the state, recording an undo step if it changed anything undoable.
+    // When it starts background work (a render, thumbnails, an export), returns the streams its
+    // results arrive on as further messages.
+    fn apply(&mut self, message: Message) -> Vec<mpsc::UnboundedReceiver<Message>> {
+        let saving = self.energy_saving();
+        if is_user_input(&message) {
+            self.last_input = Instant::now();
+            // The job reports back as cancelled, which restarts the wait for idle.
+            if let Some(job) = self.rendering.as_ref().filter(|job| job.refining) {
+                job.cancel.cancel();
+            }
+            self.check_battery(false);
+        }
+        let mut events: Vec<_> = match history_key(&message) {
+            None => self.handle(message),
+            Some(coalesce) => {
+                let before = self.snapshot();
+                let events = self.handle(message);
+                if self.snapshot() != before {
+                    self.history.record(before, coalesce, Instant::now());
+                }
+                events
+            }
+        }
+        .into_iter()
+        .collect();
+        if self.energy_saving() != saving {
+            events.extend(self.energy_saver_changed());
+        }
+        events.extend(self.schedule_exports());
+        events
+    }
+
+    fn handle(&mut self, message: Message) -> Option<mpsc::UnboundedReceiver<Message>> {
+        let mut should_draw = false;
+        match message {
+            Message::Undo | Message::Redo => {
+                let current = self.snapshot();
+                let restored = if matches!(message, Message::Undo) {
+                    self.history.undo(current.clone())
+                } else {
+                    self.history.redo(current.clone())
+                }?;
+                should_draw = restored.needs_render(&current);
+                self.restore(restored);
+                self.save_config();
+                if !should_draw {
+                    self.recolor();
+                }
+            }
+            Message::Render(generation, event) => {
+                let job = self
+                    .rendering
+                    .as_mut()
+                    .filter(|job| job.generation == generation)?;
+                match event {
+                    RenderEvent::Progress(progress) => {
+                        job.eta.record(&progress);
+                        job.progress = Some(progress);
+                    }
+                    // Painted whether or not the window has focus, so that the compositor's
+                    // preview of it keeps up; iced redraws every window after each update.
+                    RenderEvent::Partial(frame, _permit) => {
+                        let antialias = job.antialias;
+                        self.paint(&frame, antialias);
+                        IterationBuffer::recycle(frame);
+                    }
+                    RenderEvent::TileOrder(tiles) => self.tile_order = tiles,
+                    RenderEvent::Finished(result) => {
+                        let antialias = job.antialias;
+                        let refining = job.refining;
+                        // Renders the watchdog reported say nothing about the usual speed.
+                        let timed = !refining && !job.stalled && job.waited.is_zero();
+                        let elapsed = job.started.elapsed();
+                        self.rendering = None;
+                        match result {
+                            Ok(buffer) => {
+                                if let Some(params) = buffer.params.filter(|_| timed) {
+                                    self.throughput.record(
+                                        buffer.width * buffer.height,
+                                        params.max_iterations,
+                                        elapsed,
+                                    );
+                                }
+                                if let Some(params) = buffer.params.filter(|_| !refining) {
+                                    self.render_stats.record(RenderSample {
+                                        generation,
+                                        width: buffer.width,
+                                        height: buffer.height,
+                                        elapsed,
+                                        arithmetic: params.arithmetic,
+                                    });
+                                }
+                                // Refining adds detail to the view shown; it is not a new frame.
+                                if !refining {
+                                    self.keep_previous_image((
+                                        (buffer.width / antialias) as u32,
+                                        (buffer.height / antialias) as u32,
+                                    ));
+                                }
+                                IterationBuffer::recycle(std::mem::replace(
+                                    &mut self.buffer,
+                                    buffer,
+                                ));
+                                self.installed_generation = generation;
+                                self.buffer_antialias = antialias;
+                                self.pixel_classes = ClassCounts::of(&self.buffer);
+                                if let Some(failure) = &self.buffer.failure {
+                                    println!("render {} has failed tiles: {}", generation, failure);
+                                }
+                                self.refinement = if refining { self.refinement + 1 } else { 0 };
+                                // Undone on its own, without taking the view back with it.
+                                if !refining && self.config.auto_levels {
+                                    let before = self.snapshot();
+                                    if self.apply_auto_levels() && self.snapshot() != before {
+                                        self.history.record(before, None, Instant::now());
+                                    }
+                                }
+                                self.recolor();
+                                if !refining {
+                                    self.log_view();
+                                }
+                            }
+                            Err(err) if refining => println!("refinement stopped: {}", err),
+                            Err(err) => println!("render {} failed: {}", generation, err),
+                        }
+                        if self.idle_work() && !self.energy_saving() {
+                            return Some(self.schedule_idle());
+                        }
+                    }
+                }
+            }
+            Message::WatchdogTick(generation) => {
+                let job = self
+                    .rendering
+                    .as_mut()
+                    .filter(|job| job.generation == generation && !job.refining && !job.stalled)?;
+                let heartbeats = job.cancel.heartbeats();
+                let quiet = heartbeats.iter().map(|(_, age)| *age).min();
+                let elapsed = job.started.elapsed();
+                if watchdog::assess(elapsed - job.waited, job.prediction, quiet) != Verdict::Stuck {
+                    return None;
+                }
+                job.stalled = true;
+                println!(
+                    "render {} looks stuck: {:.1} s in, {:.1} s expected, {} of {} pixels done",
+                    generation,
+                    elapsed.as_secs_f64(),
+                    job.prediction.frame.as_secs_f64(),
+                    job.progress.map_or(0, |progress| progress.pixels_done),
+                    job.progress.map_or(0, |progress| progress.total_pixels)
+                );
+                for (worker, age) in &heartbeats {
+                    println!(
+                        "  worker {:?} last progressed {:.1} s ago",
+                        worker,
+                        age.as_secs_f64()
+                    );
+                }
+            }
+            Message::StallWaited => {
+                let job = self.rendering.as_mut().filter(|job| job.stalled)?;
+                job.stalled = false;
+                job.waited = job.started.elapsed();
+            }
+            Message::StallCancelled | Message::StallDrafted => {
+                let job = self.rendering.take_if(|job| job.stalled)?;
+                job.cancel.cancel();
+                if matches!(message, Message::StallDrafted) {
+                    self.status_message = String::from("render given up, showing a draft");
+                    return Some(self.start_draft());
+                }
+                self.status_message = String::from("render cancelled");
+            }
+            Message::BookmarkAdded => return self.add_bookmark(None),
+            Message::RelativeBookmarkAdded => {
+                let Some(anchor) = self.anchor else {
+                    self.status_message = String::from(NO_ANCHOR);
+                    return None;
+                };
+                return self.add_bookmark(Some(RelativeView::capture(&self.viewport, anchor)));
+            }
+            Message::RelativeBookmarkApplied(id) => {
+                let bookmark = self
+                    .bookmarks
+                    .bookmarks
+                    .iter()
+                    .find(|bookmark| bookmark.id == id)?;
+                let relative = bookmark.relative?;
+                let Some(anchor) = self.anchor else {
+                    self.status_message = String::from(NO_ANCHOR);
+                    return None;
+                };
+                self.status_message = format!(
+                    "{} applied at the {}, scaled {:.3e}x",
+                    bookmark.name,
+                    anchor.describe(),
+                    relative::size_ratio(&relative.anchor, &anchor)
+                );
+                self.go_to(bookmark.fractal, relative.apply(&anchor));
+                should_draw = true;
+            }
+            Message::BookmarkSelected(id, with_settings) => {
+                let bookmark = self
+                    .bookmarks
+                    .bookmarks
+                    .iter()
+                    .find(|bookmark| bookmark.id == id)?;
+                let (fractal, viewport) = (bookmark.fractal, bookmark.viewport);
+                let annotations = bookmark.annotations.clone();
+                if with_settings || bookmark.restore_settings {
+                    let overrides = bookmark.overrides.clone();
+                    self.apply_overrides(&overrides);
+                }
+                self.go_to(fractal, viewport);
+                self.add_annotations(&annotations);
+                should_draw = true;
+            }
+            Message::BookmarkRestoreToggled(id) => {
+                let bookmark = self
+                    .bookmarks
+                    .bookmarks
+                    .iter_mut()
+                    .find(|bookmark| bookmark.id == id)?;
+                bookmark.restore_settings = !bookmark.restore_settings;
+                self.save_bookmarks();
+                return self.request_thumbnails();
+            }
+            Message::PresetSelected(index, with_settings) => {
+                let preset = Preset::builtin().into_iter().nth(index)?;
+                if with_settings {
+                    self.apply_overrides(&preset.overrides);
+                }
+                self.go_to(preset.fractal, preset.viewport);
+                should_draw = true;
+            }
+            Message::BookmarkDeleted(id) => {
+                let index = self
+                    .bookmarks
+                    .bookmarks
+                    .iter()
+                    .position(|bookmark| bookmark.id == id)?;
+                let bookmark = self.bookmarks.bookmarks.remove(index);
+                self.thumbnails.remove(&bookmark.thumbnail_name());
+                if self.persist {
+                    if let Some(dir) = Bookmarks::thumbnail_dir() {
+                        let _ = std::fs::remove_file(dir.join(bookmark.thumbnail_name()));
+                    }
+                }
+                self.save_bookmarks();
+                return self.request_thumbnails();
+            }
+            Message::BookmarksChanged => {
+                if !self.persist {
+                    return None;
+                }
+                let merged = self.bookmarks.reload()?;
+                if let Some(summary) = merged.summary() {
+                    self.status_message = format!("bookmarks from another window: {}", summary);
+                }
+                return self.request_thumbnails();
+            }
+            Message::BookmarksToggled => {
+                self.show_bookmarks = !self.show_bookmarks;
+                if self.show_bookmarks {
+                    self.show_minibrots = false;
+                    self.show_queue = false;
+                    self.show_journal = false;
+                    self.show_path = false;
+                    return self.request_thumbnails();
+                }
+            }
+            Message::MinibrotsToggled => {
+                self.show_minibrots = !self.show_minibrots;
+                if self.show_minibrots {
+                    self.show_bookmarks = false;
+                    self.show_queue = false;
+                    self.show_journal = false;
+                    self.show_path = false;
+                    return self.scan_minibrots();
+                }
+            }
+            Message::MinibrotsRequested => {
+                return self.scan_minibrots();
+            }
+            Message::MinibrotsFound(generation, candidates) => {
+                if self.minibrot_scan == Some(generation) {
+                    self.minibrot_scan = None;
+                }
+                if generation == self.installed_generation {
+                    self.minibrots = candidates;
+                }
+            }
+            Message::ExportQueued => {
+                let profile = self.config.queued_profile();
+                let settings = profile.settings;
+                let named = self
+                    .config
+                    .export_profile
+                    .is_some()
+                    .then(|| profile.name.clone());
+                let size = self.export_size_for(settings);
+                let state = self.config.state();
+                let spec = ExportSpec {
+                    fractal: self.config.fractal,
+                    viewport: self.viewport,
+                    max_iterations: settings.max_iterations,
+                    width: size.width as u32,
+                    height: size.height as u32,
+                    antialias: settings.antialias.max(1),
+                    profile: named,
+                    coloring: state.coloring.clone(),
+                    coarse_prepass: self.config.coarse_prepass,
+                    double_double: self.config.experimental_double_double,
+                    dir: self.config.export_dir(),
+                    name: export::expand_template(
+                        &self.config.filename_template,
+                        &self.template_fields(),
+                    ),
+                }
+                .padded(self.config.safe_area.padding);
+                let id = self.exports.push(spec);
+                self.status_message = format!("queued export {}", id);
+                self.save_exports();
+            }
+            Message::QueueToggled => {
+                self.show_queue = !self.show_queue;
+                if self.show_queue {
+                    self.show_bookmarks = false;
+                    self.show_minibrots = false;
+                    self.show_journal = false;
+                    self.show_path = false;
+                }
+            }
+            Message::JournalToggled => {
+                self.show_journal = !self.show_journal;
+                if self.show_journal {
+                    self.show_bookmarks = false;
+                    self.show_minibrots = false;
+                    self.show_queue = false;
+                    self.show_path = false;
+                }
+            }
+            Message::PathToggled => {
+                self.show_path = !self.show_path;
+                if self.show_path {
+                    self.show_bookmarks = false;
+                    self.show_minibrots = false;
+                    self.show_queue = false;
+                    self.show_journal = false;
+                }
+            }
+            Message::KeyframeAdded => {
+                let path = &mut self.camera_path;
+                if path.keyframes.is_empty() {
+                    path.fractal = self.config.fractal;
+                } else if path.fractal != self.config.fractal {
+                    self.status_message = format!(
+                        "the camera path runs through the {}; switch to it to add views",
+                        path.fractal.fractal().name()
+                    );
+                    return None;
+                }
+                path.keyframes.push(Keyframe {
+                    viewport: self.viewport,
+                    hold: 0.0,
+                    travel: camera::DEFAULT_TRAVEL,
+                    easing: Easing::default(),
+                });
+                self.status_message = format!("added keyframe {}", path.keyframes.len());
+                self.save_camera_path();
+            }
+            Message::KeyframeSelected(index) => {
+                let keyframe = self.camera_path.keyframes.get(index)?;
+                let viewport = keyframe.viewport;
+                self.scrub = self.camera_path.keyframe_time(index);
+                self.go_to(self.camera_path.fractal, viewport);
+                should_draw = true;
+            }
+            Message::KeyframeDeleted(index) => {
+                if index >= self.camera_path.keyframes.len() {
+                    return None;
+                }
+                self.camera_path.keyframes.remove(index);
+                self.save_camera_path();
+            }
+            Message::KeyframeHoldCycled(index) => {
+                let keyframe = self.camera_path.keyframes.get_mut(index)?;
+                keyframe.hold = next_step(&HOLD_STEPS, keyframe.hold);
+                self.save_camera_path();
+            }
+            Message::KeyframeTravelCycled(index) => {
+                let keyframe = self.camera_path.keyframes.get_mut(index)?;
+                keyframe.travel = next_step(&TRAVEL_STEPS, keyframe.travel);
+                self.save_camera_path();
+            }
+            Message::KeyframeEasingCycled(index) => {
+                let keyframe = self.camera_path.keyframes.get_mut(index)?;
+                keyframe.easing = keyframe.easing.next();
+                self.save_camera_path();
+            }
+            // Scrubbing shows drafts; letting go renders the view properly.
+            Message::PathScrubbed(seconds) => {
+                let viewport = self.camera_path.viewport_at(seconds)?;
+                self.scrub = seconds;
+                self.go_to(self.camera_path.fractal, viewport);
+                self.data_file = None;
+                return Some(self.start_draft());
+            }
+            Message::PathScrubReleased => should_draw = true,
+            Message::JournalSelected(id) => {
+                let entry = self.journal.get(id)?;
+                let (fractal, viewport) = (entry.fractal, entry.viewport);
+                self.go_to(fractal, viewport);
+                should_draw = true;
+            }
+            Message::JournalExported => self.export_contact_sheet(),
+            Message::ExportMoved(id, offset) => {
+                self.exports.move_by(id, offset);
+                self.save_exports();
+            }
+            Message::ExportPauseToggled(id) => {
+                let job = self.exports.get_mut(id)?;
+                job.paused = !job.paused;
+                self.save_exports();
+            }
+            Message::ExportCancelled(id) => {
+                self.exports.remove(id)?;
+                if let Some((_, cancel)) = self.export_cancel.as_ref().filter(|(job, _)| *job == id)
+                {
+                    cancel.cancel();
+                }
+                self.report_batch();
+                self.save_exports();
+            }
+            Message::ExportProgress(id, progress) => {
+                self.exports.get_mut(id)?.progress = progress.fraction();
+                self.export_eta.record(&progress);
+            }
+            Message::ExportFinished(id, result) => {
+                if self
+                    .export_cancel
+                    .as_ref()
+                    .is_some_and(|(job, _)| *job == id)
+                {
+                    self.export_cancel = None;
+                }
+                // Cancelled exports have already left the queue.
+                let job = self.exports.remove(id)?;
+                self.status_message = match result {
+                    Ok(Some(path)) => {
+                        let message = format!("exported {}", path.display());
+                        self.exported.insert(job.spec.settings_hash(), path);
+                        self.batch.rendered += 1;
+                        message
+                    }
+                    Ok(None) => {
+                        self.batch.rendered += 1;
+                        format!("export {} done", id)
+                    }
+                    Err(err) => {
+                        self.batch.failed += 1;
+                        format!("export {} failed: {}", id, err)
+                    }
+                };
+                println!("{}", self.status_message);
+                self.report_batch();
+                self.save_exports();
+            }
+            Message::AnchorAtCursor => {
+                if self.config.fractal != FractalKind::Mandelbrot {
+                    self.status_message = String::from("anchors need the Mandelbrot set");
+                    return None;
+                }
+                let (re, im) = self.viewport.pixel_to_complex(
+                    self.frame_point(self.current_mouse_location),
+                    self.window_size,
+                );
+                let located = minibrot::locate(
+                    re,
+                    im,
+                    self.config.active().settings.max_iterations,
+                    self.viewport.pixel_size(self.window_size),
+                );
+                match located {
+                    Some(candidate) => self.set_anchor(relative::Anchor::of(&candidate)),
+                    None => {
+                        self.status_message = String::from("no minibrot found at the cursor");
+                    }
+                }
+            }
+            Message::MinibrotAnchored(index) => {
+                let anchor = relative::Anchor::of(self.minibrots.get(index)?);
+                self.set_anchor(anchor);
+            }
+            Message::MinibrotSelected(index) => {
+                let candidate = self.minibrots.get(index)?;
+                self.viewport = Viewport::new(candidate.re, candidate.im, candidate.width);
+                self.minibrots.clear();
+                self.status_message = String::new();
+                should_draw = true;
+            }
+            Message::TuneRequested => {
+                // Headless runs keep the defaults, so that they behave the same everywhere.
+                if !self.persist || self.tune_cancel.is_some() {
+                    return None;
+                }
+                let cancel = CancelToken::new();
+                self.tune_cancel = Some(cancel.clone());
+                self.tune_progress = 0.0;
+                let (tx, rx) = mpsc::unbounded();
+                thread::spawn(move || {
+                    let send = |message| {
+                        let _ = tx.unbounded_send(message);
+                    };
+                    let result = tune::run(&cancel, TUNE_BUDGET, |fraction| {
+                        send(Message::TuneProgress(fraction))
+                    });
+                    send(Message::TuneFinished(result));
+                });
+                return Some(rx);
+            }
+            Message::TuneProgress(fraction) => self.tune_progress = fraction,
+            Message::TuneCancelled => {
+                if let Some(cancel) = &self.tune_cancel {
+                    cancel.cancel();
+                }
+            }
+            Message::TuneFinished(result) => {
+                self.tune_cancel = None;
+                match result {
+                    Ok(tuning) => {
+                        self.status_message = format!(
+                            "tuned: {} workers, {} px tiles",
+                            tuning.threads, tuning.chunk_size
+                        );
+                        self.config.tuning = Some(tuning);
+                    }
+                    Err(err) => {
+                        self.status_message = format!("tuning stopped: {}", err);
+                        // Keep to the defaults on this machine rather than tuning on every launch.
+                        if !self.config.tuning.as_ref().is_some_and(Tuning::is_current) {
+                            self.config.tuning = Some(Tuning::heuristic());
+                        }
+                    }
+                }
+                println!("{}", self.status_message);
+                self.save_config();
+                tiling::set_chunk_size(self.tuning().chunk_size);
+                self.threadpool.set_num_threads(self.render_threads());
+                self.export_pool.set_num_threads(self.render_threads());
+            }
+            Message::ThumbnailReady(name, handle) => {
+                self.thumbnails.insert(name, handle);
+            }
+            Message::IdleTick(generation) => {
+                if generation != self.generation
+                    || self.rendering.is_some()
+                    || self.prefetching.is_some()
+                    || self.energy_saving()
+                {
+                    return None;
+                }
+                if self.last_input.elapsed() < self.idle_delay() {
+                    return Some(self.schedule_idle());
+                }
+                if self.tiles_saved.elapsed() >= TILE_SAVE_INTERVAL {
+                    self.tiles_saved = Instant::now();
+                    thread::spawn(save_tiles);
+                }
+                if self.config.idle_refinement {
+                    if let Some(events) = self.start_refinement() {
+                        return Some(events);
+                    }
+                }
+                return self.start_prefetch();
+            }
+            Message::Prefetched(generation, piece, result) => {
+                self.prefetching = None;
+                let Ok(tiles) = result else {
+                    return None;
+                };
+                if generation != self.generation {
+                    return None;
+                }
+                // A render that started since holds the prefetch and has the piece's tiles
+                // dropped; the next prefetch computes it again.
+                if let Some(prefetch) = self.prefetch.as_mut().and_then(Arc::get_mut) {
+                    prefetch.insert(piece, &tiles);
+                }
+                if self.rendering.is_some() || self.energy_saving() {
+                    return None;
+                }
+                if self.last_input.elapsed() < self.idle_delay() {
+                    return Some(self.schedule_idle());
+                }
+                return self.start_prefetch();
+            }
+            Message::ScaleFactorChanged(scale_factor) => {
+                let scale_factor = f64::from(scale_factor);
+                if !scale_factor.is_finite() || scale_factor <= 0.0 {
+                    return None;
+                }
+                if scale_factor == self.scale_factor {
+                    return None;
+                }
+                println!("scale factor {} -> {}", self.scale_factor, scale_factor);
+                self.scale_factor = scale_factor;
+                self.scale_token += 1;
+                let (tx, rx) = mpsc::unbounded();
+                let token = self.scale_token;
+                thread::spawn(move || {
+                    thread::sleep(SCALE_SETTLE);
+                    let _ = tx.unbounded_send(Message::ScaleSettled(token));
+                });
+                return Some(rx);
+            }
+            Message::ScaleSettled(token) => {
+                // The viewport keeps its framing; only the pixel count changes.
+                should_draw = token == self.scale_token && self.data_file.is_none();
+            }
+            Message::GotoChanged(input) => self.goto_input = input,
+            Message::GotoSubmitted => match numbers::parse_location(&self.goto_input) {
+                Ok((center_re, center_im, width)) => {
+                    self.viewport =
+                        Viewport::new(center_re, center_im, width.unwrap_or(self.viewport.width));
+                    self.status_message = String::new();
+                    should_draw = true;
+                }
+                Err(err) => self.status_message = err,
+            },
+            Message::DecimalSeparatorCycled => {
+                let format = &mut self.config.number_format;
+                format.decimal = format.decimal.next();
+                self.save_config();
+            }
+            Message::DigitGroupingToggled => {
+                self.config.number_format.grouping = !self.config.number_format.grouping;
+                self.save_config();
+            }
+            Message::InvertYToggled => {
+                self.config.invert_y = !self.config.invert_y;
+                self.save_config();
+                self.recolor();
+            }
+            Message::CrosshairToggled => {
+                self.config.guides.crosshair = !self.config.guides.crosshair;
+                self.save_config();
+            }
+            Message::ThirdsToggled => {
+                self.config.guides.thirds = !self.config.guides.thirds;
+                self.save_config();
+            }
+            Message::GuideColorCycled => {
+                self.config.guides.color = self.config.guides.color.next();
+                self.save_config();
+            }
+            Message::GuideOpacityCycled => {
+                self.config.guides.opacity = self.config.guides.next_opacity();
+                self.save_config();
+            }
+            Message::AnnotationAdded => {
+                let window = Rectangle::new(Point::ORIGIN, self.window_size);
+                let at = if window.contains(self.current_mouse_location) {
+                    self.current_mouse_location
+                } else {
+                    window.center()
+                };
+                let anchor = self.window_pin(at);
+                let annotations = &mut self.config.state_mut().annotations;
+                let id = annotation::next_id(annotations);
+                annotations.push(Annotation {
+                    id,
+                    text: String::from("Label"),
+                    anchor,
+                    tail: None,
+                    min_width: None,
+                    max_width: None,
+                });
+                self.config.show_annotations = true;
+                self.editing_annotation = Some(id);
+                self.save_config();
+            }
+            Message::AnnotationGrabbed(id, part) => {
+                let annotation = self
+                    .config
+                    .state()
+                    .annotations
+                    .iter()
+                    .find(|annotation| annotation.id == id)?;
+                let grabbed = match part {
+                    Part::Anchor => annotation.anchor,
+                    Part::Tail => annotation.tail?,
+                };
+                let offset = self.current_mouse_location - self.annotation_point(grabbed);
+                self.annotation_grab = Some((id, part, offset));
+                self.editing_annotation = Some(id);
+            }
+            Message::AnnotationDragged(point) => {
+                let (id, part, offset) = self.annotation_grab?;
+                let anchor = self.window_pin(point - offset);
+                let annotation = self.annotation_mut(id)?;
+                match part {
+                    Part::Anchor => annotation.anchor = anchor,
+                    Part::Tail => annotation.tail = Some(anchor),
+                }
+            }
+            Message::AnnotationReleased => {
+                if self.annotation_grab.take().is_some() {
+                    self.save_config();
+                }
+            }
+            Message::AnnotationTextChanged(text) => {
+                let id = self.editing_annotation?;
+                self.annotation_mut(id)?.text = text;
+            }
+            Message::AnnotationArrowToggled => {
+                let id = self.editing_annotation?;
+                let annotation = self
+                    .config
+                    .state()
+                    .annotations
+                    .iter()
+                    .find(|annotation| annotation.id == id)?;
+                let tail = match annotation.tail {
+                    Some(_) => None,
+                    None => {
+                        let at = self.annotation_point(annotation.anchor);
+                        Some(self.window_pin(at - ARROW_REACH))
+                    }
+                };
+                self.annotation_mut(id)?.tail = tail;
+                self.save_config();
+            }
+            Message::AnnotationRangeSet(zoomed_out) => {
+                let id = self.editing_annotation?;
+                let width = self.viewport.width;
+                let annotation = self.annotation_mut(id)?;
+                if zoomed_out {
+                    annotation.max_width = Some(width);
+                } else {
+                    annotation.min_width = Some(width);
+                }
+                self.save_config();
+            }
+            Message::AnnotationRangeCleared => {
+                let id = self.editing_annotation?;
+                let annotation = self.annotation_mut(id)?;
+                annotation.min_width = None;
+                annotation.max_width = None;
+                self.save_config();
+            }
+            Message::AnnotationDeleted => {
+                let id = self.editing_annotation.take()?;
+                self.config
+                    .state_mut()
+                    .annotations
+                    .retain(|annotation| annotation.id != id);
+                self.save_config();
+            }
+            Message::AnnotationEditorClosed => {
+                if self.editing_annotation.take().is_some() {
+                    self.save_config();
+                }
+            }
+            Message::AnnotationsToggled => {
+                self.config.show_annotations = !self.config.show_annotations;
+                if !self.config.show_annotations {
+                    self.editing_annotation = None;
+                    self.annotation_grab = None;
+                }
+                self.save_config();
+            }
+            Message::AnnotationsInExportsToggled => {
+                self.config.annotations_in_exports = !self.config.annotations_in_exports;
+                self.save_config();
+            }
+            Message::RegionsEdited => {
+                self.editing_regions = !self.editing_regions;
+                if !self.editing_regions {
+                    self.selected_region = None;
+                    self.region_grab = None;
+                }
+            }
+            Message::RegionAdded => {
+                let window = Rectangle::new(Point::ORIGIN, self.window_size);
+                let at = if window.contains(self.current_mouse_location) {
+                    self.current_mouse_location
+                } else {
+                    window.center()
+                };
+                let center = self.window_pin(at);
+                let pixel_size = self.viewport.pixel_size(self.window_size);
+                let half = f64::from(window.width.min(window.height)) / 8.0 * pixel_size;
+                let regions = &mut self.config.state_mut().coloring.regions;
+                let id = adjust::next_id(regions);
+                regions.push(Region::new(id, center, half, half));
+                self.editing_regions = true;
+                self.selected_region = Some(id);
+                self.save_config();
+                self.recolor();
+            }
+            Message::RegionGrabbed(id, handle) => {
+                let region = self
+                    .config
+                    .state()
+                    .coloring
+                    .regions
+                    .iter()
+                    .find(|region| region.id == id)?;
+                let placed = region.placed(&self.viewport, self.window_size);
+                let grabbed = match handle {
+                    Handle::Center => placed.center,
+                    Handle::Corner => placed.corner(),
+                };
+                let offset = self.current_mouse_location - self.frame_point(grabbed);
+                self.region_grab = Some((id, handle, offset));
+                self.selected_region = Some(id);
+            }
+            Message::RegionDragged(point) => {
+                let (id, handle, offset) = self.region_grab?;
+                let pin = self.window_pin(point - offset);
+                let least =
+                    f64::from(adjust::MIN_HALF_EXTENT) * self.viewport.pixel_size(self.window_size);
+                let region = self.region_mut(id)?;
+                match handle {
+                    Handle::Center => region.center = pin,
+                    Handle::Corner => {
+                        let (across, up) = adjust::offset(region.center, pin);
+                        region.half_width = across.abs().max(least);
+                        region.half_height = up.abs().max(least);
+                    }
+                }
+                self.recolor();
+            }
+            Message::RegionReleased => {
+                if self.region_grab.take().is_some() {
+                    self.save_config();
+                }
+            }
+            Message::RegionShapeToggled => {
+                let region = self.region_mut(self.selected_region?)?;
+                region.shape = region.shape.next();
+                self.save_config();
+                self.recolor();
+            }
+            Message::RegionAdjusted(knob, up) => {
+                self.region_mut(self.selected_region?)?.adjust(knob, up);
+                self.save_config();
+                self.recolor();
+            }
+            Message::RegionDeleted => {
+                let id = self.selected_region.take()?;
+                self.config
+                    .state_mut()
+                    .coloring
+                    .regions
+                    .retain(|region| region.id != id);
+                self.save_config();
+                self.recolor();
+            }
+            Message::GuidesInExportsToggled => {
+                self.config.guides.in_exports = !self.config.guides.in_exports;
+                self.save_config();
+            }
+            Message::SafeAreaCycled => {
+                self.config.safe_area.template = self.config.safe_area.next_template();
+                self.save_config();
+            }
+            Message::SafeMarginChanged(edge, margin) => {
+                self.config.safe_area.custom.set(edge, margin);
+            }
+            Message::ExportPaddingChanged(padding) => self.config.safe_area.padding = padding,
+            Message::ExportProfileCycled => {
+                self.config.export_profile = self.config.next_export_profile();
+                self.save_config();
+            }
+            Message::MaxIterationsChanged(max_iterations) => {
+                self.config.active_mut().settings.max_iterations = max_iterations;
+            }
+            Message::ResolutionScaleChanged(resolution_scale) => {
+                self.config.active_mut().settings.resolution_scale = resolution_scale;
+            }
+            Message::AntialiasChanged(antialias) => {
+                self.config.active_mut().settings.antialias = antialias;
+            }
+            Message::SettingsReleased => {
+                self.save_config();
+                should_draw = self.data_file.is_none();
+            }
+            Message::PaletteCycled | Message::PaletteCycledBack => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.set_palette(if matches!(message, Message::PaletteCycled) {
+                    Palette::next_name(&coloring.palette)
+                } else {
+                    Palette::previous_name(&coloring.palette)
+                });
+                self.save_config();
+                self.recolor();
+            }
+            Message::ColoringModeCycled => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.mode = coloring.mode.next();
+                let angle = coloring.mode.angle();
+                self.save_config();
+                if self.lacks_angle(angle) {
+                    should_draw = true;
+                } else {
+                    self.recolor();
+                }
+            }
+            Message::PaletteWrapCycled => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.set_palette_wrap(coloring.palette_wrap().next());
+                self.recolor();
+                self.save_config();
+            }
+            Message::InteriorColorCycled => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.set_interior_color(coloring.interior_color.next());
+                self.recolor();
+                self.save_config();
+            }
+            Message::UnresolvedColorCycled => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.set_unresolved_color(coloring.unresolved_color.next());
+                self.recolor();
+                self.save_config();
+            }
+            Message::ColorManagementToggled => {
+                self.config.color_management = !self.config.color_management;
+                self.save_config();
+                self.load_display_profile();
+                self.recolor();
+            }
+            Message::BufferPrecisionCycled => {
+                self.config.buffer_precision = self.config.buffer_precision.next();
+                self.save_config();
+                should_draw = true;
+            }
+            Message::CoarsePrepassToggled => {
+                self.config.coarse_prepass = !self.config.coarse_prepass;
+                self.save_config();
+                should_draw = true;
+            }
+            Message::DoubleDoubleToggled => {
+                self.config.experimental_double_double = !self.config.experimental_double_double;
+                self.save_config();
+                should_draw = true;
+            }
+            Message::EnergySaverCycled => {
+                self.config.energy_saver = self.config.energy_saver.next();
+                self.check_battery(true);
+                self.save_config();
+            }
+            Message::CpuCapChanged(percent) => {
+                let before = duty::cap();
+                duty::set_cap(percent);
+                self.config.cpu_cap = duty::cap();
+                // Renders underway slow down or speed up by the change; their estimates follow.
+                let factor = f64::from(before) / f64::from(duty::cap());
+                if let Some(job) = &mut self.rendering {
+                    job.eta.slow_by(factor);
+                }
+                self.export_eta.slow_by(factor);
+                self.save_config();
+            }
+            Message::ExplorationLogToggled => {
+                self.config.exploration_log = !self.config.exploration_log;
+                self.save_config();
+                if self.config.exploration_log && self.rendering.is_none() {
+                    self.log_view();
+                }
+            }
+            Message::OrbitSoundToggled => {
+                self.config.orbit_sound = !self.config.orbit_sound;
+                self.save_config();
+                #[cfg(feature = "sound")]
+                if !self.config.orbit_sound {
+                    self.sound = None;
+                }
+            }
+            Message::PrefetchToggled => {
+                self.config.prefetch = !self.config.prefetch;
+                self.save_config();
+                if self.config.prefetch {
+                    return (self.rendering.is_none() && !self.energy_saving())
+                        .then(|| self.schedule_idle());
+                }
+                if let Some(cancel) = self.prefetching.take() {
+                    cancel.cancel();
+                }
+                self.prefetch = None;
+            }
+            Message::PerfHudToggled => self.show_hud = !self.show_hud,
+            Message::TileOrderToggled => {
+                self.config.debug_tile_order = !self.config.debug_tile_order;
+                self.save_config();
+                should_draw = true;
+            }
+            Message::FieldBlendChanged(field_blend) => {
+                self.config.state_mut().coloring.field_blend = field_blend;
+                self.recolor();
+            }
+            Message::GammaChanged(gamma) => {
+                self.config.state_mut().coloring.gamma = gamma;
+                self.recolor();
+            }
+            Message::DensityChanged(density) => {
+                self.config.state_mut().coloring.density = density;
+                self.recolor();
+            }
+            Message::PaletteOffsetChanged(offset) => {
+                self.config.state_mut().coloring.palette_offset = offset;
+                self.recolor();
+            }
+            Message::AutoLevelsApplied => {
+                if self.apply_auto_levels() {
+                    self.recolor();
+                }
+            }
+            Message::AutoLevelsAfterRenderToggled => {
+                self.config.auto_levels = !self.config.auto_levels;
+                self.save_config();
+            }
+            Message::SectorsChanged(sectors) => {
+                self.config.state_mut().coloring.sectors = sectors;
+                self.recolor();
+            }
+            Message::HueRotationChanged(hue_rotation) => {
+                self.config.state_mut().coloring.hue_rotation = hue_rotation;
+                self.recolor();
+            }
+            Message::OcclusionToggled => {
+                let coloring = &mut self.config.state_mut().coloring;
+                coloring.occlusion = !coloring.occlusion;
+                self.recolor();
+                self.save_config();
+            }
+            Message::OcclusionRadiusChanged(radius) => {
+                self.config.state_mut().coloring.occlusion_radius = radius;
+                self.recolor();
+            }
+            Message::OcclusionStrengthChanged(strength) => {
+                self.config.state_mut().coloring.occlusion_strength = strength;
+                self.recolor();
+            }
+            Message::PostStageAdded(kind) => {
+                if let Some(stage) = Stage::BUILTIN.get(kind) {
+                    self.config.state_mut().coloring.post.push(stage.clone());
+                    self.recolor();
+                    self.save_config();
+                }
+            }
+            Message::PostStageAdjusted(index, up) => {
+                if let Some(stage) = self.config.state_mut().coloring.post.get_mut(index) {
+                    stage.adjust(up);
+                    self.recolor();
+                    self.save_config();
+                }
+            }
+            Message::PostStageMoved(index, offset) => {
+                let post = &mut self.config.state_mut().coloring.post;
+                let target = index as isize + offset;
+                if index >= post.len() || target < 0 || target as usize >= post.len() {
+                    return None;
+                }
+                post.swap(index, target as usize);
+                // Focus goes with the stage.
+                if self.focused == Some(Control::PostStage(index)) {
+                    self.focused = Some(Control::PostStage(target as usize));
+                }
+                self.recolor();
+                self.save_config();
+            }
+            Message::PostStageRemoved(index) => {
+                let post = &mut self.config.state_mut().coloring.post;
+                if index < post.len() {
+                    post.remove(index);
+                    self.recolor();
+                    self.save_config();
+                }
+            }
+            Message::PostExpressionChanged(input) => self.post_input = input,
+            Message::PostExpressionSubmitted => {
+                let source = self.post_input.trim().to_string();
+                match postprocess::compile_expression(&source) {
+                    Ok(_) => {
+                        self.config
+                            .state_mut()
+                            .coloring
+                            .post
+                            .push(Stage::Expression { source });
+                        self.post_input = String::new();
+                        self.status_message = String::new();
+                        self.recolor();
+                        self.save_config();
+                    }
+                    Err(err) => self.status_message = format!("expression {}", err),
+                }
+            }
+            Message::LookExported => {
+                self.export_look();
+            }
+            Message::ProfileCycled => {
+                self.config.cycle_profile();
+                self.save_config();
+                should_draw = true;
+            }
+            Message::QuickExported => self.quick_export(),
+            Message::ImageCopied => self.copy_image(),
+            Message::LocationCopied => self.copy_location(),
+            Message::PasteRequested => match self.clipboard.text() {
+                Ok(text) => self.offer_paste(&text),
+                Err(err) => {
+                    self.status_message = err;
+                    println!("{}", self.status_message);
+                }
+            },
+            Message::TextPasted(text) => self.offer_paste(&text),
+            Message::PasteConfirmed => {
+                self.viewport = self.paste_offer.take()?;
+                self.status_message = String::new();
+                should_draw = true;
+            }
+            Message::PasteDismissed => self.paste_offer = None,
+            Message::ImageLocated(result) => {
+                self.locating = false;
+                match result {
+                    Ok(located) => {
+                        self.paste_offer = Some(located.viewport);
+                        self.status_message = if located.likely() {
+                            format!("image located, {:.0}% match", located.confidence * 100.0)
+                        } else {
+                            format!(
+                                "best guess only, {:.0}% match: views magnified more than {:.0}x \
+                                 are rarely found",
+                                located.confidence * 100.0,
+                                locate::max_magnification()
+                            )
+                        };
+                    }
+                    Err(err) => {
+                        self.paste_offer = None;
+                        self.status_message = format!("cannot locate the image: {}", err);
+                    }
+                }
+                println!("{}", self.status_message);
+            }
+            Message::CommandPaletteToggled => {
+                self.command_query = match self.command_query {
+                    Some(_) => None,
+                    None => Some(String::new()),
+                };
+            }
+            Message::CommandQueryChanged(query) => {
+                if self.command_query.is_some() {
+                    self.command_query = Some(query);
+                }
+            }
+            Message::CommandSubmitted => {
+                let query = self.command_query.as_ref()?;
+                let action = actions::ranked(query, &self.config.recent_commands)
+                    .into_iter()
+                    .next();
+                match action {
+                    Some(action) => return self.handle(Message::CommandRun(action.name)),
+                    None => self.status_message = format!("no command matches \"{}\"", query),
+                }
+            }
+            Message::CommandRun(name) => {
+                let action = actions::named(name)?;
+                self.command_query = None;
+                let recent = &mut self.config.recent_commands;
+                recent.retain(|recent| recent != name);
+                recent.insert(0, name.to_string());
+                recent.truncate(actions::RECENT_COMMANDS);
+                self.save_config();
+                return self.handle(action.message);
+            }
+            Message::SettingFocused(setting) => {
+                self.show_settings = true;
+                self.focused = Some(Control::Setting(setting));
+            }
+            Message::DataExported => self.export_data(),
+            Message::Peeked(peeking) => self.peeking = peeking,
+            Message::BoundaryExported(format) => self.export_boundary(format),
+            Message::ProjectSaved(with_frame) => self.save_project(with_frame),
+            Message::SettingsToggled => {
+                self.show_settings = !self.show_settings;
+                self.observe_gesture(Gesture::SettingsToggled);
+            }
+            Message::TourSkipped => self.end_tour(),
+            Message::DataFileClosed => should_draw = self.data_file.is_some(),
+            Message::ViewReset => {
+                self.viewport = Viewport::home(self.config.fractal, self.window_size);
+                should_draw = true;
+            }
+            Message::FractalCycled => {
+                self.config.state_mut().viewport = Some(self.viewport);
+                self.config.cycle_fractal();
+                self.viewport = self
+                    .config
+                    .state()
+                    .viewport
+                    .unwrap_or_else(|| Viewport::home(self.config.fractal, self.window_size));
+                self.save_config();
+                should_draw = true;
+            }
+            Message::HybridStepCycled(index) => {
+                let mut rules = self.hybrid_steps()?;
+                let rule = rules.get_mut(index)?;
+                *rule = rule.next();
+                should_draw = self.set_hybrid(&rules);
+            }
+            Message::HybridStepAdded => {
+                let mut rules = self.hybrid_steps()?;
+                rules.push(Rule::Mandelbrot);
+                should_draw = self.set_hybrid(&rules);
+            }
+            Message::HybridStepRemoved(index) => {
+                let mut rules = self.hybrid_steps()?;
+                if index >= rules.len() {
+                    return None;
+                }
+                rules.remove(index);
+                should_draw = self.set_hybrid(&rules);
+            }
+            Message::ViewPanned(right, up) => {
+                let width = self.viewport.width;
+                if !right.is_finite() || !up.is_finite() {
+                    return None;
+                }
+                // In whole render pixels, so that the new frame shares the old one's pixel grid
+                // and copies what both show.
+                let pixels = self.render_size().width as f64;
+                let pixel_size = width / pixels;
+                // Up the screen is down the imaginary axis when the display is mirrored.
+                let up = if self.config.invert_y { -up } else { up };
+                self.viewport = self.viewport.shifted(
+                    (right * pixels).round() * pixel_size,
+                    (up * pixels).round() * pixel_size,
+                    width,
+                );
+                should_draw = true;
+            }
+            Message::ViewZoomed(factor) => {
+                if !factor.is_finite() || factor <= 0.0 {
+                    return None;
+                }
+                self.viewport = self
+                    .viewport
+                    .shifted(0.0, 0.0, self.viewport.width * factor);
+                self.observe_gesture(Gesture::Zoom);
+                should_draw = true;
+            }
+            Message::OpenPathChanged(input) => self.open_input = input,
+            Message::OpenPathSubmitted => {
+                let path = self.open_input.trim();
+                if path.is_empty() {
+                    return None;
+                }
+                return self.handle(Message::FileOpened(PathBuf::from(path)));
+            }
+            Message::FileOpened(path) => {
+                if Look::is_look_file(&path) {
+                    should_draw = self.apply_look(&path);
+                } else if Project::is_project_file(&path) {
+                    should_draw = self.open_project(&path);
+                } else if locate::is_image_file(&path) {
+                    return self.locate_image(&path);
+                } else {
+                    self.open_data(&path);
+                }
+            }
+            Message::SelectionStarted(point) => {
+                self.start_location = point;
+                self.end_location = point;
+                self.cursor = PrecisionCursor::at(point);
+                self.draw_bounding_box = true;
+            }
+            Message::SelectionDragged(point) => {
+                if self.draw_bounding_box {
+                    self.end_location = self.cursor.moved(point, self.modifiers.shift());
+                }
+            }
+            Message::SelectionResized(point) => {
+                if self.draw_bounding_box {
+                    self.end_location = point;
+                }
+            }
+            Message::SelectionMoved(offset) => {
+                if self.draw_bounding_box {
+                    self.start_location = self.start_location + offset;
+                    self.end_location = self.end_location + offset;
+                }
+            }
+            Message::SelectionCommitted => {
+                if !self.draw_bounding_box {
+                    return None;
+                }
+                self.draw_bounding_box = false;
+                if self.end_location.x != self.start_location.x
+                    && self.end_location.y != self.start_location.y
+                {
+                    match Viewport::from_selection(
+                        &self.viewport,
+                        self.frame_point(self.start_location),
+                        self.frame_point(self.end_location),
+                        self.window_size,
+                    ) {
+                        Ok(viewport) => {
+                            self.viewport = viewport;
+                            self.observe_gesture(Gesture::BoxZoom);
+                            should_draw = true;
+                        }
+                        Err(err) => {
+                            self.status_message = err.to_string();
+                            println!("{}", self.status_message);
+                        }
+                    }
+                }
+            }
+            Message::SelectionCancelled => self.draw_bounding_box = false,
+            Message::FocusMoved(step) => {
+                self.focused = controls::cycle(&self.controls(), self.focused, step);
+            }
+            Message::FocusActivated => {
+                let message = self.activate(self.focused_control()?)?;
+                return self.handle(message);
+            }
+            Message::FocusAdjusted(step) => {
+                let control = self.focused_control()?;
+                let events = self.handle(self.adjust(control, step)?);
+                // The same as letting go of the slider.
+                match control {
+                    Control::Setting(_) => {
+                        return self.handle(Message::SettingsReleased).or(events);
+                    }
+                    Control::PathScrubber => {
+                        return self.handle(Message::PathScrubReleased).or(events);
+                    }
+                    _ => {}
+                }
+                return events;
+            }
+            Message::FocusDeleted => {
+                let control = self.focused_control()?;
+                let message = match control {
+                    Control::Bookmark(id) => Message::BookmarkDeleted(id),
+                    Control::Keyframe(index) => Message::KeyframeDeleted(index),
+                    Control::PostStage(index) => Message::PostStageRemoved(index),
+                    Control::HybridStep(index) => Message::HybridStepRemoved(index),
+                    Control::ExportJob(id) => Message::ExportCancelled(id),
+                    _ => return None,
+                };
+                // Focus passes to the next control, as the deleted one leaves the order.
+                let next = controls::cycle(&self.controls(), Some(control), 1);
+                self.focused = next.filter(|next| *next != control);
+                return self.handle(message);
+            }
+            Message::PanelsDismissed => {
+                self.show_settings = false;
+                self.show_bookmarks = false;
+                self.show_minibrots = false;
+                self.show_queue = false;
+                self.show_journal = false;
+                self.show_path = false;
+                self.focused = None;
+            }
+            Message::EventOccurred(event) => {
+                // Read what the event means as input before the bookkeeping below changes the
+                // state it depends on.
+                let input = self.input_messages(&event);
+                match event {
+                    Event::Window(window::Event::Resized(size)) => {
+                        if size.width < 1.0 || size.height < 1.0 {
+                            return None;
+                        }
+                        if self.draw_bounding_box {
+                            self.start_location =
+                                dpi::remap(self.start_location, self.window_size, size);
+                            self.end_location =
+                                dpi::remap(self.end_location, self.window_size, size);
+                        }
+                        // A loaded data file is shown fitted to the window at its own resolution.
+                        if self.data_file.is_some() {
+                            self.window_size = size;
+                            return None;
+                        }
+                        let previous_size = self.render_size();
+                        self.window_size = size;
+                        self.previous_image = None;
+                        self.viewport = if self.buffer.values.is_empty() {
+                            self.config
+                                .state()
+                                .viewport
+                                .unwrap_or_else(|| Viewport::home(self.config.fractal, size))
+                        } else {
+                            self.viewport.resized(previous_size, self.render_size())
+                        };
+                        println!("x: {} y: {}", size.width as usize, size.height as usize);
+                        should_draw = true;
+                    }
+                    Event::Mouse(mouse::Event::CursorMoved { position }) => {
+                        self.current_mouse_location = position;
+                        let render_size = self.render_size();
+                        let position = self.frame_point(position);
+                        self.focus.set(Some((
+                            position.x * render_size.width / self.window_size.width,
+                            position.y * render_size.height / self.window_size.height,
+                        )));
+                        #[cfg(feature = "sound")]
+                        self.sonify_hover(Some(position));
+                    }
+                    Event::Mouse(mouse::Event::CursorLeft) => {
+                        self.focus.set(None);
+                        #[cfg(feature = "sound")]
+                        self.sonify_hover(None);
+                    }
+                    Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                        self.modifiers = modifiers;
+                    }
+                    Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                        self.dragging = true;
+                    }
+                    Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) => {
+                        self.dragging = false;
+                    }
+                    _ => {}
+                }
+                let mut events = None;
+                for message in input {
+                    events = self.handle(message).or(events);
+                }
+                if !should_draw {
+                    return events;
+                }
+            }
+        }
+
+        if should_draw {
+            // Anything that needs new pixels leaves the data file view.
+            self.data_file = None;
+            self.show_warp_preview();
+        }
+        should_draw.then(|| self.start_render())
+    }
+
+    // What a window event means as input. Mouse gestures and keys translate to the same
+    // messages, so a selection or an action behaves the same however it was made.
+    fn input_messages(&self, event: &Event) -> Vec<Message> {
+        use keyboard::key::Named;
+
+        let (key, modifiers) = match event {
+            Event::Window(window::Event::FileDropped(path)) => {
+                return vec![Message::FileOpened(path.clone())];
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position })
+                if self.annotation_grab.is_some() =>
+            {
+                return vec![Message::AnnotationDragged(*position)];
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) if self.region_grab.is_some() => {
+                return vec![Message::RegionDragged(*position)];
+            }
+            Event::Mouse(mouse::Event::CursorMoved { position }) if self.dragging => {
+                return vec![Message::SelectionDragged(*position)];
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Left)) => {
+                let at = self.current_mouse_location;
+                if let Some((id, handle)) = self.region_at(at) {
+                    return vec![Message::RegionGrabbed(id, handle)];
+                }
+                return vec![match self.annotation_at(at) {
+                    Some((id, part)) => Message::AnnotationGrabbed(id, part),
+                    None => Message::SelectionStarted(at),
+                }];
+            }
+            Event::Mouse(mouse::Event::ButtonPressed(mouse::Button::Right)) => {
+                return vec![Message::SelectionCancelled];
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.annotation_grab.is_some() =>
+            {
+                return vec![Message::AnnotationReleased];
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left))
+                if self.region_grab.is_some() =>
+            {
+                return vec![Message::RegionReleased];
+            }
+            Event::Mouse(mouse::Event::ButtonReleased(mouse::Button::Left)) if self.dragging => {
+                return vec![Message::SelectionCommitted];
+            }
+            Event::Keyboard(keyboard::Event::KeyReleased { key, .. })
+                if key.as_ref() == keyboard::Key::Character(PEEK_KEY) =>
+            {
+                return vec![Message::Peeked(false)];
+            }
+            Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }) => (key, modifiers),
+            _ => return Vec::new(),
+        };
+        let selecting = self.draw_bounding_box && !self.dragging;
+        let focused = self.focused_control().is_some();
+        let step = Vector::new(
+            self.window_size.width * SELECTION_STEP,
+            self.window_size.height * SELECTION_STEP,
+        );
+        let arrow = match key.as_ref() {
+            keyboard::Key::Named(Named::ArrowLeft) => Some(Vector::new(-step.x, 0.0)),
+            keyboard::Key::Named(Named::ArrowRight) => Some(Vector::new(step.x, 0.0)),
+            keyboard::Key::Named(Named::ArrowUp) => Some(Vector::new(0.0, -step.y)),
+            keyboard::Key::Named(Named::ArrowDown) => Some(Vector::new(0.0, step.y)),
+            _ => None,
+        };
+        if let Some(offset) = arrow.filter(|_| selecting) {
+            return vec![if modifiers.shift() {
+                Message::SelectionResized(self.end_location + offset)
+            } else {
+                Message::SelectionMoved(offset)
+            }];
+        }
+        let message = match key.as_ref() {
+            keyboard::Key::Named(Named::Tab) => {
+                Message::FocusMoved(if modifiers.shift() { -1 } else { 1 })
+            }
+            keyboard::Key::Named(Named::ArrowUp) if focused => Message::FocusMoved(-1),
+            keyboard::Key::Named(Named::ArrowDown) if focused => Message::FocusMoved(1),
+            keyboard::Key::Named(Named::ArrowLeft) if focused => Message::FocusAdjusted(-1),
+            keyboard::Key::Named(Named::ArrowRight) if focused => Message::FocusAdjusted(1),
+            keyboard::Key::Named(Named::ArrowLeft) => Message::ViewPanned(-PAN_STEP, 0.0),
+            keyboard::Key::Named(Named::ArrowRight) => Message::ViewPanned(PAN_STEP, 0.0),
+            keyboard::Key::Named(Named::ArrowUp) => Message::ViewPanned(0.0, PAN_STEP),
+            keyboard::Key::Named(Named::ArrowDown) => Message::ViewPanned(0.0, -PAN_STEP),
+            keyboard::Key::Named(Named::Enter) if self.command_query.is_some() => {
+                Message::CommandSubmitted
+            }
+            keyboard::Key::Named(Named::Escape) if self.command_query.is_some() => {
+                Message::CommandPaletteToggled
+            }
+            keyboard::Key::Named(Named::Enter) if self.paste_offer.is_some() => {
+                Message::PasteConfirmed
+            }
+            keyboard::Key::Named(Named::Escape) if self.paste_offer.is_some() => {
+                Message::PasteDismissed
+            }
+            keyboard::Key::Named(Named::Enter) if selecting => Message::SelectionCommitted,
+            keyboard::Key::Named(Named::Enter) if focused => Message::FocusActivated,
+            keyboard::Key::Named(Named::Delete | Named::Backspace) if focused => {
+                Message::FocusDeleted
+            }
+            keyboard::Key::Named(Named::Escape) if self.draw_bounding_box => {
+                Message::SelectionCancelled
+            }
+            keyboard::Key::Named(Named::Escape) if self.tune_cancel.is_some() => {
+                Message::TuneCancelled
+            }
+            keyboard::Key::Named(Named::Escape)
+                if self.focused.is_some() || !self.controls().is_empty() =>
+            {
+                Message::PanelsDismissed
+            }
+            keyboard::Key::Named(Named::Escape) => Message::DataFileClosed,
+            keyboard::Key::Character(PEEK_KEY) => Message::Peeked(true),
+            keyboard::Key::Character("r") => {
+                // A selection of the middle half of the window, for the arrows to take from
+                // there.
+                let size = self.window_size;
+                return vec![
+                    Message::SelectionStarted(Point::new(size.width / 4.0, size.height / 4.0)),
+                    Message::SelectionResized(Point::new(
+                        size.width * 3.0 / 4.0,
+                        size.height * 3.0 / 4.0,
+                    )),
+                ];
+            }
+            _ => match actions::for_key(key, *modifiers) {
+                Some(message) => message,
+                None => return Vec::new(),
+            },
+        };
+        vec![message]
+    }
+
+    // The controls of the open panels in Tab order.
+    fn controls(&self) -> Vec<Control> {
+        let mut controls = Vec::new();
+        if self.show_settings {
+            // The custom margins are only shown while they are the safe area.
+            let custom = self.config.safe_area.template.as_deref() == Some(CUSTOM_SAFE_AREA);
+            let stages = self.config.state().coloring.post.len();
+            let hybrid_steps = self.hybrid_steps();
+            for setting in Setting::ALL {
+                if !custom && matches!(setting, Setting::SafeMargin(_)) {
+                    continue;
+                }
+                // The hybrid's steps come before the button adding one, and neither is shown
+                // for the other fractals.
+                if setting == Setting::HybridAdd {
+                    let Some(steps) = &hybrid_steps else {
+                        continue;
+                    };
+                    controls.extend((0..steps.len()).map(Control::HybridStep));
+                }
+                controls.push(Control::Setting(setting));
+                // The pipeline's stages are listed after the last occlusion setting.
+                if setting == Setting::OcclusionStrength {
+                    controls.extend((0..stages).map(Control::PostStage));
+                }
+            }
+        }
+        if self.show_bookmarks {
+            controls.extend(
+                self.bookmarks
+                    .bookmarks
+                    .iter()
+                    .map(|bookmark| Control::Bookmark(bookmark.id)),
+            );
+            controls.extend((0..Preset::builtin().len()).map(Control::Preset));
+        }
+        if self.show_minibrots {
+            controls.push(Control::MinibrotScan);
+            controls.extend((0..self.minibrots.len()).map(Control::Minibrot));
+        }
+        if self.show_queue {
+            controls.extend(
+                self.exports
+                    .jobs
+                    .iter()
+                    .map(|job| Control::ExportJob(job.id)),
+            );
+        }
+        if self.show_journal {
+            controls.push(Control::JournalExport);
+            controls.extend(
+                self.journal
+                    .entries
+                    .iter()
+                    .map(|entry| Control::JournalEntry(entry.id)),
+            );
+        }
+        if self.show_path {
+            controls.push(Control::KeyframeAdd);
+            if self.camera_path.keyframes.len() > 1 {
+                controls.push(Control::PathScrubber);
+            }
+            controls.extend((0..self.camera_path.keyframes.len()).map(Control::Keyframe));
+        }
+        controls
+    }
+
+    // The focused control, if it is still on screen.
+    fn focused_control(&self) -> Option<Control> {
+        self.focused
+            .filter(|control| self.controls().contains(control))
+    }
+
+    // What Enter on a control does: what clicking it does, where that is possible right now.
+    fn activate(&self, control: Control) -> Option<Message> {
+        let message = match control {
+            Control::PostStage(_) => return None,
+            Control::HybridStep(index) => Message::HybridStepCycled(index),
+            Control::Setting(setting) => match setting {
+                Setting::HybridAdd => {
+                    return self
+                        .hybrid_steps()
+                        .is_some_and(|steps| steps.len() < MAX_HYBRID_STEPS)
+                        .then_some(Message::HybridStepAdded)
+                }
+                Setting::Iterations
+                | Setting::Resolution
+                | Setting::Antialias
+                | Setting::FieldBlend
+                | Setting::Gamma
+                | Setting::Density
+                | Setting::PaletteOffset
+                | Setting::Sectors
+                | Setting::HueRotation
+                | Setting::OcclusionRadius
+                | Setting::OcclusionStrength
+                | Setting::CpuCap => return None,
+                Setting::Occlusion => Message::OcclusionToggled,
+                Setting::AddStage(kind) => Message::PostStageAdded(kind),
+                Setting::PostExpression => Message::PostExpressionSubmitted,
+                Setting::Palette => Message::PaletteCycled,
+                Setting::PaletteWrap => Message::PaletteWrapCycled,
+                Setting::Coloring => Message::ColoringModeCycled,
+                Setting::InteriorColor => Message::InteriorColorCycled,
+                Setting::UnresolvedColor => Message::UnresolvedColorCycled,
+                Setting::Regions => Message::RegionsEdited,
+                Setting::ExportLook => Message::LookExported,
+                Setting::ColorManagement => Message::ColorManagementToggled,
+                Setting::CachePrecision => Message::BufferPrecisionCycled,
+                Setting::Prepass => Message::CoarsePrepassToggled,
+                Setting::DoubleDouble => Message::DoubleDoubleToggled,
+                Setting::TileOrder => Message::TileOrderToggled,
+                Setting::EnergySaver => Message::EnergySaverCycled,
+                Setting::Tune => Message::TuneRequested,
+                Setting::ExplorationLog => Message::ExplorationLogToggled,
+                Setting::Prefetch => Message::PrefetchToggled,
+                Setting::OrbitSound => Message::OrbitSoundToggled,
+                Setting::AutoLevels => Message::AutoLevelsApplied,
+                Setting::AutoLevelsAfterRender => Message::AutoLevelsAfterRenderToggled,
+                Setting::Goto => Message::GotoSubmitted,
+                Setting::OpenFile => Message::OpenPathSubmitted,
+                Setting::Decimal => Message::DecimalSeparatorCycled,
+                Setting::Grouping => Message::DigitGroupingToggled,
+                Setting::InvertY => Message::InvertYToggled,
+                Setting::Crosshair => Message::CrosshairToggled,
+                Setting::Thirds => Message::ThirdsToggled,
+                Setting::GuideColor => Message::GuideColorCycled,
+                Setting::GuideOpacity => Message::GuideOpacityCycled,
+                Setting::GuidesInExports => Message::GuidesInExportsToggled,
+                Setting::Annotations => Message::AnnotationsToggled,
+                Setting::AnnotationsInExports => Message::AnnotationsInExportsToggled,
+                Setting::SafeArea => Message::SafeAreaCycled,
+                Setting::ExportProfile => Message::ExportProfileCycled,
+                Setting::SafeMargin(_) | Setting::ExportPadding => return None,
+                Setting::Undo => return self.history.can_undo().then_some(Message::Undo),
+                Setting::Redo => return self.history.can_redo().then_some(Message::Redo),
+            },
+            Control::Bookmark(id) => Message::BookmarkSelected(id, self.modifiers.shift()),
+            Control::Preset(index) => Message::PresetSelected(index, self.modifiers.shift()),
+            Control::MinibrotScan => {
+                return self
+                    .minibrot_scan
+                    .is_none()
+                    .then_some(Message::MinibrotsRequested)
+            }
+            Control::Minibrot(index) => Message::MinibrotSelected(index),
+            Control::ExportJob(id) => Message::ExportPauseToggled(id),
+            Control::JournalExport => {
+                return (self.persist && !self.journal.entries.is_empty())
+                    .then_some(Message::JournalExported)
+            }
+            Control::JournalEntry(id) => Message::JournalSelected(id),
+            Control::KeyframeAdd => Message::KeyframeAdded,
+            Control::PathScrubber => return None,
+            Control::Keyframe(index) => Message::KeyframeSelected(index),
+        };
+        Some(message)
+    }
+
+    // What the left and right arrows do to a control: move a slider by its step, or a queued
+    // export or post-processing stage along its list.
+    fn adjust(&self, control: Control, step: i32) -> Option<Message> {
+        let settings = self.config.active().settings;
+        let coloring = &self.config.state().coloring;
+        let step_f32 = step as f32;
+        let message = match control {
+            Control::Setting(Setting::Iterations) => Message::MaxIterationsChanged(
+                (settings.max_iterations as i64 + 50 * step as i64).clamp(50, 20000) as u32,
+            ),
+            Control::Setting(Setting::Resolution) => Message::ResolutionScaleChanged(
+                (settings.resolution_scale + 0.25 * step_f32).clamp(0.25, 1.0),
+            ),
+            Control::Setting(Setting::CpuCap) => Message::CpuCapChanged(
+                (self.config.cpu_cap as i64 + 10 * step as i64).clamp(duty::MIN_CAP as i64, 100)
+                    as u32,
+            ),
+            Control::Setting(Setting::Antialias) => Message::AntialiasChanged(
+                (settings.antialias as i64 + step as i64).clamp(1, 4) as u32,
+            ),
+            Control::Setting(Setting::FieldBlend) => {
+                Message::FieldBlendChanged((coloring.field_blend + 0.05 * step_f32).clamp(0.0, 1.0))
+            }
+            Control::Setting(Setting::Gamma) => {
+                Message::GammaChanged((coloring.gamma + 0.05 * step_f32).clamp(0.2, 3.0))
+            }
+            Control::Setting(Setting::Density) => Message::DensityChanged(
+                (coloring.density + 0.1 * step_f32).clamp(levels::MIN_DENSITY, levels::MAX_DENSITY),
+            ),
+            Control::Setting(Setting::PaletteOffset) => Message::PaletteOffsetChanged(
+                (coloring.palette_offset + 0.01 * step_f32).clamp(0.0, 1.0),
+            ),
+            Control::Setting(Setting::Sectors) => {
+                Message::SectorsChanged((coloring.sectors as i64 + step as i64).clamp(2, 24) as u32)
+            }
+            // Wraps, as hues do.
+            Control::Setting(Setting::HueRotation) => Message::HueRotationChanged(
+                (coloring.hue_rotation + 0.05 * step_f32).rem_euclid(1.0),
+            ),
+            Control::Setting(Setting::OcclusionRadius) => Message::OcclusionRadiusChanged(
+                (coloring.occlusion_radius as i64 + step as i64).clamp(1, 16) as u32,
+            ),
+            Control::Setting(Setting::OcclusionStrength) => Message::OcclusionStrengthChanged(
+                (coloring.occlusion_strength + 0.05 * step_f32).clamp(0.0, 1.0),
+            ),
+            Control::Setting(Setting::SafeMargin(edge)) => Message::SafeMarginChanged(
+                edge,
+                (self.config.safe_area.custom.get(edge) + 0.01 * step_f32).clamp(0.0, 0.5),
+            ),
+            Control::Setting(Setting::ExportPadding) => Message::ExportPaddingChanged(
+                (self.config.safe_area.padding as i64 + 8 * step as i64).clamp(0, 256) as u32,
+            ),
+            Control::ExportJob(id) => Message::ExportMoved(id, step as isize),
+            Control::PostStage(index) => Message::PostStageMoved(index, step as isize),
+            Control::PathScrubber => Message::PathScrubbed(
+                (self.scrub + step_f32 as f64).clamp(0.0, self.camera_path.duration()),
+            ),
+            _ => return None,
+        };
+        Some(message)
+    }
+
+    // The steps of the hybrid fractal while it is shown, each switching to the next rule when
+    // clicked, and a button adding one.
+    fn hybrid_editor(&self) -> Element<'_, Message> {
+        let Some(steps) = self.hybrid_steps() else {
+            return column![].into();
+        };
+        let mut editor = column![text(format!(
+            "Hybrid steps, repeating ({} to {}):",
+            MIN_HYBRID_STEPS, MAX_HYBRID_STEPS
+        ))]
+        .spacing(4);
+        for (index, rule) in steps.iter().enumerate() {
+            editor = editor.push(
+                self.focus_ring(
+                    Control::HybridStep(index),
+                    row![
+                        button(text(format!("{}. {}", index + 1, rule.name())))
+                            .on_press(Message::HybridStepCycled(index)),
+                        button(text("Remove")).on_press_maybe(
+                            (steps.len() > MIN_HYBRID_STEPS)
+                                .then_some(Message::HybridStepRemoved(index))
+                        ),
+                    ]
+                    .spacing(4),
+                ),
+            );
+        }
+        editor
+            .push(self.focus_ring(
+                Control::Setting(Setting::HybridAdd),
+                button(text("+ step")).on_press_maybe(
+                    (steps.len() < MAX_HYBRID_STEPS).then_some(Message::HybridStepAdded),
+                ),
+            ))
+            .into()
+    }
+
+    // The hybrid fractal's steps, if it is the one shown.
+    fn hybrid_steps(&self) -> Option<Vec<Rule>> {
+        match self.config.fractal {
+            FractalKind::Hybrid(hybrid) => Some(hybrid.steps().to_vec()),
+            _ => None,
+        }
+    }
+
+    // Shows the hybrid with these steps, or says why it cannot be; whether the view needs drawing.
+    fn set_hybrid(&mut self, rules: &[Rule]) -> bool {
+        match Hybrid::new(rules) {
+            Ok(hybrid) => {
+                self.config.set_fractal(FractalKind::Hybrid(hybrid));
+                self.save_config();
+                self.status_message = format!("hybrid: {}", hybrid.describe());
+                true
+            }
+            Err(err) => {
+                self.status_message = err;
+                false
+            }
+        }
+    }
+
+    // The post-processing pipeline, a row per stage, and buttons adding the built-in stages.
+    fn post_stages(&self) -> Element<'_, Message> {
+        let post = &self.config.state().coloring.post;
+        let mut stages = column![text(if post.is_empty() {
+            "Post-processing: none"
+        } else {
+            "Post-processing, in order:"
+        })]
+        .spacing(4);
+        for (index, stage) in post.iter().enumerate() {
+            let mut buttons = row![].spacing(4);
+            if !matches!(stage, Stage::Expression { .. }) {
+                buttons = buttons
+                    .push(button(text("-")).on_press(Message::PostStageAdjusted(index, false)))
+                    .push(button(text("+")).on_press(Message::PostStageAdjusted(index, true)));
+            }
+            buttons = buttons
+                .push(
+                    button(text("Up"))
+                        .on_press_maybe((index > 0).then_some(Message::PostStageMoved(index, -1))),
+                )
+                .push(button(text("Down")).on_press_maybe(
+                    (index + 1 < post.len()).then_some(Message::PostStageMoved(index, 1)),
+                ))
+                .push(button(text("Remove")).on_press(Message::PostStageRemoved(index)));
+            stages = stages.push(
+                self.focus_ring(
+                    Control::PostStage(index),
+                    column![
+                        text(format!(
+                            "{}. {}: {}",
+                            index + 1,
+                            stage.name(),
+                            stage.describe()
+                        )),
+                        buttons,
+                    ]
+                    .spacing(2),
+                ),
+            );
+        }
+        let mut add = row![].spacing(4);
+        for (kind, stage) in Stage::BUILTIN.iter().enumerate() {
+            add = add.push(self.focus_ring(
+                Control::Setting(Setting::AddStage(kind)),
+                button(text(format!("+ {}", stage.name()))).on_press(Message::PostStageAdded(kind)),
+            ));
+        }
+        stages.push(add.wrap()).into()
+    }
+
+    // Sliders for the custom safe-area margins, while they are the safe area.
+    fn safe_margin_sliders(&self) -> Element<'_, Message> {
+        let safe_area = &self.config.safe_area;
+        let mut sliders = column![].spacing(4);
+        if safe_area.template.as_deref() != Some(CUSTOM_SAFE_AREA) {
+            return sliders.into();
+        }
+        for edge in Edge::ALL {
+            let margin = safe_area.custom.get(edge);
+            sliders = sliders.push(text(format!(
+                "Margin {}: {:.0}%",
+                edge.name(),
+                margin * 100.0
+            )));
+            sliders = sliders.push(
+                self.focus_ring(
+                    Control::Setting(Setting::SafeMargin(edge)),
+                    slider(0.0..=0.5, margin, move |margin| {
+                        Message::SafeMarginChanged(edge, margin)
+                    })
+                    .step(0.01)
+                    .on_release(Message::SettingsReleased),
+                ),
+            );
+        }
+        sliders.into()
+    }
+
+    // Draws a ring around a control while it has the keyboard focus.
+    fn focus_ring<'a>(
+        &self,
+        control: Control,
+        content: impl Into<Element<'a, Message>>,
+    ) -> Element<'a, Message> {
+        let focused = self.focused == Some(control);
+        container(content)
+            .padding(2)
+            .style(move |theme: &Theme| container::Style {
+                border: if focused {
+                    Border {
+                        color: theme.palette().text,
+                        width: 2.0,
+                        radius: 4.0.into(),
+                    }
+                } else {
+                    Border::default()
+                },
+                ..container::Style::default()
+            })
+            .into()
+    }
+
+    // Puts a bookmark's or preset's settings over the live ones.
+    fn apply_overrides(&mut self, overrides: &Overrides) {
+        let (max_iterations, coloring) = overrides.merge(
+            self.config.active().settings.max_iterations,
+            &self.config.state().coloring,
+        );
+        self.config.active_mut().settings.max_iterations = max_iterations;
+        self.config.state_mut().coloring = coloring;
+        self.save_config();
+    }
+
+    // Adds the view as a bookmark, relative to the anchor if `relative` is given, with the
+    // annotations in sight.
+    fn add_bookmark(
+        &mut self,
+        relative: Option<RelativeView>,
+    ) -> Option<mpsc::UnboundedReceiver<Message>> {
+        let window = Rectangle::new(Point::ORIGIN, self.window_size);
+        let annotations = self
+            .config
+            .state()
+            .annotations
+            .iter()
+            .filter(|annotation| {
+                annotation.visible(&self.viewport)
+                    && window.contains(self.annotation_point(annotation.anchor))
+            })
+            .cloned()
+            .collect();
+        self.bookmarks.add(
+            self.config.fractal,
+            self.viewport,
+            self.config.active().settings.max_iterations,
+            &self.config.state().coloring,
+        );
+        if let Some(bookmark) = self.bookmarks.bookmarks.last_mut() {
+            bookmark.annotations = annotations;
+            if let Some(relative) = relative {
+                bookmark.name = format!("Relative {}", bookmark.id);
+                bookmark.relative = Some(relative);
+            }
+            self.status_message = format!("added {}", bookmark.name);
+        }
+        self.save_bookmarks();
+        self.request_thumbnails()
+    }
+
+    fn set_anchor(&mut self, anchor: relative::Anchor) {
+        self.status_message = format!(
+            "anchored to the {} at {}, size {:.2e}",
+            anchor.describe(),
+            self.format_point(anchor.re, anchor.im),
+            anchor.size
+        );
+        self.anchor = Some(anchor);
+    }
+
+    fn go_to(&mut self, fractal: FractalKind, viewport: Viewport) {
+        if fractal != self.config.fractal {
+            self.config.state_mut().viewport = Some(self.viewport);
+            self.config.set_fractal(fractal);
+            self.save_config();
+        }
+        self.viewport = viewport;
+    }
+
+    // Generates missing bookmark and preset thumbnails on a background thread, loading cached
+    // ones from disk first and showing sampled previews of the rest until they are rendered.
+    fn request_thumbnails(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        if !self.persist {
+            return None;
+        }
+        let entries = self
+            .bookmarks
+            .bookmarks
+            .iter()
+            .map(|bookmark| {
+                (
+                    bookmark.thumbnail_name(),
+                    bookmark.fractal,
+                    bookmark.viewport,
+                )
+            })
+            .chain(
+                Preset::builtin()
+                    .into_iter()
+                    .map(|preset| (preset.thumbnail_name(), preset.fractal, preset.viewport)),
+            );
+        let mut jobs = Vec::new();
+        for (name, fractal, viewport) in entries {
+            if self.thumbnails.contains_key(&name)
+                || !self.thumbnails_requested.insert(name.clone())
+            {
+                continue;
+            }
+            let coloring = self.config.fractals[&fractal.family()].coloring.clone();
+            jobs.push((name, fractal, viewport, coloring));
+        }
+        if jobs.is_empty() {
+            return None;
+        }
+        let double_double = self.config.experimental_double_double;
+
+        let (tx, rx) = mpsc::unbounded();
+        let pool = self.threadpool.clone();
+        thread::spawn(move || {
+            let send = |name: &str, rgba: Vec<u8>| {
+                coloring::assert_frame(
+                    &rgba,
+                    THUMBNAIL_SIZE.width as usize,
+                    THUMBNAIL_SIZE.height as usize,
+                );
+                let handle = image::Handle::from_rgba(
+                    THUMBNAIL_SIZE.width as u32,
+                    THUMBNAIL_SIZE.height as u32,
+                    rgba,
+                );
+                tx.unbounded_send(Message::ThumbnailReady(String::from(name), handle))
+                    .is_ok()
+            };
+            // Cached thumbnails go first, then a sampled preview of every missing one, so the
+            // panel fills in at once; the full renders replace the previews as they finish.
+            let mut missing = Vec::new();
+            for (name, fractal, viewport, coloring) in jobs {
+                let rgba = match bookmarks::cached_thumbnail(&name) {
+                    Some(rgba) => rgba,
+                    None => {
+                        let preview = bookmarks::render_thumbnail(
+                            &pool,
+                            fractal,
+                            viewport,
+                            &coloring,
+                            double_double,
+                            Some(Sampling::THUMBNAIL),
+                        );
+                        missing.push((name.clone(), fractal, viewport, coloring));
+                        preview
+                    }
+                };
+                if !rgba.is_empty() && !send(&name, rgba) {
+                    return;
+                }
+            }
+            for (name, fractal, viewport, coloring) in missing {
+                let Some(rgba) = bookmarks::load_or_render_thumbnail(
+                    &pool,
+                    &name,
+                    fractal,
+                    viewport,
+                    &coloring,
+                    double_double,
+                ) else {
+                    continue;
+                };
+                if !send(&name, rgba) {
+                    return;
+                }
+            }
+        });
+        Some(rx)
+    }
+
+    // Adds the installed frame's view to the exploration log, when logging and the view is new.
+    fn log_view(&mut self) {
+        let Some(params) = self.buffer.params else {
+            return;
+        };
+        if !self.config.exploration_log
+            || self.data_file.is_some()
+            || !self.journal.is_new_view(params.fractal, &params.viewport)
+        {
+            return;
+        }
+        let entry = JournalEntry::new(
+            params.fractal,
+            params.viewport,
+            params.max_iterations,
+            &self.config.state().coloring.palette,
+        );
+        let thumbnail = self
+            .persist
+            .then(|| journal::thumbnail(&self.buffer, &self.config.state().coloring))
+            .flatten();
+        let appended = self.journal.append(
+            entry,
+            thumbnail
+                .as_ref()
+                .map(|(rgba, width, height)| (&rgba[..], *width, *height)),
+        );
+        match appended {
+            Ok(entry) => {
+                if let Some((rgba, width, height)) = thumbnail {
+                    let handle = image::Handle::from_rgba(width as u32, height as u32, rgba);
+                    self.journal_thumbnails.insert(entry.id, handle);
+                }
+            }
+            Err(err) => println!("failed to log view: {}", err),
+        }
+        let Some(limit) = self.config.exploration_log_limit else {
+            return;
+        };
+        match self.journal.prune(limit) {
+            Ok(dropped) => {
+                for entry in dropped {
+                    self.journal_thumbnails.remove(&entry.id);
+                }
+            }
+            Err(err) => println!("failed to prune the exploration log: {}", err),
+        }
+    }
+
+    fn export_contact_sheet(&mut self) {
+        if !self.persist {
+            return;
+        }
+        let dir = self.config.export_dir();
+        let name = format!("exploration_{}", export::timestamp());
+        let path = export::unique_path(&dir, &name, "html");
+        let html = self.journal.contact_sheet(&format!(
+            "Exploration of {}",
+            export::date_time(export::unix_seconds())
+        ));
+        let result = std::fs::create_dir_all(&dir).and_then(|_| std::fs::write(&path, html));
+        self.status_message = match result {
+            Ok(()) => format!("saved contact sheet {}", path.display()),
+            Err(err) => format!("failed to save contact sheet {}: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
+    }
+
+    // Sums up the run of the queue in the status bar once it is empty.
+    fn report_batch(&mut self) {
+        if !self.exports.jobs.is_empty() || self.batch.is_empty() {
+            return;
+        }
+        self.status_message = std::mem::take(&mut self.batch).report();
+        println!("{}", self.status_message);
+    }
+
+    fn save_exports(&self) {
+        if self.persist {
+            exports::save(&self.exports);
+        }
+    }
+
+    // Starts the next export once nothing holds it back, and pauses the running one while the
+    // view renders, so that navigation stays responsive.
+    fn schedule_exports(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        let busy = self.rendering.as_ref().is_some_and(|job| !job.refining);
+        if let Some((_, cancel)) = &self.export_cancel {
+            cancel.set_paused(self.exports.hold_running(busy));
+        }
+        let id = self.exports.next(busy)?;
+        let job = self.exports.get_mut(id)?;
+        // Profiles are looked up as the export starts, so edits made while it waited count.
+        match job
+            .spec
+            .with_profile(self.config.profiles(job.spec.fractal))
+        {
+            Ok(spec) => job.spec = spec,
+            Err(err) => {
+                println!("export {} failed: {}", id, err);
+                self.exports.remove(id);
+                self.batch.failed += 1;
+                self.report_batch();
+                self.save_exports();
+                return self.schedule_exports();
+            }
+        }
+        if let Some(original) = self
+            .exported
+            .get(&job.spec.settings_hash())
+            .filter(|path| path.exists())
+            .cloned()
+        {
+            let spec = job.spec.clone();
+            self.exports.remove(id);
+            match exports::reuse(&original, &spec) {
+                Ok(path) => {
+                    println!(
+                        "export {} would render the same image as {}; linked {} to it",
+                        id,
+                        original.display(),
+                        path.display()
+                    );
+                    self.batch.deduplicated.push((path, original));
+                }
+                Err(err) => {
+                    println!("export {} failed: {}", id, err);
+                    self.batch.failed += 1;
+                }
+            }
+            self.report_batch();
+            self.save_exports();
+            return self.schedule_exports();
+        }
+        job.running = true;
+        job.progress = 0.0;
+        let spec = job.spec.clone();
+        let cancel = CancelToken::new();
+        self.export_cancel = Some((id, cancel.clone()));
+        self.export_eta = Eta::default();
+
+        let (tx, rx) = mpsc::unbounded();
+        let pool = self.export_pool.clone();
+        let write = self.persist;
+        thread::spawn(move || {
+            let send = |message| {
+                let _ = tx.unbounded_send(message);
+            };
+            let result = exports::run(&pool, &spec, &cancel, write, |progress| {
+                send(Message::ExportProgress(id, progress))
+            });
+            send(Message::ExportFinished(id, result));
+        });
+        Some(rx)
+    }
+
+    // Searches for the view an image shows in the background, rendering with the current
+    // coloring so that the image and the renders look alike.
+    fn locate_image(&mut self, path: &Path) -> Option<mpsc::UnboundedReceiver<Message>> {
+        if self.locating {
+            return None;
+        }
+        self.locating = true;
+        self.paste_offer = None;
+        self.status_message = format!(
+            "locating {}; views magnified up to {:.0}x can be found",
+            path.display(),
+            locate::max_magnification()
+        );
+        println!("{}", self.status_message);
+        let path = path.to_path_buf();
+        let pool = self.threadpool.clone();
+        let fractal = self.config.fractal;
+        let max_iterations = self.config.active().settings.max_iterations;
+        let coloring = self.config.state().coloring.clone();
+        let (tx, rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            let cancel = CancelToken::new();
+            let result = locate::read_png(&path).and_then(|image| {
+                let size = Size::new(image.width as f32, image.height as f32);
+                let render =
+                    locate::render_gray(&pool, fractal, max_iterations, &coloring, &cancel);
+                locate::locate(&image, Viewport::home(fractal, size), render)
+            });
+            let _ = tx.unbounded_send(Message::ImageLocated(result));
+        });
+        Some(rx)
+    }
+
+    // Scans the installed frame for minibrots in the background.
+    fn scan_minibrots(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        if self.minibrot_scan.is_some() {
+            return None;
+        }
+        let generation = self.installed_generation;
+        self.minibrot_scan = Some(generation);
+        self.minibrots.clear();
+        let buffer = Arc::clone(&self.buffer);
+        let (tx, rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            let candidates = minibrot::find(&buffer);
+            let _ = tx.unbounded_send(Message::MinibrotsFound(generation, candidates));
+        });
+        Some(rx)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let profile = self.config.active();
+        Snapshot {
+            fractal: self.config.fractal,
+            viewport: self.viewport,
+            profile: profile.name.clone(),
+            settings: profile.settings,
+            coloring: self.config.state().coloring.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        if snapshot.fractal != self.config.fractal {
+            self.config.state_mut().viewport = Some(self.viewport);
+            self.config.set_fractal(snapshot.fractal);
+        }
+        self.viewport = snapshot.viewport;
+        let state = self.config.state_mut();
+        if state.profile_index(&snapshot.profile).is_some() {
+            state.active_profile = snapshot.profile;
+        }
+        state.active_mut().settings = snapshot.settings;
+        state.coloring = snapshot.coloring;
+    }
+
+    // Whether coloring with `angle` needs a render, because the frame holds no angles or the
+    // other kind.
+    fn lacks_angle(&self, angle: AngleKind) -> bool {
+        angle.tracked() && self.buffer.params.map(|params| params.angle) != Some(angle)
+    }
+
+    // The size and parameters a render of the current state uses.
+    fn frame_params(&self) -> (Size, FrameParams) {
+        let render_size = self.render_size();
+        let max_iterations = self.config.active().settings.max_iterations;
+        let angle = self.config.state().coloring.mode.angle();
+        let precision = self.config.buffer_precision.resolve(
+            render_size.width as usize * render_size.height as usize,
+            1 + angle.tracked() as usize,
+            max_iterations,
+            self.config.cache_budget_mb,
+        );
+        let params = FrameParams {
+            viewport: self.viewport,
+            max_iterations,
+            fractal: self.config.fractal,
+            angle,
+            precision,
+            coarse_prepass: self.config.coarse_prepass,
+            arithmetic: Arithmetic::select(
+                &self.viewport,
+                render_size,
+                self.config.experimental_double_double,
+            ),
+        };
+        (render_size, params)
+    }
+
+    // Supersedes any running render and starts one for the current state on a background thread.
+    fn start_render(&mut self) -> mpsc::UnboundedReceiver<Message> {
+        let (render_size, params) = self.frame_params();
+        let antialias = self.config.active().settings.antialias.max(1) as usize;
+        if !self.texture_capped && render_size != self.export_size() {
+            self.texture_capped = true;
+            self.status_message = format!(
+                "the window is larger than the {}x{} the screen can show; rendering at {}x{} \
+                 and scaling up",
+                self.texture_limit.0,
+                self.texture_limit.1,
+                render_size.width as usize / antialias,
+                render_size.height as usize / antialias
+            );
+            println!("{}", self.status_message);
+        }
+        self.launch_render(render_size, params, antialias)
+    }
+
+    // Like `start_render`, but for a quick and rough frame of the view, to stand in for a render
+    // that was given up on.
+    fn start_draft(&mut self) -> mpsc::UnboundedReceiver<Message> {
+        let (_, params) = self.frame_params();
+        let resolution_scale = self.config.active().settings.resolution_scale * DRAFT_SCALE;
+        let render_size = dpi::screen_render_size(
+            self.window_size,
+            self.scale_factor,
+            resolution_scale,
+            1,
+            self.texture_limit,
+        );
+        let params = FrameParams {
+            max_iterations: params.max_iterations.min(DRAFT_ITERATIONS),
+            arithmetic: Arithmetic::select(
+                &self.viewport,
+                render_size,
+                self.config.experimental_double_double,
+            ),
+            ..params
+        };
+        self.launch_render(render_size, params, 1)
+    }
+
+    fn launch_render(
+        &mut self,
+        render_size: Size,
+        params: FrameParams,
+        antialias: usize,
+    ) -> mpsc::UnboundedReceiver<Message> {
+        self.generation += 1;
+        if let Some(job) = self.rendering.take() {
+            job.cancel.cancel();
+        }
+        if let Some(cancel) = self.prefetching.take() {
+            cancel.cancel();
+        }
+        let generation = self.generation;
+        let precision = params.precision;
+        let cancel = CancelToken::new();
+        self.rendering = Some(RenderJob {
+            generation,
+            antialias,
+            cancel: cancel.clone(),
+            progress: None,
+            eta: Eta::default(),
+            refining: false,
+            started: Instant::now(),
+            prediction: self.predict(render_size, params.max_iterations),
+            waited: Duration::ZERO,
+            stalled: false,
+        });
+
+        let (tx, rx) = mpsc::unbounded();
+        let pool = self.threadpool.clone();
+        let previous = Arc::clone(&self.buffer);
+        let prefetch = self.prefetch.clone();
+        let focus = self.focus.clone();
+        let record_order = self.config.debug_tile_order;
+        let previews = !self.energy_saving();
+        thread::spawn(move || {
+            let start = Instant::now();
+            // The watchdog ticks until this thread is done with the render or it is cancelled.
+            let (_stop, stopped) = std::sync::mpsc::channel::<()>();
+            let (ticks, ticking) = (tx.clone(), cancel.clone());
+            thread::spawn(move || {
+                while stopped.recv_timeout(WATCHDOG_INTERVAL) == Err(RecvTimeoutError::Timeout)
+                    && !ticking.is_cancelled()
+                {
+                    let _ = ticks.unbounded_send(Message::WatchdogTick(generation));
+                }
+            });
+            let send = |event| {
+                // The app has shut down if nobody is listening.
+                let _ = tx.unbounded_send(Message::Render(generation, event));
+            };
+            let mut tiles = Vec::new();
+            let mut valve = PreviewValve::new(start);
+            let whole = [PixelRect {
+                x: 0,
+                y: 0,
+                width: previous.width,
+                height: previous.height,
+            }];
+            let mut sources = vec![(&*previous, &whole[..])];
+            if let Some(prefetch) = &prefetch {
+                sources.push((&prefetch.buffer, &prefetch.known[..]));
+            }
+            let result = render::render_reusing_from(
+                &pool,
+                &sources,
+                render_size,
+                params,
+                &cancel,
+                &focus,
+                |update| {
+                    if record_order {
+                        tiles.push((update.tile.rect, update.tile.order));
+                    }
+                    if !previews {
+                        return;
+                    }
+                    if let Some((permit, _)) = valve.tile_landed(Instant::now()) {
+                        send(RenderEvent::Progress(update.progress));
+                        let frame = Arc::new(update.frame.pooled_copy());
+                        send(RenderEvent::Partial(frame, permit));
+                        if record_order {
+                            send(RenderEvent::TileOrder(tiles.clone()));
+                        }
+                    }
+                },
+            );
+            drop(sources);
+            drop(prefetch);
+            drop(previous);
+            if let Ok(buffer) = &result {
+                println!("duration to calculate {:#?}", start.elapsed());
+                println!(
+                    "cached buffer uses {} KiB as {:?}",
+                    buffer.bytes() / 1024,
+                    precision
+                );
+            }
+            if record_order {
+                send(RenderEvent::TileOrder(tiles));
+            }
+            send(RenderEvent::Finished(result.map(Arc::new)));
+        });
+        rx
+    }
+
+    // How long a render of `size` at `max_iterations` should take at the recent speed.
+    fn predict(&self, size: Size, max_iterations: u32) -> Prediction {
+        watchdog::predict(
+            size.width as usize,
+            size.height as usize,
+            max_iterations,
+            self.throughput.rate(),
+        )
+    }
+
+    // Whether anything runs once input pauses: refinement or the prefetch.
+    fn idle_work(&self) -> bool {
+        self.config.idle_refinement || self.config.prefetch
+    }
+
+    fn idle_delay(&self) -> Duration {
+        Duration::from_millis(self.config.idle_delay_ms)
+    }
+
+    // Wakes the app with an `IdleTick` for the current generation once the input pause could
+    // have reached the idle delay.
+    fn schedule_idle(&self) -> mpsc::UnboundedReceiver<Message> {
+        let (tx, rx) = mpsc::unbounded();
+        let generation = self.generation;
+        let wait = self.idle_delay().saturating_sub(self.last_input.elapsed());
+        thread::spawn(move || {
+            thread::sleep(wait);
+            let _ = tx.unbounded_send(Message::IdleTick(generation));
+        });
+        rx
+    }
+
+    // Starts the next refinement of the installed frame in the background, unless it has
+    // reached the quality cap. The result replaces the frame like any other render.
+    fn start_refinement(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        if self.data_file.is_some() || self.installed_generation != self.generation {
+            return None;
+        }
+        let base_iterations = self.config.active().settings.max_iterations;
+        let step = refine::next_step(&self.buffer, self.buffer_antialias, base_iterations)?;
+        let params = self.buffer.params?;
+        let generation = self.generation;
+        let current_antialias = self.buffer_antialias;
+        let antialias = match step {
+            RefineStep::Iterations(_) => current_antialias,
+            RefineStep::Antialias(antialias) => antialias,
+        };
+        let size = Size::new(
+            (self.buffer.width / current_antialias * antialias) as f32,
+            (self.buffer.height / current_antialias * antialias) as f32,
+        );
+        let precision = self.config.buffer_precision.resolve(
+            size.width as usize * size.height as usize,
+            1 + params.angle.tracked() as usize,
+            params.max_iterations,
+            self.config.cache_budget_mb,
+        );
+        let arithmetic = Arithmetic::select(
+            &params.viewport,
+            size,
+            self.config.experimental_double_double,
+        );
+        let cancel = CancelToken::new();
+        self.rendering = Some(RenderJob {
+            generation,
+            antialias,
+            cancel: cancel.clone(),
+            progress: None,
+            eta: Eta::default(),
+            refining: true,
+            started: Instant::now(),
+            prediction: self.predict(size, params.max_iterations),
+            waited: Duration::ZERO,
+            stalled: false,
+        });
+
+        let (tx, rx) = mpsc::unbounded();
+        let pool = self.threadpool.clone();
+        let previous = Arc::clone(&self.buffer);
+        thread::spawn(move || {
+            let start = Instant::now();
+            let send = |event| {
+                let _ = tx.unbounded_send(Message::Render(generation, event));
+            };
+            let on_progress = |progress| send(RenderEvent::Progress(progress));
+            let result = match step {
+                RefineStep::Iterations(max_iterations) => render::extend_iterations(
+                    &pool,
+                    &previous,
+                    max_iterations,
+                    &cancel,
+                    on_progress,
+                ),
+                RefineStep::Antialias(_) => {
+                    let params = FrameParams {
+                        precision,
+                        arithmetic,
+                        ..params
+                    };
+                    render::threaded_fractal_calc(&pool, size, params, &cancel, on_progress)
+                }
+            };
+            drop(previous);
+            if result.is_ok() {
+                println!("refined {:?} in {:#?}", step, start.elapsed());
+            }
+            send(RenderEvent::Finished(result.map(Arc::new)));
+        });
+        Some(rx)
+    }
+
+    // Computes the next piece of the margin around the view in the background, once nothing is
+    // left to refine. This is the lowest priority work there is: any render cancels it, and it
+    // only continues while input stays paused.
+    fn start_prefetch(&mut self) -> Option<mpsc::UnboundedReceiver<Message>> {
+        if !self.config.prefetch
+            || self.energy_saving()
+            || self.prefetching.is_some()
+            || self.rendering.is_some()
+            || self.data_file.is_some()
+            || self.installed_generation != self.generation
+        {
+            return None;
+        }
+        let (size, params) = self.frame_params();
+        if !self
+            .prefetch
+            .as_ref()
+            .is_some_and(|prefetch| prefetch.surrounds(size, &params))
+        {
+            // Around a view that moved, whatever the old margin and the frame hold is kept.
+            let previous = self.prefetch.take();
+            let whole = [PixelRect {
+                x: 0,
+                y: 0,
+                width: self.buffer.width,
+                height: self.buffer.height,
+            }];
+            let mut sources = vec![(&*self.buffer, &whole[..])];
+            if let Some(previous) = &previous {
+                sources.push((&previous.buffer, &previous.known[..]));
+            }
+            let budget = self.config.prefetch_mb as usize * 1024 * 1024;
+            self.prefetch = Prefetch::new(size, params, &sources, budget).map(Arc::new);
+            if self.prefetch.is_none() {
+                println!(
+                    "no prefetch: the margin would take more than {} MiB",
+                    self.config.prefetch_mb
+                );
+            }
+        }
+        let prefetch = self.prefetch.as_ref()?;
+        let piece = prefetch.next()?;
+        let (bounds, params) = (prefetch.bounds(), prefetch.params());
+        let cancel = CancelToken::new();
+        self.prefetching = Some(cancel.clone());
+        let generation = self.generation;
+        let pool = self.threadpool.clone();
+        let (tx, rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            let mut tiles = Vec::new();
+            let result = render::render_rects(&pool, bounds, params, &[piece], &cancel, |tile| {
+                tiles.push(tile)
+            });
+            let _ = tx.unbounded_send(Message::Prefetched(
+                generation,
+                piece,
+                result.map(|_| tiles),
+            ));
+        });
+        Some(rx)
+    }
 
-use num::complex::Complex;
+    // The size frames of the window are rendered at, capped to what the screen can show.
+    fn render_size(&self) -> Size {
+        let settings = self.config.active().settings;
+        dpi::screen_render_size(
+            self.window_size,
+            self.scale_factor,
+            settings.resolution_scale,
+            settings.antialias.max(1) as usize,
+            self.texture_limit,
+        )
+    }
 
-use std::sync::mpsc::channel;
-use std::time::Instant;
+    // The size an export of the window renders at, which never goes to the screen.
+    fn export_size(&self) -> Size {
+        self.export_size_for(self.config.active().settings)
+    }
 
-use threadpool::ThreadPool;
+    // The size the window's view is rendered at for an export with `settings`.
+    fn export_size_for(&self, settings: RenderSettings) -> Size {
+        dpi::render_size(
+            self.window_size,
+            self.scale_factor,
+            settings.resolution_scale,
+            settings.antialias.max(1) as usize,
+        )
+    }
 
-#[derive(Clone, Debug)]
-struct Pixel {
-    x: usize,
-    y: usize,
-    color: Color,
-}
+    // Sets density, offset and gamma from the escape times of the frame on screen; whether it
+    // had escaped pixels to go by.
+    fn apply_auto_levels(&mut self) -> bool {
+        let Some(levels) = levels::suggest(&Histogram::of(&self.buffer)) else {
+            self.status_message = String::from("auto levels: nothing in this frame escaped");
+            return false;
+        };
+        let coloring = &mut self.config.state_mut().coloring;
+        coloring.density = levels.density;
+        coloring.palette_offset = levels.offset;
+        coloring.gamma = levels.gamma;
+        self.status_message = format!(
+            "auto levels: density {:.2}, offset {:.2}, gamma {:.2}",
+            levels.density, levels.offset, levels.gamma
+        );
+        self.save_config();
+        true
+    }
 
-#[derive(Debug)]
-enum Message {
-    EventOccurred(Event),
-}
+    fn recolor(&mut self) {
+        let start = Instant::now();
+        let buffer = Arc::clone(&self.buffer);
+        self.paint(&buffer, self.buffer_antialias);
+        self.settled_image = Some((self.image.clone(), self.image_size));
+        println!("duration to recolor {:#?}", start.elapsed());
+    }
 
-#[derive(Debug)]
-struct Mandelbrot {
-    current_mouse_location: Point,
-    draw_bounding_box: bool,
-    start_location: Point,
-    end_location: Point,
-    region: Rectangle,
-    window_size: Size,
-    threadpool: ThreadPool,
-    image: image::Handle,
-}
+    // Keeps the image shown so far to peek back at, as a frame of `size` replaces it; recolors of
+    // one frame never take its place.
+    fn keep_previous_image(&mut self, size: (u32, u32)) {
+        self.previous_image = self
+            .settled_image
+            .take()
+            .filter(|(_, shown)| *shown == size);
+    }
 
-impl Default for Mandelbrot {
-    fn default() -> Self {
-        Mandelbrot {
-            current_mouse_location: Point::new(-0.5, 0.0),
-            draw_bounding_box: false,
-            start_location: Point::default(),
-            end_location: Point::default(),
-            region: Rectangle::new(
-                Point { x: -2.0, y: 1.0 },
-                Size {
-                    width: 3.0,
-                    height: 2.0,
-                },
-            ),
-            window_size: Size::new(1200.0, 720.0),
-            threadpool: ThreadPool::new(8),
-            image: image::Handle::from_rgba(0, 0, Vec::new()),
+    // Colors `buffer` into the displayed image without installing it, as previews are.
+    fn paint(&mut self, buffer: &IterationBuffer, factor: usize) {
+        let mut bytes = coloring::recolor_for_display(
+            buffer,
+            &self.config.state().coloring,
+            self.threadpool.max_count(),
+            self.display_profile.as_ref(),
+        );
+        if factor > 1 {
+            let full = bytes;
+            bytes = coloring::downsample(&full, buffer.width, buffer.height, factor);
+            buffers::RGBA.give(full);
+        }
+        coloring::assert_frame(&bytes, buffer.width / factor, buffer.height / factor);
+        if self.config.invert_y {
+            coloring::flip_rows(&mut bytes, buffer.width / factor, buffer.height / factor);
         }
+        self.shown_view = buffer.params.map(|params| ShownView {
+            viewport: params.viewport,
+            fractal: params.fractal,
+            coloring: self.config.state().coloring.clone(),
+        });
+        self.show_frame(bytes, (buffer.width / factor, buffer.height / factor));
     }
-}
 
-impl Mandelbrot {
-    fn view(&self) -> Element<Message> {
-        stack![
-            image(self.image.clone()),
-            container(
-                canvas(RectangleProgram {
-                    region: Rectangle {
-                        x: self.start_location.x,
-                        y: self.start_location.y,
-                        width: self.end_location.x - self.start_location.x,
-                        height: self.end_location.y - self.start_location.y,
-                    },
-                    draw_bounding_box: self.draw_bounding_box
-                })
-                .width(Fill)
-                .height(Fill),
+    // Shows the image on screen moved and scaled to the current view, so that navigating answers
+    // at once while the render's first passes are on their way. Parts the old image did not cover
+    // show a checkerboard. An image of another fractal or coloring says nothing about the new
+    // view and stays as it is.
+    fn show_warp_preview(&mut self) {
+        let Some(shown) = &self.shown_view else {
+            return;
+        };
+        if shown.viewport == self.viewport
+            || shown.fractal != self.config.fractal
+            || shown.coloring != self.config.state().coloring
+            || self.frame.is_empty()
+        {
+            return;
+        }
+        let render_size = self.render_size();
+        let antialias = self.config.active().settings.antialias.max(1) as f32;
+        let size = (
+            (render_size.width / antialias) as usize,
+            (render_size.height / antialias) as usize,
+        );
+        let from = (self.image_size.0 as usize, self.image_size.1 as usize);
+        let mut transform = PixelTransform::between(
+            &shown.viewport,
+            Size::new(from.0 as f32, from.1 as f32),
+            &self.viewport,
+            Size::new(size.0 as f32, size.1 as f32),
+        );
+        if self.config.invert_y {
+            transform = PixelTransform::mirror_rows(size.1 as f64)
+                .then(&transform)
+                .then(&PixelTransform::mirror_rows(from.1 as f64));
+        }
+        // Skipped when none of the old image would show.
+        let corners = [(0, 0), (size.0, 0), (0, size.1), (size.0, size.1)]
+            .map(|(x, y)| transform.apply(x as f64 - 0.5, y as f64 - 0.5));
+        let overlaps = |axis: fn(&(f64, f64)) -> f64, length: usize| {
+            let low = corners.iter().map(axis).fold(f64::INFINITY, f64::min);
+            let high = corners.iter().map(axis).fold(f64::NEG_INFINITY, f64::max);
+            low < length as f64 - 0.5 && high > -0.5
+        };
+        if size.0 == 0 || size.1 == 0 || !overlaps(|c| c.0, from.0) || !overlaps(|c| c.1, from.1) {
+            return;
+        }
+        let bytes = coloring::warp(
+            &self.frame,
+            from,
+            &transform,
+            size,
+            self.threadpool.max_count(),
+        );
+        if let Some(shown) = &mut self.shown_view {
+            shown.viewport = self.viewport;
+        }
+        self.show_frame(bytes, size);
+    }
+
+    // Puts a `size` RGBA frame on screen.
+    fn show_frame(&mut self, bytes: Vec<u8>, size: (usize, usize)) {
+        self.image_size = (size.0 as u32, size.1 as u32);
+        let previous = std::mem::replace(&mut self.frame, Bytes::from(bytes));
+        self.image =
+            image::Handle::from_rgba(self.image_size.0, self.image_size.1, self.frame.clone());
+        // The widget tree may still hold the old image, in which case it is simply dropped.
+        if let Ok(previous) = previous.try_into_mut() {
+            buffers::RGBA.give(previous.into());
+        }
+    }
+
+    fn quick_export(&mut self) {
+        if self.frame.is_empty() {
+            self.status_message = String::from("nothing to export yet");
+            return;
+        }
+        let name = export::expand_template(&self.config.filename_template, &self.template_fields());
+        let path = export::unique_path(&self.config.export_dir(), &name, "png");
+        let (frame, width, height) = self.export_frame();
+        let mut text = Look::of(&self.config.state().coloring).png_text();
+        text.push((LOCATION_PNG_KEYWORD, self.viewport.location()));
+        self.status_message = match export::write_png_with_text(&path, width, height, &frame, &text)
+        {
+            Ok(()) => format!("exported {}", path.display()),
+            Err(err) => format!("export to {} failed: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
+    }
+
+    // Saves the piece beside quick exports: the view, its settings and coloring, the bookmarks
+    // within it and, if asked for and rendered, the frame.
+    fn save_project(&mut self, with_frame: bool) {
+        if !self.persist {
+            return;
+        }
+        let size = self.render_size();
+        let (half_width, half_height) =
+            (self.viewport.width / 2.0, self.viewport.height(size) / 2.0);
+        let bookmarks = self
+            .bookmarks
+            .bookmarks
+            .iter()
+            .filter(|bookmark| {
+                let (re, im) = bookmark.viewport.center_delta(&self.viewport);
+                bookmark.fractal == self.config.fractal
+                    && re.abs() <= half_width
+                    && im.abs() <= half_height
+            })
+            .map(|bookmark| ProjectBookmark {
+                name: bookmark.name.clone(),
+                fractal: bookmark.fractal,
+                viewport: bookmark.viewport,
+            })
+            .collect();
+        let settings = self.config.active().settings;
+        let mut project = Project::new(
+            self.config.fractal,
+            self.viewport,
+            settings,
+            &self.config.state().coloring,
+            bookmarks,
+        );
+        // Only a finished frame of this very view is worth keeping.
+        let frame = self.buffer.params.is_some_and(|params| {
+            params.viewport == self.viewport
+                && params.fractal == self.config.fractal
+                && params.max_iterations == settings.max_iterations
+                && self.rendering.is_none()
+        });
+        let frame = (with_frame && frame).then(|| &*self.buffer);
+        project.frame_antialias = self.buffer_antialias as u32;
+        project.annotations = self.config.state().annotations.clone();
+        let name = export::expand_template(&self.config.filename_template, &self.template_fields());
+        let path = export::unique_path(&self.config.export_dir(), &name, PROJECT_EXTENSION);
+        self.status_message = match project.write(&path, frame) {
+            Ok(()) if with_frame && frame.is_none() => format!(
+                "saved project {} without a frame, as the view is not rendered yet",
+                path.display()
             ),
-        ]
-        .into()
+            Ok(()) => format!("saved project {}", path.display()),
+            Err(err) => format!("project save to {} failed: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
     }
 
-    fn update(&mut self, message: Message) {
-        let mut should_draw = false;
-        match message {
-            Message::EventOccurred(event) => {
-                if let Event::Window(window::Event::Resized(size)) = event {
-                    self.window_size = size;
-                    println!("x: {} y: {}", size.width as usize, size.height as usize);
-                    should_draw = true;
-                }
-                if let Event::Mouse(mouse::Event::CursorMoved { position }) = event {
-                    self.current_mouse_location = position;
-                    self.end_location = position;
-                }
-                if let Event::Mouse(mouse::Event::ButtonPressed(button)) = event {
-                    if button == iced::mouse::Button::Left {
-                        self.start_location = Point {
-                            x: self.current_mouse_location.x,
-                            y: self.current_mouse_location.y,
-                        };
-                        self.draw_bounding_box = true;
-                    }
-                    if button == iced::mouse::Button::Right {
-                        self.draw_bounding_box = false;
-                    }
+    // Opens the piece saved in a project file, adding the bookmarks it keeps that are missing.
+    // Returns whether it has to be rendered, because the file holds no frame.
+    fn open_project(&mut self, path: &Path) -> bool {
+        let opened = Project::read(path)
+            .and_then(|(project, frame)| Ok((project.look.settings()?, project, frame)));
+        let (mut coloring, project, frame) = match opened {
+            Ok(opened) => opened,
+            Err(err) => {
+                self.status_message = format!("cannot open project {}: {}", path.display(), err);
+                println!("{}", self.status_message);
+                return false;
+            }
+        };
+        for saved in &project.bookmarks {
+            let known = self.bookmarks.bookmarks.iter().any(|bookmark| {
+                bookmark.fractal == saved.fractal && bookmark.viewport == saved.viewport
+            });
+            if !known {
+                self.bookmarks.add(
+                    saved.fractal,
+                    saved.viewport,
+                    project.settings.max_iterations,
+                    &coloring,
+                );
+                if let Some(bookmark) = self.bookmarks.bookmarks.last_mut() {
+                    bookmark.name = saved.name.clone();
                 }
-                if let Event::Mouse(mouse::Event::ButtonReleased(button)) = event {
-                    if button == iced::mouse::Button::Left {
-                        if self.draw_bounding_box {
-                            let x_distance_ratio = self.start_location.x / self.window_size.width;
-                            let width_ratio = (self.end_location.x - self.start_location.x)
-                                / self.window_size.width;
-                            let y_distance_ratio = self.start_location.y / self.window_size.height;
-                            let height_ratio = (self.end_location.y - self.start_location.y)
-                                / self.window_size.height;
-
-                            self.region = Rectangle {
-                                x: self.region.x + self.region.width * x_distance_ratio,
-                                y: self.region.y - self.region.height * y_distance_ratio,
-                                width: self.region.width * width_ratio,
-                                height: self.region.height * height_ratio,
-                            };
-                            should_draw = true;
-                            self.draw_bounding_box = false;
-                        }
-                    }
+            }
+        }
+        self.save_bookmarks();
+        self.go_to(project.fractal, project.viewport);
+        self.config.state_mut().viewport = Some(project.viewport);
+        self.config.active_mut().settings = project.settings;
+        coloring.regions = project.regions;
+        self.config.state_mut().coloring = coloring;
+        self.save_config();
+        let rendered = frame.is_some();
+        if let Some(frame) = frame {
+            self.show_data(frame, project.frame_antialias.max(1) as usize, path);
+        }
+        self.status_message = format!("opened project {}", path.display());
+        println!("{}", self.status_message);
+        !rendered
+    }
+
+    // Saves the current coloring as a look file beside quick exports.
+    fn export_look(&mut self) {
+        if !self.persist {
+            return;
+        }
+        let name = format!("look_{}", export::timestamp());
+        let path = export::unique_path(&self.config.export_dir(), &name, "look.json");
+        self.status_message = match Look::of(&self.config.state().coloring).write(&path) {
+            Ok(()) => format!("exported look {}", path.display()),
+            Err(err) => format!("look export to {} failed: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
+    }
+
+    // Replaces the coloring with the look in `path`, leaving the view alone. Returns whether the
+    // frame has to be rendered again for it, because it needs data the frame lacks.
+    fn apply_look(&mut self, path: &Path) -> bool {
+        let settings = match Look::read(path).and_then(|look| look.settings()) {
+            Ok(settings) => settings,
+            Err(err) => {
+                self.status_message = format!("cannot apply look {}: {}", path.display(), err);
+                println!("{}", self.status_message);
+                return false;
+            }
+        };
+        let angle = settings.mode.angle();
+        // Looks leave out regions, which belong to places in the plane; the ones here stay.
+        let regions = self.config.state().coloring.regions.clone();
+        self.config.state_mut().coloring = ColoringSettings {
+            regions,
+            ..settings
+        };
+        self.save_config();
+        self.status_message = format!("applied look {}", path.display());
+        if self.lacks_angle(angle) {
+            return true;
+        }
+        self.recolor();
+        false
+    }
+
+    // The frame as quick export saves it, with its size: sRGB, +imaginary up and free of
+    // overlays, except guides and annotations when asked for. Under color management the frame
+    // on screen is in the display's colors, so the installed frame is colored again without them.
+    fn export_frame(&self) -> (Cow<'_, [u8]>, u32, u32) {
+        let (mut frame, (width, height)) = match self.display_profile {
+            None if self.config.invert_y => {
+                let mut frame = self.frame.to_vec();
+                let (width, height) = self.image_size;
+                coloring::flip_rows(&mut frame, width as usize, height as usize);
+                (Cow::Owned(frame), self.image_size)
+            }
+            None => (Cow::Borrowed(&self.frame[..]), self.image_size),
+            Some(_) => {
+                let (buffer, factor) = (&self.buffer, self.buffer_antialias);
+                let mut bytes = coloring::recolor(
+                    buffer,
+                    &self.config.state().coloring,
+                    self.threadpool.max_count(),
+                );
+                if factor > 1 {
+                    bytes = coloring::downsample(&bytes, buffer.width, buffer.height, factor);
                 }
+                let size = (buffer.width / factor, buffer.height / factor);
+                (Cow::Owned(bytes), (size.0 as u32, size.1 as u32))
             }
+        };
+        let guides = self.config.guides;
+        if guides.in_exports && guides.any() {
+            guides.burn_in(frame.to_mut(), width, height);
+        }
+        let annotations = &self.config.state().annotations;
+        if self.config.annotations_in_exports && !annotations.is_empty() {
+            // The labels keep the size they have on screen relative to the picture.
+            let scale = width as f32 / self.window_size.width;
+            annotation::burn_in(
+                annotations,
+                &self.viewport,
+                frame.to_mut(),
+                width,
+                height,
+                scale,
+            );
         }
+        (frame, width, height)
+    }
 
-        if should_draw {
-            let start = Instant::now();
-            self.image = threaded_fractal_calc(&self.threadpool, self.window_size, self.region);
-            println!("duration to calculate {:#?}", start.elapsed());
+    // Copies the same frame quick export saves.
+    fn copy_image(&mut self) {
+        if self.frame.is_empty() || !self.persist {
+            self.status_message = String::from("nothing to copy yet");
+            return;
         }
+        let (frame, width, height) = self.export_frame();
+        let frame = frame.into_owned();
+        self.status_message = self.clipboard.copy_image(width, height, &frame);
+        println!("{}", self.status_message);
     }
 
-    fn subscription(&self) -> Subscription<Message> {
-        event::listen().map(Message::EventOccurred)
+    // Copies the view with all its digits, since the status bar may show only some.
+    fn copy_location(&mut self) {
+        let location = self.full_location();
+        if self.persist {
+            if let Err(err) = self.clipboard.copy_text(location.clone()) {
+                self.status_message = err;
+                println!("{}", self.status_message);
+                return;
+            }
+        }
+        self.status_message = format!("copied {}", location);
+        println!("{}", self.status_message);
     }
-}
 
-fn threaded_fractal_calc(pool: &ThreadPool, bounds: Size, region: Rectangle) -> image::Handle {
-    let mut overall_result = Vec::with_capacity(bounds.width as usize);
-    for _ in 0..bounds.width as usize {
-        let mut column = Vec::with_capacity(bounds.height as usize);
-        for _ in 0..bounds.height as usize {
-            column.push(Color::TRANSPARENT);
-        }
-        overall_result.push(column);
-    }
-
-    let n_jobs = 32;
-
-    let pixel_job_height = bounds.height / n_jobs as f32;
-    println!("{:#?}", region);
-    let x_res = region.width / bounds.width;
-    let y_res = region.height / bounds.height;
-
-    let (tx, rx) = channel();
-    for i in 0..n_jobs {
-        let tx = tx.clone();
-        let start_row = i * pixel_job_height as usize;
-        let end_row = start_row + pixel_job_height as usize;
-        pool.execute(move || {
-            let mut result: Vec<Pixel> = Vec::new();
-            for x in 0..bounds.width as usize {
-                for y in start_row..end_row {
-                    let i = region.x + x_res * x as f32;
-                    let j = region.y - y_res * y as f32;
-                    let c = Complex::new(i, j);
-                    let mut z = Complex::new(0.0, 0.0);
-                    let mut color = Color::BLACK;
-                    for n in 0..1000 {
-                        z = z * z + c;
-                        if z.norm() >= 2.0 {
-                            color = Color::WHITE;
-                            break;
-                        }
+    // Offers the view in `text` to jump to. Text without coordinates in it only says so in the
+    // status bar, since most of what gets pasted is not meant for this.
+    fn offer_paste(&mut self, text: &str) {
+        match pasted::extract(text) {
+            Ok(found) => {
+                let width = match found.scale {
+                    Some(Scale::Width(width)) => width,
+                    Some(Scale::Magnification(zoom)) => {
+                        Viewport::home(self.config.fractal, self.window_size).width / zoom
                     }
+                    None => self.viewport.width,
+                };
+                self.paste_offer = Some(Viewport::new(found.re, found.im, width));
+                self.status_message = String::new();
+            }
+            Err(err) => {
+                self.paste_offer = None;
+                self.status_message = err;
+            }
+        }
+    }
 
-                    result.push(Pixel { x, y, color });
-                }
+    fn export_data(&mut self) {
+        if self.buffer.values.is_empty() {
+            self.status_message = String::from("nothing to export yet");
+            return;
+        }
+        let name = export::expand_template(&self.config.filename_template, &self.template_fields());
+        let path = export::unique_path(&self.config.export_dir(), &name, rawdata::EXTENSION);
+        self.status_message = match rawdata::write(&path, &self.buffer) {
+            Ok(()) => format!("exported data {}", path.display()),
+            Err(err) => format!("data export to {} failed: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
+    }
+
+    fn export_boundary(&mut self, format: boundary::Format) {
+        if self.buffer.values.is_empty() {
+            self.status_message = String::from("nothing to export yet");
+            return;
+        }
+        let name = export::expand_template(&self.config.filename_template, &self.template_fields());
+        let path = export::unique_path(&self.config.export_dir(), &name, format.extension());
+        let spacing = self.config.boundary_spacing;
+        self.status_message = match boundary::write(&path, &self.buffer, format, spacing) {
+            Ok(points) => format!("exported {} boundary points {}", points, path.display()),
+            Err(err) => format!("boundary export to {} failed: {}", path.display(), err),
+        };
+        println!("{}", self.status_message);
+    }
+
+    // Shows the frame stored in a data file, recolored with the current settings; nothing is
+    // computed until the view is changed.
+    fn open_data(&mut self, path: &Path) {
+        match rawdata::read(path) {
+            Ok(buffer) => self.show_data(buffer, 1, path),
+            Err(err) => {
+                self.status_message = format!("cannot open {}: {}", path.display(), err);
+                println!("{}", self.status_message);
             }
-            tx.send(result)
-                .expect("channel will be there waiting for the result");
-        });
+        }
     }
 
-    for _ in 0..n_jobs {
-        let pixels = rx.recv().unwrap();
-        for pixel in pixels {
-            overall_result[pixel.x][pixel.y] = pixel.color;
+    // Plays the orbit of the point at `position` in the frame, or stops waiting to play one once
+    // the cursor has left.
+    #[cfg(feature = "sound")]
+    fn sonify_hover(&mut self, position: Option<Point>) {
+        if !self.config.orbit_sound {
+            return;
         }
+        let sound = self.sound.get_or_insert_with(audio::Sonifier::start);
+        let Some(position) = position else {
+            sound.quiet();
+            return;
+        };
+        let (re, im) = self.viewport.pixel_to_complex(position, self.window_size);
+        sound.hover(
+            self.config.fractal,
+            num::complex::Complex::new(re, im),
+            self.config.active().settings.max_iterations,
+        );
     }
 
-    let mut bytes: Vec<u8> =
-        Vec::with_capacity(bounds.width as usize * bounds.height as usize * 4 as usize);
-    for j in 0..bounds.height as usize {
-        for i in 0..bounds.width as usize {
-            bytes.push((overall_result[i][j].r * 255.0) as u8);
-            bytes.push((overall_result[i][j].g * 255.0) as u8);
-            bytes.push((overall_result[i][j].b * 255.0) as u8);
-            bytes.push(255);
+    // Installs a frame read from `path`, rendered with `antialias` samples each way per pixel,
+    // as the view it shows.
+    fn show_data(&mut self, buffer: IterationBuffer, antialias: usize, path: &Path) {
+        let params = buffer.params.expect("data files carry render parameters");
+        self.generation += 1;
+        if let Some(job) = self.rendering.take() {
+            job.cancel.cancel();
+        }
+        if let Some(cancel) = self.prefetching.take() {
+            cancel.cancel();
+        }
+        if params.fractal != self.config.fractal {
+            self.config.state_mut().viewport = Some(self.viewport);
+            self.config.set_fractal(params.fractal);
+        }
+        self.viewport = params.viewport;
+        self.keep_previous_image((
+            (buffer.width / antialias) as u32,
+            (buffer.height / antialias) as u32,
+        ));
+        self.buffer = Arc::new(buffer);
+        self.installed_generation = self.generation;
+        self.buffer_antialias = antialias;
+        self.pixel_classes = ClassCounts::of(&self.buffer);
+        self.refinement = 0;
+        self.data_file = Some(path.to_path_buf());
+        self.status_message = String::new();
+        self.recolor();
+    }
+
+    fn template_fields(&self) -> TemplateFields {
+        let digits = coordinate_digits(self.viewport.pixel_size(self.window_size));
+        TemplateFields {
+            fractal: self
+                .buffer
+                .params
+                .map_or(self.config.fractal, |params| params.fractal)
+                .fractal()
+                .name()
+                .to_string(),
+            re: format!("{:.*}", digits, self.viewport.center_re),
+            im: format!("{:.*}", digits, self.viewport.center_im),
+            zoom: format!("{:.3e}", 1.0 / self.viewport.width),
+            iter: self
+                .buffer
+                .params
+                .map_or(self.config.active().settings.max_iterations, |params| {
+                    params.max_iterations
+                })
+                .to_string(),
+            date: export::timestamp(),
         }
     }
 
-    image::Handle::from_rgba(
-        bounds.width as u32,
-        bounds.height as u32,
-        Bytes::from(bytes),
-    )
+    fn subscription(&self) -> Subscription<Message> {
+        let mut events = event::listen().map(Message::EventOccurred);
+        if self.persist {
+            events = Subscription::batch([events, watcher::subscription()]);
+        }
+        #[cfg(feature = "gamepad")]
+        let events = Subscription::batch([events, pad::subscription()]);
+        events
+    }
 }
 
 fn main() -> iced::Result {
-    iced::application("Mandelbrot", Mandelbrot::update, Mandelbrot::view)
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--soak") {
+        let steps = args
+            .get(index + 1)
+            .and_then(|steps| steps.parse().ok())
+            .unwrap_or(soak::DEFAULT_STEPS);
+        soak::run(steps);
+        return Ok(());
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--checksum") {
+        if let Err(err) = checksum::run(&args[index + 1..]) {
+            eprintln!("checksum: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if args.iter().any(|arg| arg == "--tune") {
+        let tuning = match tune::run(&CancelToken::new(), TUNE_BUDGET, |_| {}) {
+            Ok(tuning) => tuning,
+            Err(err) => {
+                eprintln!("tune: {}", err);
+                std::process::exit(1);
+            }
+        };
+        println!(
+            "tune: {} workers, {} px tiles on {}",
+            tuning.threads, tuning.chunk_size, tuning.fingerprint
+        );
+        let mut config = Config::load();
+        config.tuning = Some(tuning);
+        config.save();
+        return Ok(());
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--path") {
+        if let Err(err) = camera_path::run(&args[index + 1..]) {
+            eprintln!("path: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--boundary") {
+        if let Err(err) = boundary_export::run(&args[index + 1..]) {
+            eprintln!("boundary: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    #[cfg(feature = "http")]
+    if let Some(index) = args.iter().position(|arg| arg == "--serve-http") {
+        if let Err(err) = serve_http(&args[index + 1..]) {
+            eprintln!("serve: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    if let Some(index) = args.iter().position(|arg| arg == "--zoom") {
+        if let Err(err) = zoom::run(&args[index + 1..]) {
+            eprintln!("zoom: {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // `--open file`, or `open file` as file associations launch the app.
+    let open = args
+        .iter()
+        .position(|arg| arg == "--open")
+        .or_else(|| (args.get(1).map(String::as_str) == Some("open")).then_some(1))
+        .and_then(|index| args.get(index + 1))
+        .map(PathBuf::from);
+    let result = iced::application(Mandelbrot::title, Mandelbrot::update, Mandelbrot::view)
         .subscription(Mandelbrot::subscription)
-        .run()
+        .run_with(move || {
+            let mut app = Mandelbrot::default();
+            let opened = match open {
+                Some(path) => app.update(Message::FileOpened(path)),
+                None => Task::none(),
+            };
+            let tune =
+                app.config.auto_tune && !app.config.tuning.as_ref().is_some_and(Tuning::is_current);
+            let task = if tune {
+                app.update(Message::TuneRequested)
+            } else {
+                Task::none()
+            };
+            (app, Task::batch([opened, task]))
+        });
+    save_tiles();
+    result
+}
+
+// Saves the most valuable cached tiles to disk, when it keeps any.
+fn save_tiles() {
+    match TILE_CACHE.save_to_disk() {
+        Ok(0) => {}
+        Ok(written) => println!("saved {} cached tiles to disk", written),
+        Err(err) => println!("cannot save cached tiles: {}", err),
+    }
+}
+
+// Serves renders of the current fractal at the address given, or `serve::DEFAULT_ADDRESS`, until
+// the process is stopped.
+#[cfg(feature = "http")]
+fn serve_http(args: &[String]) -> Result<(), String> {
+    let config = Config::load();
+    let engine = serve::Engine {
+        threads: config
+            .tuning
+            .clone()
+            .filter(Tuning::is_current)
+            .unwrap_or_else(Tuning::heuristic)
+            .threads,
+        fractal: config.fractal,
+        max_iterations: config.active().settings.max_iterations,
+        coloring: config.state().coloring.clone(),
+        double_double: config.experimental_double_double,
+    };
+    let address = args.first().map_or(serve::DEFAULT_ADDRESS, String::as_str);
+    let address = serve::start(address, serve::Limits::default(), engine)?;
+    println!("serving renders at http://{}/render", address);
+    loop {
+        thread::park();
+    }
+}
+
+// The outline of a region laid out in the window.
+fn region_path(placed: &Placed) -> canvas::Path {
+    match placed.shape {
+        adjust::Shape::Rectangle => {
+            let bounds = placed.bounds();
+            canvas::Path::rectangle(bounds.position(), bounds.size())
+        }
+        adjust::Shape::Ellipse => canvas::Path::new(|builder| {
+            const SEGMENTS: usize = 64;
+            for segment in 0..=SEGMENTS {
+                let angle = segment as f32 / SEGMENTS as f32 * std::f32::consts::TAU;
+                let point = Point::new(
+                    placed.center.x + placed.half_width * angle.cos(),
+                    placed.center.y + placed.half_height * angle.sin(),
+                );
+                if segment == 0 {
+                    builder.move_to(point);
+                } else {
+                    builder.line_to(point);
+                }
+            }
+        }),
+    }
+}
+
+// The step after `current` among `steps`, wrapping around after the last.
+fn next_step(steps: &[f64], current: f64) -> f64 {
+    steps
+        .iter()
+        .copied()
+        .find(|&step| step > current)
+        .unwrap_or(steps[0])
+}
+
+fn on_off(label: &str, on: bool) -> String {
+    format!("{}: {}", label, if on { "on" } else { "off" })
+}
+
+// Enough decimals to tell neighbouring pixels apart.
+fn coordinate_digits(pixel_size: f64) -> usize {
+    (-pixel_size.log10()).ceil().max(3.0) as usize
+}
+
+// Whether a message can change undoable state, and if so the key its rapid repeats coalesce
+// under. Rendering, resizing and pointer motion are never undo steps.
+fn history_key(message: &Message) -> Option<Option<&'static str>> {
+    match message {
+        Message::Render(..)
+        | Message::WatchdogTick(_)
+        | Message::IdleTick(_)
+        | Message::Prefetched(..)
+        | Message::ScaleFactorChanged(_)
+        | Message::ScaleSettled(_)
+        | Message::MinibrotsFound(..)
+        | Message::BookmarksChanged
+        | Message::ImageLocated(_)
+        | Message::ExportProgress(..)
+        | Message::ExportFinished(..)
+        | Message::TuneProgress(_)
+        | Message::TuneFinished(_)
+        | Message::Undo
+        | Message::Redo => None,
+        Message::EventOccurred(Event::Window(window::Event::Resized(_)))
+        | Message::EventOccurred(Event::Mouse(mouse::Event::CursorMoved { .. })) => None,
+        Message::MaxIterationsChanged(_) => Some(Some("iterations")),
+        Message::ResolutionScaleChanged(_) => Some(Some("resolution")),
+        Message::AntialiasChanged(_) => Some(Some("antialias")),
+        Message::FieldBlendChanged(_) => Some(Some("field blend")),
+        Message::GammaChanged(_) => Some(Some("gamma")),
+        Message::DensityChanged(_) => Some(Some("density")),
+        Message::PaletteOffsetChanged(_) => Some(Some("palette offset")),
+        Message::SectorsChanged(_) => Some(Some("sectors")),
+        Message::HueRotationChanged(_) => Some(Some("hue rotation")),
+        Message::OcclusionRadiusChanged(_) => Some(Some("occlusion radius")),
+        Message::OcclusionStrengthChanged(_) => Some(Some("occlusion strength")),
+        Message::PostStageAdjusted(..) => Some(Some("post stage")),
+        Message::SafeMarginChanged(..) => Some(Some("safe margin")),
+        Message::ExportPaddingChanged(_) => Some(Some("export padding")),
+        Message::ViewPanned(..) => Some(Some("pan")),
+        Message::ViewZoomed(_) => Some(Some("zoom")),
+        Message::PathScrubbed(_) => Some(Some("scrub")),
+        _ => Some(None),
+    }
+}
+
+// Whether a message comes from the user, as opposed to background work or the window system.
+fn is_user_input(message: &Message) -> bool {
+    match message {
+        Message::Render(..)
+        | Message::WatchdogTick(_)
+        | Message::ThumbnailReady(..)
+        | Message::MinibrotsFound(..)
+        | Message::BookmarksChanged
+        | Message::ImageLocated(_)
+        | Message::ExportProgress(..)
+        | Message::ExportFinished(..)
+        | Message::TuneProgress(_)
+        | Message::TuneFinished(_)
+        | Message::IdleTick(_)
+        | Message::Prefetched(..)
+        | Message::ScaleFactorChanged(_)
+        | Message::ScaleSettled(_) => false,
+        Message::EventOccurred(event) => matches!(
+            event,
+            Event::Keyboard(_) | Event::Mouse(_) | Event::Touch(_)
+        ),
+        _ => true,
+    }
 }
 
 struct RectangleProgram {
     region: Rectangle,
     draw_bounding_box: bool,
+    // Outlined and numbered for the tile order overlay.
+    tiles: Vec<(Rectangle, usize)>,
+    guides: Vec<Rectangle>,
+    guide_color: Color,
+    // The bands of the image a device covers, shaded in the guide color.
+    safe_regions: Vec<Rectangle>,
+    annotations: Vec<Marks>,
+    // Drawn highlighted, as its editor is open.
+    editing_annotation: Option<u64>,
+    // Adjustment regions being edited, each with where its corner handle is.
+    regions: Vec<(u64, Placed, Point)>,
+    selected_region: Option<u64>,
+    // Coordinates shown next to the crosshair's center.
+    center_label: Option<(Point, String)>,
+    // Shown in the corner with the tile order overlay.
+    tile_note: Option<String>,
 }
 
 impl canvas::Program<Message> for RectangleProgram {
@@ -233,6 +5896,101 @@ impl canvas::Program<Message> for RectangleProgram {
         _cursor: mouse::Cursor,
     ) -> Vec<canvas::Geometry> {
         let mut frame = canvas::Frame::new(renderer, bounds.size());
+        let shade = self.guide_color.scale_alpha(SAFE_AREA_SHADE);
+        for region in &self.safe_regions {
+            frame.fill_rectangle(region.position(), region.size(), shade);
+        }
+        for guide in &self.guides {
+            frame.fill_rectangle(guide.position(), guide.size(), self.guide_color);
+        }
+        for marks in &self.annotations {
+            for rect in &marks.ink {
+                let halo = annotation::grown(*rect, marks.halo);
+                frame.fill_rectangle(halo.position(), halo.size(), Color::BLACK);
+            }
+            let ink = if Some(marks.id) == self.editing_annotation {
+                Color::from_rgb(1.0, 0.85, 0.2)
+            } else {
+                Color::WHITE
+            };
+            for rect in &marks.ink {
+                frame.fill_rectangle(rect.position(), rect.size(), ink);
+            }
+        }
+        for (id, placed, corner) in &self.regions {
+            let color = if Some(*id) == self.selected_region {
+                Color::from_rgb(1.0, 0.85, 0.2)
+            } else {
+                Color::WHITE
+            };
+            let stroke = |width| {
+                canvas::Stroke::default()
+                    .with_color(color)
+                    .with_width(width)
+            };
+            frame.stroke(&region_path(placed), stroke(1.5));
+            // Inside this the region applies in full.
+            let inner = Placed {
+                half_width: (placed.half_width - placed.feather).max(0.0),
+                half_height: (placed.half_height - placed.feather).max(0.0),
+                ..*placed
+            };
+            frame.stroke(
+                &region_path(&inner),
+                canvas::Stroke::default()
+                    .with_color(color.scale_alpha(0.5))
+                    .with_width(1.0),
+            );
+            for handle in [placed.center, *corner] {
+                let side = adjust::HIT_RADIUS;
+                let origin = Point::new(handle.x - side / 2.0, handle.y - side / 2.0);
+                frame.fill_rectangle(origin, Size::new(side, side), Color::BLACK);
+                frame.stroke(
+                    &canvas::Path::rectangle(origin, Size::new(side, side)),
+                    stroke(1.0),
+                );
+            }
+        }
+        if let Some((center, label)) = &self.center_label {
+            frame.fill_text(canvas::Text {
+                content: label.clone(),
+                position: Point::new(center.x + 6.0, center.y + 6.0),
+                color: Color {
+                    a: 1.0,
+                    ..self.guide_color
+                },
+                size: 11.0.into(),
+                ..canvas::Text::default()
+            });
+        }
+        let tile_color = Color::from_rgba(1.0, 1.0, 0.0, 0.6);
+        if let Some(note) = &self.tile_note {
+            frame.fill_text(canvas::Text {
+                content: note.clone(),
+                position: Point::new(8.0, 8.0),
+                color: Color {
+                    a: 1.0,
+                    ..tile_color
+                },
+                size: 12.0.into(),
+                ..canvas::Text::default()
+            });
+        }
+        for (tile, order) in &self.tiles {
+            frame.stroke(
+                &canvas::Path::rectangle(tile.position(), tile.size()),
+                canvas::Stroke::default()
+                    .with_color(tile_color)
+                    .with_width(1.0),
+            );
+            frame.fill_text(canvas::Text {
+                content: order.to_string(),
+                position: Point::new(tile.x + 3.0, tile.y + 2.0),
+                color: tile_color,
+                size: 12.0.into(),
+                ..canvas::Text::default()
+            });
+        }
         if self.draw_bounding_box {
             frame.stroke(
                 &canvas::Path::rectangle(
@@ -260,3 +6018,494 @@ impl canvas::Program<Message> for RectangleProgram {
 
     type State = ();
 }
+
+#[cfg(test)]
+mod tests {
+    use iced::futures::{executor, StreamExt};
+
+    use serde::Serialize;
+
+    use std::fs;
+    use std::sync::{Mutex, MutexGuard, PoisonError};
+    use std::{env, process};
+
+    use super::*;
+
+    // Stores loaded anywhere leave warnings for the next app started to show, so apps start one
+    // at a time, and the recovery test loads its stores under the same lock.
+    static STARTING: Mutex<()> = Mutex::new(());
+
+    pub(crate) fn starting() -> MutexGuard<'static, ()> {
+        STARTING.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn start(config: Config) -> Mandelbrot {
+        let _starting = starting();
+        Mandelbrot::new(config, false)
+    }
+
+    // Runs the renders `streams` started to the end.
+    fn finish_renders(app: &mut Mandelbrot, streams: Vec<mpsc::UnboundedReceiver<Message>>) {
+        for mut stream in streams {
+            while let Some(message) = executor::block_on(stream.next()) {
+                app.apply(message);
+            }
+        }
+    }
+
+    fn toml(value: &impl Serialize) -> String {
+        toml::to_string_pretty(value).unwrap_or_default()
+    }
+
+    fn scratch(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("mandelbrot-{}-{}", name, process::id()))
+    }
+
+    fn bookmarked() -> Bookmarks {
+        let mut bookmarks = Bookmarks::default();
+        bookmarks.add(
+            FractalKind::Mandelbrot,
+            Viewport::new(-0.75, 0.1, 0.05),
+            1000,
+            &ColoringSettings::default(),
+        );
+        bookmarks
+    }
+
+    // Every loader is fed a truncated and a garbage copy of its file: it must come up with
+    // defaults and move the file into quarantine byte for byte, and the app must start on what
+    // was loaded, naming each file in the status bar.
+    #[test]
+    fn corrupt_stores_are_quarantined_and_startup_goes_on() {
+        type Loader = fn(&Path) -> String;
+        type Corruption = fn(&str) -> Vec<u8>;
+        let config = Config {
+            onboarded: true,
+            ..Config::default()
+        };
+        let mut path = CameraPath::default();
+        path.keyframes.push(Keyframe {
+            viewport: Viewport::new(-0.75, 0.1, 0.05),
+            hold: 1.0,
+            travel: camera::DEFAULT_TRAVEL,
+            easing: Easing::default(),
+        });
+        // Each store's file, a valid file to corrupt, what its defaults serialize to, and its
+        // loader returning what it loaded, serialized the same way.
+        let stores: [(&str, String, String, Loader); 4] = [
+            (
+                "config.toml",
+                toml(&config),
+                toml(&Config::default()),
+                |file| toml(&Config::load_from(file)),
+            ),
+            (
+                "bookmarks.toml",
+                toml(&bookmarked()),
+                toml(&Bookmarks::default()),
+                |file| toml(&Bookmarks::load_from(file)),
+            ),
+            (
+                "exports.toml",
+                toml(&ExportQueue::default()),
+                toml(&ExportQueue::default()),
+                |file| toml(&exports::load_from(file)),
+            ),
+            (
+                "camera_path.toml",
+                toml(&path),
+                toml(&CameraPath::default()),
+                |file| toml(&camera_path::load_from(file)),
+            ),
+        ];
+        // Cut just past the opening of the first string or array, so what is left cannot parse.
+        let truncate = |valid: &str| {
+            let cut = valid.find(['"', '[']).map_or(valid.len() / 2, |at| at + 1);
+            valid.as_bytes()[..cut.min(valid.len())].to_vec()
+        };
+        let garbage = |_: &str| b"\xff\xfe\x00 = = [[not a store".to_vec();
+        let corruptions: [(&str, Corruption); 2] = [("truncated", truncate), ("garbage", garbage)];
+        let root = scratch("recovery");
+        for (corruption, corrupt) in corruptions {
+            let dir = root.join(corruption);
+            fs::create_dir_all(&dir).expect("creates a scratch directory");
+            let written: Vec<Vec<u8>> = stores
+                .iter()
+                .map(|(file, valid, ..)| {
+                    let bytes = corrupt(valid);
+                    fs::write(dir.join(file), &bytes).expect("writes a corrupt store");
+                    bytes
+                })
+                .collect();
+            let _starting = starting();
+            let loaded: Vec<String> = stores
+                .iter()
+                .map(|(file, .., load)| load(&dir.join(file)))
+                .collect();
+            let app = Mandelbrot::new(Config::load_from(&dir.join("missing.toml")), false);
+            let quarantined: Vec<Vec<u8>> = fs::read_dir(dir.join(store::QUARANTINE_DIR))
+                .expect("the quarantine folder is made")
+                .filter_map(|entry| fs::read(entry.ok()?.path()).ok())
+                .collect();
+            for (((file, _, default, _), loaded), bytes) in stores.iter().zip(&loaded).zip(&written)
+            {
+                assert_eq!(
+                    loaded, default,
+                    "a {} {} did not load as defaults",
+                    corruption, file
+                );
+                assert!(
+                    !dir.join(file).exists() && quarantined.contains(bytes),
+                    "a {} {} was not quarantined intact",
+                    corruption,
+                    file
+                );
+                assert!(
+                    app.status_message.contains(file),
+                    "a {} {} goes unmentioned at startup",
+                    corruption,
+                    file
+                );
+            }
+        }
+        fs::remove_dir_all(&root).expect("removes the scratch directory");
+    }
+
+    // Saves keep the previous contents as a backup and leave no temporary file behind.
+    #[test]
+    fn saves_keep_a_backup() {
+        let dir = scratch("saves");
+        let file = dir.join("bookmarks.toml");
+        let bookmarks = bookmarked();
+        Bookmarks::default().save_to(&file);
+        bookmarks.clone().save_to(&file);
+        let read = |file: &Path| fs::read_to_string(file).unwrap_or_default();
+        assert_eq!(read(&file), toml(&bookmarks));
+        assert_eq!(
+            read(&store::backup_path(&file)),
+            toml(&Bookmarks::default())
+        );
+        // Bookmark saves also leave the lock file they are made under.
+        let kept = fs::read_dir(&dir)
+            .expect("reads the scratch directory")
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.path().extension().is_none_or(|ext| ext != "lock"))
+            })
+            .count();
+        assert_eq!(kept, 2, "a save leaves more than the store and its backup");
+        fs::remove_dir_all(&dir).expect("removes the scratch directory");
+    }
+
+    // A file in the way of the quarantine folder leaves the unreadable store where it is.
+    #[test]
+    fn stores_that_cannot_be_moved_aside_are_not_saved_over() {
+        let dir = scratch("held");
+        let file = dir.join("config.toml");
+        fs::create_dir_all(&dir).expect("creates a scratch directory");
+        fs::write(dir.join(store::QUARANTINE_DIR), "").expect("writes a file in the way");
+        fs::write(&file, "fractal = ").expect("writes a corrupt store");
+        {
+            let _starting = starting();
+            Config::load_from(&file).save_to(&file);
+            store::take_warnings();
+        }
+        assert_eq!(fs::read_to_string(&file).unwrap_or_default(), "fractal = ");
+        fs::remove_dir_all(&dir).expect("removes the scratch directory");
+    }
+
+    // A render in a window that has lost focus to another still paints every partial frame, and
+    // the title counts up while it runs and says nothing once it is done.
+    #[test]
+    fn unfocused_windows_paint_every_partial_frame() {
+        let mut config = Config {
+            energy_saver: EnergySaver::Off,
+            ..Config::default()
+        };
+        config.state_mut().viewport = Some(Viewport::new(-0.745, 0.11, 0.01));
+        // An iteration count no other test uses, so that no tile comes from the cache.
+        config.active_mut().settings.max_iterations = 3_001;
+        let mut app = start(config);
+        app.apply(Message::EventOccurred(Event::Window(
+            window::Event::Unfocused,
+        )));
+        let mut streams = app.apply(Message::EventOccurred(Event::Window(
+            window::Event::Resized(Size::new(640.0, 480.0)),
+        )));
+        let mut stream = streams.pop().expect("resizing the window starts a render");
+        let (mut partials, mut painted, mut titled) = (0, 0, 0);
+        while let Some(message) = executor::block_on(stream.next()) {
+            let partial = matches!(message, Message::Render(_, RenderEvent::Partial(..)));
+            let before = app.frame.clone();
+            app.apply(message);
+            if partial {
+                partials += 1;
+                if app.frame != before {
+                    painted += 1;
+                }
+            }
+            if app.title().contains('%') {
+                titled += 1;
+            }
+        }
+        assert!(
+            partials >= 2 && painted == partials && titled > 0,
+            "an unfocused render painted {} of {} partial frames, titled with progress {} times",
+            painted,
+            partials,
+            titled
+        );
+        assert_eq!(app.title(), "Mandelbrot");
+    }
+
+    // Zooming shows the last frame warped to the new view until the render lands; changing the
+    // fractal or panning far away, where the old frame says nothing about the new view, does not.
+    #[test]
+    fn navigating_shows_the_last_frame_warped() {
+        let mut config = Config {
+            energy_saver: EnergySaver::Off,
+            ..Config::default()
+        };
+        config.state_mut().viewport = Some(Viewport::new(-0.745, 0.11, 0.01));
+        config.active_mut().settings.max_iterations = 3_002;
+        let mut app = start(config);
+        let streams = app.apply(Message::EventOccurred(Event::Window(
+            window::Event::Resized(Size::new(320.0, 240.0)),
+        )));
+        finish_renders(&mut app, streams);
+        let size = (app.image_size.0 as usize, app.image_size.1 as usize);
+        let before = app.frame.clone();
+        let streams = app.apply(Message::ViewZoomed(0.5));
+        let transform = PixelTransform::between(
+            &Viewport::new(-0.745, 0.11, 0.01),
+            Size::new(size.0 as f32, size.1 as f32),
+            &app.viewport,
+            Size::new(size.0 as f32, size.1 as f32),
+        );
+        let expected = coloring::warp(&before, size, &transform, size, 1);
+        assert!(
+            app.frame.as_ref() == expected.as_slice(),
+            "zooming in does not show the last frame warped to the new view"
+        );
+        finish_renders(&mut app, streams);
+        assert!(
+            app.frame.as_ref() != expected.as_slice(),
+            "the render did not replace the warped preview"
+        );
+        for (message, what) in [
+            (Message::FractalCycled, "changing the fractal"),
+            (Message::ViewPanned(5.0, 0.0), "panning five widths"),
+        ] {
+            let before = app.frame.clone();
+            let streams = app.apply(message);
+            assert!(app.frame == before, "{} warps the old frame", what);
+            finish_renders(&mut app, streams);
+        }
+    }
+
+    // Frames too large for a texture are rendered at the cap, the user is told once, and exports
+    // keep the window's full size.
+    #[test]
+    fn frames_are_capped_to_the_texture_limit() {
+        let mut app = start(Config::default());
+        app.texture_limit = (64, 32);
+        for window in [Size::new(640.0, 480.0), Size::new(800.0, 480.0)] {
+            app.status_message.clear();
+            let streams = app.apply(Message::EventOccurred(Event::Window(
+                window::Event::Resized(window),
+            )));
+            finish_renders(&mut app, streams);
+            assert!(
+                app.image_size.0 <= 64 && app.image_size.1 <= 32,
+                "a {:?} window shows a {:?} frame under a 64x32 limit",
+                window,
+                app.image_size
+            );
+        }
+        assert!(
+            !app.status_message.contains("scaling up") && app.texture_capped,
+            "the texture limit notice is not shown exactly once"
+        );
+        assert_eq!(app.export_size(), Size::new(800.0, 480.0));
+    }
+
+    // Cycles to Pastel, which brings a navy interior, picks another interior there, then cycles
+    // through every palette and back to Pastel, where the pick must still win over its own color.
+    #[test]
+    fn palettes_keep_interior_colors_picked_for_them() {
+        let mut app = start(Config::default());
+        let coloring = |app: &Mandelbrot| {
+            let coloring = &app.config.state().coloring;
+            (
+                coloring.palette.clone(),
+                coloring.interior_color,
+                coloring.unresolved_color,
+            )
+        };
+        while coloring(&app).0 != "Pastel" {
+            app.apply(Message::PaletteCycled);
+        }
+        assert_eq!(coloring(&app).1, SolidColor::Navy);
+        app.apply(Message::InteriorColorCycled);
+        let picked = coloring(&app).1;
+        for _ in 0..Palette::builtin().len() {
+            app.apply(Message::PaletteCycled);
+        }
+        assert_eq!(
+            coloring(&app),
+            (String::from("Pastel"), picked, SolidColor::Gray)
+        );
+    }
+
+    // Deep coordinates show the digits that vary, and copy with every one.
+    #[test]
+    fn deep_coordinates_are_shortened_but_copied_whole() {
+        let mut app = start(Config::default());
+        app.viewport = Viewport::new(-0.75, 0.1, 3.0);
+        let (re, im) = app.viewport.center();
+        assert_eq!(
+            app.format_precise_point(re, im),
+            app.format_point(-0.75, 0.1)
+        );
+        app.viewport = Viewport {
+            center_re_lo: 1e-20,
+            ..Viewport::new(-0.75, 0.1, 1e-27)
+        };
+        let (re, im) = app.viewport.center();
+        let deep = app.format_precise_point(re, im);
+        assert!(
+            deep.starts_with("-0.74999999…") && deep.contains(" + 0.10000000…"),
+            "a deep view's center shows as {}",
+            deep
+        );
+        app.apply(Message::LocationCopied);
+        let full = app.full_location();
+        assert!(
+            full.starts_with("-0.74999999999999999999000") && app.status_message.contains(&full),
+            "copying the location gives {:?}, saying {:?}",
+            full,
+            app.status_message
+        );
+    }
+
+    // Anchors at the minibrot under the cursor, adds a bookmark relative to it, then applies that
+    // at a smaller minibrot anchored from a scan result.
+    #[test]
+    fn relative_bookmarks_carry_over_between_minibrots() {
+        let mut app = start(Config::default());
+        app.apply(Message::RelativeBookmarkAdded);
+        assert!(
+            app.bookmarks.bookmarks.is_empty(),
+            "a relative bookmark was added without an anchor"
+        );
+        app.viewport = Viewport::new(-1.7549, 0.0, 0.1);
+        app.current_mouse_location =
+            Point::new(app.window_size.width / 2.0, app.window_size.height / 2.0);
+        app.apply(Message::AnchorAtCursor);
+        assert_eq!(app.anchor.and_then(|anchor| anchor.period), Some(3));
+        app.viewport = Viewport::new(-1.75, 0.004, 0.02);
+        app.apply(Message::RelativeBookmarkAdded);
+        let bookmark = app
+            .bookmarks
+            .bookmarks
+            .last()
+            .cloned()
+            .expect("a relative bookmark is added");
+        assert!(
+            bookmark.relative.is_some() && bookmark.name.starts_with("Relative"),
+            "the relative bookmark was added as {:?}",
+            bookmark.name
+        );
+        let smaller = minibrot::locate(-1.9408, 0.0, 1000, 1e-9).expect("a period 4 minibrot");
+        app.minibrots = vec![smaller];
+        app.apply(Message::MinibrotAnchored(0));
+        let streams = app.apply(Message::RelativeBookmarkApplied(bookmark.id));
+        let expected = bookmark
+            .relative
+            .map(|relative| relative.apply(&relative::Anchor::of(&smaller)));
+        assert_eq!(Some(app.viewport), expected);
+        finish_renders(&mut app, streams);
+    }
+
+    // Adds a region from the app, drags its corner out and steps its exposure, then checks that a
+    // queued export would carry it.
+    #[test]
+    fn regions_are_edited_in_the_window_and_exported() {
+        let mut app = start(Config::default());
+        app.apply(Message::RegionAdded);
+        let region = app.config.state().coloring.regions[0].clone();
+        assert!(
+            app.editing_regions && app.selected_region == Some(region.id),
+            "an added region was not selected for editing"
+        );
+        let placed = region.placed(&app.viewport, app.window_size);
+        app.current_mouse_location = placed.corner();
+        app.apply(Message::RegionGrabbed(region.id, Handle::Corner));
+        app.apply(Message::RegionDragged(
+            placed.center + Vector::new(100.0, 50.0),
+        ));
+        app.apply(Message::RegionReleased);
+        app.apply(Message::RegionAdjusted(Knob::Exposure, true));
+        let pixel_size = app.viewport.pixel_size(app.window_size);
+        let region = app.config.state().coloring.regions[0].clone();
+        let extents = (
+            region.half_width / pixel_size,
+            region.half_height / pixel_size,
+        );
+        assert!(
+            (extents.0 - 100.0).abs() <= 0.01 && (extents.1 - 50.0).abs() <= 0.01,
+            "dragging the corner 100 by 50 pixels from the center gave half extents of {:?}",
+            extents
+        );
+        assert_eq!(region.exposure, 0.25);
+        assert!(
+            app.region_grab.is_none(),
+            "the region stayed grabbed after the release"
+        );
+        let streams = app.apply(Message::ExportQueued);
+        let queued = app
+            .exports
+            .jobs
+            .last()
+            .map(|job| job.spec.coloring.regions.clone());
+        assert_eq!(queued, Some(vec![region]));
+        finish_renders(&mut app, streams);
+    }
+
+    #[test]
+    fn exports_are_queued_with_the_profile_picked_for_them() {
+        let mut app = start(Config::default());
+        let streams = app.apply(Message::EventOccurred(Event::Window(
+            window::Event::Resized(Size::new(64.0, 48.0)),
+        )));
+        finish_renders(&mut app, streams);
+        let mut picked = Vec::new();
+        for _ in 0..4 {
+            app.apply(Message::ExportProfileCycled);
+            picked.push(app.config.export_profile.clone());
+        }
+        let name = |name: &str| Some(String::from(name));
+        assert_eq!(
+            picked,
+            [name("Interactive"), name("Balanced"), name("Final"), None]
+        );
+        app.config.export_profile = name("Final");
+        let streams = app.apply(Message::ExportQueued);
+        let spec = app
+            .exports
+            .jobs
+            .last()
+            .map(|job| job.spec.clone())
+            .expect("an export was queued");
+        assert_eq!(
+            (spec.profile.as_deref(), spec.max_iterations, spec.antialias),
+            (Some("Final"), 5000, 2)
+        );
+        assert_eq!(
+            (spec.width / spec.antialias, spec.height / spec.antialias),
+            (64, 48)
+        );
+        finish_renders(&mut app, streams);
+    }
+}
@@ -0,0 +1,281 @@
+use toml::map::Map;
+use toml::Value;
+
+use std::collections::BTreeSet;
+
+// One entry of a store, as the table it is saved as, told apart from the others by its integer
+// "id" field.
+pub type Record = Map<String, Value>;
+
+// Appended to the name of the other side's copy of a record both sides edited in the same field.
+pub const CONFLICT_SUFFIX: &str = " (from another window)";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Merged {
+    pub records: Vec<Record>,
+    // Records only the other side had, kept after ours.
+    pub added: usize,
+    // Records the other side removed that ours left alone.
+    pub removed: usize,
+    // Records both sides changed in the same field, kept twice.
+    pub conflicts: usize,
+}
+
+impl Merged {
+    // What merging brought in, for the status bar, or None when it brought in nothing.
+    pub fn summary(&self) -> Option<String> {
+        let parts: Vec<String> = [
+            (self.added, "added"),
+            (self.removed, "removed"),
+            (self.conflicts, "edited in both, kept twice"),
+        ]
+        .iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{} {}", count, what))
+        .collect();
+        (!parts.is_empty()).then(|| parts.join(", "))
+    }
+}
+
+// Three-way merge of records: `base` is what both sides last agreed on, `ours` what this side
+// holds and `theirs` what the other side saved since. Each field takes the value of whichever
+// side changed it; a field both sides changed differently keeps ours, and theirs is kept too, as
+// a copy under a new id with `CONFLICT_SUFFIX` on its name. A record one side removed stays
+// removed unless the other changed it. Records both sides added under the same id are both kept,
+// theirs under a new id.
+pub fn merge(base: &[Record], ours: &[Record], theirs: &[Record]) -> Merged {
+    let find = |records: &[Record], id: i64| {
+        records
+            .iter()
+            .find(|record| record_id(record) == Some(id))
+            .cloned()
+    };
+    let mut next_id = base
+        .iter()
+        .chain(ours)
+        .chain(theirs)
+        .filter_map(record_id)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let mut fresh_id = |mut record: Record| {
+        record.insert(String::from("id"), Value::Integer(next_id));
+        next_id += 1;
+        record
+    };
+    let mut merged = Merged::default();
+    let mut copies = Vec::new();
+    for record in ours {
+        let Some(id) = record_id(record) else {
+            merged.records.push(record.clone());
+            continue;
+        };
+        match (find(base, id), find(theirs, id)) {
+            // They removed it; a change of ours since outweighs that.
+            (Some(old), None) => {
+                if *record == old {
+                    merged.removed += 1;
+                } else {
+                    merged.records.push(record.clone());
+                }
+            }
+            (None, None) => merged.records.push(record.clone()),
+            // Both added a record under this id.
+            (None, Some(other)) => {
+                merged.records.push(record.clone());
+                if other != *record {
+                    copies.push(fresh_id(other));
+                    merged.added += 1;
+                }
+            }
+            (Some(old), Some(other)) => {
+                let (fields, conflict) = merge_fields(&old, record, &other);
+                merged.records.push(fields);
+                if conflict {
+                    let mut copy = fresh_id(other);
+                    if let Some(Value::String(name)) = copy.get_mut("name") {
+                        name.push_str(CONFLICT_SUFFIX);
+                    }
+                    copies.push(copy);
+                    merged.conflicts += 1;
+                }
+            }
+        }
+    }
+    for record in theirs {
+        let Some(id) = record_id(record) else {
+            continue;
+        };
+        if find(ours, id).is_some() {
+            continue;
+        }
+        match find(base, id) {
+            // We removed it; a change of theirs since outweighs that.
+            Some(old) if old == *record => {}
+            Some(_) => merged.records.push(record.clone()),
+            None => {
+                merged.records.push(record.clone());
+                merged.added += 1;
+            }
+        }
+    }
+    merged.records.extend(copies);
+    merged
+}
+
+pub fn record_id(record: &Record) -> Option<i64> {
+    record.get("id").and_then(Value::as_integer)
+}
+
+// `ours` with the fields only `theirs` changed since `base` taken from it, and whether a field
+// was changed differently on both sides.
+fn merge_fields(base: &Record, ours: &Record, theirs: &Record) -> (Record, bool) {
+    let mut merged = Record::new();
+    let mut conflict = false;
+    let keys: BTreeSet<&String> = ours
+        .keys()
+        .chain(theirs.keys())
+        .chain(base.keys())
+        .collect();
+    for key in keys {
+        let (old, mine, other) = (base.get(key), ours.get(key), theirs.get(key));
+        let value = if mine == other || other == old {
+            mine
+        } else if mine == old {
+            other
+        } else {
+            conflict = true;
+            mine
+        };
+        if let Some(value) = value {
+            merged.insert(key.clone(), value.clone());
+        }
+    }
+    (merged, conflict)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(id: i64, name: &str, width: f64) -> Record {
+        let mut record = Record::new();
+        record.insert(String::from("id"), Value::Integer(id));
+        record.insert(String::from("name"), Value::String(name.to_string()));
+        record.insert(String::from("width"), Value::Float(width));
+        record
+    }
+
+    fn base() -> Vec<Record> {
+        vec![
+            record(1, "a", 1.0),
+            record(2, "b", 2.0),
+            record(3, "c", 3.0),
+        ]
+    }
+
+    // Each side adds one under the same next id, renames one and removes another.
+    #[test]
+    fn additions_removals_and_edits_of_other_fields_all_survive() {
+        let ours = vec![
+            record(1, "a2", 1.0),
+            record(3, "c", 3.0),
+            record(4, "ours", 4.0),
+        ];
+        let theirs = vec![
+            record(1, "a", 0.5),
+            record(2, "b", 2.0),
+            record(4, "theirs", 5.0),
+        ];
+        let merged = merge(&base(), &ours, &theirs);
+        assert_eq!(
+            merged.records,
+            vec![
+                record(1, "a2", 0.5),
+                record(4, "ours", 4.0),
+                record(5, "theirs", 5.0),
+            ]
+        );
+        assert_eq!((merged.added, merged.removed, merged.conflicts), (1, 1, 0));
+    }
+
+    // Both rename the same bookmark: ours keeps the id, theirs is kept as a marked copy.
+    #[test]
+    fn fields_edited_on_both_sides_are_kept_twice() {
+        let ours = vec![
+            record(1, "mine", 1.0),
+            record(2, "b", 2.0),
+            record(3, "c", 3.0),
+        ];
+        let theirs = vec![
+            record(1, "yours", 1.5),
+            record(2, "b", 2.0),
+            record(3, "c", 3.0),
+        ];
+        let merged = merge(&base(), &ours, &theirs);
+        let copy = format!("yours{}", CONFLICT_SUFFIX);
+        assert_eq!(
+            merged.records,
+            vec![
+                record(1, "mine", 1.5),
+                record(2, "b", 2.0),
+                record(3, "c", 3.0),
+                record(4, &copy, 1.5),
+            ]
+        );
+        assert_eq!(merged.conflicts, 1);
+    }
+
+    // Whichever side did what.
+    #[test]
+    fn an_edit_outweighs_a_removal() {
+        let edited = vec![
+            record(1, "a", 1.0),
+            record(2, "kept", 2.0),
+            record(3, "c", 3.0),
+        ];
+        let removed = vec![record(1, "a", 1.0), record(3, "c", 3.0)];
+        for (ours, theirs) in [(&edited, &removed), (&removed, &edited)] {
+            let merged = merge(&base(), ours, theirs);
+            let kept = merged
+                .records
+                .iter()
+                .any(|record| record.get("name").and_then(Value::as_str) == Some("kept"));
+            assert!(
+                kept && merged.records.len() == 3 && merged.removed == 0,
+                "an edit against a removal merges to {:?}",
+                merged.records
+            );
+        }
+    }
+
+    #[test]
+    fn a_side_without_changes_gives_the_other_back() {
+        let base = base();
+        let changed = vec![
+            record(2, "b", 2.5),
+            record(3, "c", 3.0),
+            record(7, "g", 7.0),
+        ];
+        for (ours, theirs) in [(&changed, &base), (&base, &changed), (&changed, &changed)] {
+            assert_eq!(merge(&base, ours, theirs).records, changed);
+        }
+        assert_eq!(
+            merge(&base, &changed, &base).summary(),
+            None,
+            "merging in an unchanged side reports changes"
+        );
+    }
+
+    // However many records both sides add at once.
+    #[test]
+    fn ids_stay_unique() {
+        let ours: Vec<Record> = (4..14).map(|id| record(id, "ours", 0.0)).collect();
+        let theirs: Vec<Record> = (4..14).map(|id| record(id, "theirs", 0.0)).collect();
+        let merged = merge(&base(), &ours, &theirs);
+        let mut ids: Vec<i64> = merged.records.iter().filter_map(record_id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        assert_eq!((merged.records.len(), ids.len()), (20, 20));
+    }
+}
@@ -0,0 +1,312 @@
+use iced::Size;
+
+use num::complex::Complex;
+
+use crate::fractal::FractalKind;
+use crate::render::{self, IterationBuffer};
+
+// Interior clusters smaller than this are mostly specks on filaments, not worth a visit.
+const MIN_PIXELS: usize = 6;
+// A cluster covering more of the frame than this is the set we are already in, not a copy.
+const MAX_COVERAGE: f64 = 0.5;
+// Filled fraction of the bounding box a minibrot shows at pixel scale: a bare cardioid fills
+// about 0.9 of its box, a cardioid with its bulbs around 0.4.
+const MIN_FILL: f64 = 0.3;
+const MAX_FILL: f64 = 0.95;
+// Bounding boxes more elongated than this are filament debris.
+const MAX_ASPECT: f64 = 2.5;
+// Score factors for clusters that look wrong or are cut off by the frame's edge.
+const MISSHAPEN: f64 = 0.25;
+const CLIPPED: f64 = 0.5;
+pub const MAX_CANDIDATES: usize = 12;
+const NEWTON_STEPS: usize = 48;
+// Periods tried per cluster before settling for the cluster's own position.
+const MAX_PERIOD_TRIES: usize = 16;
+// A view this many times a minibrot's size estimate frames it like the home view frames the set.
+const FRAMING: f64 = 3.0;
+// Candidates have to be at least this much smaller than the view they were found in.
+const MIN_ZOOM: f64 = 1.1;
+
+// A connected patch of interior pixels, in pixel coordinates of the frame it was found in.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cluster {
+    pub pixels: usize,
+    pub min_x: usize,
+    pub max_x: usize,
+    pub min_y: usize,
+    pub max_y: usize,
+    // Mean pixel position.
+    pub x: f64,
+    pub y: f64,
+    pub touches_edge: bool,
+}
+
+impl Cluster {
+    pub fn box_width(&self) -> usize {
+        self.max_x - self.min_x + 1
+    }
+
+    pub fn box_height(&self) -> usize {
+        self.max_y - self.min_y + 1
+    }
+}
+
+// A probable mini copy of the set, with the view that frames it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Candidate {
+    pub re: f64,
+    pub im: f64,
+    // Period of the nucleus Newton's method converged to, when it did.
+    pub period: Option<u32>,
+    // Estimated size in the plane, and the view width that frames it.
+    pub size: f64,
+    pub width: f64,
+    pub score: f64,
+}
+
+// The 4-connected clusters of `interior`, a row-major `width` by `height` classification map.
+pub fn clusters(interior: &[bool], width: usize, height: usize) -> Vec<Cluster> {
+    let mut seen = vec![false; interior.len()];
+    let mut clusters = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..interior.len() {
+        if !interior[start] || seen[start] {
+            continue;
+        }
+        seen[start] = true;
+        stack.push(start);
+        let (x, y) = (start % width, start / width);
+        let mut cluster = Cluster {
+            pixels: 0,
+            min_x: x,
+            max_x: x,
+            min_y: y,
+            max_y: y,
+            x: 0.0,
+            y: 0.0,
+            touches_edge: false,
+        };
+        while let Some(index) = stack.pop() {
+            let (x, y) = (index % width, index / width);
+            cluster.pixels += 1;
+            cluster.x += x as f64;
+            cluster.y += y as f64;
+            cluster.min_x = cluster.min_x.min(x);
+            cluster.max_x = cluster.max_x.max(x);
+            cluster.min_y = cluster.min_y.min(y);
+            cluster.max_y = cluster.max_y.max(y);
+            cluster.touches_edge |= x == 0 || y == 0 || x + 1 == width || y + 1 == height;
+            let neighbors = [
+                (x > 0).then(|| index - 1),
+                (x + 1 < width).then(|| index + 1),
+                (y > 0).then(|| index - width),
+                (y + 1 < height).then(|| index + width),
+            ];
+            for neighbor in neighbors.into_iter().flatten() {
+                if interior[neighbor] && !seen[neighbor] {
+                    seen[neighbor] = true;
+                    stack.push(neighbor);
+                }
+            }
+        }
+        cluster.x /= cluster.pixels as f64;
+        cluster.y /= cluster.pixels as f64;
+        clusters.push(cluster);
+    }
+    clusters
+}
+
+// How likely a cluster is to be a minibrot, or `None` if it cannot be one: bigger is better,
+// shapes unlike a cardioid with bulbs and clusters the frame cuts off count for less.
+pub fn score(cluster: &Cluster, frame_pixels: usize) -> Option<f64> {
+    if cluster.pixels < MIN_PIXELS || cluster.pixels as f64 > frame_pixels as f64 * MAX_COVERAGE {
+        return None;
+    }
+    let (width, height) = (cluster.box_width() as f64, cluster.box_height() as f64);
+    let fill = cluster.pixels as f64 / (width * height);
+    let aspect = width.max(height) / width.min(height);
+    let mut score = cluster.pixels as f64;
+    if !(MIN_FILL..=MAX_FILL).contains(&fill) || aspect > MAX_ASPECT {
+        score *= MISSHAPEN;
+    }
+    if cluster.touches_edge {
+        score *= CLIPPED;
+    }
+    Some(score)
+}
+
+// The clusters worth visiting with their scores, best first.
+pub fn rank(clusters: &[Cluster], frame_pixels: usize) -> Vec<(Cluster, f64)> {
+    let mut ranked: Vec<(Cluster, f64)> = clusters
+        .iter()
+        .filter_map(|cluster| Some((*cluster, score(cluster, frame_pixels)?)))
+        .collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranked
+}
+
+// Scans a rendered frame for minibrots. Each ranked cluster is refined to the nucleus Newton's
+// method finds from its centroid, seeded with the period of the atom domain there; for fractals
+// without that machinery, or where it fails, the cluster itself is the estimate.
+pub fn find(buffer: &IterationBuffer) -> Vec<Candidate> {
+    let Some(params) = buffer.params else {
+        return Vec::new();
+    };
+    let (width, height) = (buffer.width, buffer.height);
+    let interior: Vec<bool> = (0..width * height)
+        .map(|index| !render::escaped(buffer.values.get(index)))
+        .collect();
+    let size = Size::new(width as f32, height as f32);
+    let (left, top) = params.viewport.top_left(size);
+    let pixel_size = params.viewport.pixel_size(size);
+    let to_plane = |x: f64, y: f64| (left + (x + 0.5) * pixel_size, top - (y + 0.5) * pixel_size);
+
+    let mut candidates = Vec::new();
+    for (cluster, score) in rank(&clusters(&interior, width, height), width * height) {
+        if candidates.len() == MAX_CANDIDATES {
+            break;
+        }
+        let (re, im) = to_plane(cluster.x, cluster.y);
+        let extent = cluster.box_width().max(cluster.box_height()) as f64 * pixel_size;
+        let mut candidate = Candidate {
+            re,
+            im,
+            period: None,
+            size: extent,
+            width: extent * FRAMING,
+            score,
+        };
+        if params.fractal == FractalKind::Mandelbrot {
+            let (x0, y0) = to_plane(cluster.min_x as f64 - 1.0, cluster.min_y as f64 - 1.0);
+            let (x1, y1) = to_plane(cluster.max_x as f64 + 1.0, cluster.max_y as f64 + 1.0);
+            // A nucleus outside the cluster belongs to some other component.
+            let inside = |c: Complex<f64>| (x0..=x1).contains(&c.re) && (y1..=y0).contains(&c.im);
+            let c = Complex::new(re, im);
+            let found = atom_periods(c, params.max_iterations)
+                .into_iter()
+                .take(MAX_PERIOD_TRIES)
+                .find_map(|period| {
+                    let nucleus = nucleus(c, period, pixel_size)?;
+                    inside(nucleus).then_some((period, nucleus))
+                });
+            if let Some((period, nucleus)) = found {
+                candidate.re = nucleus.re;
+                candidate.im = nucleus.im;
+                candidate.period = Some(period);
+                if let Some(size) = size_estimate(nucleus, period) {
+                    candidate.size = size;
+                    candidate.width = size * FRAMING;
+                }
+            }
+        }
+        // Zooming out or barely in is not what the tool is for; the set the view is already in
+        // ends up here.
+        if candidate.width * MIN_ZOOM > params.viewport.width {
+            continue;
+        }
+        // Two clusters of one minibrot, split by a thin gap, converge to the same nucleus.
+        let duplicate = candidates.iter().any(|other: &Candidate| {
+            other.period.is_some()
+                && other.period == candidate.period
+                && (other.re - candidate.re).hypot(other.im - candidate.im) < pixel_size
+        });
+        if !duplicate {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+// The minibrot of the Mandelbrot set at `re + im i`, among those whose nuclei Newton's method
+// finds from there for the periods of the atom domains its orbit passes: the one nearest in
+// units of its own size, so that a speck beside the point does not win over the minibrot it is
+// on. Framed as scan results are; Newton steps stop once they fall well below `pixel_size`.
+pub fn locate(re: f64, im: f64, max_iterations: u32, pixel_size: f64) -> Option<Candidate> {
+    let c = Complex::new(re, im);
+    let mut found: Vec<(u32, Complex<f64>)> = Vec::new();
+    for period in atom_periods(c, max_iterations)
+        .into_iter()
+        .take(MAX_PERIOD_TRIES)
+    {
+        let Some(nucleus) = nucleus(c, period, pixel_size) else {
+            continue;
+        };
+        // A multiple of a period already found converges to the same nucleus, whose size it
+        // cannot tell.
+        if !found
+            .iter()
+            .any(|(_, other)| (other - nucleus).norm() < pixel_size)
+        {
+            found.push((period, nucleus));
+        }
+    }
+    let (period, nucleus, size) = found
+        .into_iter()
+        .filter_map(|(period, nucleus)| Some((period, nucleus, size_estimate(nucleus, period)?)))
+        .min_by(|a, b| ((a.1 - c).norm() / a.2).total_cmp(&((b.1 - c).norm() / b.2)))?;
+    Some(Candidate {
+        re: nucleus.re,
+        im: nucleus.im,
+        period: Some(period),
+        size,
+        width: size * FRAMING,
+        score: 0.0,
+    })
+}
+
+// The iterations at which the orbit of `c` comes closer to 0 than ever before, shortest first.
+// Inside a hyperbolic component the component's period is among them, along with its multiples
+// as the orbit settles and the periods of the atom domains passed on the way.
+fn atom_periods(c: Complex<f64>, max_iterations: u32) -> Vec<u32> {
+    let mut z = Complex::new(0.0, 0.0);
+    let mut closest = f64::INFINITY;
+    let mut periods = Vec::new();
+    for iteration in 1..=max_iterations {
+        z = z * z + c;
+        let distance = z.norm_sqr();
+        if distance > 4.0 {
+            break;
+        }
+        if distance < closest {
+            closest = distance;
+            periods.push(iteration);
+        }
+    }
+    periods
+}
+
+// Newton's method on z_period(c) = 0 from `c`, until steps fall well below a pixel.
+fn nucleus(mut c: Complex<f64>, period: u32, pixel_size: f64) -> Option<Complex<f64>> {
+    for _ in 0..NEWTON_STEPS {
+        let mut z = Complex::new(0.0, 0.0);
+        let mut dz = Complex::new(0.0, 0.0);
+        for _ in 0..period {
+            dz = z * dz * 2.0 + 1.0;
+            z = z * z + c;
+        }
+        let step = z / dz;
+        if !step.re.is_finite() || !step.im.is_finite() {
+            return None;
+        }
+        c -= step;
+        if step.norm() < pixel_size * 1e-3 {
+            return Some(c);
+        }
+    }
+    None
+}
+
+// The scale of the minibrot with nucleus `c` relative to the whole set, from the derivatives
+// along its periodic orbit.
+fn size_estimate(c: Complex<f64>, period: u32) -> Option<f64> {
+    let mut z = Complex::new(0.0, 0.0);
+    let mut l = Complex::new(1.0, 0.0);
+    let mut b = Complex::new(1.0, 0.0);
+    for _ in 1..period {
+        z = z * z + c;
+        l = z * l * 2.0;
+        b += l.inv();
+    }
+    let size = (b * l * l).inv().norm();
+    (size.is_finite() && size > 0.0).then_some(size)
+}
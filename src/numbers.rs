@@ -0,0 +1,268 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DecimalSeparator {
+    #[default]
+    Point,
+    Comma,
+}
+
+impl DecimalSeparator {
+    pub fn name(self) -> &'static str {
+        match self {
+            DecimalSeparator::Point => "1.5",
+            DecimalSeparator::Comma => "1,5",
+        }
+    }
+
+    pub fn next(self) -> DecimalSeparator {
+        match self {
+            DecimalSeparator::Point => DecimalSeparator::Comma,
+            DecimalSeparator::Comma => DecimalSeparator::Point,
+        }
+    }
+
+    fn decimal(self) -> char {
+        match self {
+            DecimalSeparator::Point => '.',
+            DecimalSeparator::Comma => ',',
+        }
+    }
+
+    fn grouping(self) -> char {
+        match self {
+            DecimalSeparator::Point => ',',
+            DecimalSeparator::Comma => '.',
+        }
+    }
+}
+
+// How numbers are shown to the user. Anything written to files or exported names always uses
+// the canonical `{:.N}` form instead.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NumberFormat {
+    pub decimal: DecimalSeparator,
+    // Separate thousands in the integer part with the other separator.
+    pub grouping: bool,
+}
+
+pub fn format_number(value: f64, decimals: usize, format: NumberFormat) -> String {
+    let canonical = format!("{:.*}", decimals, value);
+    if !value.is_finite() {
+        return canonical;
+    }
+    localize(&canonical, format)
+}
+
+// A canonical decimal, as `{:.N}` writes it, in the user's number format.
+pub fn localize(canonical: &str, format: NumberFormat) -> String {
+    let (sign, unsigned) = match canonical.strip_prefix('-') {
+        Some(unsigned) => ("-", unsigned),
+        None => ("", canonical),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+    let mut out = String::from(sign);
+    for (index, digit) in integer.chars().enumerate() {
+        if format.grouping && index > 0 && (integer.len() - index) % 3 == 0 {
+            out.push(format.decimal.grouping());
+        }
+        out.push(digit);
+    }
+    if !fraction.is_empty() {
+        out.push(format.decimal.decimal());
+        out.push_str(fraction);
+    }
+    out
+}
+
+// Parses a number written with either `.` or `,` as the decimal separator.
+//
+// - A separator that appears once, with no other kind present, is the decimal separator, so
+//   `-0,743` and `-0.743` are the same number.
+// - When both appear, the last one is the decimal separator and must appear once; the other
+//   groups thousands before it (`1.234,5`, `1,234.5`).
+// - A separator repeated with no other kind present only groups thousands (`1.234.567`).
+// - Grouped digits must come in threes; the exponent (`e-12`) takes no separators.
+pub fn parse_number(input: &str) -> Result<f64, String> {
+    let invalid = |reason: &str| format!("{:?} is not a number: {}", input.trim(), reason);
+    let trimmed = input.trim();
+    let (mantissa, exponent) = match trimmed.find(['e', 'E']) {
+        Some(index) => (&trimmed[..index], Some(&trimmed[index + 1..])),
+        None => (trimmed, None),
+    };
+    let (negative, digits) = match mantissa.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, mantissa.strip_prefix('+').unwrap_or(mantissa)),
+    };
+    if digits.is_empty() || !digits.chars().any(|c| c.is_ascii_digit()) {
+        return Err(invalid("no digits"));
+    }
+    if let Some(c) = digits
+        .chars()
+        .find(|c| !(c.is_ascii_digit() || *c == '.' || *c == ','))
+    {
+        return Err(invalid(&format!("unexpected {:?}", c)));
+    }
+
+    let points = digits.matches('.').count();
+    let commas = digits.matches(',').count();
+    let (decimal, grouping) = match (points, commas) {
+        (0, 0) => (None, None),
+        (1, 0) => (Some('.'), None),
+        (0, 1) => (Some(','), None),
+        (_, 0) => (None, Some('.')),
+        (0, _) => (None, Some(',')),
+        _ => {
+            let last = digits
+                .chars()
+                .rev()
+                .find(|c| *c == '.' || *c == ',')
+                .unwrap_or('.');
+            let (decimal, grouping) = if last == '.' { ('.', ',') } else { (',', '.') };
+            if digits.matches(decimal).count() > 1 {
+                return Err(invalid("more than one decimal separator"));
+            }
+            (Some(decimal), Some(grouping))
+        }
+    };
+    let (integer, fraction) = match decimal {
+        Some(decimal) => digits.split_once(decimal).unwrap_or((digits, "")),
+        None => (digits, ""),
+    };
+    if fraction.contains(['.', ',']) {
+        return Err(invalid("separator after the decimal separator"));
+    }
+    let integer: String = match grouping {
+        Some(grouping) => {
+            let groups: Vec<&str> = integer.split(grouping).collect();
+            let first_ok = (1..=3).contains(&groups[0].len());
+            if !first_ok || groups[1..].iter().any(|group| group.len() != 3) {
+                return Err(invalid("digit groups must have three digits"));
+            }
+            groups.concat()
+        }
+        None => integer.to_string(),
+    };
+
+    let mut canonical = format!(
+        "{}{}.{}",
+        if negative { "-" } else { "" },
+        if integer.is_empty() { "0" } else { &integer },
+        if fraction.is_empty() { "0" } else { fraction }
+    );
+    if let Some(exponent) = exponent {
+        let unsigned = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+        if unsigned.is_empty() || !unsigned.chars().all(|c| c.is_ascii_digit()) {
+            return Err(invalid("malformed exponent"));
+        }
+        canonical = format!("{}e{}", canonical, exponent);
+    }
+    let value: f64 = canonical.parse().map_err(|_| invalid("out of range"))?;
+    if !value.is_finite() {
+        return Err(invalid("out of range"));
+    }
+    Ok(value)
+}
+
+// Parses "re im" or "re im width", separated by whitespace, `;` or a comma followed by a space,
+// so comma decimals stay unambiguous: "-0,743 0,131" and "-0.743, 0.131" both work.
+pub fn parse_location(input: &str) -> Result<(f64, f64, Option<f64>), String> {
+    let fields: Vec<&str> = input
+        .split(';')
+        .flat_map(|part| part.split(", "))
+        .flat_map(str::split_whitespace)
+        .filter(|field| !field.is_empty())
+        .collect();
+    let numbers = fields
+        .iter()
+        .map(|field| parse_number(field))
+        .collect::<Result<Vec<f64>, String>>()?;
+    match numbers[..] {
+        [re, im] => Ok((re, im, None)),
+        [re, im, width] if width > 0.0 => Ok((re, im, Some(width))),
+        [_, _, _] => Err(String::from("width must be positive")),
+        _ => Err(format!(
+            "expected \"re im\" or \"re im width\", found {} numbers",
+            numbers.len()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parses_as(input: &str, expected: f64) {
+        assert_eq!(parse_number(input), Ok(expected), "parsing {:?}", input);
+    }
+
+    fn refused(input: &str) {
+        assert!(
+            parse_number(input).is_err(),
+            "{:?} parses as {:?}",
+            input,
+            parse_number(input)
+        );
+    }
+
+    #[test]
+    fn either_separator_is_a_decimal_on_its_own() {
+        parses_as("-0,743", -0.743);
+        parses_as("-0.743", -0.743);
+        parses_as("1,23", 1.23);
+        parses_as(" +2,5 ", 2.5);
+    }
+
+    #[test]
+    fn exponents_follow_either_decimal() {
+        parses_as("1.0e-12", 1.0e-12);
+        parses_as("1,0e-12", 1.0e-12);
+        parses_as("-3,25E+4", -32500.0);
+    }
+
+    #[test]
+    fn the_last_of_two_separators_is_the_decimal() {
+        parses_as("1.234,5", 1234.5);
+        parses_as("1,234.5", 1234.5);
+        parses_as("1.234.567", 1234567.0);
+        parses_as("-12,345,678.25", -12345678.25);
+    }
+
+    #[test]
+    fn malformed_mixes_are_refused() {
+        // Two decimals after grouping, or a separator in the fraction.
+        refused("1,2.3,4");
+        refused("1.234,5,6");
+        // Groups of other than three digits.
+        refused("1,23.4");
+        refused("1.23.4");
+        refused("1234,567.8");
+        // Separators in or around the exponent.
+        refused("1.2e3,4");
+        refused("1,2e3.5");
+        refused("1e");
+        refused("1e+");
+    }
+
+    #[test]
+    fn non_numbers_are_refused() {
+        refused("");
+        refused("-");
+        refused(",");
+        refused("0x10");
+        refused("1 2");
+        refused("1e999");
+    }
+
+    #[test]
+    fn locations_split_fields_without_breaking_comma_decimals() {
+        assert_eq!(parse_location("-0,743 0,131"), Ok((-0.743, 0.131, None)));
+        assert_eq!(
+            parse_location("-0.743, 0.131; 1e-6"),
+            Ok((-0.743, 0.131, Some(1e-6)))
+        );
+        assert!(parse_location("-0.743 0.131 0").is_err());
+        assert!(parse_location("-0.743").is_err());
+    }
+}
@@ -0,0 +1,139 @@
+// What the user did, as far as the first-run tour cares.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    // Dragged a box and let go, zooming into it.
+    BoxZoom,
+    // Zoomed about the center from the keyboard or a gamepad.
+    Zoom,
+    SettingsToggled,
+}
+
+// The part of the window a step's callout sits by, pointing at what it is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Anchor {
+    Center,
+    Left,
+    Right,
+    Bottom,
+}
+
+// One step of the tour: what to tell the user, and the gesture that shows they got it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Step {
+    pub text: &'static str,
+    pub anchor: Anchor,
+    pub gesture: Gesture,
+}
+
+// The tour in order. A step for a new feature takes only an entry here, and the app observing
+// its gesture wherever it handles it.
+pub const STEPS: [Step; 3] = [
+    Step {
+        text: "Drag a box over the picture to zoom into it",
+        anchor: Anchor::Center,
+        gesture: Gesture::BoxZoom,
+    },
+    Step {
+        text: "Press + or - to zoom in or out about the center",
+        anchor: Anchor::Center,
+        gesture: Gesture::Zoom,
+    },
+    Step {
+        text: "Press S to open the settings",
+        anchor: Anchor::Right,
+        gesture: Gesture::SettingsToggled,
+    },
+];
+
+// How far the user is through `steps`. A step is done once its gesture is made while it is
+// showing; gestures for other steps change nothing, so trying things out of order is harmless.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Tour {
+    steps: &'static [Step],
+    done: usize,
+}
+
+impl Tour {
+    pub fn new(steps: &'static [Step]) -> Tour {
+        Tour { steps, done: 0 }
+    }
+
+    // The step showing, or None once the tour is over.
+    pub fn step(&self) -> Option<&'static Step> {
+        self.steps.get(self.done)
+    }
+
+    // The showing step's number, counting from 1, and how many steps there are.
+    pub fn position(&self) -> (usize, usize) {
+        ((self.done + 1).min(self.steps.len()), self.steps.len())
+    }
+
+    // Records a gesture, returning whether it completed the showing step.
+    pub fn observe(&mut self, gesture: Gesture) -> bool {
+        if self.step().is_some_and(|step| step.gesture == gesture) {
+            self.done += 1;
+            return true;
+        }
+        false
+    }
+
+    pub fn skip(&mut self) {
+        self.done = self.steps.len();
+    }
+
+    pub fn finished(&self) -> bool {
+        self.step().is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use Gesture::{BoxZoom, SettingsToggled, Zoom};
+
+    // How many steps `gestures` complete, checking that the tour finishes with the last one.
+    fn steps_done(gestures: &[Gesture]) -> usize {
+        let mut tour = Tour::new(&STEPS);
+        let completed = gestures
+            .iter()
+            .filter(|&&gesture| tour.observe(gesture))
+            .count();
+        assert_eq!(tour.finished(), completed == STEPS.len());
+        completed
+    }
+
+    #[test]
+    fn steps_done_in_order_finish_the_tour() {
+        assert_eq!(steps_done(&[BoxZoom, Zoom, SettingsToggled]), 3);
+        assert_eq!(steps_done(&[]), 0);
+    }
+
+    // Only the current step's gesture counts, however often another is made.
+    #[test]
+    fn gestures_out_of_order_wait_their_turn() {
+        assert_eq!(
+            steps_done(&[SettingsToggled, Zoom, BoxZoom, SettingsToggled]),
+            1
+        );
+        assert_eq!(steps_done(&[BoxZoom, BoxZoom, Zoom, Zoom]), 2);
+        let wandering = [
+            Zoom,
+            BoxZoom,
+            BoxZoom,
+            SettingsToggled,
+            Zoom,
+            SettingsToggled,
+        ];
+        assert_eq!(steps_done(&wandering), 3);
+    }
+
+    #[test]
+    fn skipped_tours_stop() {
+        let mut tour = Tour::new(&STEPS);
+        tour.observe(BoxZoom);
+        tour.skip();
+        assert!(tour.finished());
+        assert!(tour.step().is_none());
+        assert!(!tour.observe(Zoom), "a skipped tour goes on");
+    }
+}
@@ -0,0 +1,100 @@
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mandelbrot::gamepad::{PadAction, PadAxis, PadButton, PadInput, PadState};
+
+use crate::Message;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(16);
+// How often held sticks move the view; every move is a render.
+const MOTION_INTERVAL: Duration = Duration::from_millis(50);
+
+// Gamepad input as the same messages keys and the mouse send. Controllers may come and go
+// while it runs.
+pub fn subscription() -> Subscription<Message> {
+    Subscription::run(events)
+}
+
+fn events() -> mpsc::UnboundedReceiver<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || poll(tx));
+    rx
+}
+
+fn poll(tx: mpsc::UnboundedSender<Message>) {
+    let mut gilrs = match Gilrs::new() {
+        Ok(gilrs) => gilrs,
+        Err(err) => {
+            println!("gamepad input unavailable: {}", err);
+            return;
+        }
+    };
+    let mut state = PadState::default();
+    let mut last_motion = Instant::now();
+    while !tx.is_closed() {
+        while let Some(event) = gilrs.next_event() {
+            let input = match event.event {
+                EventType::AxisChanged(Axis::LeftStickX, value, _) => {
+                    PadInput::Axis(PadAxis::PanX, value)
+                }
+                EventType::AxisChanged(Axis::LeftStickY, value, _) => {
+                    PadInput::Axis(PadAxis::PanY, value)
+                }
+                EventType::AxisChanged(Axis::RightStickY, value, _) => {
+                    PadInput::Axis(PadAxis::Zoom, value)
+                }
+                EventType::ButtonChanged(Button::RightTrigger2, value, _) => {
+                    PadInput::Axis(PadAxis::ZoomIn, value)
+                }
+                EventType::ButtonChanged(Button::LeftTrigger2, value, _) => {
+                    PadInput::Axis(PadAxis::ZoomOut, value)
+                }
+                EventType::ButtonPressed(Button::RightTrigger, _) => {
+                    PadInput::Pressed(PadButton::NextPalette)
+                }
+                EventType::ButtonPressed(Button::LeftTrigger, _) => {
+                    PadInput::Pressed(PadButton::PreviousPalette)
+                }
+                EventType::ButtonPressed(Button::Start | Button::North, _) => {
+                    PadInput::Pressed(PadButton::Reset)
+                }
+                EventType::Connected => {
+                    println!("gamepad connected: {}", gilrs.gamepad(event.id).name());
+                    continue;
+                }
+                EventType::Disconnected => {
+                    println!("gamepad disconnected");
+                    PadInput::Disconnected
+                }
+                _ => continue,
+            };
+            if let Some(action) = state.apply(input) {
+                send(&tx, action);
+            }
+        }
+        let elapsed = last_motion.elapsed();
+        if elapsed >= MOTION_INTERVAL {
+            last_motion = Instant::now();
+            for action in state.motion(elapsed) {
+                send(&tx, action);
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn send(tx: &mpsc::UnboundedSender<Message>, action: PadAction) {
+    let message = match action {
+        PadAction::Pan(right, up) => Message::ViewPanned(right, up),
+        PadAction::Zoom(factor) => Message::ViewZoomed(factor),
+        PadAction::NextPalette => Message::PaletteCycled,
+        PadAction::PreviousPalette => Message::PaletteCycledBack,
+        PadAction::Reset => Message::ViewReset,
+    };
+    let _ = tx.unbounded_send(message);
+}
@@ -0,0 +1,243 @@
+use iced::Color;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::SolidColor;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Palette {
+    pub name: String,
+    pub stops: Vec<Color>,
+    pub wrap: PaletteWrap,
+    // The interior and unresolved colors that suit the palette, taken on when it is chosen
+    // unless the user picked others for it; the defaults when None.
+    pub interior: Option<SolidColor>,
+    pub unresolved: Option<SolidColor>,
+}
+
+// What a palette does past its ends, where cycling and offsets carry lookups.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PaletteWrap {
+    // Starts over, blending the last stop back into the first so there is no seam.
+    #[default]
+    Smooth,
+    // Runs back from the last stop to the first, then forward again.
+    Mirror,
+    // Holds the first stop before it and the last one after it.
+    Clamp,
+}
+
+impl PaletteWrap {
+    pub const ALL: [PaletteWrap; 3] =
+        [PaletteWrap::Smooth, PaletteWrap::Mirror, PaletteWrap::Clamp];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PaletteWrap::Smooth => "Smooth",
+            PaletteWrap::Mirror => "Mirror",
+            PaletteWrap::Clamp => "Clamp",
+        }
+    }
+
+    pub fn next(self) -> PaletteWrap {
+        let index = PaletteWrap::ALL
+            .iter()
+            .position(|wrap| *wrap == self)
+            .unwrap_or(0);
+        PaletteWrap::ALL[(index + 1) % PaletteWrap::ALL.len()]
+    }
+}
+
+impl Palette {
+    pub fn builtin() -> Vec<Palette> {
+        vec![
+            Palette {
+                name: String::from("Monochrome"),
+                stops: vec![Color::WHITE],
+                wrap: PaletteWrap::Smooth,
+                interior: None,
+                unresolved: None,
+            },
+            Palette {
+                name: String::from("Fire"),
+                stops: vec![
+                    Color::from_rgb8(32, 0, 0),
+                    Color::from_rgb8(200, 40, 0),
+                    Color::from_rgb8(255, 180, 0),
+                    Color::from_rgb8(255, 255, 200),
+                ],
+                wrap: PaletteWrap::Smooth,
+                // Dark gray passes for the palette's dark reds; navy stands out from them.
+                interior: None,
+                unresolved: Some(SolidColor::Navy),
+            },
+            Palette {
+                name: String::from("Ocean"),
+                stops: vec![
+                    Color::from_rgb8(0, 7, 100),
+                    Color::from_rgb8(32, 107, 203),
+                    Color::from_rgb8(237, 255, 255),
+                    Color::from_rgb8(255, 170, 0),
+                ],
+                wrap: PaletteWrap::Smooth,
+                interior: None,
+                unresolved: Some(SolidColor::Crimson),
+            },
+            Palette {
+                name: String::from("Pastel"),
+                stops: vec![
+                    Color::from_rgb8(255, 209, 220),
+                    Color::from_rgb8(200, 230, 255),
+                    Color::from_rgb8(215, 250, 215),
+                    Color::from_rgb8(255, 245, 200),
+                ],
+                wrap: PaletteWrap::Smooth,
+                // Black cuts a hole in a light palette; deep navy sits with it.
+                interior: Some(SolidColor::Navy),
+                unresolved: Some(SolidColor::Gray),
+            },
+        ]
+    }
+
+    pub fn by_name(name: &str) -> Palette {
+        let mut palettes = Palette::builtin();
+        let index = palettes
+            .iter()
+            .position(|palette| palette.name == name)
+            .unwrap_or(0);
+        palettes.swap_remove(index)
+    }
+
+    pub fn next_name(name: &str) -> String {
+        let palettes = Palette::builtin();
+        let index = palettes
+            .iter()
+            .position(|palette| palette.name == name)
+            .map_or(0, |index| (index + 1) % palettes.len());
+        palettes[index].name.clone()
+    }
+
+    pub fn previous_name(name: &str) -> String {
+        let palettes = Palette::builtin();
+        let index = palettes
+            .iter()
+            .position(|palette| palette.name == name)
+            .map_or(0, |index| (index + palettes.len() - 1) % palettes.len());
+        palettes[index].name.clone()
+    }
+
+    // The palette with `wrap` past its ends.
+    pub fn with_wrap(self, wrap: PaletteWrap) -> Palette {
+        Palette { wrap, ..self }
+    }
+
+    // Lookup with linear interpolation between evenly spaced stops; one pass through the palette
+    // per unit of `t`, wrapped as the palette says.
+    pub fn color_at(&self, t: f32) -> Color {
+        let count = self.stops.len();
+        if count == 1 {
+            return self.stops[0];
+        }
+        let scaled = match self.wrap {
+            PaletteWrap::Smooth => t.rem_euclid(1.0) * count as f32,
+            PaletteWrap::Mirror => {
+                let u = t.rem_euclid(2.0);
+                (if u > 1.0 { 2.0 - u } else { u }) * (count - 1) as f32
+            }
+            PaletteWrap::Clamp => t.clamp(0.0, 1.0) * (count - 1) as f32,
+        };
+        let index = scaled as usize % count;
+        let next = match self.wrap {
+            PaletteWrap::Smooth => (index + 1) % count,
+            PaletteWrap::Mirror | PaletteWrap::Clamp => (index + 1).min(count - 1),
+        };
+        let fraction = scaled.fract();
+        let (a, b) = (self.stops[index], self.stops[next]);
+        Color::from_rgb(
+            a.r + (b.r - a.r) * fraction,
+            a.g + (b.g - a.g) * fraction,
+            a.b + (b.b - a.b) * fraction,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RED: Color = Color::from_rgb(1.0, 0.0, 0.0);
+    const GREEN: Color = Color::from_rgb(0.0, 1.0, 0.0);
+    const BLUE: Color = Color::from_rgb(0.0, 0.0, 1.0);
+    const EPSILON: f32 = 1e-4;
+
+    fn seams(wrap: PaletteWrap) -> Palette {
+        Palette {
+            name: String::from("Seams"),
+            stops: vec![RED, GREEN, BLUE],
+            wrap,
+            interior: None,
+            unresolved: None,
+        }
+    }
+
+    fn expect(wrap: PaletteWrap, t: f32, expected: Color) {
+        let color = seams(wrap).color_at(t);
+        assert!(
+            (color.r - expected.r).abs() < 0.01
+                && (color.g - expected.g).abs() < 0.01
+                && (color.b - expected.b).abs() < 0.01,
+            "{} looks up {:?} at {}, not {:?}",
+            wrap.name(),
+            color,
+            t,
+            expected
+        );
+    }
+
+    #[test]
+    fn smooth_palettes_blend_the_last_stop_into_the_first() {
+        for t in [0.0, 1.0, 2.0, -1.0, 1.0 - EPSILON, 1.0 + EPSILON] {
+            expect(PaletteWrap::Smooth, t, RED);
+        }
+        expect(
+            PaletteWrap::Smooth,
+            5.0 / 6.0,
+            Color::from_rgb(0.5, 0.0, 0.5),
+        );
+    }
+
+    #[test]
+    fn mirrored_palettes_run_back_and_forth() {
+        for t in [0.0, 2.0, -2.0, EPSILON, 2.0 - EPSILON] {
+            expect(PaletteWrap::Mirror, t, RED);
+        }
+        for t in [1.0, 1.0 - EPSILON, 1.0 + EPSILON, -1.0] {
+            expect(PaletteWrap::Mirror, t, BLUE);
+        }
+        expect(PaletteWrap::Mirror, 0.5, GREEN);
+        expect(PaletteWrap::Mirror, 1.5, GREEN);
+    }
+
+    #[test]
+    fn clamped_palettes_hold_their_ends() {
+        for t in [0.0, -EPSILON, -3.0] {
+            expect(PaletteWrap::Clamp, t, RED);
+        }
+        for t in [1.0, 1.0 - EPSILON, 1.0 + EPSILON, 7.5] {
+            expect(PaletteWrap::Clamp, t, BLUE);
+        }
+        expect(PaletteWrap::Clamp, 0.5, GREEN);
+    }
+
+    #[test]
+    fn one_stop_palettes_never_change_color() {
+        for wrap in PaletteWrap::ALL {
+            let single = Palette {
+                stops: vec![GREEN],
+                ..seams(wrap)
+            };
+            for t in [-1.5, 0.0, 0.5, 1.0, 2.5] {
+                assert_eq!(single.color_at(t), GREEN, "{} at {}", wrap.name(), t);
+            }
+        }
+    }
+}
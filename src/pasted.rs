@@ -0,0 +1,317 @@
+// Reads a point, and how far in to look at it, out of text pasted from anywhere: "(-0.7436,
+// 0.1318)", "re=-0.7436 im=0.1318 zoom 1e9", "-0.7436 + 0.1318i" or a location as the app
+// writes it. Anything that does not read as exactly one plausible point is refused rather than
+// guessed at.
+
+// Coordinates further out than this are not plausibly a point of any of the fractals.
+const PLAUSIBLE: f64 = 16.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scale {
+    Width(f64),
+    // Times the fractal's home view is magnified, as "zoom" usually means.
+    Magnification(f64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pasted {
+    pub re: f64,
+    pub im: f64,
+    pub scale: Option<Scale>,
+}
+
+// What a number says it is, from the word before it or an `i` after it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Role {
+    Real,
+    Imaginary,
+    Width,
+    Magnification,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Number {
+    value: f64,
+    role: Option<Role>,
+    // Written without a decimal point or exponent, like the counts and dates around coordinates.
+    integer: bool,
+}
+
+fn role_of(word: &str) -> Option<Role> {
+    match word {
+        "re" | "real" | "x" => Some(Role::Real),
+        "im" | "imag" | "imaginary" | "y" => Some(Role::Imaginary),
+        "width" | "w" => Some(Role::Width),
+        "zoom" | "mag" | "magnification" | "magnify" => Some(Role::Magnification),
+        _ => None,
+    }
+}
+
+// Words that may come between a label and its number without ending the label.
+fn is_filler(word: &str) -> bool {
+    matches!(
+        word,
+        "part" | "axis" | "coordinate" | "value" | "factor" | "level" | "of" | "is" | "at"
+    )
+}
+
+fn is_minus(c: char) -> bool {
+    // ASCII, the minus sign, the non-breaking hyphen and the en dash all turn up in pasted text.
+    matches!(c, '-' | '\u{2212}' | '\u{2011}' | '\u{2013}')
+}
+
+fn is_sign(c: char) -> bool {
+    c == '+' || is_minus(c)
+}
+
+pub fn extract(text: &str) -> Result<Pasted, String> {
+    let numbers = scan(text);
+    let mut re = None;
+    let mut im = None;
+    let mut scale = None;
+    let mut loose = Vec::new();
+    for number in &numbers {
+        let value = number.value;
+        let slot = match number.role {
+            Some(Role::Real) => (&mut re, "real part"),
+            Some(Role::Imaginary) => (&mut im, "imaginary part"),
+            Some(Role::Width) => {
+                set(&mut scale, Scale::Width(value), "zoom")?;
+                continue;
+            }
+            Some(Role::Magnification) => {
+                set(&mut scale, Scale::Magnification(value), "zoom")?;
+                continue;
+            }
+            None => {
+                loose.push(*number);
+                continue;
+            }
+        };
+        set(slot.0, value, slot.1)?;
+    }
+
+    // Unlabelled numbers fill the real part, the imaginary part and then the width, as in a
+    // location. With more of them than that, numbers without a decimal point are taken for
+    // counts or dates and left out.
+    let open = re.is_none() as usize + im.is_none() as usize + scale.is_none() as usize;
+    if loose.len() > open {
+        loose.retain(|number| !number.integer);
+    }
+    let mut loose = loose.into_iter().map(|number| number.value);
+    if re.is_none() {
+        re = loose.next();
+    }
+    if im.is_none() {
+        im = loose.next();
+    }
+    if scale.is_none() {
+        scale = loose.next().map(Scale::Width);
+    }
+    if loose.next().is_some() {
+        return Err(String::from(
+            "pasted text has more numbers than one location",
+        ));
+    }
+
+    let (re, im) = match (re, im) {
+        (Some(re), Some(im)) => (re, im),
+        (None, None) => return Err(String::from("no coordinates in the pasted text")),
+        _ => return Err(String::from("pasted text has only one coordinate")),
+    };
+    if re.abs() > PLAUSIBLE || im.abs() > PLAUSIBLE {
+        return Err(format!("{} {} is not a plausible point", re, im));
+    }
+    match scale {
+        Some(Scale::Width(value) | Scale::Magnification(value)) if value <= 0.0 => {
+            Err(format!("pasted zoom {} is not positive", value))
+        }
+        _ => Ok(Pasted { re, im, scale }),
+    }
+}
+
+fn set<T>(slot: &mut Option<T>, value: T, name: &str) -> Result<(), String> {
+    if slot.is_some() {
+        return Err(format!("pasted text has more than one {}", name));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+// Every number in `text`, with what the words around it say it is.
+fn scan(text: &str) -> Vec<Number> {
+    let chars: Vec<char> = text.chars().collect();
+    // Commas are decimal separators only in text with no points, as in "-0,7436 0,1318".
+    let comma_decimals = !text.contains('.');
+    let mut numbers = Vec::new();
+    let mut label = None;
+    // A sign standing apart, as in "a - bi", applies to the number after it.
+    let mut loose_sign = None;
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        if c.is_alphabetic() {
+            let start = index;
+            while index < chars.len() && chars[index].is_alphabetic() {
+                index += 1;
+            }
+            let word: String = chars[start..index]
+                .iter()
+                .collect::<String>()
+                .to_lowercase();
+            match role_of(&word) {
+                Some(role) => label = Some(role),
+                None if is_filler(&word) => {}
+                None => label = None,
+            }
+            loose_sign = None;
+            continue;
+        }
+        let digit_at = |at: usize| chars.get(at).is_some_and(|c| c.is_ascii_digit());
+        let starts_number =
+            |at: usize| digit_at(at) || (chars.get(at) == Some(&'.') && digit_at(at + 1));
+        if is_sign(c) && !starts_number(index + 1) {
+            loose_sign = Some(c);
+            index += 1;
+            continue;
+        }
+        if !(starts_number(index) || is_sign(c)) {
+            if !c.is_whitespace() {
+                loose_sign = None;
+            }
+            index += 1;
+            continue;
+        }
+
+        let mut literal = String::new();
+        if let Some(sign) = loose_sign.take() {
+            literal.push(if is_minus(sign) { '-' } else { '+' });
+        }
+        if is_sign(c) {
+            literal.push(if is_minus(c) { '-' } else { '+' });
+            index += 1;
+        }
+        let mut integer = true;
+        let mut point = false;
+        while index < chars.len() {
+            let c = chars[index];
+            let separator = c == '.' || (c == ',' && comma_decimals);
+            if c.is_ascii_digit() {
+                literal.push(c);
+            } else if separator && !point && digit_at(index + 1) {
+                literal.push('.');
+                point = true;
+                integer = false;
+            } else {
+                break;
+            }
+            index += 1;
+        }
+        // An exponent only counts with digits after it, so "0.13e" leaves the e to a word.
+        if matches!(chars.get(index), Some('e' | 'E')) {
+            let sign = chars.get(index + 1).copied().filter(|&c| is_sign(c));
+            let digits = index + 1 + sign.is_some() as usize;
+            if digit_at(digits) {
+                literal.push('e');
+                if let Some(sign) = sign {
+                    literal.push(if is_minus(sign) { '-' } else { '+' });
+                }
+                index = digits;
+                while digit_at(index) {
+                    literal.push(chars[index]);
+                    index += 1;
+                }
+                integer = false;
+            }
+        }
+        let Ok(value) = literal.parse::<f64>() else {
+            continue;
+        };
+        if !value.is_finite() {
+            continue;
+        }
+        // "0.1318i", "0.1318 i" and "0.1318*i" mark the imaginary part, but not "0.1318 is".
+        let mut after = index;
+        while chars.get(after).is_some_and(|&c| c == ' ' || c == '*') {
+            after += 1;
+        }
+        let imaginary = matches!(chars.get(after), Some('i' | 'j'))
+            && !chars.get(after + 1).is_some_and(|c| c.is_alphanumeric());
+        let role = if imaginary {
+            index = after + 1;
+            Some(Role::Imaginary)
+        } else {
+            label
+        };
+        numbers.push(Number {
+            value,
+            role,
+            integer,
+        });
+        label = None;
+    }
+    numbers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Text as it turns up in forum posts, papers, URLs and other programs.
+    #[test]
+    fn points_are_read_out_of_text_written_many_ways() {
+        let point = |re, im, scale| Pasted { re, im, scale };
+        let cases = [
+            (
+                "(-0.743643887, 0.131825904)",
+                point(-0.743643887, 0.131825904, None),
+            ),
+            (
+                "re=-0.7436 im=0.1318 zoom 1e9",
+                point(-0.7436, 0.1318, Some(Scale::Magnification(1e9))),
+            ),
+            ("\u{2011}0.7436 + 0.1318i", point(-0.7436, 0.1318, None)),
+            (
+                "\u{2212}0.7436\u{2212}0.1318i",
+                point(-0.7436, -0.1318, None),
+            ),
+            ("0.2869 - 0.0142i", point(0.2869, -0.0142, None)),
+            (
+                "-0.743643887037151 0.131825904205330 1e-4",
+                point(
+                    -0.743643887037151,
+                    0.131825904205330,
+                    Some(Scale::Width(1e-4)),
+                ),
+            ),
+            ("x = -0.1011, y = 0.9563", point(-0.1011, 0.9563, None)),
+            ("y: 0.9563 x: -0.1011", point(-0.1011, 0.9563, None)),
+            (
+                "https://example.org/view?re=-0.7436&im=0.1318&zoom=1000",
+                point(-0.7436, 0.1318, Some(Scale::Magnification(1000.0))),
+            ),
+            ("-0,7436 0,1318", point(-0.7436, 0.1318, None)),
+            (
+                "Posted 3 days ago: c = -0.7453 + 0.1127i, width 6.5E-3. Enjoy!",
+                point(-0.7453, 0.1127, Some(Scale::Width(6.5e-3))),
+            ),
+        ];
+        for (text, expected) in cases {
+            assert_eq!(extract(text), Ok(expected), "{:?}", text);
+        }
+    }
+
+    #[test]
+    fn text_without_one_point_in_it_is_refused() {
+        for text in [
+            "2024-05-01",
+            "hello there",
+            "-0.7436",
+            "-0.7436 0.1318 and -1.25 0.02",
+            "re=-0.7436 re=-0.75 im=0.1",
+        ] {
+            let found = extract(text);
+            assert!(found.is_err(), "{:?} gave {:?}", text, found);
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::render::Arithmetic;
+use crate::tilecache::CacheStats;
+
+// One finished render, as the performance HUD reports it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RenderSample {
+    pub generation: u64,
+    pub width: usize,
+    pub height: usize,
+    pub elapsed: Duration,
+    pub arithmetic: Arithmetic,
+}
+
+impl RenderSample {
+    pub fn pixels_per_second(&self) -> f64 {
+        (self.width * self.height) as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+// Finished renders: the latest, and the one before it at the same resolution to compare with,
+// since renders of other sizes say little about whether a change made things faster.
+#[derive(Clone, Debug, Default)]
+pub struct RenderStats {
+    latest: Option<RenderSample>,
+    baseline: Option<RenderSample>,
+    by_size: HashMap<(usize, usize), RenderSample>,
+}
+
+impl RenderStats {
+    pub fn record(&mut self, sample: RenderSample) {
+        self.baseline = self.by_size.insert((sample.width, sample.height), sample);
+        self.latest = Some(sample);
+    }
+
+    pub fn latest(&self) -> Option<RenderSample> {
+        self.latest
+    }
+
+    // How much longer the latest render took than the previous one at its resolution, in
+    // seconds and as a fraction of that one's time; negative when it was faster.
+    pub fn delta(&self) -> Option<(f64, f64)> {
+        let (latest, baseline) = (self.latest?, self.baseline?);
+        let before = baseline.elapsed.as_secs_f64();
+        let change = latest.elapsed.as_secs_f64() - before;
+        Some((change, change / before.max(1e-9)))
+    }
+}
+
+// The HUD's lines, a label column and a value column each, for a monospace font.
+pub fn hud_lines(
+    stats: &RenderStats,
+    threads: usize,
+    cpu_cap: u32,
+    cache: &CacheStats,
+    buffer_bytes: usize,
+    generation: u64,
+) -> Vec<String> {
+    let line = |label: &str, value: String| format!("{:<8} {}", label, value);
+    let mut lines = Vec::new();
+    match stats.latest() {
+        Some(sample) => {
+            let delta = stats
+                .delta()
+                .map_or_else(String::new, |(seconds, fraction)| {
+                    format!(" ({:+.1} ms, {:+.1}%)", seconds * 1000.0, fraction * 100.0)
+                });
+            lines.push(line(
+                "render",
+                format!("{:.1} ms{}", sample.elapsed.as_secs_f64() * 1000.0, delta),
+            ));
+            lines.push(line(
+                "speed",
+                format!("{:.2} Mpx/s", sample.pixels_per_second() / 1e6),
+            ));
+            lines.push(line("size", format!("{}x{}", sample.width, sample.height)));
+            lines.push(line("backend", sample.arithmetic.name().to_string()));
+        }
+        None => lines.push(line("render", String::from("none yet"))),
+    }
+    lines.push(line("threads", threads.to_string()));
+    lines.push(line(
+        "cpu cap",
+        if cpu_cap >= 100 {
+            String::from("off")
+        } else {
+            format!("{}% per worker", cpu_cap)
+        },
+    ));
+    lines.push(line(
+        "cache",
+        format!(
+            "{:.0}% hits, {} tiles",
+            cache.hit_rate() * 100.0,
+            cache.tiles
+        ),
+    ));
+    lines.push(line(
+        "memory",
+        format!(
+            "{:.1} MiB cached",
+            (cache.bytes + buffer_bytes) as f64 / (1024.0 * 1024.0)
+        ),
+    ));
+    lines.push(line("gen", generation.to_string()));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(generation: u64, width: usize, millis: u64) -> RenderSample {
+        RenderSample {
+            generation,
+            width,
+            height: 100,
+            elapsed: Duration::from_millis(millis),
+            arithmetic: Arithmetic::F64,
+        }
+    }
+
+    // Generation, width, milliseconds, and the expected change in milliseconds and percent.
+    type Step = (u64, usize, u64, Option<(f64, f64)>);
+
+    const SCRIPT: [Step; 7] = [
+        (1, 100, 1000, None),
+        (2, 200, 500, None),
+        (3, 100, 800, Some((-200.0, -20.0))),
+        (4, 200, 1000, Some((500.0, 100.0))),
+        (5, 100, 800, Some((0.0, 0.0))),
+        (6, 300, 50, None),
+        (7, 100, 1000, Some((200.0, 25.0))),
+    ];
+
+    fn scripted() -> RenderStats {
+        let mut stats = RenderStats::default();
+        for (generation, width, millis, _) in SCRIPT {
+            stats.record(sample(generation, width, millis));
+        }
+        stats
+    }
+
+    #[test]
+    fn renders_compare_with_the_last_at_their_size() {
+        let mut stats = RenderStats::default();
+        for (generation, width, millis, expected) in SCRIPT {
+            stats.record(sample(generation, width, millis));
+            let delta = stats
+                .delta()
+                .map(|(seconds, fraction)| (seconds * 1000.0, fraction * 100.0));
+            let matches = match (delta, expected) {
+                (Some(delta), Some(expected)) => {
+                    (delta.0 - expected.0).abs() < 1e-6 && (delta.1 - expected.1).abs() < 1e-6
+                }
+                (None, None) => true,
+                _ => false,
+            };
+            assert!(
+                matches,
+                "render {} ({} wide, {} ms) compares as {:?}, not {:?}",
+                generation, width, millis, delta, expected
+            );
+            assert_eq!(
+                stats.latest().map(|latest| latest.generation),
+                Some(generation)
+            );
+        }
+        let speed = stats.latest().map(|latest| latest.pixels_per_second());
+        assert_eq!(speed, Some(10_000.0), "10000 pixels in a second");
+    }
+
+    #[test]
+    fn the_hud_lists_the_latest_render() {
+        let cache = CacheStats {
+            hits: 3,
+            misses: 1,
+            tiles: 12,
+            bytes: 1024 * 1024,
+        };
+        let lines = hud_lines(&scripted(), 8, 50, &cache, 1024 * 1024, 7);
+        for expected in [
+            "render   1000.0 ms (+200.0 ms, +25.0%)",
+            "speed    0.01 Mpx/s",
+            "size     100x100",
+            "backend  f64",
+            "threads  8",
+            "cpu cap  50% per worker",
+            "cache    75% hits, 12 tiles",
+            "memory   2.0 MiB cached",
+            "gen      7",
+        ] {
+            assert!(
+                lines.iter().any(|line| line == expected),
+                "the HUD lacks {:?} in {:?}",
+                expected,
+                lines
+            );
+        }
+    }
+
+    #[test]
+    fn the_hud_says_when_nothing_has_rendered() {
+        let empty = hud_lines(
+            &RenderStats::default(),
+            1,
+            100,
+            &CacheStats::default(),
+            0,
+            0,
+        );
+        assert_eq!(empty.first().map(String::as_str), Some("render   none yet"));
+    }
+}
@@ -0,0 +1,509 @@
+use serde::{Deserialize, Serialize};
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use crate::expr::Expr;
+use crate::render::{self, IterationBuffer};
+
+// Below this many pixels spawning workers costs more than it saves.
+const PARALLEL_THRESHOLD: usize = 256 * 256;
+// What expressions can read for each pixel: its smooth escape time, 0 inside the set; 1 inside
+// the set and 0 outside; its position across and down the frame, from 0 to 1; and its color so
+// far, channels from 0 to 1.
+pub const VARIABLES: [&str; 7] = ["smooth", "inside", "x", "y", "r", "g", "b"];
+// Variables of other renderers that this one has nothing to fill in with, and why.
+const UNAVAILABLE: [(&str, &str); 2] = [
+    ("de", "distance estimates are not computed"),
+    ("trap", "orbit traps are not computed"),
+];
+
+// One step of post-processing, run on the colored frame in the order the stages are listed.
+// Each takes 8-bit sRGB pixels and gives 8-bit sRGB pixels, so that running two stages is exactly
+// running one on the other's output.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+pub enum Stage {
+    // Above 1 brightens midtones.
+    Gamma { gamma: f32 },
+    // Spreads channels away from mid grey; below 1 flattens them towards it.
+    Contrast { contrast: f32 },
+    // Stretches channels so that `black` maps to 0 and `white` to 1.
+    Levels { black: f32, white: f32 },
+    // Turns hues round the color wheel, keeping brightness and saturation.
+    HueShift { turns: f32 },
+    // Scales the color by the expression's value.
+    Expression { source: String },
+}
+
+impl Stage {
+    pub const BUILTIN: [Stage; 4] = [
+        Stage::Gamma { gamma: 1.0 },
+        Stage::Contrast { contrast: 1.0 },
+        Stage::Levels {
+            black: 0.0,
+            white: 1.0,
+        },
+        Stage::HueShift { turns: 0.0 },
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Gamma { .. } => "Gamma",
+            Stage::Contrast { .. } => "Contrast",
+            Stage::Levels { .. } => "Levels",
+            Stage::HueShift { .. } => "Hue shift",
+            Stage::Expression { .. } => "Expression",
+        }
+    }
+
+    // The stage's setting as shown beside its name.
+    pub fn describe(&self) -> String {
+        match self {
+            Stage::Gamma { gamma } => format!("{:.2}", gamma),
+            Stage::Contrast { contrast } => format!("{:.2}", contrast),
+            Stage::Levels { black, white } => format!("{:.2} to {:.2}", black, white),
+            Stage::HueShift { turns } => format!("{:+.2} turns", turns),
+            Stage::Expression { source } => source.clone(),
+        }
+    }
+
+    // Nudges the stage's setting up or down a step; expressions are edited as text instead.
+    pub fn adjust(&mut self, up: bool) {
+        let step = if up { 1.0 } else { -1.0 };
+        match self {
+            Stage::Gamma { gamma } => *gamma = (*gamma + step * 0.1).clamp(0.1, 5.0),
+            Stage::Contrast { contrast } => *contrast = (*contrast + step * 0.1).clamp(0.0, 4.0),
+            Stage::Levels { black, white } => {
+                // Both ends close in together, so that up raises contrast as the other stages do.
+                *black = (*black + step * 0.02).clamp(0.0, 0.45);
+                *white = 1.0 - *black;
+            }
+            Stage::HueShift { turns } => *turns = (*turns + step * 0.05).clamp(-1.0, 1.0),
+            Stage::Expression { .. } => {}
+        }
+    }
+}
+
+// A stage ready to run: built-ins as they are, expressions parsed, with the slot in `VARIABLES`
+// of each variable they read.
+#[derive(Clone, Debug)]
+enum Step {
+    Builtin(Stage),
+    Expression(Expr, Vec<usize>),
+}
+
+// Stages checked and ready to run, along with a fingerprint of each for the cache.
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    steps: Vec<(Step, u64)>,
+}
+
+// The outputs of the stages of the last run, each keyed by a hash of the input frame and every
+// stage up to it, so that changing a late stage runs only it and the ones after.
+#[derive(Debug)]
+pub struct StageCache {
+    outputs: Mutex<Vec<(u64, Vec<u8>)>>,
+}
+
+pub static CACHE: StageCache = StageCache::new();
+
+impl StageCache {
+    pub const fn new() -> Self {
+        StageCache {
+            outputs: Mutex::new(Vec::new()),
+        }
+    }
+}
+
+impl Default for StageCache {
+    fn default() -> Self {
+        StageCache::new()
+    }
+}
+
+// Parses an expression stage's source, saying what is wrong with it if it cannot run.
+pub fn compile_expression(source: &str) -> Result<(Expr, Vec<usize>), String> {
+    let expr = Expr::parse(source)?;
+    let mut slots = Vec::new();
+    for name in expr.variables() {
+        if let Some((_, why)) = UNAVAILABLE
+            .iter()
+            .find(|(unavailable, _)| unavailable == name)
+        {
+            return Err(format!("`{}` is not available: {}", name, why));
+        }
+        let Some(slot) = VARIABLES.iter().position(|known| known == name) else {
+            return Err(format!(
+                "unknown variable `{}`; expressions can use {}",
+                name,
+                VARIABLES.join(", ")
+            ));
+        };
+        slots.push(slot);
+    }
+    Ok((expr, slots))
+}
+
+// Checks every stage before any is run, naming the first that cannot be.
+pub fn compile(stages: &[Stage]) -> Result<Pipeline, String> {
+    let mut steps = Vec::new();
+    for (index, stage) in stages.iter().enumerate() {
+        let step = match stage {
+            Stage::Expression { source } => {
+                let (expr, slots) = compile_expression(source)
+                    .map_err(|err| format!("stage {} ({}): {}", index + 1, source, err))?;
+                Step::Expression(expr, slots)
+            }
+            builtin => Step::Builtin(builtin.clone()),
+        };
+        let mut hasher = DefaultHasher::new();
+        format!("{:?}", stage).hash(&mut hasher);
+        steps.push((step, hasher.finish()));
+    }
+    Ok(Pipeline { steps })
+}
+
+impl Pipeline {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // Runs the stages over `rgba`, the colored frame of `buffer`, in place, taking stage outputs
+    // from `cache` where it has them. Returns how many stages had to be run.
+    pub fn run(
+        &self,
+        rgba: &mut [u8],
+        buffer: &IterationBuffer,
+        threads: usize,
+        cache: &StageCache,
+    ) -> usize {
+        if self.steps.is_empty() {
+            return 0;
+        }
+        let mut hasher = DefaultHasher::new();
+        (buffer.width, buffer.height).hash(&mut hasher);
+        rgba.hash(&mut hasher);
+        for index in 0..buffer.values.len() {
+            buffer.values.get(index).to_bits().hash(&mut hasher);
+        }
+        let mut keys = Vec::with_capacity(self.steps.len());
+        let mut key = hasher.finish();
+        for (_, fingerprint) in &self.steps {
+            let mut hasher = DefaultHasher::new();
+            (key, fingerprint).hash(&mut hasher);
+            key = hasher.finish();
+            keys.push(key);
+        }
+
+        let mut outputs = cache.outputs.lock().unwrap_or_else(PoisonError::into_inner);
+        let reused = outputs
+            .iter()
+            .zip(&keys)
+            .take_while(|((cached, _), key)| cached == *key)
+            .count();
+        if reused > 0 {
+            rgba.copy_from_slice(&outputs[reused - 1].1);
+        }
+        outputs.truncate(reused);
+        for (step, key) in self.steps.iter().zip(&keys).skip(reused) {
+            apply(&step.0, rgba, buffer, threads);
+            outputs.push((*key, rgba.to_vec()));
+        }
+        self.steps.len() - reused
+    }
+}
+
+// Runs one stage over the frame, splitting its rows between `threads` workers.
+fn apply(step: &Step, rgba: &mut [u8], buffer: &IterationBuffer, threads: usize) {
+    let pixels = buffer.width * buffer.height;
+    if threads <= 1 || pixels < PARALLEL_THRESHOLD {
+        apply_rows(step, rgba, buffer, 0);
+        return;
+    }
+    let pixels_per_chunk = buffer.height.div_ceil(threads) * buffer.width;
+    thread::scope(|scope| {
+        for (index, out) in rgba.chunks_mut(pixels_per_chunk * 4).enumerate() {
+            scope.spawn(move || apply_rows(step, out, buffer, index * pixels_per_chunk));
+        }
+    });
+}
+
+// Runs one stage over pixels of the frame starting at pixel `start`.
+fn apply_rows(step: &Step, out: &mut [u8], buffer: &IterationBuffer, start: usize) {
+    let mut values = Vec::new();
+    for (offset, pixel) in out.chunks_exact_mut(4).enumerate() {
+        let index = start + offset;
+        let color = [pixel[0], pixel[1], pixel[2]].map(|c| c as f32 / 255.0);
+        let color = match step {
+            Step::Builtin(stage) => builtin(stage, color),
+            Step::Expression(expr, slots) => {
+                let value = buffer.values.get(index);
+                let inside = !render::escaped(value);
+                let all = [
+                    if inside { 0.0 } else { value as f64 },
+                    if inside { 1.0 } else { 0.0 },
+                    ((index % buffer.width) as f64 + 0.5) / buffer.width as f64,
+                    ((index / buffer.width) as f64 + 0.5) / buffer.height as f64,
+                    color[0] as f64,
+                    color[1] as f64,
+                    color[2] as f64,
+                ];
+                values.clear();
+                values.extend(slots.iter().map(|slot| all[*slot]));
+                let scale = expr.eval(&values) as f32;
+                // Expressions that come out NaN, as log(0) might, leave the pixel black.
+                let scale = if scale.is_nan() { 0.0 } else { scale };
+                color.map(|c| c * scale)
+            }
+        };
+        for (channel, c) in pixel.iter_mut().zip(color) {
+            *channel = (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+    }
+}
+
+fn builtin(stage: &Stage, color: [f32; 3]) -> [f32; 3] {
+    match stage {
+        Stage::Gamma { gamma } => color.map(|c| c.powf(1.0 / gamma.max(0.01))),
+        Stage::Contrast { contrast } => color.map(|c| (c - 0.5) * contrast + 0.5),
+        Stage::Levels { black, white } => {
+            let range = (white - black).max(1e-3);
+            color.map(|c| (c - black) / range)
+        }
+        Stage::HueShift { turns } => shift_hue(color, *turns),
+        Stage::Expression { .. } => color,
+    }
+}
+
+// `color` with its hue turned `turns` round the color wheel, by way of hue, chroma and value.
+pub fn shift_hue([r, g, b]: [f32; 3], turns: f32) -> [f32; 3] {
+    let value = r.max(g).max(b);
+    let chroma = value - r.min(g).min(b);
+    if chroma <= 0.0 {
+        return [r, g, b];
+    }
+    let sector = if value == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if value == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    let sector = sector + turns * 6.0;
+    let channel = |offset: f32| {
+        let k = (offset + sector).rem_euclid(6.0);
+        value - chroma * k.min(4.0 - k).clamp(0.0, 1.0)
+    };
+    [channel(5.0), channel(3.0), channel(1.0)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coloring;
+    use crate::look::Look;
+    use crate::settings::ColoringSettings;
+    use crate::storage::Channel;
+
+    // A bowl rising away from the middle with a disc of the set in it, large enough for workers.
+    fn bowl() -> IterationBuffer {
+        let (width, height) = (300, 280);
+        IterationBuffer {
+            width,
+            height,
+            values: Channel::Full(
+                (0..width * height)
+                    .map(|index| {
+                        let (dx, dy) = (
+                            (index % width) as f32 - 150.0,
+                            (index / width) as f32 - 140.0,
+                        );
+                        let distance = dx.hypot(dy);
+                        if distance < 20.0 {
+                            render::UNRESOLVED
+                        } else {
+                            distance / 4.0
+                        }
+                    })
+                    .collect(),
+            ),
+            angles: Channel::default(),
+            params: None,
+            costs: None,
+            failure: None,
+        }
+    }
+
+    fn fire() -> ColoringSettings {
+        ColoringSettings {
+            palette: String::from("Fire"),
+            ..ColoringSettings::default()
+        }
+    }
+
+    fn stages() -> Vec<Stage> {
+        vec![
+            Stage::Gamma { gamma: 1.8 },
+            Stage::Expression {
+                source: String::from("0.5 + 0.5 * cos(smooth / 8 + x * pi)"),
+            },
+            Stage::HueShift { turns: 0.3 },
+        ]
+    }
+
+    fn bad_stages() -> Vec<Stage> {
+        vec![
+            Stage::Gamma { gamma: 1.8 },
+            Stage::Expression {
+                source: String::from("smooth * trap"),
+            },
+        ]
+    }
+
+    #[test]
+    fn bad_expressions_are_refused_with_the_reason() {
+        let refused = [
+            ("1 +", "column 4"),
+            ("sqrt(1, 2)", "takes 1 argument"),
+            ("noise(x)", "unknown function"),
+            ("1 2", "column 3"),
+            ("de * 2", "distance estimates"),
+            ("trap", "orbit traps"),
+            ("z", "unknown variable"),
+        ];
+        for (source, reason) in refused {
+            match compile_expression(source) {
+                Ok(_) => panic!("{} is accepted", source),
+                Err(err) => assert!(
+                    err.contains(reason),
+                    "{} is refused with \"{}\"",
+                    source,
+                    err
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn stages_compose_exactly() {
+        let buffer = bowl();
+        let base = coloring::recolor(&buffer, &fire(), 1);
+        let two_stage = ColoringSettings {
+            post: vec![
+                Stage::Gamma { gamma: 1.8 },
+                Stage::Contrast { contrast: 1.4 },
+            ],
+            ..fire()
+        };
+        let level = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let by_hand: Vec<u8> = base
+            .iter()
+            .enumerate()
+            .map(|(index, &c)| {
+                if index % 4 == 3 {
+                    return c;
+                }
+                let gamma = level((c as f32 / 255.0).powf(1.0 / 1.8));
+                level((gamma as f32 / 255.0 - 0.5) * 1.4 + 0.5)
+            })
+            .collect();
+        let piped = coloring::recolor(&buffer, &two_stage, 1);
+        assert!(piped != base, "the pipeline leaves the frame as it was");
+        assert!(
+            piped == by_hand,
+            "gamma then contrast differs from running them by hand"
+        );
+    }
+
+    #[test]
+    fn expressions_see_which_pixels_are_inside() {
+        let buffer = bowl();
+        let base = coloring::recolor(&buffer, &fire(), 1);
+        let outside_only = ColoringSettings {
+            post: vec![Stage::Expression {
+                source: String::from("1 - inside"),
+            }],
+            ..fire()
+        };
+        let masked = coloring::recolor(&buffer, &outside_only, 1);
+        let middle = (140 * buffer.width + 150) * 4;
+        assert_eq!(masked[middle..middle + 4], [0, 0, 0, 255]);
+        assert_eq!(masked[..4], base[..4]);
+    }
+
+    // Stage outputs are reused for the same input, and only for it.
+    #[test]
+    fn stage_outputs_are_reused() {
+        let buffer = bowl();
+        let base = coloring::recolor(&buffer, &fire(), 1);
+        let pipeline = compile(&stages()).expect("valid stages compile");
+        let cache = StageCache::new();
+        let run = |pipeline: &Pipeline, threads: usize, cache: &StageCache| {
+            let mut rgba = base.clone();
+            let computed = pipeline.run(&mut rgba, &buffer, threads, cache);
+            (rgba, computed)
+        };
+        let (serial, computed) = run(&pipeline, 1, &cache);
+        assert_eq!(computed, 3, "a first run computes {} of 3 stages", computed);
+        let (again, computed) = run(&pipeline, 1, &cache);
+        assert_eq!(computed, 0, "a repeated run computes {} stages", computed);
+        assert!(again == serial, "a repeated run gives another frame");
+        let mut changed = stages();
+        changed[2] = Stage::HueShift { turns: 0.6 };
+        let changed = compile(&changed).expect("valid stages compile");
+        let (_, computed) = run(&changed, 1, &cache);
+        assert_eq!(
+            computed, 1,
+            "changing the last stage computes {} stages",
+            computed
+        );
+        let mut other = base.clone();
+        other[0] ^= 1;
+        assert_eq!(
+            pipeline.run(&mut other, &buffer, 1, &cache),
+            3,
+            "stages are reused for a different frame"
+        );
+        let (parallel, _) = run(&pipeline, 4, &StageCache::new());
+        assert!(
+            parallel == serial,
+            "threaded workers post-process differently"
+        );
+    }
+
+    #[test]
+    fn bad_stages_are_found_before_any_run() {
+        let compiled = compile(&bad_stages());
+        assert!(
+            compiled
+                .as_ref()
+                .is_err_and(|err| err.starts_with("stage 2")),
+            "a bad second stage gives {:?}",
+            compiled.err()
+        );
+    }
+
+    #[test]
+    fn looks_keep_their_pipeline() {
+        let look = Look::of(&ColoringSettings {
+            post: stages(),
+            ..fire()
+        });
+        let read = look
+            .to_json()
+            .and_then(|json| Look::from_json(&json))
+            .and_then(|look| look.settings())
+            .map(|settings| settings.post);
+        assert_eq!(read, Ok(stages()), "a look loses its pipeline");
+        let bad_look = Look::of(&ColoringSettings {
+            post: bad_stages(),
+            ..fire()
+        });
+        assert!(
+            bad_look.settings().is_err(),
+            "a look with a bad stage is accepted"
+        );
+    }
+}
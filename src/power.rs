@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+
+// Worker threads renders get while saving energy.
+pub const ENERGY_SAVER_THREADS: usize = 2;
+
+// When the app keeps to a single render per navigation, with no previews, no idle refinement and
+// fewer workers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnergySaver {
+    #[default]
+    Off,
+    On,
+    OnBattery,
+}
+
+impl EnergySaver {
+    pub fn name(self) -> &'static str {
+        match self {
+            EnergySaver::Off => "off",
+            EnergySaver::On => "on",
+            EnergySaver::OnBattery => "on battery",
+        }
+    }
+
+    pub fn next(self) -> EnergySaver {
+        match self {
+            EnergySaver::Off => EnergySaver::On,
+            EnergySaver::On => EnergySaver::OnBattery,
+            EnergySaver::OnBattery => EnergySaver::Off,
+        }
+    }
+}
+
+// Whether the machine is running on a battery, where the platform says: a battery reporting that
+// it discharges. `None` if no battery status is available.
+pub fn on_battery() -> Option<bool> {
+    let supplies = fs::read_dir("/sys/class/power_supply").ok()?;
+    let mut found = false;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        let read = |name: &str| fs::read_to_string(path.join(name)).unwrap_or_default();
+        if read("type").trim() != "Battery" {
+            continue;
+        }
+        found = true;
+        if read("status").trim() == "Discharging" {
+            return Some(true);
+        }
+    }
+    found.then_some(false)
+}
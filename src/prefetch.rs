@@ -0,0 +1,205 @@
+use iced::Size;
+
+use crate::render::{self, FrameParams, IterationBuffer, PixelRect, TileResult};
+
+// Share of the frame's width and height prefetched beyond each of its edges.
+pub const PREFETCH_MARGIN: f32 = 0.25;
+// The margin is computed in bands this many pixels thick, nearest the frame first, so that input
+// cutting a step short loses one band at most.
+const PREFETCH_BAND: usize = 32;
+
+// A frame with a margin around it on the same pixel grid, filled in a piece at a time while the
+// app is idle. Renders of views that pan within it copy its pixels instead of computing them,
+// whether or not it is complete.
+#[derive(Debug)]
+pub struct Prefetch {
+    pub buffer: IterationBuffer,
+    // The pieces of `buffer` holding computed values.
+    pub known: Vec<PixelRect>,
+    // The pieces still to compute, in order.
+    pending: Vec<PixelRect>,
+    // The frame it surrounds.
+    size: Size,
+    params: FrameParams,
+}
+
+impl Prefetch {
+    // A prefetch around frames rendered with `params` at `size`, starting from whatever the
+    // `sources` already hold of it. `None` if the enlarged frame would take more than `budget`
+    // bytes.
+    pub fn new(
+        size: Size,
+        params: FrameParams,
+        sources: &[(&IterationBuffer, &[PixelRect])],
+        budget: usize,
+    ) -> Option<Prefetch> {
+        let (width, height) = (size.width as usize, size.height as usize);
+        let margin_x = (width as f32 * PREFETCH_MARGIN).round() as usize;
+        let margin_y = (height as f32 * PREFETCH_MARGIN).round() as usize;
+        let full = PixelRect {
+            x: 0,
+            y: 0,
+            width: width + 2 * margin_x,
+            height: height + 2 * margin_y,
+        };
+        let channels = 1 + params.angle.tracked() as usize;
+        if full.area() * channels * size_of::<f32>() > budget {
+            return None;
+        }
+        let bounds = Size::new(full.width as f32, full.height as f32);
+        let viewport = params.viewport.shifted(
+            0.0,
+            0.0,
+            params.viewport.pixel_size(size) * full.width as f64,
+        );
+        let mut buffer = render::empty_buffer(bounds, FrameParams { viewport, ..params });
+        let mut missing = vec![full];
+        let mut known = Vec::new();
+        for (source, pieces) in sources {
+            known.extend(buffer.splice(source, pieces, &mut missing));
+        }
+        // The frame itself, should it not have been copied, and then the bands outwards.
+        let frame = PixelRect {
+            x: margin_x,
+            y: margin_y,
+            width,
+            height,
+        };
+        let mut order = vec![frame];
+        let mut inner = frame;
+        while inner != full {
+            let x = inner.x.saturating_sub(PREFETCH_BAND);
+            let y = inner.y.saturating_sub(PREFETCH_BAND);
+            let outer = PixelRect {
+                x,
+                y,
+                width: (inner.x + inner.width + PREFETCH_BAND).min(full.width) - x,
+                height: (inner.y + inner.height + PREFETCH_BAND).min(full.height) - y,
+            };
+            order.extend(outer.subtract(&inner));
+            inner = outer;
+        }
+        let pending = order
+            .iter()
+            .flat_map(|piece| missing.iter().filter_map(|gap| gap.intersection(piece)))
+            .collect();
+        Some(Prefetch {
+            buffer,
+            known,
+            pending,
+            size,
+            params,
+        })
+    }
+
+    // Whether this is the prefetch around frames rendered with `params` at `size`.
+    pub fn surrounds(&self, size: Size, params: &FrameParams) -> bool {
+        self.size == size && self.params == *params
+    }
+
+    pub fn bounds(&self) -> Size {
+        Size::new(self.buffer.width as f32, self.buffer.height as f32)
+    }
+
+    pub fn params(&self) -> FrameParams {
+        self.buffer
+            .params
+            .expect("prefetch buffers carry their parameters")
+    }
+
+    // The next piece to compute, in `bounds` pixels with `params`; `None` once it is complete.
+    pub fn next(&self) -> Option<PixelRect> {
+        self.pending.first().copied()
+    }
+
+    // Stores the tiles computed for `piece`, which then counts as known. A piece with a failed
+    // tile is dropped instead, rather than computed again and again.
+    pub fn insert(&mut self, piece: PixelRect, tiles: &[TileResult]) {
+        self.pending.retain(|pending| *pending != piece);
+        if tiles.iter().any(|tile| tile.failure.is_some()) {
+            return;
+        }
+        for tile in tiles {
+            self.buffer.insert_tile(tile);
+        }
+        self.known.push(piece);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use threadpool::ThreadPool;
+
+    use super::*;
+    use crate::render::CancelToken;
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+    use crate::tiling::Focus;
+
+    // Prefetches the whole margin around the first canonical view, then pans by a tenth of the
+    // frame each way, well within it. Pixels copied from another frame had their coordinates
+    // worked out from that frame's corner, which can round differently right at the boundary, so
+    // a few may differ from a fresh render.
+    #[test]
+    fn pans_within_the_margin_compute_nothing() {
+        let _settings = stats::default_render_settings();
+        let size = CANONICAL_SIZE;
+        let params = CANONICAL_VIEWS[0].params();
+        let pool = ThreadPool::new(1);
+        let cancel = CancelToken::new();
+        let frame = stats::render_frame(size, params);
+        let whole = [PixelRect {
+            x: 0,
+            y: 0,
+            width: frame.width,
+            height: frame.height,
+        }];
+        let mut prefetch = Prefetch::new(size, params, &[(&frame, &whole)], usize::MAX)
+            .expect("an unlimited budget fits any margin");
+        while let Some(piece) = prefetch.next() {
+            let mut tiles = Vec::new();
+            render::render_rects(
+                &pool,
+                prefetch.bounds(),
+                prefetch.params(),
+                &[piece],
+                &cancel,
+                |tile| tiles.push(tile),
+            )
+            .expect("renders without a cancel request complete");
+            prefetch.insert(piece, &tiles);
+        }
+        let pixel_size = params.viewport.pixel_size(size);
+        let (right, down) = (size.width / 10.0, size.height / 10.0);
+        let panned = FrameParams {
+            viewport: params.viewport.shifted(
+                right.round() as f64 * pixel_size,
+                -(down.round() as f64) * pixel_size,
+                params.viewport.width,
+            ),
+            ..params
+        };
+        let mut tiles_computed = 0;
+        let reused = render::render_reusing_from(
+            &pool,
+            &[(&frame, &whole), (&prefetch.buffer, &prefetch.known)],
+            size,
+            panned,
+            &cancel,
+            &Focus::default(),
+            |_| tiles_computed += 1,
+        )
+        .expect("renders without a cancel request complete");
+        assert_eq!(tiles_computed, 0, "the pan computed tiles");
+        let fresh = stats::render_frame(size, panned);
+        let pixels = fresh.values.len();
+        let differing = (0..pixels)
+            .filter(|&index| reused.values.get(index) != fresh.values.get(index))
+            .count();
+        assert!(
+            differing * 1000 <= pixels,
+            "{} of {} pixels differ from a fresh render",
+            differing,
+            pixels
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+
+use toml::map::Map;
+use toml::Value;
+
+use std::fs;
+use std::path::Path;
+
+use crate::adjust::Region;
+use crate::annotation::Annotation;
+use crate::fractal::FractalKind;
+use crate::json;
+use crate::look::Look;
+use crate::rawdata;
+use crate::render::IterationBuffer;
+use crate::settings::{ColoringSettings, RenderSettings};
+use crate::viewport::Viewport;
+
+pub const PROJECT_VERSION: u32 = 1;
+// Projects are recognised by this extension, e.g. when a file is dropped on the window.
+pub const PROJECT_EXTENSION: &str = "mandel";
+// Each step brings a project of the version at its index plus one up to the next version, so
+// that older projects open as the current version would have saved them. No version has been
+// superseded yet.
+const MIGRATIONS: [fn(&mut Map<String, Value>); PROJECT_VERSION as usize - 1] = [];
+
+// A view saved with a piece, as a bookmark is.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ProjectBookmark {
+    pub name: String,
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+}
+
+// Everything about a piece: where it is, how it is rendered and colored, and the views kept with
+// it. On disk a project is its JSON, optionally followed by a zero byte and the frame as a data
+// file, so that reopening it need not compute anything.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Project {
+    pub version: u32,
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    pub settings: RenderSettings,
+    pub look: Look,
+    #[serde(default)]
+    pub bookmarks: Vec<ProjectBookmark>,
+    // The labels and arrows pinned to the fractal, opened alongside any already there.
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    // The adjustment regions of the coloring, which the look leaves out as they belong to this
+    // place in the plane.
+    #[serde(default)]
+    pub regions: Vec<Region>,
+    // Samples each way per pixel of the stored frame, if there is one.
+    #[serde(default = "default_frame_antialias")]
+    pub frame_antialias: u32,
+}
+
+fn default_frame_antialias() -> u32 {
+    1
+}
+
+impl Project {
+    pub fn new(
+        fractal: FractalKind,
+        viewport: Viewport,
+        settings: RenderSettings,
+        coloring: &ColoringSettings,
+        bookmarks: Vec<ProjectBookmark>,
+    ) -> Project {
+        Project {
+            version: PROJECT_VERSION,
+            fractal,
+            viewport,
+            settings,
+            look: Look::of(coloring),
+            bookmarks,
+            annotations: Vec::new(),
+            regions: coloring.regions.clone(),
+            frame_antialias: 1,
+        }
+    }
+
+    pub fn to_bytes(&self, frame: Option<&IterationBuffer>) -> Result<Vec<u8>, String> {
+        let mut bytes = json::to_string_pretty(self)?.into_bytes();
+        if let Some(frame) = frame {
+            bytes.push(0);
+            bytes.extend(rawdata::to_bytes(frame)?);
+        }
+        Ok(bytes)
+    }
+
+    // The project in `bytes` brought up to the current version, and its frame if it has one.
+    pub fn parse(bytes: &[u8]) -> Result<(Project, Option<IterationBuffer>), String> {
+        let (text, frame) = match bytes.iter().position(|byte| *byte == 0) {
+            Some(end) => (&bytes[..end], Some(&bytes[end + 1..])),
+            None => (bytes, None),
+        };
+        let text = std::str::from_utf8(text).map_err(|_| String::from("not a project file"))?;
+        let Value::Table(mut table) = json::from_str::<Value>(text)? else {
+            return Err(String::from("not a project file"));
+        };
+        let version = table
+            .get("version")
+            .and_then(Value::as_integer)
+            .ok_or_else(|| String::from("not a project file: no version"))?;
+        if !(1..=PROJECT_VERSION as i64).contains(&version) {
+            return Err(format!(
+                "project version {} is not supported; this version reads 1 to {}",
+                version, PROJECT_VERSION
+            ));
+        }
+        for migrate in &MIGRATIONS[version as usize - 1..] {
+            migrate(&mut table);
+        }
+        table.insert(
+            String::from("version"),
+            Value::Integer(PROJECT_VERSION as i64),
+        );
+        let project: Project = Value::Table(table)
+            .try_into()
+            .map_err(|err: toml::de::Error| err.message().to_string())?;
+        let frame = frame
+            .map(|frame| rawdata::parse(frame).map_err(|err| format!("stored frame: {}", err)))
+            .transpose()?;
+        Ok((project, frame))
+    }
+
+    pub fn write(&self, path: &Path, frame: Option<&IterationBuffer>) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+        }
+        fs::write(path, self.to_bytes(frame)?).map_err(|err| err.to_string())
+    }
+
+    pub fn read(path: &Path) -> Result<(Project, Option<IterationBuffer>), String> {
+        let bytes = fs::read(path).map_err(|err| err.to_string())?;
+        Project::parse(&bytes)
+    }
+
+    pub fn is_project_file(path: &Path) -> bool {
+        path.extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| extension.eq_ignore_ascii_case(PROJECT_EXTENSION))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::postprocess::Stage;
+    use crate::render::FrameParams;
+    use crate::stats::{self, FrameStats, CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    fn coloring() -> ColoringSettings {
+        let mut coloring = ColoringSettings {
+            post: vec![Stage::Contrast { contrast: 1.3 }],
+            ..ColoringSettings::default()
+        };
+        coloring.set_palette(String::from("Ocean"));
+        coloring
+    }
+
+    // A project of the first canonical view, with a bookmark and a look of its own.
+    fn project() -> Project {
+        let params = CANONICAL_VIEWS[0].params();
+        Project::new(
+            params.fractal,
+            params.viewport,
+            RenderSettings {
+                max_iterations: params.max_iterations,
+                ..RenderSettings::default()
+            },
+            &coloring(),
+            vec![ProjectBookmark {
+                name: String::from("Detail"),
+                fractal: params.fractal,
+                viewport: Viewport::new(-0.75, 0.1, 0.01),
+            }],
+        )
+    }
+
+    fn frame() -> IterationBuffer {
+        stats::render_frame(CANONICAL_SIZE, CANONICAL_VIEWS[0].params())
+    }
+
+    // The project as a document, changed by `edit` before it is written.
+    fn document(edit: impl Fn(&mut Map<String, Value>)) -> String {
+        let mut value = Value::try_from(project()).expect("projects serialize");
+        if let Some(table) = value.as_table_mut() {
+            edit(table);
+        }
+        json::to_string_pretty(&value).expect("projects serialize")
+    }
+
+    #[test]
+    fn projects_with_a_frame_come_back_as_saved() {
+        let (project, frame) = (project(), frame());
+        let max_iterations = project.settings.max_iterations;
+        let bytes = project.to_bytes(Some(&frame)).expect("projects serialize");
+        let (read, stored) = Project::parse(&bytes).expect("a saved project opens");
+        assert_eq!(read, project);
+        let stored = stored.expect("the frame is stored");
+        assert_eq!(
+            FrameStats::of(&stored, max_iterations),
+            FrameStats::of(&frame, max_iterations),
+            "the stored frame changed"
+        );
+    }
+
+    #[test]
+    fn reopened_projects_render_the_frame_they_were_saved_from() {
+        let project = project();
+        let bytes = project.to_bytes(None).expect("projects serialize");
+        let (read, stored) = Project::parse(&bytes).expect("a saved project opens");
+        assert!(stored.is_none(), "a project saved without a frame has one");
+        let params = FrameParams {
+            viewport: read.viewport,
+            fractal: read.fractal,
+            max_iterations: read.settings.max_iterations,
+            ..CANONICAL_VIEWS[0].params()
+        };
+        assert_eq!(
+            FrameStats::of(
+                &stats::render_frame(CANONICAL_SIZE, params),
+                params.max_iterations
+            ),
+            FrameStats::of(&frame(), params.max_iterations),
+            "a reopened project renders another frame"
+        );
+        assert_eq!(
+            read.look.settings(),
+            Ok(coloring()),
+            "a reopened project loses its coloring"
+        );
+    }
+
+    #[test]
+    fn fields_added_later_default_when_missing() {
+        let bare = document(|table| {
+            table.remove("bookmarks");
+            table.remove("frame_antialias");
+        });
+        let (read, _) = Project::parse(bare.as_bytes()).expect("an older project opens");
+        assert!(read.bookmarks.is_empty());
+        assert_eq!(read.frame_antialias, 1);
+    }
+
+    #[test]
+    fn newer_versionless_and_truncated_files_are_refused() {
+        let newer = document(|table| {
+            table.insert(
+                String::from("version"),
+                Value::Integer(PROJECT_VERSION as i64 + 1),
+            );
+        });
+        let versionless = document(|table| {
+            table.remove("version");
+        });
+        let mut truncated = project()
+            .to_bytes(Some(&frame()))
+            .expect("projects serialize");
+        truncated.truncate(truncated.len() - 10);
+        for (name, bytes) in [
+            ("a newer project", newer.as_bytes()),
+            ("a project without a version", versionless.as_bytes()),
+            ("a project with a truncated frame", &truncated[..]),
+        ] {
+            assert!(Project::parse(bytes).is_err(), "{} is opened", name);
+        }
+    }
+}
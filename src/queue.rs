@@ -0,0 +1,523 @@
+use iced::Size;
+
+use serde::{Deserialize, Serialize};
+
+use std::path::PathBuf;
+
+use crate::fractal::FractalKind;
+use crate::render::Arithmetic;
+use crate::settings::{ColoringSettings, QualityProfile};
+use crate::viewport::Viewport;
+
+// What an export renders and where it goes.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportSpec {
+    pub fractal: FractalKind,
+    pub viewport: Viewport,
+    pub max_iterations: u32,
+    // Rendered size; the image is this divided by `antialias` on each side.
+    pub width: u32,
+    pub height: u32,
+    pub antialias: u32,
+    // The quality profile the export was queued with, by name. The iterations and antialiasing
+    // above are taken from it as it is when the export runs, so a queue file can name one instead
+    // of spelling them out.
+    #[serde(default)]
+    pub profile: Option<String>,
+    pub coloring: ColoringSettings,
+    pub coarse_prepass: bool,
+    pub double_double: bool,
+    // Directory and file name without extension; the name is made unique when the file is
+    // written, since queued exports of one view expand to the same name.
+    pub dir: PathBuf,
+    pub name: String,
+}
+
+impl ExportSpec {
+    // This export with `padding` more image pixels on every side, got by widening the view rather
+    // than by bordering it: the center and the size of a pixel stay as they were, so the fractal
+    // carries on into the padding and the unpadded image is exactly the middle of the padded one.
+    pub fn padded(&self, padding: u32) -> ExportSpec {
+        let rendered = padding * self.antialias.max(1);
+        let width = self.width + 2 * rendered;
+        ExportSpec {
+            viewport: Viewport {
+                width: self.viewport.width / self.width as f64 * width as f64,
+                ..self.viewport
+            },
+            width,
+            height: self.height + 2 * rendered,
+            ..self.clone()
+        }
+    }
+
+    // This export with the iterations and antialiasing of the profile it names among `profiles`,
+    // writing an image of the same size; as it is when it names none.
+    pub fn with_profile(&self, profiles: &[QualityProfile]) -> Result<ExportSpec, String> {
+        let Some(name) = &self.profile else {
+            return Ok(self.clone());
+        };
+        let settings = QualityProfile::named(profiles, name)?.settings;
+        let (from, to) = (self.antialias.max(1), settings.antialias.max(1));
+        Ok(ExportSpec {
+            max_iterations: settings.max_iterations,
+            width: self.width / from * to,
+            height: self.height / from * to,
+            antialias: to,
+            ..self.clone()
+        })
+    }
+
+    // This export with everything that cannot change the image it writes put one way: no
+    // directory, name or profile, which has been resolved by the time it runs, the center's high
+    // and low parts renormalized and no negative zeros, antialiasing of 0 read as the 1 it renders
+    // with, no pre-pass, which only changes how fast the image comes, and double-double kept only
+    // where it would be used. Coordinates written differently in the queue file, like -0.75 and
+    // -7.5e-1, are already one number once read.
+    pub fn canonical(&self) -> ExportSpec {
+        let (re, im) = self.viewport.center();
+        let viewport = Viewport {
+            center_re: re.hi + 0.0,
+            center_im: im.hi + 0.0,
+            width: self.viewport.width,
+            center_re_lo: re.lo + 0.0,
+            center_im_lo: im.lo + 0.0,
+        };
+        let size = Size::new(self.width as f32, self.height as f32);
+        ExportSpec {
+            viewport,
+            antialias: self.antialias.max(1),
+            coarse_prepass: false,
+            double_double: Arithmetic::select(&viewport, size, self.double_double)
+                == Arithmetic::DoubleDouble,
+            profile: None,
+            dir: PathBuf::new(),
+            name: String::new(),
+            ..self.clone()
+        }
+    }
+
+    // FNV-1a of the canonical export as saved, the same in every run: exports with equal hashes
+    // write the same image.
+    pub fn settings_hash(&self) -> u64 {
+        let contents = toml::to_string(&self.canonical()).unwrap_or_default();
+        contents.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+}
+
+// What a run of the queue did, reported once it empties.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct BatchSummary {
+    pub rendered: usize,
+    pub failed: usize,
+    // Exports that would have written the same image as one already written this run: the file
+    // each got instead of rendering, and the file it was linked or copied from.
+    pub deduplicated: Vec<(PathBuf, PathBuf)>,
+}
+
+impl BatchSummary {
+    pub fn is_empty(&self) -> bool {
+        self.rendered == 0 && self.failed == 0 && self.deduplicated.is_empty()
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!("exports done: {} rendered", self.rendered);
+        if !self.deduplicated.is_empty() {
+            let reused: Vec<String> = self
+                .deduplicated
+                .iter()
+                .map(|(path, original)| format!("{} from {}", path.display(), original.display()))
+                .collect();
+            report.push_str(&format!(
+                ", {} duplicates reused ({})",
+                self.deduplicated.len(),
+                reused.join(", ")
+            ));
+        }
+        if self.failed > 0 {
+            report.push_str(&format!(", {} failed", self.failed));
+        }
+        report
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExportJob {
+    pub id: u64,
+    pub spec: ExportSpec,
+    // Held back by the user: a queued job is passed over, a running one sleeps.
+    pub paused: bool,
+    #[serde(skip)]
+    pub running: bool,
+    #[serde(skip)]
+    pub progress: f32,
+}
+
+// Exports waiting to run, in order, and the one running. Only one export runs at a time, and
+// none starts while an interactive render is busy; the caller pauses a running one for those.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportQueue {
+    pub jobs: Vec<ExportJob>,
+    next_id: u64,
+}
+
+impl ExportQueue {
+    pub fn push(&mut self, spec: ExportSpec) -> u64 {
+        self.next_id += 1;
+        self.jobs.push(ExportJob {
+            id: self.next_id,
+            spec,
+            paused: false,
+            running: false,
+            progress: 0.0,
+        });
+        self.next_id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ExportJob> {
+        self.jobs.iter().find(|job| job.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: u64) -> Option<&mut ExportJob> {
+        self.jobs.iter_mut().find(|job| job.id == id)
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<ExportJob> {
+        let index = self.jobs.iter().position(|job| job.id == id)?;
+        Some(self.jobs.remove(index))
+    }
+
+    // Moves a job `offset` places towards the back of the queue, or the front if negative,
+    // stopping at either end.
+    pub fn move_by(&mut self, id: u64, offset: isize) {
+        let Some(index) = self.jobs.iter().position(|job| job.id == id) else {
+            return;
+        };
+        let target = index.saturating_add_signed(offset).min(self.jobs.len() - 1);
+        let job = self.jobs.remove(index);
+        self.jobs.insert(target, job);
+    }
+
+    pub fn running(&self) -> Option<&ExportJob> {
+        self.jobs.iter().find(|job| job.running)
+    }
+
+    // The job to start now: the first one not held back, provided nothing is running and no
+    // interactive render is `busy`.
+    pub fn next(&self, busy: bool) -> Option<u64> {
+        if busy || self.running().is_some() {
+            return None;
+        }
+        self.jobs.iter().find(|job| !job.paused).map(|job| job.id)
+    }
+
+    // Whether the running job should sleep: held back by the user, or preempted by an
+    // interactive render.
+    pub fn hold_running(&self, busy: bool) -> bool {
+        self.running().is_some_and(|job| busy || job.paused)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::FrameParams;
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    // The first canonical view, exported at the canonical size.
+    fn spec() -> ExportSpec {
+        let params = CANONICAL_VIEWS[0].params();
+        ExportSpec {
+            fractal: params.fractal,
+            viewport: params.viewport,
+            max_iterations: params.max_iterations,
+            width: CANONICAL_SIZE.width as u32,
+            height: CANONICAL_SIZE.height as u32,
+            antialias: 1,
+            profile: None,
+            coloring: ColoringSettings::default(),
+            coarse_prepass: false,
+            double_double: false,
+            dir: PathBuf::from("renders"),
+            name: String::from("home"),
+        }
+    }
+
+    fn size(spec: &ExportSpec) -> Size {
+        Size::new(spec.width as f32, spec.height as f32)
+    }
+
+    #[test]
+    fn padding_widens_the_view_around_the_same_pixels() {
+        let padding = 12;
+        let spec = spec();
+        let padded = spec.padded(padding);
+        let (before, after) = (spec.viewport, padded.viewport);
+        assert_eq!(
+            (after.center(), padded.width, padded.height),
+            (
+                before.center(),
+                spec.width + 2 * padding,
+                spec.height + 2 * padding
+            ),
+            "padding by {} moves {:?} to {:?}",
+            padding,
+            spec,
+            padded
+        );
+        // Where each corner pixel of the unpadded image lies, in both frames, as the f64 kernel
+        // places it.
+        let corner = |spec: &ExportSpec, x: u32, y: u32| {
+            let (left, top) = spec.viewport.top_left(size(spec));
+            let pixel_size = spec.viewport.pixel_size(size(spec));
+            (left + pixel_size * x as f64, top - pixel_size * y as f64)
+        };
+        for (x, y) in [(0, 0), (spec.width - 1, spec.height - 1)] {
+            let (re, im) = corner(&spec, x, y);
+            let (padded_re, padded_im) = corner(&padded, x + padding, y + padding);
+            let off = (padded_re - re).abs().max((padded_im - im).abs())
+                / spec.viewport.pixel_size(size(&spec));
+            assert!(
+                off <= 1e-6,
+                "padding moves pixel ({}, {}) by {:e} pixels",
+                x,
+                y,
+                off
+            );
+        }
+        let render = |spec: &ExportSpec| {
+            let params = FrameParams {
+                viewport: spec.viewport,
+                ..CANONICAL_VIEWS[0].params()
+            };
+            stats::render_frame(size(spec), params)
+        };
+        let (plain, wide) = (render(&spec), render(&padded));
+        let padding = padding as usize;
+        let differing = (0..plain.height)
+            .flat_map(|y| (0..plain.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| {
+                plain.values.get(y * plain.width + x)
+                    != wide.values.get((y + padding) * wide.width + x + padding)
+            })
+            .count();
+        // Coordinates can differ in their last bits, which flips a few chaotic pixels on the
+        // boundary.
+        assert!(
+            differing * 100 <= plain.values.len(),
+            "{} of {} pixels differ in the middle of the padded frame",
+            differing,
+            plain.values.len()
+        );
+    }
+
+    // Exports naming a profile take its settings when they run, whatever they were queued with.
+    #[test]
+    fn profiles_set_iterations_and_antialiasing_for_the_same_image_size() {
+        let profiles = QualityProfile::builtin();
+        let named = ExportSpec {
+            profile: Some(String::from("final")),
+            ..spec().padded(4)
+        };
+        let resolved = named
+            .with_profile(&profiles)
+            .expect("the Final profile is built in");
+        assert_eq!((resolved.max_iterations, resolved.antialias), (5000, 2));
+        assert_eq!(
+            (resolved.width, resolved.height),
+            (named.width * 2, named.height * 2)
+        );
+        assert_eq!(resolved.viewport, named.viewport);
+        assert_eq!(
+            resolved.with_profile(&profiles),
+            Ok(resolved.clone()),
+            "resolving a profile twice changes the export"
+        );
+        assert_eq!(spec().with_profile(&profiles), Ok(spec()));
+        let unknown = ExportSpec {
+            profile: Some(String::from("Poster")),
+            ..spec()
+        };
+        let err = unknown
+            .with_profile(&profiles)
+            .expect_err("unknown profiles are not resolved");
+        assert!(
+            err.contains("Poster") && err.contains("Interactive, Balanced, Final"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn exports_of_the_same_image_hash_the_same() {
+        let spec = spec();
+        let hash = spec.settings_hash();
+        assert!(
+            hash == spec.clone().settings_hash() && hash != 0,
+            "the hash of one export varies or is {:x}",
+            hash
+        );
+        let same = [
+            (
+                "another name and folder",
+                ExportSpec {
+                    dir: PathBuf::from("elsewhere"),
+                    name: String::from("home copy"),
+                    ..spec.clone()
+                },
+            ),
+            (
+                "a pre-pass",
+                ExportSpec {
+                    coarse_prepass: true,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "antialiasing of 0",
+                ExportSpec {
+                    antialias: 0,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "double-double where f64 is enough",
+                ExportSpec {
+                    double_double: true,
+                    ..spec.clone()
+                },
+            ),
+        ];
+        for (what, other) in same {
+            assert_eq!(
+                other.settings_hash(),
+                hash,
+                "an export with {} hashes differently",
+                what
+            );
+        }
+    }
+
+    #[test]
+    fn views_written_differently_hash_the_same() {
+        let spec = spec();
+        let spellings = [
+            "center_re = -0.5\ncenter_im = 0.0\nwidth = 3.0",
+            "# the same view\ncenter_re = -5e-1\ncenter_im = -0.0\nwidth = 3.000",
+            "center_re = -0.500\ncenter_im = 0.0\nwidth = 30e-1\n\
+             center_re_lo = 0.0\ncenter_im_lo = -0.0",
+            "center_re = -1.0\ncenter_im = 0.0\nwidth = 3.0\ncenter_re_lo = 0.5",
+        ];
+        let hashes: Vec<_> = spellings
+            .iter()
+            .map(|contents| {
+                let viewport = toml::from_str::<Viewport>(contents).expect("reads a view");
+                ExportSpec {
+                    viewport,
+                    ..spec.clone()
+                }
+                .settings_hash()
+            })
+            .collect();
+        assert!(
+            hashes.iter().all(|other| *other == hashes[0]),
+            "one view written {} ways hashes as {:?}",
+            spellings.len(),
+            hashes
+        );
+    }
+
+    #[test]
+    fn exports_of_other_images_hash_differently() {
+        let spec = spec();
+        let hash = spec.settings_hash();
+        let mut dark = ColoringSettings::default();
+        dark.gamma *= 2.0;
+        let different = [
+            (
+                "one more iteration",
+                ExportSpec {
+                    max_iterations: spec.max_iterations + 1,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "another width",
+                ExportSpec {
+                    width: spec.width + 1,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "antialiasing",
+                ExportSpec {
+                    antialias: 2,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "another coloring",
+                ExportSpec {
+                    coloring: dark,
+                    ..spec.clone()
+                },
+            ),
+            (
+                "a center an ulp away",
+                ExportSpec {
+                    viewport: Viewport {
+                        center_re: spec.viewport.center_re.next_up(),
+                        ..spec.viewport
+                    },
+                    ..spec.clone()
+                },
+            ),
+            (
+                "another fractal",
+                ExportSpec {
+                    fractal: FractalKind::BurningShip,
+                    ..spec.clone()
+                },
+            ),
+        ];
+        for (what, other) in different {
+            assert_ne!(
+                other.settings_hash(),
+                hash,
+                "an export with {} hashes the same",
+                what
+            );
+        }
+        // Past f64's resolution double-double changes the image.
+        let deep_f64 = ExportSpec {
+            viewport: Viewport {
+                width: 1e-20,
+                ..spec.viewport
+            },
+            ..spec.clone()
+        };
+        let deep_dd = ExportSpec {
+            double_double: true,
+            ..deep_f64.clone()
+        };
+        assert_ne!(deep_f64.settings_hash(), deep_dd.settings_hash());
+    }
+
+    #[test]
+    fn batches_are_summed_up_in_one_line() {
+        let summary = BatchSummary {
+            rendered: 2,
+            failed: 1,
+            deduplicated: vec![(PathBuf::from("b.png"), PathBuf::from("a.png"))],
+        };
+        assert_eq!(
+            summary.report(),
+            "exports done: 2 rendered, 1 duplicates reused (b.png from a.png), 1 failed"
+        );
+        assert!(!summary.is_empty());
+        assert!(BatchSummary::default().is_empty());
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::fractal::{AngleKind, FractalKind, Hybrid, Rule};
+use crate::render::{Arithmetic, FrameParams, IterationBuffer};
+use crate::storage::{Channel, Precision};
+use crate::viewport::Viewport;
+
+pub const EXTENSION: &str = "mbit";
+
+const MAGIC: &[u8; 4] = b"MBIT";
+const VERSION: u32 = 3;
+const HAS_ANGLES: u8 = 1;
+const DOUBLE_DOUBLE: u8 = 2;
+const ESCAPE_ANGLES: u8 = 4;
+const HEADER_LEN: usize = 64;
+// Version 1 headers end before the low-order center parts. Version 2 files share the current
+// layout but only ever mark pixels that did not escape as unresolved, never as proven inside.
+const V1_HEADER_LEN: usize = 48;
+
+// Raw escape values of a rendered frame, so it can be recolored later without recomputing.
+//
+// Little-endian layout: magic "MBIT", u32 version, u32 width, u32 height, u32 max_iterations,
+// u8 fractal (index into `FractalKind::ALL`), u8 flags (bit 0: angles follow, bit 1: rendered in
+// double-double, bit 2: the angles are escape directions rather than external angles), for a
+// hybrid u8 step count and u8 steps (bit n set: step n is a Burning Ship step), otherwise 2 bytes
+// padding, f64 center_re, f64 center_im, f64 view width, f64 center_re_lo,
+// f64 center_im_lo, then width * height f32 values and, if flagged, as many f32 angles. Values
+// below zero are the `PixelClass` markers. Version 1 files lack the two low-order center parts
+// and are still read, as are version 2 files.
+pub fn write(path: &Path, buffer: &IterationBuffer) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = File::create(path).map_err(|err| err.to_string())?;
+    let mut out = BufWriter::new(file);
+    write_to(&mut out, buffer)?;
+    out.flush().map_err(|err| err.to_string())
+}
+
+// The bytes of a data file holding `buffer`, as `write` would save them.
+pub fn to_bytes(buffer: &IterationBuffer) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    write_to(&mut bytes, buffer)?;
+    Ok(bytes)
+}
+
+fn write_to(out: &mut impl Write, buffer: &IterationBuffer) -> Result<(), String> {
+    let params = buffer
+        .params
+        .ok_or_else(|| String::from("frame has no render parameters"))?;
+    let fractal = FractalKind::ALL
+        .iter()
+        .position(|kind| *kind == params.fractal.family())
+        .unwrap_or(0) as u8;
+    let steps = match params.fractal {
+        FractalKind::Hybrid(hybrid) => [
+            hybrid.steps().len() as u8,
+            hybrid
+                .steps()
+                .iter()
+                .enumerate()
+                .filter(|(_, rule)| **rule == Rule::BurningShip)
+                .fold(0, |bits, (n, _)| bits | 1 << n),
+        ],
+        _ => [0, 0],
+    };
+    let has_angles = !buffer.angles.is_empty();
+    let mut flags = if has_angles { HAS_ANGLES } else { 0 };
+    if params.arithmetic == Arithmetic::DoubleDouble {
+        flags |= DOUBLE_DOUBLE;
+    }
+    if has_angles && params.angle == AngleKind::Escape {
+        flags |= ESCAPE_ANGLES;
+    }
+
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&VERSION.to_le_bytes());
+    header.extend_from_slice(&(buffer.width as u32).to_le_bytes());
+    header.extend_from_slice(&(buffer.height as u32).to_le_bytes());
+    header.extend_from_slice(&params.max_iterations.to_le_bytes());
+    header.extend_from_slice(&[fractal, flags, steps[0], steps[1]]);
+    header.extend_from_slice(&params.viewport.center_re.to_le_bytes());
+    header.extend_from_slice(&params.viewport.center_im.to_le_bytes());
+    header.extend_from_slice(&params.viewport.width.to_le_bytes());
+    header.extend_from_slice(&params.viewport.center_re_lo.to_le_bytes());
+    header.extend_from_slice(&params.viewport.center_im_lo.to_le_bytes());
+    out.write_all(&header).map_err(|err| err.to_string())?;
+
+    let mut write_channel = |channel: &Channel| {
+        (0..channel.len()).try_for_each(|index| out.write_all(&channel.get(index).to_le_bytes()))
+    };
+    write_channel(&buffer.values).map_err(|err| err.to_string())?;
+    if has_angles {
+        write_channel(&buffer.angles).map_err(|err| err.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn read(path: &Path) -> Result<IterationBuffer, String> {
+    let bytes = fs::read(path).map_err(|err| err.to_string())?;
+    parse(&bytes)
+}
+
+pub fn parse(bytes: &[u8]) -> Result<IterationBuffer, String> {
+    if bytes.len() < V1_HEADER_LEN || &bytes[..4] != MAGIC {
+        return Err(String::from("not a mandelbrot data file"));
+    }
+    let u32_at = |offset: usize| u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+    let f64_at = |offset: usize| f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap());
+    let version = u32_at(4);
+    let header_len = match version {
+        1 => V1_HEADER_LEN,
+        2 | VERSION => HEADER_LEN,
+        _ => return Err(format!("unsupported data file version {}", version)),
+    };
+    if bytes.len() < header_len {
+        return Err(String::from("truncated header"));
+    }
+    let width = u32_at(8) as usize;
+    let height = u32_at(12) as usize;
+    let max_iterations = u32_at(16);
+    let fractal = match *FractalKind::ALL
+        .get(bytes[20] as usize)
+        .ok_or_else(|| format!("unknown fractal type {}", bytes[20]))?
+    {
+        FractalKind::Hybrid(_) => {
+            let rules: Vec<Rule> = (0..bytes[22])
+                .map(|n| match bytes[23].checked_shr(n as u32).unwrap_or(0) & 1 {
+                    0 => Rule::Mandelbrot,
+                    _ => Rule::BurningShip,
+                })
+                .collect();
+            FractalKind::Hybrid(Hybrid::new(&rules)?)
+        }
+        kind => kind,
+    };
+    let has_angles = bytes[21] & HAS_ANGLES != 0;
+    let angle = match (has_angles, bytes[21] & ESCAPE_ANGLES != 0) {
+        (false, _) => AngleKind::Off,
+        (true, false) => AngleKind::External,
+        (true, true) => AngleKind::Escape,
+    };
+    let arithmetic = if bytes[21] & DOUBLE_DOUBLE != 0 {
+        Arithmetic::DoubleDouble
+    } else {
+        Arithmetic::F64
+    };
+    let mut viewport = Viewport::new(f64_at(24), f64_at(32), f64_at(40));
+    if version >= 2 {
+        viewport.center_re_lo = f64_at(48);
+        viewport.center_im_lo = f64_at(56);
+    }
+    if width == 0 || height == 0 {
+        return Err(format!("empty {}x{} frame", width, height));
+    }
+    let finite = viewport.center_re.is_finite()
+        && viewport.center_im.is_finite()
+        && viewport.center_re_lo.is_finite()
+        && viewport.center_im_lo.is_finite()
+        && viewport.width.is_finite();
+    if !finite || viewport.width <= 0.0 {
+        return Err(String::from("corrupt viewport in header"));
+    }
+
+    let pixels = width
+        .checked_mul(height)
+        .ok_or_else(|| format!("implausible {}x{} frame", width, height))?;
+    let channels = 1 + has_angles as usize;
+    let expected = pixels
+        .checked_mul(4 * channels)
+        .and_then(|len| len.checked_add(header_len))
+        .ok_or_else(|| format!("implausible {}x{} frame", width, height))?;
+    if bytes.len() != expected {
+        return Err(format!(
+            "truncated or corrupt: a {}x{} frame needs {} bytes but the file has {}",
+            width,
+            height,
+            expected,
+            bytes.len()
+        ));
+    }
+
+    let channel = |start: usize| {
+        Channel::Full(
+            bytes[start..start + pixels * 4]
+                .chunks_exact(4)
+                .map(|value| f32::from_le_bytes(value.try_into().unwrap()))
+                .collect(),
+        )
+    };
+    Ok(IterationBuffer {
+        width,
+        height,
+        values: channel(header_len),
+        angles: if has_angles {
+            channel(header_len + pixels * 4)
+        } else {
+            Channel::default()
+        },
+        params: Some(FrameParams {
+            viewport,
+            max_iterations,
+            fractal,
+            angle,
+            precision: Precision::Full,
+            coarse_prepass: false,
+            arithmetic,
+        }),
+        costs: None,
+        failure: None,
+    })
+}
@@ -0,0 +1,208 @@
+use num::Complex;
+
+use crate::doubledouble::DoubleDouble;
+use crate::fractal::{self, AngleKind, FractalKind};
+
+// Escape iterations of known points, from published tables and orbits worked out independently
+// of this code; see the file for where each comes from. The tests below iterate every one.
+const DATASET: &str = include_str!("reference_escapes.txt");
+
+// One point of the dataset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferenceCase {
+    // Line in the dataset, for reporting.
+    pub line: usize,
+    pub fractal: FractalKind,
+    pub re: f64,
+    pub im: f64,
+    pub max_iterations: u32,
+    // The escape iteration, counted like a sample's value, or None for an interior point.
+    pub expected: Option<u32>,
+    // How many iterations f64 may be off by, or None where it loses the orbit altogether.
+    pub f64_tolerance: Option<u32>,
+}
+
+impl ReferenceCase {
+    // Whether `value`, a sample's value, is within `tolerance` iterations of the expected one.
+    pub fn agrees(&self, value: f32, tolerance: u32) -> bool {
+        match self.expected {
+            None => value < 0.0,
+            Some(expected) => value >= 0.0 && (value - expected as f32).abs() <= tolerance as f32,
+        }
+    }
+}
+
+// The embedded dataset.
+pub fn cases() -> Result<Vec<ReferenceCase>, String> {
+    parse(DATASET)
+}
+
+// Reads cases from lines of "fractal re im max_iterations expected tolerance", where expected may
+// be "interior" and tolerance "*". '#' starts a comment.
+pub fn parse(text: &str) -> Result<Vec<ReferenceCase>, String> {
+    let mut cases = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let fields: Vec<&str> = line
+            .split('#')
+            .next()
+            .unwrap_or_default()
+            .split_whitespace()
+            .collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let case = parse_case(&fields, line_number)
+            .map_err(|err| format!("line {}: {}", line_number, err))?;
+        cases.push(case);
+    }
+    Ok(cases)
+}
+
+fn parse_case(fields: &[&str], line: usize) -> Result<ReferenceCase, String> {
+    let [fractal, re, im, max_iterations, expected, tolerance] = fields[..] else {
+        return Err(format!("expected 6 fields, found {}", fields.len()));
+    };
+    let fractal = match fractal {
+        "mandelbrot" => FractalKind::Mandelbrot,
+        "burning-ship" => FractalKind::BurningShip,
+        _ => return Err(format!("unknown fractal \"{}\"", fractal)),
+    };
+    let number = |field: &str, name: &str| {
+        field
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| format!("invalid {} \"{}\"", name, field))
+    };
+    let count = |field: &str, name: &str| {
+        field
+            .parse::<u32>()
+            .map_err(|_| format!("invalid {} \"{}\"", name, field))
+    };
+    let max_iterations = count(max_iterations, "iteration limit")?;
+    let expected = match expected {
+        "interior" => None,
+        expected => Some(count(expected, "escape iteration")?),
+    };
+    if expected.is_some_and(|expected| expected >= max_iterations) {
+        return Err(String::from("escape iteration is past the iteration limit"));
+    }
+    Ok(ReferenceCase {
+        line,
+        fractal,
+        re: number(re, "real part")?,
+        im: number(im, "imaginary part")?,
+        max_iterations,
+        expected,
+        f64_tolerance: match tolerance {
+            "*" => None,
+            tolerance => Some(count(tolerance, "tolerance")?),
+        },
+    })
+}
+
+// Every case iterated by the tuned f64 loop, the plain one and double-double. Double-double has
+// to match exactly, f64 within the case's tolerance.
+#[test]
+fn every_arithmetic_escapes_as_the_dataset_says() {
+    let cases = cases().expect("the embedded dataset parses");
+    assert!(!cases.is_empty());
+    for case in cases {
+        let fractal = case.fractal.fractal();
+        let c = Complex::new(case.re, case.im);
+        let dd = (DoubleDouble::from(case.re), DoubleDouble::from(case.im));
+        let results = [
+            (
+                "f64",
+                fractal.iterate(c, case.max_iterations, AngleKind::Off),
+                case.f64_tolerance,
+            ),
+            (
+                "plain f64",
+                fractal::reference_iterate(case.fractal, c, case.max_iterations, AngleKind::Off),
+                case.f64_tolerance,
+            ),
+            (
+                "double-double",
+                fractal.iterate_dd(dd, case.max_iterations, AngleKind::Off),
+                Some(0),
+            ),
+        ];
+        for (arithmetic, sample, tolerance) in results {
+            let Some(tolerance) = tolerance else {
+                continue;
+            };
+            assert!(
+                case.agrees(sample.value, tolerance),
+                "line {}: {} gives {} for {} {}, expected {}",
+                case.line,
+                arithmetic,
+                sample.value,
+                case.re,
+                case.im,
+                case.expected
+                    .map_or_else(|| String::from("interior"), |n| n.to_string())
+            );
+        }
+    }
+}
+
+#[test]
+fn cases_read_their_fields_and_skip_comments() {
+    let cases = parse(
+        "# a comment\n\
+         \n\
+         mandelbrot -0.75 0.1 500 interior * # after a case\n\
+         burning-ship 1e-3 -2 200 7 1\n",
+    )
+    .expect("the cases parse");
+    assert_eq!(
+        cases,
+        [
+            ReferenceCase {
+                line: 3,
+                fractal: FractalKind::Mandelbrot,
+                re: -0.75,
+                im: 0.1,
+                max_iterations: 500,
+                expected: None,
+                f64_tolerance: None,
+            },
+            ReferenceCase {
+                line: 4,
+                fractal: FractalKind::BurningShip,
+                re: 1e-3,
+                im: -2.0,
+                max_iterations: 200,
+                expected: Some(7),
+                f64_tolerance: Some(1),
+            },
+        ]
+    );
+}
+
+#[test]
+fn malformed_cases_name_their_line() {
+    for (text, error) in [
+        (
+            "mandelbrot 0 0 100 interior",
+            "line 1: expected 6 fields, found 5",
+        ),
+        ("\njulia 0 0 100 5 0", "line 2: unknown fractal \"julia\""),
+        (
+            "mandelbrot 0 nan 100 5 0",
+            "line 1: invalid imaginary part \"nan\"",
+        ),
+        (
+            "mandelbrot 0 0 100 100 0",
+            "line 1: escape iteration is past the iteration limit",
+        ),
+        (
+            "mandelbrot 0 0 100 5 -1",
+            "line 1: invalid tolerance \"-1\"",
+        ),
+    ] {
+        assert_eq!(parse(text), Err(String::from(error)), "parsing {:?}", text);
+    }
+}
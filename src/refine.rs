@@ -0,0 +1,37 @@
+use crate::render::{IterationBuffer, UNRESOLVED};
+
+// Idle refinement raises the iteration limit up to this multiple of the profile's.
+pub const MAX_ITERATION_SCALE: u32 = 4;
+// And supersampling up to this factor per axis.
+pub const MAX_ANTIALIAS: usize = 3;
+
+// One improvement to the installed frame, applied while the app is idle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RefineStep {
+    // Re-iterate the pixels still unresolved up to this many iterations.
+    Iterations(u32),
+    // Re-render the frame supersampled this many times per axis.
+    Antialias(usize),
+}
+
+// The next step for a frame rendered at `antialias` from a profile asking for `base_iterations`,
+// or None once the frame has reached the quality cap. Unresolved pixels are extended first since
+// that is the cheaper step and the one that changes the picture most.
+pub fn next_step(
+    buffer: &IterationBuffer,
+    antialias: usize,
+    base_iterations: u32,
+) -> Option<RefineStep> {
+    let params = buffer.params?;
+    let ceiling = base_iterations.saturating_mul(MAX_ITERATION_SCALE);
+    if params.max_iterations < ceiling {
+        let unresolved =
+            (0..buffer.values.len()).any(|index| buffer.values.get(index) == UNRESOLVED);
+        if unresolved {
+            return Some(RefineStep::Iterations(
+                params.max_iterations.saturating_mul(2).min(ceiling),
+            ));
+        }
+    }
+    (antialias < MAX_ANTIALIAS).then_some(RefineStep::Antialias(antialias + 1))
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+
+use crate::minibrot::Candidate;
+use crate::viewport::Viewport;
+
+// Views described relative to a minibrot, so that a view of some feature beside one minibrot can
+// be carried over to the same feature beside another. Copies of the set differ in size and in
+// orientation; views do not rotate, so only the size is made up for.
+
+// A minibrot views are taken relative to: its nucleus, its size in the plane and its period, when
+// known.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub re: f64,
+    pub im: f64,
+    pub size: f64,
+    #[serde(default)]
+    pub period: Option<u32>,
+}
+
+impl Anchor {
+    pub fn of(candidate: &Candidate) -> Anchor {
+        Anchor {
+            re: candidate.re,
+            im: candidate.im,
+            size: candidate.size,
+            period: candidate.period,
+        }
+    }
+
+    // "period 4 minibrot" or, for one located without a period, just "minibrot".
+    pub fn describe(&self) -> String {
+        match self.period {
+            Some(period) => format!("period {} minibrot", period),
+            None => String::from("minibrot"),
+        }
+    }
+}
+
+// A view's center as an offset from its anchor's nucleus, and its width, in units of the
+// anchor's size; with the anchor it was taken at.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RelativeView {
+    pub anchor: Anchor,
+    pub offset_re: f64,
+    pub offset_im: f64,
+    pub width: f64,
+}
+
+impl RelativeView {
+    pub fn capture(viewport: &Viewport, anchor: Anchor) -> RelativeView {
+        let nucleus = Viewport::new(anchor.re, anchor.im, viewport.width);
+        let (re, im) = viewport.center_delta(&nucleus);
+        RelativeView {
+            anchor,
+            offset_re: re / anchor.size,
+            offset_im: im / anchor.size,
+            width: viewport.width / anchor.size,
+        }
+    }
+
+    // The analogous view at `anchor`: the offset and width scaled by the ratio of the two
+    // minibrots' sizes.
+    pub fn apply(&self, anchor: &Anchor) -> Viewport {
+        let width = self.width * anchor.size;
+        Viewport::new(anchor.re, anchor.im, width).shifted(
+            self.offset_re * anchor.size,
+            self.offset_im * anchor.size,
+            width,
+        )
+    }
+}
+
+// How many times larger the minibrot at `to` is than the one at `from`.
+pub fn size_ratio(from: &Anchor, to: &Anchor) -> f64 {
+    to.size / from.size
+}
+
+#[cfg(test)]
+mod tests {
+    use num::complex::Complex;
+
+    use super::*;
+    use crate::minibrot;
+
+    // The period 3, 4 and 5 minibrots on the real axis.
+    fn anchors() -> [Anchor; 3] {
+        [(-1.7549, 3), (-1.9408, 4), (-1.9854, 5)].map(|(re, period)| {
+            let found = minibrot::locate(re, 0.0, 1000, 1e-9);
+            let candidate = found
+                .filter(|candidate| candidate.period == Some(period))
+                .unwrap_or_else(|| panic!("no minibrot of period {} at {}", period, re));
+            Anchor::of(&candidate)
+        })
+    }
+
+    #[test]
+    fn views_apply_where_they_were_taken_unchanged() {
+        let [three, ..] = anchors();
+        let beside = Viewport::new(three.re + 0.02, three.im + 0.01, 0.05);
+        let same = RelativeView::capture(&beside, three).apply(&three);
+        let (re, im) = same.center_delta(&beside);
+        assert!(
+            re.abs() <= 1e-15
+                && im.abs() <= 1e-15
+                && (same.width / beside.width - 1.0).abs() <= 1e-12,
+            "a relative view applied where it was taken gives {:?}, not {:?}",
+            same,
+            beside
+        );
+    }
+
+    #[test]
+    fn views_carry_over_scaled_by_the_minibrots_sizes() {
+        let [three, four, _] = anchors();
+        let ratio = size_ratio(&three, &four);
+        assert!(
+            (0.05..0.055).contains(&ratio),
+            "the period 4 minibrot is {} times the size of the period 3 one",
+            ratio
+        );
+        let beside = Viewport::new(three.re + 0.02, three.im + 0.01, 0.05);
+        let moved = RelativeView::capture(&beside, three).apply(&four);
+        let (re, im) = moved.center_delta(&Viewport::new(four.re, four.im, 0.0));
+        let scaled = |value: f64, expected: f64| (value / expected - 1.0).abs() < 1e-9;
+        assert!(
+            scaled(moved.width, 0.05 * ratio)
+                && scaled(re, 0.02 * ratio)
+                && scaled(im, 0.01 * ratio),
+            "a view 0.02 + 0.01i off the period 3 nucleus, 0.05 wide, applies at period 4 as \
+             {:?}, offset {} + {}i",
+            moved,
+            re,
+            im
+        );
+    }
+
+    // Points at the same place relative to each minibrot, in units of its size, escape alike.
+    #[test]
+    fn minibrots_look_alike_at_their_own_scale() {
+        let escapes = |anchor: &Anchor, u: f64, v: f64| {
+            let c = Complex::new(anchor.re + u * anchor.size, anchor.im + v * anchor.size);
+            let mut z = Complex::new(0.0, 0.0);
+            (0..3000).any(|_| {
+                z = z * z + c;
+                z.norm_sqr() > 4.0
+            })
+        };
+        let [three, others @ ..] = anchors();
+        for other in &others {
+            let (mut agree, mut interior, mut total) = (0, 0, 0);
+            for y in -10..=10 {
+                for x in -10..=10 {
+                    let (u, v) = (x as f64 / 2.5, y as f64 / 2.5);
+                    let escaped = escapes(&three, u, v);
+                    agree += (escaped == escapes(other, u, v)) as usize;
+                    interior += !escaped as usize;
+                    total += 1;
+                }
+            }
+            assert!(
+                agree * 20 >= total * 19 && interior > 0 && interior < total,
+                "relative to the period 3 and {} minibrots, {} of {} points agree ({} inside)",
+                other.describe(),
+                agree,
+                total,
+                interior
+            );
+        }
+    }
+}
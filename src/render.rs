@@ -0,0 +1,1568 @@
+use iced::Size;
+
+use num::complex::Complex;
+
+use std::any::Any;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use crate::buffers;
+use crate::doubledouble::{self, DoubleDouble};
+use crate::duty;
+use crate::fractal::{AngleKind, Fractal, FractalKind};
+use crate::sampling::Sampling;
+use crate::storage::{Channel, Precision};
+use crate::tilecache::{TileKey, TILE_CACHE};
+use crate::tiling::{self, CostMap, Focus, TileQueue};
+use crate::viewport::Viewport;
+
+// Values below zero mark pixels that did not escape: `UNRESOLVED` when the iteration limit ran
+// out first, `FAILED` when the worker computing them panicked, the others when the point was
+// proven to be inside the set, each naming the proof.
+pub const UNRESOLVED: f32 = -1.0;
+const INSIDE_CARDIOID: f32 = -2.0;
+const INSIDE_BULB: f32 = -3.0;
+const INSIDE_PERIODIC: f32 = -4.0;
+const FAILED: f32 = -5.0;
+
+// How a point was shown to be inside the set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Proof {
+    // It lies in the main cardioid.
+    Cardioid,
+    // It lies in the period-2 bulb to the cardioid's left.
+    Bulb,
+    // Its orbit came back exactly to a point it had already visited, so it cycles forever.
+    Periodic,
+}
+
+impl Proof {
+    pub const ALL: [Proof; 3] = [Proof::Cardioid, Proof::Bulb, Proof::Periodic];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Proof::Cardioid => "cardioid",
+            Proof::Bulb => "bulb",
+            Proof::Periodic => "periodic",
+        }
+    }
+}
+
+// What a pixel's value says about its point.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PixelClass {
+    // It escaped, with this escape count.
+    Escaped(f32),
+    Interior(Proof),
+    // It had not escaped by the iteration limit; more iterations may yet see it escape.
+    Unresolved,
+    // Its tile's worker panicked, so nothing is known about it.
+    Failed,
+}
+
+impl PixelClass {
+    pub fn of(value: f32) -> PixelClass {
+        if value >= 0.0 {
+            PixelClass::Escaped(value)
+        } else if value == INSIDE_CARDIOID {
+            PixelClass::Interior(Proof::Cardioid)
+        } else if value == INSIDE_BULB {
+            PixelClass::Interior(Proof::Bulb)
+        } else if value == INSIDE_PERIODIC {
+            PixelClass::Interior(Proof::Periodic)
+        } else if value == FAILED {
+            PixelClass::Failed
+        } else {
+            PixelClass::Unresolved
+        }
+    }
+
+    pub fn value(self) -> f32 {
+        match self {
+            PixelClass::Escaped(value) => value,
+            PixelClass::Interior(Proof::Cardioid) => INSIDE_CARDIOID,
+            PixelClass::Interior(Proof::Bulb) => INSIDE_BULB,
+            PixelClass::Interior(Proof::Periodic) => INSIDE_PERIODIC,
+            PixelClass::Unresolved => UNRESOLVED,
+            PixelClass::Failed => FAILED,
+        }
+    }
+}
+
+// Whether `value` is an escape count rather than one of the markers for points that did not
+// escape.
+pub fn escaped(value: f32) -> bool {
+    value >= 0.0
+}
+
+// How many pixels of a frame fall in each class.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ClassCounts {
+    pub escaped: usize,
+    pub interior: usize,
+    pub unresolved: usize,
+    pub failed: usize,
+}
+
+impl ClassCounts {
+    pub fn of(buffer: &IterationBuffer) -> ClassCounts {
+        let mut counts = ClassCounts::default();
+        for index in 0..buffer.values.len() {
+            match PixelClass::of(buffer.values.get(index)) {
+                PixelClass::Escaped(_) => counts.escaped += 1,
+                PixelClass::Interior(_) => counts.interior += 1,
+                PixelClass::Unresolved => counts.unresolved += 1,
+                PixelClass::Failed => counts.failed += 1,
+            }
+        }
+        counts
+    }
+}
+
+// Side of the blocks the coarse pre-pass probes, in pixels.
+const PREPASS_BLOCK: usize = 16;
+// A block is filled without iterating when its whole edge escapes within this many iterations.
+const PREPASS_ITERATIONS: u32 = 4;
+// How often paused workers check whether to carry on.
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+// Depth of the probes that estimate how much detail a tile holds, for scheduling.
+const IMPORTANCE_ITERATIONS: u32 = 64;
+// Pixels closer together than this many units of an arithmetic's precision at the center are
+// no longer trusted to come out distinct.
+pub const MIN_PIXEL_ULPS: f64 = 16.0;
+
+// Number type the escape loop runs in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Arithmetic {
+    #[default]
+    F64,
+    // About twice f64's mantissa at several times the cost, reaching view widths near 1e-28.
+    DoubleDouble,
+}
+
+impl Arithmetic {
+    pub fn name(self) -> &'static str {
+        match self {
+            Arithmetic::F64 => "f64",
+            Arithmetic::DoubleDouble => "double-double",
+        }
+    }
+
+    // Relative precision of one value.
+    pub fn epsilon(self) -> f64 {
+        match self {
+            Arithmetic::F64 => f64::EPSILON,
+            Arithmetic::DoubleDouble => doubledouble::EPSILON,
+        }
+    }
+
+    // Whether neighbouring pixels of `viewport` at `size` stay distinct in this arithmetic.
+    pub fn resolves(self, viewport: &Viewport, size: Size, min_pixel_ulps: f64) -> bool {
+        viewport.pixel_size(size) >= viewport.magnitude() * self.epsilon() * min_pixel_ulps
+    }
+
+    // f64 wherever it is enough, double-double beyond that if allowed.
+    pub fn select(viewport: &Viewport, size: Size, allow_double_double: bool) -> Arithmetic {
+        if allow_double_double && !Arithmetic::F64.resolves(viewport, size, MIN_PIXEL_ULPS) {
+            Arithmetic::DoubleDouble
+        } else {
+            Arithmetic::F64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FrameParams {
+    pub viewport: Viewport,
+    pub max_iterations: u32,
+    pub fractal: FractalKind,
+    // What the angle channel holds; it is left out when off.
+    pub angle: AngleKind,
+    pub precision: Precision,
+    // Fill blocks far outside the set from a few probes instead of iterating every pixel.
+    pub coarse_prepass: bool,
+    pub arithmetic: Arithmetic,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct IterationBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub values: Channel,
+    // Angles in turns, of the kind `params.angle` says; empty when that is off.
+    pub angles: Channel,
+    pub params: Option<FrameParams>,
+    // Where the render spent its time, for sizing the next render's tiles.
+    pub costs: Option<Arc<CostMap>>,
+    // What the first worker to panic on this frame said; its tile's pixels are `FAILED`.
+    pub failure: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PixelRect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl PixelRect {
+    pub fn area(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn intersection(&self, other: &PixelRect) -> Option<PixelRect> {
+        let x = self.x.max(other.x);
+        let y = self.y.max(other.y);
+        let right = (self.x + self.width).min(other.x + other.width);
+        let bottom = (self.y + self.height).min(other.y + other.height);
+        (x < right && y < bottom).then(|| PixelRect {
+            x,
+            y,
+            width: right - x,
+            height: bottom - y,
+        })
+    }
+
+    // What is left of this rect outside `other`: the full-width strips above and below it, then
+    // the pieces to its left and right, leaving out empty ones.
+    pub fn subtract(&self, other: &PixelRect) -> Vec<PixelRect> {
+        let Some(inner) = self.intersection(other) else {
+            return vec![*self];
+        };
+        let (right, bottom) = (self.x + self.width, self.y + self.height);
+        [
+            PixelRect {
+                x: self.x,
+                y: self.y,
+                width: self.width,
+                height: inner.y - self.y,
+            },
+            PixelRect {
+                x: self.x,
+                y: inner.y + inner.height,
+                width: self.width,
+                height: bottom - inner.y - inner.height,
+            },
+            PixelRect {
+                x: self.x,
+                y: inner.y,
+                width: inner.x - self.x,
+                height: inner.height,
+            },
+            PixelRect {
+                x: inner.x + inner.width,
+                y: inner.y,
+                width: right - inner.x - inner.width,
+                height: inner.height,
+            },
+        ]
+        .into_iter()
+        .filter(|rect| rect.area() > 0)
+        .collect()
+    }
+}
+
+// Shared switches that stop or pause a render between rows; clones observe the same switches.
+// Skipping cancels only the job running now, for batches that go on to the next one.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<Switches>);
+
+#[derive(Debug, Default)]
+struct Switches {
+    cancelled: AtomicBool,
+    skipped: AtomicBool,
+    paused: AtomicBool,
+    // When each worker that took part last finished a row.
+    beats: Mutex<Vec<(ThreadId, Instant)>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.cancelled.load(Ordering::Relaxed) || self.0.skipped.load(Ordering::Relaxed)
+    }
+
+    pub fn skip(&self) {
+        self.0.skipped.store(true, Ordering::Relaxed);
+    }
+
+    // Whether the last job was skipped rather than the whole batch cancelled; clears the skip so
+    // the next job can run.
+    pub fn take_skip(&self) -> bool {
+        self.0.skipped.swap(false, Ordering::Relaxed) && !self.0.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.0.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.0.paused.load(Ordering::Relaxed)
+    }
+
+    // Blocks a worker for as long as the render is paused and not cancelled.
+    fn wait_while_paused(&self) {
+        while self.is_paused() && !self.is_cancelled() {
+            thread::sleep(PAUSE_POLL);
+        }
+    }
+
+    // Rests the calling worker after `worked` of work for as long as the CPU cap asks, waking
+    // early if the render is cancelled.
+    fn keep_to_cap(&self, worked: Duration) {
+        let mut owed = duty::owed(worked);
+        while !owed.is_zero() && !self.is_cancelled() {
+            let started = Instant::now();
+            thread::sleep(owed.min(PAUSE_POLL));
+            let slept = started.elapsed();
+            duty::rested(slept);
+            owed = owed.saturating_sub(slept);
+        }
+    }
+
+    // Records that the calling worker is making progress, which tells a slow render from a
+    // stuck one.
+    fn beat(&self) {
+        let id = thread::current().id();
+        let now = Instant::now();
+        let mut beats = self.0.beats.lock().unwrap_or_else(PoisonError::into_inner);
+        match beats.iter_mut().find(|(worker, _)| *worker == id) {
+            Some((_, last)) => *last = now,
+            None => beats.push((id, now)),
+        }
+    }
+
+    // How long ago each worker that took part last made progress, in the order they joined.
+    pub fn heartbeats(&self) -> Vec<(ThreadId, Duration)> {
+        self.0
+            .beats
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .map(|(worker, last)| (*worker, last.elapsed()))
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Progress {
+    pub pixels_done: usize,
+    pub total_pixels: usize,
+    pub elapsed: Duration,
+    // What the previous frame's cost map predicted the pixels computed so far and all of them to
+    // take; zero without a map.
+    pub predicted_done: Duration,
+    pub predicted_total: Duration,
+}
+
+impl Progress {
+    pub fn fraction(&self) -> f32 {
+        if self.total_pixels == 0 {
+            1.0
+        } else {
+            self.pixels_done as f32 / self.total_pixels as f32
+        }
+    }
+}
+
+// One finished block of pixels, row-major within `rect`. `angles` is empty unless the frame
+// tracks angles.
+#[derive(Clone, Debug)]
+pub struct TileResult {
+    pub rect: PixelRect,
+    // Position in the order the render took tiles up.
+    pub order: usize,
+    pub values: Vec<f32>,
+    pub angles: Vec<f32>,
+    // How long a worker spent computing it.
+    pub cost: Duration,
+    // What the worker panicked with, in which case every value is `FAILED`.
+    pub failure: Option<String>,
+}
+
+// A tile that has just landed in the frame being built.
+pub struct TileUpdate<'a> {
+    pub tile: &'a TileResult,
+    // The frame so far, with pixels not yet computed still `UNRESOLVED`.
+    pub frame: &'a IterationBuffer,
+    pub progress: Progress,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct FrameSummary {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: usize,
+    pub failed_tiles: usize,
+    pub pixels_computed: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderError {
+    Cancelled,
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Cancelled => write!(f, "render cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl TileResult {
+    // A standalone buffer holding just this tile, for coloring or saving it on its own.
+    pub fn to_buffer(&self) -> IterationBuffer {
+        IterationBuffer {
+            width: self.rect.width,
+            height: self.rect.height,
+            values: Channel::Full(self.values.clone()),
+            angles: Channel::Full(self.angles.clone()),
+            params: None,
+            costs: None,
+            failure: self.failure.clone(),
+        }
+    }
+}
+
+impl IterationBuffer {
+    pub fn bytes(&self) -> usize {
+        self.values.bytes() + self.angles.bytes()
+    }
+
+    // A copy in pooled memory, for previews of a frame still being built.
+    pub fn pooled_copy(&self) -> IterationBuffer {
+        IterationBuffer {
+            width: self.width,
+            height: self.height,
+            values: self.values.pooled_copy(),
+            angles: self.angles.pooled_copy(),
+            params: self.params,
+            costs: self.costs.clone(),
+            failure: self.failure.clone(),
+        }
+    }
+
+    // Hands the frame's memory back to the pool once nothing else holds it.
+    pub fn recycle(frame: Arc<IterationBuffer>) {
+        if let Some(frame) = Arc::into_inner(frame) {
+            frame.values.recycle();
+            frame.angles.recycle();
+        }
+    }
+
+    // Copies of the values and angles in `rect`, row-major.
+    fn cut(&self, rect: PixelRect) -> (Vec<f32>, Vec<f32>) {
+        let copy = |channel: &Channel| {
+            if channel.is_empty() {
+                return Vec::new();
+            }
+            (rect.y..rect.y + rect.height)
+                .flat_map(|y| {
+                    let row = y * self.width;
+                    (rect.x..rect.x + rect.width).map(move |x| channel.get(row + x))
+                })
+                .collect()
+        };
+        (copy(&self.values), copy(&self.angles))
+    }
+
+    // Writes a tile into a buffer that is still at full precision.
+    pub fn insert_tile(&mut self, tile: &TileResult) {
+        if self.failure.is_none() {
+            self.failure.clone_from(&tile.failure);
+        }
+        let width = self.width;
+        let rect = tile.rect;
+        let values = self.values.full_mut();
+        for (row, samples) in tile.values.chunks(rect.width).enumerate() {
+            let offset = (rect.y + row) * width + rect.x;
+            values[offset..offset + rect.width].copy_from_slice(samples);
+        }
+        if tile.angles.is_empty() {
+            return;
+        }
+        let angles = self.angles.full_mut();
+        for (row, samples) in tile.angles.chunks(rect.width).enumerate() {
+            let offset = (rect.y + row) * width + rect.x;
+            angles[offset..offset + rect.width].copy_from_slice(samples);
+        }
+    }
+
+    // Copies the `known` pieces of `previous`, a frame on the same pixel grid, into this frame
+    // where they fall inside `missing`, and takes what was copied out of `missing`. Returns the
+    // pieces copied, in this frame's pixels; none if the grids differ.
+    pub fn splice(
+        &mut self,
+        previous: &IterationBuffer,
+        known: &[PixelRect],
+        missing: &mut Vec<PixelRect>,
+    ) -> Vec<PixelRect> {
+        let Some(params) = self.params else {
+            return Vec::new();
+        };
+        let bounds = Size::new(self.width as f32, self.height as f32);
+        let Some((dx, dy)) = previous.pixel_offset(bounds, params) else {
+            return Vec::new();
+        };
+        let frame = full_rect(bounds);
+        let mut copied = Vec::new();
+        for rect in known {
+            // Where the piece lands here, cut to this frame.
+            let x0 = (rect.x as i64 + dx).clamp(0, self.width as i64) as usize;
+            let y0 = (rect.y as i64 + dy).clamp(0, self.height as i64) as usize;
+            let x1 = (rect.x as i64 + rect.width as i64 + dx).clamp(0, self.width as i64) as usize;
+            let y1 =
+                (rect.y as i64 + rect.height as i64 + dy).clamp(0, self.height as i64) as usize;
+            let Some(target) = frame.intersection(&PixelRect {
+                x: x0,
+                y: y0,
+                width: x1.saturating_sub(x0),
+                height: y1.saturating_sub(y0),
+            }) else {
+                continue;
+            };
+            let pieces: Vec<PixelRect> = missing
+                .iter()
+                .filter_map(|gap| gap.intersection(&target))
+                .collect();
+            for piece in &pieces {
+                self.copy_from(previous, *piece, (dx, dy));
+            }
+            *missing = missing
+                .iter()
+                .flat_map(|gap| gap.subtract(&target))
+                .collect();
+            copied.extend(pieces);
+        }
+        copied
+    }
+
+    // Copies `rect` of this frame from `previous`, whose pixels appear here shifted by `offset`.
+    fn copy_from(&mut self, previous: &IterationBuffer, rect: PixelRect, (dx, dy): (i64, i64)) {
+        let width = self.width;
+        let source = |x: usize, y: usize| {
+            (y as i64 - dy) as usize * previous.width + (x as i64 - dx) as usize
+        };
+        let values = self.values.full_mut();
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                values[y * width + x] = previous.values.get(source(x, y));
+            }
+        }
+        if self.angles.is_empty() || previous.angles.is_empty() {
+            return;
+        }
+        let angles = self.angles.full_mut();
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                angles[y * width + x] = previous.angles.get(source(x, y));
+            }
+        }
+    }
+
+    fn compact(&mut self) {
+        let Some(params) = self.params else {
+            return;
+        };
+        let range = (0.0, params.max_iterations as f32);
+        self.values = std::mem::take(&mut self.values).compact(params.precision, range);
+        self.angles = std::mem::take(&mut self.angles).compact_angles(params.precision);
+    }
+
+    // Integer pixel offset at which this buffer's pixels appear in a render of `viewport`, if the
+    // two share a pixel grid.
+    fn pixel_offset(&self, bounds: Size, params: FrameParams) -> Option<(i64, i64)> {
+        let previous_params = self.params?;
+        let same_settings = FrameParams {
+            viewport: params.viewport,
+            ..previous_params
+        } == params;
+        // Failed pixels are computed again rather than carried over.
+        if self.values.is_empty() || !same_settings || self.failure.is_some() {
+            return None;
+        }
+        let previous = previous_params.viewport;
+        let viewport = params.viewport;
+        let previous_size = Size::new(self.width as f32, self.height as f32);
+        let pixel_size = viewport.pixel_size(bounds);
+        if ((previous.pixel_size(previous_size) - pixel_size) / pixel_size).abs() > 1e-9 {
+            return None;
+        }
+        // Offset of the previous top-left corner from the new one, from the center difference
+        // so that it stays exact in views too narrow for f64 coordinates.
+        let (center_re, center_im) = previous.center_delta(&viewport);
+        let dx = (center_re - previous.width / 2.0 + viewport.width / 2.0) / pixel_size;
+        let dy = (viewport.height(bounds) / 2.0 - previous.height(previous_size) / 2.0 - center_im)
+            / pixel_size;
+        if (dx - dx.round()).abs() > 1e-3 || (dy - dy.round()).abs() > 1e-3 {
+            return None;
+        }
+        Some((dx.round() as i64, dy.round() as i64))
+    }
+}
+
+// Renders the whole frame, handing each tile to `on_tile` as soon as it completes; tiles near the
+// middle go first. Progress counts pixels; cancelling `cancel` stops the workers between rows and
+// returns `RenderError::Cancelled` without delivering the remaining tiles.
+pub fn render_streaming(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(Progress),
+    mut on_tile: impl FnMut(TileResult),
+) -> Result<FrameSummary, RenderError> {
+    let work = Work {
+        rects: &[full_rect(bounds)],
+        keep: None,
+        focus: Focus::default(),
+        costs: None,
+        cached: false,
+        kernel: None,
+        sampling: None,
+    };
+    stream_rects(pool, bounds, params, work, cancel, |tile, progress| {
+        on_tile(tile);
+        on_progress(progress);
+    })
+}
+
+pub fn threaded_fractal_calc(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<IterationBuffer, RenderError> {
+    render_focused(pool, bounds, params, cancel, &Focus::default(), |update| {
+        on_progress(update.progress)
+    })
+}
+
+// Renders the whole frame starting with the tiles nearest `focus`, which may move while the
+// render runs.
+pub fn render_focused(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    cancel: &CancelToken,
+    focus: &Focus,
+    on_tile: impl FnMut(TileUpdate),
+) -> Result<IterationBuffer, RenderError> {
+    let work = Work {
+        rects: &[full_rect(bounds)],
+        keep: None,
+        focus: focus.clone(),
+        costs: None,
+        cached: false,
+        kernel: None,
+        sampling: None,
+    };
+    fill(pool, empty_buffer(bounds, params), work, cancel, on_tile)
+}
+
+// Renders `viewport`, copying every pixel that `previous` already covers on the same pixel grid
+// and computing only the newly exposed strips, nearest `focus` first. Tiles are looked up in and
+// added to `TILE_CACHE`, as this is the interactive path.
+pub fn render_reusing(
+    pool: &ThreadPool,
+    previous: &IterationBuffer,
+    bounds: Size,
+    params: FrameParams,
+    cancel: &CancelToken,
+    focus: &Focus,
+    on_tile: impl FnMut(TileUpdate),
+) -> Result<IterationBuffer, RenderError> {
+    let whole = [full_rect(Size::new(
+        previous.width as f32,
+        previous.height as f32,
+    ))];
+    render_reusing_from(
+        pool,
+        &[(previous, &whole)],
+        bounds,
+        params,
+        cancel,
+        focus,
+        on_tile,
+    )
+}
+
+// `render_reusing` with several sources, each a buffer and the pieces of it that hold computed
+// values, such as the previous frame and a prefetched margin around it. Earlier sources win
+// where they overlap, and the first one's cost map plans the tiles.
+pub fn render_reusing_from(
+    pool: &ThreadPool,
+    sources: &[(&IterationBuffer, &[PixelRect])],
+    bounds: Size,
+    params: FrameParams,
+    cancel: &CancelToken,
+    focus: &Focus,
+    on_tile: impl FnMut(TileUpdate),
+) -> Result<IterationBuffer, RenderError> {
+    let costs = sources
+        .first()
+        .and_then(|(previous, _)| previous.costs.as_ref())
+        .and_then(|costs| costs.project(bounds, params));
+    let mut buffer = empty_buffer(bounds, params);
+    let mut exposed = vec![full_rect(bounds)];
+    let mut reused = 0;
+    for (previous, known) in sources {
+        let copied = buffer.splice(previous, known, &mut exposed);
+        reused += copied.iter().map(PixelRect::area).sum::<usize>();
+    }
+    if reused > 0 {
+        println!(
+            "reused {} of {} pixels",
+            reused,
+            buffer.width * buffer.height
+        );
+    }
+    let work = Work {
+        rects: &exposed,
+        keep: None,
+        focus: focus.clone(),
+        costs,
+        cached: true,
+        kernel: None,
+        sampling: None,
+    };
+    fill(pool, buffer, work, cancel, on_tile)
+}
+
+// Computes just `rects` of a `bounds` frame, handing over each tile as it completes.
+pub fn render_rects(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    rects: &[PixelRect],
+    cancel: &CancelToken,
+    mut on_tile: impl FnMut(TileResult),
+) -> Result<FrameSummary, RenderError> {
+    let work = Work {
+        rects,
+        keep: None,
+        focus: Focus::default(),
+        costs: None,
+        cached: false,
+        kernel: None,
+        sampling: None,
+    };
+    stream_rects(pool, bounds, params, work, cancel, |tile, _| on_tile(tile))
+}
+
+// Renders the whole frame with `kernel` iterating in place of `params.fractal`, for trying out
+// kernels that are not among the fractal kinds yet.
+pub fn render_kernel(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    kernel: &'static dyn Fractal,
+    cancel: &CancelToken,
+) -> Result<IterationBuffer, RenderError> {
+    let work = Work {
+        rects: &[full_rect(bounds)],
+        keep: None,
+        focus: Focus::default(),
+        costs: None,
+        cached: false,
+        kernel: Some(kernel),
+        sampling: None,
+    };
+    fill(pool, empty_buffer(bounds, params), work, cancel, |_| {})
+}
+
+// Computes only the samples `sampling` picks, at its share of the iterations, and fills in the
+// rest of the frame from them: a rough frame at a fraction of the cost, for previews that stand
+// in for a full render. The frame carries the reduced iteration limit. Nothing renders this way
+// unless its caller asks to; the view and exports never do.
+pub fn render_sampled(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    sampling: Sampling,
+    cancel: &CancelToken,
+) -> Result<IterationBuffer, RenderError> {
+    // The pre-pass probes more pixels per block than the samples it would save.
+    let params = FrameParams {
+        max_iterations: sampling.iterations(params.max_iterations),
+        coarse_prepass: false,
+        ..params
+    };
+    let work = Work {
+        rects: &[full_rect(bounds)],
+        keep: None,
+        focus: Focus::default(),
+        costs: None,
+        cached: false,
+        kernel: None,
+        sampling: Some(sampling),
+    };
+    fill(pool, empty_buffer(bounds, params), work, cancel, |_| {})
+}
+
+// Re-iterates only the pixels `previous` left unresolved, up to `max_iterations`; escaped pixels
+// and those proven inside keep their values. Orbits are not stored, so the rest start again from
+// zero.
+pub fn extend_iterations(
+    pool: &ThreadPool,
+    previous: &Arc<IterationBuffer>,
+    max_iterations: u32,
+    cancel: &CancelToken,
+    mut on_progress: impl FnMut(Progress),
+) -> Result<IterationBuffer, RenderError> {
+    let Some(previous_params) = previous.params else {
+        return Ok(IterationBuffer::clone(previous));
+    };
+    let params = FrameParams {
+        max_iterations,
+        ..previous_params
+    };
+    let bounds = Size::new(previous.width as f32, previous.height as f32);
+    let work = Work {
+        rects: &[full_rect(bounds)],
+        keep: Some(Arc::clone(previous)),
+        focus: Focus::default(),
+        costs: None,
+        cached: false,
+        kernel: None,
+        sampling: None,
+    };
+    fill(pool, empty_buffer(bounds, params), work, cancel, |update| {
+        on_progress(update.progress)
+    })
+}
+
+fn full_rect(bounds: Size) -> PixelRect {
+    PixelRect {
+        x: 0,
+        y: 0,
+        width: bounds.width as usize,
+        height: bounds.height as usize,
+    }
+}
+
+// A full-precision frame of `bounds` with every pixel still to compute.
+pub fn empty_buffer(bounds: Size, params: FrameParams) -> IterationBuffer {
+    let width = bounds.width as usize;
+    let height = bounds.height as usize;
+    let filled = |value: f32| {
+        let mut data = buffers::FRAMES.take(width * height);
+        data.resize(width * height, value);
+        data
+    };
+    IterationBuffer {
+        width,
+        height,
+        values: Channel::Full(filled(UNRESOLVED)),
+        angles: Channel::Full(if params.angle.tracked() {
+            filled(0.0)
+        } else {
+            Vec::new()
+        }),
+        params: Some(params),
+        costs: None,
+        failure: None,
+    }
+}
+
+// The value shared by every pixel on a block's edge, if they all escaped quickly and agree. A band
+// of one escape count has no holes and reaches out to infinity, so a block whose whole edge lies
+// in one band lies in it throughout; its corners and center alone would miss a filament crossing.
+fn coarse_fill(edge: impl IntoIterator<Item = f32>) -> Option<f32> {
+    let mut edge = edge.into_iter();
+    let first = edge.next()?;
+    (escaped(first) && edge.all(|value| value == first)).then_some(first)
+}
+
+// How much detail a tile's probes suggest, in [0, 1]: their spread in escape counts relative to
+// the probing depth, with probes that did not escape counted as reaching it.
+fn importance(probes: [f32; 5]) -> f32 {
+    let depth = IMPORTANCE_ITERATIONS as f32;
+    let values = probes.map(|value| if escaped(value) { value } else { depth });
+    let mean = values.iter().sum::<f32>() / 5.0;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f32>()
+        / 5.0;
+    (2.0 * variance.sqrt() / depth).min(1.0)
+}
+
+// The pixels a pass over the frame computes.
+struct Work<'a> {
+    rects: &'a [PixelRect],
+    // A same-sized frame whose escaped pixels are copied, so only its interior ones are iterated.
+    keep: Option<Arc<IterationBuffer>>,
+    focus: Focus,
+    // Predicted cost over the frame, from the previous render.
+    costs: Option<CostMap>,
+    // Take tiles from `TILE_CACHE` where it has them and add the ones computed.
+    cached: bool,
+    // Iterates instead of `params.fractal`, for trying out a kernel that is not a fractal kind.
+    kernel: Option<&'static dyn Fractal>,
+    // Compute only these samples; the other pixels are filled in from them once all have landed.
+    sampling: Option<Sampling>,
+}
+
+// Computes `work` into `buffer`, which it then compacts, reporting each tile as it lands.
+fn fill(
+    pool: &ThreadPool,
+    mut buffer: IterationBuffer,
+    work: Work,
+    cancel: &CancelToken,
+    mut on_tile: impl FnMut(TileUpdate),
+) -> Result<IterationBuffer, RenderError> {
+    let params = buffer
+        .params
+        .expect("frames being built carry their parameters");
+    let bounds = Size::new(buffer.width as f32, buffer.height as f32);
+    // Re-iterating unresolved pixels says little about what a fresh render costs, so such
+    // frames keep the map they were built from.
+    let kept_costs = work.keep.as_ref().map(|keep| keep.costs.clone());
+    let sampling = work.sampling;
+    let mut costs = kept_costs.is_none().then(|| {
+        work.costs
+            .clone()
+            .unwrap_or_else(|| CostMap::new(bounds, params))
+    });
+
+    // Cached tiles land first; the rest of the frame is then computed as the tiles missing. The
+    // grid cells are what is cached, whatever tiles the plan merges or splits them into.
+    let start = Instant::now();
+    let cached = work.cached && work.keep.is_none() && TILE_CACHE.enabled();
+    let cells = if cached {
+        tiling::split(work.rects)
+    } else {
+        Vec::new()
+    };
+    let total_pixels: usize = work.rects.iter().map(|rect| rect.width * rect.height).sum();
+    let mut missing = Vec::new();
+    let mut pixels_cached = 0;
+    let mut hits = 0;
+    for cell in cells {
+        let Some((values, angles)) = TILE_CACHE.get(&TileKey::new(bounds, &params, cell)) else {
+            missing.push(cell);
+            continue;
+        };
+        let tile = TileResult {
+            rect: cell,
+            order: hits,
+            values,
+            angles,
+            cost: Duration::ZERO,
+            failure: None,
+        };
+        hits += 1;
+        pixels_cached += cell.width * cell.height;
+        buffer.insert_tile(&tile);
+        on_tile(TileUpdate {
+            tile: &tile,
+            frame: &buffer,
+            progress: Progress {
+                pixels_done: pixels_cached,
+                total_pixels,
+                elapsed: start.elapsed(),
+                predicted_done: Duration::ZERO,
+                predicted_total: Duration::ZERO,
+            },
+        });
+        buffers::TILES.give(tile.values);
+        buffers::TILES.give(tile.angles);
+    }
+    let work = if hits > 0 {
+        Work {
+            rects: &missing,
+            ..work
+        }
+    } else {
+        work
+    };
+    // Only fresh renders are predicted; see `kept_costs`.
+    let prediction = costs.is_some().then(|| work.costs.clone()).flatten();
+    let predicted_total = prediction
+        .as_ref()
+        .and_then(|map| work.rects.iter().map(|rect| map.predict(*rect)).sum())
+        .unwrap_or_default();
+    let mut predicted_done = Duration::ZERO;
+    stream_rects(
+        pool,
+        bounds,
+        params,
+        work,
+        cancel,
+        |mut tile, mut progress| {
+            if let Some(costs) = &mut costs {
+                costs.record(tile.rect, tile.cost);
+            }
+            if let Some(prediction) = &prediction {
+                predicted_done += prediction.predict(tile.rect).unwrap_or_default();
+            }
+            tile.order += hits;
+            progress.pixels_done += pixels_cached;
+            progress.total_pixels += pixels_cached;
+            progress.predicted_done = predicted_done;
+            progress.predicted_total = predicted_total;
+            buffer.insert_tile(&tile);
+            on_tile(TileUpdate {
+                tile: &tile,
+                frame: &buffer,
+                progress,
+            });
+            buffers::TILES.give(tile.values);
+            buffers::TILES.give(tile.angles);
+        },
+    )?;
+    if cached {
+        for cell in missing {
+            let (values, angles) = buffer.cut(cell);
+            if values.contains(&FAILED) {
+                continue;
+            }
+            TILE_CACHE.insert(TileKey::new(bounds, &params, cell), values, angles);
+        }
+    }
+    if let Some(sampling) = sampling {
+        let (width, height) = (buffer.width, buffer.height);
+        sampling.infill(
+            buffer.values.full_mut(),
+            buffer.angles.full_mut(),
+            width,
+            height,
+        );
+    }
+    buffer.compact();
+    buffer.costs = costs.map(Arc::new).or(kept_costs.flatten());
+    Ok(buffer)
+}
+
+fn stream_rects(
+    pool: &ThreadPool,
+    bounds: Size,
+    params: FrameParams,
+    work: Work,
+    cancel: &CancelToken,
+    mut on_tile: impl FnMut(TileResult, Progress),
+) -> Result<FrameSummary, RenderError> {
+    let start = Instant::now();
+    let viewport = params.viewport;
+    let max_iterations = params.max_iterations;
+    let fractal = params.fractal;
+    let kernel = work.kernel;
+    let angle = params.angle;
+    let track_angle = angle.tracked();
+    let (frame_width, frame_height) = (bounds.width as usize, bounds.height as usize);
+    let keep = work.keep;
+    // Angles vary across every block, so only escape values can be filled.
+    let prepass = params.coarse_prepass && !track_angle && keep.is_none();
+    let sampling = work.sampling;
+
+    let (left, top) = viewport.top_left(bounds);
+    let pixel_size = viewport.pixel_size(bounds);
+    let arithmetic = params.arithmetic;
+    let (center_re, center_im) = viewport.center();
+    let (half_width, half_height) = (bounds.width as f64 / 2.0, bounds.height as f64 / 2.0);
+    let point = move |x: usize, y: usize| {
+        Complex::new(left + pixel_size * x as f64, top - pixel_size * y as f64)
+    };
+    // Double-double pixels are offsets from the exact center, since `left` and `top` have already
+    // lost the digits that tell them apart.
+    let iterate = move |x: usize, y: usize, max_iterations: u32, angle: AngleKind| {
+        let fractal = match kernel {
+            Some(kernel) => kernel,
+            None => fractal.fractal(),
+        };
+        match arithmetic {
+            Arithmetic::F64 => fractal.iterate(point(x, y), max_iterations, angle),
+            Arithmetic::DoubleDouble => {
+                let re = (x as f64 - half_width) * pixel_size;
+                let im = (half_height - y as f64) * pixel_size;
+                let c = (
+                    center_re + DoubleDouble::from(re),
+                    center_im + DoubleDouble::from(im),
+                );
+                fractal.iterate_dd(c, max_iterations, angle)
+            }
+        }
+    };
+    let probe = move |rect: PixelRect, max_iterations: u32| {
+        let (x0, y0) = (rect.x, rect.y);
+        let (x1, y1) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+        [
+            (x0, y0),
+            (x1, y0),
+            (x0, y1),
+            (x1, y1),
+            ((x0 + x1) / 2, (y0 + y1) / 2),
+        ]
+        .map(|(x, y)| iterate(x, y, max_iterations, AngleKind::Off).value)
+    };
+    // The pixels around the edge of `rect`, iterated lazily so that the first disagreement ends it.
+    let edge = move |rect: PixelRect, max_iterations: u32| {
+        let (x0, y0) = (rect.x, rect.y);
+        let (x1, y1) = (rect.x + rect.width - 1, rect.y + rect.height - 1);
+        let rows = (x0..=x1).flat_map(move |x| [(x, y0), (x, y1)]);
+        let columns = (y0 + 1..y1).flat_map(move |y| [(x0, y), (x1, y)]);
+        rows.chain(columns)
+            .map(move |(x, y)| iterate(x, y, max_iterations, AngleKind::Off).value)
+    };
+
+    let tiles: Vec<(PixelRect, f32)> = tiling::plan(work.rects, work.costs.as_ref())
+        .into_iter()
+        .map(|tile| {
+            // A kernel that panics on the probes panics again in the tile's worker, which
+            // reports it.
+            let probed = panic::catch_unwind(AssertUnwindSafe(|| {
+                importance(probe(tile, IMPORTANCE_ITERATIONS))
+            }));
+            (tile, probed.unwrap_or(0.0))
+        })
+        .collect();
+    let total_pixels: usize = tiles.iter().map(|(tile, _)| tile.width * tile.height).sum();
+    let jobs = tiles.len();
+    let center = (bounds.width / 2.0, bounds.height / 2.0);
+    let queue = Arc::new(Mutex::new(TileQueue::new(tiles, center)));
+
+    let (tx, rx) = channel();
+    for _ in 0..jobs {
+        let tx = tx.clone();
+        let cancel = cancel.clone();
+        let keep = keep.clone();
+        let queue = Arc::clone(&queue);
+        let focus = work.focus.clone();
+        // Each job takes whichever tile is most wanted when it starts, so the order follows the
+        // focus as it moves.
+        pool.execute(move || {
+            let next = queue
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .pop(focus.get());
+            let Some((order, job)) = next else {
+                return;
+            };
+            cancel.beat();
+            let started = Instant::now();
+            let pixels = job.width * job.height;
+            let mut values = buffers::TILES.take(pixels);
+            let mut angles = if track_angle {
+                buffers::TILES.take(pixels)
+            } else {
+                Vec::new()
+            };
+            // A panicking kernel costs only its own tile: the worker carries on, and the tile is
+            // delivered marked failed so that the frame still completes.
+            let computed = panic::catch_unwind(AssertUnwindSafe(|| {
+                let first_block = job.x / PREPASS_BLOCK;
+                let mut block_row = usize::MAX;
+                let mut fills: Vec<Option<f32>> = Vec::new();
+                for y in job.y..job.y + job.height {
+                    cancel.wait_while_paused();
+                    if cancel.is_cancelled() {
+                        return false;
+                    }
+                    let row_started = Instant::now();
+                    if prepass && y / PREPASS_BLOCK != block_row {
+                        block_row = y / PREPASS_BLOCK;
+                        fills = (first_block..=(job.x + job.width - 1) / PREPASS_BLOCK)
+                            .map(|block_column| {
+                                let x = block_column * PREPASS_BLOCK;
+                                let y = block_row * PREPASS_BLOCK;
+                                let block = PixelRect {
+                                    x,
+                                    y,
+                                    width: PREPASS_BLOCK.min(frame_width - x),
+                                    height: PREPASS_BLOCK.min(frame_height - y),
+                                };
+                                coarse_fill(edge(block, PREPASS_ITERATIONS))
+                            })
+                            .collect();
+                    }
+                    for x in job.x..job.x + job.width {
+                        if sampling.is_some_and(|sampling| {
+                            !sampling.picks(x, y, frame_width, frame_height)
+                        }) {
+                            values.push(UNRESOLVED);
+                            if track_angle {
+                                angles.push(0.0);
+                            }
+                            continue;
+                        }
+                        if let Some(value) = fills
+                            .get(x / PREPASS_BLOCK - first_block)
+                            .copied()
+                            .flatten()
+                        {
+                            values.push(value);
+                            continue;
+                        }
+                        if let Some(keep) = &keep {
+                            let index = y * frame_width + x;
+                            let value = keep.values.get(index);
+                            // Escaped and proven pixels are settled; only unresolved ones can
+                            // change with more iterations, and failed ones are tried again.
+                            if !matches!(
+                                PixelClass::of(value),
+                                PixelClass::Unresolved | PixelClass::Failed
+                            ) {
+                                values.push(value);
+                                if track_angle {
+                                    angles.push(keep.angles.get(index));
+                                }
+                                continue;
+                            }
+                        }
+                        let sample = iterate(x, y, max_iterations, angle);
+                        values.push(sample.value);
+                        if track_angle {
+                            angles.push(sample.angle);
+                        }
+                    }
+                    cancel.beat();
+                    cancel.keep_to_cap(row_started.elapsed());
+                }
+                true
+            }));
+            let failure = match computed {
+                Ok(true) => None,
+                Ok(false) => return,
+                Err(payload) => {
+                    values.clear();
+                    values.resize(pixels, FAILED);
+                    angles.clear();
+                    if track_angle {
+                        angles.resize(pixels, 0.0);
+                    }
+                    Some(panic_message(payload.as_ref()))
+                }
+            };
+            // The receiver is gone if the render was abandoned.
+            let _ = tx.send(TileResult {
+                rect: job,
+                order,
+                values,
+                angles,
+                cost: started.elapsed(),
+                failure,
+            });
+        });
+    }
+    drop(tx);
+
+    let mut summary = FrameSummary {
+        width: bounds.width as usize,
+        height: bounds.height as usize,
+        tiles: 0,
+        failed_tiles: 0,
+        pixels_computed: 0,
+        elapsed: Duration::ZERO,
+    };
+    let mut pixels_done = 0;
+    for tile in rx {
+        if cancel.is_cancelled() {
+            return Err(RenderError::Cancelled);
+        }
+        pixels_done += tile.values.len();
+        summary.tiles += 1;
+        if tile.failure.is_some() {
+            summary.failed_tiles += 1;
+        }
+        summary.pixels_computed += tile.values.len();
+        on_tile(
+            tile,
+            Progress {
+                pixels_done,
+                total_pixels,
+                elapsed: start.elapsed(),
+                predicted_done: Duration::ZERO,
+                predicted_total: Duration::ZERO,
+            },
+        );
+    }
+    if cancel.is_cancelled() {
+        return Err(RenderError::Cancelled);
+    }
+    summary.elapsed = start.elapsed();
+    Ok(summary)
+}
+
+// What a panic was raised with, when it was raised with text.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        String::from(*message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("panicked without a message")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fractal::{self, Sample};
+    use crate::stats::{self, FrameStats, CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    // Points whose class is known, at the iteration limit they are checked at.
+    const KNOWN_CLASSES: [(f64, f64, u32, PixelClass); 6] = [
+        (0.0, 0.0, 100, PixelClass::Interior(Proof::Cardioid)),
+        (-1.0, 0.0, 100, PixelClass::Interior(Proof::Bulb)),
+        (
+            -0.122_561_166_876_653_6,
+            0.744_861_766_619_744_2,
+            1000,
+            PixelClass::Interior(Proof::Periodic),
+        ),
+        (1.0, 1.0, 100, PixelClass::Escaped(1.0)),
+        (0.26, 0.0, 10, PixelClass::Unresolved),
+        (0.26, 0.0, 1000, PixelClass::Escaped(29.0)),
+    ];
+    // The view classes are compared across iteration limits in: the whole set, so that every
+    // class shows up, at a limit low enough to leave the boundary unresolved and one well past it.
+    const CLASS_VIEW: (f64, f64, f64) = (-0.75, 0.0, 3.0);
+    const CLASS_SIZE: Size = Size::new(96.0, 72.0);
+    const CLASS_ITERATIONS: (u32, u32) = (40, 640);
+
+    #[test]
+    fn known_points_land_in_their_class() {
+        let mandelbrot = FractalKind::Mandelbrot.fractal();
+        for (re, im, max_iterations, expected) in KNOWN_CLASSES {
+            let dd = (DoubleDouble::from(re), DoubleDouble::from(im));
+            let samples = [
+                (
+                    "f64",
+                    mandelbrot.iterate(Complex::new(re, im), max_iterations, AngleKind::Off),
+                ),
+                (
+                    "double-double",
+                    mandelbrot.iterate_dd(dd, max_iterations, AngleKind::Off),
+                ),
+            ];
+            for (arithmetic, sample) in samples {
+                assert_eq!(
+                    PixelClass::of(sample.value),
+                    expected,
+                    "{} {} at {} iterations in {}",
+                    re,
+                    im,
+                    max_iterations,
+                    arithmetic
+                );
+            }
+        }
+        // The Burning Ship has no shapes to test for, but an orbit stuck at zero still repeats.
+        let ship =
+            FractalKind::BurningShip
+                .fractal()
+                .iterate(Complex::new(0.0, 0.0), 100, AngleKind::Off);
+        assert_eq!(
+            PixelClass::of(ship.value),
+            PixelClass::Interior(Proof::Periodic)
+        );
+    }
+
+    #[test]
+    fn the_reference_kernel_proves_nothing() {
+        let origin = Complex::new(0.0, 0.0);
+        let reference =
+            fractal::reference_iterate(FractalKind::Mandelbrot, origin, 100, AngleKind::Off);
+        assert_eq!(PixelClass::of(reference.value), PixelClass::Unresolved);
+    }
+
+    // Raising the iteration limit, by rendering again or by extending a frame, only ever moves
+    // pixels out of unresolved.
+    #[test]
+    fn deeper_limits_only_settle_unresolved_pixels() {
+        let pool = ThreadPool::new(1);
+        let render = |max_iterations: u32| {
+            let params = FrameParams {
+                viewport: Viewport::new(CLASS_VIEW.0, CLASS_VIEW.1, CLASS_VIEW.2),
+                max_iterations,
+                fractal: FractalKind::Mandelbrot,
+                angle: AngleKind::Off,
+                precision: Precision::Full,
+                coarse_prepass: false,
+                arithmetic: Arithmetic::F64,
+            };
+            stats::render_frame(CLASS_SIZE, params)
+        };
+        let (low, high) = CLASS_ITERATIONS;
+        let shallow = Arc::new(render(low));
+        let deep = render(high);
+        let extended = extend_iterations(&pool, &shallow, high, &CancelToken::new(), |_| {})
+            .expect("extending without a cancel request completes");
+        for (name, frame) in [("rendered", &deep), ("extended", &extended)] {
+            let moved = (0..shallow.values.len())
+                .filter(|&index| {
+                    let before = PixelClass::of(shallow.values.get(index));
+                    before != PixelClass::Unresolved
+                        && before != PixelClass::of(frame.values.get(index))
+                })
+                .count();
+            assert_eq!(
+                moved, 0,
+                "settled pixels change class when {} to {} iterations",
+                name, high
+            );
+        }
+        assert!(
+            extended.values.len() == deep.values.len()
+                && (0..deep.values.len())
+                    .all(|index| extended.values.get(index) == deep.values.get(index)),
+            "extending a frame gives other values than rendering it deeper"
+        );
+        let (before, after) = (ClassCounts::of(&shallow), ClassCounts::of(&deep));
+        assert!(
+            before.interior > 0 && before.escaped > 0 && before.unresolved > after.unresolved,
+            "raising the limit leaves the classes at {:?} from {:?}",
+            after,
+            before
+        );
+    }
+
+    // The pre-pass only saves work: frames come out bit for bit as without it, on the whole set,
+    // along the antenna, where filaments thinner than a block reach far out, and on a size the
+    // blocks do not divide.
+    #[test]
+    fn the_coarse_prepass_changes_no_pixel() {
+        let views = [
+            ("home", (-0.7, 0.0, 3.2), CANONICAL_SIZE),
+            ("antenna", (-1.8, 0.0, 0.8), CANONICAL_SIZE),
+            ("odd-sized", (-0.7, 0.0, 3.2), Size::new(101.0, 67.0)),
+        ];
+        for (name, (re, im, width), size) in views {
+            let render = |coarse_prepass: bool| {
+                let params = FrameParams {
+                    viewport: Viewport::new(re, im, width),
+                    max_iterations: 500,
+                    fractal: FractalKind::Mandelbrot,
+                    angle: AngleKind::Off,
+                    precision: Precision::Full,
+                    coarse_prepass,
+                    arithmetic: Arithmetic::F64,
+                };
+                stats::render_frame(size, params)
+            };
+            let (filled, computed) = (render(true), render(false));
+            let differing = (0..computed.values.len())
+                .filter(|&index| {
+                    filled.values.get(index).to_bits() != computed.values.get(index).to_bits()
+                })
+                .count();
+            assert_eq!(
+                differing, 0,
+                "{}: {} pixels differ with the pre-pass",
+                name, differing
+            );
+        }
+    }
+
+    // The Mandelbrot set's kernel, panicking right of the imaginary axis.
+    struct GivesUpRight;
+
+    impl Fractal for GivesUpRight {
+        fn name(&self) -> &'static str {
+            "Gives up right"
+        }
+
+        fn bounds(&self) -> (f64, f64, f64, f64) {
+            FractalKind::Mandelbrot.fractal().bounds()
+        }
+
+        fn iterate(&self, c: Complex<f64>, max_iterations: u32, angle: AngleKind) -> Sample {
+            if c.re > 0.0 {
+                panic!("gave up at {}", c);
+            }
+            FractalKind::Mandelbrot
+                .fractal()
+                .iterate(c, max_iterations, angle)
+        }
+
+        fn iterate_dd(
+            &self,
+            c: (DoubleDouble, DoubleDouble),
+            max_iterations: u32,
+            angle: AngleKind,
+        ) -> Sample {
+            if c.0.to_f64() > 0.0 {
+                panic!("gave up at {}", c.0.to_f64());
+            }
+            FractalKind::Mandelbrot
+                .fractal()
+                .iterate_dd(c, max_iterations, angle)
+        }
+    }
+
+    // A frame whose kernel panics completes with exactly the tiles it panicked in marked failed,
+    // and the pool renders the next frame as usual.
+    #[test]
+    fn panicking_kernels_fail_only_their_own_tiles() {
+        let view = CANONICAL_VIEWS[0];
+        let params = view.params();
+        let size = CANONICAL_SIZE;
+        let pool = ThreadPool::new(2);
+        let cancel = CancelToken::new();
+        let broken = render_kernel(&pool, size, params, &GivesUpRight, &cancel)
+            .expect("a panicking kernel still completes its frame");
+        let next = render_focused(&pool, size, params, &cancel, &Focus::default(), |_| {})
+            .expect("the render after a panic completes");
+        assert!(
+            broken
+                .failure
+                .as_ref()
+                .is_some_and(|failure| failure.starts_with("gave up at")),
+            "the failed frame reports {:?}",
+            broken.failure
+        );
+        let (left, _) = params.viewport.top_left(size);
+        let pixel_size = params.viewport.pixel_size(size);
+        let mut failed = 0;
+        for index in 0..broken.values.len() {
+            let re = left + pixel_size * (index % broken.width) as f64;
+            let value = broken.values.get(index);
+            if PixelClass::of(value) == PixelClass::Failed {
+                failed += 1;
+                continue;
+            }
+            assert!(re <= 0.0, "pixel {} right of the axis is {}", index, value);
+            assert_eq!(
+                value,
+                next.values.get(index),
+                "pixel {} beside failed tiles and alone",
+                index
+            );
+        }
+        assert!(
+            failed > 0 && failed < broken.values.len(),
+            "{} of {} pixels failed",
+            failed,
+            broken.values.len()
+        );
+        let stats = FrameStats::of(&next, params.max_iterations);
+        assert!(
+            next.failure.is_none() && view.matches(&stats),
+            "the render after the failed one gives {} and {:?}",
+            stats,
+            next.failure
+        );
+        assert_eq!(pool.panic_count(), 0, "panics reached the pool");
+    }
+}
@@ -0,0 +1,250 @@
+use crate::render;
+
+// Sparse renders for previews that need to be cheap more than exact: the frame is cut into cells
+// of `stride` by `stride` pixels and one pixel of each is computed, at a spot in the cell picked
+// from the seed, so the samples spread evenly without lining up on a grid. The other pixels are
+// filled in from the samples of the four cells nearest them.
+
+// Samples are placed from this unless a call site asks for other spots, so that the same preview
+// comes out the same every time.
+pub const SEED: u64 = 0x5a3b_1e5f_0c0f_fee5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sampling {
+    // Pixels each way of the cells that get one sample each.
+    pub stride: usize,
+    // The iteration limit is divided by this for the samples.
+    pub iteration_divisor: u32,
+    pub seed: u64,
+}
+
+impl Sampling {
+    // One pixel in 16, at half the iterations: previews an order of magnitude cheaper than the
+    // frames they stand in for.
+    pub const THUMBNAIL: Sampling = Sampling {
+        stride: 4,
+        iteration_divisor: 2,
+        seed: SEED,
+    };
+
+    pub fn iterations(&self, max_iterations: u32) -> u32 {
+        (max_iterations / self.iteration_divisor.max(1)).max(1)
+    }
+
+    // Cells across and down a `width` by `height` frame; those on the right and bottom edges may
+    // be cut short.
+    pub fn cells(&self, width: usize, height: usize) -> (usize, usize) {
+        let stride = self.stride.max(1);
+        (width.div_ceil(stride), height.div_ceil(stride))
+    }
+
+    // The pixel computed for the cell `column` across and `row` down.
+    pub fn spot(&self, column: usize, row: usize, width: usize, height: usize) -> (usize, usize) {
+        let stride = self.stride.max(1);
+        let (x, y) = (column * stride, row * stride);
+        let (cell_width, cell_height) = (stride.min(width - x), stride.min(height - y));
+        let hash = mix(self.seed ^ ((column as u64) << 32 | row as u64));
+        (
+            x + (hash % cell_width as u64) as usize,
+            y + ((hash >> 32) % cell_height as u64) as usize,
+        )
+    }
+
+    // Whether the pixel at `x`, `y` is one of the samples.
+    pub fn picks(&self, x: usize, y: usize, width: usize, height: usize) -> bool {
+        let stride = self.stride.max(1);
+        self.spot(x / stride, y / stride, width, height) == (x, y)
+    }
+
+    // Fills in every pixel of `values` and `angles`, rows of `width`, that is not a sample.
+    // Escape values are blended between the four samples around a pixel where all of them
+    // escaped; elsewhere, and always for angles, the nearest of the four is taken, so that
+    // pixels inside the set are not blended with the escape counts beside them.
+    pub fn infill(&self, values: &mut [f32], angles: &mut [f32], width: usize, height: usize) {
+        let stride = self.stride.max(1);
+        let (columns, rows) = self.cells(width, height);
+        let spots: Vec<(usize, usize)> = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| self.spot(column, row, width, height))
+            .collect();
+        // For each position along an axis: its own cell, the cells either side of it and how far
+        // it is from the first.
+        let around = |length: usize, cells: usize| {
+            (0..length)
+                .map(|position| {
+                    let cell = ((position as f32 + 0.5) / stride as f32 - 0.5).max(0.0);
+                    let first = (cell as usize).min(cells - 1);
+                    let next = (first + 1).min(cells - 1);
+                    (
+                        position / stride,
+                        first,
+                        next,
+                        (cell - first as f32).min(1.0),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let (across, down) = (around(width, columns), around(height, rows));
+        for (y, &(row, top, bottom, down)) in down.iter().enumerate() {
+            for (x, &(column, left, right, across)) in across.iter().enumerate() {
+                if spots[row * columns + column] == (x, y) {
+                    continue;
+                }
+                let corners = [(left, top), (right, top), (left, bottom), (right, bottom)]
+                    .map(|(column, row)| spots[row * columns + column]);
+                let samples = corners.map(|(sx, sy)| values[sy * width + sx]);
+                let nearest = || {
+                    let distance =
+                        |(sx, sy): (usize, usize)| x.abs_diff(sx).pow(2) + y.abs_diff(sy).pow(2);
+                    let (nx, ny) = corners
+                        .into_iter()
+                        .min_by_key(|corner| distance(*corner))
+                        .expect("every pixel has four cells around it");
+                    ny * width + nx
+                };
+                let pixel = y * width + x;
+                values[pixel] = if samples.into_iter().all(render::escaped) {
+                    let upper = samples[0] + (samples[1] - samples[0]) * across;
+                    let lower = samples[2] + (samples[3] - samples[2]) * across;
+                    upper + (lower - upper) * down
+                } else {
+                    values[nearest()]
+                };
+                if !angles.is_empty() {
+                    angles[pixel] = angles[nearest()];
+                }
+            }
+        }
+    }
+}
+
+// SplitMix64's finalizer: spreads neighbouring cells' inputs over unrelated spots.
+fn mix(value: u64) -> u64 {
+    let mut z = value.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use iced::Size;
+    use threadpool::ThreadPool;
+
+    use super::*;
+    use crate::coloring;
+    use crate::render::{CancelToken, FrameParams, IterationBuffer};
+    use crate::settings::ColoringSettings;
+    use crate::stats::{self, CANONICAL_VIEWS};
+
+    // Thumbnails are made as bookmark thumbnails are: rendered at `THUMBNAIL_SUPERSAMPLE` times
+    // `THUMBNAIL_SIZE` and downscaled.
+    const THUMBNAIL_SIZE: Size = Size::new(128.0, 80.0);
+    const THUMBNAIL_SUPERSAMPLE: f32 = 3.0;
+
+    // The seahorse valley canonical view, at the size its thumbnail is rendered at.
+    fn thumbnail_frame() -> (Size, FrameParams) {
+        let size = Size::new(
+            THUMBNAIL_SIZE.width * THUMBNAIL_SUPERSAMPLE,
+            THUMBNAIL_SIZE.height * THUMBNAIL_SUPERSAMPLE,
+        );
+        let params = FrameParams {
+            coarse_prepass: true,
+            ..CANONICAL_VIEWS[2].params()
+        };
+        (size, params)
+    }
+
+    fn render_sampled(sampling: Sampling) -> IterationBuffer {
+        let (size, params) = thumbnail_frame();
+        render::render_sampled(
+            &ThreadPool::new(1),
+            size,
+            params,
+            sampling,
+            &CancelToken::new(),
+        )
+        .expect("renders without a cancel request complete")
+    }
+
+    fn values(buffer: &IterationBuffer) -> Vec<f32> {
+        (0..buffer.width * buffer.height)
+            .map(|index| buffer.values.get(index))
+            .collect()
+    }
+
+    #[test]
+    fn thumbnails_compute_one_pixel_in_sixteen() {
+        let (size, _) = thumbnail_frame();
+        let (width, height) = (size.width as usize, size.height as usize);
+        let (columns, rows) = Sampling::THUMBNAIL.cells(width, height);
+        assert!(
+            columns * rows * 16 <= width * height,
+            "{} samples for {} pixels",
+            columns * rows,
+            width * height
+        );
+    }
+
+    #[test]
+    fn samples_are_placed_by_the_seed() {
+        let first = values(&render_sampled(Sampling::THUMBNAIL));
+        let again = values(&render_sampled(Sampling::THUMBNAIL));
+        assert!(
+            first == again,
+            "a second sampled render came out differently"
+        );
+        let reseeded = values(&render_sampled(Sampling {
+            seed: SEED + 1,
+            ..Sampling::THUMBNAIL
+        }));
+        assert!(first != reseeded, "another seed sampled the same pixels");
+    }
+
+    #[test]
+    fn sampled_thumbnails_look_like_full_ones() {
+        let (size, params) = thumbnail_frame();
+        let full = stats::render_frame(size, params);
+        let sampled = render_sampled(Sampling::THUMBNAIL);
+        let (width, height) = (full.width, full.height);
+        let (columns, rows) = Sampling::THUMBNAIL.cells(width, height);
+        // Samples that escaped within the reduced limit escaped at the same count in full.
+        for (column, row) in (0..rows).flat_map(|row| (0..columns).map(move |column| (column, row)))
+        {
+            let (x, y) = Sampling::THUMBNAIL.spot(column, row, width, height);
+            let value = sampled.values.get(y * width + x);
+            if render::escaped(value) {
+                assert_eq!(
+                    value,
+                    full.values.get(y * width + x),
+                    "the sample at {}, {} differs from the full render",
+                    x,
+                    y
+                );
+            }
+        }
+        let settings = ColoringSettings::default();
+        let thumbnail = |buffer: &IterationBuffer| {
+            coloring::downscale(
+                buffer,
+                &settings,
+                THUMBNAIL_SIZE.width as usize,
+                THUMBNAIL_SIZE.height as usize,
+            )
+        };
+        let (expected, actual) = (thumbnail(&full), thumbnail(&sampled));
+        let total: u64 = expected
+            .chunks_exact(4)
+            .zip(actual.chunks_exact(4))
+            .flat_map(|(expected, actual)| expected[..3].iter().zip(&actual[..3]))
+            .map(|(a, b)| a.abs_diff(*b) as u64)
+            .sum();
+        let channels = (THUMBNAIL_SIZE.width * THUMBNAIL_SIZE.height) as u64 * 3;
+        let mean_error = total as f32 / (channels * 255) as f32;
+        assert!(
+            mean_error <= 0.01,
+            "sampled thumbnails are off by {:.1}% on average",
+            mean_error * 100.0
+        );
+    }
+}
@@ -0,0 +1,523 @@
+use iced::Size;
+
+use serde::Serialize;
+
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use crate::coloring;
+use crate::export::{self, LOCATION_PNG_KEYWORD};
+use crate::fractal::{AngleKind, FractalKind};
+use crate::json;
+use crate::look::Look;
+use crate::palette::Palette;
+use crate::render::{self, Arithmetic, CancelToken, FrameParams};
+use crate::settings::{ColoringSettings, RenderSettings};
+use crate::storage::Precision;
+use crate::tune::Tuning;
+use crate::viewport::Viewport;
+
+// Renders served as PNGs over HTTP, for dashboards and notebooks that embed live views:
+//
+//     GET /render?re=-0.743&im=0.131&width=1e-6&px=800&py=600&iter=5000&palette=fire
+//
+// `re`, `im` and `width` are required; the frame size defaults to `DEFAULT_SIZE`, the iterations
+// and coloring to those of the engine. Errors come back with their status and a JSON body saying
+// what was wrong. Request heads are read by a few threads of their own, each against one deadline
+// for the whole head, so that slow clients cannot hold up the others. Only a few renders run at
+// once and a few more wait; the rest are turned away, and renders that run past their time are
+// given up.
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:8080";
+const DEFAULT_SIZE: (u32, u32) = (800, 600);
+// Largest frame served each way, and most iterations, as the iteration control allows.
+const MAX_SIDE: u32 = 4096;
+const MAX_ITERATIONS: u32 = 20_000;
+// The most a client may send as its request head.
+const MAX_HEAD: usize = 8 * 1024;
+// Heads read at once, and connections waiting for a reader beyond those; more are answered 503.
+const HEAD_READERS: usize = 4;
+const HEADS_WAITING: usize = 16;
+// Longest a response may take to be written out.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Limits {
+    // Renders running at once; they share one pool of workers.
+    pub concurrent: usize,
+    // Requests waiting for a render to finish beyond those; more are answered 503.
+    pub queued: usize,
+    // How long a request may take from arriving to its PNG before it is answered 504.
+    pub timeout: Duration,
+    // How long a client may take to send its whole request head before it is answered 408.
+    pub head_timeout: Duration,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            concurrent: 2,
+            queued: 8,
+            timeout: Duration::from_secs(60),
+            head_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+// What the renders are made with: a fractal and its settings, fixed at start.
+#[derive(Clone, Debug)]
+pub struct Engine {
+    // Workers shared by the renders running at once.
+    pub threads: usize,
+    pub fractal: FractalKind,
+    // For requests that do not ask for a count.
+    pub max_iterations: u32,
+    pub coloring: ColoringSettings,
+    pub double_double: bool,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Engine {
+            threads: Tuning::heuristic().threads,
+            fractal: FractalKind::Mandelbrot,
+            max_iterations: RenderSettings::default().max_iterations,
+            coloring: ColoringSettings::default(),
+            double_double: false,
+        }
+    }
+}
+
+// What a render request asked for, checked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderRequest {
+    pub viewport: Viewport,
+    pub width: u32,
+    pub height: u32,
+    // The current fractal's when not asked for.
+    pub max_iterations: Option<u32>,
+    pub palette: Option<String>,
+}
+
+// A failed request: its status and what to tell the client.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Failure {
+    pub status: u16,
+    pub message: String,
+}
+
+impl Failure {
+    fn new(status: u16, message: impl Into<String>) -> Failure {
+        Failure {
+            status,
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    status: u16,
+    error: &'a str,
+}
+
+// A PNG and the headers describing it.
+struct Rendered {
+    png: Vec<u8>,
+    headers: Vec<(&'static str, String)>,
+}
+
+struct Job {
+    stream: TcpStream,
+    request: RenderRequest,
+    deadline: Instant,
+}
+
+// A connection waiting for its head to be read, and when it arrived.
+struct Arrival {
+    stream: TcpStream,
+    arrived: Instant,
+}
+
+// Listens at `address`, which must be a loopback one, and serves renders on background threads;
+// returns the address listened at, with the port picked if it was 0.
+pub fn start(address: &str, limits: Limits, engine: Engine) -> Result<SocketAddr, String> {
+    let address: SocketAddr = address
+        .parse()
+        .map_err(|err| format!("invalid address {}: {}", address, err))?;
+    if !address.ip().is_loopback() {
+        return Err(format!("{} is not a loopback address", address.ip()));
+    }
+    let listener = TcpListener::bind(address)
+        .map_err(|err| format!("cannot listen at {}: {}", address, err))?;
+    let address = listener.local_addr().map_err(|err| err.to_string())?;
+    let pool = ThreadPool::new(engine.threads.max(1));
+    let (jobs, waiting) = mpsc::sync_channel::<Job>(limits.queued);
+    let waiting = Arc::new(Mutex::new(waiting));
+    for _ in 0..limits.concurrent.max(1) {
+        let waiting = Arc::clone(&waiting);
+        let (pool, engine) = (pool.clone(), engine.clone());
+        thread::spawn(move || render_jobs(&waiting, &pool, &engine));
+    }
+    let (arrivals, unread) = mpsc::sync_channel::<Arrival>(HEADS_WAITING);
+    let unread = Arc::new(Mutex::new(unread));
+    for _ in 0..HEAD_READERS {
+        let unread = Arc::clone(&unread);
+        let jobs = jobs.clone();
+        thread::spawn(move || read_heads(&unread, &jobs, limits));
+    }
+    thread::spawn(move || accept(listener, arrivals));
+    Ok(address)
+}
+
+// Hands each connection to the head readers, turning it away if too many are waiting already.
+fn accept(listener: TcpListener, arrivals: SyncSender<Arrival>) {
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let _ = stream.set_write_timeout(Some(WRITE_TIMEOUT));
+        let arrival = Arrival {
+            stream,
+            arrived: Instant::now(),
+        };
+        match arrivals.try_send(arrival) {
+            Ok(()) => {}
+            Err(TrySendError::Full(mut arrival) | TrySendError::Disconnected(mut arrival)) => {
+                let failure = Failure::new(503, "too many requests waiting; try again later");
+                respond_error(&mut arrival.stream, &failure);
+            }
+        }
+    }
+}
+
+// Reads each request as it comes and queues the renders, answering everything else at once.
+fn read_heads(unread: &Mutex<Receiver<Arrival>>, jobs: &SyncSender<Job>, limits: Limits) {
+    loop {
+        let arrival = unread.lock().unwrap_or_else(PoisonError::into_inner).recv();
+        let Ok(Arrival {
+            mut stream,
+            arrived,
+        }) = arrival
+        else {
+            return;
+        };
+        let deadline = Instant::now() + limits.head_timeout;
+        let request = read_target(&stream, deadline).and_then(|target| parse_target(&target));
+        let request = match request {
+            Ok(request) => request,
+            Err(failure) => {
+                respond_error(&mut stream, &failure);
+                continue;
+            }
+        };
+        let job = Job {
+            stream,
+            request,
+            deadline: arrived + limits.timeout,
+        };
+        match jobs.try_send(job) {
+            Ok(()) => {}
+            Err(TrySendError::Full(mut job) | TrySendError::Disconnected(mut job)) => {
+                let failure = Failure::new(503, "too many renders waiting; try again later");
+                respond_error(&mut job.stream, &failure);
+            }
+        }
+    }
+}
+
+fn render_jobs(waiting: &Mutex<Receiver<Job>>, pool: &ThreadPool, engine: &Engine) {
+    loop {
+        let job = waiting
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .recv();
+        let Ok(mut job) = job else {
+            return;
+        };
+        match render_png(&job.request, pool, engine, job.deadline) {
+            Ok(rendered) => respond(
+                &mut job.stream,
+                200,
+                "image/png",
+                &rendered.headers,
+                &rendered.png,
+            ),
+            Err(failure) => respond_error(&mut job.stream, &failure),
+        }
+    }
+}
+
+// The request line's target, after reading and dropping the rest of the head, which must all
+// arrive by `deadline`.
+fn read_target(stream: &TcpStream, deadline: Instant) -> Result<String, Failure> {
+    let mut reader = BufReader::new(Deadlined { stream, deadline }.take(MAX_HEAD as u64));
+    let unreadable = || {
+        if Instant::now() >= deadline {
+            Failure::new(408, "the request head took too long to arrive")
+        } else {
+            Failure::new(400, "cannot read the request")
+        }
+    };
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(|_| unreadable())?;
+    let mut header = String::new();
+    loop {
+        header.clear();
+        match reader.read_line(&mut header) {
+            Ok(0) => return Err(Failure::new(431, "request head too large or cut short")),
+            Ok(_) if header.trim_end().is_empty() => break,
+            Ok(_) => {}
+            Err(_) => return Err(unreadable()),
+        }
+    }
+    let mut words = line.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("GET"), Some(target)) => Ok(target.to_string()),
+        (Some(_), Some(_)) => Err(Failure::new(405, "only GET is served")),
+        _ => Err(Failure::new(400, "malformed request line")),
+    }
+}
+
+// A stream read against one deadline for everything read from it, rather than a timeout for
+// each read that a client sending a byte at a time would keep putting off.
+struct Deadlined<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for Deadlined<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let left = self.deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(left))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+// Checks a request target such as `/render?re=-0.5&im=0&width=3`, as the command line checks
+// its arguments.
+pub fn parse_target(target: &str) -> Result<RenderRequest, Failure> {
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    if path != "/render" {
+        return Err(Failure::new(404, format!("no such endpoint {}", path)));
+    }
+    let mut fields = Vec::new();
+    for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+        let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value = decode(value).ok_or_else(|| Failure::new(400, "malformed query"))?;
+        if !["re", "im", "width", "px", "py", "iter", "palette"].contains(&name) {
+            return Err(Failure::new(400, format!("unknown parameter {}", name)));
+        }
+        fields.push((name, value));
+    }
+    let field = |name: &str| {
+        fields
+            .iter()
+            .rev()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    };
+    let number = |name: &str, what: &str| -> Result<Option<f64>, Failure> {
+        let Some(value) = field(name) else {
+            return Ok(None);
+        };
+        match value.parse::<f64>() {
+            Ok(number) if number.is_finite() => Ok(Some(number)),
+            Ok(_) => Err(Failure::new(400, format!("invalid {}: not finite", what))),
+            Err(err) => Err(Failure::new(400, format!("invalid {}: {}", what, err))),
+        }
+    };
+    let required = |name: &str, what: &str| {
+        number(name, what)?.ok_or_else(|| Failure::new(400, format!("missing {}", what)))
+    };
+    let count = |name: &str, what: &str, max: u32| -> Result<Option<u32>, Failure> {
+        let Some(value) = field(name) else {
+            return Ok(None);
+        };
+        match value.parse::<u32>() {
+            Ok(count) if (1..=max).contains(&count) => Ok(Some(count)),
+            Ok(_) => Err(Failure::new(
+                400,
+                format!("invalid {}: must be from 1 to {}", what, max),
+            )),
+            Err(err) => Err(Failure::new(400, format!("invalid {}: {}", what, err))),
+        }
+    };
+    let re = required("re", "real coordinate")?;
+    let im = required("im", "imaginary coordinate")?;
+    let width = required("width", "width")?;
+    if width <= 0.0 {
+        return Err(Failure::new(400, "invalid width: must be above 0"));
+    }
+    let palette = match field("palette") {
+        Some(name) => Some(
+            Palette::builtin()
+                .into_iter()
+                .find(|palette| palette.name.eq_ignore_ascii_case(name))
+                .map(|palette| palette.name)
+                .ok_or_else(|| {
+                    let names: Vec<String> = Palette::builtin()
+                        .into_iter()
+                        .map(|palette| palette.name.to_lowercase())
+                        .collect();
+                    Failure::new(
+                        400,
+                        format!("unknown palette {}, expected {}", name, names.join(", ")),
+                    )
+                })?,
+        ),
+        None => None,
+    };
+    Ok(RenderRequest {
+        viewport: Viewport::new(re, im, width),
+        width: count("px", "pixel width", MAX_SIDE)?.unwrap_or(DEFAULT_SIZE.0),
+        height: count("py", "pixel height", MAX_SIDE)?.unwrap_or(DEFAULT_SIZE.1),
+        max_iterations: count("iter", "iteration count", MAX_ITERATIONS)?,
+        palette,
+    })
+}
+
+// Percent-decodes a query value, with `+` for a space.
+fn decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let hex = std::str::from_utf8(bytes.get(index + 1..index + 3)?).ok()?;
+                decoded.push(u8::from_str_radix(hex, 16).ok()?);
+                index += 3;
+            }
+            b'+' => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            byte => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+// Renders `request` to a PNG carrying its look and location, with the headers describing it.
+fn render_png(
+    request: &RenderRequest,
+    pool: &ThreadPool,
+    engine: &Engine,
+    deadline: Instant,
+) -> Result<Rendered, Failure> {
+    let timed_out = || Failure::new(504, "the render ran out of time");
+    if Instant::now() >= deadline {
+        return Err(timed_out());
+    }
+    let size = Size::new(request.width as f32, request.height as f32);
+    let max_iterations = request.max_iterations.unwrap_or(engine.max_iterations);
+    let params = FrameParams {
+        viewport: request.viewport,
+        max_iterations,
+        fractal: engine.fractal,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: true,
+        arithmetic: Arithmetic::select(&request.viewport, size, engine.double_double),
+    };
+    let started = Instant::now();
+    let cancel = CancelToken::new();
+    let buffer = render::threaded_fractal_calc(pool, size, params, &cancel, |_| {
+        if Instant::now() >= deadline {
+            cancel.cancel();
+        }
+    })
+    .map_err(|_| timed_out())?;
+    let coloring = match &request.palette {
+        Some(palette) => ColoringSettings {
+            palette: palette.clone(),
+            ..engine.coloring.clone()
+        },
+        None => engine.coloring.clone(),
+    };
+    let rgba = coloring::recolor(&buffer, &coloring, pool.max_count());
+    let mut text = Look::of(&coloring).png_text();
+    text.push((LOCATION_PNG_KEYWORD, request.viewport.location()));
+    let png = export::png_bytes(request.width, request.height, &rgba, &text)
+        .map_err(|err| Failure::new(500, err))?;
+    let headers = vec![
+        ("X-Fractal", String::from(engine.fractal)),
+        ("X-Location", request.viewport.location()),
+        ("X-Iterations", max_iterations.to_string()),
+        ("X-Palette", coloring.palette.clone()),
+        ("X-Arithmetic", params.arithmetic.name().to_string()),
+        (
+            "X-Render-Milliseconds",
+            started.elapsed().as_millis().to_string(),
+        ),
+    ];
+    Ok(Rendered { png, headers })
+}
+
+fn respond_error(stream: &mut TcpStream, failure: &Failure) {
+    let body = ErrorBody {
+        status: failure.status,
+        error: &failure.message,
+    };
+    let json = json::to_string_pretty(&body).unwrap_or_default();
+    respond(
+        stream,
+        failure.status,
+        "application/json",
+        &[],
+        json.as_bytes(),
+    );
+}
+
+// Writes a whole response and closes the connection; a client that has gone is not an error.
+fn respond(
+    stream: &mut TcpStream,
+    status: u16,
+    content_type: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status,
+        reason(status),
+        content_type,
+        body.len()
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body))
+        .and_then(|()| stream.flush());
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        431 => "Request Header Fields Too Large",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,633 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+
+use crate::adjust::Region;
+use crate::fractal::AngleKind;
+use crate::palette::{Palette, PaletteWrap};
+use crate::postprocess::Stage;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RenderSettings {
+    pub max_iterations: u32,
+    pub resolution_scale: f32,
+    pub antialias: u32,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            max_iterations: 1000,
+            resolution_scale: 1.0,
+            antialias: 1,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QualityProfile {
+    pub name: String,
+    pub settings: RenderSettings,
+}
+
+impl QualityProfile {
+    pub fn builtin() -> Vec<QualityProfile> {
+        vec![
+            QualityProfile {
+                name: String::from("Interactive"),
+                settings: RenderSettings {
+                    max_iterations: 250,
+                    resolution_scale: 0.5,
+                    antialias: 1,
+                },
+            },
+            QualityProfile {
+                name: String::from("Balanced"),
+                settings: RenderSettings::default(),
+            },
+            QualityProfile {
+                name: String::from("Final"),
+                settings: RenderSettings {
+                    max_iterations: 5000,
+                    resolution_scale: 1.0,
+                    antialias: 2,
+                },
+            },
+        ]
+    }
+
+    // The profile among `profiles` called `name`, in any case.
+    pub fn named<'a>(
+        profiles: &'a [QualityProfile],
+        name: &str,
+    ) -> Result<&'a QualityProfile, String> {
+        profiles
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                let names: Vec<&str> = profiles
+                    .iter()
+                    .map(|profile| profile.name.as_str())
+                    .collect();
+                format!(
+                    "unknown quality profile {}, expected {}",
+                    name,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColoringMode {
+    #[default]
+    EscapeTime,
+    FieldLines,
+    // Hue from the direction the orbit escaped in, in sectors, over escape-time brightness.
+    EscapeDirection,
+}
+
+impl ColoringMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            ColoringMode::EscapeTime => "Escape time",
+            ColoringMode::FieldLines => "Field lines",
+            ColoringMode::EscapeDirection => "Escape direction",
+        }
+    }
+
+    pub fn next(self) -> ColoringMode {
+        match self {
+            ColoringMode::EscapeTime => ColoringMode::FieldLines,
+            ColoringMode::FieldLines => ColoringMode::EscapeDirection,
+            ColoringMode::EscapeDirection => ColoringMode::EscapeTime,
+        }
+    }
+
+    // The angle frames need for this mode.
+    pub fn angle(self) -> AngleKind {
+        match self {
+            ColoringMode::EscapeTime => AngleKind::Off,
+            ColoringMode::FieldLines => AngleKind::External,
+            ColoringMode::EscapeDirection => AngleKind::Escape,
+        }
+    }
+}
+
+// The flat colors pixels that did not escape are painted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SolidColor {
+    Black,
+    DarkGray,
+    Gray,
+    White,
+    Navy,
+    Crimson,
+}
+
+impl SolidColor {
+    pub const ALL: [SolidColor; 6] = [
+        SolidColor::Black,
+        SolidColor::DarkGray,
+        SolidColor::Gray,
+        SolidColor::White,
+        SolidColor::Navy,
+        SolidColor::Crimson,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SolidColor::Black => "Black",
+            SolidColor::DarkGray => "Dark gray",
+            SolidColor::Gray => "Gray",
+            SolidColor::White => "White",
+            SolidColor::Navy => "Navy",
+            SolidColor::Crimson => "Crimson",
+        }
+    }
+
+    pub fn rgb(self) -> [u8; 3] {
+        match self {
+            SolidColor::Black => [0, 0, 0],
+            SolidColor::DarkGray => [48, 48, 48],
+            SolidColor::Gray => [128, 128, 128],
+            SolidColor::White => [255, 255, 255],
+            SolidColor::Navy => [0, 0, 80],
+            SolidColor::Crimson => [150, 0, 30],
+        }
+    }
+
+    pub fn next(self) -> SolidColor {
+        let index = SolidColor::ALL
+            .iter()
+            .position(|color| *color == self)
+            .unwrap_or(0);
+        SolidColor::ALL[(index + 1) % SolidColor::ALL.len()]
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColoringSettings {
+    pub mode: ColoringMode,
+    pub palette: String,
+    // How strongly field lines or escape directions modulate the escape-time color, from 0 to 1.
+    pub field_blend: f32,
+    // Output gamma; above 1 brightens midtones.
+    pub gamma: f32,
+    // Palette cycles per period of escape time; higher packs the bands closer together.
+    pub density: f32,
+    // How far along the palette escape time 0 falls, in passes through it.
+    pub palette_offset: f32,
+    // Hue sectors escape directions are quantized into, and how far their hues are turned, in
+    // turns.
+    pub sectors: u32,
+    pub hue_rotation: f32,
+    // Darkens the valleys of the escape-time height field, sampled out to `occlusion_radius`
+    // pixels, by up to `occlusion_strength`.
+    pub occlusion: bool,
+    pub occlusion_radius: u32,
+    pub occlusion_strength: f32,
+    // Stages run over the colored frame, in order.
+    pub post: Vec<Stage>,
+    // What pixels proven inside the set are painted, and those the iteration limit left
+    // unresolved, told apart so that a frame short of iterations does not pass for a finished one.
+    pub interior_color: SolidColor,
+    pub unresolved_color: SolidColor,
+    // How each palette wraps past its ends, by name; palettes not listed wrap smoothly.
+    pub palette_wraps: BTreeMap<String, PaletteWrap>,
+    // The interior and unresolved colors the user picked for palettes, by name, where they
+    // differ from the palette's own; the two fields above hold those of the palette in use.
+    pub interior_overrides: BTreeMap<String, SolidColor>,
+    pub unresolved_overrides: BTreeMap<String, SolidColor>,
+    // Parts of the plane recolored on their own, after everything else, in order.
+    pub regions: Vec<Region>,
+}
+
+impl ColoringSettings {
+    // The palette in use, with its wrap.
+    pub fn palette(&self) -> Palette {
+        Palette::by_name(&self.palette).with_wrap(self.palette_wrap())
+    }
+
+    pub fn palette_wrap(&self) -> PaletteWrap {
+        self.palette_wraps
+            .get(&self.palette)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    // Switches to the palette named `name` with its interior and unresolved colors.
+    pub fn set_palette(&mut self, name: String) {
+        self.palette = name;
+        let palette = Palette::by_name(&self.palette);
+        self.interior_color = palette_color(
+            self.interior_overrides.get(&self.palette).copied(),
+            palette.interior,
+            DEFAULT_INTERIOR,
+        );
+        self.unresolved_color = palette_color(
+            self.unresolved_overrides.get(&self.palette).copied(),
+            palette.unresolved,
+            DEFAULT_UNRESOLVED,
+        );
+    }
+
+    // Picks the interior color for the palette in use; picking its own again forgets the pick.
+    pub fn set_interior_color(&mut self, color: SolidColor) {
+        self.interior_color = color;
+        let preferred = palette_color(None, self.palette().interior, DEFAULT_INTERIOR);
+        choose(
+            &mut self.interior_overrides,
+            &self.palette,
+            color,
+            preferred,
+        );
+    }
+
+    pub fn set_unresolved_color(&mut self, color: SolidColor) {
+        self.unresolved_color = color;
+        let preferred = palette_color(None, self.palette().unresolved, DEFAULT_UNRESOLVED);
+        choose(
+            &mut self.unresolved_overrides,
+            &self.palette,
+            color,
+            preferred,
+        );
+    }
+
+    // Whether the interior and unresolved colors in use were picked for this palette, rather
+    // than being its own or the defaults.
+    pub fn palette_colors_chosen(&self) -> (bool, bool) {
+        (
+            self.interior_overrides.contains_key(&self.palette),
+            self.unresolved_overrides.contains_key(&self.palette),
+        )
+    }
+
+    pub fn set_palette_wrap(&mut self, wrap: PaletteWrap) {
+        if wrap == PaletteWrap::default() {
+            self.palette_wraps.remove(&self.palette);
+        } else {
+            self.palette_wraps.insert(self.palette.clone(), wrap);
+        }
+    }
+}
+
+impl Default for ColoringSettings {
+    fn default() -> Self {
+        ColoringSettings {
+            mode: ColoringMode::default(),
+            palette: String::from("Monochrome"),
+            field_blend: 0.6,
+            gamma: 1.0,
+            density: 1.0,
+            palette_offset: 0.0,
+            sectors: 6,
+            hue_rotation: 0.0,
+            occlusion: false,
+            occlusion_radius: 6,
+            occlusion_strength: 0.6,
+            post: Vec::new(),
+            interior_color: DEFAULT_INTERIOR,
+            unresolved_color: DEFAULT_UNRESOLVED,
+            palette_wraps: BTreeMap::new(),
+            interior_overrides: BTreeMap::new(),
+            unresolved_overrides: BTreeMap::new(),
+            regions: Vec::new(),
+        }
+    }
+}
+
+// The interior and unresolved colors of palettes that state none.
+pub const DEFAULT_INTERIOR: SolidColor = SolidColor::Black;
+pub const DEFAULT_UNRESOLVED: SolidColor = SolidColor::DarkGray;
+
+// A palette's interior or unresolved color: the user's pick for it, else its own, else the
+// default.
+pub fn palette_color(
+    chosen: Option<SolidColor>,
+    preferred: Option<SolidColor>,
+    default: SolidColor,
+) -> SolidColor {
+    chosen.or(preferred).unwrap_or(default)
+}
+
+fn choose(
+    chosen: &mut BTreeMap<String, SolidColor>,
+    palette: &str,
+    color: SolidColor,
+    preferred: SolidColor,
+) {
+    if color == preferred {
+        chosen.remove(palette);
+    } else {
+        chosen.insert(palette.to_string(), color);
+    }
+}
+
+// Settings a bookmark or preset carries besides its view. Restoring one applies only the view
+// unless asked to, so that tweaks made since are not lost; then each of these that is set
+// replaces the live one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Overrides {
+    pub max_iterations: Option<u32>,
+    pub coloring: Option<ColoringSettings>,
+}
+
+impl Overrides {
+    // The live iteration count and coloring with the overrides over them.
+    pub fn merge(
+        &self,
+        max_iterations: u32,
+        coloring: &ColoringSettings,
+    ) -> (u32, ColoringSettings) {
+        (
+            self.max_iterations.unwrap_or(max_iterations),
+            self.coloring.clone().unwrap_or_else(|| coloring.clone()),
+        )
+    }
+
+    // What merging would change in the live settings, one line each, as "name: from -> to".
+    pub fn changes(&self, max_iterations: u32, coloring: &ColoringSettings) -> Vec<String> {
+        let (merged_iterations, merged) = self.merge(max_iterations, coloring);
+        let mut changes = Vec::new();
+        let mut compare = |name: &str, from: String, to: String| {
+            if from != to {
+                changes.push(format!("{}: {} -> {}", name, from, to));
+            }
+        };
+        compare(
+            "iterations",
+            max_iterations.to_string(),
+            merged_iterations.to_string(),
+        );
+        compare(
+            "coloring",
+            coloring.mode.name().to_string(),
+            merged.mode.name().to_string(),
+        );
+        compare("palette", coloring.palette.clone(), merged.palette.clone());
+        compare(
+            "palette wrap",
+            coloring.palette_wrap().name().to_string(),
+            merged.palette_wrap().name().to_string(),
+        );
+        let decimal = |value: f32| format!("{:.2}", value);
+        compare(
+            "mode blend",
+            decimal(coloring.field_blend),
+            decimal(merged.field_blend),
+        );
+        compare("gamma", decimal(coloring.gamma), decimal(merged.gamma));
+        compare(
+            "density",
+            decimal(coloring.density),
+            decimal(merged.density),
+        );
+        compare(
+            "sectors",
+            coloring.sectors.to_string(),
+            merged.sectors.to_string(),
+        );
+        compare(
+            "hue rotation",
+            decimal(coloring.hue_rotation),
+            decimal(merged.hue_rotation),
+        );
+        let occlusion = |settings: &ColoringSettings| {
+            if settings.occlusion {
+                format!(
+                    "radius {}, strength {:.2}",
+                    settings.occlusion_radius, settings.occlusion_strength
+                )
+            } else {
+                String::from("off")
+            }
+        };
+        compare("occlusion", occlusion(coloring), occlusion(&merged));
+        compare(
+            "interior",
+            coloring.interior_color.name().to_string(),
+            merged.interior_color.name().to_string(),
+        );
+        compare(
+            "unresolved",
+            coloring.unresolved_color.name().to_string(),
+            merged.unresolved_color.name().to_string(),
+        );
+        let stages = |settings: &ColoringSettings| {
+            let names: Vec<&str> = settings.post.iter().map(Stage::name).collect();
+            if names.is_empty() {
+                String::from("none")
+            } else {
+                names.join(", ")
+            }
+        };
+        compare(
+            "adjustment regions",
+            coloring.regions.len().to_string(),
+            merged.regions.len().to_string(),
+        );
+        if coloring.post != merged.post {
+            changes.push(format!(
+                "post-processing: {} -> {}",
+                stages(coloring),
+                stages(&merged)
+            ));
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::look::Look;
+    use SolidColor::{Black, Crimson, DarkGray, Gray, Navy, White};
+
+    fn colors(coloring: &ColoringSettings) -> (SolidColor, SolidColor) {
+        (coloring.interior_color, coloring.unresolved_color)
+    }
+
+    // A pick beats the palette's own color, which beats the default.
+    #[test]
+    fn palette_colors_give_way_to_picks() {
+        let orders = [
+            (Some(White), Some(Navy), Black, White),
+            (None, Some(Navy), Black, Navy),
+            (Some(Crimson), None, Black, Crimson),
+            (None, None, DarkGray, DarkGray),
+        ];
+        for (chosen, preferred, default, expected) in orders {
+            assert_eq!(
+                palette_color(chosen, preferred, default),
+                expected,
+                "picked {:?}, preferred {:?} and default {:?}",
+                chosen,
+                preferred,
+                default
+            );
+        }
+    }
+
+    #[test]
+    fn builtin_palettes_state_only_colors_of_their_own() {
+        let pastel = Palette::by_name("Pastel");
+        assert_eq!(pastel.name, "Pastel");
+        assert_eq!(
+            (pastel.interior, pastel.unresolved),
+            (Some(Navy), Some(Gray))
+        );
+        for palette in Palette::builtin() {
+            assert!(
+                palette.interior != Some(Black) && palette.unresolved != Some(DarkGray),
+                "{} states the default colors as its own",
+                palette.name
+            );
+        }
+    }
+
+    // A pick for one palette stays with it across switches, and is forgotten when it matches
+    // the palette's own.
+    #[test]
+    fn picks_stay_with_their_palette() {
+        let mut coloring = ColoringSettings::default();
+        coloring.set_palette(String::from("Pastel"));
+        assert_eq!(colors(&coloring), (Navy, Gray), "switching to Pastel");
+        assert_eq!(coloring.palette_colors_chosen(), (false, false));
+        coloring.set_interior_color(White);
+        coloring.set_palette(String::from("Fire"));
+        assert_eq!(
+            colors(&coloring),
+            (Black, Navy),
+            "switching from Pastel to Fire"
+        );
+        coloring.set_palette(String::from("Pastel"));
+        assert_eq!(
+            colors(&coloring),
+            (White, Gray),
+            "returning to Pastel after picking white"
+        );
+        assert_eq!(coloring.palette_colors_chosen(), (true, false));
+        coloring.set_interior_color(Navy);
+        assert_eq!(
+            coloring.palette_colors_chosen(),
+            (false, false),
+            "picking Pastel's own interior again is remembered as a pick"
+        );
+        assert!(coloring.interior_overrides.is_empty());
+    }
+
+    #[test]
+    fn picks_survive_saving_and_looks() {
+        let mut coloring = ColoringSettings::default();
+        coloring.set_palette(String::from("Pastel"));
+        coloring.set_interior_color(White);
+        let text = toml::to_string(&coloring).expect("coloring settings serialize");
+        let saved = toml::from_str::<ColoringSettings>(&text);
+        assert_eq!(
+            saved.as_ref().ok(),
+            Some(&coloring),
+            "saved coloring reads back as {:?}",
+            saved
+        );
+        let look = Look::of(&coloring).settings();
+        assert!(
+            look.as_ref()
+                .is_ok_and(|settings| settings.interior_overrides.get("Pastel") == Some(&White)),
+            "a look drops the pick of white: {:?}",
+            look
+        );
+    }
+
+    // Settings saved before palettes had colors of their own read with no picks.
+    #[test]
+    fn older_settings_read_without_picks() {
+        let older =
+            toml::from_str::<ColoringSettings>("palette = \"Fire\"\ninterior_color = \"White\"")
+                .expect("older settings read");
+        assert!(older.interior_overrides.is_empty());
+        assert_eq!(older.interior_color, White);
+    }
+
+    #[test]
+    fn palette_wraps_stay_with_their_palette() {
+        let mut coloring = ColoringSettings {
+            palette: String::from("Fire"),
+            ..ColoringSettings::default()
+        };
+        coloring.set_palette_wrap(PaletteWrap::Mirror);
+        let other = ColoringSettings {
+            palette: String::from("Ocean"),
+            ..coloring.clone()
+        };
+        assert_eq!(coloring.palette().wrap, PaletteWrap::Mirror);
+        assert_eq!(
+            other.palette().wrap,
+            PaletteWrap::Smooth,
+            "a wrap set for Fire reaches Ocean"
+        );
+        let look = Look::of(&coloring)
+            .settings()
+            .expect("a look with a wrap loads");
+        assert_eq!(look.palette_wrap(), PaletteWrap::Mirror);
+    }
+
+    fn live() -> ColoringSettings {
+        ColoringSettings {
+            palette: String::from("Fire"),
+            gamma: 1.4,
+            ..ColoringSettings::default()
+        }
+    }
+
+    fn stored() -> ColoringSettings {
+        ColoringSettings {
+            palette: String::from("Ocean"),
+            post: vec![Stage::Gamma { gamma: 1.2 }],
+            ..live()
+        }
+    }
+
+    #[test]
+    fn unset_overrides_change_nothing() {
+        let none = Overrides::default();
+        assert_eq!(none.merge(800, &live()), (800, live()));
+        assert!(none.changes(800, &live()).is_empty());
+        let iterations = Overrides {
+            max_iterations: Some(3000),
+            coloring: None,
+        };
+        assert_eq!(
+            iterations.merge(800, &live()),
+            (3000, live()),
+            "an iteration override does not keep the live coloring"
+        );
+    }
+
+    // Set overrides replace the live settings, listing exactly what changes.
+    #[test]
+    fn set_overrides_replace_the_live_settings() {
+        let full = Overrides {
+            max_iterations: Some(3000),
+            coloring: Some(stored()),
+        };
+        assert_eq!(full.merge(800, &live()), (3000, stored()));
+        assert_eq!(
+            full.changes(800, &live()),
+            [
+                "iterations: 800 -> 3000",
+                "palette: Fire -> Ocean",
+                "post-processing: none -> Gamma",
+            ]
+        );
+        let changes = full.changes(3000, &stored());
+        assert!(
+            changes.is_empty(),
+            "overrides equal to the live settings list {:?}",
+            changes
+        );
+    }
+}
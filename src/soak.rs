@@ -0,0 +1,621 @@
+use iced::event::Event;
+use iced::futures::channel::mpsc::UnboundedReceiver;
+use iced::futures::{executor, StreamExt};
+use iced::keyboard::{self, key};
+use iced::{mouse, window, Point, Size, Vector};
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+use threadpool::ThreadPool;
+
+use mandelbrot::adjust::{Handle, Knob};
+use mandelbrot::annotation::Part;
+use mandelbrot::fractal::{AngleKind, FractalKind};
+use mandelbrot::guides::Edge;
+use mandelbrot::locate::Located;
+use mandelbrot::rawdata;
+use mandelbrot::render::{self, Arithmetic, CancelToken, FrameParams};
+use mandelbrot::storage::Precision;
+use mandelbrot::tilecache::TILE_CACHE;
+use mandelbrot::viewport::Viewport;
+
+use crate::config::Config;
+use crate::controls::Setting;
+use crate::{Mandelbrot, Message};
+
+pub const DEFAULT_STEPS: usize = 10_000;
+
+const MAX_SIZE: u32 = 64;
+const MAX_ITERATIONS: u32 = 200;
+const WATCHDOG: Duration = Duration::from_secs(10);
+
+// Headlessly fires randomized message sequences at the app state, checking invariants after
+// every step. Exits the process with an error on the first violation or stall.
+pub fn run(steps: usize) {
+    match soak(steps) {
+        Ok(completed) => eprintln!("soak: {} steps completed without violations", completed),
+        Err(err) => {
+            eprintln!("soak: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
+// Runs `steps` steps, returning how many completed or what went wrong first.
+fn soak(steps: usize) -> Result<usize, String> {
+    let seed = 0x5eed_1234_abcd_ef01;
+    let data_file = env::temp_dir().join(format!("mandelbrot-soak-{}.mbit", process::id()));
+    write_data_file(&data_file)
+        .map_err(|err| format!("cannot write {}: {}", data_file.display(), err))?;
+    let (tx, rx) = channel();
+    let dropped = data_file.clone();
+    thread::spawn(move || {
+        let mut rng = Rng(seed);
+        let mut app = Mandelbrot::new(soak_config(), false);
+        // Render streams are drained at random points, so results arrive late and interleaved
+        // with newer requests as they can in the real event loop.
+        let mut renders: Vec<UnboundedReceiver<Message>> = Vec::new();
+        // Exports are kept apart and never waited on, since one paused in the queue only
+        // resumes when a later message says so.
+        let mut exports: Vec<UnboundedReceiver<Message>> = Vec::new();
+        renders.extend(app.apply(Message::EventOccurred(Event::Window(
+            window::Event::Resized(Size::new(MAX_SIZE as f32, MAX_SIZE as f32)),
+        ))));
+        for step in 0..steps {
+            let messages = match rng.below(8) {
+                0 | 1 => next_render_event(&mut rng, &mut renders)
+                    .into_iter()
+                    .collect(),
+                2 => settle(&mut renders),
+                3 => next_render_event(&mut rng, &mut exports)
+                    .into_iter()
+                    .collect(),
+                _ => vec![random_message(&mut rng, &app, &dropped)],
+            };
+            for message in messages {
+                let previous_generation = app.generation;
+                let previous_installed = app.installed_generation;
+                let description = format!("{:?}", message);
+                let previous_export = running_export(&app);
+                let mut streams = app.apply(message);
+                // A newly started export's stream comes last.
+                if running_export(&app).is_some_and(|id| Some(id) != previous_export) {
+                    exports.extend(streams.pop());
+                }
+                renders.extend(streams);
+                if let Err(violation) =
+                    check_invariants(&app, previous_generation, previous_installed)
+                {
+                    tx.send(Err(format!(
+                        "step {}: {} after {}",
+                        step, violation, description
+                    )))
+                    .expect("soak supervisor is waiting");
+                    return;
+                }
+            }
+            tx.send(Ok(step)).expect("soak supervisor is waiting");
+        }
+    });
+
+    let mut completed = 0;
+    let outcome = loop {
+        match rx.recv_timeout(WATCHDOG) {
+            Ok(Ok(step)) => completed = step + 1,
+            Ok(Err(violation)) => break Err(format!("invariant violated at {}", violation)),
+            Err(RecvTimeoutError::Timeout) => {
+                break Err(format!(
+                    "no progress for {:?} after step {}",
+                    WATCHDOG, completed
+                ))
+            }
+            Err(RecvTimeoutError::Disconnected) => break Ok(completed),
+        }
+    };
+    let _ = std::fs::remove_file(&data_file);
+    outcome
+}
+
+fn soak_config() -> Config {
+    let mut config = Config::default();
+    for state in config.fractals.values_mut() {
+        for profile in &mut state.profiles {
+            profile.settings.max_iterations = profile.settings.max_iterations.min(MAX_ITERATIONS);
+        }
+    }
+    // Short enough that idle refinement kicks in between bursts of messages.
+    config.idle_delay_ms = 2;
+    // Small enough that the log is pruned now and then.
+    config.exploration_log = true;
+    config.exploration_log_limit = Some(8);
+    config
+}
+
+// Takes the next event from a random pending render, dropping streams that have finished.
+fn next_render_event(
+    rng: &mut Rng,
+    renders: &mut Vec<UnboundedReceiver<Message>>,
+) -> Option<Message> {
+    if renders.is_empty() {
+        return None;
+    }
+    let index = rng.below(renders.len() as u32) as usize;
+    match renders[index].try_next() {
+        Ok(Some(message)) => Some(message),
+        Ok(None) => {
+            renders.remove(index);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+// Waits for the newest render to finish and returns everything it sent.
+fn settle(renders: &mut Vec<UnboundedReceiver<Message>>) -> Vec<Message> {
+    renders
+        .pop()
+        .map_or_else(Vec::new, |events| executor::block_on(events.collect()))
+}
+
+fn running_export(app: &Mandelbrot) -> Option<u64> {
+    app.export_cancel.as_ref().map(|(id, _)| *id)
+}
+
+fn check_invariants(
+    app: &Mandelbrot,
+    previous_generation: u64,
+    previous_installed: u64,
+) -> Result<(), String> {
+    if app.generation < previous_generation {
+        return Err(format!(
+            "generation went backwards from {} to {}",
+            previous_generation, app.generation
+        ));
+    }
+    if app.installed_generation > app.generation {
+        return Err(String::from("installed a frame from a future generation"));
+    }
+    if app.installed_generation != previous_installed && app.installed_generation != app.generation
+    {
+        return Err(format!(
+            "installed stale generation {} while {} is current",
+            app.installed_generation, app.generation
+        ));
+    }
+    if app.threadpool.max_count() != app.render_threads() {
+        return Err(format!(
+            "{} workers while the energy saver wants {}",
+            app.threadpool.max_count(),
+            app.render_threads()
+        ));
+    }
+    if app.exports.jobs.iter().filter(|job| job.running).count() > 1 {
+        return Err(String::from("more than one export running"));
+    }
+    if let Some(job) = app.exports.running() {
+        let Some((id, cancel)) = &app.export_cancel else {
+            return Err(format!("export {} runs without a cancel token", job.id));
+        };
+        if *id != job.id {
+            return Err(format!("export {} runs under the token of {}", job.id, id));
+        }
+        let busy = app.rendering.as_ref().is_some_and(|job| !job.refining);
+        if cancel.is_paused() != app.exports.hold_running(busy) {
+            return Err(format!(
+                "export {} paused is {} while the queue wants {}",
+                job.id,
+                cancel.is_paused(),
+                app.exports.hold_running(busy)
+            ));
+        }
+    }
+    if let Some(limit) = app.config.exploration_log_limit {
+        if app.journal.entries.len() > limit {
+            return Err(format!(
+                "{} log entries beyond the limit of {}",
+                app.journal.entries.len(),
+                limit
+            ));
+        }
+    }
+    let cached = TILE_CACHE.stats().bytes;
+    if cached > app.config.tile_cache_mb as usize * 1024 * 1024 {
+        return Err(format!(
+            "tile cache holds {} bytes over its {} MiB budget",
+            cached, app.config.tile_cache_mb
+        ));
+    }
+    if app.buffer.values.len() != app.buffer.width * app.buffer.height {
+        return Err(String::from("buffer length does not match its dimensions"));
+    }
+    if app.buffer.values.is_empty() {
+        return Ok(());
+    }
+    let factor = app.buffer_antialias;
+    let expected = (
+        (app.buffer.width / factor) as u32,
+        (app.buffer.height / factor) as u32,
+    );
+    if app.installed_generation == app.generation && app.image_size != expected {
+        return Err(format!(
+            "image is {:?} but cached buffer implies {:?}",
+            app.image_size, expected
+        ));
+    }
+    let viewport = app.viewport;
+    let finite = viewport.center_re.is_finite()
+        && viewport.center_im.is_finite()
+        && viewport.width.is_finite();
+    if !finite || viewport.width <= 0.0 {
+        return Err(format!("degenerate viewport {:?}", viewport));
+    }
+    Ok(())
+}
+
+// A small frame for exercising the data file view.
+fn write_data_file(path: &Path) -> Result<(), String> {
+    let size = Size::new(24.0, 16.0);
+    let params = FrameParams {
+        viewport: Viewport::home(FractalKind::BurningShip, size),
+        max_iterations: MAX_ITERATIONS,
+        fractal: FractalKind::BurningShip,
+        angle: AngleKind::External,
+        precision: Precision::Full,
+        coarse_prepass: false,
+        arithmetic: Arithmetic::F64,
+    };
+    let pool = ThreadPool::new(1);
+    let buffer = render::threaded_fractal_calc(&pool, size, params, &CancelToken::new(), |_| {})
+        .map_err(|err| err.to_string())?;
+    rawdata::write(path, &buffer)
+}
+
+fn random_message(rng: &mut Rng, app: &Mandelbrot, data_file: &Path) -> Message {
+    let point = |rng: &mut Rng| {
+        Point::new(
+            rng.below(app.window_size.width as u32 + 1) as f32,
+            rng.below(app.window_size.height as u32 + 1) as f32,
+        )
+    };
+    match rng.below(153) {
+        0 => Message::EventOccurred(Event::Window(window::Event::Resized(Size::new(
+            (1 + rng.below(MAX_SIZE)) as f32,
+            (1 + rng.below(MAX_SIZE)) as f32,
+        )))),
+        1..=3 => Message::EventOccurred(Event::Mouse(mouse::Event::CursorMoved {
+            position: point(rng),
+        })),
+        4 => Message::EventOccurred(Event::Mouse(mouse::Event::ButtonPressed(random_button(
+            rng,
+        )))),
+        5 => Message::EventOccurred(Event::Mouse(mouse::Event::ButtonReleased(random_button(
+            rng,
+        )))),
+        6 => key_press(
+            match rng.below(14) {
+                0 => keyboard::Key::Character("q".into()),
+                1 => keyboard::Key::Character("s".into()),
+                2 => keyboard::Key::Character("f".into()),
+                3 => keyboard::Key::Character("p".into()),
+                4 => keyboard::Key::Character("r".into()),
+                5 | 6 => keyboard::Key::Named(key::Named::Escape),
+                7 => keyboard::Key::Named(key::Named::Home),
+                8 => keyboard::Key::Named(key::Named::Tab),
+                9 => keyboard::Key::Named(key::Named::Enter),
+                10 => keyboard::Key::Named(key::Named::Delete),
+                _ => random_arrow(rng),
+            },
+            keyboard::Modifiers::default(),
+        ),
+        7 => Message::MaxIterationsChanged(50 + rng.below(MAX_ITERATIONS)),
+        8 => Message::ResolutionScaleChanged(0.25 * (1 + rng.below(4)) as f32),
+        9 => Message::AntialiasChanged(1 + rng.below(4)),
+        10 => Message::PaletteCycled,
+        11 => Message::ColoringModeCycled,
+        12 => Message::FieldBlendChanged(rng.below(21) as f32 * 0.05),
+        13 => Message::BufferPrecisionCycled,
+        14 => Message::GammaChanged(0.2 + rng.below(57) as f32 * 0.05),
+        15 => Message::DensityChanged(0.1 * (1 + rng.below(40)) as f32),
+        16 => Message::EventOccurred(Event::Window(window::Event::FileDropped(
+            if rng.below(4) == 0 {
+                PathBuf::from("/nonexistent/soak.mbit")
+            } else {
+                data_file.to_path_buf()
+            },
+        ))),
+        17 => Message::CoarsePrepassToggled,
+        18 => Message::Undo,
+        19 => Message::Redo,
+        20 => Message::GotoChanged(
+            [
+                "-0,743 0,131",
+                "-0.75, 0.1, 1e-3",
+                "1,2.3 4",
+                "",
+                "0 0 -1",
+                "nan 0",
+                "-0.743643887037151 0.131825904205330 1e-20",
+            ][rng.below(7) as usize]
+                .to_string(),
+        ),
+        21 => Message::GotoSubmitted,
+        22 => Message::DecimalSeparatorCycled,
+        23 => Message::DigitGroupingToggled,
+        24 => Message::BookmarkAdded,
+        25 => Message::BookmarkSelected(rng.below(4) as u64, rng.below(2) == 0),
+        26 => Message::BookmarkDeleted(rng.below(4) as u64),
+        27 => Message::PresetSelected(rng.below(7) as usize, rng.below(2) == 0),
+        28 => Message::BookmarksToggled,
+        29 => Message::DoubleDoubleToggled,
+        30 => Message::IdleTick(app.generation),
+        31 => Message::TileOrderToggled,
+        32 => Message::ScaleFactorChanged([1.0, 1.25, 1.5, 1.75, 2.0][rng.below(5) as usize]),
+        33 => Message::ScaleSettled(app.scale_token),
+        34 => Message::CrosshairToggled,
+        35 => Message::ThirdsToggled,
+        36 => Message::GuideColorCycled,
+        37 => Message::GuideOpacityCycled,
+        38 => Message::GuidesInExportsToggled,
+        39 => Message::LookExported,
+        40 => Message::MinibrotsToggled,
+        41 => Message::MinibrotSelected(rng.below(3) as usize),
+        42 => Message::EnergySaverCycled,
+        43 | 44 => Message::ExportQueued,
+        45 => Message::QueueToggled,
+        46..=49 => {
+            let jobs = &app.exports.jobs;
+            let Some(id) =
+                (!jobs.is_empty()).then(|| jobs[rng.below(jobs.len() as u32) as usize].id)
+            else {
+                return Message::ExportQueued;
+            };
+            match rng.below(4) {
+                0 => Message::ExportMoved(id, rng.below(5) as isize - 2),
+                1 => Message::ExportPauseToggled(id),
+                _ => Message::ExportCancelled(id),
+            }
+        }
+        50 => Message::JournalToggled,
+        51 => Message::ExplorationLogToggled,
+        52 | 53 => {
+            let entries = &app.journal.entries;
+            if entries.is_empty() {
+                Message::JournalExported
+            } else {
+                Message::JournalSelected(entries[rng.below(entries.len() as u32) as usize].id)
+            }
+        }
+        54 => key_press(
+            if rng.below(2) == 0 {
+                keyboard::Key::Named(key::Named::Tab)
+            } else {
+                random_arrow(rng)
+            },
+            keyboard::Modifiers::SHIFT,
+        ),
+        55 => Message::FocusMoved(rng.below(5) as isize - 2),
+        56 => Message::FocusActivated,
+        57 => Message::FocusAdjusted(rng.below(3) as i32 - 1),
+        58 => Message::FocusDeleted,
+        59 => Message::PanelsDismissed,
+        60 => Message::SelectionStarted(point(rng)),
+        61 => Message::SelectionResized(point(rng)),
+        62 => Message::SelectionMoved(Vector::new(
+            rng.below(9) as f32 - 4.0,
+            rng.below(9) as f32 - 4.0,
+        )),
+        63 => Message::SelectionCommitted,
+        64 => Message::SelectionCancelled,
+        65 => Message::SettingsToggled,
+        66 => Message::OpenPathChanged(if rng.below(4) == 0 {
+            String::from("/nonexistent/soak.mbit")
+        } else {
+            data_file.display().to_string()
+        }),
+        67 => Message::OpenPathSubmitted,
+        68 => Message::PaletteCycledBack,
+        69 => Message::ViewPanned(
+            (rng.below(21) as f64 - 10.0) * 0.01,
+            (rng.below(21) as f64 - 10.0) * 0.01,
+        ),
+        70 => Message::ViewZoomed([0.5, 0.9, 1.1, 2.0][rng.below(4) as usize]),
+        71 => Message::ColorManagementToggled,
+        72 => Message::PrefetchToggled,
+        73 => Message::InvertYToggled,
+        74 => Message::StallWaited,
+        75 => Message::StallDrafted,
+        76 => Message::SectorsChanged(2 + rng.below(23)),
+        77 => Message::HueRotationChanged(rng.below(101) as f32 * 0.01),
+        78 => Message::TourSkipped,
+        79 => Message::PathToggled,
+        80 => Message::KeyframeAdded,
+        81 => Message::KeyframeSelected(rng.below(4) as usize),
+        82 => Message::KeyframeDeleted(rng.below(4) as usize),
+        83 => Message::KeyframeHoldCycled(rng.below(4) as usize),
+        84 => Message::KeyframeTravelCycled(rng.below(4) as usize),
+        85 => Message::KeyframeEasingCycled(rng.below(4) as usize),
+        86 => Message::PathScrubbed(rng.below(200) as f64 * 0.1),
+        87 => Message::PathScrubReleased,
+        88 => Message::TextPasted(
+            [
+                "",
+                "(-0.7436, 0.1318)",
+                "re=-0.7436 im=0.1318 zoom 1e9",
+                "2024-05-01",
+                "x = 1e300, y = 0",
+                "0.2869 - 0.0142i width 0",
+            ][rng.below(6) as usize]
+                .to_string(),
+        ),
+        89 => Message::PasteConfirmed,
+        90 => Message::PasteDismissed,
+        91 => Message::OcclusionToggled,
+        92 => Message::OcclusionRadiusChanged(1 + rng.below(16)),
+        93 => Message::OcclusionStrengthChanged(rng.below(21) as f32 * 0.05),
+        94 => Message::CommandPaletteToggled,
+        95 => Message::CommandQueryChanged(
+            ["", "zoom", "next", "gc", "xyz"][rng.below(5) as usize].to_string(),
+        ),
+        96 => Message::CommandSubmitted,
+        97 => Message::CommandRun(
+            [
+                "view.zoom_in",
+                "view.goto",
+                "coloring.palette_next",
+                "panels.settings",
+                "panels.path",
+                "no.such_command",
+            ][rng.below(6) as usize],
+        ),
+        98 => Message::SettingFocused(Setting::ALL[rng.below(Setting::ALL.len() as u32) as usize]),
+        99 => Message::SafeAreaCycled,
+        100 => Message::SafeMarginChanged(
+            Edge::ALL[rng.below(4) as usize],
+            rng.below(51) as f32 * 0.01,
+        ),
+        101 => Message::ExportPaddingChanged(rng.below(33) * 8),
+        102 => Message::PostStageAdded(rng.below(5) as usize),
+        103 => Message::PostStageAdjusted(rng.below(4) as usize, rng.below(2) == 0),
+        104 => Message::PostStageMoved(rng.below(4) as usize, rng.below(3) as isize - 1),
+        105 => Message::PostStageRemoved(rng.below(4) as usize),
+        106 => Message::PostExpressionChanged(
+            [
+                "",
+                "0.5 + 0.5 * cos(smooth / 8)",
+                "mix(1, x, inside)",
+                "de * 2",
+                "sqrt(",
+                "log(smooth)",
+            ][rng.below(6) as usize]
+                .to_string(),
+        ),
+        107 => Message::PostExpressionSubmitted,
+        108 => Message::ImageLocated(Ok(Located {
+            viewport: Viewport::new(
+                rng.below(300) as f64 * 0.01 - 2.0,
+                rng.below(200) as f64 * 0.01 - 1.0,
+                0.001 * (1 + rng.below(3000)) as f64,
+            ),
+            confidence: rng.below(101) as f64 * 0.01,
+            levels: rng.below(5) as usize,
+            too_deep: rng.below(4) == 0,
+        })),
+        109 => Message::ImageLocated(Err(String::from("the image is one flat color"))),
+        110 => Message::BookmarkRestoreToggled(rng.below(4) as u64),
+        111 => Message::ProjectSaved(rng.below(2) == 0),
+        112 => Message::SelectionDragged(point(rng)),
+        113 => Message::InteriorColorCycled,
+        114 => Message::UnresolvedColorCycled,
+        115 => Message::OrbitSoundToggled,
+        116 => Message::BookmarksChanged,
+        117 => Message::AnnotationAdded,
+        118 => Message::AnnotationGrabbed(
+            rng.below(4) as u64,
+            if rng.below(2) == 0 {
+                Part::Anchor
+            } else {
+                Part::Tail
+            },
+        ),
+        119 => Message::AnnotationDragged(point(rng)),
+        120 => Message::AnnotationReleased,
+        121 => Message::AnnotationTextChanged(format!("Feature {}", rng.below(100))),
+        122 => Message::AnnotationArrowToggled,
+        123 => Message::AnnotationRangeSet(rng.below(2) == 0),
+        124 => Message::AnnotationRangeCleared,
+        125 => Message::AnnotationDeleted,
+        126 => Message::AnnotationEditorClosed,
+        127 => Message::AnnotationsToggled,
+        128 => Message::AnnotationsInExportsToggled,
+        129 => Message::HybridStepCycled(rng.below(9) as usize),
+        130 => Message::HybridStepAdded,
+        131 => Message::HybridStepRemoved(rng.below(9) as usize),
+        132 => Message::AutoLevelsApplied,
+        133 => Message::AutoLevelsAfterRenderToggled,
+        134 => Message::PaletteOffsetChanged(rng.below(101) as f32 / 100.0),
+        135 => Message::Peeked(rng.below(2) == 0),
+        136 => Message::PaletteWrapCycled,
+        137 => Message::AnchorAtCursor,
+        138 => Message::MinibrotAnchored(rng.below(3) as usize),
+        139 => Message::RelativeBookmarkAdded,
+        140 => Message::RelativeBookmarkApplied(rng.below(4) as u64),
+        141 => Message::PerfHudToggled,
+        142 => Message::LocationCopied,
+        143 => Message::CpuCapChanged(rng.below(101)),
+        144 => Message::RegionsEdited,
+        145 => Message::RegionAdded,
+        146 => Message::RegionGrabbed(
+            rng.below(4) as u64,
+            if rng.below(2) == 0 {
+                Handle::Center
+            } else {
+                Handle::Corner
+            },
+        ),
+        147 => Message::RegionDragged(point(rng)),
+        148 => Message::RegionReleased,
+        149 => Message::RegionShapeToggled,
+        150 => Message::RegionAdjusted(
+            Knob::ALL[rng.below(Knob::ALL.len() as u32) as usize],
+            rng.below(2) == 0,
+        ),
+        151 => Message::RegionDeleted,
+        152 => Message::ExportProfileCycled,
+        _ => Message::SettingsReleased,
+    }
+}
+
+fn random_arrow(rng: &mut Rng) -> keyboard::Key {
+    keyboard::Key::Named(match rng.below(4) {
+        0 => key::Named::ArrowLeft,
+        1 => key::Named::ArrowRight,
+        2 => key::Named::ArrowUp,
+        _ => key::Named::ArrowDown,
+    })
+}
+
+fn random_button(rng: &mut Rng) -> mouse::Button {
+    if rng.below(4) == 0 {
+        mouse::Button::Right
+    } else {
+        mouse::Button::Left
+    }
+}
+
+fn key_press(key: keyboard::Key, modifiers: keyboard::Modifiers) -> Message {
+    Message::EventOccurred(Event::Keyboard(keyboard::Event::KeyPressed {
+        key: key.clone(),
+        modified_key: key,
+        physical_key: key::Physical::Unidentified(key::NativeCode::Unidentified),
+        location: keyboard::Location::Standard,
+        modifiers,
+        text: None,
+    }))
+}
+
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, bound: u32) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % bound as u64) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_few_hundred_random_steps_keep_every_invariant() {
+        let _starting = crate::tests::starting();
+        assert_eq!(soak(300), Ok(300));
+    }
+}
@@ -0,0 +1,238 @@
+use num::complex::Complex;
+
+use std::time::{Duration, Instant};
+
+use crate::fractal::FractalKind;
+
+// Orbit points played per sound, each for `NOTE_SECONDS`, so a sound lasts at most a third of a
+// second.
+pub const NOTES: usize = 32;
+pub const NOTE_SECONDS: f32 = 0.01;
+// Pitches |z| is mapped between, logarithmically: the origin sounds lowest and anything at or
+// past the escape radius highest, so escaping orbits sweep upward before they stop.
+pub const LOW_PITCH: f32 = 220.0;
+pub const HIGH_PITCH: f32 = 1760.0;
+pub const AMPLITUDE: f32 = 0.2;
+// Ramps at either end of a sound, so it starts and stops without a click.
+const FADE_SECONDS: f32 = 0.005;
+// How long the cursor has to rest on a point before it is played.
+pub const DEBOUNCE: Duration = Duration::from_millis(80);
+
+// The orbit of `c` as far as a sound plays it: the points z takes, ending with the first one
+// outside the escape radius if it gets there.
+pub fn orbit(kind: FractalKind, c: Complex<f64>, max_iterations: u32) -> Vec<Complex<f64>> {
+    let mut z: Complex<f64> = Complex::new(0.0, 0.0);
+    let mut orbit = Vec::with_capacity(NOTES);
+    for n in 0..max_iterations.min(NOTES as u32) {
+        if kind.folds(n) {
+            z = Complex::new(z.re.abs(), z.im.abs());
+        }
+        z = z * z + c;
+        orbit.push(z);
+        if z.norm() >= 2.0 {
+            break;
+        }
+    }
+    orbit
+}
+
+// The pitch, in Hz, an orbit point is played at.
+pub fn pitch(z: Complex<f64>) -> f32 {
+    let t = (z.norm() / 2.0).min(1.0) as f32;
+    LOW_PITCH * (HIGH_PITCH / LOW_PITCH).powf(t)
+}
+
+// Mono samples at `sample_rate` playing `orbit` as a run of sine notes, one per point. The phase
+// carries over from note to note, so an orbit settled on a point holds one steady tone, a cycle
+// trills between its points and a chaotic orbit sounds like noise.
+pub fn samples(orbit: &[Complex<f64>], sample_rate: u32) -> Vec<f32> {
+    let note_len = (NOTE_SECONDS * sample_rate as f32).round().max(1.0) as usize;
+    let total = orbit.len().min(NOTES) * note_len;
+    let fade = ((FADE_SECONDS * sample_rate as f32) as usize).clamp(1, total.max(2) / 2);
+    let mut out = Vec::with_capacity(total);
+    let mut phase = 0.0f32;
+    for z in orbit.iter().take(NOTES) {
+        let step = pitch(*z) / sample_rate as f32;
+        for _ in 0..note_len {
+            let index = out.len();
+            let envelope = (index.min(total - 1 - index) as f32 / fade as f32).min(1.0);
+            out.push(AMPLITUDE * envelope * (phase * std::f32::consts::TAU).sin());
+            phase = (phase + step).fract();
+        }
+    }
+    out
+}
+
+// How long the sound of an orbit of `points` points lasts.
+pub fn duration(points: usize) -> Duration {
+    Duration::from_secs_f32(points.min(NOTES) as f32 * NOTE_SECONDS)
+}
+
+// When hovered points are played: once the cursor has rested on one for `DEBOUNCE`, and never
+// before the last sound has finished, so that moving the cursor neither stacks sounds nor plays
+// every point it crosses.
+#[derive(Clone, Debug)]
+pub struct Pacer<T> {
+    pending: Option<(T, Instant)>,
+    busy_until: Option<Instant>,
+}
+
+impl<T> Default for Pacer<T> {
+    fn default() -> Self {
+        Pacer {
+            pending: None,
+            busy_until: None,
+        }
+    }
+}
+
+impl<T> Pacer<T> {
+    // The cursor moved onto `what` at `now`; it replaces whatever was waiting to be played.
+    pub fn request(&mut self, what: T, now: Instant) {
+        self.pending = Some((what, now));
+    }
+
+    pub fn cancel(&mut self) {
+        self.pending = None;
+    }
+
+    // What to play at `now`, if anything is due, given that it will sound for `length`.
+    pub fn due(&mut self, now: Instant, length: impl FnOnce(&T) -> Duration) -> Option<T> {
+        let ready = self.ready_at()?;
+        if now < ready {
+            return None;
+        }
+        let (what, _) = self.pending.take()?;
+        self.busy_until = Some(now + length(&what));
+        Some(what)
+    }
+
+    // How long from `now` until something waiting could be due, or None when nothing waits.
+    pub fn wait(&self, now: Instant) -> Option<Duration> {
+        self.ready_at()
+            .map(|ready| ready.saturating_duration_since(now))
+    }
+
+    fn ready_at(&self) -> Option<Instant> {
+        let (_, requested) = self.pending.as_ref()?;
+        let rested = *requested + DEBOUNCE;
+        Some(self.busy_until.map_or(rested, |busy| busy.max(rested)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48_000;
+
+    // The points of the orbit of `re`, `im`, their pitches and the sound they make.
+    fn play(re: f64, im: f64) -> (usize, Vec<f32>, Vec<f32>) {
+        let orbit = orbit(FractalKind::Mandelbrot, Complex::new(re, im), 1000);
+        let pitches: Vec<f32> = orbit.iter().map(|z| pitch(*z)).collect();
+        (orbit.len(), pitches, samples(&orbit, RATE))
+    }
+
+    fn note_samples() -> usize {
+        (NOTE_SECONDS * RATE as f32).round() as usize
+    }
+
+    #[test]
+    fn bounded_orbits_play_every_note_and_start_and_end_silent() {
+        let (points, _, samples) = play(-0.5, 0.1);
+        assert_eq!(
+            (points, samples.len()),
+            (NOTES, NOTES * note_samples()),
+            "an orbit that stays bounded plays {} points in {} samples",
+            points,
+            samples.len()
+        );
+        assert!(
+            samples.iter().all(|sample| sample.abs() <= AMPLITUDE),
+            "a sound is louder than its amplitude"
+        );
+        assert!(
+            samples.first() == Some(&0.0) && samples.last().is_some_and(|last| last.abs() <= 1e-6),
+            "a sound does not start and end silent"
+        );
+        assert!(
+            samples == play(-0.5, 0.1).2,
+            "the same orbit sounds different twice"
+        );
+    }
+
+    // An attracting fixed point settles into one tone.
+    #[test]
+    fn fixed_points_settle_into_one_tone() {
+        let (_, pitches, _) = play(-0.5, 0.1);
+        let settled = &pitches[pitches.len() - 8..];
+        assert!(
+            settled
+                .iter()
+                .all(|pitch| (pitch - settled[0]).abs() <= 1.0),
+            "an attracting fixed point plays {:?}",
+            settled
+        );
+    }
+
+    // The period-2 orbit of -1 alternates between the lowest pitch and a higher one.
+    #[test]
+    fn cycles_trill() {
+        let (_, pitches, _) = play(-1.0, 0.0);
+        let trill = pitches
+            .chunks_exact(2)
+            .all(|pair| pair[1] == LOW_PITCH && pair[0] > LOW_PITCH);
+        assert!(trill, "the period-2 cycle plays {:?}", &pitches[..4]);
+    }
+
+    // An escape sweeps up to the top pitch and stops there.
+    #[test]
+    fn escapes_sweep_up_and_stop_early() {
+        let (points, pitches, samples) = play(0.5, 0.5);
+        assert!(
+            points < NOTES
+                && samples.len() == points * note_samples()
+                && pitches.last() == Some(&HIGH_PITCH)
+                && pitches.iter().rev().nth(1) < pitches.last(),
+            "an escaping orbit plays {:?}",
+            pitches
+        );
+    }
+
+    #[test]
+    fn the_pacer_plays_where_the_cursor_rests_one_sound_at_a_time() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let length = |_: &u32| Duration::from_millis(300);
+        let mut pacer = Pacer::default();
+        // The cursor crossing points every 10 ms plays none of them.
+        for step in 0..20 {
+            pacer.request(step, at(step as u64 * 10));
+            assert_eq!(
+                pacer.due(at(step as u64 * 10 + 5), length),
+                None,
+                "a point is played while the cursor moves"
+            );
+        }
+        let rested = at(190) + DEBOUNCE;
+        assert_eq!(pacer.wait(at(190)), Some(DEBOUNCE));
+        assert_eq!(
+            pacer.due(rested, length),
+            Some(19),
+            "the last point is not played once the cursor rests"
+        );
+        // Resting on another point before that sound is over waits for it to finish.
+        pacer.request(20, rested);
+        let finished = rested + Duration::from_millis(300);
+        assert_eq!(pacer.due(finished - Duration::from_millis(1), length), None);
+        assert_eq!(pacer.due(finished, length), Some(20));
+        pacer.request(21, finished);
+        pacer.cancel();
+        assert_eq!(pacer.wait(finished), None);
+        assert_eq!(
+            pacer.due(at(10_000), length),
+            None,
+            "a point is played after the cursor left"
+        );
+    }
+}
@@ -0,0 +1,373 @@
+use iced::Size;
+
+use std::fmt;
+#[cfg(test)]
+use std::sync::{PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+#[cfg(test)]
+use num::complex::Complex;
+
+use threadpool::ThreadPool;
+
+#[cfg(test)]
+use crate::fractal::{self, AngleKind, FractalKind, Hybrid};
+use crate::render::{self, CancelToken, FrameParams, IterationBuffer};
+#[cfg(test)]
+use crate::render::{Arithmetic, UNRESOLVED};
+use crate::storage::Precision;
+use crate::tiling::Focus;
+#[cfg(test)]
+use crate::viewport::Viewport;
+
+pub const HISTOGRAM_BUCKETS: usize = 16;
+// Canonical views are small so that checking all of them single-threaded takes a moment.
+pub const CANONICAL_SIZE: Size = Size::new(160.0, 120.0);
+// Points per canonical view at which the tuned kernel is compared with the reference one.
+#[cfg(test)]
+const KERNEL_GRID: Size = Size::new(640.0, 480.0);
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// A fingerprint of a frame's iteration data, for catching numeric changes that images would hide
+// within their tolerance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameStats {
+    pub width: usize,
+    pub height: usize,
+    // Sum of the escape counts of all escaped pixels.
+    pub escape_sum: u64,
+    // Pixels that did not escape, proven inside or not.
+    pub interior: usize,
+    // Escaped pixels by escape count, in equal slices of the iteration limit.
+    pub histogram: [usize; HISTOGRAM_BUCKETS],
+    // FNV-1a over the dimensions and the bits of every value and angle, so it does not depend on
+    // the standard library's hasher.
+    pub hash: u64,
+}
+
+impl FrameStats {
+    pub fn of(buffer: &IterationBuffer, max_iterations: u32) -> FrameStats {
+        let mut stats = FrameStats {
+            width: buffer.width,
+            height: buffer.height,
+            escape_sum: 0,
+            interior: 0,
+            histogram: [0; HISTOGRAM_BUCKETS],
+            hash: FNV_OFFSET,
+        };
+        stats.hash_word(buffer.width as u32);
+        stats.hash_word(buffer.height as u32);
+        for index in 0..buffer.values.len() {
+            let value = buffer.values.get(index);
+            stats.hash_word(value.to_bits());
+            if !render::escaped(value) {
+                stats.interior += 1;
+                continue;
+            }
+            stats.escape_sum += value as u64;
+            let bucket =
+                (value as u64 * HISTOGRAM_BUCKETS as u64 / max_iterations.max(1) as u64) as usize;
+            stats.histogram[bucket.min(HISTOGRAM_BUCKETS - 1)] += 1;
+        }
+        for index in 0..buffer.angles.len() {
+            stats.hash_word(buffer.angles.get(index).to_bits());
+        }
+        stats
+    }
+
+    fn hash_word(&mut self, word: u32) {
+        for byte in word.to_le_bytes() {
+            self.hash = (self.hash ^ byte as u64).wrapping_mul(FNV_PRIME);
+        }
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "size: {}x{}", self.width, self.height)?;
+        writeln!(f, "escape sum: {}", self.escape_sum)?;
+        writeln!(f, "interior: {}", self.interior)?;
+        writeln!(f, "histogram: {:?}", self.histogram)?;
+        write!(f, "hash: {:016x}", self.hash)
+    }
+}
+
+// Renders `params` at `size` on a single thread at full precision and fingerprints the result.
+pub fn frame_checksum(size: Size, params: FrameParams) -> FrameStats {
+    let params = FrameParams {
+        precision: Precision::Full,
+        ..params
+    };
+    let buffer = render::render_focused(
+        &ThreadPool::new(1),
+        size,
+        params,
+        &CancelToken::new(),
+        &Focus::default(),
+        |_| {},
+    )
+    .expect("renders without a cancel request complete");
+    FrameStats::of(&buffer, params.max_iterations)
+}
+
+// Renders `params` at `size` on a single thread, for tests that look at the frame itself.
+#[cfg(test)]
+pub(crate) fn render_frame(size: Size, params: FrameParams) -> IterationBuffer {
+    let pool = ThreadPool::new(1);
+    render::render_focused(
+        &pool,
+        size,
+        params,
+        &CancelToken::new(),
+        &Focus::default(),
+        |_| {},
+    )
+    .expect("renders without a cancel request complete")
+}
+
+// FNV-1a over an image's bytes, for pinning images like the canonical views' stats.
+#[cfg(test)]
+pub(crate) fn image_hash(rgba: &[u8]) -> u64 {
+    rgba.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// Tests that change process-wide render settings, the CPU cap and the tile cache, hold this for
+// writing; tests whose renders those settings would upset hold it for reading.
+#[cfg(test)]
+static RENDER_SETTINGS: RwLock<()> = RwLock::new(());
+
+#[cfg(test)]
+pub(crate) fn changing_render_settings() -> RwLockWriteGuard<'static, ()> {
+    RENDER_SETTINGS
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+pub(crate) fn default_render_settings() -> RwLockReadGuard<'static, ()> {
+    RENDER_SETTINGS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+}
+
+// How many points of a `grid` over `view` the fractal's kernel iterates differently from
+// `fractal::reference_iterate`, down to the bits of the value and angle.
+#[cfg(test)]
+fn kernel_mismatches(view: &CanonicalView, grid: Size) -> usize {
+    let params = view.params();
+    let kernel = params.fractal.fractal();
+    let (left, top) = params.viewport.top_left(grid);
+    let pixel_size = params.viewport.pixel_size(grid);
+    let mut mismatches = 0;
+    for y in 0..grid.height as usize {
+        for x in 0..grid.width as usize {
+            let c = Complex::new(
+                left + (x as f64 + 0.5) * pixel_size,
+                top - (y as f64 + 0.5) * pixel_size,
+            );
+            let tuned = kernel.iterate(c, params.max_iterations, params.angle);
+            let reference =
+                fractal::reference_iterate(params.fractal, c, params.max_iterations, params.angle);
+            // The reference proves nothing, so any point the kernel did not see escape agrees
+            // with one the reference left unresolved.
+            let bits = |sample: fractal::Sample| {
+                let value = if render::escaped(sample.value) {
+                    sample.value
+                } else {
+                    UNRESOLVED
+                };
+                (value.to_bits(), sample.angle.to_bits())
+            };
+            if bits(tuned) != bits(reference) {
+                mismatches += 1;
+            }
+        }
+    }
+    mismatches
+}
+
+// A view whose stats are pinned. A mismatch means numeric behavior changed; if that was
+// intended, the new numbers are blessed by updating the table.
+#[cfg(test)]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CanonicalView {
+    pub name: &'static str,
+    pub fractal: FractalKind,
+    pub center: (f64, f64),
+    pub width: f64,
+    pub max_iterations: u32,
+    pub angle: AngleKind,
+    pub coarse_prepass: bool,
+    pub escape_sum: u64,
+    // Pixels that did not escape, proven inside or not.
+    pub interior: usize,
+    pub hash: u64,
+}
+
+#[cfg(test)]
+impl CanonicalView {
+    pub fn params(&self) -> FrameParams {
+        FrameParams {
+            viewport: Viewport::new(self.center.0, self.center.1, self.width),
+            max_iterations: self.max_iterations,
+            fractal: self.fractal,
+            angle: self.angle,
+            precision: Precision::Full,
+            coarse_prepass: self.coarse_prepass,
+            arithmetic: Arithmetic::F64,
+        }
+    }
+
+    pub fn matches(&self, stats: &FrameStats) -> bool {
+        (stats.escape_sum, stats.interior, stats.hash)
+            == (self.escape_sum, self.interior, self.hash)
+    }
+}
+
+#[cfg(test)]
+pub(crate) const CANONICAL_VIEWS: [CanonicalView; 7] = [
+    CanonicalView {
+        name: "mandelbrot home",
+        fractal: FractalKind::Mandelbrot,
+        center: (-0.7, 0.0),
+        width: 3.2,
+        max_iterations: 500,
+        angle: AngleKind::Off,
+        coarse_prepass: false,
+        escape_sum: 74452,
+        interior: 3799,
+        hash: 0xa0c19b8585a3c6ba,
+    },
+    CanonicalView {
+        name: "mandelbrot home with pre-pass",
+        fractal: FractalKind::Mandelbrot,
+        center: (-0.7, 0.0),
+        width: 3.2,
+        max_iterations: 500,
+        angle: AngleKind::Off,
+        coarse_prepass: true,
+        escape_sum: 74452,
+        interior: 3798,
+        hash: 0x62c58f1d226300db,
+    },
+    CanonicalView {
+        name: "seahorse valley",
+        fractal: FractalKind::Mandelbrot,
+        center: (-0.743643887037151, 0.131825904205330),
+        width: 1e-4,
+        max_iterations: 2000,
+        angle: AngleKind::Off,
+        coarse_prepass: false,
+        escape_sum: 2535857,
+        interior: 20,
+        hash: 0x0ca3d69160d98942,
+    },
+    CanonicalView {
+        name: "field lines",
+        fractal: FractalKind::Mandelbrot,
+        center: (-0.75, 0.1),
+        width: 0.05,
+        max_iterations: 1000,
+        angle: AngleKind::External,
+        coarse_prepass: false,
+        escape_sum: 882849,
+        interior: 9176,
+        hash: 0xc3c2d34d5fe9b5ad,
+    },
+    CanonicalView {
+        name: "burning ship",
+        fractal: FractalKind::BurningShip,
+        center: (-1.755, -0.03),
+        width: 0.1,
+        max_iterations: 1000,
+        angle: AngleKind::Off,
+        coarse_prepass: false,
+        escape_sum: 426207,
+        interior: 2117,
+        hash: 0xccec321202377e6e,
+    },
+    CanonicalView {
+        name: "escape directions",
+        fractal: FractalKind::Mandelbrot,
+        center: (-0.75, 0.1),
+        width: 0.05,
+        max_iterations: 1000,
+        angle: AngleKind::Escape,
+        coarse_prepass: false,
+        escape_sum: 882849,
+        interior: 9176,
+        hash: 0x973ebc98e57016a1,
+    },
+    // The default hybrid: two Mandelbrot steps, then one Burning Ship step, repeating.
+    CanonicalView {
+        name: "hybrid",
+        fractal: FractalKind::Hybrid(Hybrid::DEFAULT),
+        center: (-0.4, 0.0),
+        width: 3.2,
+        max_iterations: 500,
+        angle: AngleKind::Off,
+        coarse_prepass: false,
+        escape_sum: 89796,
+        interior: 3277,
+        hash: 0x169c8320d6d26db2,
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    // A mismatch means numeric behavior changed; if that was intended, the numbers printed are
+    // blessed by pasting them into the table.
+    #[test]
+    fn canonical_views_match_their_pinned_stats() {
+        let changed: Vec<String> = CANONICAL_VIEWS
+            .iter()
+            .filter_map(|view| {
+                let stats = frame_checksum(CANONICAL_SIZE, view.params());
+                (!view.matches(&stats)).then(|| {
+                    format!(
+                        "{}:\n  escape_sum: {},\n  interior: {},\n  hash: 0x{:016x},",
+                        view.name, stats.escape_sum, stats.interior, stats.hash
+                    )
+                })
+            })
+            .collect();
+        assert!(
+            changed.is_empty(),
+            "{} of {} canonical views changed\n{}",
+            changed.len(),
+            CANONICAL_VIEWS.len(),
+            changed.join("\n")
+        );
+    }
+
+    #[test]
+    fn kernels_iterate_every_canonical_view_like_the_reference() {
+        let mismatches: Vec<(&str, usize)> = thread::scope(|scope| {
+            let checks: Vec<_> = CANONICAL_VIEWS
+                .iter()
+                .map(|view| scope.spawn(move || (view.name, kernel_mismatches(view, KERNEL_GRID))))
+                .collect();
+            checks
+                .into_iter()
+                .map(|check| check.join().expect("kernel checks do not panic"))
+                .collect()
+        });
+        for (name, mismatches) in mismatches {
+            assert_eq!(
+                mismatches,
+                0,
+                "{}: the kernel differs from the reference at {} of {} points",
+                name,
+                mismatches,
+                KERNEL_GRID.width * KERNEL_GRID.height
+            );
+        }
+    }
+}
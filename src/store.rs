@@ -0,0 +1,208 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+use crate::export;
+
+// Unreadable files are moved into this folder beside them, with the time they were found.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+// What loading stores ran into since the last `take_warnings`, for the status bar.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+// Unreadable files that could not be moved aside. Saving over them would lose what they hold, so
+// saves to them are refused for the rest of the session.
+static HELD: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// Reads the store at `path` and parses it. Returns None when there is no file, or when it cannot
+// be read or parsed, in which case it is moved into quarantine, a warning naming it is recorded
+// and the caller goes on with defaults.
+pub fn load<T>(
+    path: &Path,
+    name: &str,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Option<T> {
+    let bytes = match fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == ErrorKind::NotFound => return None,
+        Err(err) => {
+            hold(path);
+            warn(format!("cannot read {} {}: {}", name, path.display(), err));
+            return None;
+        }
+    };
+    let err = match String::from_utf8(bytes) {
+        Ok(text) => match parse(&text) {
+            Ok(value) => return Some(value),
+            Err(err) => err,
+        },
+        Err(err) => err.to_string(),
+    };
+    // Parse errors quote the offending line in a gutter of "12 |" between where it is and what is
+    // wrong with it; the status bar has room for one line, so the quote is left out.
+    let quoted = |line: &str| {
+        line.split_once('|')
+            .is_some_and(|(gutter, _)| gutter.trim().chars().all(|c| c.is_ascii_digit()))
+    };
+    let mut lines = err
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !quoted(line));
+    let err = match (lines.next(), lines.next_back()) {
+        (Some(first), Some(last)) => format!("{}: {}", first, last),
+        (first, _) => first.unwrap_or_default().to_string(),
+    };
+    warn(match quarantine(path) {
+        Ok(moved) => format!(
+            "{} {} was unreadable ({}); moved it to {} and started afresh",
+            name,
+            path.display(),
+            err,
+            moved.display()
+        ),
+        Err(move_err) => {
+            hold(path);
+            format!(
+                "{} {} is unreadable ({}) and could not be moved aside ({}); leaving it untouched",
+                name,
+                path.display(),
+                err,
+                move_err
+            )
+        }
+    });
+    None
+}
+
+// Moves `path` into the quarantine folder beside it, returning where it went.
+pub fn quarantine(path: &Path) -> Result<PathBuf, String> {
+    let parent = path.parent().unwrap_or(Path::new("."));
+    let dir = parent.join(QUARANTINE_DIR);
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = path.extension().unwrap_or_default().to_string_lossy();
+    let target = export::unique_path(
+        &dir,
+        &format!("{}-{}", stem, export::timestamp()),
+        &extension,
+    );
+    fs::rename(path, &target).map_err(|err| err.to_string())?;
+    Ok(target)
+}
+
+// Writes a store without ever leaving it half written: the contents go to a temporary file that
+// then replaces the store, and the previous contents are kept as its one backup.
+pub fn save(path: &Path, contents: &str) -> Result<(), String> {
+    if HELD
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .any(|held| held == path)
+    {
+        return Err(String::from(
+            "the file on disk was unreadable and is kept as it is",
+        ));
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let temp = with_suffix(path, "tmp");
+    let written = File::create(&temp).and_then(|mut file| {
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()
+    });
+    if let Err(err) = written {
+        let _ = fs::remove_file(&temp);
+        return Err(err.to_string());
+    }
+    if path.exists() {
+        fs::copy(path, backup_path(path)).map_err(|err| err.to_string())?;
+    }
+    fs::rename(&temp, path).map_err(|err| err.to_string())
+}
+
+// Rewrites the store at `path` while holding its lock: `contents` is given what is on disk now,
+// None when there is no file, and returns what to write, so that changes another instance of the
+// app saved meanwhile can be merged in rather than overwritten. Returns what was written.
+pub fn update(
+    path: &Path,
+    contents: impl FnOnce(Option<&str>) -> Result<String, String>,
+) -> Result<String, String> {
+    let _lock = lock(path)?;
+    let current = match fs::read_to_string(path) {
+        Ok(text) => Some(text),
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => return Err(err.to_string()),
+    };
+    let next = contents(current.as_deref())?;
+    save(path, &next)?;
+    Ok(next)
+}
+
+// An advisory lock on a store, held by one instance of the app at a time and released when it is
+// dropped. It lives in a file of its own beside the store, since saving replaces the store's.
+pub struct StoreLock {
+    _file: File,
+}
+
+pub fn lock(path: &Path) -> Result<StoreLock, String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(with_suffix(path, "lock"))
+        .map_err(|err| err.to_string())?;
+    file.lock().map_err(|err| err.to_string())?;
+    Ok(StoreLock { _file: file })
+}
+
+// When a store was last written and how long it is, to notice another instance saving it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stamp {
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+// The stamp of the store at `path`, or None when there is no file.
+pub fn stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(Stamp {
+        modified: metadata.modified().ok(),
+        len: metadata.len(),
+    })
+}
+
+// Where `save` keeps the previous contents of `path`.
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, "bak")
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+fn warn(warning: String) {
+    println!("{}", warning);
+    WARNINGS
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(warning);
+}
+
+fn hold(path: &Path) {
+    HELD.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .push(path.to_path_buf());
+}
+
+// The warnings recorded since the last call.
+pub fn take_warnings() -> Vec<String> {
+    std::mem::take(&mut *WARNINGS.lock().unwrap_or_else(PoisonError::into_inner))
+}
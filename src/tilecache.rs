@@ -0,0 +1,385 @@
+use iced::Size;
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use crate::buffers;
+use crate::fractal::{AngleKind, FractalKind};
+use crate::render::{Arithmetic, FrameParams, PixelRect};
+use crate::tiledisk::{self, Entry};
+
+// Finished tiles of interactive renders, so that returning to a view (undo, redo, a bookmark)
+// copies the tiles it had instead of computing them again. Off until given a budget. Given a
+// folder too, the most valuable tiles are kept there between sessions.
+pub static TILE_CACHE: TileCache = TileCache::new();
+
+// One tile of one frame, exactly: the frame's view and size as bits, the tile's pixel rect and
+// everything else that decides its values. Tiles are cut from the frame's fixed tile grid and a
+// pixel's value depends only on the frame and its position, so the same view always yields the
+// same keys and the same values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TileKey {
+    viewport: [u64; 5],
+    bounds: (u32, u32),
+    rect: (usize, usize, usize, usize),
+    max_iterations: u32,
+    fractal: FractalKind,
+    angle: AngleKind,
+    coarse_prepass: bool,
+    arithmetic: Arithmetic,
+}
+
+impl TileKey {
+    pub fn new(bounds: Size, params: &FrameParams, rect: PixelRect) -> TileKey {
+        let viewport = params.viewport;
+        TileKey {
+            viewport: [
+                viewport.center_re.to_bits(),
+                viewport.center_im.to_bits(),
+                viewport.width.to_bits(),
+                viewport.center_re_lo.to_bits(),
+                viewport.center_im_lo.to_bits(),
+            ],
+            bounds: (bounds.width.to_bits(), bounds.height.to_bits()),
+            rect: (rect.x, rect.y, rect.width, rect.height),
+            max_iterations: params.max_iterations,
+            fractal: params.fractal,
+            angle: params.angle,
+            coarse_prepass: params.coarse_prepass,
+            arithmetic: params.arithmetic,
+        }
+    }
+
+    // The key as text, which tile files hold to be told apart; its hash names them.
+    fn text(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub tiles: usize,
+    pub bytes: usize,
+}
+
+impl CacheStats {
+    // Fraction of lookups that found their tile, or 0 before the first.
+    pub fn hit_rate(&self) -> f32 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            0.0
+        } else {
+            self.hits as f32 / lookups as f32
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CachedTile {
+    values: Vec<f32>,
+    angles: Vec<f32>,
+    // When it was last stored or found, on the cache's own clock.
+    used: u64,
+    // How often it was found, in this session and, halved for each, earlier ones.
+    hits: u64,
+}
+
+impl CachedTile {
+    fn bytes(&self) -> usize {
+        (self.values.len() + self.angles.len()) * size_of::<f32>()
+    }
+}
+
+// Where tiles are kept between sessions, and how many bytes of them. The index is read at the
+// first lookup that needs it.
+#[derive(Debug)]
+struct Disk {
+    dir: PathBuf,
+    budget: usize,
+    index: Option<BTreeMap<u64, Entry>>,
+}
+
+impl Disk {
+    fn index(&mut self) -> &mut BTreeMap<u64, Entry> {
+        let dir = &self.dir;
+        self.index.get_or_insert_with(|| tiledisk::load_index(dir))
+    }
+}
+
+#[derive(Debug)]
+struct Store {
+    tiles: BTreeMap<TileKey, CachedTile>,
+    // Keys by last use, oldest first.
+    recency: BTreeMap<u64, TileKey>,
+    clock: u64,
+    bytes: usize,
+    budget: usize,
+    stats: CacheStats,
+    disk: Option<Disk>,
+    // Whether a tile was stored or found since the last save to disk.
+    changed: bool,
+}
+
+impl Store {
+    fn touch(&mut self, key: TileKey) {
+        let Some(tile) = self.tiles.get_mut(&key) else {
+            return;
+        };
+        self.clock += 1;
+        self.recency.remove(&tile.used);
+        tile.used = self.clock;
+        self.recency.insert(self.clock, key);
+    }
+
+    fn remove(&mut self, key: &TileKey) {
+        if let Some(tile) = self.tiles.remove(key) {
+            self.recency.remove(&tile.used);
+            self.bytes -= tile.bytes();
+        }
+    }
+
+    // Stores a tile as the most recently used, evicting older ones past the budget. Tiles larger
+    // than the whole budget are not kept.
+    fn keep(&mut self, key: TileKey, tile: CachedTile) {
+        self.remove(&key);
+        if tile.bytes() > self.budget {
+            return;
+        }
+        self.bytes += tile.bytes();
+        self.tiles.insert(key, tile);
+        self.touch(key);
+        self.evict();
+        self.changed = true;
+    }
+
+    // Drops the least recently used tiles until the cache fits its budget.
+    fn evict(&mut self) {
+        while self.bytes > self.budget {
+            let Some((_, key)) = self.recency.pop_first() else {
+                break;
+            };
+            if let Some(tile) = self.tiles.remove(&key) {
+                self.bytes -= tile.bytes();
+            }
+        }
+    }
+}
+
+// A least-recently-used map from tiles to their iteration values and angles, bounded by bytes.
+#[derive(Debug)]
+pub struct TileCache {
+    store: Mutex<Store>,
+    // Held while saving to disk, so that saves follow one another.
+    saving: Mutex<()>,
+}
+
+impl TileCache {
+    pub const fn new() -> TileCache {
+        TileCache {
+            store: Mutex::new(Store {
+                tiles: BTreeMap::new(),
+                recency: BTreeMap::new(),
+                clock: 0,
+                bytes: 0,
+                budget: 0,
+                stats: CacheStats {
+                    hits: 0,
+                    misses: 0,
+                    tiles: 0,
+                    bytes: 0,
+                },
+                disk: None,
+                changed: false,
+            }),
+            saving: Mutex::new(()),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Store> {
+        self.store.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // Sets how many bytes of tiles the cache may hold, evicting down to it; 0 turns it off.
+    pub fn set_budget(&self, bytes: usize) {
+        let mut store = self.lock();
+        store.budget = bytes;
+        store.evict();
+    }
+
+    // Keeps up to `budget` bytes of tiles in `dir` between sessions, read back as they are looked
+    // up; None, or a budget of 0, keeps none.
+    pub fn set_disk(&self, dir: Option<PathBuf>, budget: usize) {
+        self.lock().disk = dir.filter(|_| budget > 0).map(|dir| Disk {
+            dir,
+            budget,
+            index: None,
+        });
+    }
+
+    pub fn budget(&self) -> usize {
+        self.lock().budget
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.lock().budget > 0
+    }
+
+    // Copies of a tile's values and angles, counting the lookup as a hit or a miss. Tiles not in
+    // memory are read from disk when it has them, and dropped from it when unreadable.
+    pub fn get(&self, key: &TileKey) -> Option<(Vec<f32>, Vec<f32>)> {
+        let copy = |data: &[f32]| {
+            let mut copy = buffers::TILES.take(data.len());
+            copy.extend_from_slice(data);
+            copy
+        };
+        let mut store = self.lock();
+        if let Some(tile) = store.tiles.get_mut(key) {
+            tile.hits += 1;
+            let found = (copy(&tile.values), copy(&tile.angles));
+            store.stats.hits += 1;
+            store.changed = true;
+            store.touch(*key);
+            return Some(found);
+        }
+        let on_disk = store.disk.as_mut().and_then(|disk| {
+            let text = key.text();
+            let hash = tiledisk::key_hash(&text);
+            let hits = disk.index().get(&hash)?.hits;
+            Some((disk.dir.clone(), hash, text, hits))
+        });
+        let Some((dir, hash, text, hits)) = on_disk else {
+            store.stats.misses += 1;
+            return None;
+        };
+        // The file is read unlocked, so that workers looking up other tiles are not held up.
+        drop(store);
+        let read = tiledisk::read(&dir, hash, &text);
+        let mut store = self.lock();
+        match read {
+            Ok((values, angles)) => {
+                let found = (copy(&values), copy(&angles));
+                store.stats.hits += 1;
+                let tile = CachedTile {
+                    values,
+                    angles,
+                    used: 0,
+                    hits: hits + 1,
+                };
+                store.keep(*key, tile);
+                Some(found)
+            }
+            Err(err) => {
+                let path = tiledisk::path(&dir, hash);
+                println!("dropping cached tile {}: {}", path.display(), err);
+                if let Some(disk) = store.disk.as_mut() {
+                    disk.index().remove(&hash);
+                }
+                let _ = fs::remove_file(path);
+                store.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn insert(&self, key: TileKey, values: Vec<f32>, angles: Vec<f32>) {
+        let tile = CachedTile {
+            values,
+            angles,
+            used: 0,
+            hits: 0,
+        };
+        self.lock().keep(key, tile);
+    }
+
+    // Writes the most valuable tiles to disk, up to its budget, and removes the rest from it:
+    // the most often found first and, among those found as often, the most recently used, with
+    // tiles only on disk after those in memory. Returns how many tiles were written, none when
+    // there is no disk or no tile was stored or found since the last save.
+    pub fn save_to_disk(&self) -> Result<usize, String> {
+        let _saving = self.saving.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut store = self.lock();
+        let Store {
+            tiles,
+            disk,
+            changed,
+            ..
+        } = &mut *store;
+        let Some(disk) = disk.as_mut().filter(|_| *changed) else {
+            return Ok(0);
+        };
+        let (dir, budget) = (disk.dir.clone(), disk.budget);
+        let index = disk.index();
+        let mut ranked: Vec<(u64, Entry, u64, Option<TileKey>)> = tiles
+            .iter()
+            .map(|(key, tile)| {
+                let entry = Entry {
+                    hits: tile.hits,
+                    bytes: tile.bytes(),
+                };
+                (
+                    tiledisk::key_hash(&key.text()),
+                    entry,
+                    tile.used,
+                    Some(*key),
+                )
+            })
+            .collect();
+        let in_memory: BTreeSet<u64> = ranked.iter().map(|(hash, ..)| *hash).collect();
+        ranked.extend(
+            index
+                .iter()
+                .filter(|(hash, _)| !in_memory.contains(hash))
+                .map(|(hash, entry)| (*hash, *entry, 0, None)),
+        );
+        ranked.sort_by(|a, b| b.1.hits.cmp(&a.1.hits).then(b.2.cmp(&a.2)));
+        let mut bytes = 0;
+        let mut kept = Vec::new();
+        let mut writes = Vec::new();
+        for (hash, entry, _, key) in ranked {
+            if bytes + entry.bytes > budget {
+                continue;
+            }
+            bytes += entry.bytes;
+            kept.push((hash, entry));
+            if let Some(key) = key.filter(|_| !index.contains_key(&hash)) {
+                let tile = &tiles[&key];
+                writes.push((hash, key.text(), tile.values.clone(), tile.angles.clone()));
+            }
+        }
+        drop(store);
+
+        for (hash, text, values, angles) in &writes {
+            tiledisk::write(&dir, *hash, text, values, angles)?;
+        }
+        tiledisk::save_index(&dir, &kept)?;
+        let kept: BTreeMap<u64, Entry> = kept.into_iter().collect();
+        {
+            let mut store = self.lock();
+            if let Some(disk) = store.disk.as_mut().filter(|disk| disk.dir == dir) {
+                disk.index = Some(kept.clone());
+            }
+            store.changed = false;
+        }
+        tiledisk::prune(&dir, &kept);
+        Ok(writes.len())
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        let store = self.lock();
+        CacheStats {
+            tiles: store.tiles.len(),
+            bytes: store.bytes,
+            ..store.stats
+        }
+    }
+}
+
+impl Default for TileCache {
+    fn default() -> Self {
+        TileCache::new()
+    }
+}
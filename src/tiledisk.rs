@@ -0,0 +1,323 @@
+use serde::{Deserialize, Serialize};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::store;
+
+// The tile cache's tiles kept on disk between sessions, so that views visited before a restart
+// come back without being computed again. A folder holds one file per tile, named by the hash of
+// its key, and an index of the tiles it is meant to hold. Only tiles in the index are read, and
+// files outside it are removed at the next save.
+
+// Bumped whenever the tile file layout or what goes into a tile key changes; an index of another
+// version is ignored and its tiles are pruned.
+pub const VERSION: u32 = 1;
+pub const INDEX: &str = "index.toml";
+pub const EXTENSION: &str = "tile";
+
+const MAGIC: &[u8; 4] = b"MBTL";
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+// What the index knows of a tile on disk: how often it was looked up, and its size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub hits: u64,
+    pub bytes: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    #[serde(default)]
+    tiles: Vec<IndexEntry>,
+}
+
+// TOML integers are signed, so the hash is written in hex.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    hash: String,
+    hits: u64,
+    bytes: usize,
+}
+
+// FNV-1a of a tile key's text, the same in every run: the name of the tile's file.
+pub fn key_hash(key: &str) -> u64 {
+    fnv(key.as_bytes())
+}
+
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+pub fn path(dir: &Path, hash: u64) -> PathBuf {
+    dir.join(format!("{:016x}.{}", hash, EXTENSION))
+}
+
+// The index in `dir`, empty when there is none or it cannot be used. Hits carried over from
+// earlier sessions are halved, so that tiles looked up often long ago give way to recent ones.
+pub fn load_index(dir: &Path) -> BTreeMap<u64, Entry> {
+    let path = dir.join(INDEX);
+    let Ok(text) = fs::read_to_string(&path) else {
+        return BTreeMap::new();
+    };
+    let index: Index = match toml::from_str(&text) {
+        Ok(index) => index,
+        Err(err) => {
+            println!("ignoring tile index {}: {}", path.display(), err);
+            return BTreeMap::new();
+        }
+    };
+    if index.version != VERSION {
+        println!(
+            "ignoring tile index {} of version {}",
+            path.display(),
+            index.version
+        );
+        return BTreeMap::new();
+    }
+    index
+        .tiles
+        .iter()
+        .filter_map(|tile| {
+            let hash = u64::from_str_radix(&tile.hash, 16).ok()?;
+            let entry = Entry {
+                hits: tile.hits / 2,
+                bytes: tile.bytes,
+            };
+            Some((hash, entry))
+        })
+        .collect()
+}
+
+// Writes the index of `tiles`, most valuable first.
+pub fn save_index(dir: &Path, tiles: &[(u64, Entry)]) -> Result<(), String> {
+    let index = Index {
+        version: VERSION,
+        tiles: tiles
+            .iter()
+            .map(|(hash, entry)| IndexEntry {
+                hash: format!("{:016x}", hash),
+                hits: entry.hits,
+                bytes: entry.bytes,
+            })
+            .collect(),
+    };
+    let text = toml::to_string(&index).map_err(|err| err.to_string())?;
+    store::save(&dir.join(INDEX), &text)
+}
+
+// Little-endian layout: magic "MBTL", u32 version, u32 key length, the key's text, u32 value
+// count, u32 angle count, the f32 values and angles, then the FNV-1a of everything before it.
+pub fn encode(key: &str, values: &[f32], angles: &[f32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(24 + key.len() + (values.len() + angles.len()) * 4);
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(key.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(key.as_bytes());
+    bytes.extend_from_slice(&(values.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&(angles.len() as u32).to_le_bytes());
+    for value in values.iter().chain(angles) {
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes.extend_from_slice(&fnv(&bytes).to_le_bytes());
+    bytes
+}
+
+// The values and angles of a tile file, checked to be whole and to hold the tile keyed `key`.
+pub fn decode(bytes: &[u8], key: &str) -> Result<(Vec<f32>, Vec<f32>), String> {
+    if bytes.len() < 20 || &bytes[..4] != MAGIC {
+        return Err(String::from("not a tile file"));
+    }
+    let (body, sum) = bytes.split_at(bytes.len() - 8);
+    if fnv(body).to_le_bytes() != sum {
+        return Err(String::from("checksum mismatch"));
+    }
+    let u32_at = |offset: usize| {
+        body.get(offset..offset + 4)
+            .map(|word| u32::from_le_bytes(word.try_into().unwrap()) as usize)
+            .ok_or_else(|| String::from("truncated tile file"))
+    };
+    let version = u32_at(4)?;
+    if version != VERSION as usize {
+        return Err(format!("unsupported tile file version {}", version));
+    }
+    let key_end = 12 + u32_at(8)?;
+    if body.get(12..key_end) != Some(key.as_bytes()) {
+        return Err(String::from("holds another tile"));
+    }
+    let (values, angles) = (u32_at(key_end)?, u32_at(key_end + 4)?);
+    let data = &body[key_end + 8..];
+    if data.len() != (values + angles) * 4 {
+        return Err(String::from("truncated tile file"));
+    }
+    let mut floats = data
+        .chunks_exact(4)
+        .map(|word| f32::from_le_bytes(word.try_into().unwrap()));
+    let values = floats.by_ref().take(values).collect();
+    Ok((values, floats.collect()))
+}
+
+pub fn write(
+    dir: &Path,
+    hash: u64,
+    key: &str,
+    values: &[f32],
+    angles: &[f32],
+) -> Result<(), String> {
+    fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    fs::write(path(dir, hash), encode(key, values, angles)).map_err(|err| err.to_string())
+}
+
+pub fn read(dir: &Path, hash: u64, key: &str) -> Result<(Vec<f32>, Vec<f32>), String> {
+    let bytes = fs::read(path(dir, hash)).map_err(|err| err.to_string())?;
+    decode(&bytes, key)
+}
+
+// Removes the tile files in `dir` that `index` does not hold, returning how many.
+pub fn prune(dir: &Path, index: &BTreeMap<u64, Entry>) -> usize {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    let stray = |path: &Path| {
+        path.extension()
+            .is_some_and(|extension| extension == EXTENSION)
+            && !path
+                .file_stem()
+                .and_then(|stem| u64::from_str_radix(&stem.to_string_lossy(), 16).ok())
+                .is_some_and(|hash| index.contains_key(&hash))
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| stray(path) && fs::remove_file(path).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, process};
+
+    use threadpool::ThreadPool;
+
+    use super::*;
+    use crate::render::{self, CancelToken, FrameParams, IterationBuffer};
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+    use crate::tilecache::TILE_CACHE;
+    use crate::tiling::Focus;
+
+    // The frame, and how many of its tiles were found in the cache and how many missed.
+    fn render_cached(params: FrameParams) -> (IterationBuffer, u64, u64) {
+        let before = TILE_CACHE.stats();
+        let frame = render::render_reusing_from(
+            &ThreadPool::new(2),
+            &[],
+            CANONICAL_SIZE,
+            params,
+            &CancelToken::new(),
+            &Focus::default(),
+            |_| {},
+        )
+        .expect("renders without a cancel request complete");
+        let after = TILE_CACHE.stats();
+        (
+            frame,
+            after.hits - before.hits,
+            after.misses - before.misses,
+        )
+    }
+
+    fn same(a: &IterationBuffer, b: &IterationBuffer) -> bool {
+        (0..a.values.len()).all(|index| a.values.get(index) == b.values.get(index))
+    }
+
+    // Views seen before a restart come back from disk without being computed, unless their tile
+    // file or the index was spoiled in the meantime.
+    #[test]
+    fn views_seen_before_a_restart_come_back_from_disk() {
+        let _settings = stats::changing_render_settings();
+        let dir = env::temp_dir().join(format!("mandelbrot-tiles-{}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let memory = TILE_CACHE.budget();
+        let (seen, other) = (CANONICAL_VIEWS[1].params(), CANONICAL_VIEWS[2].params());
+        let restart = |disk_budget| {
+            TILE_CACHE.set_budget(0);
+            TILE_CACHE.set_budget(64 * 1024 * 1024);
+            TILE_CACHE.set_disk(Some(dir.clone()), disk_budget);
+        };
+
+        // Seen twice, and so looked up more often than the other view, which is seen last; the
+        // disk has room for one of them.
+        restart(0);
+        let (first, _, cells) = render_cached(seen);
+        let room = TILE_CACHE.stats().bytes;
+        render_cached(seen);
+        render_cached(other);
+        TILE_CACHE.set_disk(Some(dir.clone()), room);
+        let saved = TILE_CACHE.save_to_disk();
+        assert!(
+            matches!(saved, Ok(written) if written as u64 == cells),
+            "saving {} tiles' worth of disk gives {:?}",
+            cells,
+            saved
+        );
+
+        restart(room);
+        let (again, hits, misses) = render_cached(seen);
+        assert!(
+            misses == 0 && hits == cells && same(&first, &again),
+            "after a restart a view seen before finds {} of {} tiles, missing {}",
+            hits,
+            cells,
+            misses
+        );
+        let (_, hits, _) = render_cached(other);
+        assert_eq!(
+            hits, 0,
+            "after a restart tiles of a view seen less often are found"
+        );
+
+        let tile = fs::read_dir(&dir)
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .find(|path| path.extension().is_some_and(|ext| ext == EXTENSION))
+            .expect("tile files were written");
+        let mut bytes = fs::read(&tile).unwrap_or_default();
+        let middle = bytes.len() / 2;
+        bytes[middle] ^= 0xff;
+        let _ = fs::write(&tile, bytes);
+        restart(room);
+        let (again, hits, misses) = render_cached(seen);
+        assert!(
+            misses == 1 && hits == cells - 1 && same(&first, &again) && !tile.exists(),
+            "with one tile file corrupt {} tiles are found and {} missed",
+            hits,
+            misses
+        );
+
+        let index = dir.join(INDEX);
+        let text = fs::read_to_string(&index).unwrap_or_default();
+        let _ = fs::write(
+            &index,
+            text.replace(
+                &format!("version = {}", VERSION),
+                &format!("version = {}", VERSION + 1),
+            ),
+        );
+        restart(room);
+        let (_, hits, _) = render_cached(seen);
+        assert_eq!(
+            hits, 0,
+            "tiles are read through an index of another version"
+        );
+
+        TILE_CACHE.set_disk(None, 0);
+        TILE_CACHE.set_budget(0);
+        TILE_CACHE.set_budget(memory);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
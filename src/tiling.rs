@@ -0,0 +1,494 @@
+use iced::Size;
+
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::render::{FrameParams, PixelRect};
+
+// Side of the square tiles a frame is split into for scheduling, in pixels. A multiple of the
+// coarse pre-pass block so blocks never straddle tiles.
+pub const TILE_SIZE: usize = 64;
+// The queue is re-sorted once the focus has moved this far, in pixels, since the last sort.
+pub const RESORT_DISTANCE: f32 = TILE_SIZE as f32;
+// How many tiles of distance the most detailed tile may jump ahead by.
+pub const IMPORTANCE_WEIGHT: f32 = 2.0;
+
+// Smallest tile an expensive one is split down to; the coarse pre-pass block, so blocks never
+// straddle tiles.
+pub const MIN_TILE_SIZE: usize = 16;
+// Tiles predicted to cost this many times the median per pixel are split into quarters, and
+// again past the square of it; 2x2 groups of tiles all below the median by this factor are
+// merged.
+pub const COST_SPREAD: f32 = 4.0;
+// A cost map only predicts a frame whose pixels are at most this factor larger or smaller...
+const MAX_SCALE_CHANGE: f64 = 2.0;
+// ...and which it covers at least this fraction of.
+const MIN_COVERAGE: f32 = 0.5;
+
+const NO_FOCUS: u64 = u64::MAX;
+
+// Tile sizes renders can be scheduled in, from the finest to the coarsest: the grid tile
+// quartered down to the smallest tile, the grid tile itself, and 2x2 groups of it.
+pub const CHUNK_SIZES: [usize; 4] = [MIN_TILE_SIZE, TILE_SIZE / 2, TILE_SIZE, 2 * TILE_SIZE];
+
+// The size planned tiles start from before costs split or merge them, for every render in the
+// process. Only scheduling depends on it: pixel values, the cost map grid and cached tiles stay on
+// the `TILE_SIZE` grid.
+static CHUNK_SIZE: AtomicUsize = AtomicUsize::new(TILE_SIZE);
+
+pub fn chunk_size() -> usize {
+    CHUNK_SIZE.load(Ordering::Relaxed)
+}
+
+// Sets the tile size renders start from, rounded to the nearest of `CHUNK_SIZES`.
+pub fn set_chunk_size(size: usize) {
+    let nearest = CHUNK_SIZES
+        .into_iter()
+        .min_by_key(|chunk| chunk.abs_diff(size))
+        .unwrap_or(TILE_SIZE);
+    CHUNK_SIZE.store(nearest, Ordering::Relaxed);
+}
+
+// Shared pixel position, in frame coordinates, that rendering should start nearest to, such as
+// the cursor. Clones observe the same position, so it can be moved while a render runs.
+#[derive(Clone, Debug)]
+pub struct Focus(Arc<AtomicU64>);
+
+impl Default for Focus {
+    fn default() -> Self {
+        Focus(Arc::new(AtomicU64::new(NO_FOCUS)))
+    }
+}
+
+impl Focus {
+    pub fn set(&self, position: Option<(f32, f32)>) {
+        let bits = position.map_or(NO_FOCUS, |(x, y)| {
+            (x.to_bits() as u64) << 32 | y.to_bits() as u64
+        });
+        self.0.store(bits, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> Option<(f32, f32)> {
+        let bits = self.0.load(Ordering::Relaxed);
+        (bits != NO_FOCUS).then(|| {
+            (
+                f32::from_bits((bits >> 32) as u32),
+                f32::from_bits(bits as u32),
+            )
+        })
+    }
+}
+
+// Splits rects into tiles of at most `TILE_SIZE` square, aligned to the frame's tile grid.
+pub fn split(rects: &[PixelRect]) -> Vec<PixelRect> {
+    let mut tiles = Vec::new();
+    for rect in rects {
+        let mut y = rect.y;
+        while y < rect.y + rect.height {
+            let bottom = ((y / TILE_SIZE + 1) * TILE_SIZE).min(rect.y + rect.height);
+            let mut x = rect.x;
+            while x < rect.x + rect.width {
+                let right = ((x / TILE_SIZE + 1) * TILE_SIZE).min(rect.x + rect.width);
+                tiles.push(PixelRect {
+                    x,
+                    y,
+                    width: right - x,
+                    height: bottom - y,
+                });
+                x = right;
+            }
+            y = bottom;
+        }
+    }
+    tiles
+}
+
+// Splits rects into tiles, sizing them by the cost `costs` predicts: expensive regions in smaller
+// tiles so they spread over the workers, cheap ones in larger tiles to save scheduling. Both start
+// from `chunk_size()`; without a prediction every tile is that size.
+pub fn plan(rects: &[PixelRect], costs: Option<&CostMap>) -> Vec<PixelRect> {
+    let tiles = split(rects);
+    let chunk = chunk_size();
+    let prediction = costs.and_then(|costs| Some((costs, costs.median()?)));
+    if prediction.is_none() && chunk == TILE_SIZE {
+        return tiles;
+    }
+    // How many times a grid tile is quartered, or -1 for one merged with its neighbours.
+    let base = if chunk > TILE_SIZE {
+        -1
+    } else {
+        (TILE_SIZE / chunk.max(MIN_TILE_SIZE)).ilog2() as i32
+    };
+    let level = |tile: &PixelRect| {
+        let Some((costs, median)) = prediction else {
+            return base;
+        };
+        let ratio = costs.density_at(tile).unwrap_or(median) / median;
+        let shift = if ratio >= COST_SPREAD * COST_SPREAD {
+            2
+        } else if ratio >= COST_SPREAD {
+            1
+        } else if ratio * COST_SPREAD <= 1.0 {
+            -1
+        } else {
+            0
+        };
+        (base + shift).clamp(-1, 2)
+    };
+
+    let mut planned = Vec::with_capacity(tiles.len());
+    let mut merging = Vec::new();
+    for tile in tiles {
+        match level(&tile) {
+            2.. => planned.extend(quarters(tile).into_iter().flat_map(quarters)),
+            1 => planned.extend(quarters(tile)),
+            ..=-1 if tile.width == TILE_SIZE && tile.height == TILE_SIZE => merging.push(tile),
+            _ => planned.push(tile),
+        }
+    }
+    // Merge each complete, aligned 2x2 group of the tiles marked for it.
+    let merged_size = 2 * TILE_SIZE;
+    merging.sort_by_key(|tile| (tile.y / merged_size, tile.x / merged_size, tile.y, tile.x));
+    for group in merging.chunk_by(|a, b| {
+        (a.x / merged_size, a.y / merged_size) == (b.x / merged_size, b.y / merged_size)
+    }) {
+        let aligned = group[0].x % merged_size == 0 && group[0].y % merged_size == 0;
+        if group.len() == 4 && aligned {
+            planned.push(PixelRect {
+                x: group[0].x,
+                y: group[0].y,
+                width: merged_size,
+                height: merged_size,
+            });
+        } else {
+            planned.extend_from_slice(group);
+        }
+    }
+    planned
+}
+
+fn quarters(tile: PixelRect) -> Vec<PixelRect> {
+    if tile.width <= MIN_TILE_SIZE || tile.height <= MIN_TILE_SIZE {
+        return vec![tile];
+    }
+    let left = tile
+        .width
+        .div_ceil(2)
+        .next_multiple_of(MIN_TILE_SIZE)
+        .min(tile.width);
+    let top = tile
+        .height
+        .div_ceil(2)
+        .next_multiple_of(MIN_TILE_SIZE)
+        .min(tile.height);
+    [
+        (tile.x, tile.y, left, top),
+        (tile.x + left, tile.y, tile.width - left, top),
+        (tile.x, tile.y + top, left, tile.height - top),
+        (
+            tile.x + left,
+            tile.y + top,
+            tile.width - left,
+            tile.height - top,
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, _, width, height)| *width > 0 && *height > 0)
+    .map(|(x, y, width, height)| PixelRect {
+        x,
+        y,
+        width,
+        height,
+    })
+    .collect()
+}
+
+// Time per pixel over a frame, on the `TILE_SIZE` grid: measured where tiles were computed, and
+// elsewhere predicted from the previous frame's map.
+#[derive(Clone, Debug)]
+pub struct CostMap {
+    params: FrameParams,
+    size: Size,
+    columns: usize,
+    rows: usize,
+    // Nanoseconds per pixel carried over from the previous frame; NaN where unknown.
+    predicted: Vec<f32>,
+    // Nanoseconds and pixels measured in this frame.
+    measured: Vec<(f64, usize)>,
+}
+
+impl CostMap {
+    pub fn new(size: Size, params: FrameParams) -> CostMap {
+        let columns = (size.width as usize).div_ceil(TILE_SIZE);
+        let rows = (size.height as usize).div_ceil(TILE_SIZE);
+        CostMap {
+            params,
+            size,
+            columns,
+            rows,
+            predicted: vec![f32::NAN; columns * rows],
+            measured: vec![(0.0, 0); columns * rows],
+        }
+    }
+
+    // A map for a frame of `params` at `size`, predicted from this one; None when the view has
+    // changed too much for this map to say anything about it.
+    pub fn project(&self, size: Size, params: FrameParams) -> Option<CostMap> {
+        let same_settings = FrameParams {
+            viewport: params.viewport,
+            precision: params.precision,
+            ..self.params
+        } == params;
+        let pixel_size = params.viewport.pixel_size(size);
+        let previous_pixel_size = self.params.viewport.pixel_size(self.size);
+        let scale = pixel_size / previous_pixel_size;
+        if !same_settings || !(1.0 / MAX_SCALE_CHANGE..=MAX_SCALE_CHANGE).contains(&scale) {
+            return None;
+        }
+        let (delta_re, delta_im) = self.params.viewport.center_delta(&params.viewport);
+        let mut map = CostMap::new(size, params);
+        let mut covered = 0;
+        for row in 0..map.rows {
+            for column in 0..map.columns {
+                // Where this cell's center lies in the previous frame's pixels.
+                let x = ((column as f64 + 0.5) * TILE_SIZE as f64 - size.width as f64 / 2.0)
+                    * scale
+                    + self.size.width as f64 / 2.0
+                    - delta_re / previous_pixel_size;
+                let y = ((row as f64 + 0.5) * TILE_SIZE as f64 - size.height as f64 / 2.0) * scale
+                    + self.size.height as f64 / 2.0
+                    + delta_im / previous_pixel_size;
+                if let Some(density) = self.density_at_pixel(x, y) {
+                    map.predicted[row * map.columns + column] = density;
+                    covered += 1;
+                }
+            }
+        }
+        let cells = map.columns * map.rows;
+        (covered as f32 >= cells as f32 * MIN_COVERAGE).then_some(map)
+    }
+
+    // Attributes the time a tile took to the cells it covers, by their share of its pixels.
+    pub fn record(&mut self, tile: PixelRect, cost: Duration) {
+        let pixels = tile.width * tile.height;
+        if pixels == 0 {
+            return;
+        }
+        let nanos = cost.as_nanos() as f64;
+        for (index, share) in overlaps(tile, self.columns) {
+            let cell = &mut self.measured[index];
+            cell.0 += nanos * share as f64 / pixels as f64;
+            cell.1 += share;
+        }
+    }
+
+    // What computing `rect` should take, by the cost of the cells it covers; cells with none
+    // known are taken to cost the median. None when no cell's cost is known.
+    pub fn predict(&self, rect: PixelRect) -> Option<Duration> {
+        let median = self.median()?;
+        let nanos: f64 = overlaps(rect, self.columns)
+            .map(|(index, share)| self.density(index).unwrap_or(median) as f64 * share as f64)
+            .sum();
+        Some(Duration::from_nanos(nanos as u64))
+    }
+
+    fn density(&self, index: usize) -> Option<f32> {
+        let (nanos, pixels) = self.measured[index];
+        let density = if pixels > 0 {
+            (nanos / pixels as f64) as f32
+        } else {
+            self.predicted[index]
+        };
+        (!density.is_nan()).then_some(density)
+    }
+
+    fn density_at_pixel(&self, x: f64, y: f64) -> Option<f32> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let (column, row) = (x as usize / TILE_SIZE, y as usize / TILE_SIZE);
+        if column >= self.columns || row >= self.rows {
+            return None;
+        }
+        self.density(row * self.columns + column)
+    }
+
+    // Predicted time per pixel at a tile's center.
+    fn density_at(&self, tile: &PixelRect) -> Option<f32> {
+        let x = tile.x as f64 + tile.width as f64 / 2.0;
+        let y = tile.y as f64 + tile.height as f64 / 2.0;
+        self.density_at_pixel(x, y)
+    }
+
+    fn median(&self) -> Option<f32> {
+        let mut densities: Vec<f32> = (0..self.columns * self.rows)
+            .filter_map(|index| self.density(index))
+            .filter(|density| *density > 0.0)
+            .collect();
+        if densities.is_empty() {
+            return None;
+        }
+        let middle = densities.len() / 2;
+        Some(*densities.select_nth_unstable_by(middle, f32::total_cmp).1)
+    }
+}
+
+// The cells of a grid `columns` wide that `tile` covers, with how many of its pixels lie in each.
+fn overlaps(tile: PixelRect, columns: usize) -> impl Iterator<Item = (usize, usize)> {
+    let rows = tile.y / TILE_SIZE..(tile.y + tile.height).div_ceil(TILE_SIZE);
+    rows.flat_map(move |row| {
+        let spanned = tile.x / TILE_SIZE..(tile.x + tile.width).div_ceil(TILE_SIZE);
+        spanned.map(move |column| {
+            let width = ((column + 1) * TILE_SIZE).min(tile.x + tile.width)
+                - (column * TILE_SIZE).max(tile.x);
+            let height =
+                ((row + 1) * TILE_SIZE).min(tile.y + tile.height) - (row * TILE_SIZE).max(tile.y);
+            (row * columns + column, width * height)
+        })
+    })
+}
+
+// Pending tiles, handed out nearest the focus first. `importance` in [0, 1] estimates how much
+// detail a tile holds and moves it forward by up to `IMPORTANCE_WEIGHT` tiles. Without a focus,
+// tiles are ordered around `center`.
+#[derive(Debug)]
+pub struct TileQueue {
+    // Sorted so the next tile is last.
+    pending: Vec<(PixelRect, f32)>,
+    center: (f32, f32),
+    sorted_for: Option<(f32, f32)>,
+    taken: usize,
+}
+
+impl TileQueue {
+    pub fn new(tiles: Vec<(PixelRect, f32)>, center: (f32, f32)) -> TileQueue {
+        TileQueue {
+            pending: tiles,
+            center,
+            sorted_for: None,
+            taken: 0,
+        }
+    }
+
+    // The next tile for `focus`, numbered in the order tiles were taken.
+    pub fn pop(&mut self, focus: Option<(f32, f32)>) -> Option<(usize, PixelRect)> {
+        let target = focus.unwrap_or(self.center);
+        let moved = self
+            .sorted_for
+            .is_none_or(|(x, y)| (x - target.0).hypot(y - target.1) >= RESORT_DISTANCE);
+        if moved {
+            self.pending
+                .sort_by(|a, b| priority(b.0, b.1, target).total_cmp(&priority(a.0, a.1, target)));
+            self.sorted_for = Some(target);
+        }
+        let (tile, _) = self.pending.pop()?;
+        self.taken += 1;
+        Some((self.taken - 1, tile))
+    }
+}
+
+// Lower comes first: distance from `target` in tiles, less the importance bonus.
+fn priority(tile: PixelRect, importance: f32, target: (f32, f32)) -> f32 {
+    let x = tile.x as f32 + tile.width as f32 / 2.0;
+    let y = tile.y as f32 + tile.height as f32 / 2.0;
+    (x - target.0).hypot(y - target.1) / TILE_SIZE as f32 - IMPORTANCE_WEIGHT * importance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The tiles of a `columns` by `rows` grid, all with importance `importance`.
+    fn grid(columns: usize, rows: usize, importance: f32) -> Vec<(PixelRect, f32)> {
+        let frame = PixelRect {
+            x: 0,
+            y: 0,
+            width: columns * TILE_SIZE,
+            height: rows * TILE_SIZE,
+        };
+        split(&[frame])
+            .into_iter()
+            .map(|tile| (tile, importance))
+            .collect()
+    }
+
+    fn center(tile: PixelRect) -> (f32, f32) {
+        (
+            tile.x as f32 + tile.width as f32 / 2.0,
+            tile.y as f32 + tile.height as f32 / 2.0,
+        )
+    }
+
+    fn distance(tile: PixelRect, target: (f32, f32)) -> f32 {
+        let (x, y) = center(tile);
+        (x - target.0).hypot(y - target.1)
+    }
+
+    fn drain(queue: &mut TileQueue, focus: Option<(f32, f32)>) -> Vec<PixelRect> {
+        std::iter::from_fn(|| queue.pop(focus).map(|(_, tile)| tile)).collect()
+    }
+
+    #[test]
+    fn tiles_come_nearest_the_focus_first() {
+        let focus = (40.0, 200.0);
+        let mut queue = TileQueue::new(grid(6, 4, 0.0), (192.0, 128.0));
+        assert_eq!(queue.pop(Some(focus)).map(|(number, _)| number), Some(0));
+        assert_eq!(queue.pop(Some(focus)).map(|(number, _)| number), Some(1));
+        let mut queue = TileQueue::new(grid(6, 4, 0.0), (192.0, 128.0));
+        let order = drain(&mut queue, Some(focus));
+        assert_eq!(order.len(), 24);
+        assert_eq!((order[0].x, order[0].y), (0, 192));
+        for pair in order.windows(2) {
+            assert!(distance(pair[0], focus) <= distance(pair[1], focus));
+        }
+    }
+
+    #[test]
+    fn tiles_come_nearest_the_center_without_a_focus() {
+        let frame_center = (192.0, 128.0);
+        let mut queue = TileQueue::new(grid(6, 4, 0.0), frame_center);
+        let order = drain(&mut queue, None);
+        for pair in order.windows(2) {
+            assert!(distance(pair[0], frame_center) <= distance(pair[1], frame_center));
+        }
+    }
+
+    #[test]
+    fn importance_breaks_ties_and_jumps_ahead_by_its_weight() {
+        // Four tiles around the focus, equally far from it, with one of them more detailed.
+        let mut tiles = grid(2, 2, 0.1);
+        tiles[2].1 = 0.9;
+        let detailed = tiles[2].0;
+        let mut queue = TileQueue::new(tiles, (0.0, 0.0));
+        assert_eq!(queue.pop(Some((64.0, 64.0))), Some((0, detailed)));
+
+        // A tile moves ahead of plain ones by up to `IMPORTANCE_WEIGHT` tiles, by its importance:
+        // here ahead of one a tile nearer, and still behind one two tiles nearer.
+        let mut tiles = grid(8, 1, 0.0);
+        tiles[4].1 = 0.75;
+        let mut queue = TileQueue::new(tiles, (0.0, 0.0));
+        let order: Vec<usize> = drain(&mut queue, Some(center(grid(8, 1, 0.0)[0].0)))
+            .into_iter()
+            .map(|tile| tile.x / TILE_SIZE)
+            .collect();
+        assert_eq!(order, [0, 1, 2, 4, 3, 5, 6, 7]);
+    }
+
+    #[test]
+    fn the_queue_is_resorted_once_the_focus_moves_far_enough() {
+        let mut queue = TileQueue::new(grid(8, 1, 0.0), (0.0, 0.0));
+        let first = queue.pop(Some((32.0, 32.0))).map(|(_, tile)| tile.x);
+        assert_eq!(first, Some(0));
+        // A move shorter than `RESORT_DISTANCE` keeps the order planned for the old focus.
+        let nudged = (32.0 + RESORT_DISTANCE - 1.0, 32.0);
+        assert_eq!(queue.pop(Some(nudged)).map(|(_, tile)| tile.x), Some(64));
+        // A longer one plans again, from the tile under the new focus.
+        let moved = (7.0 * TILE_SIZE as f32 + 32.0, 32.0);
+        let rest: Vec<usize> = drain(&mut queue, Some(moved))
+            .into_iter()
+            .map(|tile| tile.x / TILE_SIZE)
+            .collect();
+        assert_eq!(rest, [7, 6, 5, 4, 3, 2]);
+    }
+}
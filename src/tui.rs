@@ -0,0 +1,308 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mandelbrot::render::{CancelToken, Progress, TileUpdate};
+
+// Live panels redraw at most this often; tiles land far faster than anyone can read.
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+const BAR_WIDTH: usize = 40;
+// The completion map is this many characters wide, and as tall as keeps the frame's shape with
+// characters about twice as tall as they are wide.
+const MAP_COLUMNS: usize = 32;
+const MAP_MAX_ROWS: usize = 16;
+// Plain output reports each job at these steps of its progress.
+const PLAIN_STEPS: usize = 4;
+
+// Progress front-end for the command line modes. On a terminal it keeps a panel up to date with
+// a progress bar, ETA and completion map for the job running; otherwise it prints a line at each
+// quarter of a job. Either way, lines typed on stdin control the batch: `p` pauses or resumes,
+// `s` skips the job running and `c` cancels the rest.
+pub struct Tui {
+    panel: Arc<Mutex<Panel>>,
+}
+
+struct Panel {
+    live: bool,
+    cancel: CancelToken,
+    jobs: usize,
+    job: usize,
+    name: String,
+    progress: Option<Progress>,
+    map: CompletionMap,
+    // Pixels and time of the jobs already finished, for the batch estimate.
+    done_pixels: usize,
+    done_time: Duration,
+    // Lines the panel took when last drawn, including commands echoed under it since.
+    drawn: usize,
+    last_draw: Option<Instant>,
+    reported_step: usize,
+    // What the last command did, shown until the next job starts.
+    notice: &'static str,
+}
+
+impl Tui {
+    pub fn new(jobs: usize, cancel: &CancelToken) -> Tui {
+        let live = io::stdout().is_terminal();
+        let panel = Arc::new(Mutex::new(Panel {
+            live,
+            cancel: cancel.clone(),
+            jobs,
+            job: 0,
+            name: String::new(),
+            progress: None,
+            map: CompletionMap::default(),
+            done_pixels: 0,
+            done_time: Duration::ZERO,
+            drawn: 0,
+            last_draw: None,
+            reported_step: 0,
+            notice: "",
+        }));
+        let echoes = io::stdin().is_terminal();
+        let commands = panel.clone();
+        thread::spawn(move || {
+            for line in io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                let mut panel = commands.lock().unwrap_or_else(PoisonError::into_inner);
+                if echoes {
+                    panel.drawn += 1;
+                }
+                panel.command(line.trim());
+            }
+        });
+        if live {
+            println!("commands: p + enter to pause or resume, s to skip a job, c to cancel");
+        }
+        Tui { panel }
+    }
+
+    pub fn start_job(&self, name: &str) {
+        let mut panel = self.lock();
+        panel.job += 1;
+        panel.name = name.to_string();
+        panel.progress = None;
+        panel.reported_step = 0;
+        panel.last_draw = None;
+        panel.notice = "";
+    }
+
+    pub fn update(&self, update: &TileUpdate) {
+        let mut panel = self.lock();
+        let frame = update.frame;
+        if panel.progress.is_none() {
+            panel.map = CompletionMap::new(frame.width, frame.height);
+        }
+        let rect = update.tile.rect;
+        panel.map.add(
+            rect.x,
+            rect.y,
+            rect.width,
+            rect.height,
+            frame.width,
+            frame.height,
+        );
+        panel.progress = Some(update.progress);
+        if panel.live {
+            let due = panel
+                .last_draw
+                .is_none_or(|last| last.elapsed() >= REDRAW_INTERVAL);
+            if due || update.progress.pixels_done == update.progress.total_pixels {
+                panel.draw();
+            }
+            return;
+        }
+        let step = update.progress.pixels_done * PLAIN_STEPS / update.progress.total_pixels.max(1);
+        if step > panel.reported_step && step < PLAIN_STEPS {
+            panel.reported_step = step;
+            println!("{}", panel.status());
+        }
+    }
+
+    // Ends the job running with `message`, which stays on screen above the next job's panel.
+    pub fn finish_job(&self, message: &str) {
+        let mut panel = self.lock();
+        if let Some(progress) = panel.progress {
+            panel.done_pixels += progress.pixels_done;
+            panel.done_time += progress.elapsed;
+        }
+        panel.clear();
+        println!("[{}/{}] {}: {}", panel.job, panel.jobs, panel.name, message);
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Panel> {
+        self.panel.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Panel {
+    fn command(&mut self, command: &str) {
+        let message = match command {
+            "p" if self.cancel.is_paused() => {
+                self.cancel.set_paused(false);
+                "resumed"
+            }
+            "p" => {
+                self.cancel.set_paused(true);
+                "paused"
+            }
+            "s" => {
+                self.cancel.skip();
+                "skipping"
+            }
+            "c" | "q" => {
+                self.cancel.cancel();
+                "cancelling"
+            }
+            _ => "unknown command; p pauses or resumes, s skips a job, c cancels",
+        };
+        self.notice = message;
+        if self.live && self.progress.is_some() {
+            self.draw();
+        } else {
+            println!("{}", message);
+        }
+    }
+
+    // One line on the job running: its bar, throughput and estimates.
+    fn status(&self) -> String {
+        let head = format!("[{}/{}] {}", self.job, self.jobs, self.name);
+        let Some(progress) = self.progress else {
+            return head;
+        };
+        let fraction = progress.fraction();
+        let filled = (fraction * BAR_WIDTH as f32).round() as usize;
+        let seconds = progress.elapsed.as_secs_f64();
+        let rate = progress.pixels_done as f64 / seconds.max(1e-3);
+        let remaining = progress.total_pixels - progress.pixels_done.min(progress.total_pixels);
+        // Later jobs are taken to be the same size as this one and to go at the batch's speed.
+        let batch_rate = (self.done_pixels + progress.pixels_done) as f64
+            / (self.done_time + progress.elapsed).as_secs_f64().max(1e-3);
+        let batch_remaining =
+            remaining + self.jobs.saturating_sub(self.job) * progress.total_pixels;
+        let mut status = format!(
+            "{} [{}{}] {:>3.0}% {:.2} Mpx/s eta {} batch eta {}",
+            head,
+            "#".repeat(filled.min(BAR_WIDTH)),
+            " ".repeat(BAR_WIDTH - filled.min(BAR_WIDTH)),
+            fraction * 100.0,
+            rate / 1e6,
+            format_eta(remaining as f64 / rate),
+            format_eta(batch_remaining as f64 / batch_rate)
+        );
+        if self.cancel.is_paused() {
+            status.push_str(" (paused)");
+        } else if !self.notice.is_empty() {
+            status.push_str(&format!(" ({})", self.notice));
+        }
+        status
+    }
+
+    fn draw(&mut self) {
+        self.clear();
+        let mut lines = vec![self.status()];
+        lines.extend(self.map.rows());
+        let mut stdout = io::stdout().lock();
+        for line in &lines {
+            let _ = writeln!(stdout, "{}", line);
+        }
+        let _ = stdout.flush();
+        self.drawn = lines.len();
+        self.last_draw = Some(Instant::now());
+    }
+
+    // Erases the panel, leaving the cursor where it started.
+    fn clear(&mut self) {
+        if self.live && self.drawn > 0 {
+            print!("\x1b[{}F\x1b[J", self.drawn);
+        }
+        self.drawn = 0;
+    }
+}
+
+// How much of each cell of a coarse grid over the frame is done: `#` for all of it, `+` for some
+// and `.` for none.
+#[derive(Default)]
+struct CompletionMap {
+    columns: usize,
+    rows: usize,
+    done: Vec<usize>,
+    area: Vec<usize>,
+}
+
+impl CompletionMap {
+    fn new(width: usize, height: usize) -> CompletionMap {
+        let columns = MAP_COLUMNS.min(width.max(1));
+        let rows = ((columns * height) as f64 / width.max(1) as f64 / 2.0)
+            .round()
+            .clamp(1.0, MAP_MAX_ROWS as f64) as usize;
+        let mut map = CompletionMap {
+            columns,
+            rows,
+            done: vec![0; columns * rows],
+            area: vec![0; columns * rows],
+        };
+        map.add(0, 0, width, height, width, height);
+        map.area = std::mem::replace(&mut map.done, vec![0; columns * rows]);
+        map
+    }
+
+    // Marks the pixel rectangle at (`x`, `y`) of a `width` by `height` frame as done.
+    fn add(&mut self, x: usize, y: usize, w: usize, h: usize, width: usize, height: usize) {
+        if self.done.is_empty() {
+            return;
+        }
+        // Cell edges in pixels.
+        let edge = |cell: usize, cells: usize, size: usize| cell * size / cells;
+        for row in 0..self.rows {
+            let (top, bottom) = (
+                edge(row, self.rows, height),
+                edge(row + 1, self.rows, height),
+            );
+            let rows = bottom.min(y + h).saturating_sub(top.max(y));
+            if rows == 0 {
+                continue;
+            }
+            for column in 0..self.columns {
+                let (left, right) = (
+                    edge(column, self.columns, width),
+                    edge(column + 1, self.columns, width),
+                );
+                let columns = right.min(x + w).saturating_sub(left.max(x));
+                self.done[row * self.columns + column] += rows * columns;
+            }
+        }
+    }
+
+    fn rows(&self) -> Vec<String> {
+        self.done
+            .chunks(self.columns.max(1))
+            .zip(self.area.chunks(self.columns.max(1)))
+            .map(|(done, area)| {
+                done.iter()
+                    .zip(area)
+                    .map(|(done, area)| match done {
+                        0 => '.',
+                        done if done >= area => '#',
+                        _ => '+',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+fn format_eta(seconds: f64) -> String {
+    if !seconds.is_finite() {
+        return "?".to_string();
+    }
+    let seconds = seconds.round() as u64;
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!("{}h{:02}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
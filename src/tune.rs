@@ -0,0 +1,191 @@
+use iced::Size;
+
+use serde::{Deserialize, Serialize};
+
+use std::sync::OnceLock;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use threadpool::ThreadPool;
+
+use crate::fractal::{AngleKind, FractalKind};
+use crate::render::{self, Arithmetic, CancelToken, FrameParams};
+use crate::storage::Precision;
+use crate::tiling::{self, CHUNK_SIZES, TILE_SIZE};
+use crate::viewport::Viewport;
+
+// Trials stop once this much time has gone, keeping the best found so far; with the trial
+// in progress the tuner finishes well inside 15 seconds.
+pub const TUNE_BUDGET: Duration = Duration::from_secs(12);
+// The worker count used when nothing was measured.
+pub const DEFAULT_THREADS: usize = 8;
+// The standard frame: the seahorse valley, busy enough everywhere that scheduling matters.
+const TUNE_SIZE: Size = Size::new(640.0, 480.0);
+const TUNE_ITERATIONS: u32 = 1000;
+// Each combination is timed this many times and scored by its fastest, to see past noise.
+const TUNE_ROUNDS: usize = 3;
+
+// The fastest chunk size and worker count found on a machine, and which machine that was.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tuning {
+    pub fingerprint: String,
+    pub chunk_size: usize,
+    pub threads: usize,
+    // False for the heuristic defaults, recorded when tuning was skipped so that it is not
+    // offered again on the same machine.
+    pub measured: bool,
+}
+
+impl Tuning {
+    // The defaults used without measurements, for this machine.
+    pub fn heuristic() -> Tuning {
+        Tuning {
+            fingerprint: fingerprint(),
+            chunk_size: TILE_SIZE,
+            threads: DEFAULT_THREADS,
+            measured: false,
+        }
+    }
+
+    // Whether this was found on the machine running now.
+    pub fn is_current(&self) -> bool {
+        self.fingerprint == fingerprint()
+    }
+}
+
+// What renders are fast or slow on: the architecture, the core count and the SIMD extensions
+// available. Worked out once per process.
+pub fn fingerprint() -> String {
+    static FINGERPRINT: OnceLock<String> = OnceLock::new();
+    FINGERPRINT
+        .get_or_init(|| {
+            let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+            format!(
+                "{} {} cores {}",
+                std::env::consts::ARCH,
+                cores,
+                simd_features().join(",")
+            )
+            .trim_end()
+            .to_string()
+        })
+        .clone()
+}
+
+#[cfg(target_arch = "x86_64")]
+fn simd_features() -> Vec<&'static str> {
+    [
+        ("sse4.2", std::arch::is_x86_feature_detected!("sse4.2")),
+        ("avx", std::arch::is_x86_feature_detected!("avx")),
+        ("avx2", std::arch::is_x86_feature_detected!("avx2")),
+        ("fma", std::arch::is_x86_feature_detected!("fma")),
+        ("avx512f", std::arch::is_x86_feature_detected!("avx512f")),
+    ]
+    .into_iter()
+    .filter_map(|(name, present)| present.then_some(name))
+    .collect()
+}
+
+#[cfg(target_arch = "aarch64")]
+fn simd_features() -> Vec<&'static str> {
+    if std::arch::is_aarch64_feature_detected!("neon") {
+        vec!["neon"]
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+fn simd_features() -> Vec<&'static str> {
+    Vec::new()
+}
+
+// Worker counts worth trying: half, all and twice the cores, and the default.
+pub fn thread_counts() -> Vec<usize> {
+    let cores = thread::available_parallelism().map_or(1, |cores| cores.get());
+    let mut counts = vec![(cores / 2).max(1), cores, cores * 2, DEFAULT_THREADS];
+    counts.sort_unstable();
+    counts.dedup();
+    counts
+}
+
+// Renders the standard frame with every chunk size at every worker count and returns the
+// fastest combination. Stops early past `budget` with the best so far, and fails when cancelled.
+// The chunk size in effect beforehand is restored either way, since trials change it for the
+// whole process.
+pub fn run(
+    cancel: &CancelToken,
+    budget: Duration,
+    mut on_progress: impl FnMut(f32),
+) -> Result<Tuning, String> {
+    let previous_chunk = tiling::chunk_size();
+    let result = trials(cancel, budget, &mut on_progress);
+    tiling::set_chunk_size(previous_chunk);
+    result
+}
+
+fn trials(
+    cancel: &CancelToken,
+    budget: Duration,
+    on_progress: &mut impl FnMut(f32),
+) -> Result<Tuning, String> {
+    let start = Instant::now();
+    let viewport = Viewport::new(-0.7453, 0.1127, 0.0065);
+    let params = FrameParams {
+        viewport,
+        max_iterations: TUNE_ITERATIONS,
+        fractal: FractalKind::Mandelbrot,
+        angle: AngleKind::Off,
+        precision: Precision::Full,
+        coarse_prepass: false,
+        arithmetic: Arithmetic::F64,
+    };
+    let counts = thread_counts();
+    let total = counts.len() * CHUNK_SIZES.len();
+    let mut best: Option<(Duration, usize, usize)> = None;
+    let mut done = 0;
+    for threads in counts {
+        let pool = ThreadPool::new(threads);
+        for chunk in CHUNK_SIZES {
+            if cancel.is_cancelled() {
+                return Err(String::from("cancelled"));
+            }
+            if start.elapsed() >= budget && best.is_some() {
+                println!("tuning budget spent after {} of {} trials", done, total);
+                return Ok(tuned(best));
+            }
+            tiling::set_chunk_size(chunk);
+            let mut elapsed = Duration::MAX;
+            for _ in 0..TUNE_ROUNDS {
+                let trial = Instant::now();
+                render::threaded_fractal_calc(&pool, TUNE_SIZE, params, cancel, |_| {})
+                    .map_err(|err| err.to_string())?;
+                elapsed = elapsed.min(trial.elapsed());
+            }
+            println!(
+                "tuning: {} threads, {} px chunks: {:.1} ms",
+                threads,
+                chunk,
+                elapsed.as_secs_f64() * 1000.0
+            );
+            if best.is_none_or(|(fastest, _, _)| elapsed < fastest) {
+                best = Some((elapsed, chunk, threads));
+            }
+            done += 1;
+            on_progress(done as f32 / total as f32);
+        }
+    }
+    Ok(tuned(best))
+}
+
+fn tuned(best: Option<(Duration, usize, usize)>) -> Tuning {
+    match best {
+        Some((_, chunk_size, threads)) => Tuning {
+            fingerprint: fingerprint(),
+            chunk_size,
+            threads,
+            measured: true,
+        },
+        None => Tuning::heuristic(),
+    }
+}
@@ -0,0 +1,171 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Previews of a render that may be on their way to the UI at once. Each is a copy of the frame,
+// so this bounds the memory a render ties up in the message queue when the UI falls behind.
+pub const MAX_IN_FLIGHT: usize = 2;
+// The UI rebuilds its image for every preview; at most 20 times a second.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(50);
+
+// Decides which landed tiles a render sends a preview of the frame for. Tiles that land while
+// previews are held back are not dropped but coalesced: the next preview is of the frame with all
+// of them, and the finished frame always goes out in full.
+#[derive(Debug)]
+pub struct PreviewValve {
+    in_flight: Arc<AtomicUsize>,
+    last_sent: Instant,
+    // Tiles landed since the last preview.
+    pending: usize,
+}
+
+// A preview's place in flight, given back once every copy of it is dropped, whether the preview
+// was painted or found stale.
+#[derive(Clone, Debug)]
+pub struct Permit {
+    _slot: Arc<Slot>,
+}
+
+#[derive(Debug)]
+struct Slot(Arc<AtomicUsize>);
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+impl PreviewValve {
+    // A valve for a render started at `now`; the first preview waits for the interval too.
+    pub fn new(now: Instant) -> PreviewValve {
+        PreviewValve {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            last_sent: now,
+            pending: 0,
+        }
+    }
+
+    // Records a tile landing at `now`. When a preview should go out, returns its permit and how
+    // many tiles it brings.
+    pub fn tile_landed(&mut self, now: Instant) -> Option<(Permit, usize)> {
+        self.pending += 1;
+        if now.saturating_duration_since(self.last_sent) < MIN_INTERVAL
+            || self.in_flight() >= MAX_IN_FLIGHT
+        {
+            return None;
+        }
+        // Only the render's own thread sends, so nothing takes the place between the check and
+        // here.
+        self.in_flight.fetch_add(1, Ordering::AcqRel);
+        self.last_sent = now;
+        let permit = Permit {
+            _slot: Arc::new(Slot(Arc::clone(&self.in_flight))),
+        };
+        Some((permit, std::mem::take(&mut self.pending)))
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Acquire)
+    }
+
+    // Tiles no preview has brought yet, which the finished frame brings.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use super::*;
+    use crate::stats::{self, CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    // How a flood of tiles came through the valve to a UI that falls behind.
+    #[derive(Debug)]
+    struct Flood {
+        tiles: usize,
+        previews: usize,
+        // The most frame copies alive with the previews queued for the UI.
+        most_copies: usize,
+        // Tiles the previews and the finished frame brought between them.
+        tiles_brought: usize,
+        // The most previews sent within any second.
+        most_per_second: usize,
+    }
+
+    // Floods a valve with a tile every `tile_interval` of simulated time, copying the frame for
+    // every preview it lets through, while the UI takes `paint_time` to paint each one.
+    fn flood(tiles: usize, tile_interval: Duration, paint_time: Duration) -> Flood {
+        let frame = stats::render_frame(CANONICAL_SIZE, CANONICAL_VIEWS[0].params());
+        let start = Instant::now();
+        let mut valve = PreviewValve::new(start);
+        let mut queue = VecDeque::new();
+        let mut painted_until = start;
+        let mut sent = Vec::new();
+        let mut flood = Flood {
+            tiles,
+            previews: 0,
+            most_copies: 0,
+            tiles_brought: 0,
+            most_per_second: 0,
+        };
+        for tile in 1..=tiles {
+            let now = start + tile_interval * tile as u32;
+            // The UI paints the oldest preview whenever it is done with the one before.
+            while painted_until + paint_time <= now {
+                let Some(_painted) = queue.pop_front() else {
+                    painted_until = now;
+                    break;
+                };
+                painted_until += paint_time;
+            }
+            if let Some((permit, brought)) = valve.tile_landed(now) {
+                queue.push_back((Arc::new(frame.pooled_copy()), permit));
+                flood.previews += 1;
+                flood.tiles_brought += brought;
+                sent.push(now);
+            }
+            flood.most_copies = flood.most_copies.max(valve.in_flight());
+        }
+        flood.tiles_brought += valve.pending();
+        flood.most_per_second = (0..sent.len())
+            .map(|first| {
+                sent[first..]
+                    .iter()
+                    .take_while(|&&at| at < sent[first] + Duration::from_secs(1))
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        flood
+    }
+
+    fn per_second() -> usize {
+        (Duration::from_secs(1).as_millis() / MIN_INTERVAL.as_millis()) as usize
+    }
+
+    // A tile every millisecond for ten seconds, to a UI taking 300 ms per preview.
+    #[test]
+    fn slow_uis_hold_few_copies_and_lose_no_tiles() {
+        let slow = flood(10_000, Duration::from_millis(1), Duration::from_millis(300));
+        assert!(
+            slow.most_copies <= MAX_IN_FLIGHT,
+            "{} frame copies were alive at once",
+            slow.most_copies
+        );
+        assert_eq!(slow.tiles_brought, slow.tiles, "{:?}", slow);
+        assert!(slow.most_per_second <= per_second(), "{:?}", slow);
+    }
+
+    // The same flood, to a UI taking 1 ms per preview.
+    #[test]
+    fn fast_uis_get_previews_at_the_full_rate() {
+        let fast = flood(10_000, Duration::from_millis(1), Duration::from_millis(1));
+        assert!(
+            fast.most_per_second <= per_second() && fast.previews >= per_second() * 9,
+            "{:?}",
+            fast
+        );
+    }
+}
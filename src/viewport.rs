@@ -0,0 +1,649 @@
+use iced::{Point, Size};
+
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+
+use crate::doubledouble::{self, DoubleDouble};
+use crate::fractal::FractalKind;
+use crate::render;
+
+pub const FIT_MARGIN: f64 = 0.05;
+
+// Complex-plane view with square pixels, in the mathematical convention: +imaginary points up,
+// so row 0 of a frame is its largest imaginary part. Renders, locations, files and exports all
+// use it; the `invert_y` setting only mirrors how frames are shown and how window positions map
+// onto them, through `mirror_y`.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    pub center_re: f64,
+    pub center_im: f64,
+    pub width: f64,
+    // Low-order parts of the center, so it can be placed finer than f64 allows once zoomed past
+    // f64's resolution. Always below half an ulp of the high part.
+    #[serde(default)]
+    pub center_re_lo: f64,
+    #[serde(default)]
+    pub center_im_lo: f64,
+}
+
+impl Viewport {
+    pub fn new(center_re: f64, center_im: f64, width: f64) -> Viewport {
+        Viewport {
+            center_re,
+            center_im,
+            width,
+            center_re_lo: 0.0,
+            center_im_lo: 0.0,
+        }
+    }
+
+    pub fn center(&self) -> (DoubleDouble, DoubleDouble) {
+        (
+            DoubleDouble::new(self.center_re, self.center_re_lo),
+            DoubleDouble::new(self.center_im, self.center_im_lo),
+        )
+    }
+
+    // Moves the center by (re, im) without rounding it to f64.
+    pub fn shifted(&self, re: f64, im: f64, width: f64) -> Viewport {
+        let (center_re, center_im) = self.center();
+        let center_re = center_re + DoubleDouble::from(re);
+        let center_im = center_im + DoubleDouble::from(im);
+        Viewport {
+            center_re: center_re.hi,
+            center_im: center_im.hi,
+            width,
+            center_re_lo: center_re.lo,
+            center_im_lo: center_im.lo,
+        }
+    }
+
+    // `self`'s center minus `other`'s, exact enough to compare views narrower than f64 resolves.
+    pub fn center_delta(&self, other: &Viewport) -> (f64, f64) {
+        let (re, im) = self.center();
+        let (other_re, other_im) = other.center();
+        ((re - other_re).to_f64(), (im - other_im).to_f64())
+    }
+
+    // Largest magnitude of the center's coordinates, which sets the absolute precision f64 has
+    // around it.
+    pub fn magnitude(&self) -> f64 {
+        self.center_re
+            .abs()
+            .max(self.center_im.abs())
+            .max(f64::MIN_POSITIVE)
+    }
+
+    pub fn fit(bounds: (f64, f64, f64, f64), size: Size, margin: f64) -> Viewport {
+        let (re_min, re_max, im_min, im_max) = bounds;
+        let aspect = size.width as f64 / size.height as f64;
+        let width = (re_max - re_min).max((im_max - im_min) * aspect);
+        Viewport::new(
+            (re_min + re_max) / 2.0,
+            (im_min + im_max) / 2.0,
+            width * (1.0 + 2.0 * margin),
+        )
+    }
+
+    pub fn home(fractal: FractalKind, size: Size) -> Viewport {
+        Viewport::fit(fractal.fractal().bounds(), size, FIT_MARGIN)
+    }
+
+    pub fn height(&self, size: Size) -> f64 {
+        self.width * size.height as f64 / size.width as f64
+    }
+
+    pub fn pixel_size(&self, size: Size) -> f64 {
+        self.width / size.width as f64
+    }
+
+    pub fn top_left(&self, size: Size) -> (f64, f64) {
+        (
+            self.center_re + self.center_re_lo - self.width / 2.0,
+            self.center_im + self.center_im_lo + self.height(size) / 2.0,
+        )
+    }
+
+    // The view as "re im width", which `numbers::parse_location` reads back as the same view.
+    pub fn location(&self) -> String {
+        format!("{} {} {}", self.center_re, self.center_im, self.width)
+    }
+
+    pub fn pixel_to_complex(&self, point: Point, size: Size) -> (f64, f64) {
+        let (left, top) = self.top_left(size);
+        let pixel_size = self.pixel_size(size);
+        (
+            left + point.x as f64 * pixel_size,
+            top - point.y as f64 * pixel_size,
+        )
+    }
+
+    // The point of the plane at `point` of the window, to the precision of the center.
+    pub fn precise_point(&self, point: Point, size: Size) -> (DoubleDouble, DoubleDouble) {
+        let pixel_size = self.pixel_size(size);
+        let (re, im) = self.center();
+        (
+            re + DoubleDouble::from((point.x as f64 - size.width as f64 / 2.0) * pixel_size),
+            im + DoubleDouble::from((size.height as f64 / 2.0 - point.y as f64) * pixel_size),
+        )
+    }
+
+    // Keeps the center and the complex units per pixel, so a resize reveals or crops the plane.
+    // The center snaps by at most half a pixel to stay on the old pixel grid.
+    pub fn resized(&self, old_size: Size, new_size: Size) -> Viewport {
+        let pixel_size = self.pixel_size(old_size);
+        let dx = ((new_size.width - old_size.width) / 2.0).floor() as f64;
+        let dy = ((new_size.height - old_size.height) / 2.0).floor() as f64;
+        // How far the new center lies from the old one, in pixels.
+        let shift_x = (new_size.width as f64 - old_size.width as f64) / 2.0 - dx;
+        let shift_y = (old_size.height as f64 - new_size.height as f64) / 2.0 + dy;
+        self.shifted(
+            shift_x * pixel_size,
+            shift_y * pixel_size,
+            pixel_size * new_size.width as f64,
+        )
+    }
+
+    // Works in pixel offsets from the center, so selections stay exact when the view is too
+    // narrow for f64 coordinates, and in f64 from the start, so that thin selections lose nothing
+    // to f32. The view narrows no further than double-double arithmetic resolves around it, or
+    // than the current view where that is already past it.
+    pub fn from_selection(
+        current: &Viewport,
+        start: Point,
+        end: Point,
+        size: Size,
+    ) -> Result<Viewport, SelectionError> {
+        let (width, height) = (size.width as f64, size.height as f64);
+        let (x0, y0, x1, y1) = (start.x as f64, start.y as f64, end.x as f64, end.y as f64);
+        let pixel_size = current.pixel_size(size);
+        if ![width, height, x0, y0, x1, y1, pixel_size]
+            .iter()
+            .all(|value| value.is_finite())
+            || width < 1.0
+            || height < 1.0
+            || pixel_size <= 0.0
+        {
+            return Err(SelectionError::Invalid);
+        }
+        let (selected_width, selected_height) = ((x1 - x0).abs(), (y1 - y0).abs());
+        if selected_width < MIN_SELECTION || selected_height < MIN_SELECTION {
+            return Err(SelectionError::TooSmall {
+                width: selected_width,
+                height: selected_height,
+            });
+        }
+        let mid_x = (x0 + x1) / 2.0 - width / 2.0;
+        let mid_y = (y0 + y1) / 2.0 - height / 2.0;
+        let narrowest =
+            current.magnitude() * doubledouble::EPSILON * render::MIN_PIXEL_ULPS * width;
+        let view_width = (selected_width.max(selected_height * width / height) * pixel_size)
+            .max(narrowest.min(current.width))
+            .max(f64::MIN_POSITIVE);
+        let view = current.shifted(mid_x * pixel_size, -mid_y * pixel_size, view_width);
+        if !(view.center_re.is_finite() && view.center_im.is_finite() && view.width.is_finite()) {
+            return Err(SelectionError::Invalid);
+        }
+        Ok(view)
+    }
+}
+
+// Selections narrower or shorter than this many pixels are taken for slips of the mouse.
+pub const MIN_SELECTION: f64 = 4.0;
+
+// Why a selection gives no view to zoom to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SelectionError {
+    // Narrower or shorter than `MIN_SELECTION` pixels, in pixels.
+    TooSmall { width: f64, height: f64 },
+    // Corners, a frame or a view that are not finite, or a frame with no pixels.
+    Invalid,
+}
+
+impl fmt::Display for SelectionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelectionError::TooSmall { width, height } => write!(
+                f,
+                "a {:.0}x{:.0} px selection is too thin to zoom to; drag at least {} px each way",
+                width, height, MIN_SELECTION
+            ),
+            SelectionError::Invalid => write!(f, "the selection lies outside any usable view"),
+        }
+    }
+}
+
+impl std::error::Error for SelectionError {}
+
+// An affine map between the pixel positions of two frames: (x, y) goes to
+// (xx * x + xy * y + x0, yx * x + yy * y + y0). Pixel positions are where a frame samples the
+// plane, as `Viewport::pixel_to_complex` places them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PixelTransform {
+    pub xx: f64,
+    pub xy: f64,
+    pub yx: f64,
+    pub yy: f64,
+    pub x0: f64,
+    pub y0: f64,
+}
+
+impl PixelTransform {
+    // Where each pixel of a `to_size` frame of `to` lies in a `from_size` frame of `from`. Views
+    // do not rotate, so this only scales and translates; it works from the centers' difference,
+    // so it stays exact for views too narrow for f64 coordinates.
+    pub fn between(
+        from: &Viewport,
+        from_size: Size,
+        to: &Viewport,
+        to_size: Size,
+    ) -> PixelTransform {
+        let from_pixel = from.pixel_size(from_size);
+        let scale = to.pixel_size(to_size) / from_pixel;
+        let (re, im) = to.center_delta(from);
+        PixelTransform {
+            xx: scale,
+            xy: 0.0,
+            yx: 0.0,
+            yy: scale,
+            x0: (re + (from.width - to.width) / 2.0) / from_pixel,
+            y0: (-im + (from.height(from_size) - to.height(to_size)) / 2.0) / from_pixel,
+        }
+    }
+
+    // Row `y` of a frame `height` pixels tall to the row it is shown in upside down.
+    pub fn mirror_rows(height: f64) -> PixelTransform {
+        PixelTransform {
+            xx: 1.0,
+            xy: 0.0,
+            yx: 0.0,
+            yy: -1.0,
+            x0: 0.0,
+            y0: height - 1.0,
+        }
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.xx * x + self.xy * y + self.x0,
+            self.yx * x + self.yy * y + self.y0,
+        )
+    }
+
+    // This map followed by `next`.
+    pub fn then(&self, next: &PixelTransform) -> PixelTransform {
+        let (x0, y0) = next.apply(self.x0, self.y0);
+        PixelTransform {
+            xx: next.xx * self.xx + next.xy * self.yx,
+            xy: next.xx * self.xy + next.xy * self.yy,
+            yx: next.yx * self.xx + next.yy * self.yx,
+            yy: next.yx * self.xy + next.yy * self.yy,
+            x0,
+            y0,
+        }
+    }
+}
+
+// The frame position shown at `point` of a `size` window that shows +imaginary down: the same
+// column, mirrored top to bottom.
+pub fn mirror_y(point: Point, size: Size) -> Point {
+    Point::new(point.x, size.height - point.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use num::Complex;
+
+    use super::*;
+    use crate::fractal::{self, AngleKind};
+    use crate::numbers;
+    use crate::render::{Arithmetic, FrameParams};
+    use crate::stats::{self, CANONICAL_VIEWS};
+    use crate::storage::Precision;
+
+    // An off-axis view, so that a frame drawn upside down differs from the right way up.
+    const ORIENTATION_VIEW: (f64, f64, f64) = (-0.7453, 0.1127, 0.0065);
+    const ORIENTATION_SIZE: Size = Size::new(16.0, 12.0);
+
+    // The visible spans of the home view of a `size` window, as multiples of the set's bounding
+    // box with its margin each way.
+    fn home_spans(size: Size) -> (f64, f64) {
+        let (re_min, re_max, im_min, im_max) = FractalKind::Mandelbrot.fractal().bounds();
+        let view = Viewport::home(FractalKind::Mandelbrot, size);
+        assert!((view.center_re - (re_min + re_max) / 2.0).abs() < 1e-12);
+        assert!((view.center_im - (im_min + im_max) / 2.0).abs() < 1e-12);
+        let margin = 1.0 + 2.0 * FIT_MARGIN;
+        (
+            view.width / ((re_max - re_min) * margin),
+            view.height(size) / ((im_max - im_min) * margin),
+        )
+    }
+
+    #[test]
+    fn home_fits_landscape_windows_by_height() {
+        let (across, down) = home_spans(Size::new(1920.0, 1080.0));
+        assert!(
+            (down - 1.0).abs() < 1e-9,
+            "the set spans {} of the height",
+            down
+        );
+        assert!(across > 1.5, "the set spans {} of the width", across);
+    }
+
+    #[test]
+    fn home_fits_portrait_windows_by_width() {
+        let (across, down) = home_spans(Size::new(600.0, 1000.0));
+        assert!(
+            (across - 1.0).abs() < 1e-9,
+            "the set spans {} of the width",
+            across
+        );
+        assert!(down > 1.5, "the set spans {} of the height", down);
+    }
+
+    #[test]
+    fn home_fits_square_windows_by_the_wider_span() {
+        let (across, down) = home_spans(Size::new(800.0, 800.0));
+        assert!(
+            (across - 1.0).abs() < 1e-9,
+            "the set spans {} of the width",
+            across
+        );
+        assert!(down > 1.0, "the set spans {} of the height", down);
+    }
+
+    #[test]
+    fn canonical_locations_read_back_unchanged() {
+        for view in CANONICAL_VIEWS {
+            let viewport = view.params().viewport;
+            let location = viewport.location();
+            let read = numbers::parse_location(&location);
+            let reads_back = match read {
+                Ok((re, im, Some(width))) => Viewport::new(re, im, width) == viewport,
+                _ => false,
+            };
+            assert!(
+                reads_back,
+                "{}: location \"{}\" reads back as {:?}",
+                view.name, location, read
+            );
+        }
+    }
+
+    #[test]
+    fn frames_are_drawn_imaginary_up() {
+        let viewport = Viewport::new(ORIENTATION_VIEW.0, ORIENTATION_VIEW.1, ORIENTATION_VIEW.2);
+        let size = ORIENTATION_SIZE;
+        let params = FrameParams {
+            viewport,
+            max_iterations: 500,
+            fractal: FractalKind::Mandelbrot,
+            angle: AngleKind::Off,
+            precision: Precision::Full,
+            coarse_prepass: false,
+            arithmetic: Arithmetic::F64,
+        };
+        let frame = stats::render_frame(size, params);
+        let pixel_size = viewport.pixel_size(size);
+        let (width, height) = (size.width as usize, size.height as usize);
+        // Pixel corners worked out from the center outwards, independently of `top_left`.
+        let point = |x: usize, y: usize| {
+            Complex::new(
+                viewport.center_re + (x as f64 - width as f64 / 2.0) * pixel_size,
+                viewport.center_im + (height as f64 / 2.0 - y as f64) * pixel_size,
+            )
+        };
+        // Coordinates worked out another way may round differently, so values are compared
+        // loosely, and the frame has to match its upright reading far better than its mirrored
+        // one.
+        let reference: Vec<f32> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(|(x, y)| {
+                fractal::reference_iterate(params.fractal, point(x, y), 500, AngleKind::Off).value
+            })
+            .collect();
+        let mismatches = |row: &dyn Fn(usize) -> usize| {
+            (0..width * height)
+                .filter(|&index| {
+                    let (x, y) = (index % width, index / width);
+                    (frame.values.get(index) - reference[row(y) * width + x]).abs() > 1e-3
+                })
+                .count()
+        };
+        let upright = mismatches(&|y| y);
+        let mirrored = mismatches(&|y| height - 1 - y);
+        assert!(
+            upright * 10 <= width * height && upright < mirrored,
+            "frame rows do not run from +imaginary down ({} pixels off upright, {} mirrored)",
+            upright,
+            mirrored
+        );
+    }
+
+    #[test]
+    fn window_tops_read_above_the_center() {
+        let viewport = Viewport::new(ORIENTATION_VIEW.0, ORIENTATION_VIEW.1, ORIENTATION_VIEW.2);
+        let size = ORIENTATION_SIZE;
+        let top = Point::new(size.width / 2.0, 0.0);
+        let (_, im) = viewport.pixel_to_complex(top, size);
+        assert!(
+            im > viewport.center_im,
+            "the top of the window reads below the center"
+        );
+        let (_, im) = viewport.pixel_to_complex(mirror_y(top, size), size);
+        assert!(
+            im < viewport.center_im,
+            "the top of a mirrored window reads above the center"
+        );
+    }
+
+    // Each view's pixels land on the same points of the plane.
+    #[test]
+    fn transforms_map_pixels_onto_the_points_they_show() {
+        let size = Size::new(64.0, 48.0);
+        let from = Viewport::new(-0.5, 0.25, 4.0);
+        for to in [
+            from.shifted(0.0, 0.0, 2.0),
+            from.shifted(0.0, 0.0, 8.0),
+            from.shifted(0.3, -0.2, 5.0),
+        ] {
+            let transform = PixelTransform::between(&from, size, &to, size);
+            let pixel = Point::new(13.0, 41.0);
+            let (x, y) = transform.apply(pixel.x as f64, pixel.y as f64);
+            let (new_re, new_im) = to.pixel_to_complex(pixel, size);
+            let (old_re, old_im) = from.pixel_to_complex(Point::new(x as f32, y as f32), size);
+            assert!(
+                (new_re - old_re).abs() <= 1e-6 && (new_im - old_im).abs() <= 1e-6,
+                "pixel {:?} of {:?} maps to ({}, {}) of {:?}, off the point it shows",
+                pixel,
+                to,
+                x,
+                y,
+                from
+            );
+        }
+    }
+
+    // Past f64's resolution, a move maps as the same move does at a shallow zoom.
+    #[test]
+    fn deep_moves_map_like_shallow_ones() {
+        let size = Size::new(64.0, 48.0);
+        let deep = Viewport::new(-0.75, 0.1, 1e-20).shifted(3e-37, -2e-37, 1e-20);
+        let deep_move =
+            PixelTransform::between(&deep, size, &deep.shifted(1e-22, 4e-22, 5e-21), size);
+        let shallow = Viewport::new(-0.75, 0.1, 1.0);
+        let shallow_move =
+            PixelTransform::between(&shallow, size, &shallow.shifted(1e-2, 4e-2, 0.5), size);
+        let (deep_x, deep_y) = deep_move.apply(13.0, 41.0);
+        let (shallow_x, shallow_y) = shallow_move.apply(13.0, 41.0);
+        assert!(
+            (deep_x - shallow_x).abs() <= 1e-6 && (deep_y - shallow_y).abs() <= 1e-6,
+            "a deep move maps pixel (13, 41) to ({}, {}), not ({}, {})",
+            deep_x,
+            deep_y,
+            shallow_x,
+            shallow_y
+        );
+    }
+
+    // Panning a frame shown upside down moves it the other way on screen.
+    #[test]
+    fn mirrored_frames_pan_the_other_way() {
+        let size = Size::new(64.0, 48.0);
+        let from = Viewport::new(-0.5, 0.25, 4.0);
+        let panned = from.shifted(0.0, 10.0 * from.pixel_size(size), 4.0);
+        let flip = PixelTransform::mirror_rows(size.height as f64);
+        let shown = flip
+            .then(&PixelTransform::between(&from, size, &panned, size))
+            .then(&flip);
+        let (x, y) = shown.apply(40.0, 17.0);
+        assert!(
+            (x - 40.0).abs() <= 1e-9 && (y - 27.0).abs() <= 1e-9,
+            "panning up 10 pixels on a mirrored display shows row 17 from ({}, {})",
+            x,
+            y
+        );
+    }
+
+    type Selected = Result<Viewport, SelectionError>;
+
+    fn select(current: &Viewport, start: (f32, f32), end: (f32, f32), size: Size) -> Selected {
+        Viewport::from_selection(
+            current,
+            Point::new(start.0, start.1),
+            Point::new(end.0, end.1),
+            size,
+        )
+    }
+
+    // Box zooms over random views, frames and corners, thin and reversed ones included.
+    #[test]
+    fn selections_give_finite_views_centered_on_them_or_are_too_thin() {
+        let mut rng = 0x5e1ec7u64;
+        let mut next = || {
+            rng = rng
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (rng >> 11) as f64 / (1u64 << 53) as f64
+        };
+        for _ in 0..5_000 {
+            let size = Size::new(
+                (1.0 + next() * 2_000.0).floor() as f32,
+                (1.0 + next() * 2_000.0).floor() as f32,
+            );
+            let current = Viewport::new(
+                4.0 * next() - 2.0,
+                4.0 * next() - 2.0,
+                10f64.powf(-28.0 * next()),
+            )
+            .shifted(1e-20 * next(), 1e-20 * next(), 10f64.powf(-28.0 * next()));
+            // Corners up to 50 px past the frame, and every tenth selection a sliver.
+            let corner = |next: &mut dyn FnMut() -> f64| {
+                Point::new(
+                    (next() * (size.width as f64 + 100.0) - 50.0) as f32,
+                    (next() * (size.height as f64 + 100.0) - 50.0) as f32,
+                )
+            };
+            let start = corner(&mut next);
+            let mut end = corner(&mut next);
+            if next() < 0.1 {
+                end.y = start.y + (next() * 8.0 - 4.0) as f32;
+            }
+            let (width, height) = (
+                (end.x as f64 - start.x as f64).abs(),
+                (end.y as f64 - start.y as f64).abs(),
+            );
+            let thin = width < MIN_SELECTION || height < MIN_SELECTION;
+            let selected = Viewport::from_selection(&current, start, end, size);
+            let what = || {
+                format!(
+                    "selecting {:?} to {:?} of {:?} in {:?}",
+                    start, end, size, current
+                )
+            };
+            match selected {
+                Ok(view) => {
+                    let middle = Point::new((start.x + end.x) / 2.0, (start.y + end.y) / 2.0);
+                    let (re, im) = current.precise_point(middle, size);
+                    let (view_re, view_im) = view.center();
+                    let off = ((view_re - re).to_f64().abs(), (view_im - im).to_f64().abs());
+                    let pixel = current.pixel_size(size);
+                    let usable = [view.width, view.height(size)]
+                        .iter()
+                        .all(|extent| extent.is_finite() && *extent > 0.0)
+                        && view.center_re.is_finite()
+                        && view.center_im.is_finite();
+                    assert!(
+                        !thin && usable && off.0 <= pixel && off.1 <= pixel,
+                        "{} gives {:?}",
+                        what(),
+                        view
+                    );
+                }
+                Err(SelectionError::TooSmall { .. }) if thin => {}
+                Err(err) => panic!("{} fails: {}", what(), err),
+            }
+        }
+    }
+
+    #[test]
+    fn slivers_are_refused_and_anything_wider_zooms() {
+        let size = Size::new(1200.0, 800.0);
+        let home = Viewport::new(-0.75, 0.0, 3.0);
+        for (start, end) in [
+            ((0.0, 400.0), (1200.0, 403.0)),
+            ((600.0, 0.0), (603.0, 800.0)),
+        ] {
+            let selected = select(&home, start, end, size);
+            let refused = matches!(selected, Err(SelectionError::TooSmall { .. }));
+            assert!(
+                refused,
+                "a sliver from {:?} to {:?} gives {:?}",
+                start, end, selected
+            );
+        }
+        let view = select(&home, (0.0, 400.0), (1200.0, 404.0), size)
+            .expect("a 1200x4 px selection zooms");
+        assert!(
+            (view.width - home.width).abs() < 1e-12,
+            "a 1200x4 px selection gives {:?}",
+            view
+        );
+    }
+
+    #[test]
+    fn unusable_selections_are_invalid() {
+        let size = Size::new(1200.0, 800.0);
+        let home = Viewport::new(-0.75, 0.0, 3.0);
+        let nan = Point::new(f32::NAN, 0.0);
+        for selected in [
+            Viewport::from_selection(&home, nan, Point::new(10.0, 10.0), size),
+            select(&home, (0.0, 0.0), (10.0, 10.0), Size::new(1200.0, 0.0)),
+            select(
+                &home,
+                (0.0, 0.0),
+                (10.0, 10.0),
+                Size::new(f32::INFINITY, 800.0),
+            ),
+        ] {
+            assert_eq!(selected, Err(SelectionError::Invalid));
+        }
+    }
+
+    // Four pixels of a view 1e-26 wide ask for more than double-double resolves.
+    #[test]
+    fn deep_selections_stop_where_double_double_resolves() {
+        let size = Size::new(1200.0, 800.0);
+        let deep = Viewport::new(-0.75, 0.1, 1e-26);
+        let view =
+            select(&deep, (598.0, 398.0), (602.0, 402.0), size).expect("a deep selection zooms");
+        assert!(
+            view.width < deep.width
+                && view.width > 4.0 * deep.pixel_size(size)
+                && Arithmetic::DoubleDouble.resolves(&view, size, render::MIN_PIXEL_ULPS * 0.999),
+            "a deep selection gives {:?}",
+            view
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+// A render is suspected of being stuck once it has run this many times as long as predicted,
+// and never sooner than `MIN_BOUND`, since predictions for small frames are mostly noise.
+pub const OVERDUE_FACTOR: f64 = 4.0;
+pub const MIN_BOUND: Duration = Duration::from_secs(10);
+// Workers report after every row. Silence from all of them for this many times a row's predicted
+// time, and at least `MIN_QUIET`, means no progress: rows of deep views can take seconds each,
+// and are slower still where they are all interior.
+pub const QUIET_FACTOR: f64 = 16.0;
+pub const MIN_QUIET: Duration = Duration::from_secs(10);
+// Pixels times iteration budget per second assumed before any render was timed; low, so that
+// first predictions err long.
+pub const DEFAULT_RATE: f64 = 1e8;
+// Weight of the newest render in the throughput estimate.
+const RATE_SMOOTHING: f64 = 0.3;
+
+// Recent render speed, as pixels times their iteration budget per second. Counting the budget
+// rather than the iterations done lets a prediction scale with the settings alone.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Throughput {
+    rate: Option<f64>,
+}
+
+impl Throughput {
+    pub fn record(&mut self, pixels: usize, max_iterations: u32, elapsed: Duration) {
+        if pixels == 0 || elapsed.is_zero() {
+            return;
+        }
+        let rate = pixels as f64 * max_iterations as f64 / elapsed.as_secs_f64();
+        self.rate = Some(match self.rate {
+            Some(previous) => previous + (rate - previous) * RATE_SMOOTHING,
+            None => rate,
+        });
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate.unwrap_or(DEFAULT_RATE)
+    }
+}
+
+// How long a frame should take, and one row of it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Prediction {
+    pub frame: Duration,
+    pub row: Duration,
+}
+
+impl Prediction {
+    // How long a render may run before it is suspected of being stuck.
+    pub fn bound(&self) -> Duration {
+        scale(self.frame, OVERDUE_FACTOR).max(MIN_BOUND)
+    }
+
+    // How long every worker may stay silent before the render counts as making no progress.
+    pub fn quiet_bound(&self) -> Duration {
+        scale(self.row, QUIET_FACTOR).max(MIN_QUIET)
+    }
+}
+
+// What a `width` by `height` frame at `max_iterations` should take at `rate`.
+pub fn predict(width: usize, height: usize, max_iterations: u32, rate: f64) -> Prediction {
+    let seconds = |pixels: usize| pixels as f64 * max_iterations as f64 / rate.max(1.0);
+    Prediction {
+        frame: scale(Duration::from_secs(1), seconds(width * height)),
+        row: scale(Duration::from_secs(1), seconds(width)),
+    }
+}
+
+// Saturates rather than panicking on predictions too long to represent.
+fn scale(duration: Duration, factor: f64) -> Duration {
+    Duration::try_from_secs_f64(duration.as_secs_f64() * factor).unwrap_or(Duration::MAX)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Verdict {
+    OnTime,
+    // Past its bound but still making progress, as deep renders legitimately are.
+    Overdue,
+    // Past its bound with no worker making progress.
+    Stuck,
+}
+
+// Judges a render that has run for `elapsed` against its prediction. `quiet` is how long ago any
+// worker last made progress, or None when none has yet.
+pub fn assess(elapsed: Duration, prediction: Prediction, quiet: Option<Duration>) -> Verdict {
+    if elapsed <= prediction.bound() {
+        return Verdict::OnTime;
+    }
+    if quiet.unwrap_or(elapsed) <= prediction.quiet_bound() {
+        Verdict::Overdue
+    } else {
+        Verdict::Stuck
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use threadpool::ThreadPool;
+
+    use super::*;
+    use crate::render::{self, CancelToken};
+    use crate::stats::{CANONICAL_SIZE, CANONICAL_VIEWS};
+
+    // A 640x480 frame at 1000 iterations, after two renders of it took 0.2 s each.
+    fn timed_prediction() -> (Throughput, Prediction) {
+        let mut throughput = Throughput::default();
+        throughput.record(640 * 480, 1000, Duration::from_millis(200));
+        throughput.record(640 * 480, 1000, Duration::from_millis(200));
+        (throughput, predict(640, 480, 1000, throughput.rate()))
+    }
+
+    #[test]
+    fn predictions_follow_the_recent_speed() {
+        let (throughput, prediction) = timed_prediction();
+        let frame = prediction.frame.as_secs_f64();
+        assert!(
+            (0.19..=0.21).contains(&frame),
+            "a frame timed at 0.2 s twice is predicted to take {:.3} s",
+            frame
+        );
+        let deep = predict(640, 480, 1_000_000, throughput.rate());
+        assert!(
+            deep.frame >= prediction.frame * 999,
+            "a thousand times the iterations is not predicted to take longer"
+        );
+    }
+
+    // A deep view at a thousand times the budget: working past it row by row is not being stuck.
+    #[test]
+    fn only_silent_renders_are_stuck() {
+        let (throughput, prediction) = timed_prediction();
+        let deep = predict(640, 480, 1_000_000, throughput.rate());
+        let cases = [
+            (
+                "on schedule",
+                prediction,
+                prediction.frame,
+                Some(Duration::ZERO),
+                Verdict::OnTime,
+            ),
+            (
+                "not started",
+                prediction,
+                prediction.frame,
+                None,
+                Verdict::OnTime,
+            ),
+            (
+                "slow but progressing",
+                deep,
+                deep.bound() * 10,
+                Some(deep.row),
+                Verdict::Overdue,
+            ),
+            (
+                "between slow rows",
+                prediction,
+                prediction.bound() * 10,
+                Some(prediction.quiet_bound()),
+                Verdict::Overdue,
+            ),
+            (
+                "silent",
+                prediction,
+                prediction.bound() * 10,
+                Some(prediction.quiet_bound() + Duration::from_millis(1)),
+                Verdict::Stuck,
+            ),
+            (
+                "silent from the start",
+                prediction,
+                prediction.bound() * 10,
+                None,
+                Verdict::Stuck,
+            ),
+        ];
+        for (name, prediction, elapsed, quiet, expected) in cases {
+            assert_eq!(assess(elapsed, prediction, quiet), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn workers_report_their_progress() {
+        let cancel = CancelToken::new();
+        let params = CANONICAL_VIEWS[0].params();
+        render::threaded_fractal_calc(&ThreadPool::new(2), CANONICAL_SIZE, params, &cancel, |_| {})
+            .expect("renders without a cancel request complete");
+        let heartbeats = cancel.heartbeats();
+        assert!(
+            heartbeats.len() == 2 && heartbeats.iter().all(|(_, age)| *age < MIN_QUIET),
+            "a render on 2 workers left heartbeats {:?}",
+            heartbeats
+        );
+    }
+}
@@ -0,0 +1,40 @@
+use iced::futures::channel::mpsc;
+use iced::Subscription;
+
+use std::thread;
+use std::time::Duration;
+
+use mandelbrot::store;
+
+use crate::bookmarks::Bookmarks;
+use crate::Message;
+
+// How often the bookmark file is checked for saves from other windows.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Sends `BookmarksChanged` whenever the bookmark file is written, which includes this window's
+// own saves; those are told apart when the file is reloaded.
+pub fn subscription() -> Subscription<Message> {
+    Subscription::run(events)
+}
+
+fn events() -> mpsc::UnboundedReceiver<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || poll(tx));
+    rx
+}
+
+fn poll(tx: mpsc::UnboundedSender<Message>) {
+    let Some(path) = Bookmarks::path() else {
+        return;
+    };
+    let mut stamp = store::stamp(&path);
+    while !tx.is_closed() {
+        thread::sleep(POLL_INTERVAL);
+        let now = store::stamp(&path);
+        if now != stamp {
+            stamp = now;
+            let _ = tx.unbounded_send(Message::BookmarksChanged);
+        }
+    }
+}
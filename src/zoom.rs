@@ -0,0 +1,112 @@
+use iced::Size;
+
+use std::fs;
+
+use threadpool::ThreadPool;
+
+use mandelbrot::animation::{self, ZoomSequence};
+use mandelbrot::coloring;
+use mandelbrot::export;
+use mandelbrot::look::Look;
+use mandelbrot::render::CancelToken;
+use mandelbrot::viewport::Viewport;
+
+use crate::config::{self, Config};
+use crate::tui::Tui;
+
+pub const DEFAULT_FRAMES: usize = 600;
+
+const FRAME_SIZE: Size = Size::new(640.0, 360.0);
+const ZOOM_PER_FRAME: f64 = 1.05;
+
+// Writes a zoom sequence into `re + im i` as numbered PNGs plus a manifest, using the current
+// fractal, profile iterations and coloring; `--profile NAME` picks another profile. Stops early
+// per the configured auto-stop policy.
+pub fn run(args: &[String]) -> Result<(), String> {
+    let (args, profile) = config::profile_option(args)?;
+    let parse = |index: usize, name: &str| {
+        args.get(index)
+            .ok_or_else(|| format!("missing {}", name))?
+            .parse::<f64>()
+            .map_err(|err| format!("invalid {}: {}", name, err))
+    };
+    let target_re = parse(0, "real coordinate")?;
+    let target_im = parse(1, "imaginary coordinate")?;
+    let max_frames = match args.get(2) {
+        Some(frames) => frames
+            .parse()
+            .map_err(|err| format!("invalid frame count: {}", err))?,
+        None => DEFAULT_FRAMES,
+    };
+
+    let mut config = Config::load();
+    if let Some(name) = &profile {
+        config.select_profile(name)?;
+    }
+    let sequence = ZoomSequence {
+        target_re,
+        target_im,
+        start_width: Viewport::home(config.fractal, FRAME_SIZE).width,
+        zoom_per_frame: ZOOM_PER_FRAME,
+        max_frames,
+        size: FRAME_SIZE,
+        max_iterations: config.active().settings.max_iterations,
+        fractal: config.fractal,
+        coarse_prepass: config.coarse_prepass,
+        double_double: config.experimental_double_double,
+        auto_stop: config.zoom_auto_stop,
+    };
+    let dir = config
+        .export_dir()
+        .join(format!("zoom_{}", export::timestamp()));
+    let coloring_settings = config.state().coloring.clone();
+    let look = Look::of(&coloring_settings).png_text();
+    let pool = ThreadPool::new(8);
+    let cancel = CancelToken::new();
+    let tui = Tui::new(max_frames, &cancel);
+    let mut started = None;
+    let manifest = animation::render_zoom(
+        &pool,
+        &sequence,
+        &cancel,
+        |index, update| {
+            if started != Some(index) {
+                started = Some(index);
+                tui.start_job(&format!("frame {}", index));
+            }
+            tui.update(update);
+        },
+        |index, buffer| {
+            let file = format!("frame_{:05}.png", index);
+            let rgba = coloring::recolor(buffer, &coloring_settings, pool.max_count());
+            if let Err(err) = export::write_png_with_text(
+                &dir.join(&file),
+                buffer.width as u32,
+                buffer.height as u32,
+                &rgba,
+                &look,
+            ) {
+                println!("failed to write {}: {}", file, err);
+            }
+            if let Some(params) = buffer.params {
+                tui.finish_job(&format!(
+                    "width {:e} in {}",
+                    params.viewport.width,
+                    params.arithmetic.name()
+                ));
+            }
+            file
+        },
+    );
+    let contents = toml::to_string_pretty(&manifest).map_err(|err| err.to_string())?;
+    fs::create_dir_all(&dir).map_err(|err| err.to_string())?;
+    fs::write(dir.join("manifest.toml"), contents).map_err(|err| err.to_string())?;
+    println!(
+        "{} frames in {}, stopped by {:?} at zoom {:.3e}",
+        manifest.frames.len(),
+        dir.display(),
+        manifest.stop_reason,
+        manifest.final_zoom
+    );
+    Ok(())
+}
@@ -0,0 +1,266 @@
+#![cfg(feature = "http")]
+
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use mandelbrot::json;
+use mandelbrot::serve::{self, Engine, Limits};
+
+#[derive(Deserialize)]
+struct ErrorReply {
+    status: u16,
+    error: String,
+}
+
+// Headers are named in lower case.
+struct Response {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+fn server(limits: Limits) -> SocketAddr {
+    serve::start("127.0.0.1:0", limits, Engine::default()).expect("a server starts")
+}
+
+fn send(address: SocketAddr, request: &str) -> TcpStream {
+    let mut stream = TcpStream::connect(address).expect("connects to the server");
+    stream
+        .write_all(format!("{}\r\nHost: localhost\r\n\r\n", request).as_bytes())
+        .expect("sends a request");
+    stream
+}
+
+fn receive(mut stream: TcpStream) -> Response {
+    let mut bytes = Vec::new();
+    stream.read_to_end(&mut bytes).expect("reads the response");
+    let end = bytes
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .expect("the response head ends");
+    let head = String::from_utf8_lossy(&bytes[..end]).to_string();
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|status| status.parse().ok())
+        .expect("the response has a status");
+    let headers = lines
+        .filter_map(|line| line.split_once(": "))
+        .map(|(name, value)| (name.to_lowercase(), value.to_string()))
+        .collect();
+    Response {
+        status,
+        headers,
+        body: bytes[end + 4..].to_vec(),
+    }
+}
+
+fn get(address: SocketAddr, request: &str) -> Response {
+    receive(send(address, request))
+}
+
+#[test]
+fn servers_listen_on_loopback_only() {
+    assert!(serve::start("0.0.0.0:0", Limits::default(), Engine::default()).is_err());
+}
+
+// A render comes back as a PNG of the size asked for, carrying its location.
+#[test]
+fn renders_are_served_as_pngs() {
+    let target = "/render?re=-0.743&im=0.131&width=1e-2&px=64&py=48&iter=200&palette=fire";
+    let response = get(
+        server(Limits::default()),
+        &format!("GET {} HTTP/1.1", target),
+    );
+    assert_eq!(
+        response.status,
+        200,
+        "a good render was answered {}: {}",
+        response.status,
+        String::from_utf8_lossy(&response.body)
+    );
+    assert_eq!(response.header("content-type"), Some("image/png"));
+    assert_eq!(response.header("x-iterations"), Some("200"));
+    assert_eq!(response.header("x-palette"), Some("Fire"));
+    let reader = png::Decoder::new(response.body.as_slice())
+        .read_info()
+        .expect("the render is a PNG");
+    let info = reader.info();
+    assert_eq!((info.width, info.height), (64, 48));
+    let location = info
+        .uncompressed_latin1_text
+        .iter()
+        .find(|chunk| chunk.keyword == "Location")
+        .map(|chunk| chunk.text.as_str());
+    assert_eq!(location, Some("-0.743 0.131 0.01"));
+}
+
+// Bad requests come back with their status and a JSON error saying what was wrong.
+#[test]
+fn bad_requests_are_answered_with_their_status() {
+    let address = server(Limits::default());
+    let bad = [
+        ("GET /render?re=0&im=0 HTTP/1.1", 400, "missing width"),
+        (
+            "GET /render?re=0&im=0&width=-1 HTTP/1.1",
+            400,
+            "invalid width",
+        ),
+        (
+            "GET /render?re=x&im=0&width=1 HTTP/1.1",
+            400,
+            "invalid real coordinate",
+        ),
+        (
+            "GET /render?re=0&im=0&width=1&px=0 HTTP/1.1",
+            400,
+            "invalid pixel width",
+        ),
+        (
+            "GET /render?re=0&im=0&width=1&py=5000 HTTP/1.1",
+            400,
+            "invalid pixel height",
+        ),
+        (
+            "GET /render?re=0&im=0&width=1&iter=99999 HTTP/1.1",
+            400,
+            "invalid iteration",
+        ),
+        (
+            "GET /render?re=0&im=0&width=1&palette=mud HTTP/1.1",
+            400,
+            "unknown palette",
+        ),
+        (
+            "GET /render?re=0&im=0&width=1&zoom=2 HTTP/1.1",
+            400,
+            "unknown parameter",
+        ),
+        ("GET /other HTTP/1.1", 404, "no such endpoint"),
+        ("POST /render?re=0&im=0&width=1 HTTP/1.1", 405, "only GET"),
+    ];
+    for (request, status, error) in bad {
+        let response = get(address, request);
+        assert_eq!(response.status, status, "{}", request);
+        assert_eq!(response.header("content-type"), Some("application/json"));
+        let reply: ErrorReply =
+            json::from_str(&String::from_utf8_lossy(&response.body)).expect("errors come as JSON");
+        assert!(
+            reply.status == status && reply.error.contains(error),
+            "{} was answered {:?}",
+            request,
+            reply.error
+        );
+    }
+}
+
+fn error(response: &Response) -> ErrorReply {
+    assert_eq!(response.header("content-type"), Some("application/json"));
+    json::from_str(&String::from_utf8_lossy(&response.body)).expect("errors come as JSON")
+}
+
+// A head that fills the limit without ending is cut off there.
+#[test]
+fn heads_too_large_are_answered_431() {
+    let mut stream = TcpStream::connect(server(Limits::default())).expect("connects to the server");
+    let line = "GET /render?re=0&im=0&width=4 HTTP/1.1\r\nX-Padding: ";
+    let head = format!("{}{}", line, "a".repeat(8 * 1024 - line.len()));
+    stream.write_all(head.as_bytes()).expect("sends a request");
+    let response = receive(stream);
+    assert_eq!(response.status, 431);
+    assert_eq!(error(&response).status, 431);
+}
+
+// A client that stops sending halfway through its head is answered 408 once its time is up, and
+// requests arriving meanwhile are served without waiting for it.
+#[test]
+fn stalled_heads_time_out_without_holding_up_others() {
+    let head_timeout = Duration::from_secs(2);
+    let address = server(Limits {
+        head_timeout,
+        ..Limits::default()
+    });
+    let mut stalled = TcpStream::connect(address).expect("connects to the server");
+    stalled
+        .write_all(b"GET /render?re=0&im=0&width=4 HTTP/1.1\r\n")
+        .expect("sends half a request");
+    let started = Instant::now();
+    let served = get(address, "GET /render?re=0&im=0&width=4&px=8&py=8 HTTP/1.1");
+    assert_eq!(served.status, 200);
+    assert!(
+        started.elapsed() < head_timeout,
+        "a request waited {:?} behind a stalled one",
+        started.elapsed()
+    );
+    let response = receive(stalled);
+    assert_eq!(response.status, 408);
+    assert!(error(&response).error.contains("too long"));
+}
+
+// The deadline covers the whole head, so sending it a byte at a time does not put it off.
+#[test]
+fn trickled_heads_time_out_all_the_same() {
+    let head_timeout = Duration::from_secs(1);
+    let address = server(Limits {
+        head_timeout,
+        ..Limits::default()
+    });
+    let mut stream = TcpStream::connect(address).expect("connects to the server");
+    stream
+        .set_read_timeout(Some(Duration::from_millis(100)))
+        .expect("sets a read timeout");
+    let started = Instant::now();
+    let head = b"GET /render?re=0&im=0&width=4 HTTP/1.1\r\nX-Padding: ";
+    let trickle = head.iter().chain([b'a'].iter().cycle());
+    for byte in trickle.take_while(|_| started.elapsed() < head_timeout * 5) {
+        if stream.write_all(&[*byte]).is_err() {
+            break;
+        }
+        // Waits a little for each byte, and stops once the server has answered.
+        if stream.peek(&mut [0]).is_ok_and(|read| read > 0) {
+            break;
+        }
+    }
+    stream
+        .set_read_timeout(None)
+        .expect("clears the read timeout");
+    let response = receive(stream);
+    assert_eq!(response.status, 408);
+    assert!(
+        started.elapsed() < head_timeout * 3,
+        "a trickled head was read for {:?}",
+        started.elapsed()
+    );
+}
+
+// A server whose only renderer is busy turns the next request away, and the busy render
+// answers 504 once its time is up.
+#[test]
+fn busy_servers_turn_requests_away_and_give_up_late_renders() {
+    let address = server(Limits {
+        concurrent: 1,
+        queued: 0,
+        timeout: Duration::from_secs(2),
+        ..Limits::default()
+    });
+    let slow = "GET /render?re=-0.7436&im=0.1318&width=1e-4&px=4096&py=4096&iter=20000 HTTP/1.1";
+    let busy = send(address, slow);
+    thread::sleep(Duration::from_millis(500));
+    let turned_away = get(address, "GET /render?re=0&im=0&width=4&px=8&py=8 HTTP/1.1");
+    assert_eq!(turned_away.status, 503);
+    assert_eq!(receive(busy).status, 504);
+}